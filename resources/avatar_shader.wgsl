@@ -0,0 +1,20 @@
+// avatar_shader.wgsl - boxes of the other players in a network game, same line layout as highlight_shader.wgsl
+
+@group(0) @binding(0) var<storage> lines: array<f32>;
+@group(1) @binding(0) var<uniform> camera_proj: mat4x4f;
+
+fn line_vec(index: u32) -> vec3f {
+	return vec3f(lines[index], lines[index + 1u], lines[index + 2u]);
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32, @builtin(instance_index) instance_index: u32) -> @builtin(position) vec4f {
+	let base = instance_index * 6u;
+	let world_position = line_vec(base) + f32(vertex_index % 2u) * line_vec(base + 3u);
+	return camera_proj * vec4f(world_position, 1.0);
+}
+
+@fragment
+fn fs_main() -> @location(0) vec4f {
+	return vec4f(0.2, 0.6, 1.0, 1.0);
+}
@@ -0,0 +1,83 @@
+// bloom_shader.wgsl - bright pass, blur and composite of the bloom chain (see render::pipeline::Bloom)
+
+struct VertexOutput {
+	@builtin(position) position: vec4f,
+	@location(0) uv: vec2f,
+};
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+	// Full-screen triangle
+	let uv = vec2f(f32((vertex_index << 1u) & 2u), f32(vertex_index & 2u));
+	var out: VertexOutput;
+	out.position = vec4f(uv * 2.0 - 1.0, 0.0, 1.0);
+	out.uv = vec2f(uv.x, 1.0 - uv.y); // textures go top down
+	return out;
+}
+
+@group(0) @binding(0) var source: texture_2d<f32>;
+@group(0) @binding(1) var source_sampler: sampler;
+
+struct BloomParams {
+	threshold: f32,
+	intensity: f32,
+	_padding: vec2f,
+};
+@group(1) @binding(0) var<uniform> params: BloomParams;
+
+fn luminance(color: vec3f) -> f32 {
+	return dot(color, vec3f(0.2126, 0.7152, 0.0722));
+}
+
+// 4 bilinear taps, averages a 4x4 block of the (twice as big) source
+fn downsample(uv: vec2f) -> vec3f {
+	let texel = 1.0 / vec2f(textureDimensions(source));
+	var color = textureSample(source, source_sampler, uv + texel * vec2f(-1.0, -1.0)).rgb;
+	color += textureSample(source, source_sampler, uv + texel * vec2f(1.0, -1.0)).rgb;
+	color += textureSample(source, source_sampler, uv + texel * vec2f(-1.0, 1.0)).rgb;
+	color += textureSample(source, source_sampler, uv + texel * vec2f(1.0, 1.0)).rgb;
+	return color * 0.25;
+}
+
+@fragment
+fn fs_extract(in: VertexOutput) -> @location(0) vec4f {
+	let color = downsample(in.uv);
+	// Soft knee so pixels right around the threshold fade in instead of flickering on and off
+	let brightness = luminance(color);
+	let knee = max(params.threshold * 0.5, 0.0001);
+	let soft = clamp(brightness - params.threshold + knee, 0.0, 2.0 * knee);
+	let contribution = max(soft * soft / (4.0 * knee), brightness - params.threshold) / max(brightness, 0.0001);
+	return vec4f(color * max(contribution, 0.0), 1.0);
+}
+
+@fragment
+fn fs_downsample(in: VertexOutput) -> @location(0) vec4f {
+	return vec4f(downsample(in.uv), 1.0);
+}
+
+// 9 tap gaussian done with 5 linear samples
+fn blur(uv: vec2f, direction: vec2f) -> vec4f {
+	let step = direction / vec2f(textureDimensions(source));
+	var color = textureSample(source, source_sampler, uv).rgb * 0.2270270270;
+	color += textureSample(source, source_sampler, uv + step * 1.3846153846).rgb * 0.3162162162;
+	color += textureSample(source, source_sampler, uv - step * 1.3846153846).rgb * 0.3162162162;
+	color += textureSample(source, source_sampler, uv + step * 3.2307692308).rgb * 0.0702702703;
+	color += textureSample(source, source_sampler, uv - step * 3.2307692308).rgb * 0.0702702703;
+	return vec4f(color, 1.0);
+}
+
+@fragment
+fn fs_blur_h(in: VertexOutput) -> @location(0) vec4f {
+	return blur(in.uv, vec2f(1.0, 0.0));
+}
+
+@fragment
+fn fs_blur_v(in: VertexOutput) -> @location(0) vec4f {
+	return blur(in.uv, vec2f(0.0, 1.0));
+}
+
+// Added on top of the frame (the pipeline blends additively)
+@fragment
+fn fs_composite(in: VertexOutput) -> @location(0) vec4f {
+	return vec4f(textureSample(source, source_sampler, in.uv).rgb * params.intensity, 0.0);
+}
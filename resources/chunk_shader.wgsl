@@ -1,5 +1,11 @@
 
-@group(1) @binding(0) var<uniform> camera_proj: mat4x4f;
+struct CameraUniform {
+	view_proj: mat4x4f,
+	position: vec4f,
+	fog_color: vec4f,
+	fog: vec4f, // start, end, ambient (day/night brightness), mode (0 off, 1 linear, 2 exponential)
+};
+@group(1) @binding(0) var<uniform> camera: CameraUniform;
 /*
 
 // Camera uniform usual type ... yeah
@@ -13,7 +19,7 @@ struct CameraUniform {
 */
 @group(2) @binding(0) var<uniform> chunk_pos: u64;
 
-// Assuming CHUNK_SIZE_I is passed as a uniform or constant
+// Patched at load time to match Chunk::SIZE (see render::pipeline)
 const CHUNK_SIZE_I: i32 = 32i;
 
 fn to_world_pos(coord: u64) -> vec3f {
@@ -71,6 +77,16 @@ fn normal_to_rot(pos: vec3f, normal: u32) -> vec3f {
 	return out;
 }
 
+// Stretches the unit quad to the greedy quad size, has to match FACE_AXES in meshing.rs
+fn face_scale(extent: vec2f, normal: u32) -> vec3f {
+	if normal <= 1u {
+		return vec3f(1.0, extent.x, extent.y); // Left / Right : width along Y, height along Z
+	} else if normal <= 3u {
+		return vec3f(extent.x, extent.y, 1.0); // Front / Back : width along X, height along Y
+	}
+	return vec3f(extent.x, 1.0, extent.y); // Top / Bottom : width along X, height along Z
+}
+
 const NORMALS: array<vec3f, 6> = array<vec3f, 6>(
 	vec3f(-1.0, 0.0, 0.0),   // [0] Left face
 	vec3f(1.0, 0.0, 0.0),    // [1] Right face
@@ -85,12 +101,15 @@ struct VertexOutput {
 	@location(0) world_normal: vec3f,
 	@location(1) uv: vec2f,
 	@location(2) id: u32,
+	@location(3) light: f32,
+	@location(4) world_pos: vec3f,
 };
 
 @vertex
 fn vs_main(
 	@location(0) vertex_data: u32,
 	@location(1) instance_data: u32,
+	@location(2) instance_extent: u32,
 	@builtin(vertex_index) vert_idx: u32
 ) -> VertexOutput {
 	// Unpack vertex position using 4-bit extractor
@@ -102,19 +121,31 @@ fn vs_main(
 	// Get normal from instance data (bits 16-19)
 	let normal_idx = (instance_data >> 15u) & 0x7u;
 
-	let model_pos = normal_to_rot(vertex_pos, normal_idx); // Combine vertex and instance positions
+	// Quad size in blocks (5 bits each, stored as size - 1)
+	let extent = vec2f(
+		f32(instance_extent & 0x1Fu) + 1.0,
+		f32((instance_extent >> 5u) & 0x1Fu) + 1.0
+	);
+
+	// Block light level of the cell in front of the face (bits 10-13)
+	let light = f32((instance_extent >> 10u) & 0xFu) / 15.0;
+
+	let model_pos = normal_to_rot(vertex_pos, normal_idx) * face_scale(extent, normal_idx); // Combine vertex and instance positions
 	
 	let normal = NORMALS[normal_idx];
 	
 	var output: VertexOutput;
 
-	output.id = instance_data >> 19u; // (bits 19-32) -> no need for masking
+	// (bits 19-32) -> no need for masking, animated materials point at their current frame
+	output.id = layer_map[instance_data >> 19u];
 	
 	// Apply chunk position (as translation), then camera view_proj
 	let world_pos = to_world_pos(chunk_pos) + model_pos + instance_pos;
-	output.clip_position = camera_proj * vec4f(world_pos, 1.0);
+	output.clip_position = camera.view_proj * vec4f(world_pos, 1.0);
+	output.world_pos = world_pos;
 	
 	output.world_normal = normal;
+	output.light = light;
 	
 	// Calculate UV based on original vertex positions
 	// Since your quad is defined with positions:
@@ -124,9 +155,9 @@ fn vs_main(
 	let local_idx = vert_idx % 6u;
 	// Replace the UV calculation with:
 	output.uv = vec2f(
-		f32(local_idx >= 1u && local_idx <= 3u),
-		f32(local_idx >= 2u && local_idx <= 4u)
-	);
+		f32(local_idx >= 1u && local_idx <= 3u) * extent.y,
+		f32(local_idx >= 2u && local_idx <= 4u) * extent.x
+	); // goes past 1.0 on merged quads, the fragment shader repeats the texture
 	
 	return output;
 }
@@ -134,13 +165,31 @@ fn vs_main(
 // The rest of your shader can remain the same
 @group(0) @binding(0) var t_diffuse: texture_2d_array<f32>;
 @group(0) @binding(1) var s_diffuse: sampler;
+// Texture layer of each material, the identity except for animated materials
+@group(0) @binding(2) var<storage> layer_map: array<u32>;
 // tbh only one more bind group can be used, after that it might not be supported on all devices
 //@group(3) @binding(0) var<uniform> data: u32;
 
 
+// How dark a completely unlit face gets (caves)
+const MIN_BRIGHTNESS: f32 = 0.08;
+
+// Same math as Fog::factor in game::player, uses the real distance to the camera
+// (not the depth) so the fog doesn't creep in at the screen edges when turning
+fn fog_factor(world_pos: vec3f) -> f32 {
+	let mode = u32(camera.fog.w);
+	if mode == 0u { return 0.0; }
+	let dist = distance(world_pos, camera.position.xyz);
+	let t = clamp((dist - camera.fog.x) / max(camera.fog.y - camera.fog.x, 0.001), 0.0, 1.0);
+	if mode == 1u { return t; }
+	let e = t * 2.5;
+	return 1.0 - exp(-e * e);
+}
+
 @fragment
 fn fs_main(vertex: VertexOutput) -> @location(0) vec4f {
-	let texture_color = textureSample(t_diffuse, s_diffuse, vertex.uv, vertex.id);
+	// Repeat the texture per block on greedy quads, the gradients come from the un-wrapped uv so there are no seams
+	let texture_color = textureSampleGrad(t_diffuse, s_diffuse, fract(vertex.uv), vertex.id, dpdx(vertex.uv), dpdy(vertex.uv));
 	if texture_color.a < 0.1 { discard; }
 	
 	let light_dir = normalize(vec3f(0.5, 1.0, 0.5));
@@ -149,8 +198,9 @@ fn fs_main(vertex: VertexOutput) -> @location(0) vec4f {
 	// Combined lighting calculation
 	let directional = max(dot(vertex.world_normal, light_dir), 0.0);
 	let hemi = 0.5 + 0.5 * dot(vertex.world_normal, up);
-	let final_light = mix(0.35 + 0.55 * directional, hemi, 0.3);
+	let final_light = mix(0.35 + 0.55 * directional, hemi, 0.3) * mix(MIN_BRIGHTNESS, 1.0, vertex.light) * camera.fog.z;
 	
-	return vec4f(texture_color.rgb * final_light, texture_color.a);
+	let lit = texture_color.rgb * final_light;
+	return vec4f(mix(lit, camera.fog_color.rgb, fog_factor(vertex.world_pos)), texture_color.a);
 }
 
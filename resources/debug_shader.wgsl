@@ -1,15 +1,14 @@
 // debug_shader.wgsl - FIXED VERSION
 
-// Line structure matching your Rust code
-struct Line {
-	start: vec3f,
-	direction: vec3f,
-}
-
-// Bind groups
-@group(0) @binding(0) var<storage> lines: array<Line>;
+// Lines are read as plain floats (start xyz, direction xyz) like in highlight_shader.wgsl,
+// an array of vec3 pairs would be padded to 32 bytes while the rust `Line` is 24
+@group(0) @binding(0) var<storage> lines: array<f32>;
 @group(1) @binding(0) var<uniform> camera_proj: mat4x4f;
 
+fn line_vec(index: u32) -> vec3f {
+	return vec3f(lines[index], lines[index + 1u], lines[index + 2u]);
+}
+
 struct VertexOutput {
 	@builtin(position) position: vec4f,
 	@location(0) color: vec4f,
@@ -17,11 +16,11 @@ struct VertexOutput {
 
 @vertex
 fn vs_main(@builtin(vertex_index) vertex_index: u32, @builtin(instance_index) instance_index: u32) -> VertexOutput {
-	let line = lines[instance_index];
+	let base = instance_index * 6u;
 	let is_end = vertex_index % 2u;
 	
 	// Calculate world position
-	let world_position = line.start + f32(is_end) * line.direction;
+	let world_position = line_vec(base) + f32(is_end) * line_vec(base + 3u);
 	
 	// Apply camera transformation
 	let position = camera_proj * vec4f(world_position, 1.0);
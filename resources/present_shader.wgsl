@@ -0,0 +1,16 @@
+// present_shader.wgsl - copies the offscreen frame to the surface
+
+@group(0) @binding(0) var frame_texture: texture_2d<f32>;
+@group(0) @binding(1) var frame_sampler: sampler; // part of the shared layout, the copy is 1:1 so it loads texels directly
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> @builtin(position) vec4f {
+	// Full-screen triangle
+	let uv = vec2f(f32((vertex_index << 1u) & 2u), f32(vertex_index & 2u));
+	return vec4f(uv * 2.0 - 1.0, 0.0, 1.0);
+}
+
+@fragment
+fn fs_main(@builtin(position) position: vec4f) -> @location(0) vec4f {
+	return textureLoad(frame_texture, vec2i(position.xy), 0);
+}
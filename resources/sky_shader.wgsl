@@ -76,8 +76,12 @@ fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
 
 @group(0) @binding(0) var s_diffuse: sampler;
 @group(0) @binding(1) var t_diffuse: texture_2d<f32>;
+@group(0) @binding(2) var t_night: texture_2d<f32>;
+@group(0) @binding(3) var<uniform> night_blend: vec4f; // only x is used, 0 day -> 1 night
 
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4f {
-	return textureSample(t_diffuse, s_diffuse, in.uv);
+	let day = textureSample(t_diffuse, s_diffuse, in.uv);
+	let night = textureSample(t_night, s_diffuse, in.uv);
+	return mix(day, night, night_blend.x);
 }
\ No newline at end of file
@@ -0,0 +1,278 @@
+
+use crate::{
+	block::{
+		main::{Block, Chunk, Material},
+		math::{ChunkCoord, LocalPos},
+		extra::get_block_id_from_item_name,
+		storage::BlockStorage,
+	},
+	render::meshing::CUBE_FACES,
+	world::main::World,
+};
+use std::collections::{HashSet, VecDeque};
+use glam::IVec3;
+
+/// Brightest light level (full sky light)
+pub const MAX_LIGHT: u8 = 15;
+/// How many queued chunks `World::update_light` recomputes a frame, the rest waits for the next one
+pub const LIGHT_UPDATE_BUDGET: usize = 16;
+/// Blocks that give off light, by texture name (missing names are just skipped)
+pub const LIGHT_EMITTERS: [(&str, u8); 3] = [
+	("lava", 15),
+	("torch", 14),
+	("glowstone", 15),
+];
+
+/// Light level per block (0-15), packed 2 per byte like the compact block indices
+#[derive(Debug, Clone, PartialEq)]
+pub enum LightStorage {
+	/// Same level everywhere (fully lit air above ground, pitch black inside stone)
+	Uniform(u8),
+	/// 4 bits per block
+	Packed(Box<[u8; Chunk::VOLUME/2]>),
+}
+
+impl LightStorage {
+	/// Chunks that were never propagated render fully bright instead of black
+	#[inline] pub const fn default() -> Self {
+		Self::Uniform(MAX_LIGHT)
+	}
+	#[inline] pub const fn uniform(level: u8) -> Self {
+		Self::Uniform(if level > MAX_LIGHT { MAX_LIGHT } else { level })
+	}
+
+	#[inline] pub fn get(&self, index: usize) -> u8 {
+		match self {
+			Self::Uniform(level) => *level,
+			Self::Packed(levels) => BlockStorage::get_compact_index(&**levels, index),
+		}
+	}
+
+	#[inline] pub fn set(&mut self, index: usize, level: u8) {
+		let level = level.min(MAX_LIGHT);
+		match self {
+			Self::Uniform(current) => {
+				if *current == level { return; }
+				let fill = *current | *current << 4;
+				let mut levels = Box::new([fill; Chunk::VOLUME/2]);
+				BlockStorage::set_compact_index(&mut levels, index, level);
+				*self = Self::Packed(levels);
+			}
+			Self::Packed(levels) => BlockStorage::set_compact_index(levels, index, level),
+		}
+	}
+
+	/// Collapses back to `Uniform` if every block has the same level
+	pub fn optimize(&mut self) {
+		let Self::Packed(levels) = self else { return; };
+		let first = levels[0];
+		if first & 0x0F != first >> 4 { return; }
+		if levels.iter().all(|&b| b == first) {
+			*self = Self::Uniform(first & 0x0F);
+		}
+	}
+}
+
+/// Chunks waiting to be relit, in the order they were queued and each one only once
+#[derive(Debug, Default)]
+pub struct LightQueue {
+	order: VecDeque<ChunkCoord>,
+	queued: HashSet<ChunkCoord>,
+}
+
+impl LightQueue {
+	#[inline] pub fn new() -> Self { Self::default() }
+	#[inline] pub fn push(&mut self, coord: ChunkCoord) {
+		if self.queued.insert(coord) { self.order.push_back(coord); }
+	}
+	#[inline] pub fn pop(&mut self) -> Option<ChunkCoord> {
+		let coord = self.order.pop_front()?;
+		self.queued.remove(&coord);
+		Some(coord)
+	}
+	#[inline] pub fn len(&self) -> usize { self.order.len() }
+	#[inline] pub fn is_empty(&self) -> bool { self.order.is_empty() }
+	#[inline] pub fn clear(&mut self) {
+		self.order.clear();
+		self.queued.clear();
+	}
+}
+
+// Small inline functions in Chunk that delegate to LightStorage
+impl Chunk {
+	#[inline] pub fn get_light(&self, index: usize) -> u8 {
+		self.light().get(index)
+	}
+	#[inline] pub fn set_light(&mut self, index: usize, level: u8) {
+		self.light_mut().set(index, level);
+	}
+
+	/// Recomputes the light of this chunk from scratch :
+	/// sky light falls straight down from the chunk above (or the sky if there is none loaded),
+	/// emitters and the neighbours' border light spread out with -1 per block, solid blocks stop it.
+	/// Returns which borders (in `CUBE_FACES` order) changed, so the neighbours know to update too.
+	pub fn compute_light(&mut self, neighbors: &[Option<&Chunk>; 6], emitters: &[(Material, u8)]) -> [bool; 6] {
+		const SIZE: i32 = Chunk::SIZE_I;
+		let old = self.light().clone();
+		let sky_from_above = |x: i32, z: i32| -> bool {
+			let Some(above) = neighbors[4] else { return true; };
+			let idx = usize::from(LocalPos::from(IVec3::new(x, 0, z)));
+			above.get_block(idx).is_empty() && above.get_light(idx) == MAX_LIGHT
+		};
+
+		// Fast paths, no need to flood fill a chunk that is all air or all solid
+		if let BlockStorage::Uniform { block } = self.storage() {
+			let block = *block;
+			let open_sky = (0..SIZE).all(|x| (0..SIZE).all(|z| sky_from_above(x, z)));
+			if !block.is_empty() {
+				*self.light_mut() = LightStorage::uniform(emission(block, emitters));
+				return border_changes(&old, self.light());
+			}
+			if open_sky {
+				*self.light_mut() = LightStorage::uniform(MAX_LIGHT);
+				return border_changes(&old, self.light());
+			}
+		}
+
+		let mut light = LightStorage::uniform(0);
+		let mut queue: VecDeque<(IVec3, u8)> = VecDeque::new();
+
+		// Sky columns
+		for x in 0..SIZE {
+			for z in 0..SIZE {
+				if !sky_from_above(x, z) { continue; }
+				for y in (0..SIZE).rev() {
+					let pos = IVec3::new(x, y, z);
+					let idx = usize::from(LocalPos::from(pos));
+					if !self.get_block(idx).is_empty() { break; }
+					light.set(idx, MAX_LIGHT);
+					queue.push_back((pos, MAX_LIGHT));
+				}
+			}
+		}
+
+		// Light emitting blocks
+		if !emitters.is_empty() {
+			for idx in 0..Chunk::VOLUME {
+				let level = emission(self.get_block(idx), emitters);
+				if level == 0 || level <= light.get(idx) { continue; }
+				light.set(idx, level);
+				queue.push_back((IVec3::from(LocalPos::from(idx)), level));
+			}
+		}
+
+		// Light coming in from the neighbouring chunks
+		for (face, neighbor) in neighbors.iter().enumerate() {
+			let Some(neighbor) = neighbor else { continue; };
+			let normal = CUBE_FACES[face];
+			for a in 0..SIZE {
+				for b in 0..SIZE {
+					let pos = border_pos(face, a, b);
+					let idx = usize::from(LocalPos::from(pos));
+					if !self.get_block(idx).is_empty() { continue; }
+					// LocalPos wraps, so this is the touching cell in the neighbour
+					let level = neighbor.get_light(usize::from(LocalPos::from(pos + normal))).saturating_sub(1);
+					if level <= light.get(idx) { continue; }
+					light.set(idx, level);
+					queue.push_back((pos, level));
+				}
+			}
+		}
+
+		// Flood fill
+		while let Some((pos, level)) = queue.pop_front() {
+			if level <= 1 { continue; }
+			for normal in CUBE_FACES {
+				let next = pos + normal;
+				if !self.contains_position(next) { continue; }
+				let idx = usize::from(LocalPos::from(next));
+				if light.get(idx) >= level - 1 || !self.get_block(idx).is_empty() { continue; }
+				light.set(idx, level - 1);
+				queue.push_back((next, level - 1));
+			}
+		}
+
+		light.optimize();
+		*self.light_mut() = light;
+		border_changes(&old, self.light())
+	}
+}
+
+impl World {
+	/// Queues the chunk to be relit, `update_light` spreads it to the neighbours whose border light changes.
+	/// Has to be called after a block change (breaking a block re-propagates from scratch, so light flows back in),
+	/// many edits to one chunk in a frame still relight it once
+	#[inline] pub fn propagate_light(&mut self, coord: ChunkCoord) {
+		self.light_queue.push(coord);
+	}
+
+	/// Recomputes up to `budget` queued chunks, the neighbours whose border light changed are queued behind them.
+	/// Whatever is left stays queued for the next call, returns how many chunks are still waiting
+	pub fn update_light(&mut self, mut budget: usize) -> usize {
+		if self.light_queue.is_empty() { return 0; }
+		let emitters = emitter_materials();
+
+		while budget > 0 {
+			let Some(coord) = self.light_queue.pop() else { break; };
+			// Take the chunk out so the neighbours can be borrowed next to it
+			let Some(mut chunk) = self.chunks.remove(&coord) else { continue; };
+			budget -= 1;
+			let changed = {
+				let neighbors = self.get_neighboring_chunks(coord);
+				chunk.compute_light(neighbors.as_array(), &emitters)
+			};
+			chunk.dirty = true;
+			self.chunks.insert(coord, chunk);
+
+			for (face, next) in coord.get_adjacent().into_iter().enumerate() {
+				if !changed[face] { continue; }
+				let Some(neighbor) = self.get_chunk_mut(&next) else { continue; };
+				neighbor.final_mesh = false;
+				self.light_queue.push(next);
+			}
+		}
+		self.light_queue.len()
+	}
+}
+
+/// Resolves `LIGHT_EMITTERS` to material ids
+pub fn emitter_materials() -> Vec<(Material, u8)> {
+	LIGHT_EMITTERS.iter()
+		.map(|&(name, level)| (Material(get_block_id_from_item_name(name)), level))
+		.filter(|(material, _)| material.inner() != 0) // 0 is the "missing texture" id
+		.collect()
+}
+
+#[inline]
+fn emission(block: Block, emitters: &[(Material, u8)]) -> u8 {
+	emitters.iter()
+		.find(|(material, _)| *material == block.material())
+		.map(|(_, level)| *level)
+		.unwrap_or(0)
+}
+
+/// Position on the chunk border facing `face`, `a` and `b` walk the border plane
+#[inline]
+fn border_pos(face: usize, a: i32, b: i32) -> IVec3 {
+	const LAST: i32 = Chunk::SIZE_I - 1;
+	match face {
+		0 => IVec3::new(0, a, b),
+		1 => IVec3::new(LAST, a, b),
+		2 => IVec3::new(a, b, 0),
+		3 => IVec3::new(a, b, LAST),
+		4 => IVec3::new(a, LAST, b),
+		_ => IVec3::new(a, 0, b),
+	}
+}
+
+fn border_changes(old: &LightStorage, new: &LightStorage) -> [bool; 6] {
+	let mut changed = [false; 6];
+	if old == new { return changed; }
+	for (face, slot) in changed.iter_mut().enumerate() {
+		*slot = (0..Chunk::SIZE_I).any(|a| (0..Chunk::SIZE_I).any(|b| {
+			let idx = usize::from(LocalPos::from(border_pos(face, a, b)));
+			old.get(idx) != new.get(idx)
+		}));
+	}
+	changed
+}
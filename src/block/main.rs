@@ -6,8 +6,10 @@ use crate::{
 	block::math::{self, ChunkCoord, LocalPos, BlockRotation},
 	block::storage::BlockStorage,
 	block::entity::EntityStorage,
-	utils::rng::{Noise},
+	block::light::LightStorage,
+	world::{biome::{BiomeGen, CaveGen, CaveParams}, structure::StructurePlacer, generation::GenMode},
 	render::meshing::GeometryBuffer,
+	ext::timer::ScopeTimer,
 };
 use glam::IVec3;
 
@@ -85,10 +87,15 @@ impl Block {
 pub struct Chunk {
 	storage: BlockStorage,
 	entities: EntityStorage,
+	light: LightStorage,
 
 	pub dirty: bool,
 	pub final_mesh: bool,
+	pub occluded: Option<bool>, // cached `is_occluded`, None until computed or after a neighbor changed
+	mesh_lod: u8, // level of detail the current mesh was built with
+	mesh_job: u32, // id of the remesh running on a worker, 0 if none
 	finished_gen: bool,
+	edits_since_optimize: u32,
 
 	mesh: Option<GeometryBuffer>,
 	bind_group: Option<wgpu::BindGroup>,
@@ -98,10 +105,15 @@ impl Clone for Chunk {
 		Self {
 			storage: self.storage.clone(),
 			entities: self.entities.clone(),
+			light: self.light.clone(),
 			
 			dirty: self.dirty,
 			final_mesh: self.final_mesh,
+			occluded: None,
+			mesh_lod: self.mesh_lod,
+			mesh_job: 0,
 			finished_gen: self.finished_gen,
+			edits_since_optimize: self.edits_since_optimize,
 			
 			// These are typically not cloned as they're GPU resources
 			mesh: None,
@@ -110,20 +122,30 @@ impl Clone for Chunk {
 	}
 }
 impl Chunk {
-	pub const SIZE: usize = 32;
+	/// Bits per axis of a local position, everything chunk-size related derives from this
+	/// (the `small_chunks` feature makes 16³ chunks for low-end devices)
+	pub const BITS: u8 = if cfg!(feature = "small_chunks") { 4 } else { 5 };
+	pub const SIZE: usize = 1 << Self::BITS;
 	pub const SIZE_I: i32 = Self::SIZE as i32;
 	pub const SIZE_F: f32 = Self::SIZE as f32;
-	pub const VOLUME: usize = Self::SIZE * Self::SIZE * Self::SIZE; // 32K+
+	pub const VOLUME: usize = Self::SIZE * Self::SIZE * Self::SIZE; // 32K+ (4K with small_chunks)
+	/// The storage gets optimized after this many `set_block` calls on the same chunk
+	pub const OPTIMIZE_EVERY: u32 = 100;
 
 	/// Creates an empty chunk (all blocks are air)
 	#[inline] pub fn empty() -> Self {
 		Self {
 			storage: BlockStorage::empty(),
 			entities: EntityStorage::Empty,
+			light: LightStorage::default(),
 
 			dirty: false,
 			final_mesh: false,
+			occluded: None,
+			mesh_lod: 0,
+			mesh_job: 0,
 			finished_gen: false,
+			edits_since_optimize: 0,
 
 			mesh: None,
 			bind_group: None,
@@ -136,10 +158,15 @@ impl Chunk {
 		Self {
 			storage: BlockStorage::uniform(block),
 			entities: EntityStorage::Empty,
+			light: LightStorage::default(),
 
 			dirty: true,
 			final_mesh: false,
+			occluded: None,
+			mesh_lod: 0,
+			mesh_job: 0,
 			finished_gen: true,
+			edits_since_optimize: 0,
 
 			mesh: None,
 			bind_group: None,
@@ -150,10 +177,15 @@ impl Chunk {
 		Self {
 			storage,
 			entities: EntityStorage::Empty,
+			light: LightStorage::default(),
 
 			dirty: true,
 			final_mesh: false,
+			occluded: None,
+			mesh_lod: 0,
+			mesh_job: 0,
 			finished_gen: true,
+			edits_since_optimize: 0,
 
 			mesh: None,
 			bind_group: None,
@@ -163,47 +195,100 @@ impl Chunk {
 		Self {
 			storage,
 			entities,
+			light: LightStorage::default(),
 
 			dirty: true,
 			final_mesh: false,
+			occluded: None,
+			mesh_lod: 0,
+			mesh_job: 0,
 			finished_gen: true,
+			edits_since_optimize: 0,
 
 			mesh: None,
 			bind_group: None,
 		}
 	}
 
-	pub fn generate(coord: ChunkCoord, seed: u32) -> Self {
-		if coord.y() > 6i32 { return Self::empty(); }
-		if coord.y() <= -2i32 { return Self::new(2u16); }
+	/// Generates the chunk for the given terrain mode, `generate` is the Noise one
+	pub fn generate_with(coord: ChunkCoord, seed: u32, mode: &GenMode) -> Self {
+		let _timer = ScopeTimer::new("chunk gen");
+		let Some((bottom, top)) = mode.block_range() else { return Self::generate(coord, seed); };
+		let (world_x, world_y, world_z) = coord.unpack_to_worldpos();
+		let mut chunk = Self::empty();
 		
-		let noise_gen = Noise::new(seed);
+		for y in 0..Self::SIZE {
+			let pos_y = world_y + y as i32;
+			if pos_y < bottom || pos_y > top { continue; }
+			for x in 0..Self::SIZE {
+				for z in 0..Self::SIZE {
+					let Some(block) = mode.block_at(world_x + x as i32, pos_y, world_z + z as i32) else { continue; };
+					chunk.set_block(usize::from(LocalPos::from((x, y, z))), block);
+				}
+			}
+		}
+		chunk.finished_gen = true;
+		chunk
+	}
+
+	/// The top terrain block `generate` leaves in a column of that height (structures and cave openings aside):
+	/// chunk 6 is the last one with terrain and from chunk -2 down everything is solid
+	#[inline] pub const fn generated_surface(height: i32) -> i32 {
+		let (lowest, highest) = (-Self::SIZE_I - 1, 7 * Self::SIZE_I - 1);
+		if height < lowest { lowest } else if height > highest { highest } else { height }
+	}
+
+	#[inline] pub fn generate(coord: ChunkCoord, seed: u32) -> Self {
+		Self::generate_with_caves(coord, seed, CaveParams::default())
+	}
+
+	/// The Noise terrain with caves of that shape, `CaveParams::none()` keeps the ground solid
+	pub fn generate_with_caves(coord: ChunkCoord, seed: u32, caves: CaveParams) -> Self {
+		if coord.y() > 7i32 { return Self::empty(); }
 		let (world_x, world_y, world_z) = coord.unpack_to_worldpos();
+		let cave_gen = CaveGen::new(seed, caves);
+
+		// Deep chunks are plain stone, hollowed out down to the cave floor
+		if coord.y() <= -2i32 {
+			let mut chunk = Self::new(2u16);
+			if !cave_gen.reaches(world_y + Self::SIZE_I - 1) { return chunk; }
+			for x in 0..Self::SIZE {
+				for z in 0..Self::SIZE {
+					for y in 0..Self::SIZE {
+						let (pos_x, pos_y, pos_z) = (world_x + x as i32, world_y + y as i32, world_z + z as i32);
+						if !cave_gen.is_carved(pos_x, pos_y, pos_z, i32::MAX) { continue; }
+						chunk.set_block(usize::from(LocalPos::from((x, y, z))), Block::default());
+					}
+				}
+			}
+			return chunk;
+		}
+		
+		let biome_gen = BiomeGen::new(seed);
 		let mut chunk = Self::empty();
-		let block = Block::new(Material(2u16));
 		
-		// Pre-calculate all noise values for this chunk's XZ plane
-		for x in 0..Self::SIZE {
-			for z in 0..Self::SIZE {
-				let pos_x: i32 = world_x + x as i32;
-				let pos_z: i32 = world_z + z as i32;
+		// Chunk 7 only gets the structures
+		if coord.y() <= 6i32 {
+			for x in 0..Self::SIZE {
+				for z in 0..Self::SIZE {
+					let pos_x: i32 = world_x + x as i32;
+					let pos_z: i32 = world_z + z as i32;
 				
-				// Get noise value and scale it to a reasonable height range
-				let noise: f32 = noise_gen.terrain_noise_2d(pos_x, pos_z);
-				let final_noise = noise * (8 * Chunk::SIZE) as f32;
+					// Biome is picked per column from world XZ so neighbouring chunks line up
+					let column = biome_gen.column(pos_x, pos_z);
+					if column.height < world_y { continue; } // whole column is air in this chunk
 				
-				for y in 0..Self::SIZE {
-					let pos_y = world_y + y as i32;
-					// If this block is under or in terrain height, make it solid
-					if pos_y <= final_noise as i32 {
-						// Correct block indexing : BlockPosition
+					for y in 0..Self::SIZE {
+						let pos_y = world_y + y as i32;
+						let Some(material) = column.material_at(pos_y) else { break; };
+						if cave_gen.is_carved(pos_x, pos_y, pos_z, column.height) { continue; }
 						let idx: LocalPos = LocalPos::from((x, y, z));
-						chunk.set_block(usize::from(idx), block); // Set to solid
+						chunk.set_block(usize::from(idx), Block::new(material));
 					}
-					// Else leave as air
 				}
 			}
 		}
+		StructurePlacer::new(seed).place(coord, &mut chunk, &biome_gen);
 		chunk.finished_gen = true;
 		chunk
 	}
@@ -212,20 +297,18 @@ impl Chunk {
 		self.storage.get(index)
 	}
 
+	/// Iterates over the non-air blocks of the chunk, way cheaper than scanning `0..VOLUME` on mostly empty chunks
+	#[inline] pub fn iter_solid(&self) -> impl Iterator<Item = (LocalPos, Block)> + '_ {
+		self.storage.iter_solid().map(|(i, block)| (LocalPos::from(i), block))
+	}
+
 	/// Checks if the chunk is completely empty (all blocks are air)
 	#[inline]
 	pub fn is_empty(&self) -> bool {
 		match &self.storage {
 			BlockStorage::Uniform { block } => block.is_empty(),
-			_ => {
-				// For compact/sparse storage, check if all blocks are air
-				for i in 0..Self::VOLUME {
-					if !self.storage.get(i).is_empty() {
-						return false;
-					}
-				}
-				true
-			}
+			// Stops at the first solid block
+			_ => self.iter_solid().next().is_none(),
 		}
 	}
 
@@ -257,13 +340,11 @@ impl Chunk {
 		self.dirty = true;
 
 		// Periodically optimize storage to avoid bloat
-		// Only optimize sparse storage periodically to avoid performance hits
-		static mut OPTIMIZATION_COUNTER: usize = 0;
-		unsafe {
-			OPTIMIZATION_COUNTER += 1;
-			if OPTIMIZATION_COUNTER % 100 == 0 {
-				self.storage.optimize();
-			}
+		// counted per chunk so it does not depend on what other chunks (or threads) are doing
+		self.edits_since_optimize += 1;
+		if self.edits_since_optimize >= Self::OPTIMIZE_EVERY {
+			self.edits_since_optimize = 0;
+			self.storage.optimize();
 		}
 	}
 
@@ -303,13 +384,20 @@ impl Chunk {
 
 	/// Returns a reference to the mesh if it exists
 	#[inline] pub const fn mesh(&self) -> Option<&GeometryBuffer> { self.mesh.as_ref() }
-	#[inline] pub fn set_mesh(&mut self, gb: Option<GeometryBuffer>) { self.mesh = gb; }
+	/// Swaps in the new mesh and hands back the old one, so its buffer can go back to the pool
+	#[inline] pub fn set_mesh(&mut self, gb: Option<GeometryBuffer>) -> Option<GeometryBuffer> { std::mem::replace(&mut self.mesh, gb) }
+	#[inline] pub fn take_mesh(&mut self) -> Option<GeometryBuffer> { self.mesh.take() }
+	#[inline] pub const fn mesh_lod(&self) -> u8 { self.mesh_lod }
+	#[inline] pub const fn set_mesh_lod(&mut self, lod: u8) { self.mesh_lod = lod; }
+	#[inline] pub const fn mesh_job(&self) -> u32 { self.mesh_job }
+	#[inline] pub const fn set_mesh_job(&mut self, job: u32) { self.mesh_job = job; }
 	
 	/// Returns a reference to the bind group if it exists
 	#[inline] pub const fn bind_group(&self) -> Option<&wgpu::BindGroup> { self.bind_group.as_ref() }
 	#[inline] pub fn set_bind_group(&mut self, bg: Option<wgpu::BindGroup>) { self.bind_group = bg; }
 
 	#[inline] pub const fn finished_gen(&self) -> bool { self.finished_gen }
+	#[inline] pub const fn edits_since_optimize(&self) -> u32 { self.edits_since_optimize }
 
 	#[inline] pub const fn storage(&self) -> &BlockStorage { &self.storage }
 	#[inline] pub const fn storage_mut(&mut self) -> &mut BlockStorage { &mut self.storage }
@@ -317,6 +405,9 @@ impl Chunk {
 	#[inline] pub const fn entities(&self) -> &EntityStorage { &self.entities }
 	#[inline] pub const fn entities_mut(&mut self) -> &mut EntityStorage { &mut self.entities }
 
+	#[inline] pub const fn light(&self) -> &LightStorage { &self.light }
+	#[inline] pub const fn light_mut(&mut self) -> &mut LightStorage { &mut self.light }
+
 
 	#[inline] pub fn optimize_storage(&mut self) { self.storage.optimize(); }
 	#[inline] pub fn storage_info(&self) -> (usize, &'static str) { self.storage.memory_usage() }
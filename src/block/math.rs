@@ -93,6 +93,13 @@ impl ChunkCoord {
 		)
 	}
 
+	/// World space bounds of the chunk (min, max)
+	#[inline] pub const fn world_aabb(&self) -> (Vec3, Vec3) {
+		let chunk_size = Chunk::SIZE_F;
+		let min = Vec3::new(self.x() as f32 * chunk_size, self.y() as f32 * chunk_size, self.z() as f32 * chunk_size);
+		(min, Vec3::new(min.x + chunk_size, min.y + chunk_size, min.z + chunk_size))
+	}
+
 	/// Creates from world position
 	#[inline] pub fn from_world_pos(world_pos: IVec3) -> Self {
 		let chunk_size = Chunk::SIZE_I;
@@ -198,7 +205,7 @@ impl std::ops::Add for ChunkCoord {
 	}
 }
 
-/// Compact position within a chunk (0-15 on each axis)
+/// Compact position within a chunk (0..Chunk::SIZE on each axis)
 // 2 ; 4 ; 8 ; 16 ; 32
 #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct LocalPos(u16);
@@ -206,7 +213,7 @@ pub struct LocalPos(u16);
 impl LocalPos {
 	pub const ZERO:Self = Self::new(0,0,0);
 	pub const CORNER:Self = Self::new(Chunk::SIZE as u8 -1, Chunk::SIZE as u8 -1, Chunk::SIZE as u8 -1);
-	pub const OFFSET:u8 = 5;
+	pub const OFFSET:u8 = Chunk::BITS;
 	pub const MASK: u16 = (1 << Self::OFFSET) - 1;
 
 	/// Creates a new LocalPos from x,y,z coordinates (0-15)
@@ -119,7 +119,7 @@ impl BlockStorage {
 			Self::Rle { palette, runs } => {
 				let mut pos = 0;
 				for (block_idx, count) in runs {
-					let end_pos = pos + *count as usize;
+					let end_pos = pos + *count as usize + 1; // count is stored as actual_count - 1
 					if index < end_pos {
 						return palette[*block_idx as usize];
 					}
@@ -130,6 +130,53 @@ impl BlockStorage {
 		}
 	}
 
+	/// Iterates over the non-air blocks only (index, block)
+	/// air runs / air palette entries are skipped without decoding every single cell where the format allows it
+	pub fn iter_solid(&self) -> Box<dyn Iterator<Item = (usize, Block)> + '_> {
+		match self {
+			Self::Uniform { block } => {
+				let block = *block;
+				let count = if block.is_empty() { 0 } else { Chunk::VOLUME };
+				Box::new((0..count).map(move |i| (i, block)))
+			}
+			Self::Compact { palette, indices } => {
+				let mut solid = [false; Self::COMPACT_PALETTE_SIZE];
+				for (slot, block) in solid.iter_mut().zip(palette) { *slot = !block.is_empty(); }
+				if !solid.contains(&true) { return Box::new(std::iter::empty()); }
+
+				Box::new(indices.iter().enumerate()
+					.filter(move |&(_, &byte)| solid[(byte & 0x0F) as usize] || solid[(byte >> 4) as usize])
+					.flat_map(move |(byte_idx, &byte)| {
+						// Low nibble is the even position
+						[(byte_idx * 2, byte & 0x0F), (byte_idx * 2 + 1, byte >> 4)].into_iter()
+							.filter(move |&(_, p)| solid[p as usize])
+							.map(move |(i, p)| (i, palette[p as usize]))
+					}))
+			}
+			Self::Sparse { palette, indices } => {
+				let solid: Vec<bool> = palette.iter().map(|block| !block.is_empty()).collect();
+				Box::new(indices.iter().enumerate()
+					.filter(move |&(_, &p)| solid[p as usize])
+					.map(move |(i, &p)| (i, palette[p as usize])))
+			}
+			Self::Giant { .. } | Self::Zigzag { .. } => {
+				Box::new((0..Chunk::VOLUME)
+					.map(move |i| (i, self.get(i)))
+					.filter(|(_, block)| !block.is_empty()))
+			}
+			Self::Rle { palette, runs } => {
+				let mut start = 0;
+				Box::new(runs.iter().flat_map(move |&(p, count)| {
+					let run = start..start + count as usize + 1; // count is stored as actual_count - 1
+					start = run.end;
+					let block = palette[p as usize];
+					let run = if block.is_empty() { 0..0 } else { run };
+					run.map(move |i| (i, block))
+				}))
+			}
+		}
+	}
+
 	/// Sets the block at the given position, automatically handling storage transitions
 	pub fn set(&mut self, index: usize, block: Block) {
 		match self {
@@ -305,6 +352,32 @@ impl BlockStorage {
 			indices[byte_idx] & 0x0F
 		}
 	}
+	/// Every index points into the palette, the palette fits the index width and RLE runs cover the chunk exactly.
+	/// Decoded data has to pass this before `get` can trust it
+	pub fn is_consistent(&self) -> bool {
+		match self {
+			Self::Uniform { .. } | Self::Zigzag { .. } => true,
+			Self::Compact { palette, indices } => {
+				let len = palette.len();
+				(1..=Self::COMPACT_PALETTE_SIZE).contains(&len)
+					&& indices.iter().all(|&byte| ((byte & 0x0F) as usize) < len && ((byte >> 4) as usize) < len)
+			}
+			Self::Sparse { palette, indices } => {
+				let len = palette.len();
+				(1..=Self::SPARSE_PALETTE_SIZE).contains(&len) && indices.iter().all(|&p| (p as usize) < len)
+			}
+			Self::Giant { palette, indices } => {
+				let len = palette.len();
+				(1..=Self::GIANT_PALETTE_SIZE).contains(&len)
+					&& (0..Chunk::VOLUME).all(|i| (Self::get_giant_index(&**indices, i) as usize) < len)
+			}
+			Self::Rle { palette, runs } => {
+				runs.iter().all(|&(p, _)| (p as usize) < palette.len())
+					&& runs.iter().map(|&(_, count)| count as usize + 1).sum::<usize>() == Chunk::VOLUME // count is stored as actual_count - 1
+			}
+		}
+	}
+
 	// Helper functions for Giant storage 12-bit packing
 	#[inline]
 	pub fn get_giant_index(indices: &[u8], position: usize) -> u16 {
@@ -389,18 +462,21 @@ impl BlockStorage {
 					}
 					
 					*self = Self::Sparse { palette, indices };
-				}/* else if unique_blocks.len() <= Self::MAX_GIANT_PALETTE_SIZE {
-					// Can fit in giant storage
+				} else if unique_blocks.len() <= Self::GIANT_PALETTE_SIZE {
+					// Can fit in giant storage (12 bit indices)
 					let palette: Vec<Block> = unique_blocks.into_iter().collect();
-					let mut indices = Box::new([0u16; Chunk::VOLUME]);
+					let lookup: std::collections::HashMap<Block, u16> = palette.iter()
+						.enumerate()
+						.map(|(i, &b)| (b, i as u16))
+						.collect();
+					let mut indices = Box::new([0u8; Chunk::VOLUME * 3 / 2]);
 					
 					for i in 0..Chunk::VOLUME {
-						indices[i] = palette.iter().position(|&b| b == blocks[i]).unwrap() as u16;
+						Self::set_giant_index(&mut *indices, i, lookup[&blocks[i]]);
 					}
 					
 					*self = Self::Giant { palette, indices };
-				}*/
-				// have to make this "u12" not just u16 ...
+				}
 				// Otherwise stay in Zigzag format
 			},
 			Self::Giant { palette, indices } => {
@@ -539,12 +615,12 @@ impl BlockStorage {
 			Self::Uniform { .. } => (std::mem::size_of::<Block>(), "Uniform"),
 			Self::Compact { palette, .. } => {
 				let palette_size = palette.len() * std::mem::size_of::<Block>();
-				let indices_size = Chunk::VOLUME/2; // Box<[u8; 2048]>
+				let indices_size = Chunk::VOLUME/2; // Box<[u8; VOLUME/2]>
 				(palette_size + indices_size, "Compact")
 			}
 			Self::Sparse { palette, .. } => {
 				let palette_size = palette.len() * std::mem::size_of::<Block>();
-				let indices_size = Chunk::VOLUME; // Box<[u8; 4096]>
+				let indices_size = Chunk::VOLUME; // Box<[u8; VOLUME]>
 				(palette_size + indices_size, "Sparse")
 			},
 			Self::Giant { palette, .. } => {
@@ -570,10 +646,10 @@ impl BlockStorage {
 	pub fn to_rle(&self) -> Option<BlockStorage> {
 		let rle = match self {
 			BlockStorage::Uniform { block } => {
-				// For uniform storage, create a single run covering the entire chunk
+				// For uniform storage, create max length runs covering the entire chunk
 				Self::Rle { 
 					palette: vec![*block], 
-					runs: vec![(0, (Chunk::VOLUME - 1) as u8)] // (palette_index, count-1)
+					runs: vec![(0, u8::MAX); Chunk::VOLUME / 256] // (palette_index, count-1)
 				}
 			}
 			BlockStorage::Compact { palette, indices } => {
@@ -619,6 +695,8 @@ impl BlockStorage {
 				Self::Rle { palette: palette.clone(), runs }
 			}
 			BlockStorage::Giant { palette, indices } => {
+				// Runs store the palette index as u8, bigger palettes would get truncated
+				if palette.len() > Self::SPARSE_PALETTE_SIZE { return None; }
 				let mut runs = Vec::with_capacity(32);
 				let mut current_block_idx = Self::get_giant_index(&**indices, 0) as u8;
 				let mut count = 0u8;
@@ -647,6 +725,8 @@ impl BlockStorage {
 				
 				for &block in blocks.iter() {
 					if !block_to_index.contains_key(&block) {
+						// Runs store the palette index as u8, bigger palettes would get truncated
+						if palette.len() == Self::SPARSE_PALETTE_SIZE { return None; }
 						block_to_index.insert(block, palette.len() as u8);
 						palette.push(block);
 					}
@@ -0,0 +1,61 @@
+#[cfg(test)]
+mod tests {
+	#[test]
+	fn world_sounds_fade_pan_and_cull() {
+		use crate::ext::audio::{channel_gains, pick_voice, spatialize, Listener, HEARING_RANGE};
+		use glam::Vec3;
+		let listener = Listener { pos: Vec3::ZERO, right: Vec3::X };
+
+		// Full volume close by, fading out to nothing at the hearing range
+		assert_eq!(spatialize(listener, Vec3::new(0., 0., -1.), true), (1., 0.));
+		let (near, _) = spatialize(listener, Vec3::new(0., 0., -8.), true);
+		let (far, _) = spatialize(listener, Vec3::new(0., 0., -20.), true);
+		assert!(1. > near && near > far && far > 0.);
+		assert_eq!(spatialize(listener, Vec3::new(0., 0., -HEARING_RANGE), true).0, 0.);
+
+		// A sound on the right leans right, the left ear still hears some, and nothing leans without panning
+		let (_, balance) = spatialize(listener, Vec3::new(5., 0., 0.), true);
+		let [left, right] = channel_gains(balance);
+		assert!(balance > 0. && right == 1. && 0. < left && left < 1.);
+		assert_eq!(spatialize(listener, Vec3::new(5., 0., 0.), false).1, 0.);
+		assert_eq!(channel_gains(0.), [1., 1.]);
+
+		// A free voice first, then the quietest one if the new sound is louder, else it is dropped
+		assert_eq!(pick_voice(&[Some(1.), None, Some(0.5)], 0.1), Some(1));
+		assert_eq!(pick_voice(&[Some(1.), Some(0.2), Some(0.5)], 0.3), Some(1));
+		assert_eq!(pick_voice(&[Some(1.), Some(0.2), Some(0.5)], 0.1), None);
+	}
+
+	#[test]
+	fn audio_queue_drops_old_sounds_and_drains_on_close() {
+		use crate::ext::audio::{AudioEvent, EventQueue, Sound};
+		let shot = |gain| AudioEvent::PlayOneShot { sound: Sound::Click, gain, balance: 0., speed: 1. };
+		let music = AudioEvent::SetMusic { path: "background_music.ogg".into(), speed: 1. };
+		let queue = EventQueue::new(3);
+
+		// Nothing is taken before it opens
+		assert_eq!(queue.push(shot(1.)), Some(shot(1.)));
+		queue.open();
+
+		// Full, the oldest sound goes and the music change stays
+		assert_eq!(queue.push(music.clone()), None);
+		assert_eq!(queue.push(shot(0.1)), None);
+		assert_eq!(queue.push(shot(0.2)), None);
+		assert_eq!(queue.push(shot(0.3)), Some(shot(0.1)));
+		assert_eq!(queue.len(), 3);
+
+		// Stopping everything drops the waiting sounds but keeps the controls in order
+		assert_eq!(queue.push(AudioEvent::StopAll), None);
+		assert_eq!(queue.pop(), Some(music.clone()));
+		assert_eq!(queue.pop(), Some(AudioEvent::StopAll));
+		assert!(queue.is_empty());
+
+		// Closing keeps only what still matters for the thread to finish with, then takes nothing
+		queue.push(shot(1.));
+		queue.push(music.clone());
+		queue.close();
+		assert_eq!(queue.pop(), Some(music));
+		assert!(queue.is_empty());
+		assert!(queue.push(AudioEvent::StopAll).is_some());
+	}
+}
@@ -0,0 +1,131 @@
+#[cfg(test)]
+mod tests {
+	use glam::{Vec2, Vec3};
+
+	#[test]
+	fn key_bindings_refuse_conflicts_and_load_what_was_saved() {
+		use crate::{ext::settings::KeyBindings, fs::json::JsonParser, utils::input::{Action, Binding, Keyboard}};
+		use crate::fs::json::JsonSerializable;
+		use winit::{event::MouseButton, keyboard::KeyCode};
+
+		let mut bindings = KeyBindings::default();
+		assert!(bindings.conflicts().is_empty());
+		assert_eq!(bindings.action_of(Binding::Key(KeyCode::ShiftRight)), Some(Action::Run));
+		// A taken key is refused and nothing changes
+		assert_eq!(bindings.set(Action::Jump, Binding::Key(KeyCode::KeyW)), Err(Action::Forward));
+		assert_eq!(bindings.get(Action::Jump), Binding::Key(KeyCode::Space));
+		assert_eq!(bindings.set(Action::Jump, Binding::Key(KeyCode::KeyJ)), Ok(()));
+		assert_eq!(bindings.action_of(Binding::Key(KeyCode::Space)), None);
+
+		let loaded = KeyBindings::from_json(&bindings.to_json()).unwrap();
+		assert_eq!(loaded, bindings);
+		// Swapped keys load, a key given twice resets the later action
+		let swapped = KeyBindings::from_json(&JsonParser::parse(r#"{"forward": "KeyS", "backward": "KeyW"}"#).unwrap()).unwrap();
+		assert_eq!(swapped.get(Action::Forward), Binding::Key(KeyCode::KeyS));
+		assert_eq!(swapped.get(Action::Backward), Binding::Key(KeyCode::KeyW));
+		let twice = KeyBindings::from_json(&JsonParser::parse(r#"{"jump": "KeyQ", "chat": "KeyQ", "run": "Nope"}"#).unwrap()).unwrap();
+		assert_eq!(twice.get(Action::Jump), Binding::Key(KeyCode::KeyQ));
+		assert_eq!(twice.get(Action::Chat), Binding::Key(KeyCode::KeyT));
+		assert_eq!(twice.get(Action::Run), Binding::Key(KeyCode::ShiftLeft));
+		// Still taken after the reset, everything goes back to the defaults
+		let taken = KeyBindings::from_json(&JsonParser::parse(r#"{"forward": "KeyE"}"#).unwrap()).unwrap();
+		assert_eq!(taken, KeyBindings::default());
+
+		assert_eq!(Binding::from_config_name("MouseLeft"), Some(Binding::Mouse(MouseButton::Left)));
+		assert!(!Binding::Key(KeyCode::Escape).is_bindable());
+		// Undo and redo keep their keys
+		assert!(!Binding::Key(KeyCode::KeyZ).is_bindable() && !Binding::Key(KeyCode::KeyY).is_bindable());
+		let jump_on_z = KeyBindings::from_json(&JsonParser::parse(r#"{"jump": "KeyZ"}"#).unwrap()).unwrap();
+		assert_eq!(jump_on_z.get(Action::Jump), Binding::Key(KeyCode::Space));
+		let mut keyboard = Keyboard::default();
+		keyboard.space = true;
+		assert!(keyboard.is_action_down(Action::Jump));
+		assert!(!keyboard.is_action_down(Action::Forward));
+	}
+
+	#[test]
+	fn mouse_sensitivity_scales_both_axes_and_pitch_stays_clamped() {
+		use crate::{ext::config::CameraConfig, game::player::{look_delta, PlayerController}};
+
+		let mouse = Vec2::new(10., 4.);
+		let normal = look_delta(mouse, 1., false);
+		let fast = look_delta(mouse, 2., false);
+		assert!((fast - normal * 2.).length() < 1e-6);
+		// Turning left and looking up are both negative mouse deltas
+		assert!(normal.x < 0. && normal.y < 0.);
+		let inverted = look_delta(mouse, 1., true);
+		assert_eq!(inverted.x, normal.x);
+		assert_eq!(inverted.y, -normal.y);
+
+		let mut controller = PlayerController::new(CameraConfig::default());
+		controller.process_mouse(0., -100_000.);
+		controller.apply_look(3., false);
+		let (_, pitch) = controller.target_look();
+		assert!(pitch < std::f32::consts::FRAC_PI_2 && pitch > 1.5);
+		controller.process_mouse(0., -100_000.);
+		controller.apply_look(3., true);
+		let (_, pitch) = controller.target_look();
+		assert!(pitch > -std::f32::consts::FRAC_PI_2 && pitch < -1.5);
+	}
+
+	#[test]
+	fn gamepad_sticks_respect_deadzones_and_feed_the_actions() {
+		use crate::{ext::settings::GamepadConfig, utils::{gamepad::{apply_deadzone, Gamepad}, input::{Action, InputDevice, InputSystem, Keyboard}}};
+		use winit::dpi::PhysicalPosition;
+
+		assert_eq!(apply_deadzone(Vec2::new(0.1, 0.1), 0.2), Vec2::ZERO);
+		assert!((apply_deadzone(Vec2::new(0., 1.), 0.2) - Vec2::new(0., 1.)).length() < 1e-6);
+		// Just past the deadzone starts from zero, not from the deadzone
+		assert!(apply_deadzone(Vec2::new(0.25, 0.), 0.2).x < 0.1);
+
+		let config = GamepadConfig::default();
+		let mut pad = Gamepad::new();
+		assert!(pad.button(Action::Jump, true));
+		assert!(!pad.button(Action::Jump, true));
+		pad.set_sticks(Vec2::new(0., 0.9), Vec2::new(1., 0.));
+		let poll = pad.update(0.5, &config, false);
+		assert_eq!(poll.presses, vec![(Action::Jump, true)]);
+		// Right stick to the right turns right like the mouse moving right, y is flipped to mouse direction
+		assert!(poll.look.x > 0. && poll.look.y == 0.);
+		assert_eq!(poll.cursor, Vec2::ZERO);
+		let mut keyboard = Keyboard::default();
+		keyboard.pad = *pad.state();
+		assert!(keyboard.is_action_down(Action::Jump) && keyboard.is_action_down(Action::Forward));
+		assert!(!keyboard.is_action_down(Action::Backward));
+
+		// In the menus the left stick moves the cursor (up is up on screen) and the player stands still
+		let poll = pad.update(0.5, &config, true);
+		assert!(poll.presses.is_empty() && poll.cursor.y < 0. && poll.look == Vec2::ZERO);
+		assert_eq!(pad.state().movement, Vec2::ZERO);
+		pad.release_all();
+		assert_eq!(pad.update(0.5, &config, false).presses, vec![(Action::Jump, false)]);
+
+		// The cursor move the gamepad caused comes back from the system without taking the cursor from it
+		let mut input = InputSystem::default();
+		assert_eq!(*input.last_device(), InputDevice::KeyboardMouse);
+		input.gamepad_mut().button(Action::Jump, true);
+		input.poll_gamepad(0.1);
+		assert_eq!(*input.last_device(), InputDevice::Gamepad);
+		assert!(input.keyboard().is_action_down(Action::Jump));
+		input.set_pad_cursor(PhysicalPosition::new(10., 20.));
+		input.cursor_moved(PhysicalPosition::new(10., 20.));
+		assert_eq!(*input.last_device(), InputDevice::Gamepad);
+		input.cursor_moved(PhysicalPosition::new(40., 20.));
+		assert_eq!(*input.last_device(), InputDevice::KeyboardMouse);
+
+		// A stick half way over walks at half speed, all the way over at full speed
+		let mut controller = crate::game::player::PlayerController::new(crate::ext::config::CameraConfig::default());
+		let mut keyboard = Keyboard::default();
+		keyboard.pad.movement = apply_deadzone(Vec2::new(0., 0.6), 0.2);
+		controller.process_keyboard(&keyboard);
+		let direction = controller.get_direction();
+		assert!((direction - Vec3::new(0., 0., 0.5)).length() < 1e-5, "{:?}", direction);
+		keyboard.pad.movement = apply_deadzone(Vec2::new(-1., 0.), 0.2);
+		controller.process_keyboard(&keyboard);
+		assert!((controller.get_direction() - Vec3::NEG_X).length() < 1e-5);
+
+		// The help texts name the gamepad buttons, what has none keeps its key
+		assert_eq!(crate::utils::gamepad::button_label(Action::Inventory), Some("Y"));
+		assert_eq!(crate::utils::gamepad::button_label(Action::FillChunk), None);
+	}
+}
@@ -249,4 +249,267 @@ mod tests {
 		}
 	}
 	*/
+
+	#[test]
+	fn settings_survive_broken_files() {
+		use crate::ext::settings::Settings;
+		let dir = std::env::temp_dir().join(format!("rusticubes_settings_{}", std::process::id()));
+		let _ = std::fs::remove_dir_all(&dir);
+		let path = dir.join("settings.json");
+		let defaults = Settings::default().to_json();
+
+		// No file yet, the defaults are written
+		let settings = Settings::load_or_default(&path);
+		assert_eq!(settings.to_json(), defaults);
+		assert_eq!(JsonParser::parse(&std::fs::read_to_string(&path).unwrap()).unwrap(), defaults);
+
+		// Garbage is kept aside and replaced by a valid file
+		std::fs::write(&path, "{ \"audio\": [1, 2").unwrap();
+		let settings = Settings::load_or_default(&path);
+		assert_eq!(settings.to_json(), defaults);
+		assert_eq!(std::fs::read_to_string(dir.join("settings.json.bad")).unwrap(), "{ \"audio\": [1, 2");
+		assert_eq!(JsonParser::parse(&std::fs::read_to_string(&path).unwrap()).unwrap(), defaults);
+
+		// Unknown entries from a newer version are skipped and the file is left as it is
+		let newer = r#"{"mouse": {"sensitivity": 0.5, "acceleration": true}, "shaders": {"water": "fancy"}}"#;
+		std::fs::write(&path, newer).unwrap();
+		let settings = Settings::load_or_default(&path);
+		assert_eq!(settings.mouse_config.mouse_sensitivity, 0.5);
+		assert_eq!(std::fs::read_to_string(&path).unwrap(), newer);
+
+		// Out of range is clamped, mistyped keeps the default, and the fixed file is written back
+		std::fs::write(&path, r#"{"audio": {"main_volume": 7, "bg_volume": "loud"}, "render": {"anisotropy": 6}}"#).unwrap();
+		let settings = Settings::load_or_default(&path);
+		let music = &settings.music_settings;
+		assert_eq!(music.main_volume.val, music.main_volume.max);
+		assert_eq!(music.bg_volume.val, Settings::default().music_settings.bg_volume.val);
+		assert_eq!(settings.render_config.anisotropy, 4);
+		let written = JsonParser::parse(&std::fs::read_to_string(&path).unwrap()).unwrap();
+		assert_eq!(written, settings.to_json());
+
+		// The save goes through a temporary file that doesn't stay around
+		settings.save(&path).unwrap();
+		let leftovers: Vec<_> = std::fs::read_dir(&dir).unwrap().filter_map(|e| e.ok())
+			.filter(|e| e.file_name().to_string_lossy().ends_with(".tmp")).collect();
+		assert!(leftovers.is_empty());
+		let _ = std::fs::remove_dir_all(&dir);
+	}
+
+	#[test]
+	fn bloom_settings_persist_and_clamp() {
+		use crate::ext::settings::Settings;
+		let mut settings = Settings::default();
+		assert!(!settings.render_config.bloom);
+		settings.render_config.bloom = true;
+		settings.render_config.bloom_threshold = 0.5;
+		settings.render_config.bloom_intensity = 1.5;
+		let JsonValue::Object(root) = settings.to_json() else { panic!("settings are an object") };
+		let mut loaded = Settings::default();
+		assert!(!loaded.apply_json(&root));
+		assert!(loaded.render_config.bloom);
+		assert_eq!(loaded.render_config.bloom_threshold, 0.5);
+		assert_eq!(loaded.render_config.bloom_intensity, 1.5);
+
+		// Out of range is clamped, a mistyped toggle keeps what was there
+		let JsonValue::Object(root) = JsonParser::parse(r#"{"render": {"bloom": "yes", "bloom_threshold": 3, "bloom_intensity": -1}}"#).unwrap() else { panic!("an object") };
+		assert!(loaded.apply_json(&root));
+		assert!(loaded.render_config.bloom);
+		assert_eq!(loaded.render_config.bloom_threshold, 1.);
+		assert_eq!(loaded.render_config.bloom_intensity, 0.);
+	}
+
+	#[test]
+	fn volume_channels_mix_clamp_and_persist() {
+		use crate::ext::{audio::Channel, settings::Settings};
+		let mut settings = Settings::default();
+		let music = &mut settings.music_settings;
+		music.set_volume(Channel::Master, 0.5);
+		music.set_volume(Channel::Music, 1.);
+		music.set_volume(Channel::Sfx, 9.);
+		assert_eq!(music.volume(Channel::Sfx).val, music.fg_volume.max);
+		// Master scales the others but not itself
+		assert_eq!(music.mixed_volume(Channel::Master), 0.5);
+		assert_eq!(music.mixed_volume(Channel::Music), 0.5);
+		assert_eq!(music.mixed_volume(Channel::Sfx), music.fg_volume.max * 0.5);
+		music.set_volume(Channel::Music, -1.);
+		assert_eq!(music.mixed_volume(Channel::Music), 0.);
+
+		// The levels come back from the saved settings
+		let JsonValue::Object(root) = settings.to_json() else { panic!("settings aren't an object") };
+		let mut loaded = Settings::default();
+		assert!(!loaded.apply_json(&root));
+		for channel in [Channel::Master, Channel::Music, Channel::Sfx] {
+			assert_eq!(loaded.music_settings.volume(channel).val, settings.music_settings.volume(channel).val);
+		}
+	}
+
+	#[test]
+	fn parse_limits_refuse_pathological_nesting() {
+		use crate::fs::json::{parse_with_limits, DEFAULT_MAX_DEPTH};
+		let doc = r#"{"items": [{"name": "stone", "tags": ["a", ["b", {"c": null}]]}], "count": -1.5e2, "ok": true}"#;
+		assert_eq!(parse_with_limits(doc, DEFAULT_MAX_DEPTH, doc.len()).unwrap(), JsonParser::parse(doc).unwrap());
+
+		// Exactly at the limit is fine, one more level is an error with where it happened
+		assert!(parse_with_limits("[[[1]]]", 3, usize::MAX).is_ok());
+		match parse_with_limits("[[[[1]]]]", 3, usize::MAX) {
+			Err(JsonError::TooDeep { limit: 3, line: 1, column: _ }) => {}
+			other => panic!("expected TooDeep, got {:?}", other),
+		}
+		assert!(matches!(parse_with_limits(doc, DEFAULT_MAX_DEPTH, 10), Err(JsonError::TooLong { limit: 10, .. })));
+
+		// Deep enough to blow the stack without the limit, closed or not, every shape is refused cleanly
+		let depth = 200_000;
+		for open in ["[", "{\"a\":", "[{\"a\":"] {
+			let unclosed = open.repeat(depth);
+			assert!(matches!(JsonParser::parse(&unclosed), Err(JsonError::TooDeep { .. })));
+		}
+		let closed = format!("{}{}", "[".repeat(depth), "]".repeat(depth));
+		assert!(matches!(JsonParser::parse(&closed), Err(JsonError::TooDeep { .. })));
+		// Garbage that never nests still fails as a parse error
+		assert!(matches!(JsonParser::parse("[1, 2,, 3]"), Err(JsonError::Parse { .. })));
+	}
+
+	#[test]
+	fn serialize_compact_and_pretty_round_trip() {
+		use crate::fs::json::{to_string, to_string_pretty};
+		let doc = r#"{"b": [1, 0.1, -2.5e-8, 1e300, 123456789.125, []], "a": {"quote\"s": "tab\tnew\nline\\ \u0001 \u00e9", "empty": {}}, "c": null, "d": false}"#;
+		let value = JsonParser::parse(doc).unwrap();
+
+		// Keys sorted, no whitespace, and the same text every time
+		let compact = to_string(&value);
+		assert!(compact.starts_with(r#"{"a":{"empty":{},"quote\"s":"tab\tnew\nline\\ \u0001 é"},"b":[1,0.1,"#));
+		assert_eq!(JsonParser::parse(&compact).unwrap(), value);
+		assert_eq!(to_string(&JsonParser::parse(&compact).unwrap()), compact);
+
+		let pretty = to_string_pretty(&value, 2);
+		assert!(pretty.starts_with("{\n  \"a\": {\n    \"empty\": {},\n"));
+		assert!(pretty.ends_with("\n  \"d\": false\n}"));
+		assert_eq!(JsonParser::parse(&pretty).unwrap(), value);
+		assert_eq!(to_string_pretty(&JsonParser::parse(&pretty).unwrap(), 2), pretty);
+
+		// Floats come back bit for bit
+		for n in [0.1, 1. / 3., f64::MAX, f64::MIN_POSITIVE, -0.000_001_234_5, 9_007_199_254_740_993.] {
+			let back = JsonParser::parse(&to_string(&JsonValue::Number(n))).unwrap().as_f64().unwrap();
+			assert_eq!(back.to_bits(), n.to_bits());
+		}
+	}
+
+	#[test]
+	fn themes_switch_recolor_and_persist() {
+		use crate::ext::{config::UITheme, settings::Settings};
+		use crate::utils::color::{Border, Color};
+		assert_eq!(Color::BLACK.contrast_ratio(&Color::WHITE), 21.);
+		assert_eq!(Color::GRAY.contrast_ratio(&Color::GRAY), 1.);
+
+		// Every text of the high contrast theme clears 7:1 against what it is drawn on
+		let dark = UITheme::default();
+		let contrast = UITheme::built_in("high_contrast").unwrap();
+		let lowest = |theme: &UITheme| UITheme::keys()
+			.filter_map(|key| theme.style(&key))
+			.filter_map(|style| Some(style.text_color?.contrast_ratio(&style.color)))
+			.fold(f32::MAX, f32::min);
+		assert!(lowest(&contrast) >= 7., "{}", lowest(&contrast));
+		assert!(lowest(&contrast) > lowest(&dark));
+		assert!(lowest(&UITheme::built_in("light").unwrap()) >= 4.5);
+		assert_eq!(UITheme::built_in("neon"), None);
+
+		// On screen elements are restyled by the key they were made with, a frame made wider by hand stays wider
+		let button = dark.buttons.bad;
+		let wide = Border::colf(button.border.color, button.border.width * 2.);
+		let (color, border, text) = dark.restyle(&contrast, "buttons.bad", wide).unwrap();
+		assert_eq!((color, text), (contrast.buttons.bad.color, contrast.buttons.bad.text_color()));
+		assert_eq!(border.color, contrast.buttons.bad.border.color);
+		assert!((border.width - contrast.buttons.bad.border.width * 2.).abs() < 1e-6);
+		assert_eq!(dark.restyle(&contrast, "buttons.sparkly", Border::NONE), None);
+		// Styles that share colors in one theme still keep their own key
+		let element = crate::ui::element::UIElement::default().with_style(&dark, "buttons.extra");
+		assert_eq!(element.style_key, Some("buttons.extra"));
+		assert_eq!(element.color, dark.buttons.extra().color);
+		let (color, _, text) = dark.restyle(&contrast, "buttons.extra", element.border).unwrap();
+		assert_eq!((color, text), (contrast.buttons.extra().color, contrast.buttons.extra().text_color()));
+
+		// Custom themes: a full one round trips, a partial one keeps its base for the rest
+		let text = crate::fs::json::to_string(&contrast.to_json());
+		let back = UITheme::from_json(&JsonParser::parse(&text).unwrap()).unwrap();
+		assert_eq!(back, contrast);
+		let partial = r##"{"name": "mine", "base": "light", "styles": {"buttons.basic": {"color": "#102030", "border_width": 0.02}, "sparkles": {}}}"##;
+		let mine = UITheme::from_json(&JsonParser::parse(partial).unwrap()).unwrap();
+		let light = UITheme::built_in("light").unwrap();
+		assert_eq!(mine.name, "mine");
+		assert_eq!(mine.buttons.basic.color, Color::rgb(0x10, 0x20, 0x30));
+		assert_eq!(mine.buttons.basic.border.width, 0.02);
+		assert_eq!(mine.buttons.basic.text_color, light.buttons.basic.text_color);
+		assert_eq!(mine.panels, light.panels);
+		for bad in [r#"{"base": "neon"}"#, r#"{"styles": {"bg_panel": {"color": "blue"}}}"#, r#"{"styles": {"bg_panel": {"border_width": 5}}}"#] {
+			assert!(UITheme::from_json(&JsonParser::parse(bad).unwrap()).is_err(), "{bad}");
+		}
+
+		// The choice is saved by name, an unknown one is fixed back to the default
+		let mut settings = Settings::default();
+		settings.ui_theme = UITheme::built_in("light").unwrap();
+		let JsonValue::Object(root) = settings.to_json() else { panic!("settings aren't an object") };
+		let mut loaded = Settings::default();
+		assert!(!loaded.apply_json(&root));
+		assert_eq!(loaded.ui_theme, settings.ui_theme);
+		let mut root = root;
+		root.insert("ui".into(), JsonValue::Object(HashMap::from([("theme".to_string(), JsonValue::Number(3.))])));
+		assert!(Settings::default().apply_json(&root));
+	}
+
+	#[test]
+	fn hsv_primaries_round_trip() {
+		use crate::utils::color::Color;
+		for (color, hue) in [(Color::RED, 0.), (Color::YELLOW, 1. / 6.), (Color::GREEN, 2. / 6.), (Color::CYAN, 0.5), (Color::BLUE, 4. / 6.), (Color::MAGENTA, 5. / 6.)] {
+			let (h, s, v) = color.to_hsv();
+			assert!((h - hue).abs() < 1e-6 && s == 1. && v == 1., "{color:?} {h} {s} {v}");
+			assert_eq!(Color::from_hsv(h, s, v), color);
+		}
+		// A full turn is the same hue, saturation and value are clamped
+		assert_eq!(Color::from_hsv(1. + 1. / 3., 2., 1.), Color::GREEN);
+		assert_eq!(Color::from_hsv(-1. / 3., 1., 1.), Color::BLUE);
+		assert_eq!(Color::from_hsv(-1e-9, 1., 1.), Color::RED);
+
+		// Every color comes back exactly
+		for r in (0..=255u8).step_by(5) {
+			for g in (0..=255u8).step_by(3) {
+				for b in (0..=255u8).step_by(7) {
+					let color = Color::rgb(r, g, b);
+					let (h, s, v) = color.to_hsv();
+					assert_eq!(Color::from_hsv(h, s, v), color);
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn hsv_lerp_takes_the_short_way() {
+		use crate::utils::color::Color;
+		// Red to blue goes through magenta, not green
+		let middle = Color::RED.lerp_hsv(Color::BLUE, 0.5);
+		assert_eq!(middle, Color::MAGENTA);
+		assert!((middle.to_hsv().0 - 5. / 6.).abs() < 1e-6);
+		assert_eq!(Color::BLUE.lerp_hsv(Color::RED, 0.5), middle);
+		// Across 0 from magenta-ish to orange-ish
+		let (from, to) = (Color::from_hsv(0.9, 1., 1.), Color::from_hsv(0.1, 1., 1.));
+		let hue = from.lerp_hsv(to, 0.5).to_hsv().0;
+		assert!(hue < 0.01 || hue > 0.99, "{hue}");
+		assert_eq!(from.lerp_hsv(to, 0.), from);
+		assert_eq!(from.lerp_hsv(to, 1.), to);
+
+		// Gray has no hue: it fades in the other end's instead of sweeping the wheel
+		let (h, s, _) = Color::GRAY.to_hsv();
+		assert_eq!((h, s), (0., 0.));
+		let green = Color::from_hsv(1. / 3., 1., 0.5);
+		for t in [0.25, 0.5, 0.75] {
+			let (h, ..) = Color::GRAY.lerp_hsv(green, t).to_hsv();
+			assert!((h - 1. / 3.).abs() < 0.01, "{t} {h}");
+		}
+		assert_eq!(Color::BLACK.lerp_hsv(Color::WHITE, 0.5), Color::rgb(128, 128, 128));
+
+		// Alpha rides along, blended on its own
+		let (clear, solid) = (Color::RED.with_a(0), Color::BLUE.with_a(200));
+		assert_eq!(clear.lerp_hsv(solid, 0.5).a, 100);
+		assert_eq!(Color::from_hsv(0.5, 1., 1.).with_a(42).a, 42);
+		assert_eq!(Color::rgba(10, 20, 30, 7).lerp_hsv(Color::rgba(10, 20, 30, 7), 0.3), Color::rgba(10, 20, 30, 7));
+	}
 }
@@ -3,9 +3,10 @@
 mod tests {
 
 	use crate::fs::binary::BinarySerializable;
-	use crate::world::manager::{WorldData, get_save_path, load_world_data, save_world_data, update_world_data};
+	use crate::world::{manager::get_save_path, data::{WorldData, load_world_data, save_world_data, update_world_data}};
 	use std::io::{self};
 	use crate::utils::time::Time;
+	use crate::world::generation::GenMode;
 
 	// Test 1: Simple roundtrip serialization/deserialization with perfect data
 	#[test]
@@ -14,6 +15,9 @@ mod tests {
 			version: "1.0.0".to_string(),
 			creation_date: Time::now(),
 			last_opened_date: Time::now(),
+			time_of_day: 0.75,
+			gen_mode: GenMode::Void,
+			play_time: 5025,
 		};
 
 		let bytes = original.to_binary();
@@ -22,6 +26,23 @@ mod tests {
 		assert_eq!(original.version, deserialized.version);
 		assert_eq!(original.creation_date, deserialized.creation_date);
 		assert_eq!(original.last_opened_date, deserialized.last_opened_date);
+		assert_eq!(original.time_of_day, deserialized.time_of_day);
+		assert_eq!(original.gen_mode, deserialized.gen_mode);
+		assert_eq!(original.play_time, deserialized.play_time);
+
+		// Saves from before the play time start at none
+		let old_save = &bytes[..bytes.len() - 8];
+		assert_eq!(WorldData::from_binary(old_save).unwrap().play_time, 0);
+
+		// Saves from before the generation modes are Noise
+		let old_save = &bytes[..bytes.len() - 9];
+		assert_eq!(WorldData::from_binary(old_save).unwrap().gen_mode, GenMode::Noise);
+
+		// Saves from before the day cycle still load
+		let old_save = &bytes[..bytes.len() - 13];
+		let deserialized = WorldData::from_binary(old_save).unwrap();
+		assert_eq!(deserialized.last_opened_date, original.last_opened_date);
+		assert_eq!(deserialized.time_of_day, crate::game::state::TimeOfDay::default().time());
 	}
 
 	// Test 2: File operations with correct data
@@ -51,6 +72,37 @@ mod tests {
 		Ok(())
 	} // works just does not save new version for now...
 
+	#[test]
+	fn world_data_file_is_versioned() {
+		use crate::fs::binary::{self, FORMAT_VERSION, MAGIC};
+		use std::io::ErrorKind;
+		let dir = std::env::temp_dir().join(format!("rusticubes_world_data_{}", std::process::id()));
+		let _ = std::fs::remove_dir_all(&dir);
+		let file = dir.join("world_data.dat");
+
+		let mut data = WorldData::new();
+		data.play_time = 42;
+		save_world_data(&dir, &data).unwrap();
+		let bytes = std::fs::read(&file).unwrap();
+		assert!(bytes.starts_with(&MAGIC));
+		assert_eq!(load_world_data(&dir).unwrap().play_time, 42);
+
+		// Files from before the header still load
+		std::fs::write(&file, data.to_binary()).unwrap();
+		assert_eq!(load_world_data(&dir).unwrap().play_time, 42);
+
+		// A damaged one or one from a newer version is refused, not read as garbage
+		let mut damaged = bytes.clone();
+		*damaged.last_mut().unwrap() ^= 1;
+		std::fs::write(&file, &damaged).unwrap();
+		assert_eq!(load_world_data(&dir).unwrap_err().kind(), ErrorKind::InvalidData);
+		let mut newer = binary::write_versioned(&data.to_binary());
+		newer[MAGIC.len()..binary::HEADER_SIZE].copy_from_slice(&(FORMAT_VERSION + 1).to_le_bytes());
+		std::fs::write(&file, &newer).unwrap();
+		assert_eq!(load_world_data(&dir).unwrap_err().kind(), ErrorKind::Unsupported);
+		let _ = std::fs::remove_dir_all(&dir);
+	}
+
 	// Test 3: Malformed data handling
 	#[test]
 	fn deserialization_errors() {
@@ -71,4 +123,35 @@ mod tests {
 		partial_time.extend_from_slice(&[0; 5]); // Only half of first Time struct
 		assert!(WorldData::from_binary(&partial_time).is_none());
 	}
+
+	#[test]
+	fn play_time_sums_across_sessions_and_formats() {
+		use crate::utils::time::{day_clock, format_day_time, format_duration, PlaySession};
+		use crate::game::state::TimeOfDay;
+		assert_eq!(format_duration(0), "0s");
+		assert_eq!(format_duration(59), "59s");
+		assert_eq!(format_duration(60 * 12 + 30), "12m");
+		assert_eq!(format_duration(3600 + 23 * 60 + 59), "1h 23m");
+		assert_eq!(format_duration(86_400 - 1), "23h 59m");
+		assert_eq!(format_duration(2 * 86_400 + 5 * 3600 + 7), "2d 5h");
+
+		// The saved time and this session add up, and the sum is what the next load starts from
+		let mut session = PlaySession::new(3600);
+		for _ in 0..600 { session.tick(0.1); }
+		session.tick(-1.);
+		assert_eq!(session.session_secs(), 60);
+		assert_eq!(session.total_secs(), 3660);
+		assert_eq!(session.display(), "1h 1m");
+		let mut data = WorldData::new();
+		data.play_time = session.total_secs();
+		let reloaded = PlaySession::new(WorldData::from_binary(&data.to_binary()).unwrap().play_time);
+		assert_eq!(reloaded.total_secs(), 3660);
+
+		// The day clock starts at midnight and wraps
+		assert_eq!(day_clock(0.), (0, 0));
+		assert_eq!(day_clock(0.5), (12, 0));
+		assert_eq!(day_clock(1.25), (6, 0));
+		assert_eq!(format_day_time(0.75), "18:00");
+		assert_eq!(TimeOfDay::default().clock_text(), "07:12");
+	}
 }
@@ -88,3 +88,77 @@ pub fn network_host_test() {
 	api::cleanup_network();
 	println!("=== HOST TEST COMPLETE ===");
 }
+
+#[cfg(test)]
+mod tests {
+	use crate::block::{main::Chunk, math::ChunkCoord};
+	use crate::game::remote::{PlayerState, RemotePlayers};
+	use crate::network::{stream::stream_radius, sync::may_sync_chunk};
+	use crate::world::main::World;
+	use glam::Vec3;
+
+	#[test]
+	fn host_takes_chunks_only_near_the_client() {
+		let mut world = World::empty();
+		let near = ChunkCoord::new(1, 0, -1);
+		let far = ChunkCoord::new(stream_radius() as i32 + 5, 0, 0);
+		world.set_chunk(near, Chunk::empty());
+		world.set_chunk(far, Chunk::empty());
+		let mut players = RemotePlayers::new();
+
+		// Nobody knows where the client is yet
+		assert!(!may_sync_chunk(&world, &players, 1, near));
+		players.update(1, 0, PlayerState::new(Vec3::new(1., 1., 1.), 0.));
+		assert!(may_sync_chunk(&world, &players, 1, near));
+		assert!(!may_sync_chunk(&world, &players, 1, far));
+		assert!(!may_sync_chunk(&world, &players, 2, near));
+		// Chunks the host doesn't have can't be forced on it
+		assert!(!may_sync_chunk(&world, &players, 1, ChunkCoord::new(0, 1, 0)));
+	}
+
+	#[test]
+	fn streamed_garbage_is_refused() {
+		use crate::block::{main::{Block, Material}, storage::BlockStorage};
+		use crate::fs::binary::BinarySerializable;
+		use crate::network::stream::apply_chunk;
+		let mut world = World::empty();
+		let coord = ChunkCoord::new(0, 0, 0);
+		world.set_chunk(coord, Chunk::new(2));
+
+		// Sparse storage of a two block palette with an index of 2
+		let sparse = BlockStorage::Sparse { palette: vec![Block::default(), Block::new(Material(2))], indices: Box::new([1u8; Chunk::VOLUME]) };
+		let mut data = sparse.to_binary();
+		*data.last_mut().unwrap() = 2;
+		data.extend_from_slice(&Chunk::empty().entities().to_binary());
+		assert!(apply_chunk(&mut world, 1, coord, &data).is_err());
+		assert!(apply_chunk(&mut world, 2, coord, &[0xFF, 1, 2]).is_err());
+		assert_eq!(world.get_chunk(&coord).unwrap().storage(), &BlockStorage::uniform(Block::new(Material(2))));
+
+		assert!(apply_chunk(&mut world, 3, coord, &Chunk::empty().to_binary()).is_ok());
+		assert!(world.get_chunk(&coord).unwrap().is_empty());
+	}
+
+	#[cfg(feature = "steam")]
+	#[test]
+	fn steam_lobbies_become_hosts() {
+		use crate::network::steam::{discovery_timeout, lobby_host, timed_out_discovery};
+		use std::time::Duration;
+		// Only lobbies of this game are listed, the id is what joining needs
+		let host = lobby_host(76561198000000001, Some("rusticubes"), Some("Island")).unwrap();
+		assert_eq!(host.world_name, "Island");
+		assert_eq!(host.join, "76561198000000001");
+		assert_eq!(lobby_host(1, Some("rusticubes"), None).unwrap().world_name, "Unknown");
+		assert!(lobby_host(1, Some("othergame"), Some("Island")).is_none());
+		assert!(lobby_host(1, None, Some("Island")).is_none());
+
+		// Steam is slow to answer, short LAN timeouts are stretched
+		assert_eq!(discovery_timeout(100), Duration::from_secs(5));
+		assert_eq!(discovery_timeout(8000), Duration::from_secs(8));
+
+		// A list that never came keeps the hosts found before and says why
+		let result = timed_out_discovery(&[host.clone()], Duration::from_secs(5));
+		assert_eq!(result.hosts.len(), 1);
+		assert_eq!(result.hosts[0].join, host.join);
+		assert_eq!(result.errors, vec!["Lobby list timed out after 5000ms".to_string()]);
+	}
+}
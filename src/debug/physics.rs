@@ -162,6 +162,44 @@ mod tests {
 		assert!(penetration.is_some());
 	}
 
+	#[test]
+	fn ray_intersection() {
+		let a = AABB::new(vec3(0.0, 0.0, 0.0), vec3(2.0, 2.0, 2.0));
+
+		// Hitting each face from outside
+		let faces = [
+			(vec3(-3.0, 1.0, 1.0), Vec3::X),
+			(vec3(5.0, 1.0, 1.0), Vec3::NEG_X),
+			(vec3(1.0, -3.0, 1.0), Vec3::Y),
+			(vec3(1.0, 5.0, 1.0), Vec3::NEG_Y),
+			(vec3(1.0, 1.0, -3.0), Vec3::Z),
+			(vec3(1.0, 1.0, 5.0), Vec3::NEG_Z),
+		];
+		for (origin, dir) in faces {
+			let t = a.ray_intersect(origin, dir).expect("face hit");
+			assert!((t - 3.0).abs() < 1e-5, "{origin} {dir} {t}");
+		}
+
+		// Not normalized, the distance is in direction lengths
+		assert_eq!(a.ray_intersect(vec3(-4.0, 1.0, 1.0), vec3(2.0, 0.0, 0.0)), Some(2.0));
+		// Diagonal
+		let t = a.ray_intersect(vec3(-1.0, -1.0, -1.0), Vec3::ONE.normalize()).unwrap();
+		assert!((t - 3f32.sqrt()).abs() < 1e-5);
+
+		// Starting inside
+		assert_eq!(a.ray_intersect(vec3(1.0, 1.0, 1.0), Vec3::NEG_Y), Some(0.0));
+		// Pointing away, the box is behind
+		assert_eq!(a.ray_intersect(vec3(-3.0, 1.0, 1.0), Vec3::NEG_X), None);
+
+		// Parallel to a slab outside of it never hits, even when the other axes line up
+		assert_eq!(a.ray_intersect(vec3(-3.0, 3.0, 1.0), Vec3::X), None);
+		assert_eq!(a.ray_intersect(vec3(1.0, 1.0, 2.5), Vec3::Y), None);
+
+		// Just missing past a corner
+		assert_eq!(a.ray_intersect(vec3(-1.1, 1.0, 1.0), vec3(1.0, 1.1, 0.0)), None);
+		assert_eq!(a.ray_intersect(vec3(3.0, 3.0, 3.0), vec3(-1.0, -1.0, -0.2)), None);
+	}
+
 	#[test] #[ignore]
 	fn collision_resolution() {
 		let mut a = AABB::new(vec3(0.0, 0.0, 0.0), vec3(10.0, 10.0, 10.0));
@@ -344,4 +382,268 @@ mod tests {
 		zero_mass_body.update(1.0, GRAVITY);
 		// Should not crash and should not move due to zero mass
 	}
+
+	/// Empty world with the given solid blocks (written straight into the chunk storage, no lut needed)
+	fn world_with_blocks(blocks: &[glam::IVec3]) -> crate::world::main::World {
+		let mut world = crate::world::main::World::empty();
+		add_blocks(&mut world, blocks, crate::block::main::Material(2));
+		world
+	}
+	fn add_blocks(world: &mut crate::world::main::World, blocks: &[glam::IVec3], material: crate::block::main::Material) {
+		use crate::block::{main::{Block, Chunk}, math::{ChunkCoord, LocalPos}};
+		for &pos in blocks {
+			let coord = ChunkCoord::from_world_pos(pos);
+			if world.get_chunk(&coord).is_none() {
+				world.set_chunk(coord, Chunk::empty());
+			}
+			let chunk = world.get_chunk_mut(&coord).expect("just added");
+			chunk.storage_mut().set(usize::from(LocalPos::from(pos)), Block::new(material));
+		}
+	}
+	fn floor(y: i32, size: i32) -> Vec<glam::IVec3> {
+		(-size..size).flat_map(|x| (-size..size).map(move |z| glam::IVec3::new(x, y, z))).collect()
+	}
+
+	#[test]
+	fn body_lands_and_slides() {
+		use crate::physic::body::Body;
+		let world = world_with_blocks(&floor(0, 4));
+		
+		// Falling onto the floor, y is blocked and zeroed but the horizontal move still happens
+		let mut body = Body::new(AABB::from_pos(vec3(0.5, 3.0, 0.5), vec3(0.3, 1.8, 0.3)));
+		body.velocity = vec3(1.0, -5.0, 0.0);
+		let flags = body.move_and_collide(&world, vec3(0.5, -5.0, 0.0));
+		assert!(flags.y && !flags.x && !flags.z);
+		assert!((body.aabb.min.y - 1.0).abs() < 1e-3);
+		assert!((body.position().x - 1.0).abs() < 1e-3);
+		assert_eq!(body.velocity, vec3(1.0, 0.0, 0.0));
+		
+		// Walking on the floor is not blocked by the block edges
+		let flags = body.move_and_collide(&world, vec3(2.0, 0.0, 1.5));
+		assert!(!flags.any());
+	}
+
+	#[test]
+	fn body_does_not_tunnel() {
+		use crate::physic::body::Body;
+		// One block thick wall at x = 5
+		let wall: Vec<glam::IVec3> = (0..4).flat_map(|y| (-2..3).map(move |z| glam::IVec3::new(5, y, z))).collect();
+		let world = world_with_blocks(&wall);
+		
+		let mut body = Body::new(AABB::from_pos(vec3(0.5, 1.0, 0.5), vec3(0.3, 1.8, 0.3)));
+		body.velocity = vec3(500.0, 0.0, 0.0);
+		let flags = body.move_and_collide(&world, vec3(50.0, 0.0, 0.0));
+		assert!(flags.x);
+		assert!((body.aabb.max.x - 5.0).abs() < 1e-3);
+		assert_eq!(body.velocity.x, 0.0);
+		
+		// Same thing going the other way
+		let mut body = Body::new(AABB::from_pos(vec3(9.5, 1.0, 0.5), vec3(0.3, 1.8, 0.3)));
+		let flags = body.move_and_collide(&world, vec3(-30.0, 0.0, 0.0));
+		assert!(flags.x);
+		assert!((body.aabb.min.x - 6.0).abs() < 1e-3);
+	}
+
+	#[test]
+	fn body_steps_onto_ledge() {
+		use crate::physic::body::Body;
+		// Floor at y = 0 with a one block high ledge from x = 2
+		let mut blocks = floor(0, 4);
+		blocks.extend((-4..4).map(|z| glam::IVec3::new(2, 1, z)));
+		let world = world_with_blocks(&blocks);
+		let standing = || {
+			let mut body = Body::new(AABB::from_pos(vec3(0.5, 1.0, 0.5), vec3(0.3, 1.8, 0.3))).with_step_height(1.0);
+			body.move_and_collide(&world, vec3(0.0, -0.1, 0.0));
+			assert!(body.grounded);
+			body
+		};
+		
+		// Walks up without losing the horizontal velocity
+		let mut body = standing();
+		body.velocity = vec3(3.0, 0.0, 0.0);
+		let flags = body.move_and_collide(&world, vec3(3.0, -0.01, 0.0));
+		assert!(!flags.x && flags.y);
+		assert!((body.aabb.min.y - 2.0).abs() < 1e-3);
+		assert!((body.position().x - 3.5).abs() < 1e-3);
+		assert_eq!(body.velocity.x, 3.0);
+		assert!(body.grounded);
+		
+		// Too high for the step height
+		let mut body = standing().with_step_height(0.6);
+		let flags = body.move_and_collide(&world, vec3(3.0, -0.01, 0.0));
+		assert!(flags.x);
+		assert!((body.aabb.max.x - 2.0).abs() < 1e-3);
+		
+		// Not while airborne
+		let mut body = Body::new(AABB::from_pos(vec3(0.5, 1.5, 0.5), vec3(0.3, 1.8, 0.3))).with_step_height(1.0);
+		let flags = body.move_and_collide(&world, vec3(3.0, 0.0, 0.0));
+		assert!(flags.x);
+		assert!((body.aabb.min.y - 1.5).abs() < 1e-3);
+		
+		// Not when the head would hit a ceiling
+		blocks.push(glam::IVec3::new(0, 3, 0));
+		let world = world_with_blocks(&blocks);
+		let mut body = Body::new(AABB::from_pos(vec3(0.5, 1.0, 0.5), vec3(0.3, 1.8, 0.3))).with_step_height(1.0);
+		body.grounded = true;
+		let flags = body.move_and_collide(&world, vec3(3.0, -0.01, 0.0));
+		assert!(flags.x);
+		assert!((body.aabb.min.y - 1.0).abs() < 1e-3);
+	}
+
+	#[test]
+	fn body_gravity_and_jump() {
+		use crate::physic::body::Body;
+		let world = world_with_blocks(&floor(0, 4));
+		let mut body = Body::new(AABB::from_pos(vec3(0.5, 40.0, 0.5), vec3(0.3, 1.8, 0.3))).with_gravity(30.0, 20.0);
+		
+		// Speeds up until the terminal velocity
+		body.apply_gravity(0.5);
+		assert!((body.velocity.y + 15.0).abs() < 1e-3);
+		body.apply_gravity(0.5);
+		assert_eq!(body.velocity.y, -20.0);
+		assert!(!body.jump(10.0));
+		
+		// Landing resets the vertical velocity
+		for _ in 0..300 { body.update(&world, 0.01); }
+		assert!(body.grounded);
+		assert!((body.aabb.min.y - 1.0).abs() < 1e-3);
+		
+		// Standing still does not build up speed or bounce
+		for _ in 0..100 {
+			body.update(&world, 0.01);
+			assert!(body.grounded);
+			assert!((body.aabb.min.y - 1.0).abs() < 1e-3);
+			assert!(body.velocity.y > -1.0);
+		}
+		
+		// The jump decays, peaks and lands again
+		assert!(body.jump(10.0));
+		let mut peak: f32 = 0.;
+		for _ in 0..200 {
+			body.update(&world, 0.01);
+			peak = peak.max(body.aabb.min.y);
+		}
+		// v² / 2g = 1.666 blocks above the floor
+		assert!((peak - 2.666).abs() < 0.1, "peak {peak}");
+		assert!(body.grounded);
+		assert!((body.aabb.min.y - 1.0).abs() < 1e-3);
+	}
+
+	#[test]
+	fn player_falls_and_jumps() {
+		use crate::{game::player::Player, ext::config::CameraConfig, utils::input::Keyboard};
+		let world = world_with_blocks(&floor(0, 4));
+		let mut player = Player::dummy(vec3(0.5, 3.0, 0.5), CameraConfig::new(Vec3::ZERO));
+		let frame = |player: &mut Player| {
+			let delta = player.step(0.01, &world);
+			player.append_position(delta);
+		};
+		
+		for _ in 0..100 { frame(&mut player); }
+		assert!((player.pos().y - 1.0).abs() < 1e-3);
+		assert!(player.body().grounded);
+		
+		let mut keyboard = Keyboard::default();
+		keyboard.space = true;
+		player.controller_mut().process_keyboard(&keyboard);
+		frame(&mut player);
+		player.controller_mut().process_keyboard(&Keyboard::default());
+		assert!(player.pos().y > 1.0);
+		
+		let mut peak: f32 = 0.;
+		for _ in 0..200 {
+			frame(&mut player);
+			peak = peak.max(player.pos().y);
+		}
+		assert!(peak > 2.0);
+		assert!((player.pos().y - 1.0).abs() < 1e-3);
+		
+		// Flying ignores gravity
+		player.set_fly(true);
+		player.append_position(vec3(0.0, 2.0, 0.0));
+		for _ in 0..50 { frame(&mut player); }
+		assert!((player.pos().y - 3.0).abs() < 1e-3);
+	}
+
+	#[test]
+	fn body_floats_in_fluid() {
+		use crate::{physic::body::Body, block::main::Material};
+		let water = Material(3);
+		// Two blocks deep pool (y 1 to 3) on a floor
+		let mut world = world_with_blocks(&floor(0, 4));
+		let pool: Vec<glam::IVec3> = floor(1, 4).into_iter().chain(floor(2, 4)).collect();
+		add_blocks(&mut world, &pool, water);
+		let body_at = |y: f32| Body::new(AABB::from_pos(vec3(0.5, y, 0.5), vec3(0.3, 1.8, 0.3)));
+		
+		assert!((body_at(1.0).submersion(&world, water) - 1.0).abs() < 1e-3);
+		assert!((body_at(2.0).submersion(&world, water) - 1.0 / 1.8).abs() < 1e-3);
+		assert_eq!(body_at(5.0).submersion(&world, water), 0.0);
+		
+		// Fully under it floats up, feet in it sinks slower than in the air, out of it is plain gravity
+		let mut under = body_at(1.0);
+		under.update_in_fluid(&world, water, 0.1);
+		assert!(under.velocity.y > 0.0);
+		let mut feet = body_at(2.0);
+		feet.update_in_fluid(&world, water, 0.1);
+		let mut dry = body_at(5.0);
+		dry.update_in_fluid(&world, water, 0.1);
+		assert!(feet.velocity.y < 0.0 && feet.velocity.y > dry.velocity.y);
+		assert!((dry.velocity.y + dry.gravity * 0.1).abs() < 1e-3);
+		
+		// Dropped in, it passes the surface and settles floating with the head out
+		let mut body = body_at(6.0);
+		for _ in 0..1000 {
+			body.update_in_fluid(&world, water, 0.01);
+			body.move_and_collide(&world, body.velocity * 0.01);
+		}
+		assert!(body.velocity.length() < 0.05);
+		assert!(body.aabb.min.y > 1.0 && body.aabb.max.y > 3.0);
+		
+		// Jumping out of the water gets the normal gravity right away
+		body.aabb = body.aabb.translate(vec3(0.0, 3.0, 0.0));
+		body.velocity = Vec3::ZERO;
+		body.update_in_fluid(&world, water, 0.1);
+		assert!((body.velocity.y + body.gravity * 0.1).abs() < 1e-3);
+	}
+
+	#[test]
+	fn fly_goes_through_blocks_and_lands_cleanly() {
+		use crate::{game::player::{Player, MAX_FLY_SPEED, MIN_FLY_SPEED}, ext::config::CameraConfig, utils::input::Keyboard, world::data::PlayerData};
+		let world = world_with_blocks(&floor(0, 4));
+		let mut player = Player::dummy(vec3(0.5, 1.0, 0.5), CameraConfig::new(Vec3::ZERO));
+		let frame = |player: &mut Player| {
+			let delta = player.step(0.01, &world);
+			player.append_position(delta);
+		};
+
+		// Flying a bit into the floor, nothing pushes back
+		player.set_fly(true);
+		player.set_pos(vec3(0.5, 0.3, 0.5));
+		for _ in 0..20 { frame(&mut player); }
+		assert!((player.pos().y - 0.3).abs() < 1e-3);
+		let mut keyboard = Keyboard::default();
+		keyboard.space = true;
+		player.controller_mut().process_keyboard(&keyboard);
+		for _ in 0..20 { frame(&mut player); }
+		assert!(player.pos().y > 0.3);
+		player.controller_mut().process_keyboard(&Keyboard::default());
+		player.set_pos(vec3(0.5, 0.3, 0.5));
+
+		// Flying is for this session unless kept
+		assert!(!PlayerData::for_save(&player, false).flying);
+		assert!(PlayerData::for_save(&player, true).flying);
+
+		// Walking again lifts it out of the block onto the floor, from rest
+		player.set_fly(false);
+		frame(&mut player);
+		assert!((player.pos().y - 1.0).abs() < 1e-3);
+		for _ in 0..50 { frame(&mut player); }
+		assert!((player.pos().y - 1.0).abs() < 1e-3);
+		assert!(player.body().grounded);
+
+		player.scale_fly_speed(100.);
+		assert_eq!(player.fly_speed(), MAX_FLY_SPEED);
+		player.scale_fly_speed(-100.);
+		assert_eq!(player.fly_speed(), MIN_FLY_SPEED);
+	}
 }
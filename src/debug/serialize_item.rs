@@ -243,4 +243,387 @@ mod tests {
 
 		assert_eq!(item, parsed_item);
 	}
+
+	#[test]
+	fn double_click_gathers_into_cursor() {
+		use crate::item::{items::{ItemStack, init_item_lut}, inventory::{Inventory, AreaType}};
+		use crate::utils::input::ClickMode;
+		use winit::keyboard::ModifiersState;
+		init_item_lut();
+		let mods = ModifiersState::empty();
+		
+		let mut inv = Inventory::new(4, 3, 9, 9);
+		inv.get_area_mut(AreaType::Hotbar).set_at(0, 1, ItemStack::from_str("arrow").with_stack_size(10).opt());
+		inv.get_area_mut(AreaType::Hotbar).set_at(0, 2, ItemStack::from_str("bow").with_stack_size(1).opt());
+		inv.get_area_mut(AreaType::Inventory).set_at(0, 0, ItemStack::from_str("arrow").with_stack_size(30).opt());
+		inv.get_area_mut(AreaType::Inventory).set_at(1, 0, ItemStack::from_str("arrow").with_stack_size(40).opt());
+		
+		// Empty cursor : nothing happens
+		inv.handle_click_press((0, 5), &mods, false, AreaType::Hotbar, ClickMode::Double);
+		assert!(inv.get_cursor().is_none());
+		
+		// Pick up 10 then gather : hotbar first, then inventory, up to the max stack
+		inv.handle_click_press((0, 1), &mods, false, AreaType::Hotbar, ClickMode::Left);
+		inv.handle_click_press((0, 1), &mods, false, AreaType::Hotbar, ClickMode::Double);
+		let cursor = inv.get_cursor().expect("cursor keeps the gathered stack");
+		assert_eq!(cursor.stack(), cursor.max_stack_size());
+		assert_eq!(cursor.stack(), 64);
+		assert!(inv.get_area(&AreaType::Inventory).get_at(0, 0).is_none()); // 30 taken whole
+		assert_eq!(inv.get_area(&AreaType::Inventory).get_at(1, 0).map(|i| i.stack()), Some(16)); // remainder stays
+		assert!(inv.get_area(&AreaType::Hotbar).get_at(0, 2).is_some()); // different item is left alone
+	}
+	
+	#[test]
+	fn shaped_recipe_shifts_but_does_not_mirror() {
+		use crate::item::recipes::{Recipe, CraftingInput};
+		let (a, b): (usize, usize) = (3, 5);
+		// Asymmetric L shape
+		let recipe = Recipe::shaped(vec![vec![a, 0], vec![a, b]], 7usize);
+		
+		// Same shape in the bottom right corner of a 3x3 grid
+		let shifted = CraftingInput::from(vec![vec![0, 0, 0], vec![0, a, 0], vec![0, a, b]]);
+		assert!(recipe.matches(&shifted));
+		// Mirrored horizontally
+		let mirrored = CraftingInput::from(vec![vec![0, a, 0], vec![b, a, 0], vec![0, 0, 0]]);
+		assert!(!recipe.matches(&mirrored));
+		// Extra item next to the pattern
+		let extra = CraftingInput::from(vec![vec![a, 0, 0], vec![a, b, b], vec![0, 0, 0]]);
+		assert!(!recipe.matches(&extra));
+	}
+	
+	#[test]
+	fn shapeless_recipe_ignores_slot_order() {
+		use crate::item::recipes::{Recipe, CraftingInput};
+		let (a, b, c): (usize, usize, usize) = (3, 5, 9);
+		let recipe = Recipe::shapeless(&[a, b, c, a], 11usize);
+		
+		assert!(recipe.matches(&CraftingInput::from(vec![vec![a, a, b], vec![c, 0, 0], vec![0, 0, 0]])));
+		assert!(recipe.matches(&CraftingInput::from(vec![vec![c, 0, a], vec![0, b, 0], vec![a, 0, 0]])));
+		// Count matters
+		assert!(!recipe.matches(&CraftingInput::from(vec![vec![c, 0, a], vec![0, b, 0], vec![0, 0, 0]])));
+		assert!(!recipe.matches(&CraftingInput::from(vec![vec![c, b, a], vec![a, b, 0], vec![0, 0, 0]])));
+	}
+	
+	#[test]
+	fn item_durability_damage_and_roundtrip() {
+		use crate::item::items::{ItemStack, init_item_lut};
+		init_item_lut();
+		
+		let mut sword = ItemStack::from_str("iron_sword");
+		let max = sword.max_durability().expect("tools have durability");
+		assert_eq!(sword.durability(), Some(max));
+		let fresh = sword.clone();
+		
+		assert!(!sword.damage(10));
+		assert_eq!(sword.durability(), Some(max - 10));
+		assert!(!sword.can_stack_with(&fresh)); // damaged and undamaged don't stack
+		
+		let bytes = sword.to_binary();
+		assert_eq!(bytes.len(), sword.binary_size());
+		assert_eq!(ItemStack::from_binary(&bytes), Some(sword.clone()));
+		
+		assert!(sword.damage(u16::MAX)); // breaks, saturating at 0
+		assert_eq!(sword.durability(), Some(0));
+		
+		// Non-tools stay without durability and stack as usual
+		let mut arrow = ItemStack::from_str("arrow");
+		assert_eq!(arrow.durability(), None);
+		assert!(!arrow.damage(5));
+		assert!(arrow.can_stack_with(&ItemStack::from_str("arrow").with_stack_size(3)));
+		let bytes = arrow.to_binary();
+		assert_eq!(ItemStack::from_binary(&bytes).and_then(|i| i.durability()), None);
+
+		// Items saved before the layout version (name, count, custom data) still read, tools at full durability
+		let mut old = "iron_sword".to_string().to_binary();
+		old.extend_from_slice(&4u32.to_binary());
+		old.push(0);
+		let loaded = ItemStack::from_binary(&old).expect("old items read");
+		assert_eq!((loaded.name().to_string(), loaded.stack, loaded.durability()), ("iron_sword".to_string(), 4, Some(250)));
+		assert!(loaded.can_stack_with(&ItemStack::from_str("iron_sword")));
+		let mut old_arrow = "arrow".to_string().to_binary();
+		old_arrow.extend_from_slice(&4u32.to_binary());
+		old_arrow.push(0);
+		assert_eq!(ItemStack::from_binary(&old_arrow).and_then(|i| i.durability()), None);
+		// One from a newer layout is refused instead of read as the wrong fields
+		let mut newer = sword.to_binary();
+		newer[2] += 1;
+		assert_eq!(ItemStack::from_binary(&newer), None);
+	}
+	
+	#[test]
+	fn container_search() {
+		use crate::item::{items::{ItemStack, CustomData, init_item_lut}, inventory::ItemContainer};
+		init_item_lut();
+		
+		// 2D container
+		let mut grid = ItemContainer::new(3, 3);
+		grid.set_at(0, 1, ItemStack::from_str("iron_sword").opt());
+		grid.set_at(2, 2, ItemStack::from_str("arrow").with_stack_size(5).opt());
+		let mut named = ItemStack::from_str("bow");
+		named.set_stack_data(Some(Box::new(CustomData { name: Some("Long Bow".to_string()), ..CustomData::default() })));
+		grid.set_at(1, 0, named.opt());
+		
+		assert_eq!(grid.find_by_name("IRON sword"), vec![1]);
+		assert_eq!(grid.find_by_name("long"), vec![3]);
+		assert_eq!(grid.find_by_name("ow"), vec![3, 8]); // bow + arrow
+		assert!(grid.find_by_name("apple").is_empty());
+		assert_eq!(grid.find_all(|item| item.stack() > 1), vec![8]);
+		
+		// Linear container, empty slots are never matched
+		let mut row = ItemContainer::new(1, 4);
+		row.set(2, ItemStack::from_str("arrow").opt());
+		assert_eq!(row.find_by_name("arr"), vec![2]);
+		assert_eq!(row.find_all(|_| true), vec![2]);
+	}
+	
+	#[test]
+	fn item_take_exact_count() {
+		use crate::item::items::{ItemStack, init_item_lut};
+		init_item_lut();
+		
+		let mut stack = ItemStack::from_str("arrow").with_stack_size(10);
+		assert_eq!(stack.take(0), None);
+		assert_eq!(stack.stack(), 10);
+		
+		let taken = stack.take(3).expect("3 of 10");
+		assert_eq!((taken.stack(), stack.stack()), (3, 7));
+		assert!(taken.can_stack_with(&stack));
+		
+		// More than there is : clamped
+		let taken = stack.take(50).expect("clamped to 7");
+		assert_eq!((taken.stack(), stack.stack()), (7, 0));
+		assert_eq!(stack.take(1), None);
+		assert!(stack.clone().opt().is_none());
+		
+		// Exactly the whole stack
+		let mut stack = ItemStack::from_str("arrow").with_stack_size(4);
+		assert_eq!(stack.take(4).map(|i| i.stack()), Some(4));
+		assert!(stack.opt().is_none());
+	}
+	
+	#[test]
+	fn drag_distributes_cursor_stack() {
+		use crate::item::{items::{ItemStack, init_item_lut}, inventory::{Inventory, AreaType}};
+		use crate::utils::input::ClickMode;
+		init_item_lut();
+		
+		let mut inv = Inventory::new(4, 3, 9, 9);
+		inv.get_area_mut(AreaType::Inventory).set_at(0, 1, ItemStack::from_str("arrow").with_stack_size(60).opt());
+		inv.get_area_mut(AreaType::Inventory).set_at(0, 2, ItemStack::from_str("bow").opt());
+		
+		// Left : evenly, full and non stackable slots are skipped, the remainder stays in the cursor
+		inv.set_cursor(ItemStack::from_str("arrow").with_stack_size(10).opt());
+		assert!(inv.begin_drag());
+		assert!(inv.drag_over((0, 0), AreaType::Inventory));
+		assert!(!inv.drag_over((0, 0), AreaType::Inventory)); // already in
+		assert!(inv.drag_over((0, 1), AreaType::Inventory)); // stackable, 4 room left
+		assert!(!inv.drag_over((0, 2), AreaType::Inventory)); // bow
+		assert!(!inv.drag_over((0, 0), AreaType::Armor)); // arrows are not armor
+		assert!(inv.drag_over((0, 3), AreaType::Hotbar));
+		inv.end_drag(ClickMode::Left);
+		assert!(!inv.is_dragging());
+		let stack_at = |inv: &Inventory, area: AreaType, pos: (u8, u8)| inv.get_area(&area).get_at(pos.0, pos.1).map(|i| i.stack());
+		assert_eq!(stack_at(&inv, AreaType::Inventory, (0, 0)), Some(3));
+		assert_eq!(stack_at(&inv, AreaType::Inventory, (0, 1)), Some(63));
+		assert_eq!(stack_at(&inv, AreaType::Hotbar, (0, 3)), Some(3));
+		assert_eq!(inv.get_cursor().map(|i| i.stack()), Some(1));
+		
+		// Right : one per slot
+		inv.set_cursor(ItemStack::from_str("arrow").with_stack_size(5).opt());
+		inv.begin_drag();
+		inv.drag_over((1, 0), AreaType::Inventory);
+		inv.drag_over((1, 1), AreaType::Inventory);
+		inv.end_drag(ClickMode::Right);
+		assert_eq!(stack_at(&inv, AreaType::Inventory, (1, 0)), Some(1));
+		assert_eq!(stack_at(&inv, AreaType::Inventory, (1, 1)), Some(1));
+		assert_eq!(inv.get_cursor().map(|i| i.stack()), Some(3));
+		
+		// Cancel : nothing moves
+		inv.begin_drag();
+		inv.drag_over((2, 0), AreaType::Inventory);
+		assert!(inv.cancel_drag());
+		inv.end_drag(ClickMode::Left);
+		assert_eq!(stack_at(&inv, AreaType::Inventory, (2, 0)), None);
+		assert_eq!(inv.get_cursor().map(|i| i.stack()), Some(3));
+	}
+	
+	#[test]
+	fn container_expand_with_aspect() {
+		use crate::item::inventory::ItemContainer;
+		let mut container = ItemContainer::new(3, 3);
+		
+		for (capacity, target) in [(27, 9), (24, 5), (40, 4), (13, 4), (3, 9), (18, 7), (1, 1)] {
+			container.expand_with_aspect(capacity, target);
+			assert_eq!(container.capacity(), capacity as usize, "capacity {capacity} target {target}");
+			assert_eq!(container.items().len(), capacity as usize);
+			if capacity != 13 { // prime, no clean split under the target
+				assert!(container.cols() <= target, "capacity {capacity} target {target}");
+			}
+		}
+		container.expand_with_aspect(27, 9);
+		assert_eq!((container.rows(), container.cols()), (3, 9));
+		container.expand_with_aspect(24, 5);
+		assert_eq!((container.rows(), container.cols()), (6, 4));
+		container.expand_with_aspect(13, 4);
+		assert_eq!(container.capacity(), 13);
+	}
+	
+	#[test]
+	fn recipes_from_json_file() {
+		use crate::item::items::{ItemStack, init_item_lut};
+		use crate::item::recipes::{recipes_from_json, RecipeKind, CraftingInput, CraftingResult};
+		init_item_lut();
+		let plank = ItemStack::from_str("plank").resource_index();
+		let arrow = ItemStack::from_str("arrow").resource_index();
+		let crafting = ItemStack::from_str("crafting").resource_index();
+		
+		let recipes = recipes_from_json(r#"{ "recipes": [
+			{ "type": "shaped", "pattern": [["plank", "plank"], [null, "plank"]], "output": { "name": "crafting", "count": 2 } },
+			{ "type": "shapeless", "ingredients": ["arrow", "plank"], "output": "arrow" }
+		] }"#).expect("valid recipes");
+		assert_eq!(recipes.len(), 2);
+		
+		assert_eq!(recipes[0].kind(), RecipeKind::Shaped);
+		assert_eq!(recipes[0].output(), &CraftingResult::Stack { item_id: crafting, count: 2 });
+		assert!(recipes[0].matches(&CraftingInput::from(vec![vec![0, 0, 0], vec![0, plank, plank], vec![0, 0, plank]])));
+		assert_eq!(recipes[1].kind(), RecipeKind::Shapeless);
+		assert_eq!(recipes[1].output(), &CraftingResult::Single(arrow));
+		assert!(recipes[1].matches(&CraftingInput::from(vec![vec![plank, 0], vec![0, arrow]])));
+		
+		// Errors
+		let err = |json: &str| recipes_from_json(json).expect_err(json).to_string();
+		assert!(err(r#"[{ "type": "shapeless", "ingredients": ["plonk"], "output": "arrow" }]"#).contains("plonk"));
+		assert!(err(r#"[{ "type": "shaped", "pattern": [["plank"], ["plank", "plank"]], "output": "arrow" }]"#).contains("same length"));
+		assert!(err(r#"[{ "type": "shaped", "pattern": [["plank"], ["plank"], ["plank"], ["plank"]], "output": "arrow" }]"#).contains("rows"));
+		assert!(err(r#"[{ "type": "shaped", "pattern": "plank", "output": "arrow" }]"#).contains("pattern"));
+		assert!(err(r#"[{ "type": "rotated", "ingredients": ["plank"], "output": "arrow" }]"#).contains("rotated"));
+		assert!(err(r#"[{ "type": "shapeless", "ingredients": ["plank"], "output": "nothing" }]"#).contains("nothing"));
+	}
+	
+	#[test]
+	fn shift_click_gives_every_crafted_item() {
+		use crate::item::{items::{ItemStack, init_item_lut}, inventory::{Inventory, ItemContainer, AreaType}};
+		use crate::item::{recipes::{recipes_from_json, register_recipes}, ui_inventory::{InventoryLayout, AreaLayout}};
+		use crate::utils::input::ClickMode;
+		use winit::keyboard::ModifiersState;
+		init_item_lut();
+		register_recipes(recipes_from_json(r#"[
+			{ "type": "shapeless", "ingredients": ["wheat", "brick_red"], "output": { "name": "arrow", "count": 4 } }
+		]"#).expect("valid recipe"));
+		let shift = ModifiersState::SHIFT;
+		
+		let mut grid = ItemContainer::new(2, 2);
+		grid.set_at(0, 0, ItemStack::from_str("wheat").with_stack_size(5).opt());
+		grid.set_at(0, 1, ItemStack::from_str("brick_red").with_stack_size(5).opt());
+		let mut inv = Inventory::from_raw(ItemContainer::new(4, 1), ItemContainer::new(1, 1), ItemContainer::new(1, 1), ItemContainer::new(2, 2), Some(&mut grid as *mut _));
+		let mut layout = InventoryLayout::default();
+		layout.areas.push(AreaLayout::new(1, 1, (0., 0.), AreaType::Output));
+		inv.set_layout(&layout);
+		
+		// 5 crafts of 4 arrows, all in one stack
+		inv.handle_click_press((0, 0), &shift, false, AreaType::Output, ClickMode::Left);
+		assert_eq!(inv.get_area(&AreaType::Hotbar).get_at(0, 0).map(|i| i.stack()), Some(20));
+		assert!(inv.get_area(&AreaType::Storage).get_at(0, 0).is_none());
+		
+		// Only 2 of 3 crafts fit on top of 56 arrows : the last wheat and brick stay in the grid
+		inv.get_area_mut(AreaType::Storage).set_at(0, 0, ItemStack::from_str("wheat").with_stack_size(3).opt());
+		inv.get_area_mut(AreaType::Storage).set_at(0, 1, ItemStack::from_str("brick_red").with_stack_size(3).opt());
+		inv.get_area_mut(AreaType::Hotbar).set_at(0, 0, ItemStack::from_str("arrow").with_stack_size(56).opt());
+		inv.get_area_mut(AreaType::Inventory).set_at(0, 0, ItemStack::from_str("plank").opt());
+		inv.handle_click_press((0, 0), &shift, false, AreaType::Output, ClickMode::Left);
+		assert_eq!(inv.get_area(&AreaType::Hotbar).get_at(0, 0).map(|i| i.stack()), Some(64));
+		assert_eq!(inv.get_area(&AreaType::Storage).get_at(0, 1).map(|i| i.stack()), Some(1));
+		
+		// Nothing fits : nothing is used up
+		inv.handle_click_press((0, 0), &shift, false, AreaType::Output, ClickMode::Left);
+		assert_eq!(inv.get_area(&AreaType::Storage).get_at(0, 0).map(|i| i.stack()), Some(1));
+	}
+	
+	#[test]
+	fn processor_smelting() {
+		use crate::item::{items::{ItemStack, init_item_lut}, recipes::Processor};
+		init_item_lut();
+		
+		let mut furnace = Processor::new();
+		furnace.input.set(0, ItemStack::from_str("fish").with_stack_size(3).opt());
+		
+		// No fuel : nothing happens
+		furnace.tick(10.);
+		assert_eq!((furnace.progress, furnace.output.first()), (0., None));
+		
+		// One plank is 15 s = 3 fish
+		furnace.input.set(0, ItemStack::from_str("fish").with_stack_size(5).opt());
+		furnace.fuel.set(0, ItemStack::from_str("plank").with_stack_size(1).opt());
+		furnace.tick(12.);
+		assert_eq!(furnace.output.first().map(|i| (i.name().to_string(), i.stack())), Some(("cooked_fish".to_string(), 2)));
+		assert!((furnace.progress - 2.).abs() < 1e-4);
+		furnace.tick(10.);
+		assert_eq!(furnace.output.first().map(|i| i.stack()), Some(3));
+		assert!(!furnace.is_burning());
+		assert!(furnace.fuel.first().is_none());
+		assert_eq!(furnace.input.first().map(|i| i.stack()), Some(2));
+		assert_eq!(furnace.progress, 0.); // stopped right after finishing
+		
+		// Full output : progress pauses and no fuel is wasted
+		furnace.fuel.set(0, ItemStack::from_str("plank").with_stack_size(1).opt());
+		furnace.output.set(0, ItemStack::new("cooked_fish".to_string()).with_stack_size(64).opt());
+		furnace.tick(3.);
+		assert_eq!(furnace.progress, 0.);
+		assert_eq!(furnace.fuel.first().map(|i| i.stack()), Some(1));
+		
+		// Different item in the output also blocks it
+		furnace.output.set(0, ItemStack::from_str("arrow").opt());
+		furnace.tick(3.);
+		assert_eq!(furnace.input.first().map(|i| i.stack()), Some(2));
+		
+		furnace.output.set(0, None);
+		furnace.tick(5.);
+		assert_eq!(furnace.output.first().map(|i| i.stack()), Some(1));
+		assert!((furnace.fuel_remaining - 10.).abs() < 1e-4);
+		
+		// Fuel running out mid smelt keeps the progress until more fuel comes
+		let mut furnace = Processor::new();
+		furnace.input.set(0, ItemStack::new("iron_ore".to_string()).with_stack_size(2).opt());
+		furnace.fuel.set(0, ItemStack::from_str("plank").with_stack_size(1).opt());
+		furnace.tick(20.); // 10 s per ore, 15 s of fuel
+		assert_eq!(furnace.output.first().map(|i| i.stack()), Some(1));
+		assert!((furnace.progress - 5.).abs() < 1e-4);
+		furnace.tick(20.);
+		assert!((furnace.progress - 5.).abs() < 1e-4);
+		furnace.fuel.set(0, ItemStack::from_str("plank").with_stack_size(1).opt());
+		furnace.tick(5.);
+		assert_eq!(furnace.output.first().map(|i| (i.name().to_string(), i.stack())), Some(("iron".to_string(), 2)));
+		assert!(furnace.input.first().is_none());
+	}
+	
+	#[test]
+	fn inventory_sort_area() {
+		use crate::item::{items::{ItemStack, init_item_lut}, inventory::{Inventory, AreaType}};
+		init_item_lut();
+		
+		let mut inv = Inventory::new(4, 3, 3, 9);
+		let area = inv.get_area_mut(AreaType::Inventory);
+		area.set_at(0, 2, ItemStack::from_str("arrow").with_stack_size(40).opt());
+		area.set_at(1, 1, ItemStack::from_str("bow").with_stack_size(1).opt());
+		area.set_at(2, 0, ItemStack::from_str("arrow").with_stack_size(40).opt());
+		inv.get_area_mut(AreaType::Armor).set_at(2, 0, ItemStack::from_str("coat").opt());
+		inv.set_cursor(ItemStack::from_str("arrow").with_stack_size(7).opt());
+		
+		assert!(inv.sort_area(AreaType::Inventory));
+		let area = inv.get_area(&AreaType::Inventory);
+		let arrow = ItemStack::from_str("arrow").resource_index();
+		let bow = ItemStack::from_str("bow").resource_index();
+		let arrows: Vec<u32> = area.iter().flatten().filter(|i| i.name() == "arrow").map(|i| i.stack()).collect();
+		assert_eq!(arrows, vec![64, 16]); // two half stacks -> one full + the rest
+		assert_eq!(area.count_items(), 3);
+		assert!(area.iter().skip(3).all(|slot| slot.is_none())); // compacted to the front
+		let first_id = area.get(0).map(|i| i.resource_index());
+		assert_eq!(first_id, Some(arrow.min(bow)));
+		
+		// Cursor and armor untouched, output and armor refused
+		assert_eq!(inv.get_cursor().map(|i| i.stack()), Some(7));
+		assert!(!inv.sort_area(AreaType::Output));
+		assert!(!inv.sort_area(AreaType::Armor));
+		assert!(inv.get_area(&AreaType::Armor).get_at(2, 0).is_some());
+	}
 }
@@ -0,0 +1,309 @@
+#[cfg(test)]
+mod tests {
+	use crate::block::{main::{Block, Chunk, Material}, storage::StorageType};
+	use glam::Vec2;
+
+	#[test]
+	fn tab_focus_order_wraps_and_skips_hidden() {
+		use crate::ui::{element::{UIElement, FOCUS_RING}, events::{focus_order, next_focus}};
+		let mut hidden = UIElement::button(4, "hidden".into()).with_position(Vec2::new(0., 0.3)).with_z_index(5);
+		hidden.visible = false;
+		let mut disabled = UIElement::button(5, "disabled".into()).with_position(Vec2::new(0., 0.2)).with_z_index(5);
+		disabled.enabled = false;
+		let elements = [
+			UIElement::panel(1).with_z_index(9),
+			UIElement::button(2, "lower".into()).with_position(Vec2::new(0., -0.5)).with_z_index(5),
+			UIElement::input(3).with_position(Vec2::new(0., 0.5)).with_z_index(5),
+			hidden,
+			disabled,
+			UIElement::button(6, "right".into()).with_position(Vec2::new(0.4, -0.5)).with_z_index(5),
+			UIElement::button(7, "dialog".into()).with_position(Vec2::new(0., -0.9)).with_z_index(20),
+		];
+		// Top layer first, then top to bottom, left to right
+		let order = focus_order(&elements);
+		assert_eq!(order, vec![7, 3, 2, 6]);
+
+		assert_eq!(next_focus(&order, 0, 1), Some(7));
+		assert_eq!(next_focus(&order, 0, -1), Some(6));
+		assert_eq!(next_focus(&order, 3, 1), Some(2));
+		assert_eq!(next_focus(&order, 6, 1), Some(7));
+		assert_eq!(next_focus(&order, 7, -1), Some(6));
+		assert_eq!(next_focus(&[], 3, 1), None);
+
+		let mut focused = UIElement::button(8, "ring".into());
+		assert_eq!(focused.drawn_border().width, 0.);
+		focused.focused = true;
+		assert_eq!(focused.drawn_border().width, FOCUS_RING.width);
+		assert_eq!(focused.drawn_border().color, FOCUS_RING.color);
+	}
+
+	#[test]
+	fn scroll_list_clips_children_and_maps_the_thumb() {
+		use crate::ui::{element::{self, UIElement, UIElementData}, render::scissor_rect};
+		let mut list = UIElement::scroll_list(1).with_position(Vec2::new(-0.6, -0.38)).with_size(Vec2::new(1.2, 0.72));
+		// Everything fits, no scrollbar
+		assert_eq!(list.max_scroll(), 0.);
+		assert!(list.scrollbar_rects().is_none());
+		assert_eq!(list.scroll_for_thumb_at(0.), 0.);
+
+		list.data = UIElementData::ScrollList { scroll: 0., content: 1.2 };
+		assert!((list.max_scroll() - 0.48).abs() < 1e-5);
+		let ((track_pos, track_size), (_, thumb_size)) = list.scrollbar_rects().unwrap();
+		assert_eq!(track_pos.x, 0.6 - element::SCROLLBAR_WIDTH);
+		assert!(thumb_size.y < track_size.y);
+		assert!(list.on_scrollbar(0.59, 0.));
+		assert!(!list.on_scrollbar(0., 0.));
+		// Top of the track is the start, bottom the end
+		assert_eq!(list.scroll_for_thumb_at(0.34), 0.);
+		assert!((list.scroll_for_thumb_at(-0.38) - list.max_scroll()).abs() < 1e-5);
+
+		let partly = UIElement::button(2, "partly".into()).with_position(Vec2::new(-0.4, 0.3)).with_size(Vec2::new(0.8, 0.1)).with_parent(1);
+		let hidden = UIElement::button(3, "hidden".into()).with_position(Vec2::new(-0.4, 0.5)).with_size(Vec2::new(0.8, 0.1)).with_parent(1);
+		let outside = UIElement::button(4, "outside".into()).with_position(Vec2::new(-0.4, 0.5)).with_size(Vec2::new(0.8, 0.1));
+		let elements = [list, partly, hidden, outside];
+		assert_eq!(element::clip_rect(&elements, &elements[1]), Some((Vec2::new(-0.6, -0.38), Vec2::new(1.2 - element::SCROLLBAR_WIDTH, 0.72))));
+		assert_eq!(element::clip_rect(&elements, &elements[3]), None);
+		assert!(element::in_clip(&elements, &elements[1]));
+		assert!(!element::in_clip(&elements, &elements[2]));
+		assert!(element::in_clip(&elements, &elements[3]));
+		// The part scrolled out of the list can't be clicked
+		assert!(element::hits(&elements, &elements[1], 0., 0.32));
+		assert!(!element::hits(&elements, &elements[1], 0., 0.37));
+		assert!(element::hits(&elements, &elements[3], 0., 0.55));
+
+		assert_eq!(scissor_rect((Vec2::new(-1., -1.), Vec2::new(2., 2.)), 800, 600), (0, 0, 800, 600));
+		assert_eq!(scissor_rect((Vec2::ZERO, Vec2::new(1., 1.)), 800, 600), (400, 0, 400, 300));
+	}
+
+	#[test]
+	fn wrapped_lines_break_words_and_keep_new_lines() {
+		use crate::ui::{element::UIElement, text::wrap_lines};
+		// Every character one unit wide
+		let wrap = |text: &str, width: f32| wrap_lines(text, width, |_| 1.0);
+		assert_eq!(wrap("the quick brown fox", 10.), vec!["the quick", "brown fox"]);
+		assert_eq!(wrap("fits", 10.), vec!["fits"]);
+		// A word longer than a line is broken up, the next word goes on behind it
+		assert_eq!(wrap("a abcdefghijkl b", 5.), vec!["a", "abcde", "fghij", "kl b"]);
+		assert_eq!(wrap("one\n\ntwo", 10.), vec!["one", "", "two"]);
+		assert_eq!(wrap("", 10.), vec![""]);
+		// Too narrow for anything still makes progress
+		assert_eq!(wrap("ab", 0.), vec!["a", "b"]);
+
+		assert!(!UIElement::label(1, "text".into()).is_wrapped());
+		assert!(UIElement::label(1, "text".into()).with_wrap(true).is_wrapped());
+		assert!(!UIElement::button(2, "text".into()).with_wrap(true).is_wrapped());
+	}
+
+	#[test]
+	fn tooltip_waits_follows_and_stays_on_screen() {
+		use crate::ui::tooltip::{Tooltip, TOOLTIP_DELAY, tooltip_position, tooltip_size};
+		use crate::item::items::display_name;
+		assert_eq!(display_name("brick_grey"), "Brick grey");
+		assert_eq!(display_name(""), "");
+
+		let mut tooltip = Tooltip::new();
+		// Nothing to show, nothing to wait for
+		assert!(!tooltip.advance(1.));
+		assert!(!tooltip.rest("Stone", Vec2::ZERO));
+		assert!(!tooltip.advance(TOOLTIP_DELAY / 2.));
+		// Moving over the same item keeps the time waited, another item starts over
+		assert!(tooltip.rest("Stone", Vec2::new(0.1, 0.)));
+		assert!(tooltip.advance(TOOLTIP_DELAY / 2.));
+		assert!(!tooltip.rest("Dirt", Vec2::ZERO));
+		assert!(!tooltip.advance(TOOLTIP_DELAY / 2.));
+
+		// Measured the way the label wraps, a long line takes as many lines as it breaks into
+		use crate::ui::{text::{wrap_lines, WRAP_LINE_HEIGHT}, tooltip::TOOLTIP_WIDTH};
+		let wrap = |text: &str, size: Vec2| wrap_lines(text, size.x, |_| 0.02);
+		let size = tooltip_size("Stone\nBlock\nStack: 1/64", wrap);
+		assert!(size.y > tooltip_size("Stone", wrap).y);
+		assert_eq!(size.x, TOOLTIP_WIDTH);
+		let long = "A very long description of a block that does not fit on a single line";
+		let lines = wrap_lines(long, TOOLTIP_WIDTH, |_| 0.02).len();
+		assert!(lines > 1);
+		assert!(tooltip_size(long, wrap).y >= tooltip_size("Stone", wrap).y + (lines - 1) as f32 * WRAP_LINE_HEIGHT - 1e-5);
+		// Right of and below the cursor
+		let pos = tooltip_position(Vec2::ZERO, size);
+		assert!(pos.x > 0. && pos.y + size.y < 0.);
+		// Pushed back in at the right and bottom edges
+		let pos = tooltip_position(Vec2::new(0.95, -0.95), size);
+		assert!((pos.x + size.x - 1.).abs() < 1e-5);
+		assert_eq!(pos.y, -1.);
+	}
+
+	#[test]
+	fn slider_clamps_snaps_and_jumps_to_clicks() {
+		use crate::ui::element::{snap_value, UIElement};
+		assert_eq!(snap_value(5., 0., 4., None), 4.);
+		assert_eq!(snap_value(-1., 0., 4., Some(1.)), 0.);
+		// Steps count from min, not from zero
+		assert_eq!(snap_value(2.2, 1., 9., Some(2.)), 3.);
+		assert_eq!(snap_value(8.9, 1., 9., Some(2.)), 9.);
+		// The max is reachable even off the step grid
+		assert_eq!(snap_value(10., 0., 10., Some(3.)), 10.);
+		assert_eq!(snap_value(0.5, 0., 1., Some(0.)), 0.5);
+
+		let mut slider = UIElement::slider(1, 0., 4.)
+			.with_position(Vec2::new(-0.4, 0.))
+			.with_size(Vec2::new(0.8, 0.1))
+			.with_step(1.)
+			.with_value(7.);
+		assert_eq!(slider.get_value(), Some(4.));
+		// Clicking the track jumps the handle, the far ends are min and max
+		assert!(slider.set_calc_value(-0.4, 0.05));
+		assert_eq!(slider.get_value(), Some(0.));
+		assert!(!slider.set_calc_value(-0.9, 0.05));
+		assert!(slider.set_calc_value(0.01, 0.05));
+		assert_eq!(slider.get_value(), Some(2.));
+		assert!(slider.set_calc_value(0.9, 0.05));
+		assert_eq!(slider.get_value(), Some(4.));
+		// Not a slider, nothing changes
+		assert!(!UIElement::button(2, "b".into()).set_calc_value(0., 0.));
+	}
+
+	#[test]
+	fn dropdown_lists_options_above_everything_while_open() {
+		use crate::ui::{element::{UIElement, DROPDOWN_OPEN_Z}, render::dropdown_rows};
+		let options = vec!["Noise".into(), "Flat".into(), "Void".into()];
+		let mut dropdown = UIElement::dropdown(1, options)
+			.with_position(Vec2::new(0., 0.))
+			.with_size(Vec2::new(0.4, 0.1))
+			.with_z_index(5);
+		// Closed it is only the box
+		assert_eq!(dropdown.option_rect(0), None);
+		assert_eq!(dropdown.option_at(0.1, -0.05), None);
+		assert!(!dropdown.contains_point(0.1, -0.05));
+		assert_eq!(dropdown.layer_z(), 5);
+		assert!(dropdown_rows(&dropdown).is_empty());
+
+		dropdown.set_open(true);
+		assert_eq!(dropdown.layer_z(), DROPDOWN_OPEN_Z);
+		assert_eq!(dropdown.option_rect(0).map(|(pos, _)| pos), Some(Vec2::new(0., -0.1)));
+		assert_eq!(dropdown.option_at(0.1, -0.05), Some(0));
+		assert_eq!(dropdown.option_at(0.1, -0.25), Some(2));
+		assert_eq!(dropdown.option_at(0.5, -0.25), None);
+		assert!(dropdown.contains_point(0.1, -0.25));
+		assert_eq!(dropdown_rows(&dropdown).len(), 3);
+
+		// Picking clamps to the options, the arrows stop at the ends
+		assert!(dropdown.select(7));
+		assert_eq!(dropdown.get_selected(), Some(2));
+		assert!(!dropdown.step_selected(1));
+		assert!(dropdown.step_selected(-1));
+		assert_eq!(dropdown.get_element_data().text(), Some("Flat".to_string()));
+		assert!(dropdown.step_selected(-5));
+		assert_eq!(dropdown.get_selected(), Some(0));
+
+		// No room below, the list opens upwards
+		let mut low = UIElement::dropdown(2, vec!["a".into(), "b".into()])
+			.with_position(Vec2::new(0., -0.9))
+			.with_size(Vec2::new(0.4, 0.1));
+		low.set_open(true);
+		assert!(low.option_rect(1).is_some_and(|(pos, _)| (pos.y + 0.7).abs() < 1e-5));
+		assert!(!UIElement::button(3, "b".into()).select(1));
+	}
+
+	#[test]
+	fn anchored_elements_keep_their_proportions_on_wide_windows() {
+		use crate::ui::element::{Anchor, UIElement, REFERENCE_ASPECT};
+		let close = |a: Vec2, b: Vec2| (a - b).abs().max_element() < 1e-5;
+		let (wide, narrow) = (21.0 / 9.0, 4.0 / 3.0);
+		let element = |anchor| UIElement::panel(1)
+			.with_position(Vec2::new(-0.4, 0.1))
+			.with_size(Vec2::new(0.8, 0.2))
+			.with_anchor(anchor);
+
+		// Stretched elements stay where the screen coordinates put them
+		let mut stretch = element(Anchor::Stretch);
+		stretch.fit_aspect(REFERENCE_ASPECT, wide);
+		assert!(close(stretch.position, Vec2::new(-0.4, 0.1)) && close(stretch.size, Vec2::new(0.8, 0.2)));
+
+		// Centered ones narrow around the middle on wider windows, narrower ones don't squeeze them
+		let mut center = element(Anchor::Center);
+		center.fit_aspect(REFERENCE_ASPECT, REFERENCE_ASPECT);
+		center.fit_aspect(REFERENCE_ASPECT, narrow);
+		assert!(close(center.size, Vec2::new(0.8, 0.2)));
+		center.fit_aspect(narrow, wide);
+		let scale = REFERENCE_ASPECT / wide;
+		assert!(close(center.position, Vec2::new(-0.4 * scale, 0.1)) && close(center.size, Vec2::new(0.8 * scale, 0.2)));
+		center.fit_aspect(wide, REFERENCE_ASPECT);
+		assert!(close(center.position, Vec2::new(-0.4, 0.1)) && close(center.size, Vec2::new(0.8, 0.2)));
+
+		// Right anchored ones keep their gap to the right edge
+		let mut right = element(Anchor::Right);
+		right.fit_aspect(REFERENCE_ASPECT, wide);
+		assert!((1.0 - (right.position.x + right.size.x) - 0.6 * scale).abs() < 1e-5);
+
+		// Keeping the shape makes it wider on a narrower window
+		let mut square = element(Anchor::Center).with_keep_aspect(true);
+		square.fit_aspect(REFERENCE_ASPECT, narrow);
+		assert!(square.size.x > 0.8 && close(square.size, Vec2::new(0.8 * REFERENCE_ASPECT / narrow, 0.2)));
+	}
+
+	#[test]
+	fn hover_reports_only_changes_that_need_a_redraw() {
+		use crate::ui::element::UIElement;
+		let mut button = UIElement::button(1, "b".into());
+		assert!(button.update_hover_state(true));
+		assert!(button.hovered);
+		// Staying on it keeps the buffers as they are
+		assert!(!button.update_hover_state(true));
+		assert!(button.update_hover_state(false));
+		assert!(!button.update_hover_state(false));
+
+		// Labels don't light up, so hovering them never dirties the ui
+		let mut label = UIElement::label(2, "l".into());
+		assert!(!label.update_hover_state(true));
+		assert!(label.hovered);
+	}
+
+	#[test]
+	fn confirm_dialogs_run_only_the_chosen_action() {
+		use crate::ui::{dialog::ConfirmDialog, manager::UIState};
+		use std::{cell::Cell, rc::Rc};
+		let (yes, no) = (Rc::new(Cell::new(0)), Rc::new(Cell::new(0)));
+		let mut dialog = ConfirmDialog::new("Delete world?")
+			.on_yes({ let yes = yes.clone(); move || yes.set(yes.get() + 1) })
+			.on_no({ let no = no.clone(); move || no.set(no.get() + 1) });
+		assert_eq!(dialog.message, "Delete world?");
+		assert!(matches!(dialog.previous(), UIState::None));
+
+		dialog.answer(true);
+		assert_eq!((yes.get(), no.get()), (1, 0));
+		dialog.answer(false);
+		assert_eq!((yes.get(), no.get()), (1, 1));
+
+		// Without actions either answer just closes it
+		let mut plain = ConfirmDialog::new("Sure?");
+		plain.answer(true);
+		plain.answer(false);
+	}
+
+	#[test]
+	fn debug_overlay_counts_storages_and_refreshes_sparingly() {
+		use crate::ui::overlay::{storage_counts, DebugOverlay, DebugStats, OVERLAY_REFRESH};
+		let mut mixed = Chunk::empty();
+		mixed.set_block(0, Block::new(Material(2)));
+		let counts = storage_counts([&Chunk::empty(), &Chunk::empty(), &mixed]);
+		assert_eq!(counts[StorageType::Uniform.as_u8() as usize], 2);
+		assert_eq!(counts.iter().sum::<usize>(), 3);
+
+		// Frames are averaged over the refresh, nothing in between
+		let mut overlay = DebugOverlay::new();
+		let frame = OVERLAY_REFRESH / 4.;
+		assert_eq!(overlay.advance(frame), None);
+		assert_eq!(overlay.advance(frame), None);
+		assert_eq!(overlay.advance(frame), None);
+		let (fps, frame_ms) = overlay.advance(frame * 1.01).expect("a refresh");
+		assert!((fps - 4. / (OVERLAY_REFRESH * 1.0025)).abs() < 0.1);
+		assert!((frame_ms - frame * 1000. * 1.0025).abs() < 0.01);
+		assert_eq!(overlay.advance(frame), None);
+
+		let stats = DebugStats { fps: 60., frame_ms: 16.67, chunks: 3, loaded: 2, queued: None, storage: counts, ..Default::default() };
+		let text = stats.text();
+		assert_eq!(text.lines().count(), 6);
+		assert!(text.starts_with("60 fps (16.67ms)"));
+		assert!(text.contains("Uniform 2") && text.contains("? queued"));
+		assert!(text.contains("update 0.00ms, mesh 0.00ms, render 0.00ms"));
+	}
+}
@@ -5,12 +5,14 @@ mod tests {
 		block::{
 			main::{Block, Chunk, Material},
 			storage::{StorageType, BlockStorage},
-			math::{LocalPos, BlockRotation, ChunkCoord},
+			light::{LightStorage, MAX_LIGHT},
+			math::{LocalPos, BlockRotation, ChunkCoord, AxisBasic},
 		},
-		fs::binary::BinarySerializable,
+		fs::binary::{BinarySerializable, FixedBinarySize},
 		world::main::World,
+		world::biome::{BiomeGen, BIOMES, Column},
 	};
-	use glam::IVec3;
+	use glam::{IVec3, Vec2, Vec3};
 
 	#[test]
 	fn basic_block_operations() {
@@ -149,6 +151,33 @@ mod tests {
 		assert!(air_count > 0);
 	}
 
+	#[test]
+	fn biome_blending_is_continuous() {
+		// Outside the biome range it snaps to the edge biomes
+		assert_eq!(BiomeGen::biome_weights(-1.), (0, 0, 0.));
+		assert_eq!(BiomeGen::biome_weights(1.), (BIOMES.len() - 1, BIOMES.len() - 1, 0.));
+		
+		// Walking along the noise axis the mixed amplitude never jumps
+		let amplitude = |t: f32| {
+			let (a, b, s) = BiomeGen::biome_weights(t);
+			BIOMES[a].height_amplitude + (BIOMES[b].height_amplitude - BIOMES[a].height_amplitude) * s
+		};
+		let mut t = -1.;
+		while t < 1. {
+			assert!((amplitude(t + 0.001) - amplitude(t)).abs() < 2.);
+			t += 0.001;
+		}
+	}
+
+	#[test]
+	fn biome_column_layers() {
+		let column = Column { height: 10, surface_depth: 3, base: Material(2), surface: Material(5) };
+		assert_eq!(column.material_at(11), None);
+		assert_eq!(column.material_at(10), Some(Material(5)));
+		assert_eq!(column.material_at(8), Some(Material(5)));
+		assert_eq!(column.material_at(7), Some(Material(2)));
+	}
+
 	#[test]
 	fn storage_transitions() {
 		let mut chunk = Chunk::empty();
@@ -190,6 +219,189 @@ mod tests {
 		assert_eq!(chunk.get_block(pos1.into()), block1);
 		assert_eq!(chunk.get_block(pos2.into()), block2);
 	}
+
+	#[test]
+	fn raycast_across_chunks() {
+		let mut world = World::empty();
+		world.set_chunk(ChunkCoord::new(1, 0, 0), Chunk::new(2));
+		world.set_chunk(ChunkCoord::new(-1, 0, 0), Chunk::new(2));
+		
+		// Crossing from chunk 0 into chunk 1
+		let hit = world.raycast(Vec3::new(0.5, 5.5, 5.5), Vec3::X, 64.).expect("should hit");
+		assert_eq!(hit.world_pos, IVec3::new(Chunk::SIZE_I, 5, 5));
+		assert_eq!(hit.prev_pos, IVec3::new(Chunk::SIZE_I - 1, 5, 5));
+		assert_eq!(hit.face, AxisBasic::X);
+		assert!((hit.distance - (Chunk::SIZE as f32 - 0.5)).abs() < 1e-4);
+		
+		// Negative direction, crossing into negative chunk coords
+		let hit = world.raycast(Vec3::new(5.5, 5.5, 5.5), Vec3::NEG_X * 3., 64.).expect("should hit");
+		assert_eq!(hit.world_pos, IVec3::new(-1, 5, 5));
+		assert_eq!(hit.normal(), IVec3::X);
+		
+		// Out of range and pointing at nothing
+		assert!(world.raycast(Vec3::new(0.5, 5.5, 5.5), Vec3::X, 10.).is_none());
+		assert!(world.raycast(Vec3::new(0.5, 5.5, 5.5), Vec3::Y, 64.).is_none());
+		assert!(world.raycast(Vec3::new(0.5, 5.5, 5.5), Vec3::ZERO, 64.).is_none());
+	}
+
+	#[test]
+	fn raycast_starting_inside_block() {
+		let mut world = World::empty();
+		world.set_chunk(ChunkCoord::new(1, 0, 0), Chunk::new(2));
+		
+		let inside = Chunk::SIZE_I + 8;
+		let hit = world.raycast(Vec3::new(inside as f32 + 0.5, 5.5, 5.5), Vec3::new(0., -1., 0.2), 8.).expect("should hit");
+		assert_eq!(hit.world_pos, IVec3::new(inside, 5, 5));
+		assert_eq!(hit.prev_pos, hit.world_pos);
+		assert_eq!(hit.face, AxisBasic::Y);
+		assert_eq!(hit.distance, 0.);
+	}
+	#[test]
+	fn light_storage_packing() {
+		let mut light = LightStorage::default();
+		assert_eq!(light.get(123), MAX_LIGHT);
+		light.set(123, 4);
+		light.set(124, 20); // clamped
+		assert_eq!(light.get(123), 4);
+		assert_eq!(light.get(124), MAX_LIGHT);
+		assert_eq!(light.get(125), MAX_LIGHT);
+		light.set(123, MAX_LIGHT);
+		light.optimize();
+		assert_eq!(light, LightStorage::uniform(MAX_LIGHT));
+	}
+
+	#[test]
+	fn light_flood_fill_and_rebreak() {
+		let top = Chunk::SIZE - 1;
+		let idx = |x: usize, y: usize, z: usize| usize::from(LocalPos::from((x, y, z)));
+		let mut chunk = Chunk::empty();
+		assert_eq!(chunk.compute_light(&[None; 6], &[]), [false; 6]); // open sky, stays fully lit
+		
+		// Solid roof with a single hole
+		for x in 0..Chunk::SIZE {
+			for z in 0..Chunk::SIZE {
+				if (x, z) != (5, 5) { chunk.storage_mut().set(idx(x, top, z), block(2)); }
+			}
+		}
+		chunk.compute_light(&[None; 6], &[]);
+		assert_eq!(chunk.get_light(idx(5, 3, 5)), MAX_LIGHT);
+		assert_eq!(chunk.get_light(idx(8, 3, 5)), MAX_LIGHT - 3);
+		assert_eq!(chunk.get_light(idx(25, 3, 25)), 0);
+		assert_eq!(chunk.get_light(idx(0, top, 0)), 0); // solid blocks stay dark
+		
+		// Breaking a roof block lets the light back in
+		chunk.storage_mut().set(idx(25, top, 25), Block::default());
+		let changed = chunk.compute_light(&[None; 6], &[]);
+		assert_eq!(chunk.get_light(idx(25, 3, 25)), MAX_LIGHT);
+		assert!(changed[5]); // the bottom border got brighter
+		
+		// Light coming from a neighbour
+		let mut cave = Chunk::empty();
+		for x in 0..Chunk::SIZE {
+			for z in 0..Chunk::SIZE {
+				cave.storage_mut().set(idx(x, top, z), block(2));
+			}
+		}
+		let lit = Chunk::empty(); // default light is fully lit
+		cave.compute_light(&[Some(&lit), None, None, None, None, None], &[]);
+		assert_eq!(cave.get_light(idx(0, 10, 10)), MAX_LIGHT - 1);
+		assert_eq!(cave.get_light(idx(2, 10, 10)), MAX_LIGHT - 3);
+	}
+
+	#[test]
+	fn light_updates_carry_over_to_the_next_frame() {
+		use crate::block::light::LIGHT_UPDATE_BUDGET;
+		let mut world = World::empty();
+		// Far enough apart that none of them queues another
+		let solid: Vec<ChunkCoord> = (0..LIGHT_UPDATE_BUDGET as i32 + 3).map(|i| ChunkCoord::new(i * 2, 0, 0)).collect();
+		for &coord in &solid {
+			world.set_chunk(coord, Chunk::new(2));
+			world.propagate_light(coord);
+			world.propagate_light(coord); // queued once
+		}
+		assert_eq!(world.light_queue.len(), solid.len());
+		assert_eq!(world.update_light(LIGHT_UPDATE_BUDGET), 3);
+		assert_eq!(world.update_light(LIGHT_UPDATE_BUDGET), 0);
+		assert!(solid.iter().all(|coord| world.get_chunk(coord).unwrap().light() == &LightStorage::uniform(0)));
+
+		// A changed border queues the neighbour behind it instead of dropping it when the budget runs out
+		let idx = |x: usize, y: usize, z: usize| usize::from(LocalPos::from((x, y, z)));
+		let (cave_at, side) = (ChunkCoord::new(0, 4, 0), ChunkCoord::new(-1, 4, 0));
+		let mut cave = Chunk::empty();
+		for x in 0..Chunk::SIZE {
+			for z in 0..Chunk::SIZE {
+				cave.storage_mut().set(idx(x, Chunk::SIZE - 1, z), block(2));
+			}
+		}
+		world.set_chunk(cave_at, cave);
+		let mut dark = Chunk::empty();
+		*dark.light_mut() = LightStorage::uniform(0);
+		world.set_chunk(side, dark);
+		world.propagate_light(side);
+		assert_eq!(world.update_light(1), 1);
+		assert_eq!(world.light_queue.len(), 1);
+		world.update_light(1);
+		assert_eq!(world.get_chunk(&cave_at).unwrap().get_light(idx(0, 10, 10)), MAX_LIGHT - 1);
+		// The cave's darker border sends it back to the side once, which then settles
+		assert_eq!(world.update_light(LIGHT_UPDATE_BUDGET), 0);
+	}
+
+	#[test]
+	fn fill_region_bulk() {
+		init_luts();
+		let stone = block(2);
+		
+		// Whole chunks are set at once, each marked for one remesh and one relight instead of one per block
+		let mut world = World::empty();
+		world.fill_region(IVec3::ZERO, IVec3::splat(2 * Chunk::SIZE_I - 1), stone);
+		
+		assert_eq!(world.chunks.len(), 8);
+		for chunk in world.chunks.values() {
+			assert_eq!(chunk.storage(), &BlockStorage::uniform(stone));
+			assert!(chunk.dirty);
+		}
+		// Each chunk is relit once, the upper ones first
+		assert_eq!(world.light_queue.len(), 8);
+		assert_eq!(world.light_queue.pop().map(|coord| coord.y()), Some(1));
+		world.update_light(usize::MAX);
+		assert!(world.light_queue.is_empty());
+		
+		// Unaligned box with swapped corners, spans negative chunks too
+		let mut world = World::empty();
+		world.fill_region(IVec3::new(40, 3, 10), IVec3::new(-5, 0, -2), stone);
+		let spanned = |lo: i32, hi: i32| (hi.div_euclid(Chunk::SIZE_I) - lo.div_euclid(Chunk::SIZE_I) + 1) as usize;
+		assert_eq!(world.chunks.len(), spanned(-5, 40) * spanned(0, 3) * spanned(-2, 10));
+		assert_eq!(world.get_block(IVec3::new(-5, 0, -2)), stone);
+		assert_eq!(world.get_block(IVec3::new(40, 3, 10)), stone);
+		assert_eq!(world.get_block(IVec3::new(17, 2, 0)), stone);
+		assert!(world.get_block(IVec3::new(41, 3, 10)).is_empty());
+		assert!(world.get_block(IVec3::new(0, 4, 0)).is_empty());
+		assert!(world.get_block(IVec3::new(-6, 0, 0)).is_empty());
+	}
+
+	#[test]
+	fn fill_region_stays_inside_the_border() {
+		use crate::world::border::WorldBorder;
+		use glam::IVec2;
+		init_luts();
+		let stone = block(2);
+		let mut world = World::empty();
+		world.border = Some(WorldBorder::new(IVec2::ZERO, 8));
+
+		// Straddling the border only the inside part is filled and no chunk past it is made
+		world.fill_region(IVec3::new(-20, 0, -3), IVec3::new(3 * Chunk::SIZE_I, 1, 20), stone);
+		assert_eq!(world.get_block(IVec3::new(-8, 0, -3)), stone);
+		assert_eq!(world.get_block(IVec3::new(7, 1, 7)), stone);
+		assert!(world.get_block(IVec3::new(-9, 0, 0)).is_empty());
+		assert!(world.get_block(IVec3::new(8, 0, 0)).is_empty());
+		assert!(world.get_block(IVec3::new(0, 0, 8)).is_empty());
+		assert!(world.chunks.keys().all(|&coord| world.chunk_in_border(coord)));
+
+		// All of it outside, nothing happens
+		let chunks = world.chunks.len();
+		world.fill_region(IVec3::new(100, 0, 100), IVec3::new(110, 5, 110), stone);
+		assert_eq!(world.chunks.len(), chunks);
+	}
 /*
 // world saving is now region related so no single .to_binary() is implemented for the world
 	#[test]
@@ -247,7 +459,50 @@ mod tests {
 		assert_eq!(chunk, restored_chunk);
 	}
 
+	#[test]
+	fn save_chunk_giant_roundtrip() {
+		let mut storage = BlockStorage::uniform(block(1));
+		for i in 0..Chunk::VOLUME {
+			storage.set(i, block((i % 3000) as u16 + 1));
+		}
+		assert_eq!(storage.to_type(), StorageType::Giant);
+
+		let coord = ChunkCoord::new(4, -1, 2);
+		let mut world = World::empty();
+		world.set_chunk(coord, Chunk::from_storage(storage.clone()));
+
+		let bytes = world.save_chunk(coord).unwrap();
+		let restored = Chunk::from_binary(&bytes).unwrap();
+		// Same palette and the exact same 12-bit indices
+		assert_eq!(restored.storage(), &storage);
+	}
+
+	#[test]
+	fn save_chunk_empty_is_tiny() {
+		let mut world = World::empty();
+		world.set_chunk(ChunkCoord::ZERO, Chunk::empty());
+		world.set_chunk(ChunkCoord::new(1, 0, 0), Chunk::empty());
+
+		let bytes = world.save_chunk(ChunkCoord::ZERO).unwrap();
+		assert_eq!(bytes, world.save_chunk(ChunkCoord::new(1, 0, 0)).unwrap());
+		assert_eq!(bytes.len(), 1 + Block::BINARY_SIZE + 1); // storage type + block + empty entities
+
+		assert!(world.save_chunk(ChunkCoord::new(9, 9, 9)).is_err());
+	}
+
 
+	/// `Chunk::set_block` looks the blocks up by name (is_storage), so the texture map and item lut have to exist
+	fn init_luts() {
+		use crate::{render::texture::TEXTURE_MAP, item::items::init_item_lut, fs::rs::find_png_resources};
+		static INIT: std::sync::Once = std::sync::Once::new();
+		INIT.call_once(|| {
+			let names = find_png_resources("block").iter()
+				.map(|path| path.rsplit('/').next().unwrap_or("").split('.').next().unwrap_or("").to_string())
+				.collect();
+			let _ = TEXTURE_MAP.set(names);
+			init_item_lut();
+		});
+	}
 
 	fn block(id: u16) -> Block {
 		Block::new(Material(id))
@@ -270,6 +525,90 @@ mod tests {
 
 
 
+	#[test]
+	fn chunk_size_is_consistent() {
+		#[cfg(feature = "small_chunks")]
+		assert_eq!(Chunk::SIZE, 16);
+		#[cfg(not(feature = "small_chunks"))]
+		assert_eq!(Chunk::SIZE, 32);
+		
+		assert_eq!(Chunk::SIZE, 1 << Chunk::BITS);
+		assert_eq!(usize::from(LocalPos::CORNER), Chunk::VOLUME - 1);
+		let pos = LocalPos::new(1, 2, 3);
+		assert_eq!((pos.x(), pos.y(), pos.z()), (1, 2, 3));
+	}
+
+	#[test]
+	fn storage_variants_roundtrip_any_size() {
+		let mut compact = BlockStorage::uniform(block(1));
+		compact.set(Chunk::VOLUME - 1, block(2));
+		let mut sparse = BlockStorage::uniform(block(1));
+		let mut giant = BlockStorage::uniform(block(1));
+		for i in 0..Chunk::VOLUME {
+			sparse.set(i, block((i % 100) as u16 + 1));
+			giant.set(i, block((i % 1000) as u16 + 1));
+		}
+		let mut blocks = Box::new([Block::default(); Chunk::VOLUME]);
+		for (i, b) in blocks.iter_mut().enumerate() { *b = block((i % 7) as u16 + 1); }
+		let zigzag = BlockStorage::Zigzag { blocks };
+		
+		assert_eq!(compact.to_type(), StorageType::Compact);
+		assert_eq!(sparse.to_type(), StorageType::Sparse);
+		assert_eq!(giant.to_type(), StorageType::Giant);
+		
+		for storage in [BlockStorage::uniform(block(3)), compact, sparse, giant, zigzag] {
+			let restored = BlockStorage::from_binary(&storage.to_binary()).unwrap();
+			assert_eq!(restored, storage);
+			if let Some(rle) = storage.to_rle() {
+				let back = rle.from_rle().unwrap();
+				for i in 0..Chunk::VOLUME {
+					assert_eq!(back.get(i), storage.get(i));
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn iter_solid_matches_naive_scan() {
+		let naive = |chunk: &Chunk| (0..Chunk::VOLUME).filter(|&i| !chunk.get_block(i).is_empty()).count();
+		
+		// Mostly air with a bit of ground, in every storage variant
+		let mut compact = BlockStorage::uniform(Block::default());
+		let mut sparse = BlockStorage::uniform(Block::default());
+		let mut giant = BlockStorage::uniform(Block::default());
+		for i in 0..Chunk::VOLUME / 8 {
+			compact.set(i, block(2 + (i % 3) as u16));
+			sparse.set(i, block(2 + (i % 40) as u16));
+			giant.set(i, block(2 + (i % 400) as u16));
+		}
+		let mut blocks = Box::new([Block::default(); Chunk::VOLUME]);
+		for i in (0..Chunk::VOLUME).step_by(7) { blocks[i] = block(2); }
+		let rle = compact.to_rle().expect("mostly air should compress");
+		
+		let storages = [
+			BlockStorage::empty(),
+			BlockStorage::uniform(block(2)),
+			compact, sparse, giant,
+			BlockStorage::Zigzag { blocks },
+			rle,
+		];
+		for storage in storages {
+			let chunk = Chunk::from_storage(storage);
+			
+			let start = std::time::Instant::now();
+			let expected = naive(&chunk);
+			let naive_time = start.elapsed();
+			let start = std::time::Instant::now();
+			let solid = chunk.iter_solid().count();
+			let iter_time = start.elapsed();
+			println!("{:?}: {} solid, naive {:?} vs iter_solid {:?}", chunk.storage().to_type(), solid, naive_time, iter_time);
+			
+			assert_eq!(solid, expected);
+			assert!(chunk.iter_solid().all(|(pos, b)| chunk.get_block(usize::from(pos)) == b && !b.is_empty()));
+			assert_eq!(chunk.is_empty(), expected == 0);
+		}
+	}
+
 	#[test]
 	fn empty_storage() {
 		let storage = BlockStorage::empty();
@@ -374,6 +713,57 @@ mod tests {
 		assert_eq!(storage.get(2048), block(6000));
 	}
 
+	#[test]
+	fn optimize_zigzag_to_giant() {
+		let mut blocks = Box::new([Block::default(); Chunk::VOLUME]);
+		for (i, b) in blocks.iter_mut().enumerate() {
+			*b = block((i % 300) as u16 + 1);
+		}
+		let original = blocks.clone();
+		let mut storage = BlockStorage::Zigzag { blocks };
+		
+		storage.optimize();
+		assert_eq!(storage.to_type(), StorageType::Giant);
+		assert_eq!(storage.palette().len(), 300);
+		for i in 0..Chunk::VOLUME {
+			assert_eq!(storage.get(i), original[i]);
+		}
+	}
+
+	#[test]
+	fn optimize_counter_is_per_chunk() {
+		init_luts();
+		let mut a = Chunk::empty();
+		let mut b = Chunk::empty();
+		// The first 20 edits push the chunk to Sparse, the rest make it fit in Compact again
+		let edit = |chunk: &mut Chunk, i: usize| {
+			let bl = if i < 20 { block(2 + i as u16) } else { block(2) };
+			chunk.set_block(i % 20, bl);
+		};
+		
+		// b starts 50 edits later, interleaved with a
+		for i in 0..50 { edit(&mut a, i); }
+		for i in 50..Chunk::OPTIMIZE_EVERY as usize - 1 {
+			edit(&mut a, i);
+			edit(&mut b, i - 50);
+		}
+		assert_eq!(a.edits_since_optimize(), Chunk::OPTIMIZE_EVERY - 1);
+		assert_eq!(b.edits_since_optimize(), Chunk::OPTIMIZE_EVERY - 51);
+		assert_eq!(a.storage().to_type(), StorageType::Sparse);
+		
+		// a's 100th own edit optimizes a only
+		edit(&mut a, Chunk::OPTIMIZE_EVERY as usize - 1);
+		assert_eq!(a.edits_since_optimize(), 0);
+		assert_eq!(a.storage().to_type(), StorageType::Compact);
+		assert_eq!(b.storage().to_type(), StorageType::Sparse);
+		
+		for i in 49..Chunk::OPTIMIZE_EVERY as usize - 1 { edit(&mut b, i); }
+		assert_eq!(b.storage().to_type(), StorageType::Sparse);
+		edit(&mut b, Chunk::OPTIMIZE_EVERY as usize - 1);
+		assert_eq!(b.edits_since_optimize(), 0);
+		assert_eq!(b.storage().to_type(), StorageType::Compact);
+	}
+
 	#[test]
 	fn optimize_uniform() {
 		let mut storage = BlockStorage::uniform(block(1));
@@ -570,4 +960,1868 @@ mod tests {
 			assert_eq!(storage.get(i), expected[i]);
 		}
 	}
+
+	#[test]
+	fn chunk_frustum_culling() {
+		use crate::{render::world::chunk_in_view, player::Frustum};
+		use glam::Mat4;
+		let s = Chunk::SIZE_F;
+		let (min, max) = ChunkCoord::new(1, -2, 3).world_aabb();
+		assert_eq!(min, Vec3::new(s, -2. * s, 3. * s));
+		assert_eq!(max, min + Vec3::splat(s));
+
+		// Camera at the origin looking down -Z with a 90° square view, so the sides are the x = ±z planes
+		let view_proj = Mat4::perspective_rh(std::f32::consts::FRAC_PI_2, 1., 0.1, 1000.) * Mat4::look_to_rh(Vec3::ZERO, Vec3::NEG_Z, Vec3::Y);
+		let frustum = Frustum::from_view_proj_matrix(view_proj);
+		let far = 1000. * 1000.;
+		let visible = |x, y, z| chunk_in_view(&ChunkCoord::new(x, y, z), &frustum, Vec3::ZERO, far, true);
+
+		assert!(visible(0, 0, -2)); // straight ahead
+		assert!(visible(-1, -1, -1)); // the camera sits on its corner
+		assert!(visible(-2, 0, -2)); // cut by the left plane
+		assert!(!visible(0, 0, 1)); // behind
+		assert!(!visible(-3, 0, -1)); // off to the side
+
+		// Turned off only the distance counts
+		assert!(chunk_in_view(&ChunkCoord::new(0, 0, 1), &frustum, Vec3::ZERO, far, false));
+		assert!(!chunk_in_view(&ChunkCoord::new(0, 0, -20), &frustum, Vec3::ZERO, s * s, false));
+	}
+
+	#[test]
+	fn lod_mesh_cells() {
+		use crate::render::{meshing::{ChunkMeshBuilder, InstanceRaw}, world::chunk_lod};
+		let set = |chunk: &mut Chunk, x: u8, y: u8, z: u8, mat: u16| chunk.storage_mut().set(LocalPos::new(x, y, z).into(), Block::new(Material(mat)));
+		let lod_mesh = |chunk: &Chunk, neighbors: [Option<&Chunk>; 6]| {
+			let mut builder = ChunkMeshBuilder::new();
+			builder.push_lod(chunk, &neighbors, 1);
+			builder.instances
+		};
+		let face = |i: &InstanceRaw| (i.packed_data >> 15) & 0b111;
+		let material = |i: &InstanceRaw| i.packed_data >> 19;
+		let pos = |i: &InstanceRaw| (i.packed_data & 31, (i.packed_data >> 5) & 31, (i.packed_data >> 10) & 31);
+
+		// One block in the corner fills the whole 2x2x2 cell, the 3 border faces are culled by the missing neighbors
+		let mut chunk = Chunk::empty();
+		set(&mut chunk, 0, 0, 0, 2);
+		let mut mesh = lod_mesh(&chunk, [None; 6]);
+		mesh.sort_by_key(|i| face(i));
+		assert_eq!(mesh.iter().map(|i| face(i)).collect::<Vec<_>>(), vec![1, 3, 4]);
+		assert!(mesh.iter().all(|i| i.extent & 0x3FF == 1 | 1 << 5)); // 2x2 quads
+		// Positive faces sit on the far block of the cell so they end up 2 blocks out
+		assert_eq!(pos(&mesh[0]), (1, 0, 0));
+		assert_eq!(pos(&mesh[1]), (0, 0, 1));
+		assert_eq!(pos(&mesh[2]), (0, 1, 0));
+
+		// The most common material wins
+		set(&mut chunk, 1, 0, 0, 3);
+		set(&mut chunk, 1, 1, 0, 3);
+		assert!(lod_mesh(&chunk, [None; 6]).iter().all(|i| material(i) == 3));
+
+		// Touching cells hide each other
+		set(&mut chunk, 2, 0, 0, 2);
+		assert_eq!(lod_mesh(&chunk, [None; 6]).len(), 2 + 3);
+
+		// The -X border face shows next to an air neighbor, or a neighbor with any gap along the cell face
+		let air = Chunk::empty();
+		let mut solid = Chunk::new(2);
+		let with_left = |left: &Chunk| lod_mesh(&chunk, [Some(left), None, None, None, None, None]).iter().filter(|i| face(i) == 0).count();
+		assert_eq!(with_left(&air), 1);
+		assert_eq!(with_left(&solid), 0);
+		set(&mut solid, Chunk::SIZE as u8 - 1, 1, 1, 1);
+		assert_eq!(with_left(&solid), 1);
+
+		// Full detail near the player, coarser further out, never past the last level
+		let player = ChunkCoord::new(0, 0, 0);
+		assert_eq!(chunk_lod(ChunkCoord::new(3, -3, 0), player, 4), 0);
+		assert_eq!(chunk_lod(ChunkCoord::new(0, 0, -4), player, 4), 1);
+		assert_eq!(chunk_lod(ChunkCoord::new(9, 0, 0), player, 4), 2);
+		assert_eq!(chunk_lod(ChunkCoord::new(90, 0, 0), player, 4), 2);
+		assert_eq!(chunk_lod(ChunkCoord::new(90, 0, 0), player, 0), 0);
+	}
+
+	#[test]
+	fn chunk_occlusion() {
+		use crate::render::world::is_occluded;
+		let center = ChunkCoord::new(0, 0, 0);
+		let mut world = World::empty();
+		world.set_chunk(center, Chunk::new(2));
+		let adjacent = center.get_adjacent();
+		for &coord in &adjacent[..5] {
+			world.set_chunk(coord, Chunk::new(2));
+		}
+		// The last neighbor is missing, the edge of the world is never occluded
+		assert!(!is_occluded(center, &world));
+
+		world.set_chunk(adjacent[5], Chunk::new(2));
+		assert!(is_occluded(center, &world));
+		world.get_chunk_mut(&center).unwrap().occluded = Some(true);
+
+		// Digging into a neighbor drops the cache and the chunk shows again
+		let neighbor = adjacent[1];
+		world.get_chunk_mut(&neighbor).unwrap().storage_mut().set(LocalPos::new(0, 5, 5).into(), Block::default());
+		world.clear_adjacent_occlusion(neighbor);
+		assert_eq!(world.get_chunk(&center).unwrap().occluded, None);
+		assert!(!is_occluded(center, &world));
+
+		// Remeshing a chunk clears its neighbors too
+		world.get_chunk_mut(&center).unwrap().occluded = Some(false);
+		world.set_adjacent_un_final(neighbor);
+		assert_eq!(world.get_chunk(&center).unwrap().occluded, None);
+
+		// Unloading a neighbor clears it as well, the chunk is at the edge of the loaded area now
+		let mut world = World::empty();
+		let (kept, dropped) = (ChunkCoord::new(1, 0, 0), ChunkCoord::new(0, 0, 0));
+		world.set_chunk(kept, Chunk::new(2));
+		world.set_chunk(dropped, Chunk::new(2));
+		world.get_chunk_mut(&kept).unwrap().occluded = Some(true);
+		world.update_loaded_chunks(Vec3::new(Chunk::SIZE as f32 + 1., 1., 1.), 0.);
+		assert!(world.get_chunk(&dropped).is_none());
+		assert_eq!(world.get_chunk(&kept).unwrap().occluded, None);
+	}
+
+	#[test]
+	fn greedy_mesh_merges_and_matches_naive() {
+		use crate::render::{meshing::{InstanceRaw, FACE_AXES}, world::NeighboringChunks};
+		// Every quad split back into the single block faces it covers, as (pos, face, material, light)
+		let unit_faces = |instances: &[InstanceRaw]| {
+			let mut faces = Vec::new();
+			for i in instances {
+				let pos = IVec3::new((i.packed_data & 31) as i32, ((i.packed_data >> 5) & 31) as i32, ((i.packed_data >> 10) & 31) as i32);
+				let face = ((i.packed_data >> 15) & 0b111) as usize;
+				let (_, a_axis, b_axis) = FACE_AXES[face];
+				for b in 0..=((i.extent >> 5) & 31) as i32 {
+					for a in 0..=(i.extent & 31) as i32 {
+						let mut p = pos;
+						p[a_axis] += a;
+						p[b_axis] += b;
+						faces.push((p.to_array(), face, i.packed_data >> 19, (i.extent >> 10) & 0xF));
+					}
+				}
+			}
+			faces.sort();
+			faces
+		};
+		let meshes = |chunk: &Chunk, neighbors: [Option<&Chunk>; 6]| {
+			let neighbors = NeighboringChunks::new(neighbors);
+			(chunk.build_instances(&neighbors, 0, true), chunk.build_instances(&neighbors, 0, false))
+		};
+
+		// A full chunk between air neighbors is one quad per side
+		let air = Chunk::empty();
+		let full = Chunk::new(2);
+		let (greedy, naive) = meshes(&full, [Some(&air); 6]);
+		assert_eq!(greedy.len(), 6);
+		assert!(greedy.iter().all(|i| i.extent & 0x3FF == (Chunk::SIZE as u32 - 1) * (1 | 1 << 5)));
+		assert_eq!(naive.len(), 6 * Chunk::SIZE * Chunk::SIZE);
+		assert_eq!(unit_faces(&greedy), unit_faces(&naive));
+		// Missing neighbors cull the border like the naive path does
+		assert!(meshes(&full, [None; 6]).0.is_empty());
+
+		// A 3x1x2 slab merges into a quad per side instead of 22 faces
+		let mut chunk = Chunk::empty();
+		for x in 4..7 {
+			for z in 4..6 {
+				chunk.storage_mut().set(LocalPos::new(x, 4, z).into(), block(2));
+			}
+		}
+		let (greedy, naive) = meshes(&chunk, [None; 6]);
+		assert_eq!(greedy.len(), 6);
+		assert_eq!(naive.len(), 22);
+		assert_eq!(unit_faces(&greedy), unit_faces(&naive));
+
+		// Another material splits the quads it is part of, the faces stay the same
+		chunk.storage_mut().set(LocalPos::new(5, 4, 4).into(), block(3));
+		let (greedy, naive) = meshes(&chunk, [None; 6]);
+		assert!(greedy.len() > 6 && greedy.len() < naive.len());
+		assert_eq!(unit_faces(&greedy), unit_faces(&naive));
+	}
+
+	#[test]
+	fn texture_mip_chain() {
+		use crate::render::texture::{mip_level_count, downsample_rgba};
+		assert_eq!(mip_level_count(16, 16), 5);
+		assert_eq!(mip_level_count(32, 8), 6);
+		assert_eq!(mip_level_count(1, 1), 1);
+
+		// Half opaque red, half fully transparent black: the color stays red instead of going dark
+		let red = [255, 0, 0, 255];
+		let clear = [0, 0, 0, 0];
+		let image: Vec<u8> = [red, clear, red, clear].concat();
+		let (mip, w, h) = downsample_rgba(&image, 2, 2);
+		assert_eq!((w, h), (1, 1));
+		assert_eq!(mip, vec![255, 0, 0, 128]);
+
+		// Averaging happens in linear space, black and white give the sRGB middle grey
+		let image: Vec<u8> = [[0, 0, 0, 255], [255, 255, 255, 255]].concat();
+		let (mip, w, h) = downsample_rgba(&image, 2, 1);
+		assert_eq!((w, h), (1, 1));
+		assert!((mip[0] as i32 - 188).abs() <= 1, "{}", mip[0]);
+
+		// Odd sizes still shrink down to 1x1
+		let mut image = vec![200u8; 5 * 3 * 4];
+		let (mut w, mut h) = (5, 3);
+		for _ in 1..mip_level_count(5, 3) {
+			(image, w, h) = downsample_rgba(&image, w, h);
+			assert_eq!(image.len(), (w * h * 4) as usize);
+			assert!(image.iter().all(|&v| v == 200));
+		}
+		assert_eq!((w, h), (1, 1));
+	}
+
+	#[test]
+	fn anisotropy_clamping() {
+		use crate::render::texture::clamp_anisotropy;
+		assert_eq!(clamp_anisotropy(0), 1);
+		assert_eq!(clamp_anisotropy(1), 1);
+		assert_eq!(clamp_anisotropy(3), 2);
+		assert_eq!(clamp_anisotropy(8), 8);
+		assert_eq!(clamp_anisotropy(16), 16);
+		assert_eq!(clamp_anisotropy(200), 16);
+	}
+
+	#[test]
+	fn chunk_shader_size_is_patched() {
+		use crate::{render::pipeline::set_shader_const, block::main::Chunk, get_string};
+		let patched = set_shader_const(&get_string!("chunk_shader.wgsl"), "CHUNK_SIZE_I", Chunk::SIZE as i32).unwrap();
+		assert!(patched.contains(&format!("const CHUNK_SIZE_I: i32 = {}i;", Chunk::SIZE)));
+
+		// Whatever value the file has gets replaced, and a missing constant is an error instead of a silent no-op
+		assert_eq!(set_shader_const("const N: i32 = 16i;\nfn f() {}", "N", 64).as_deref(), Some("const N: i32 = 64i;\nfn f() {}"));
+		assert_eq!(set_shader_const("const M: i32 = 16i;", "N", 64), None);
+	}
+
+	#[test]
+	fn block_outline_edges() {
+		use crate::render::debug::cube_outline;
+		let min = Vec3::new(2., -1., 5.);
+		let lines = cube_outline(min, 1.);
+
+		// Every edge is a unit step along one axis and stays on the cube
+		let mut ends = Vec::new();
+		for line in &lines {
+			let (start, dir) = (Vec3::from(line.start), Vec3::from(line.direction));
+			assert_eq!(dir.length(), 1.);
+			assert_eq!(dir.abs().max_element(), 1.);
+			for p in [start, start + dir] {
+				assert!((p - min).cmpge(Vec3::ZERO).all() && (p - min).cmple(Vec3::ONE).all());
+				ends.push((p - min).as_ivec3());
+			}
+		}
+		// No edge is repeated and each of the 8 corners joins exactly 3 edges
+		for (i, a) in lines.iter().enumerate() {
+			assert!(lines[i + 1..].iter().all(|b| a.start != b.start || a.direction != b.direction));
+		}
+		for corner in 0..8 {
+			let corner = IVec3::new(corner & 1, (corner >> 1) & 1, corner >> 2);
+			assert_eq!(ends.iter().filter(|&&e| e == corner).count(), 3);
+		}
+	}
+
+	#[test]
+	fn block_texture_animation() {
+		use crate::render::texture::{find_material_animations, current_frame, BlockAnimations};
+		let names: Vec<String> = ["stone", "water_frame2", "water", "lava", "water_frame1", "ghost_frame1", "water_framex"]
+			.iter().map(|s| s.to_string()).collect();
+
+		// Frames follow the base texture in suffix order, orphans and bad suffixes are skipped
+		let animations = find_material_animations(&names);
+		assert_eq!(animations.len(), 1);
+		assert_eq!(animations[&Material(2)], vec![2, 4, 1]);
+
+		assert_eq!(current_frame(3, 2., 0.4), 0);
+		assert_eq!(current_frame(3, 2., 0.6), 1);
+		assert_eq!(current_frame(3, 2., 1.6), 0);
+		assert_eq!(current_frame(3, 0., 5.), 0);
+
+		// Only frame changes are reported, each animation at its own rate
+		let mut anim = BlockAnimations::new(animations, 2.);
+		anim.material_animation.insert(Material(3), vec![3, 5]);
+		anim.set_frame_rate(Material(3), 1.);
+		let mut first = anim.advance(0.1);
+		first.sort_unstable_by_key(|(m, _)| m.inner());
+		assert_eq!(first, vec![(Material(2), 2), (Material(3), 3)]);
+		assert!(anim.advance(0.1).is_empty());
+		assert_eq!(anim.advance(0.4), vec![(Material(2), 4)]);
+		assert_eq!(anim.frame_rate(Material(3)), 1.);
+		let mut later = anim.advance(0.5);
+		later.sort_unstable_by_key(|(m, _)| m.inner());
+		assert_eq!(later, vec![(Material(2), 1), (Material(3), 5)]);
+
+		// Nothing animated means nothing to do
+		assert!(BlockAnimations::new(Default::default(), 8.).advance(1.).is_empty());
+	}
+
+	#[test]
+	fn screenshot_rows() {
+		use crate::render::screenshot::{padded_bytes_per_row, unpad_rows, is_bgra};
+		assert_eq!(padded_bytes_per_row(1), 256);
+		assert_eq!(padded_bytes_per_row(64), 256);
+		assert_eq!(padded_bytes_per_row(65), 512);
+		assert_eq!(padded_bytes_per_row(1920), 7680);
+
+		// 2x2 image, rows padded to 256 bytes with garbage after the texels
+		let padded = padded_bytes_per_row(2) as usize;
+		let mut data = vec![0xAB; padded * 2];
+		data[..8].copy_from_slice(&[1, 2, 3, 4, 5, 6, 7, 8]);
+		data[padded..padded + 8].copy_from_slice(&[9, 10, 11, 12, 13, 14, 15, 16]);
+		assert_eq!(unpad_rows(&data, 2, 2, padded as u32, false), (1..=16).collect::<Vec<u8>>());
+		assert_eq!(unpad_rows(&data, 2, 2, padded as u32, true)[..8], [3, 2, 1, 4, 7, 6, 5, 8]);
+
+		assert_eq!(is_bgra(wgpu::TextureFormat::Bgra8UnormSrgb), Some(true));
+		assert_eq!(is_bgra(wgpu::TextureFormat::Rgba8Unorm), Some(false));
+		assert_eq!(is_bgra(wgpu::TextureFormat::Rgba16Float), None);
+	}
+
+	#[test]
+	fn fog_factor() {
+		use crate::game::player::{Fog, FogMode};
+		let mut fog = Fog { mode: FogMode::Linear, start: 50., end: 100., color: Vec3::ONE };
+		assert_eq!(fog.factor(10.), 0.);
+		assert_eq!(fog.factor(75.), 0.5);
+		assert_eq!(fog.factor(200.), 1.);
+
+		// Exponential starts slower but is (nearly) solid at the end too
+		fog.mode = FogMode::Exponential;
+		assert_eq!(fog.factor(50.), 0.);
+		assert!(fog.factor(60.) < 0.2);
+		assert!(fog.factor(100.) > 0.99);
+
+		fog.mode = FogMode::Off;
+		assert_eq!(fog.factor(1000.), 0.);
+
+		// The default fog is gone before the render distance ends, and moves along with it
+		use crate::block::extra::render_distance;
+		let mut fog = Fog::default();
+		assert_eq!(fog.factor(render_distance()), 1.);
+		fog.fit_view(render_distance() * 2.);
+		assert_eq!(fog.factor(render_distance()), 0.);
+		assert_eq!(fog.factor(render_distance() * 2.), 1.);
+	}
+
+	#[test]
+	fn skybox_horizon_color() {
+		use crate::render::skybox::horizon_color;
+		// 2x3 image, only the middle row counts
+		let image: Vec<u8> = [
+			[0, 0, 0, 255], [0, 0, 0, 255],
+			[255, 0, 0, 255], [255, 255, 0, 255],
+			[0, 0, 0, 255], [0, 0, 0, 255],
+		].concat();
+		assert_eq!(horizon_color(&image, 2, 3), Vec3::new(1., 0.5, 0.));
+		assert_eq!(horizon_color(&[], 0, 0), Vec3::ZERO);
+	}
+
+	#[test]
+	fn time_of_day_cycle() {
+		use crate::game::state::{TimeOfDay, NIGHT_AMBIENT};
+		let mut time = TimeOfDay::new(0.5);
+		assert_eq!(time.daylight(), 1.);
+		assert_eq!(time.ambient(), 1.);
+		time.set(0.);
+		assert_eq!(time.daylight(), 0.);
+		assert_eq!(time.ambient(), NIGHT_AMBIENT);
+
+		// Wraps around after a full day
+		time.day_length = 100.;
+		time.advance(125.);
+		assert!((time.time() - 0.25).abs() < 1e-5);
+		time.set(-0.25);
+		assert!((time.time() - 0.75).abs() < 1e-5);
+
+		// Frozen clocks don't move but can still be set
+		time.freeze(true);
+		time.advance(50.);
+		assert!((time.time() - 0.75).abs() < 1e-5);
+		time.set(0.1);
+		assert_eq!(time.time(), 0.1);
+
+		// No jumps across sunrise, the light only ever goes up in the morning
+		let mut last = TimeOfDay::new(0.).daylight();
+		for step in 1..=500 {
+			let light = TimeOfDay::new(step as f32 / 1000.).daylight();
+			assert!(light >= last && light - last < 0.02, "step {}: {} -> {}", step, last, light);
+			last = light;
+		}
+	}
+
+	#[test]
+	fn msaa_sample_fallback() {
+		use crate::render::texture::nearest_sample_count;
+		let supported = [1, 4];
+		assert_eq!(nearest_sample_count(4, &supported), 4);
+		assert_eq!(nearest_sample_count(1, &supported), 1);
+		assert_eq!(nearest_sample_count(8, &supported), 4);
+		assert_eq!(nearest_sample_count(16, &supported), 4);
+		// Ties go to the cheaper count
+		assert_eq!(nearest_sample_count(2, &[1, 2, 4]), 2);
+		assert_eq!(nearest_sample_count(3, &[2, 4]), 2);
+		assert_eq!(nearest_sample_count(0, &supported), 1);
+		assert_eq!(nearest_sample_count(4, &[]), 1);
+	}
+
+	#[test]
+	fn geometry_pool_buckets() {
+		use crate::render::meshing::{pool_bucket, pool_release_bucket, POOL_MIN_CAPACITY};
+		assert_eq!(pool_bucket(0), POOL_MIN_CAPACITY);
+		assert_eq!(pool_bucket(POOL_MIN_CAPACITY), POOL_MIN_CAPACITY);
+		assert_eq!(pool_bucket(POOL_MIN_CAPACITY + 1), POOL_MIN_CAPACITY * 2);
+		assert_eq!(pool_bucket(1000), 1024);
+
+		// A released buffer only serves buckets it fully covers
+		assert_eq!(pool_release_bucket(1024), Some(1024));
+		assert_eq!(pool_release_bucket(1000), Some(512));
+		assert_eq!(pool_release_bucket(POOL_MIN_CAPACITY - 1), None);
+		for instances in [0, 1, 63, 64, 65, 500, 4096, 5000] {
+			let bucket = pool_bucket(instances);
+			assert!(bucket >= instances);
+			assert_eq!(pool_release_bucket(bucket), Some(bucket));
+		}
+	}
+
+	#[test]
+	fn mesh_job_snapshot() {
+		use crate::render::world::NeighboringChunks;
+		use crate::world::threading::MeshJob;
+		let mut chunk = Chunk::empty();
+		chunk.storage_mut().set(LocalPos::new(0, 0, 0).into(), block(2));
+		let mut left = Chunk::new(2);
+		let neighbors = NeighboringChunks::new([Some(&left), None, None, None, None, None]);
+		let expected = chunk.build_instances(&neighbors, 1, false);
+		let job = MeshJob::new(ChunkCoord::new(1, 2, 3), 7, &chunk, &neighbors, 1, false);
+
+		// Edits after queueing don't reach the worker
+		chunk.storage_mut().set(LocalPos::new(4, 0, 0).into(), block(2));
+		left.storage_mut().set(LocalPos::new(Chunk::SIZE as u8 - 1, 0, 0).into(), block(1));
+
+		let result = job.build();
+		assert_eq!(result.coord, ChunkCoord::new(1, 2, 3));
+		assert_eq!(result.id, 7);
+		assert_eq!(result.instances.iter().map(|i| i.packed_data).collect::<Vec<_>>(), expected.iter().map(|i| i.packed_data).collect::<Vec<_>>());
+	}
+
+	#[test]
+	fn look_direction_priority() {
+		use crate::world::threading::PriorityChunk;
+		use std::collections::BinaryHeap;
+		let center = ChunkCoord::new(0, 0, 0);
+		let look = Vec3::NEG_Z;
+		let at = |x, y, z| PriorityChunk::new_with_dir(ChunkCoord::new(x, y, z), center, look);
+
+		// Same distance, the one in view wins
+		assert!(at(0, 0, -3) > at(0, 0, 3));
+		assert!(at(0, 0, -3) > at(3, 0, 0));
+		// But a close chunk behind still beats a far one ahead
+		assert!(at(0, 0, 2) > at(0, 0, -5));
+		// No direction is plain distance
+		assert_eq!(PriorityChunk::new(ChunkCoord::new(0, 0, 3), center).priority(), PriorityChunk::new(ChunkCoord::new(0, 0, -3), center).priority());
+		// Degenerate directions don't break the ordering
+		let nan = PriorityChunk::new_with_dir(ChunkCoord::new(1, 0, 0), center, Vec3::NAN);
+		assert_eq!(nan.cmp(&nan), std::cmp::Ordering::Equal);
+
+		// Everything comes out, the in view half first
+		let mut heap = BinaryHeap::new();
+		for x in -3..=3 { for z in -3..=3 { heap.push(at(x, 0, z)); } }
+		let order: Vec<ChunkCoord> = std::iter::from_fn(|| heap.pop()).map(|c| c.coord()).collect();
+		assert_eq!(order.len(), 49);
+		assert_eq!(order[0], center);
+		let first_behind = order.iter().position(|c| c.unpack().2 > 0).unwrap();
+		assert!(order[1..first_behind].iter().all(|c| c.unpack().2 <= 0));
+		assert!(first_behind > 3);
+	}
+
+	#[test]
+	fn generation_workers_stop() {
+		use crate::world::threading::PriorityChunk;
+		use std::sync::atomic::Ordering;
+		let mut world = World::empty();
+		world.stop_generation(); // nothing running yet
+
+		for _ in 0..2 {
+			world.start_generation_threads(2);
+			assert_eq!(world.generation_workers.len(), 2);
+			world.stop_generation();
+			world.stop_generation();
+			assert!(world.generation_workers.is_empty());
+			assert_eq!(world.active_workers.load(Ordering::Relaxed), 0);
+		}
+
+		// Queued chunks are forgotten so they load again after a restart
+		let coord = ChunkCoord::new(40, 0, 40);
+		world.generate_chunk(PriorityChunk::new(coord, coord));
+		assert!(world.loaded_chunks.contains(&coord));
+		world.stop_generation();
+		assert!(!world.loaded_chunks.contains(&coord));
+		assert!(world.chunk_generation_queue.lock().unwrap().is_empty());
+	}
+
+	#[test]
+	fn world_border_bounds() {
+		use crate::world::border::WorldBorder;
+		use crate::render::debug::border_grid;
+		use glam::IVec2;
+		let size = Chunk::SIZE_I;
+		// Off center and not chunk aligned
+		let border = WorldBorder::new(IVec2::new(-size - 3, 5), size);
+		assert_eq!(border.min(), IVec2::new(-2 * size - 3, 5 - size));
+		assert!(border.contains(IVec3::new(-size - 3, 100, 5)));
+		assert!(border.contains(IVec3::new(-2 * size - 3, 0, 4 - size + 1)));
+		assert!(!border.contains(IVec3::new(-3, 0, 5))); // max is outside
+		assert!(!border.contains(IVec3::new(-2 * size - 4, 0, 5)));
+
+		// Chunks straddling the edge still load, the ones past it don't
+		assert!(border.overlaps_chunk(ChunkCoord::new(-3, 7, 0)));
+		assert!(!border.contains_chunk(ChunkCoord::new(-3, 7, 0)));
+		assert!(border.contains_chunk(ChunkCoord::new(-2, 0, 0)));
+		assert!(!border.overlaps_chunk(ChunkCoord::new(0, 0, 0)));
+		assert!(!border.overlaps_chunk(ChunkCoord::new(-4, 0, 0)));
+
+		// Only the in-bounds columns of a straddling chunk survive
+		let coord = ChunkCoord::new(-3, 0, 0);
+		let mut chunk = Chunk::new(2);
+		border.crop_chunk(coord, &mut chunk);
+		let origin = IVec3::new(-3 * size, 0, 0);
+		let first_inside = (border.min().x - origin.x) as u8;
+		for x in [0, first_inside - 1, first_inside, Chunk::SIZE as u8 - 1] {
+			let inside = border.contains(origin + IVec3::new(x as i32, 0, 0));
+			assert_eq!(chunk.get_block(LocalPos::new(x, 3, 0).into()).is_empty(), !inside, "x {}", x);
+		}
+
+		// Movement stops at the wall
+		let mut world = World::empty();
+		world.set_border(8);
+		assert_eq!(world.clamp_movement(Vec3::new(7., 0., 0.), Vec3::new(5., 1., 0.), 0.5), Vec3::new(0.5, 1., 0.));
+		assert!(!world.in_border(IVec3::new(8, 0, 0)));
+		world.set_block(IVec3::new(20, 0, 0), block(2));
+		assert!(world.get_chunk(&ChunkCoord::new(0, 0, 0)).is_none());
+		world.remove_border();
+		assert!(world.in_border(IVec3::new(1000, 0, 0)));
+		assert_eq!(world.clamp_movement(Vec3::ZERO, Vec3::X * 100., 0.5), Vec3::X * 100.);
+
+		// The grid only shows up close to a wall
+		let border = WorldBorder::new(IVec2::ZERO, 100);
+		assert!(border_grid(&border, Vec3::ZERO).is_empty());
+		let lines = border_grid(&border, Vec3::new(95., 10., 0.));
+		assert!(!lines.is_empty());
+		assert!(lines.iter().all(|l| l.start[0] == 100.));
+	}
+
+	#[test]
+	fn structure_placement() {
+		use crate::world::structure::StructurePlacer;
+		init_luts();
+		let placer = StructurePlacer::new(42);
+
+		// Same seed, same cells
+		let again = StructurePlacer::new(42);
+		let other = StructurePlacer::new(43);
+		let spots = |p: &StructurePlacer| (-20..20).flat_map(|x| (-20..20).map(move |z| (x, z))).map(|(x, z)| p.cell_spot(x, z)).collect::<Vec<_>>();
+		assert_eq!(spots(&placer), spots(&again));
+		assert_ne!(spots(&placer), spots(&other));
+		assert!(spots(&placer).iter().any(Option::is_some));
+
+		// A tree on the chunk border ends up whole across both chunks
+		let origin = IVec3::new(Chunk::SIZE_I - 1, 3, 5);
+		let (left, right) = (ChunkCoord::new(0, 0, 0), ChunkCoord::new(1, 0, 0));
+		let (mut a, mut b) = (Chunk::empty(), Chunk::empty());
+		placer.stamp(left, &mut a, &[origin]);
+		placer.stamp(right, &mut b, &[origin]);
+		let solid = |c: &Chunk| c.iter_solid().count();
+		assert_eq!(solid(&a) + solid(&b), placer.tree().blocks.len());
+		for (offset, block) in &placer.tree().blocks {
+			let pos = origin + *offset;
+			let chunk = if pos.x < Chunk::SIZE_I { &a } else { &b };
+			assert_eq!(chunk.get_block(LocalPos::from(pos).into()), *block);
+		}
+
+		// Existing terrain is never replaced
+		let mut filled = Chunk::new(2);
+		placer.stamp(left, &mut filled, &[origin]);
+		assert!(filled.iter_solid().all(|(_, b)| b == block(2)));
+	}
+
+	#[test]
+	fn flat_and_void_generation() {
+		use crate::world::generation::GenMode;
+		init_luts();
+		let layers = vec![(Material(2), 3), (Material(3), Chunk::SIZE as u32)];
+		let flat = GenMode::Flat { layers: layers.clone() };
+		assert_eq!(flat.block_range(), Some((-3 - Chunk::SIZE_I, -1)));
+
+		// The top layer ends right under y = 0, the one below reaches into the next chunk down
+		let top = Chunk::generate_with(ChunkCoord::new(5, -1, -7), 1, &flat);
+		let at = |c: &Chunk, y: u8| c.get_block(LocalPos::new(3, y, 4).into());
+		assert_eq!(at(&top, Chunk::SIZE as u8 - 1), block(3));
+		assert_eq!(at(&top, 0), block(3));
+		let below = Chunk::generate_with(ChunkCoord::new(5, -2, -7), 1, &flat);
+		assert_eq!(at(&below, Chunk::SIZE as u8 - 1), block(2));
+		assert_eq!(at(&below, Chunk::SIZE as u8 - 3), block(2));
+		assert!(at(&below, Chunk::SIZE as u8 - 4).is_empty());
+		assert!(Chunk::generate_with(ChunkCoord::new(0, 0, 0), 1, &flat).is_empty());
+		assert!(Chunk::generate_with(ChunkCoord::new(0, -3, 0), 1, &flat).is_empty());
+		assert!(top.finished_gen());
+
+		// Void is empty apart from the spawn platform
+		let void = Chunk::generate_with(ChunkCoord::new(0, -1, 0), 1, &GenMode::Void);
+		assert_eq!(void.iter_solid().count(), 3 * 3); // the corner quarter of the 5x5 platform
+		assert!(Chunk::generate_with(ChunkCoord::new(4, -1, 0), 1, &GenMode::Void).is_empty());
+		assert!(Chunk::generate_with(ChunkCoord::new(0, 0, 0), 1, &GenMode::Void).is_empty());
+
+		// Saved by material name
+		for mode in [GenMode::Noise, GenMode::Void, flat, GenMode::superflat()] {
+			let bytes = mode.to_binary();
+			assert_eq!(bytes.len(), mode.binary_size());
+			assert_eq!(GenMode::from_binary(&bytes), Some(mode));
+		}
+	}
+
+	#[test]
+	fn storage_entities_roundtrip() {
+		use crate::block::entity::{BlockEntity, EntityStorage};
+		use crate::item::{inventory::ItemContainer, items::ItemStack};
+		init_luts();
+		// A filled chest at a negative world position and an empty one in the same chunk
+		let chest = IVec3::new(-5, 17, -30);
+		let coord = ChunkCoord::from_world_pos(chest);
+		let mut chunk = Chunk::new(2);
+		let mut items = ItemContainer::new(3, 9);
+		let mut sword = ItemStack::from_str("iron_sword");
+		sword.damage(7);
+		items.set_at(1, 4, sword.clone().opt());
+		items.set_at(2, 8, ItemStack::from_str("arrow").with_stack_size(37).opt());
+		chunk.add_entity(LocalPos::from(chest), items.clone());
+		chunk.add_entity(LocalPos::new(0, 0, 0), ItemContainer::new(3, 9));
+
+		// Walked back to back like the chunks of a region file
+		let mut bytes = chunk.to_binary();
+		assert_eq!(bytes.len(), chunk.binary_size());
+		bytes.extend_from_slice(&Chunk::empty().to_binary());
+		let (loaded, len) = Chunk::read_binary(&bytes).expect("chunk reads back");
+		assert_eq!(len, chunk.binary_size());
+		assert!(Chunk::read_binary(&bytes[len..]).is_some_and(|(c, _)| c.entities().is_empty()));
+
+		// Positions map back to the same world block, the items come back exactly
+		let (pos, _) = loaded.entities().iter().find(|(_, e)| e.storage.count_items() > 0).expect("chest kept");
+		let (x, y, z) = coord.unpack_to_worldpos();
+		assert_eq!(IVec3::new(x, y, z) + IVec3::from(pos), chest);
+		assert_eq!(loaded.get_entity(pos), Some(&items));
+		assert_eq!(loaded.get_entity(pos).and_then(|c| c.get_at(1, 4)).and_then(|s| s.durability()), sword.durability());
+		assert_eq!(loaded.get_entity(LocalPos::new(0, 0, 0)), Some(&ItemContainer::new(3, 9)));
+
+		// The empty chest only keeps its size
+		let empty = BlockEntity::from_item(ItemContainer::new(3, 9));
+		assert!(EntityStorage::Sparse([(LocalPos::ZERO, empty.clone())].into_iter().collect()).binary_size() < 8);
+
+		// Older saves wrote every container in full and still load with the right length
+		let mut old = vec![1u8];
+		old.extend_from_slice(&1u16.to_binary());
+		old.extend_from_slice(&LocalPos::ZERO.to_binary());
+		old.extend_from_slice(&empty.to_binary());
+		old.push(0xAB); // whatever follows
+		let (storage, len) = EntityStorage::read_binary(&old).expect("old format reads");
+		assert_eq!(len, old.len() - 1);
+		assert_eq!(storage.get(LocalPos::ZERO), Some(&empty));
+	}
+
+	#[test]
+	fn autosave_tracks_changes() {
+		use crate::world::autosave::AutoSave;
+		use crate::item::inventory::ItemContainer;
+		init_luts();
+		let mut world = World::empty();
+		let (near, far) = (ChunkCoord::new(0, 0, 0), ChunkCoord::new(1, 0, 0));
+		world.set_chunk(near, Chunk::new(1));
+		world.set_chunk(far, Chunk::new(1));
+		assert!(!world.has_unsaved()); // loading or generating isn't a change
+
+		// The box pokes into a chunk that isn't there yet, it stays marked until generated
+		world.fill_region(IVec3::new(2, 2, 2), IVec3::new(4, 4, -1), block(2));
+		world.create_storage(IVec3::new(40, 1, 1), ItemContainer::new(1, 1));
+		let mut saved = world.take_unsaved();
+		saved.sort_by_key(|(coord, _)| coord.unpack());
+		assert_eq!(saved.iter().map(|(coord, _)| *coord).collect::<Vec<_>>(), vec![near, far]);
+		assert_eq!(saved[0].1.get_block(LocalPos::new(3, 3, 0).into()), block(2));
+		assert!(saved[1].1.get_entity(LocalPos::new(8, 1, 1)).is_some());
+		assert_eq!(world.unsaved.iter().copied().collect::<Vec<_>>(), vec![ChunkCoord::new(0, 0, -1)]);
+		assert!(world.take_unsaved().is_empty());
+
+		// Nothing is due while off or held back
+		let mut autosave = AutoSave::new();
+		assert!(!autosave.tick(100., 0., false));
+		assert_eq!(autosave.elapsed(), 0.);
+		assert!(!autosave.tick(10., 30., false));
+		assert!(!autosave.tick(90., 30., true));
+		assert!(autosave.tick(0., 30., false));
+		assert!(!autosave.is_saving());
+	}
+
+	#[test]
+	fn player_data_roundtrip() {
+		use crate::world::data::PlayerData;
+		use crate::game::player::Player;
+		use crate::ext::config::CameraConfig;
+		use crate::item::{inventory::{AreaType, Inventory}, items::ItemStack};
+		init_luts();
+		let config = CameraConfig::new(Vec3::new(0., 1.7, 0.));
+		let mut player = Player::dummy(Vec3::new(0.5, 0.5, 0.5), config);
+		player.set_pos(Vec3::new(-120.25, 64., 3.5));
+		player.set_look(2.5, 3.); // pitch past straight up gets clamped
+		player.set_fly(true);
+		let mut sword = ItemStack::from_str("iron_sword");
+		sword.damage(3);
+		player.inventory_mut().get_area_mut(AreaType::Hotbar).set_at(0, 4, sword.opt());
+		player.inventory_mut().get_area_mut(AreaType::Inventory).set_at(1, 2, ItemStack::from_str("arrow").with_stack_size(21).opt());
+		player.inventory_mut().select_slot(4);
+
+		let saved = PlayerData::from_player(&player);
+		assert!(saved.pitch < std::f32::consts::FRAC_PI_2);
+		let bytes = saved.to_binary();
+		assert_eq!(bytes.len(), saved.binary_size());
+		let loaded = PlayerData::from_binary(&bytes).expect("player reads back");
+		assert!(loaded == saved);
+
+		let mut fresh = Player::dummy(Vec3::new(0.5, 0.5, 0.5), config);
+		loaded.apply(&mut fresh);
+		assert_eq!(fresh.pos(), player.pos());
+		assert_eq!((fresh.yaw(), fresh.pitch()), (player.yaw(), player.pitch()));
+		assert_eq!(fresh.camera().rotation(), player.camera().rotation());
+		assert!(fresh.is_flying());
+		assert_eq!(fresh.inventory().selected_index(), 4);
+		assert!(fresh.inventory().selected_item().is_some_and(|s| s.durability() == player.inventory().selected_item().and_then(|s| s.durability())));
+		assert!(fresh.inventory().hotbar() == player.inventory().hotbar() && fresh.inventory().inv() == player.inventory().inv());
+
+		// Without a readable inventory the place is still kept, too short is no data at all
+		let cut = PlayerData::from_binary(&bytes[..Vec3::BINARY_SIZE + 10]).expect("position reads");
+		assert_eq!(cut.pos, saved.pos);
+		assert!(cut.inventory == Inventory::default());
+		assert!(PlayerData::from_binary(&bytes[..Vec3::BINARY_SIZE + 9]).is_none());
+	}
+
+	#[test]
+	fn edit_history_undo_redo() {
+		use crate::block::extra::get_block_id_from_item_name;
+		use crate::item::items::ItemStack;
+		init_luts();
+		let mut world = World::empty();
+		world.set_chunk(ChunkCoord::ZERO, Chunk::new(1));
+		let chest = Block::new(Material(get_block_id_from_item_name("plank")));
+		let pos = IVec3::new(1, 1, 1);
+
+		// A filled chest, then a fill over it, each fill is one step
+		world.fill_region(pos, pos, chest);
+		let items = world.get_storage_mut(pos).expect("placed storage gets a container");
+		items.set_at(2, 3, ItemStack::from_str("arrow").with_stack_size(12).opt());
+		let items = items.clone();
+		world.fill_region(IVec3::ZERO, IVec3::splat(2), block(2));
+		assert!(world.get_storage(pos).is_none());
+		assert_eq!(world.history.undo_steps(), 2);
+
+		// Undo brings back the chest with its items, the chunk gets remeshed
+		world.get_chunk_mut(&ChunkCoord::ZERO).unwrap().dirty = false;
+		assert!(world.undo());
+		assert_eq!(world.get_block(pos), chest);
+		assert_eq!(world.get_block(IVec3::ZERO), block(1));
+		assert_eq!(world.get_storage(pos), Some(&items));
+		assert!(world.get_chunk(&ChunkCoord::ZERO).unwrap().dirty);
+
+		// Redo takes it again, undoing that keeps whatever was stored in between
+		assert!(world.redo());
+		assert_eq!(world.get_block(pos), block(2));
+		assert!(world.get_storage(pos).is_none());
+		assert!(world.undo() && world.undo());
+		assert_eq!(world.get_block(pos), block(1));
+		assert!(!world.undo());
+		assert_eq!(world.history.redo_steps(), 2);
+		assert!(world.redo() && world.redo() && world.undo());
+		assert_eq!(world.get_storage(pos), Some(&items));
+
+		// A new edit drops the redo stack
+		world.record_edit(IVec3::new(5, 5, 5), block(1), block(3));
+		world.end_edit_group();
+		assert_eq!(world.history.redo_steps(), 0);
+		assert!(!world.redo());
+
+		// Only the newest steps are kept
+		world.history.max_steps = 3;
+		for id in 2..8 {
+			world.fill_region(IVec3::ZERO, IVec3::ZERO, block(id));
+		}
+		assert_eq!(world.history.undo_steps(), 3);
+		world.history.max_edits = 1;
+		world.fill_region(IVec3::ZERO, IVec3::splat(3), block(2));
+		assert_eq!(world.history.undo_steps(), 1); // over the edit cap alone, but still undoable
+		assert!(world.undo());
+		assert_eq!(world.get_block(IVec3::ZERO), block(7));
+	}
+
+	#[test]
+	fn edit_history_keeps_fills_compact_and_no_items_twice() {
+		use crate::block::extra::get_block_id_from_item_name;
+		use crate::item::items::ItemStack;
+		use crate::world::history::SNAPSHOT_WEIGHT;
+		init_luts();
+		let mut world = World::empty();
+		let side = ChunkCoord::new(1, 0, 0);
+		world.set_chunk(ChunkCoord::ZERO, Chunk::new(1));
+		world.set_chunk(side, Chunk::new(1));
+
+		// A fill over a whole chunk and a bit of the next is one snapshot per chunk, not an edit per block
+		world.fill_region(IVec3::ZERO, IVec3::new(Chunk::SIZE_I, Chunk::SIZE_I - 1, Chunk::SIZE_I - 1), block(2));
+		assert_eq!(world.history.undo_steps(), 1);
+		assert_eq!(world.history.edits(), 2 * SNAPSHOT_WEIGHT);
+		// Filling what is already there keeps nothing
+		world.fill_region(IVec3::ZERO, IVec3::splat(3), block(2));
+		assert_eq!(world.history.undo_steps(), 1);
+
+		// A chunk that went away is skipped, not brought back empty, and redo leaves it alone too
+		let gone = world.chunks.remove(&side).unwrap();
+		assert!(world.undo());
+		assert!(!world.chunks.contains_key(&side));
+		assert_eq!(world.get_block(IVec3::ONE), block(1));
+		world.set_chunk(side, gone);
+		assert!(world.redo());
+		assert_eq!(world.get_block(IVec3::ONE), block(2));
+		assert_eq!(world.get_block(IVec3::new(Chunk::SIZE_I, 0, 0)), block(2));
+		assert_eq!(world.history.edits(), SNAPSHOT_WEIGHT);
+
+		// Breaking a chest hands its items out, so undo brings it back empty
+		let chest = Block::new(Material(get_block_id_from_item_name("plank")));
+		let pos = IVec3::new(1, 1, 1);
+		world.fill_region(pos, pos, chest);
+		let items = world.get_storage_mut(pos).unwrap();
+		items.set_at(0, 0, ItemStack::from_str("arrow").with_stack_size(12).opt());
+		world.record_edit(pos, chest, Block::default());
+		world.end_edit_group();
+		let chunk = world.get_chunk_mut(&ChunkCoord::ZERO).unwrap();
+		chunk.remove_entity(LocalPos::from(pos));
+		chunk.set_block(usize::from(LocalPos::from(pos)), Block::default());
+		assert!(world.undo());
+		assert_eq!(world.get_block(pos), chest);
+		assert!(world.get_storage(pos).is_some_and(|items| items.iter().all(|slot| slot.is_none())));
+	}
+
+	#[test]
+	fn schematic_copy_paste_rotated() {
+		use crate::world::schematic::{Schematic, PasteRotation};
+		init_luts();
+		let mut world = World::empty();
+		world.set_chunk(ChunkCoord::ZERO, Chunk::new(1));
+		let facing_x = Block::from(Material(3), BlockRotation::XPLUS_YPLUS);
+		world.fill_region(IVec3::ZERO, IVec3::new(2, 0, 0), block(2));
+		world.fill_region(IVec3::new(0, 1, 0), IVec3::new(0, 1, 0), facing_x);
+
+		let copied = world.copy_region(IVec3::new(2, 1, 0), IVec3::ZERO).unwrap();
+		assert_eq!(copied.size, IVec3::new(3, 2, 1));
+		assert_eq!(copied.get(IVec3::new(0, 1, 0)), Some(facing_x));
+
+		// A quarter turn around y: the row along x now runs along z, the block faces -z
+		let turn = PasteRotation::around_y(1);
+		let rotated = copied.rotated(turn);
+		assert_eq!(rotated.size, IVec3::new(1, 2, 3));
+		assert_eq!(rotated.get(IVec3::new(0, 1, 2)), Some(Block::from(Material(3), BlockRotation::ZMINUS_YPLUS)));
+		assert!(copied.rotated(PasteRotation::around_y(4)) == copied);
+		assert!(rotated.rotated(PasteRotation::around_y(3)) == copied);
+
+		// Keeping what is there only fills air
+		let origin = IVec3::new(8, 0, 8);
+		world.fill_region(IVec3::new(8, 0, 9), IVec3::new(8, 0, 9), block(5));
+		assert_eq!(world.paste(origin, &copied, turn, false), 3);
+		assert_eq!(world.get_block(IVec3::new(8, 0, 8)), block(2));
+		assert_eq!(world.get_block(IVec3::new(8, 0, 9)), block(5));
+		assert_eq!(world.get_block(IVec3::new(8, 1, 10)).get_rotation(), BlockRotation::ZMINUS_YPLUS);
+		// Overwriting replaces it, the paste is one undo step
+		assert_eq!(world.paste(origin, &copied, turn, true), 1);
+		assert_eq!(world.get_block(IVec3::new(8, 0, 9)), block(2));
+		assert!(world.undo());
+		assert_eq!(world.get_block(IVec3::new(8, 0, 9)), block(5));
+
+		// On disk the blocks go by name and the runs read back exactly
+		let bytes = rotated.to_binary();
+		assert_eq!(bytes.len(), rotated.binary_size());
+		assert!(Schematic::from_binary(&bytes).is_some_and(|s| s == rotated));
+		assert!(Schematic::from_binary(&bytes[..bytes.len() - 1]).is_none());
+		assert_eq!(Schematic::new(IVec3::new(-1, 2, 2)).unwrap().volume(), 0);
+	}
+
+	#[test]
+	fn schematic_sizes_are_bounded() {
+		use crate::world::schematic::{Schematic, PasteRotation, MAX_SCHEMATIC_VOLUME};
+		init_luts();
+		// Too big or overflowing boxes are refused instead of allocated
+		assert!(Schematic::new(IVec3::splat(i32::MAX)).is_none());
+		assert!(Schematic::new(IVec3::new(MAX_SCHEMATIC_VOLUME as i32 + 1, 1, 1)).is_none());
+		assert!(Schematic::new(IVec3::new(MAX_SCHEMATIC_VOLUME as i32, 0, 1)).is_some_and(|s| s.volume() == 0));
+		let world = World::empty();
+		assert!(world.copy_region(IVec3::splat(i32::MIN), IVec3::splat(i32::MAX)).is_none());
+
+		// A header asking for a huge box with no runs behind it reads as nothing
+		let mut bytes = IVec3::new(1 << 12, 1 << 12, 1 << 12).to_binary();
+		bytes.extend_from_slice(&0u16.to_binary());
+		bytes.extend_from_slice(&0u32.to_binary());
+		assert!(Schematic::from_binary(&bytes).is_none());
+		let mut bytes = IVec3::new(1 << 10, 1 << 10, 4).to_binary();
+		bytes.extend_from_slice(&0u16.to_binary());
+		bytes.extend_from_slice(&0u32.to_binary());
+		assert!(Schematic::from_binary(&bytes).is_none());
+
+		// Pasting only reaches loaded chunks, nothing is made up for the others
+		let mut world = World::empty();
+		world.set_chunk(ChunkCoord::ZERO, Chunk::new(1));
+		let mut wall = Schematic::new(IVec3::new(2, 1, 1)).unwrap();
+		wall.set(IVec3::ZERO, block(2));
+		wall.set(IVec3::X, block(2));
+		assert_eq!(world.paste(IVec3::new(Chunk::SIZE_I - 1, 0, 0), &wall, PasteRotation::NONE, true), 1);
+		assert_eq!(world.get_block(IVec3::new(Chunk::SIZE_I - 1, 0, 0)), block(2));
+		assert!(world.get_chunk(&ChunkCoord::new(1, 0, 0)).is_none());
+	}
+
+	#[test]
+	fn mod_block_arguments_checked() {
+		use crate::mods::api::{block_from_mod, pack_block};
+		use crate::render::texture::material_count;
+		init_luts();
+		let rotated = Block::from(Material(2), BlockRotation::ZMINUS_YPLUS);
+		let packed = pack_block(rotated);
+		assert_eq!(block_from_mod((packed & 0xFFFF) as i32, (packed >> 16) as i32), Some(rotated));
+
+		// Anything a mod could pass that isn't a real block is refused instead of reaching the world
+		let materials = material_count() as i32;
+		assert!(block_from_mod(materials, BlockRotation::XPLUS_YPLUS.as_u8() as i32).is_none());
+		assert!(block_from_mod(-1, 0).is_none());
+		assert!(block_from_mod(2, 0).is_none()); // +x with +x
+		assert!(block_from_mod(2, 0xFF).is_none());
+		assert!(block_from_mod(2, 256 + BlockRotation::XPLUS_YPLUS.as_u8() as i32).is_none());
+	}
+
+	#[test]
+	fn mod_event_handlers_ordered() {
+		use crate::mods::api::{ModHost, ModEvent};
+		assert_eq!(ModEvent::from_name("on_block_place"), Some(ModEvent::BlockPlace));
+		assert!(ModEvent::ALL.iter().all(|e| ModEvent::from_name(e.name()) == Some(*e)));
+		assert_eq!(ModEvent::from_name("on_explode"), None);
+
+		let mut host = ModHost::new();
+		host.module = "b_mod".to_string();
+		assert!(host.register(ModEvent::Tick, "tick"));
+		assert!(!host.register(ModEvent::Tick, "tick"));
+		assert!(host.register(ModEvent::BlockBreak, "broke"));
+		assert!(host.register_for("a_mod", ModEvent::Tick, "tick"));
+
+		// Registration order, a disabled handler is skipped but the others keep their place
+		let ticks = host.handlers_of(ModEvent::Tick);
+		assert_eq!(ticks.iter().map(|(_, m, f)| (m.as_str(), f.as_str())).collect::<Vec<_>>(), vec![("b_mod", "tick"), ("a_mod", "tick")]);
+		host.handlers[ticks[0].0].enabled = false;
+		assert_eq!(host.handlers_of(ModEvent::Tick).iter().map(|(i, _, _)| *i).collect::<Vec<_>>(), vec![2]);
+		assert_eq!(host.handlers_of(ModEvent::BlockBreak).len(), 1);
+		assert!(host.handlers_of(ModEvent::BlockPlace).is_empty());
+	}
+
+	#[test]
+	fn mod_materials_stable_and_capped() {
+		use crate::render::texture::{ModMaterials, MAX_BLOCK_LAYERS};
+		let base = 40;
+		let mut materials = ModMaterials::new();
+		assert_eq!(materials.add(base, "ruby_ore"), Some(40));
+		assert_eq!(materials.add(base, "ruby_block"), Some(41));
+		assert_eq!(materials.add(base, "ruby_ore"), Some(40)); // same name, same material
+		assert_eq!(materials.len(), 2);
+		assert_eq!(materials.name_of(base, 41), Some("ruby_block"));
+		assert_eq!(materials.name_of(base, 39), None); // built in, not ours
+		assert_eq!(materials.id_of(base, "ruby_block"), Some(41));
+
+		// Every layer taken, new names are refused but the old ones still resolve
+		for i in materials.len()..MAX_BLOCK_LAYERS - base {
+			assert!(materials.add(base, &format!("filler_{}", i)).is_some());
+		}
+		assert_eq!(materials.add(base, "one_too_many"), None);
+		assert_eq!(materials.add(base, "ruby_ore"), Some(40));
+		assert_eq!(materials.name_of(base, (MAX_BLOCK_LAYERS - 1) as u16).map(str::to_string), Some(format!("filler_{}", MAX_BLOCK_LAYERS - base - 1)));
+	}
+
+	#[test]
+	fn mod_lengths_are_checked_against_their_memory() {
+		use crate::mods::api::{guest_range, WasmRuntime};
+		assert_eq!(guest_range(16, 4, 64).unwrap(), (16, 4));
+		assert_eq!(guest_range(60, 4, 64).unwrap(), (60, 4));
+		assert!(guest_range(61, 4, 64).is_err());
+		assert!(guest_range(0, -1, 64).is_err());
+		assert!(guest_range(-8, 4, 64).is_err());
+		assert!(guest_range(i32::MAX, i32::MAX, usize::MAX).is_ok());
+
+		// A bad length traps the call instead of allocating gigabytes, the mod keeps working
+		let mut runtime = WasmRuntime::new().expect("runtime starts");
+		let logger = br#"(module (import "env" "log" (func $log (param i32 i32)))
+			(memory (export "memory") 1)
+			(data (i32.const 0) "hi")
+			(func (export "huge") (call $log (i32.const 0) (i32.const 2147483647)))
+			(func (export "negative") (call $log (i32.const 0) (i32.const -1)))
+			(func (export "hello") (call $log (i32.const 0) (i32.const 2))))"#;
+		runtime.load_module_bytes("logger", logger).expect("module loads");
+		assert!(runtime.call_function_simple("logger", "huge").is_err());
+		assert!(runtime.call_function_simple("logger", "negative").is_err());
+		assert!(runtime.call_function_simple("logger", "hello").is_ok());
+	}
+
+	#[test]
+	fn mod_fuel_stops_runaway_loops() {
+		use crate::mods::api::{WasmRuntime, WasmError};
+		let mut runtime = WasmRuntime::new().expect("runtime starts");
+		runtime.fuel_per_call = 100_000;
+		let spinner = br#"(module (memory (export "memory") 1)
+			(func (export "spin") (loop br 0))
+			(func (export "sum") (param i32) (result i32) (local i32)
+				(loop (local.set 1 (i32.add (local.get 1) (local.get 0)))
+					(local.set 0 (i32.sub (local.get 0) (i32.const 1)))
+					(br_if 0 (local.get 0)))
+				(local.get 1)))"#;
+		runtime.load_module_bytes("spinner", spinner).expect("module loads");
+		runtime.load_module_bytes("worker", spinner).expect("module loads");
+
+		// Modest work fits the budget, every call gets it again
+		for _ in 0..3 {
+			assert_eq!(runtime.call_function_i32("worker", "sum", 1000).ok(), Some(500_500));
+		}
+		// The endless loop traps and only its mod is turned off
+		assert!(matches!(runtime.call_function_simple("spinner", "spin"), Err(WasmError::OutOfFuel { .. })));
+		assert!(runtime.is_disabled("spinner"));
+		assert!(matches!(runtime.call_function_i32("spinner", "sum", 3), Err(WasmError::ModDisabled { .. })));
+		assert_eq!(runtime.call_function_i32("worker", "sum", 10).ok(), Some(55));
+		runtime.enable_module("spinner");
+		assert_eq!(runtime.call_function_i32("spinner", "sum", 4).ok(), Some(10));
+	}
+
+	#[test]
+	fn mod_manifest_load_order() {
+		use crate::mods::{api::WasmError, manifest::{ModManifest, load_order}};
+		use std::path::{Path, PathBuf};
+		let dir = Path::new("comp_mods/ruby");
+		let parsed = ModManifest::from_json(r#"{"name": "ruby", "version": "1.2.0", "deps": ["gems", "core", "gems"]}"#, dir).expect("valid manifest");
+		assert_eq!(parsed.deps, vec!["core".to_string(), "gems".to_string()]); // sorted and deduped
+		assert_eq!(parsed.wasm, dir.join("ruby.wasm"));
+		assert!(ModManifest::from_json(r#"{"version": "1.0"}"#, dir).is_err());
+		assert!(ModManifest::from_json(r#"{"name": "x", "version": "1.0", "deps": "core"}"#, dir).is_err());
+
+		let m = |name: &str, deps: &[&str]| ModManifest::new(name.to_string(), "1.0".to_string(), deps.iter().map(|d| d.to_string()).collect(), PathBuf::from(format!("{}.wasm", name)));
+		let mods = vec![
+			m("ruby", &["gems", "core"]), m("gems", &["core"]), m("core", &[]),
+			m("lonely", &[]), m("needs_ghost", &["ghost"]), m("needs_needy", &["needs_ghost"]),
+			m("loop_a", &["loop_b"]), m("loop_b", &["loop_a"]), m("after_loop", &["loop_a"]),
+		];
+		let (order, errors) = load_order(&mods);
+		let names: Vec<&str> = order.iter().map(|&i| mods[i].name.as_str()).collect();
+		// Deps first, ties go by name
+		assert_eq!(names, vec!["core", "gems", "lonely", "ruby"]);
+		assert_eq!(load_order(&mods).0, order);
+
+		let failed = |name: &str| errors.iter().find(|(n, _)| n == name).map(|(_, e)| e);
+		assert!(matches!(failed("needs_ghost"), Some(WasmError::MissingDependency { dependency, .. }) if dependency == "ghost"));
+		assert!(matches!(failed("needs_needy"), Some(WasmError::MissingDependency { dependency, .. }) if dependency == "needs_ghost"));
+		assert!(matches!(failed("loop_a"), Some(WasmError::DependencyCycle { modules }) if modules.contains(&"loop_b".to_string())));
+		assert!(failed("after_loop").is_some());
+		assert_eq!(errors.len(), 5);
+	}
+
+	#[test]
+	fn game_message_frames() {
+		use crate::network::types::{GameMessage, encode_frame, decode_frames, MAX_FRAME_SIZE};
+		use crate::fs::binary::BinarySerializable;
+		let messages = [GameMessage::GameState(-42), GameMessage::WorldSync("New World".to_string())];
+		for msg in &messages {
+			assert_eq!(GameMessage::from_binary(&msg.to_binary()).as_ref(), Some(msg));
+			assert_eq!(msg.to_binary().len(), msg.binary_size());
+		}
+		assert_eq!(GameMessage::from_binary(&[9, 0, 0, 0, 0]), None);
+
+		// A stream can split frames anywhere, the rest waits for the next read
+		let stream: Vec<u8> = messages.iter().flat_map(encode_frame).collect();
+		let mut buffer = stream[..7].to_vec();
+		assert_eq!(decode_frames(&mut buffer), Ok(vec![]));
+		buffer.extend_from_slice(&stream[7..stream.len() - 1]);
+		assert_eq!(decode_frames(&mut buffer), Ok(vec![messages[0].clone()]));
+		buffer.push(stream[stream.len() - 1]);
+		assert_eq!(decode_frames(&mut buffer), Ok(vec![messages[1].clone()]));
+		assert!(buffer.is_empty());
+
+		let mut huge = ((MAX_FRAME_SIZE + 1) as u32).to_le_bytes().to_vec();
+		assert!(decode_frames(&mut huge).is_err());
+		let mut garbage = vec![1, 0, 0, 0, 7];
+		assert!(decode_frames(&mut garbage).is_err());
+	}
+
+	#[test]
+	fn block_edit_packets_checked_and_idempotent() {
+		use crate::network::{types::{GameMessage, encode_frame, decode_frames}, sync::{block_from_packet, block_edit_message}};
+		use crate::fs::binary::BinarySerializable;
+		use crate::render::texture::material_count;
+		init_luts();
+		let rotated = Block::from(Material(2), BlockRotation::ZMINUS_YPLUS);
+		let pos = IVec3::new(-3, 7, 40);
+		let msg = block_edit_message(pos, rotated);
+		assert_eq!(msg, GameMessage::BlockEdit { pos: [-3, 7, 40], material: 2, rotation: BlockRotation::ZMINUS_YPLUS.as_u8() });
+		assert_eq!(GameMessage::from_binary(&msg.to_binary()).as_ref(), Some(&msg));
+		let sync = GameMessage::ChunkSync { coord: ChunkCoord::new(1, -2, 3), data: vec![5; 300] };
+		let mut stream = encode_frame(&msg);
+		stream.extend(encode_frame(&sync));
+		assert_eq!(decode_frames(&mut stream), Ok(vec![msg.clone(), sync]));
+
+		// Malformed edits are refused, not applied or panicked on
+		let bytes = msg.to_binary();
+		assert_eq!(GameMessage::from_binary(&bytes[..bytes.len() - 1]), None);
+		assert_eq!(block_from_packet(2, BlockRotation::ZMINUS_YPLUS.as_u8()), Some(rotated));
+		assert_eq!(block_from_packet(2, 0), None); // +x with +x
+		assert_eq!(block_from_packet(2, 0xFF), None);
+		assert_eq!(block_from_packet(material_count() as u16, BlockRotation::XPLUS_YPLUS.as_u8()), None);
+
+		// The same edit twice (the host echoing it back) leaves the world as it was after the first
+		let mut world = World::empty();
+		world.set_chunk(ChunkCoord::ZERO, Chunk::new(1));
+		let pos = IVec3::new(4, 5, 6);
+		assert!(world.apply_remote_block(pos, rotated));
+		let after_first = world.get_chunk(&ChunkCoord::ZERO).unwrap().clone();
+		assert!(world.apply_remote_block(pos, rotated));
+		assert_eq!(world.get_block(pos), rotated);
+		assert_eq!(world.get_chunk(&ChunkCoord::ZERO).unwrap().storage(), after_first.storage());
+		// Remote edits aren't the local player's to undo, and unloaded chunks stay untouched
+		assert_eq!(world.history.undo_steps(), 0);
+		assert!(!world.apply_remote_block(IVec3::splat(Chunk::SIZE as i32 * 4), rotated));
+	}
+
+	#[test]
+	fn chunk_stream_window_and_resend() {
+		use crate::network::{stream::{ChunkStream, Streams, STREAM_WINDOW, STREAM_RESEND}, types::GameMessage};
+		use crate::fs::binary::BinarySerializable;
+		use std::time::Instant;
+		let coords: Vec<ChunkCoord> = (-3..=3).flat_map(|x| (-3..=3).map(move |z| ChunkCoord::new(x, 0, z))).collect();
+		let mut stream = ChunkStream::new(7, ChunkCoord::ZERO, coords.clone(), 100);
+		let now = Instant::now();
+
+		// Nearest first, never more than the window in flight
+		let first = stream.due(now);
+		assert_eq!(first.len(), STREAM_WINDOW);
+		assert_eq!(first[0], (100, ChunkCoord::ZERO));
+		assert!(first.windows(2).all(|w| {
+			let d = |c: ChunkCoord| { let (x, y, z) = c.unpack(); x * x + y * y + z * z };
+			d(w[0].1) <= d(w[1].1)
+		}));
+		assert!(stream.due(now).is_empty());
+
+		// Acks open the window again, a lost chunk goes again under the same number
+		assert!(stream.ack(100) && stream.ack(101));
+		assert!(!stream.ack(100));
+		let more = stream.due(now);
+		assert_eq!(more.iter().map(|(seq, _)| *seq).collect::<Vec<_>>(), vec![116, 117]);
+		let resent = stream.due(now + STREAM_RESEND);
+		assert_eq!(resent.len(), STREAM_WINDOW);
+		assert!(resent.iter().any(|(seq, coord)| *seq == 102 && *coord == first[2].1));
+		for seq in 100..100 + coords.len() as u32 {
+			stream.ack(seq);
+			stream.due(now);
+		}
+		assert!(stream.is_done());
+
+		// The client keeps each chunk once and can pull one forward for an edit
+		let mut streams = Streams::new();
+		assert!(streams.receive(5, ChunkCoord::ZERO, vec![1, 2]));
+		assert!(!streams.receive(5, ChunkCoord::ZERO, vec![1, 2]));
+		assert!(streams.receive(6, ChunkCoord::new(1, 0, 0), vec![3]));
+		assert_eq!(streams.take_pending(ChunkCoord::new(1, 0, 0)), Some((6, ChunkCoord::new(1, 0, 0), vec![3])));
+		assert_eq!(streams.inbox.len(), 1);
+
+		// Run length encoded chunks go through the message and back
+		let chunk = Chunk::new(3);
+		let msg = GameMessage::ChunkData { seq: 9, coord: ChunkCoord::new(2, -1, 0), data: chunk.to_binary() };
+		let Some(GameMessage::ChunkData { seq: 9, data, .. }) = GameMessage::from_binary(&msg.to_binary()) else { panic!("chunk data lost") };
+		let mut restored = Chunk::from_binary(&data).expect("chunk reads back");
+		if let Some(storage) = restored.storage().from_rle() { *restored.storage_mut() = storage; }
+		assert_eq!(restored.get_block(0), block(3));
+		assert_eq!(GameMessage::from_binary(&GameMessage::ChunkAck { seq: 3 }.to_binary()), Some(GameMessage::ChunkAck { seq: 3 }));
+	}
+
+	#[test]
+	fn remote_players_interpolate_and_leave() {
+		use crate::game::remote::{PlayerState, RemotePlayers, STATE_INTERVAL};
+		use crate::network::{types::GameMessage, sync::relayed_player};
+		use crate::render::world::avatar_lines;
+		use crate::fs::binary::BinarySerializable;
+
+		let msg = GameMessage::PlayerState { player: 4, pos: [1.5, 64., -3.25], yaw: 0.5 };
+		assert_eq!(GameMessage::from_binary(&msg.to_binary()), Some(msg.clone()));
+		assert_eq!(msg.to_binary().len(), msg.binary_size());
+		let mut bad = GameMessage::PlayerState { player: 0, pos: [f32::NAN, 0., 0.], yaw: 0. }.to_binary();
+		assert_eq!(GameMessage::from_binary(&bad), None);
+		bad.truncate(bad.len() - 1);
+		assert_eq!(GameMessage::from_binary(&bad), None);
+		assert_eq!(GameMessage::from_binary(&GameMessage::PlayerLeft { player: 2 }.to_binary()), Some(GameMessage::PlayerLeft { player: 2 }));
+		assert_ne!(relayed_player(0), 0);
+
+		// The first state places the avatar, later ones are eased into
+		let mut players = RemotePlayers::new();
+		players.update(1, 0, PlayerState::new(Vec3::ZERO, 0.));
+		assert_eq!(players.get(1, 0).unwrap().state().pos, Vec3::ZERO);
+		players.update(1, 0, PlayerState::new(Vec3::new(2., 0., 0.), 0.));
+		assert_eq!(players.get(1, 0).unwrap().state().pos, Vec3::ZERO);
+		players.advance(STATE_INTERVAL / 2.);
+		assert!((players.get(1, 0).unwrap().state().pos.x - 1.).abs() < 1e-4);
+		// A new state midway starts from where the avatar is, not where the old one was headed
+		players.update(1, 0, PlayerState::new(Vec3::new(4., 0., 0.), 0.));
+		assert!((players.get(1, 0).unwrap().state().pos.x - 1.).abs() < 1e-4);
+		players.advance(STATE_INTERVAL * 3.);
+		assert_eq!(players.get(1, 0).unwrap().state().pos, Vec3::new(4., 0., 0.));
+
+		// A peer leaving takes everyone that came through it
+		players.update(1, relayed_player(7), PlayerState::default());
+		players.update(2, 0, PlayerState::default());
+		assert_eq!(players.remove_peer(1), 2);
+		assert_eq!(players.len(), 1);
+		assert!(players.remove(2, 0) && players.is_empty());
+
+		// The avatar box stands on the position like the player's collider, the last line faces -Z at yaw 0
+		let lines = avatar_lines(&PlayerState::new(Vec3::new(0., 10., 0.), 0.));
+		assert_eq!(lines[0].start[1], 10.);
+		assert!(lines.iter().all(|l| l.start[1] >= 10. && l.start[1] + l.direction[1] <= 11.8 + 1e-4));
+		assert_eq!(lines[12].direction, [0., 0., -1.]);
+	}
+
+	#[test]
+	fn chat_log_capped_and_scrolls() {
+		use crate::ui::chat::{ChatLog, clean_chat_text, CHAT_HISTORY, CHAT_MAX_LEN, CHAT_VISIBLE_LINES};
+		use crate::network::types::GameMessage;
+		use crate::fs::binary::BinarySerializable;
+
+		let msg = GameMessage::ChatMessage { sender: "Host".to_string(), text: "hello there".to_string() };
+		assert_eq!(GameMessage::from_binary(&msg.to_binary()), Some(msg.clone()));
+		assert_eq!(msg.to_binary().len(), msg.binary_size());
+		let empty = GameMessage::ChatMessage { sender: String::new(), text: "hi".to_string() };
+		assert_eq!(GameMessage::from_binary(&empty.to_binary()), Some(empty));
+
+		assert_eq!(clean_chat_text("  hi\n there \u{1b}"), Some("hi there".to_string()));
+		assert_eq!(clean_chat_text(" \t\r\n "), None);
+		assert_eq!(clean_chat_text(&"a".repeat(CHAT_MAX_LEN * 2)).map(|t| t.chars().count()), Some(CHAT_MAX_LEN));
+
+		let mut chat = ChatLog::new();
+		for i in 0..CHAT_HISTORY + 20 {
+			chat.push("Player", &i.to_string());
+		}
+		assert_eq!(chat.len(), CHAT_HISTORY);
+		let newest: Vec<String> = chat.visible(3).map(|l| l.text.clone()).collect();
+		assert_eq!(newest, vec!["117", "118", "119"]);
+
+		// Scrolling back stays on the same lines while new ones come in, and stops at the oldest
+		chat.scroll_by(5);
+		assert_eq!(chat.visible(1).next().unwrap().text, "114");
+		chat.push("Player", "new");
+		assert_eq!(chat.visible(1).next().unwrap().text, "114");
+		chat.scroll_by(1000);
+		assert_eq!(chat.scroll(), CHAT_HISTORY - CHAT_VISIBLE_LINES);
+		assert_eq!(chat.visible(CHAT_VISIBLE_LINES).next().unwrap().text, "21");
+		chat.scroll_by(-1000);
+		assert_eq!(chat.scroll(), 0);
+		assert_eq!(chat.visible(1).next().unwrap().display(), "<Player> new");
+	}
+
+	#[test]
+	fn connect_attempt_times_out_and_retries() {
+		use crate::network::types::ConnectAttempt;
+		use std::time::{Duration, Instant};
+
+		let mut attempt = ConnectAttempt::new("1.2.3.4", Duration::from_secs(1), 2);
+		let now = Instant::now();
+		assert!(!attempt.timed_out(now));
+		assert!(attempt.timed_out(now + Duration::from_secs(2)));
+		assert_eq!(attempt.label(), "Connecting to 1.2.3.4...");
+
+		assert!(attempt.next_try(now));
+		assert!(!attempt.timed_out(now));
+		assert!(attempt.next_try(now));
+		assert!(attempt.label().contains("(retry 2/2)"));
+		assert!(!attempt.next_try(now));
+	}
+
+	#[test]
+	fn wire_formats_round_trip_and_drop_garbage() {
+		use crate::network::{types::{GameMessage, MAX_FRAME_SIZE}, wire::{self, WireFormat}};
+		use crate::fs::binary::BinarySerializable;
+		let messages = [
+			GameMessage::ChunkData { seq: 7, coord: ChunkCoord::new(-1, 2, -3), data: vec![4; 4000] },
+			GameMessage::PlayerState { player: u64::MAX - 5, pos: [0.1, -2.5, 1e6], yaw: 3.1 },
+			GameMessage::ChatMessage { sender: "a \"quoted\"\nname".to_string(), text: "héllo\t!".to_string() },
+			GameMessage::ChunkRequest { center: ChunkCoord::new(5, 0, -5), radius: 4 },
+		];
+		for msg in &messages {
+			for format in [WireFormat::Json, WireFormat::Binary, WireFormat::Compressed] {
+				assert_eq!(wire::decode_packet(&wire::encode_packet_as(msg, format)).as_ref(), Ok(msg), "{:?}", format);
+			}
+		}
+
+		// Only big packets are compressed, and only when it helps
+		let big = wire::encode_packet_as(&messages[0], WireFormat::Compressed);
+		assert_eq!(big[0], wire::HEADER_COMPRESSED);
+		assert!(big.len() < messages[0].to_binary().len());
+		assert_eq!(wire::encode_packet_as(&messages[3], WireFormat::Compressed)[0], wire::HEADER_BINARY);
+		assert_eq!(wire::encode_packet_as(&messages[3], WireFormat::Json)[0], wire::HEADER_JSON);
+
+		// Broken packets are refused, not panicked on
+		let mut truncated = big.clone();
+		truncated.truncate(big.len() / 2);
+		assert!(wire::decode_packet(&truncated).is_err());
+		let mut huge = vec![wire::HEADER_COMPRESSED];
+		huge.extend_from_slice(&((MAX_FRAME_SIZE + 1) as u32).to_le_bytes());
+		assert!(wire::decode_packet(&huge).is_err());
+		assert!(wire::decode_packet(&[wire::HEADER_JSON, b'{', b'}']).is_err());
+		assert!(wire::decode_packet(&[wire::HEADER_JSON, 0xff]).is_err());
+		assert!(wire::decode_packet(&[]).is_err());
+		assert!(wire::decode_packet(&[42, 1, 2]).is_err());
+	}
+
+	#[test]
+	fn spawn_chunks_counted_for_loading() {
+		use crate::world::handler;
+		let mut world = World::empty();
+		// Radius 1 is the center and its six neighbours
+		assert_eq!(world.generated_around(ChunkCoord::new(0, 0, 0), 1), (0, 7));
+		world.set_chunk(ChunkCoord::new(0, 0, 0), Chunk::new(2));
+		world.set_chunk(ChunkCoord::new(1, 0, 0), Chunk::new(2));
+		// Still waiting on the generator, not ready yet
+		world.set_chunk(ChunkCoord::new(0, 1, 0), Chunk::empty());
+		world.set_chunk(ChunkCoord::new(1, 1, 0), Chunk::new(2));
+		assert_eq!(world.generated_around(ChunkCoord::new(0, 0, 0), 1), (2, 7));
+		assert_eq!(world.generated_around(ChunkCoord::new(0, 0, 0), 0), (1, 1));
+
+		// Nothing is counted without a world loading
+		handler::report_loading(&world, Vec3::ZERO);
+		assert_eq!(handler::loading_progress(), None);
+	}
+
+	#[test]
+	fn sprinting_eases_the_field_of_view_wider_and_back() {
+		use crate::{ext::{config::CameraConfig, settings::FovConfig}, game::player::{ease_fov, Player}};
+
+		let config = FovConfig::default();
+		let walk = config.target(false);
+		let sprint = config.target(true);
+		assert!((sprint - walk - config.sprint_boost.to_radians()).abs() < 1e-5);
+		// Out of range settings are kept in range
+		assert_eq!(FovConfig { fov: 400., ..config }.target(false), FovConfig::MAX_FOV.to_radians());
+
+		// No snapping: a frame only covers part of the gap, and two half frames land where one whole frame does
+		let step = ease_fov(walk, sprint, config.transition_speed, 1. / 60.);
+		assert!(step > walk && step < sprint && step - walk < (sprint - walk) * 0.5);
+		let halves = ease_fov(ease_fov(walk, sprint, 8., 0.01), sprint, 8., 0.01);
+		assert!((halves - ease_fov(walk, sprint, 8., 0.02)).abs() < 1e-5);
+
+		let world = World::empty();
+		let mut player = Player::dummy(Vec3::ZERO, CameraConfig::default());
+		player.step(10., &world);
+		assert!((player.fov() - walk).abs() < 1e-4);
+		player.set_sprinting(true);
+		assert!(player.is_sprinting());
+		let mut fov = player.fov();
+		for _ in 0..120 {
+			player.set_sprinting(true);
+			let mut keyboard = crate::utils::input::Keyboard::default();
+			keyboard.w = true;
+			keyboard.shift_left = true;
+			player.controller_mut().process_keyboard(&keyboard);
+			player.step(1. / 60., &world);
+			assert!(player.fov() >= fov);
+			fov = player.fov();
+		}
+		assert!((player.fov() - sprint).abs() < 1e-3);
+		player.controller_mut().process_keyboard(&crate::utils::input::Keyboard::default());
+		player.step(10., &world);
+		assert!(!player.is_sprinting());
+		assert!((player.fov() - walk).abs() < 1e-4);
+	}
+
+	#[test]
+	fn damaged_region_is_kept_aside() {
+		use crate::world::manager::{write_regions, region_file_path};
+		use crate::ext::settings::backup_path;
+		let world_dir = std::env::temp_dir().join(format!("rusticubes_bad_region_{}", std::process::id()));
+		let _ = std::fs::remove_dir_all(&world_dir);
+		let (first, second) = (ChunkCoord::ZERO, ChunkCoord::new(1, 0, 0));
+		write_regions(&world_dir, [(first, &Chunk::new(2))]).unwrap();
+
+		// One flipped byte fails the checksum, the file is moved to `.bad` instead of being overwritten
+		let path = region_file_path(&world_dir.join("region"), first);
+		let mut damaged = std::fs::read(&path).unwrap();
+		let middle = damaged.len() / 2;
+		damaged[middle] ^= 0xFF;
+		std::fs::write(&path, &damaged).unwrap();
+		write_regions(&world_dir, [(second, &Chunk::new(3))]).unwrap();
+		assert_eq!(std::fs::read(backup_path(&path)).unwrap(), damaged);
+		let written = std::fs::read(&path).unwrap();
+		assert_ne!(written, damaged);
+
+		// A good file is merged into as before
+		write_regions(&world_dir, [(first, &Chunk::new(2))]).unwrap();
+		assert!(std::fs::read(&path).unwrap().len() > written.len());
+		let _ = std::fs::remove_dir_all(&world_dir);
+	}
+
+	#[test]
+	fn archived_chunks_keep_their_chests() {
+		use crate::item::{inventory::ItemContainer, items::ItemStack};
+		init_luts();
+		let mut world = World::empty();
+		world.set_archive_enabled(true);
+		let coord = ChunkCoord::ZERO;
+		let mut chunk = Chunk::new(2);
+		let mut items = ItemContainer::new(3, 9);
+		items.set_at(0, 2, ItemStack::from_str("arrow").with_stack_size(12).opt());
+		chunk.add_entity(LocalPos::new(3, 4, 5), items.clone());
+		world.set_chunk(coord, chunk);
+
+		// Unloaded into the archive and back, the chest still has its arrows
+		world.update_loaded_chunks(Vec3::new(Chunk::SIZE as f32 * 10. + 1., 1., 1.), 0.);
+		assert!(world.get_chunk(&coord).is_none());
+		assert!(world.archive.contains_key(&coord));
+		world.update_loaded_chunks(Vec3::ONE, 1.);
+		assert!(!world.archive.contains_key(&coord));
+		assert_eq!(world.get_chunk(&coord).unwrap().get_entity(LocalPos::new(3, 4, 5)), Some(&items));
+
+		// And archived again the next time it unloads
+		world.update_loaded_chunks(Vec3::new(Chunk::SIZE as f32 * 10. + 1., 1., 1.), 0.);
+		assert!(world.archive.contains_key(&coord));
+	}
+
+	#[test]
+	fn edits_survive_unloading_and_coming_back() {
+		use crate::world::{manager::{save_chunks, load_existing_chunks, region_file_path}, generation::GenMode};
+		let world_dir = std::env::temp_dir().join(format!("rusticubes_unload_edits_{}", std::process::id()));
+		let _ = std::fs::remove_dir_all(&world_dir);
+		let mut world = World::empty();
+		let coord = ChunkCoord::ZERO;
+		world.set_chunk(coord, Chunk::new(2));
+		world.set_block(IVec3::new(1, 1, 1), Block::default());
+
+		// Walking away unloads it before the edit is saved
+		world.update_loaded_chunks(Vec3::new(Chunk::SIZE as f32 * 10. + 1., 1., 1.), 0.);
+		assert!(world.get_chunk(&coord).is_none());
+		assert!(world.unsaved_unloaded.contains_key(&coord));
+
+		// Coming back brings the edited chunk back instead of generating it again
+		world.update_loaded_chunks(Vec3::ONE, 1.);
+		assert!(world.unsaved_unloaded.is_empty());
+		assert!(world.unsaved.contains(&coord));
+		assert!(world.get_block(IVec3::new(1, 1, 1)).is_empty());
+
+		// The edits before and after the trip are both saved
+		world.set_block(IVec3::new(2, 1, 1), Block::default());
+		save_chunks(&world_dir, 0., 0, &GenMode::Void, &[], &world.take_unsaved()).unwrap();
+		let saved = load_existing_chunks(&region_file_path(&world_dir.join("region"), coord), ChunkCoord::to_region_step(coord)).unwrap();
+		let (_, chunk) = saved.iter().find(|(c, _)| *c == coord).unwrap();
+		assert!(chunk.get_block(LocalPos::new(1, 1, 1).into()).is_empty());
+		assert!(chunk.get_block(LocalPos::new(2, 1, 1).into()).is_empty());
+		let _ = std::fs::remove_dir_all(&world_dir);
+	}
+
+	#[test]
+	fn damaged_world_data_does_not_stop_a_save() {
+		use crate::world::{manager::{save_chunks, region_file_path}, data::{load_world_data, save_world_data, WorldData}, generation::GenMode};
+		use crate::ext::settings::backup_path;
+		let world_dir = std::env::temp_dir().join(format!("rusticubes_bad_world_data_{}", std::process::id()));
+		let _ = std::fs::remove_dir_all(&world_dir);
+		let file = world_dir.join("world_data.dat");
+		save_world_data(&world_dir, &WorldData { gen_mode: GenMode::Void, ..WorldData::new() }).unwrap();
+		let damaged = b"not world data".to_vec();
+		std::fs::write(&file, &damaged).unwrap();
+
+		// The save goes through, the world data starts over with the running world's mode and the clocks
+		save_chunks(&world_dir, 0.3, 99, &GenMode::Void, &[], &[(ChunkCoord::ZERO, Chunk::new(2))]).unwrap();
+		assert_eq!(std::fs::read(backup_path(&file)).unwrap(), damaged);
+		let data = load_world_data(&world_dir).unwrap();
+		assert_eq!((data.gen_mode, data.play_time, data.time_of_day), (GenMode::Void, 99, 0.3));
+		assert!(region_file_path(&world_dir.join("region"), ChunkCoord::ZERO).exists());
+
+		// World data from a newer game is left alone and reported, the chunks are still written
+		let mut newer = std::fs::read(&file).unwrap();
+		newer[crate::fs::binary::MAGIC.len()] = u8::MAX;
+		std::fs::write(&file, &newer).unwrap();
+		let region = region_file_path(&world_dir.join("region"), ChunkCoord::ZERO);
+		let before = std::fs::read(&region).unwrap().len();
+		let error = save_chunks(&world_dir, 0.5, 120, &GenMode::Void, &[], &[(ChunkCoord::new(1, 0, 0), Chunk::new(3))]).unwrap_err();
+		assert_eq!(error.kind(), std::io::ErrorKind::Unsupported);
+		assert_eq!(std::fs::read(&file).unwrap(), newer);
+		assert!(std::fs::read(&region).unwrap().len() > before);
+		let _ = std::fs::remove_dir_all(&world_dir);
+	}
+
+	#[test]
+	fn file_header_versions_and_migrates() {
+		use crate::fs::binary::{self, Migration, FORMAT_VERSION, HEADER_SIZE, CHECKSUM_SIZE, UNVERSIONED};
+		use crate::world::manager::REGION_MIGRATIONS;
+		use std::io::ErrorKind;
+		let body = 3usize.to_binary(); // a region file starts with its chunk count
+
+		// Current files round trip, the header and checksum are a handful of bytes
+		let file = binary::write_versioned(&body);
+		assert_eq!(file.len(), body.len() + HEADER_SIZE + CHECKSUM_SIZE);
+		assert!(HEADER_SIZE + CHECKSUM_SIZE <= 12);
+		let mut reader = &file[..];
+		assert_eq!(binary::read_header(&mut reader).unwrap(), FORMAT_VERSION);
+		assert_eq!(&*binary::read_versioned(&file, &REGION_MIGRATIONS).unwrap(), &body[..]);
+
+		// A file from before the header is read from the start as the oldest version and upgraded step by step
+		let mut reader = &body[..];
+		assert_eq!(binary::read_header(&mut reader).unwrap(), UNVERSIONED);
+		assert_eq!(reader, &body[..]);
+		assert_eq!(&*binary::read_versioned(&body, &REGION_MIGRATIONS).unwrap(), &body[..]);
+		let mark: Migration = |data| Some([data, &b"+"[..]].concat());
+		let marks = [mark; FORMAT_VERSION as usize];
+		let upgraded = [&body[..], "+".repeat(FORMAT_VERSION as usize).as_bytes()].concat();
+		assert_eq!(&*binary::read_versioned(&body, &marks).unwrap(), upgraded.as_slice());
+		assert_eq!(&*binary::read_versioned(&file, &marks).unwrap(), &body[..]);
+		// No step to get there, or a step that fails, is an error and not garbage
+		assert_eq!(binary::read_versioned(&body, &[]).unwrap_err().kind(), ErrorKind::Unsupported);
+		let broken: Migration = |_| None;
+		assert_eq!(binary::read_versioned(&body, &[broken; FORMAT_VERSION as usize]).unwrap_err().kind(), ErrorKind::InvalidData);
+
+		// A newer game's file is refused, so it isn't misread or written over
+		let mut newer = binary::MAGIC.to_vec();
+		newer.extend_from_slice(&(FORMAT_VERSION + 1).to_le_bytes());
+		let error = binary::read_header(&mut &newer[..]).unwrap_err();
+		assert_eq!(error.kind(), ErrorKind::Unsupported);
+		assert!(error.to_string().contains("newer version"));
+		assert_eq!(binary::read_header(&mut &binary::MAGIC[..]).unwrap_err().kind(), ErrorKind::InvalidData);
+	}
+
+	#[test]
+	fn checksum_catches_any_flipped_bit() {
+		use crate::fs::binary::{self, CHECKSUM_SIZE};
+		use crate::world::manager::REGION_MIGRATIONS;
+		use std::io::ErrorKind;
+		// The known CRC-32 of the standard check string
+		assert_eq!(binary::crc32(b"123456789"), 0xCBF4_3926);
+
+		let mut data: Vec<u8> = (0..300u32).map(|i| (i * 7 % 251) as u8).collect();
+		let payload = data.clone();
+		binary::write_with_checksum(&mut data);
+		assert_eq!(data.len(), payload.len() + CHECKSUM_SIZE);
+		assert_eq!(binary::read_verified(&data).unwrap(), &payload[..]);
+
+		// Every single bit of the payload and of the checksum itself
+		for byte in 0..data.len() {
+			for bit in 0..8 {
+				let mut damaged = data.clone();
+				damaged[byte] ^= 1 << bit;
+				let error = binary::read_verified(&damaged).unwrap_err();
+				assert_eq!(error.kind(), ErrorKind::InvalidData);
+			}
+		}
+		assert!(binary::read_verified(&[1, 2]).is_err());
+
+		// A damaged versioned file is refused with a clear message rather than read
+		let mut file = binary::write_versioned(&payload);
+		file[40] ^= 0x10;
+		let error = binary::read_versioned(&file, &REGION_MIGRATIONS).unwrap_err();
+		assert!(error.to_string().contains("Checksum mismatch"));
+		// Cut to the header, there's nothing to check against
+		let header_only = binary::header();
+		assert!(binary::read_versioned(&header_only, &REGION_MIGRATIONS).is_err());
+	}
+
+	#[test]
+	fn resources_prefer_dev_files_and_fall_back() {
+		use crate::fs::resources;
+		let dir = std::env::temp_dir().join(format!("rusticubes_dev_resources_{}", std::process::id()));
+		std::fs::create_dir_all(&dir).unwrap();
+		std::fs::write(dir.join("ui_shader.wgsl"), "// edited").unwrap();
+		assert_eq!(resources::load_dev(&dir, "ui_shader.wgsl").unwrap(), b"// edited");
+		// Not in the dev folder, not an error, the embedded one is used instead
+		assert_eq!(resources::load_dev(&dir, "click.ogg"), None);
+		let _ = std::fs::remove_dir_all(&dir);
+
+		// The loose files are what got embedded, so both ways read the same
+		let shader = resources::embedded("ui_shader.wgsl").unwrap();
+		assert_eq!(resources::load("ui_shader.wgsl").unwrap(), shader);
+		assert!(resources::load_string("ui_shader.wgsl").unwrap().contains("fn"));
+		// Missing everywhere is None rather than a panic
+		assert_eq!(resources::load("no_such_file.wgsl"), None);
+		assert_eq!(resources::load_string("no_such_file.wgsl"), None);
+	}
+
+	#[test]
+	fn heightmap_export_matches_generated_terrain() {
+		use crate::utils::rng::Noise;
+		use glam::{IVec2, UVec2};
+		let (seed, origin, size) = (1234, IVec2::new(-40, 25), UVec2::new(24, 16));
+		let heights = Noise::heightmap(seed, origin, size);
+		assert_eq!(heights.len(), 24 * 16);
+		let biome_gen = BiomeGen::new(seed);
+		for (i, &h) in heights.iter().enumerate() {
+			let (x, z) = (origin.x + (i % 24) as i32, origin.y + (i / 24) as i32);
+			assert_eq!(h, Chunk::generated_surface(biome_gen.column(x, z).height));
+		}
+		assert_eq!(Chunk::generated_surface(-1000), -Chunk::SIZE_I - 1);
+		assert_eq!(Chunk::generated_surface(1000), 7 * Chunk::SIZE_I - 1);
+
+		let path = std::env::temp_dir().join(format!("rusticubes_heightmap_{}.png", std::process::id()));
+		let (lowest, highest) = Noise::export_heightmap(seed, origin, size, &path).unwrap();
+		let image = image::open(&path).unwrap().into_luma8();
+		let _ = std::fs::remove_file(&path);
+		assert_eq!(image.dimensions(), (24, 16));
+		assert_eq!((lowest, highest), (*heights.iter().min().unwrap(), *heights.iter().max().unwrap()));
+		// Gray levels keep the order of the heights
+		let (first, last) = (heights[0], heights[heights.len() - 1]);
+		let (g0, g1) = (image.get_pixel(0, 0)[0], image.get_pixel(23, 15)[0]);
+		assert_eq!(first.cmp(&last), g0.cmp(&g1));
+	}
+
+	#[test]
+	fn caves_carve_deterministically_across_chunks() {
+		use crate::{utils::rng::Noise, world::biome::{CaveGen, CaveParams}};
+		let noise = Noise::new(7);
+		assert_eq!(noise.noise_3d(1.3, -2.7, 5.5), Noise::new(7).noise_3d(1.3, -2.7, 5.5));
+		assert_ne!(noise.noise_3d(1.3, -2.7, 5.5), Noise::new(8).noise_3d(1.3, -2.7, 5.5));
+		assert_eq!(noise.noise_3d(3., -4., 5.), 0.);
+		for i in 0..1000 {
+			let n = noise.noise_3d(i as f32 * 0.37, i as f32 * -0.21, i as f32 * 0.13);
+			assert!(n.abs() <= 1.1, "{n}");
+		}
+
+		// Solid without caves, hollowed out exactly where the cave noise says so
+		let (seed, coord) = (99, ChunkCoord::new(2, -3, -1));
+		let (wx, wy, wz) = coord.unpack_to_worldpos();
+		assert!(Chunk::generate_with_caves(coord, seed, CaveParams::none()).is_full());
+		// The default floor is above this chunk with the big chunk size, so it goes down to the chunk bottom
+		let caves = CaveParams { density: 0.5, floor: wy, ..CaveParams::default() };
+		let chunk = Chunk::generate_with_caves(coord, seed, caves);
+		let cave_gen = CaveGen::new(seed, caves);
+		let mut carved = 0;
+		for i in 0..Chunk::VOLUME {
+			let pos = IVec3::from(LocalPos::from(i)) + IVec3::new(wx, wy, wz);
+			let air = chunk.get_block(i).is_empty();
+			assert_eq!(air, cave_gen.is_carved(pos.x, pos.y, pos.z, i32::MAX));
+			carved += air as usize;
+		}
+		assert!(carved > 0 && carved < Chunk::VOLUME);
+		// The neighbour carves the same world blocks, so the tunnels meet at the border
+		let side = Chunk::generate_with_caves(ChunkCoord::new(3, -3, -1), seed, caves);
+		for y in 0..Chunk::SIZE {
+			for z in 0..Chunk::SIZE {
+				let pos = IVec3::new(wx + Chunk::SIZE_I, wy + y as i32, wz + z as i32);
+				let air = side.get_block(usize::from(LocalPos::from((0, y, z)))).is_empty();
+				assert_eq!(air, cave_gen.is_carved(pos.x, pos.y, pos.z, i32::MAX));
+			}
+		}
+		// More density, more holes, and nothing below the floor
+		let sparse = CaveParams { density: 0.05, ..caves };
+		let sparse_chunk = Chunk::generate_with_caves(coord, seed, sparse);
+		let sparse_carved = (0..Chunk::VOLUME).filter(|&i| sparse_chunk.get_block(i).is_empty()).count();
+		assert!(sparse_carved < carved);
+		let floored = CaveParams { floor: wy + Chunk::SIZE_I, ..caves };
+		assert!(Chunk::generate_with_caves(coord, seed, floored).is_full());
+		// The roof stays solid under the surface
+		assert!(!cave_gen.is_carved(0, 10, 0, 12));
+	}
+
+	#[test]
+	fn seeded_rng_repeats_per_coordinate() {
+		use crate::utils::rng::SeededRng;
+		let sequence = |seed, x, y, z| {
+			let mut rng = SeededRng::from_coord(seed, x, y, z);
+			(0..16).map(|_| rng.next_u32()).collect::<Vec<_>>()
+		};
+		// Same answer whatever order and thread asks
+		let coords: Vec<(i32, i32, i32)> = (-4..4).flat_map(|x| (-4..4).map(move |z| (x, x * z, z))).collect();
+		let forward: Vec<_> = coords.iter().map(|&(x, y, z)| sequence(5, x, y, z)).collect();
+		let threaded: Vec<_> = std::thread::scope(|scope| {
+			let handles: Vec<_> = coords.iter().rev().map(|&(x, y, z)| scope.spawn(move || sequence(5, x, y, z))).collect();
+			handles.into_iter().map(|h| h.join().unwrap()).collect()
+		});
+		assert!(forward.iter().eq(threaded.iter().rev()));
+		// Neighbours, swapped coordinates and other seeds differ
+		assert_ne!(sequence(5, 1, 2, 3), sequence(5, 3, 2, 1));
+		assert_ne!(sequence(5, 1, 2, 3), sequence(5, 1, 2, 4));
+		assert_ne!(sequence(5, 1, 2, 3), sequence(6, 1, 2, 3));
+		assert_ne!(sequence(0, 0, 0, 0)[0], sequence(0, 0, 0, 0)[1]);
+
+		// Close to uniform: 10 buckets of 20000 draws each within 5% of the mean
+		let mut rng = SeededRng::from_coord(1, 2, 3, 4);
+		let mut buckets = [0u32; 10];
+		for _ in 0..200_000 {
+			let f = rng.next_f32();
+			assert!((0. ..1.).contains(&f));
+			buckets[(f * 10.) as usize] += 1;
+		}
+		assert!(buckets.iter().all(|&b| (19_000..21_000).contains(&b)), "{buckets:?}");
+		let mut seen = [false; 7];
+		for _ in 0..1000 {
+			let v = rng.next_range(-3, 4);
+			assert!((-3..4).contains(&v));
+			seen[(v + 3) as usize] = true;
+		}
+		assert!(seen.iter().all(|&s| s));
+		assert_eq!(rng.next_range(5, 5), 5);
+		assert!((i32::MIN..i32::MAX).contains(&rng.next_range(i32::MIN, i32::MAX)));
+		assert!((0..1000).all(|_| !rng.chance(0.) && rng.chance(1.)));
+		let hits = (0..10_000).filter(|_| rng.chance(0.25)).count();
+		assert!((2_300..2_700).contains(&hits), "{hits}");
+	}
+
+	#[test]
+	fn scope_timings_aggregate_per_name() {
+		use crate::ext::timer::{self, Buckets, ScopeTimer};
+		let mut buckets = Buckets::new();
+		for ms in [2., 4., 9.] { buckets.record("mesh build", ms); }
+		buckets.record("chunk gen", 1.5);
+		let mesh = buckets.get("mesh build").unwrap();
+		assert_eq!((mesh.min(), mesh.avg(), mesh.max(), mesh.count()), (2., 5., 9., 3));
+		assert_eq!(buckets.get("chunk gen").unwrap().count(), 1);
+		assert!(buckets.get("render").is_none());
+		// Sorted by name, names padded to line up
+		let lines = buckets.lines();
+		assert_eq!(lines.len(), 2);
+		assert!(lines[0].starts_with("chunk gen   min    1.500 ms"), "{}", lines[0]);
+		assert!(lines[1].starts_with("mesh build  min    2.000 ms  avg    5.000 ms  max    9.000 ms  x3"), "{}", lines[1]);
+		buckets.clear();
+		assert!(buckets.is_empty() && buckets.lines().is_empty());
+
+		// Nested timers fill their own buckets
+		{
+			let _outer = ScopeTimer::new("test outer");
+			let _inner = ScopeTimer::new("test inner");
+		}
+		let recorded = timer::buckets();
+		if cfg!(feature = "profiling") {
+			assert!(recorded.get("test inner").is_some() && recorded.get("test outer").is_some());
+		} else {
+			// Nothing kept and nothing to carry around
+			assert!(recorded.is_empty());
+			assert_eq!(std::mem::size_of::<ScopeTimer>(), 0);
+		}
+	}
+
+	#[test]
+	fn frame_times_track_percentiles_and_the_worst_recent_frame() {
+		use crate::ext::timer::{FrameTimes, FRAME_HISTORY};
+		let mut frames = FrameTimes::with_budget(16.6);
+		assert_eq!(frames.worst(), None);
+		assert_eq!(frames.percentile(99.), 0.);
+
+		// 1..=100 ms, then the nearest rank is exact
+		for ms in 1..=100 { frames.record(ms as f32 / 1000.); }
+		let summary = frames.summary();
+		assert!((summary.p50 - 50.).abs() < 1e-3 && (summary.p95 - 95.).abs() < 1e-3 && (summary.p99 - 99.).abs() < 1e-3);
+		assert!((frames.percentile(95.) - summary.p95).abs() < 1e-6);
+		assert_eq!(frames.over_budget(), 84);
+		let worst = frames.worst().unwrap();
+		assert!((worst.ms - 100.).abs() < 1e-3 && worst.frame == 100);
+		assert!(summary.worst_ago.abs() < 1e-6);
+
+		// The worst frame leaves the ring, the next slowest still in it takes over
+		frames.clear();
+		frames.record(0.080);
+		frames.record(0.050);
+		for _ in 0..FRAME_HISTORY - 2 { frames.record(0.010); }
+		assert_eq!(frames.len(), FRAME_HISTORY);
+		assert_eq!(frames.worst().unwrap().frame, 1);
+		frames.record(0.010);
+		assert_eq!(frames.len(), FRAME_HISTORY);
+		let worst = frames.worst().unwrap();
+		assert!((worst.ms - 50.).abs() < 1e-3 && worst.frame == 2);
+		assert!(((frames.clock() - worst.at) - (FRAME_HISTORY - 1) as f64 * 0.010).abs() < 1e-4);
+		assert_eq!(frames.over_budget(), 1);
+
+		for _ in 0..FRAME_HISTORY { frames.record(0.010); }
+		assert_eq!(frames.over_budget(), 0);
+		let summary = frames.summary();
+		assert!((summary.p99 - 10.).abs() < 1e-3 && (summary.worst_ms - 10.).abs() < 1e-3);
+	}
+
+	#[test]
+	fn gpu_trim_leaves_chunks_without_gpu_data_alone() {
+		use crate::ext::memory::{trim_chunk_gpu, GpuTrim};
+		let mut world = World::empty();
+		let loaded = ChunkCoord::new(0, 0, 0);
+		let unloaded = ChunkCoord::new(4, 0, 0);
+		world.set_chunk(loaded, Chunk::empty());
+		world.set_chunk(unloaded, Chunk::empty());
+		world.loaded_chunks.remove(&unloaded);
+		for chunk in world.chunks.values_mut() { chunk.dirty = false; chunk.final_mesh = true; }
+
+		// Nothing on the gpu yet, so nothing is freed and nothing needs a remesh
+		assert_eq!(trim_chunk_gpu(&mut world), GpuTrim::default());
+		assert_eq!(world.chunks.len(), 2);
+		assert!(world.chunks.values().all(|chunk| !chunk.dirty && chunk.final_mesh));
+		assert_eq!(world.mesh_pool.pending(), 0);
+	}
+
+	#[test]
+	fn render_distance_steps_the_load_radius_and_is_capped() {
+		use crate::{ext::settings::{RenderConfig, Settings}, fs::json::{JsonParser, JsonValue}, world::border::WorldBorder};
+		use glam::IVec2;
+		// A narrow border keeps the queue small, only a few columns can load
+		let mut world = World::empty();
+		world.border = Some(WorldBorder::new(IVec2::ZERO, 8));
+		let mut loaded = 0;
+		for radius in 1..=3 {
+			world.update_loaded_chunks(Vec3::ZERO, 3.);
+			assert_eq!(world.load_radius(), radius);
+			assert!(world.loaded_chunks.len() > loaded);
+			loaded = world.loaded_chunks.len();
+		}
+		world.update_loaded_chunks(Vec3::ZERO, 3.);
+		assert_eq!(world.loaded_chunks.len(), loaded);
+
+		// Way too far is capped, shrinking drops what is now out of range
+		for _ in 0..64 { world.update_loaded_chunks(Vec3::ZERO, 10_000.); }
+		assert_eq!(world.load_radius(), RenderConfig::MAX_RENDER_DISTANCE as i32);
+		world.update_loaded_chunks(Vec3::ZERO, 2.);
+		assert_eq!(world.load_radius(), RenderConfig::MAX_RENDER_DISTANCE as i32 - 1);
+		for _ in 0..64 { world.update_loaded_chunks(Vec3::ZERO, 2.); }
+		assert_eq!(world.load_radius(), 2);
+		assert!(!world.loaded_chunks.is_empty());
+		assert!(world.loaded_chunks.iter().all(|coord| {
+			let (x, y, z) = coord.unpack();
+			x * x + y * y + z * z <= 4
+		}));
+
+		// Saved with the render settings, clamped when read back
+		let mut settings = Settings::default();
+		settings.render_config.render_distance = 9;
+		let JsonValue::Object(root) = settings.to_json() else { panic!("settings are an object") };
+		let mut loaded = Settings::default();
+		assert!(!loaded.apply_json(&root));
+		assert_eq!(loaded.render_config.render_distance, 9);
+		let JsonValue::Object(root) = JsonParser::parse(r#"{"render": {"render_distance": 500}}"#).unwrap() else { panic!("an object") };
+		assert!(loaded.apply_json(&root));
+		assert_eq!(loaded.render_config.render_distance, RenderConfig::MAX_RENDER_DISTANCE);
+	}
+
+	#[test]
+	fn chunk_bytes_with_out_of_range_indices_are_refused() {
+		let air = Block::default();
+		let sparse = BlockStorage::Sparse { palette: vec![air, block(2)], indices: Box::new([1u8; Chunk::VOLUME]) };
+		let mut bytes = sparse.to_binary();
+		assert_eq!(BlockStorage::from_binary(&bytes).as_ref(), Some(&sparse));
+		*bytes.last_mut().unwrap() = 2;
+		assert_eq!(BlockStorage::from_binary(&bytes), None);
+		let mut bad_chunk = bytes;
+		bad_chunk.extend_from_slice(&Chunk::empty().entities().to_binary());
+
+		// A nibble past a two block palette, and a palette too big for nibbles
+		let compact = BlockStorage::Compact { palette: vec![air, block(2)], indices: Box::new([0x10u8; Chunk::VOLUME / 2]) };
+		let mut bytes = compact.to_binary();
+		assert!(BlockStorage::from_binary(&bytes).is_some());
+		*bytes.last_mut().unwrap() = 0x21;
+		assert_eq!(BlockStorage::from_binary(&bytes), None);
+		let wide = BlockStorage::Compact { palette: vec![air; 17], indices: Box::new([0u8; Chunk::VOLUME / 2]) };
+		assert_eq!(BlockStorage::from_binary(&wide.to_binary()), None);
+
+		// Runs have to cover the chunk exactly and point into the palette
+		let short = BlockStorage::Rle { palette: vec![air], runs: vec![(0, 255)] };
+		assert_eq!(BlockStorage::from_binary(&short.to_binary()), None);
+		let stray = BlockStorage::Rle { palette: vec![air], runs: vec![(1, 255); Chunk::VOLUME / 256] };
+		assert_eq!(BlockStorage::from_binary(&stray.to_binary()), None);
+		let full = BlockStorage::Rle { palette: vec![air], runs: vec![(0, 255); Chunk::VOLUME / 256] };
+		assert!(BlockStorage::from_binary(&full.to_binary()).is_some());
+
+		// A peer's bad chunk is an error and the world keeps what it had
+		let mut world = World::empty();
+		let coord = ChunkCoord::new(0, 0, 0);
+		world.set_chunk(coord, Chunk::new(2));
+		assert!(world.load_chunk_bytes(coord, &bad_chunk).is_err());
+		assert_eq!(world.get_chunk(&coord).unwrap().storage(), &BlockStorage::uniform(block(2)));
+	}
 }
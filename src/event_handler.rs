@@ -1,6 +1,6 @@
 
-use crate::utils::input::{Keyboard, ClickMode};
-use crate::ext::{ptr, memory};
+use crate::utils::input::{Action, Binding, Keyboard, ClickMode, InputDevice};
+use crate::ext::{audio::{self, Sound}, ptr, memory, settings::key_bindings, timer::{self, Section}};
 use crate::block::extra;
 use crate::ui::manager::{self, UIState};
 use crate::item::ui_inventory::InventoryUIState;
@@ -26,8 +26,8 @@ impl<'a> crate::State<'a> {
 			},
 			WindowEvent::RedrawRequested => {
 				self.window().request_redraw();
-				self.update();
-				match self.render() {
+				timer::time_section(Section::Update, || self.update());
+				match timer::time_section(Section::Render, || self.render()) {
 					Ok(_) => {},
 					Err(wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated) => {
 						self.resize(*self.size());
@@ -52,14 +52,8 @@ impl<'a> crate::State<'a> {
 				}
 			},
 			WindowEvent::ModifiersChanged(modifiers) => {
-				let change = self.input_system.compare(&modifiers.state());
+				// Locking the mouse is a bound action now, see `Action::MouseLock`
 				self.input_system.set_modifiers(modifiers.state());
-				if change.alt_key() {
-					if self.input_system.modifiers().alt_key() {
-						self.toggle_mouse_capture();
-					}
-					self.center_mouse();
-				}
 			},
 			WindowEvent::KeyboardInput { is_synthetic, event: winit::event::KeyEvent {
 					physical_key, state, // ElementState::Released or ElementState::Pressed
@@ -69,20 +63,39 @@ impl<'a> crate::State<'a> {
 				let winit::keyboard::PhysicalKey::Code(key) = physical_key else { println!("-WindowEvent-KeyboardInput error : no keyboard input"); return };
 				let is_pressed = *state == ElementState::Pressed;
 				let input_str = logical_key.to_text().unwrap_or("");
+				self.input_system.use_keyboard_mouse();
 				self.handle_key_input(*key, is_pressed, input_str);
 			},
-			WindowEvent::MouseInput { button, state, device_id: _ } => self.handle_mouse_input(button, state),
-			WindowEvent::CursorMoved { position, device_id: _ } => self.handle_mouse_movement(position),
-			WindowEvent::MouseWheel { delta, phase: _, device_id: _ } => self.handle_mouse_scroll(delta),
+			WindowEvent::MouseInput { button, state, device_id: _ } => {
+				self.input_system.use_keyboard_mouse();
+				self.handle_mouse_input(button, state);
+			},
+			WindowEvent::CursorMoved { position, device_id: _ } => {
+				self.input_system.cursor_moved(*position);
+				self.handle_mouse_movement(position);
+			},
+			WindowEvent::MouseWheel { delta, phase: _, device_id: _ } => {
+				self.input_system.use_keyboard_mouse();
+				self.handle_mouse_scroll(delta);
+			},
 			_ => {},
 		}
 	}
 	#[inline] fn can_handle_game_input(&self) -> bool {
-		self.is_world_running && ptr::get_gamestate().is_running()
+		self.is_world_running && ptr::get_gamestate().is_running() && !self.ui_manager.chat.is_open()
 	}
 	#[inline] pub fn handle_key_input(&mut self, key: KeyCode, is_pressed: bool, input_str: &str) {
 		self.input_system.handle_key_input(key, is_pressed);
-
+		// The controls screen is waiting for a key to bind
+		if is_pressed && self.ui_manager.capture_binding(Binding::Key(key)) { return }
+
+		// An open chat takes the whole keyboard, the player stops where it is
+		if self.ui_manager.chat.is_open() {
+			self.input_system.reset_keyboard();
+			ptr::get_gamestate().player_mut().controller_mut().process_keyboard(self.input_system.keyboard());
+			if is_pressed { self.ui_manager.handle_chat_key(key, input_str); }
+			return
+		}
 		// Handle UI input first if there's a focused element
 		if self.ui_manager.visibility {
 			if let Some(element) = self.ui_manager.get_focused_element() { // if focused you can't press Esc, have to handle them in a custom way
@@ -98,6 +111,12 @@ impl<'a> crate::State<'a> {
 				}
 			}
 		}
+		let action = key_bindings().action_of(Binding::Key(key));
+		// The mouse can be freed in the menus too
+		if action == Some(Action::MouseLock) {
+			self.handle_action(Action::MouseLock, is_pressed);
+			return
+		}
 		// Handle game controls if no UI element is focused
 		// `key` is of type `KeyCode` (e.g., KeyCode::W)
 		// `state` is of type `ElementState` (Pressed or Released)
@@ -105,13 +124,8 @@ impl<'a> crate::State<'a> {
 			if matches!(self.ui_manager.state, UIState::InGame)  {
 				ptr::get_gamestate().player_mut().controller_mut().process_keyboard(self.input_system.keyboard());
 			} // only handle player movement if not in inventory ...
+			if action.is_some_and(|action| self.handle_action(action, is_pressed)) { return }
 			match key {
-				KeyCode::KeyG => {
-					if !is_pressed { return }
-
-					extra::add_full_chunk();
-					return
-				},
 				k if k >= KeyCode::Digit1 && k <= KeyCode::Digit9 => {
 					if !is_pressed || !self.can_handle_game_input() { return }
 					
@@ -119,37 +133,18 @@ impl<'a> crate::State<'a> {
 					ptr::get_gamestate().player_mut().inventory_mut().select_slot(slot);
 					self.ui_manager.setup_ui();
 				},
-				KeyCode::KeyE => {
-					if !is_pressed { return }
-
-					match self.ui_manager.state.clone() {
-						UIState::Inventory(_) => self.close_inventory(),
-						UIState::InGame => {
-							self.transition_inventory_state(InventoryUIState::default());
-						}
-						_ => return,
+				KeyCode::KeyZ | KeyCode::KeyY => {
+					if !is_pressed || !matches!(self.ui_manager.state, UIState::InGame) { return }
+					let keyboard = self.input_system.keyboard();
+					if !keyboard.is_ctrl() { return }
+
+					// Ctrl+Z undoes, Ctrl+Y and Ctrl+Shift+Z redo
+					let world = ptr::get_gamestate().world_mut();
+					let redo = key == KeyCode::KeyY || keyboard.is_shift();
+					let done = if redo { world.redo() } else { world.undo() };
+					if !done {
+						println!("Nothing to {}", if redo { "redo" } else { "undo" });
 					}
-					self.ui_manager.setup_ui();
-					return
-				},
-				KeyCode::KeyR => {
-					if !is_pressed { return }
-
-					let game_state = &mut ptr::get_gamestate(); let play_mut = game_state.player_mut();
-
-					match self.ui_manager.state.clone() {
-						UIState::Inventory(_) => self.close_inventory(),
-						UIState::InGame => {
-							let slots = play_mut.inventory().get_crafting().slots();
-							self.transition_inventory_state(InventoryUIState::craft().input(slots).b());
-						}
-						_ => return,
-					}
-					let storage = play_mut.inventory_mut().get_crafting_mut();
-					let storage_ptr: *mut ItemContainer = storage;
-					play_mut.inventory_mut().storage_ptr = Some(storage_ptr);
-
-					self.ui_manager.setup_ui();
 					return
 				},
 				_ => { },
@@ -177,19 +172,74 @@ impl<'a> crate::State<'a> {
 				if !is_pressed || matches!(self.ui_manager.state, UIState::InGame) { return }
 
 				if self.ui_manager.visibility {
-					self.ui_manager.trigger_click_on_focused_element();
-					self.ui_manager.setup_ui();
+					self.ui_manager.activate_focused();
 				}
 				return
 			},
 			KeyCode::Tab => {
 				if !is_pressed || matches!(self.ui_manager.state, UIState::InGame) { return }
 
-				if self.ui_manager.visibility {
-					self.ui_manager.select_next_element();
+				if !self.ui_manager.visibility { return }
+				if self.input_system.keyboard().is_shift() {
+					self.ui_manager.focus_prev();
+				} else {
+					self.ui_manager.focus_next();
+				}
+				return
+			},
+			KeyCode::F3 => {
+				if !is_pressed { return }
+
+				// Shift+F3 draws the chunks as wireframe for debugging the meshing
+				if !self.input_system.keyboard().is_shift() {
+					self.ui_manager.toggle_debug_overlay();
+					return
+				}
+				let wireframe = !self.pipeline.is_wireframe();
+				if !self.pipeline.set_wireframe(wireframe) {
+					println!("Wireframe mode is not supported on this device");
 				}
 				return
 			},
+			KeyCode::F2 => {
+				if !is_pressed { return }
+
+				let timestamp = std::time::SystemTime::now()
+					.duration_since(std::time::UNIX_EPOCH)
+					.map_or(0, |time| time.as_millis());
+				let path = crate::world::manager::get_save_path()
+					.join("screenshots")
+					.join(format!("screenshot_{}.png", timestamp));
+				match self.capture_screenshot(&path) {
+					Ok(()) => println!("Saved screenshot to {}", path.display()),
+					Err(e) => println!("Failed to save screenshot: {}", e),
+				}
+				return
+			},
+			KeyCode::F5 => {
+				if !is_pressed { return }
+
+				// F5 reloads the world shaders, Shift+F5 the UI one; a broken shader keeps the old pipeline
+				let crate::RenderContext { device, surface_config, layouts, .. } = &self.render_context;
+				let (name, result) = if self.input_system.keyboard().is_shift() {
+					("UI", self.ui_manager.reload_shader(device, surface_config.format))
+				} else {
+					("World", self.pipeline.reload_shaders(device, surface_config, layouts))
+				};
+				match result {
+					Ok(()) => println!("{} shaders reloaded", name),
+					Err(e) => println!("{} shaders not reloaded, keeping the old ones: {}", name, e),
+				}
+				return
+			},
+			KeyCode::F7 => {
+				if !is_pressed { return }
+
+				// Scope timings since the last F7 (profiling builds only)
+				timer::report();
+				timer::reset();
+				return
+			},
 			KeyCode::F11 => {
 				if !is_pressed { return }
 
@@ -252,41 +302,131 @@ impl<'a> crate::State<'a> {
 		let (x, y) = convert_mouse_position(self.size(), &self.input_system.previous_mouse());
 		let pressed = *state == ElementState::Pressed;
 		self.input_system.handle_mouse_event(*button, pressed, *self.input_system.previous_mouse());
+		if pressed && self.ui_manager.capture_binding(Binding::Mouse(*button)) { return }
 		let mods = self.input_system.modifiers(); let keyboard = self.input_system.keyboard();
 
 		if self.ui_manager.visibility {
-			self.ui_manager.handle_mouse_click(x, y, pressed, mods, keyboard, ClickMode::from(*button));
+			self.ui_manager.handle_mouse_click(x, y, pressed, mods, keyboard, *self.input_system.click_mode());
 		}
-		match button {
-			MouseButton::Left => {
-				if pressed && self.input_system.is_mouse_captured() {
-					self.handle_lclick_interaction();
-					return
-				}
+		// Moving can be bound to a button too
+		if self.can_handle_game_input() && matches!(self.ui_manager.state, UIState::InGame) {
+			ptr::get_gamestate().player_mut().controller_mut().process_keyboard(self.input_system.keyboard());
+		}
+		// (the middle button could pick the block once there is a middle click interaction)
+		let Some(action) = key_bindings().action_of(Binding::Mouse(*button)) else { return };
+		if action == Action::MouseLock || self.can_handle_game_input() {
+			self.handle_action(action, pressed);
+		}
+	}
+	/// Reads the gamepad, runs once a frame. In game its stick and buttons work like the bound keys,
+	/// in the menus the left stick moves the cursor and jump clicks
+	pub fn handle_gamepad(&mut self, delta: f32) {
+		let poll = self.input_system.poll_gamepad(delta);
+		let device = *self.input_system.last_device();
+		self.ui_manager.set_prompt_device(device);
+		let in_game = self.can_handle_game_input() && matches!(self.ui_manager.state, UIState::InGame);
+		if in_game {
+			let controller = ptr::get_gamestate().player_mut().controller_mut();
+			controller.process_keyboard(self.input_system.keyboard());
+			if poll.look != glam::Vec2::ZERO { controller.process_mouse(poll.look.x, poll.look.y); }
+		}
+		// The mouse keeps the cursor until the gamepad is the one used last
+		if poll.cursor != glam::Vec2::ZERO && device == InputDevice::Gamepad {
+			let size = *self.size();
+			let pos = self.input_system.previous_mouse();
+			let position = PhysicalPosition::new(
+				(pos.x + (poll.cursor.x * size.width as f32) as f64).clamp(0., size.width as f64),
+				(pos.y + (poll.cursor.y * size.height as f32) as f64).clamp(0., size.height as f64),
+			);
+			self.handle_mouse_movement(&position);
+			self.input_system.set_pad_cursor(position);
+			if let Err(e) = self.window().set_cursor_position(position) { println!("error: {:?}", e); }
+		}
+		for (action, pressed) in poll.presses {
+			if action == Action::Jump && !self.input_system.is_mouse_captured() {
+				let state = if pressed { ElementState::Pressed } else { ElementState::Released };
+				self.handle_mouse_input(&MouseButton::Left, &state);
+			} else if action == Action::MouseLock || self.can_handle_game_input() {
+				self.handle_action(action, pressed);
+			}
+		}
+	}
+	/// Runs a bound action that happens once per press, true if it took the input.
+	/// Moving isn't handled here, the player reads the held keys
+	fn handle_action(&mut self, action: Action, is_pressed: bool) -> bool {
+		match action {
+			Action::MouseLock => {
+				if is_pressed { self.toggle_mouse_capture(); }
+				self.center_mouse();
 			},
-			MouseButton::Right => {
-				if pressed && self.input_system.is_mouse_captured() {
-					self.handle_rclick_interaction();
-					return
+			Action::Break => if is_pressed && self.input_system.is_mouse_captured() {
+				self.handle_lclick_interaction();
+			},
+			Action::Place => if is_pressed && self.input_system.is_mouse_captured() {
+				self.handle_rclick_interaction();
+			},
+			Action::Chat => {
+				if !is_pressed || !matches!(self.ui_manager.state, UIState::InGame) { return true }
+
+				self.input_system.reset_keyboard();
+				ptr::get_gamestate().player_mut().controller_mut().process_keyboard(self.input_system.keyboard());
+				self.ui_manager.open_chat();
+			},
+			Action::FillChunk => if is_pressed { extra::add_full_chunk(); },
+			Action::Fly => if is_pressed && matches!(self.ui_manager.state, UIState::InGame) {
+				let player = ptr::get_gamestate().player_mut();
+				player.set_fly(!player.is_flying());
+			},
+			Action::Inventory => {
+				if !is_pressed { return true }
+
+				match self.ui_manager.state.clone() {
+					UIState::Inventory(_) => self.close_inventory(),
+					UIState::InGame => {
+						self.transition_inventory_state(InventoryUIState::default());
+					}
+					_ => return true,
 				}
+				self.ui_manager.setup_ui();
 			},
-			MouseButton::Middle => {
-				if pressed && self.input_system.is_mouse_captured() {
-					//self.handle_mclick_interaction(); // will have to make a Middle click interaction too (for picking the block)
-					return
+			Action::Craft => {
+				if !is_pressed { return true }
+
+				let game_state = &mut ptr::get_gamestate(); let play_mut = game_state.player_mut();
+
+				match self.ui_manager.state.clone() {
+					UIState::Inventory(_) => self.close_inventory(),
+					UIState::InGame => {
+						let slots = play_mut.inventory().get_crafting().slots();
+						self.transition_inventory_state(InventoryUIState::craft().input(slots).b());
+					}
+					_ => return true,
 				}
+				let storage = play_mut.inventory_mut().get_crafting_mut();
+				let storage_ptr: *mut ItemContainer = storage;
+				play_mut.inventory_mut().storage_ptr = Some(storage_ptr);
+
+				self.ui_manager.setup_ui();
 			},
-			MouseButton::Back => {},
-			MouseButton::Forward => {},
-			MouseButton::Other(_) => {},
+			Action::Forward | Action::Backward | Action::Left | Action::Right |
+			Action::Jump | Action::Sneak | Action::Run => return false,
 		}
+		true
 	}
 	#[inline] pub fn handle_mouse_scroll(&mut self, delta: &MouseScrollDelta) {
+		let delta = match delta {
+			MouseScrollDelta::LineDelta(_, y) => y * -0.5,
+			MouseScrollDelta::PixelDelta(pos) => pos.y as f32 * -0.01,
+		}; // delta is reversed for some reason ... might need to look into it more (maybe it's different for platforms so yeah)
+		if self.ui_manager.scroll_chat(delta) { return }
+		if self.ui_manager.visibility && self.ui_manager.scroll_hovered_list(delta) { return }
 		if self.can_handle_game_input() {
-			let delta = match delta {
-				MouseScrollDelta::LineDelta(_, y) => y * -0.5,
-				MouseScrollDelta::PixelDelta(pos) => pos.y as f32 * -0.01,
-			}; // delta is reversed for some reason ... might need to look into it more (maybe it's different for platforms so yeah)
+			// While flying the wheel sets the speed, the number keys still pick the hotbar slot
+			let player = ptr::get_gamestate().player_mut();
+			if player.is_flying() && matches!(self.ui_manager.state, UIState::InGame) {
+				player.scale_fly_speed(-delta * 2.);
+				return
+			}
 			self.ui_manager.handle_scroll(delta);
 		}
 	}
@@ -328,6 +468,7 @@ use crate::item::inventory::ItemContainer;
 use crate::block::extra::*;
 use crate::block::math::ChunkCoord;
 use crate::block::main::{Block, Material};
+use crate::mods::{self, api::ModEvent};
 const CRAFTING_BLOCK:&str = "crafting";
 impl<'a> crate::State<'a> {
 	pub fn handle_rclick_interaction(&mut self) {
@@ -379,8 +520,11 @@ impl<'a> crate::State<'a> {
 
 		if !self.remove_selected_item_from_inv() { return false;};
 
-		world.set_block(placement_pos, Block::new(Material(block_id)));
+		let block = Block::new(Material(block_id));
+		world.set_block(placement_pos, block);
 		update_chunk_mesh(world, ChunkCoord::from_world_pos(placement_pos));
+		mods::api::dispatch_block(ModEvent::BlockPlace, placement_pos, block);
+		audio::play_at(Sound::BlockPlace, placement_pos.as_vec3() + 0.5);
 		true
 	}
 	fn handle_block_breaking(&mut self, player: &Player) -> bool {
@@ -396,15 +540,11 @@ impl<'a> crate::State<'a> {
 
 			inv_mut.add_item_anywhere(&mut ItemStack::new(item_name).with_stack_size(1));
 		}
-		if block.is_storage() { if let Some(storage) = world.get_storage_mut(block_pos) {
-			for item in storage.iter_mut() {
-				let Some(itm) = item else { continue };
-				inv_mut.add_item_anywhere(itm);
-			}
-		}}
-
+		// set_block hands back the storage contents, undo only brings the storage back empty
 		world.set_block(block_pos, Block::default());
 		update_chunk_mesh(world, ChunkCoord::from_world_pos(block_pos));
+		mods::api::dispatch_block(ModEvent::BlockBreak, block_pos, block);
+		audio::play_at(Sound::BlockBreak, block_pos.as_vec3() + 0.5);
 
 		true
 	}
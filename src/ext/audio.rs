@@ -1,169 +1,378 @@
-use crate::ptr::get_settings;
+use crate::ptr::{self as state, get_settings, has_settings};
 use crate::utils::rng;
-use rodio::{Sink, Decoder, OutputStream, source::Source};
+use glam::Vec3;
+use rodio::{Sink, Decoder, OutputStream, source::{ChannelVolume, Source}};
+use std::collections::{HashMap, VecDeque};
 use std::io::{Cursor, Write};
-use std::sync::atomic::{AtomicPtr, Ordering};
-use std::ptr;
+use std::sync::{Arc, Condvar, Mutex, mpsc};
+use std::thread::JoinHandle;
 
-/// Audio system structure with multiple sinks
-struct AudioSystem {
-	bg_sink: Sink,         // For background music
-	fg_sink: Sink,         // For UI sounds
-	_stream: OutputStream, // Keep stream alive
+// The output stream and the sinks live on their own thread, everything here only queues events for it.
+// That way a slow device or a big file never holds up the frame, and the audio thread never reads the settings.
+
+/// The volume channels, every sink plays at its own channel times master
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Channel {
+	Master,
+	Music,
+	Sfx,
 }
 
-static AUDIO_SYSTEM_PTR: AtomicPtr<AudioSystem> = AtomicPtr::new(ptr::null_mut());
+/// The one-shot sounds, the block ones reuse the click at a lower pitch until they get their own files
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sound {
+	Click,
+	BlockBreak,
+	BlockPlace,
+}
 
-/// Helper function to safely access the AudioSystem pointer
-#[inline]
-fn get_audio_system() -> Option<&'static mut AudioSystem> {
-	let system_ptr = AUDIO_SYSTEM_PTR.load(Ordering::Acquire);
-	if system_ptr.is_null() {
-		None
-	} else {
-		unsafe { Some(&mut *system_ptr) }
+impl Sound {
+	#[inline] pub const fn path(self) -> &'static str {
+		match self {
+			Self::Click | Self::BlockBreak | Self::BlockPlace => "click.ogg",
+		}
+	}
+	#[inline] pub const fn pitch(self) -> f32 {
+		match self {
+			Self::Click => 1.,
+			Self::BlockBreak => 0.6,
+			Self::BlockPlace => 0.8,
+		}
 	}
 }
 
-/// Initialize the audio system
-#[inline]
-pub fn init_audio() -> Result<(), Box<dyn std::error::Error>> {
-	let (stream, stream_handle) = OutputStream::try_default()?;
-	let music_settings = &get_settings().music_settings;
-	
-	// Create separate sinks for different audio types
-	let bg_sink = Sink::try_new(&stream_handle)?;
-	let fg_sink = Sink::try_new(&stream_handle)?;
-	
-	// Set different volume levels for each sink
-	bg_sink.set_volume(music_settings.bg_volume.val * music_settings.main_volume.val); 
-	fg_sink.set_volume(music_settings.fg_volume.val * music_settings.main_volume.val); 
-	
-	let system = Box::new(AudioSystem {
-		bg_sink,
-		fg_sink,
-		_stream: stream,
-	});
-	
-	let old_ptr = AUDIO_SYSTEM_PTR.swap(Box::into_raw(system), Ordering::AcqRel);
-	if !old_ptr.is_null() {
-		unsafe { let _ = Box::from_raw(old_ptr); }
+/// How many effects can ring at once, past that the quietest one is cut (or the new one dropped if it's quieter still)
+const SFX_VOICES: usize = 8;
+/// Past this many blocks a sound in the world isn't heard
+pub const HEARING_RANGE: f32 = 32.;
+/// Closer than this a sound plays at full volume
+const FULL_VOLUME_DISTANCE: f32 = 2.;
+/// Quieter than this a sound isn't worth a voice
+const MIN_AUDIBLE: f32 = 0.02;
+/// How far to one side a sound right beside the listener goes, the other ear still hears some of it
+const MAX_PAN: f32 = 0.7;
+
+/// Where the world sounds are heard from
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Listener {
+	pub pos: Vec3, // the camera
+	pub right: Vec3, // towards the right ear
+}
+
+/// Volume and balance (-1 left .. 1 right) of a sound at `pos`, the balance stays 0 without panning
+pub fn spatialize(listener: Listener, pos: Vec3, pan: bool) -> (f32, f32) {
+	let offset = pos - listener.pos;
+	let distance = offset.length();
+	let fade = ((distance - FULL_VOLUME_DISTANCE) / (HEARING_RANGE - FULL_VOLUME_DISTANCE)).clamp(0., 1.);
+	let gain = (1. - fade) * (1. - fade);
+	let balance = if pan && distance > f32::EPSILON { offset.dot(listener.right) / distance * MAX_PAN } else { 0. };
+	(gain, balance)
+}
+
+/// Left and right volume of a balance, the near side stays at full volume
+#[inline] pub fn channel_gains(balance: f32) -> [f32; 2] {
+	let balance = balance.clamp(-1., 1.);
+	[(1. - balance).min(1.), (1. + balance).min(1.)]
+}
+
+/// The voice for a sound this loud: a free one, else the quietest if it is quieter than the sound.
+/// `voices` holds the loudness of what each one plays, None when free
+pub fn pick_voice(voices: &[Option<f32>], loudness: f32) -> Option<usize> {
+	if let Some(free) = voices.iter().position(Option::is_none) { return Some(free); }
+	let (index, quietest) = voices.iter().enumerate()
+		.filter_map(|(index, voice)| voice.map(|loudness| (index, loudness)))
+		.min_by(|a, b| a.1.total_cmp(&b.1))?;
+	(quietest < loudness).then_some(index)
+}
+
+/// What the game asks of the audio thread, the levels and speeds are worked out before queueing
+#[derive(Debug, Clone, PartialEq)]
+pub enum AudioEvent {
+	PlayOneShot { sound: Sound, gain: f32, balance: f32, speed: f32 },
+	SetMusic { path: String, speed: f32 },
+	SetVolume { music: f32, sfx: f32 },
+	StopAll,
+}
+
+impl AudioEvent {
+	/// A sound that can be missed, dropped first when the queue is full
+	#[inline] pub const fn is_low_priority(&self) -> bool {
+		matches!(self, Self::PlayOneShot { .. })
 	}
-	
-	Ok(())
 }
 
-/// Set a new background sound with looping - falls back to terminal ping on error
-pub fn set_bg<T: Into<String>>(path: T) {
-	let path = path.into();
-	
-	match get_audio_system() {
-		Some(system) => {
-			system.bg_sink.stop();
-			
-			match try_play_bg_sound(path, system) {
-				Ok(_duration) => {
-					// Successfully started background audio
-				}
-				Err(_) => {
-					// Failed to play audio, use terminal ping as fallback
-					play_terminal_ping();
-				}
-			}
+/// How many events can wait for the audio thread
+const QUEUE_CAPACITY: usize = 64;
+
+/// A bounded queue that never makes the game wait: when full the oldest sound is dropped for the new event
+pub struct EventQueue {
+	state: Mutex<QueueState>,
+	ready: Condvar,
+	capacity: usize,
+}
+
+struct QueueState {
+	events: VecDeque<AudioEvent>,
+	open: bool, // something takes the events, closed ones drop what is pushed
+}
+
+impl EventQueue {
+	#[inline] pub const fn new(capacity: usize) -> Self {
+		Self { state: Mutex::new(QueueState { events: VecDeque::new(), open: false }), ready: Condvar::new(), capacity }
+	}
+	#[inline] fn lock(&self) -> std::sync::MutexGuard<'_, QueueState> {
+		self.state.lock().unwrap_or_else(|e| e.into_inner())
+	}
+	#[inline] pub fn open(&self) { self.lock().open = true; }
+	#[inline] pub fn is_open(&self) -> bool { self.lock().open }
+	#[inline] pub fn len(&self) -> usize { self.lock().events.len() }
+	#[inline] pub fn is_empty(&self) -> bool { self.len() == 0 }
+
+	/// Adds an event, handing back the one that got dropped for it (maybe itself).
+	/// Stopping everything also drops the sounds still waiting
+	pub fn push(&self, event: AudioEvent) -> Option<AudioEvent> {
+		let mut state = self.lock();
+		if !state.open { return Some(event); }
+		if event == AudioEvent::StopAll { state.events.retain(|e| !e.is_low_priority()); }
+		let mut dropped = None;
+		if state.events.len() >= self.capacity {
+			let oldest = state.events.iter().position(AudioEvent::is_low_priority);
+			dropped = match oldest {
+				Some(index) => state.events.remove(index),
+				// Only controls waiting, a new sound is the one to go, a newer control overrides the oldest
+				None if event.is_low_priority() => return Some(event),
+				None => state.events.pop_front(),
+			};
 		}
-		None => {
-			// Audio system not initialized, use terminal ping
-			play_terminal_ping();
+		state.events.push_back(event);
+		self.ready.notify_one();
+		dropped
+	}
+	/// The next event without waiting
+	#[inline] pub fn pop(&self) -> Option<AudioEvent> { self.lock().events.pop_front() }
+
+	/// The next event, waiting for one, None once closed and drained
+	fn wait(&self) -> Option<AudioEvent> {
+		let mut state = self.lock();
+		loop {
+			if let Some(event) = state.events.pop_front() { return Some(event); }
+			if !state.open { return None; }
+			state = self.ready.wait(state).unwrap_or_else(|e| e.into_inner());
 		}
 	}
+	/// Stops taking events, the sounds still waiting are dropped (no point starting them now) and the rest handed out
+	pub fn close(&self) {
+		let mut state = self.lock();
+		state.open = false;
+		state.events.retain(|e| !e.is_low_priority());
+		self.ready.notify_all();
+	}
+}
+
+static QUEUE: EventQueue = EventQueue::new(QUEUE_CAPACITY);
+static AUDIO_THREAD: Mutex<Option<JoinHandle<()>>> = Mutex::new(None);
+
+/// Hands an event to the audio thread without waiting on it, nothing happens before `init_audio`
+#[inline] pub fn queue_event(event: AudioEvent) {
+	match QUEUE.push(event) {
+		Some(dropped) if QUEUE.is_open() => println!("Audio queue full, dropped {:?}", dropped),
+		_ => {}
+	}
+}
+
+/// One effect sink and how loud what it plays is
+struct Voice {
+	sink: Sink,
+	loudness: f32,
 }
 
-/// Play a one-time UI sound - falls back to terminal ping on error
-pub fn set_fg<T: Into<String>>(path: T) {
-	let path = path.into();
-	
-	match get_audio_system() {
-		Some(system) => {
-			system.fg_sink.stop();
-			
-			match try_play_fg_sound(path, system) {
-				Ok(_duration) => {
-					// Successfully played foreground sound
+/// Audio system structure with multiple sinks, owned by the audio thread
+struct AudioSystem {
+	bg_sink: Sink,         // For background music
+	voices: Vec<Voice>,    // One per effect playing, so they layer over each other and the music
+	sounds: HashMap<&'static str, Arc<[u8]>>, // Effect files read once, not on every play
+	_stream: OutputStream, // Keep stream alive
+}
+
+impl AudioSystem {
+	fn new(music: f32, sfx: f32) -> Result<Self, Box<dyn std::error::Error>> {
+		let (stream, stream_handle) = OutputStream::try_default()?;
+		
+		// Create separate sinks for different audio types
+		let bg_sink = Sink::try_new(&stream_handle)?;
+		let voices = (0..SFX_VOICES)
+			.map(|_| Sink::try_new(&stream_handle).map(|sink| Voice { sink, loudness: 0. }))
+			.collect::<Result<Vec<_>, _>>()?;
+		
+		let system = Self { bg_sink, voices, sounds: HashMap::new(), _stream: stream };
+		system.set_volumes(music, sfx);
+		Ok(system)
+	}
+
+	fn handle(&mut self, event: AudioEvent) {
+		match event {
+			AudioEvent::PlayOneShot { sound, gain, balance, speed } => {
+				if let Err(e) = self.try_play_fg_sound(sound, gain, balance, speed) {
+					println!("Failed to play {:?}: {}", sound, e);
 				}
-				Err(_) => {
+			}
+			AudioEvent::SetMusic { path, speed } => {
+				self.bg_sink.stop();
+				if self.try_play_bg_sound(path, speed).is_err() {
 					// Failed to play audio, use terminal ping as fallback
 					play_terminal_ping();
 				}
 			}
-		}
-		None => {
-			// Audio system not initialized, use terminal ping
-			play_terminal_ping();
+			AudioEvent::SetVolume { music, sfx } => self.set_volumes(music, sfx),
+			AudioEvent::StopAll => self.stop_all(),
 		}
 	}
+
+	/// Frees a voice for a sound this loud, None if every voice plays something louder
+	fn voice(&mut self, loudness: f32) -> Option<usize> {
+		let playing: Vec<_> = self.voices.iter().map(|v| (!v.sink.empty()).then_some(v.loudness)).collect();
+		let index = pick_voice(&playing, loudness)?;
+		let voice = &mut self.voices[index];
+		voice.sink.stop();
+		voice.loudness = loudness;
+		Some(index)
+	}
+	#[inline] fn sound_bytes(&mut self, path: &'static str) -> Arc<[u8]> {
+		self.sounds.entry(path).or_insert_with(|| crate::get_bytes!(path).into()).clone()
+	}
+	/// What is playing keeps playing at the new volume
+	fn set_volumes(&self, music: f32, sfx: f32) {
+		self.bg_sink.set_volume(music);
+		self.voices.iter().for_each(|voice| voice.sink.set_volume(sfx));
+	}
+	fn stop_all(&self) {
+		self.bg_sink.stop();
+		self.voices.iter().for_each(|voice| voice.sink.stop());
+	}
+
+	/// Play background sound looping
+	fn try_play_bg_sound(&mut self, path: String, speed: f32) -> Result<std::time::Duration, Box<dyn std::error::Error>> {
+		let sound_bytes = crate::get_bytes!(path);
+		let cursor = Cursor::new(sound_bytes);
+		let source = Decoder::new(cursor)?;
+		
+		let original_duration = source.total_duration()
+			.unwrap_or(std::time::Duration::from_secs(1));
+		
+		let source = source.speed(speed);
+		let duration = original_duration.div_f32(speed);
+		
+		// Apply looping and append to background sink
+		let source: Box<dyn Source<Item = i16> + Send> = 
+			Box::new(source.repeat_infinite());
+		
+		self.bg_sink.append(source);
+		
+		Ok(duration)
+	}
+
+	/// Play a sound effect on a voice of its own
+	fn try_play_fg_sound(&mut self, sound: Sound, gain: f32, balance: f32, speed: f32) -> Result<std::time::Duration, Box<dyn std::error::Error>> {
+		// Culled before decoding, a busy moment shouldn't cost more than it plays
+		let Some(voice) = self.voice(gain) else { return Ok(std::time::Duration::ZERO) };
+		let cursor = Cursor::new(self.sound_bytes(sound.path()));
+		let source = Decoder::new(cursor)?;
+		
+		let original_duration = source.total_duration()
+			.unwrap_or(std::time::Duration::from_secs(1));
+		
+		let source = source.speed(speed);
+		let duration = original_duration.div_f32(speed);
+		
+		// No looping for foreground sounds, centered ones keep their own channels
+		let source = source.amplify(gain);
+		let source: Box<dyn Source<Item = i16> + Send> = if balance == 0. {
+			Box::new(source)
+		} else {
+			Box::new(ChannelVolume::new(source, channel_gains(balance).to_vec()))
+		};
+		
+		self.voices[voice].sink.append(source);
+		
+		Ok(duration)
+	}
 }
 
-/// Play background sound with speed and randomization settings
-#[inline]
-fn try_play_bg_sound(
-	path: String, 
-	system: &mut AudioSystem
-) -> Result<std::time::Duration, Box<dyn std::error::Error>> {
-	let sound_bytes = crate::get_bytes!(path);
-	let cursor = Cursor::new(sound_bytes);
-	let source = Decoder::new(cursor)?;
-	
-	let original_duration = source.total_duration()
-		.unwrap_or(std::time::Duration::from_secs(1));
+/// Starts the audio thread, waits only for the device to open
+pub fn init_audio() -> Result<(), Box<dyn std::error::Error>> {
+	let music_settings = &get_settings().music_settings;
+	let (music, sfx) = (music_settings.mixed_volume(Channel::Music), music_settings.mixed_volume(Channel::Sfx));
+	let (started_tx, started_rx) = mpsc::channel();
+	let thread = std::thread::Builder::new().name("audio".into()).spawn(move || {
+		let mut system = match AudioSystem::new(music, sfx) {
+			Ok(system) => { let _ = started_tx.send(Ok(())); system }
+			Err(e) => { let _ = started_tx.send(Err(e.to_string())); return; }
+		};
+		while let Some(event) = QUEUE.wait() { system.handle(event); }
+		system.stop_all();
+	})?;
+	started_rx.recv()??;
+	QUEUE.open();
+	if let Some(old) = AUDIO_THREAD.lock().unwrap_or_else(|e| e.into_inner()).replace(thread) {
+		let _ = old.join();
+	}
+	Ok(())
+}
+
+/// Sets a channel's volume (kept in the settings so it is saved with them), playing sounds change right away
+pub fn set_volume(channel: Channel, level: f32) {
+	if !has_settings() { return; }
+	let music_settings = &mut get_settings().music_settings;
+	music_settings.set_volume(channel, level);
+	queue_event(AudioEvent::SetVolume {
+		music: music_settings.mixed_volume(Channel::Music),
+		sfx: music_settings.mixed_volume(Channel::Sfx),
+	});
+}
 
+/// Set a new background sound with looping - the audio thread pings the terminal if it can't play it
+pub fn set_bg<T: Into<String>>(path: T) {
 	let music_settings = &get_settings().music_settings;
 	let speed = calculate_playback_speed(
 		music_settings.bg_speed.val * music_settings.main_speed.val,
 		music_settings.use_random,
 		music_settings.random_value
 	);
-	
-	let source = source.speed(speed);
-	let duration = original_duration.div_f32(speed);
-	
-	// Apply looping and append to background sink
-	let source: Box<dyn Source<Item = i16> + Send> = 
-		Box::new(source.repeat_infinite());
-	
-	system.bg_sink.append(source);
-	
-	Ok(duration)
-}
-
-/// Play foreground sound with speed and randomization settings
-#[inline]
-fn try_play_fg_sound(
-	path: String, 
-	system: &mut AudioSystem, 
-) -> Result<std::time::Duration, Box<dyn std::error::Error>> {
-	let sound_bytes = crate::get_bytes!(path);
-	let cursor = Cursor::new(sound_bytes);
-	let source = Decoder::new(cursor)?;
-	
-	let original_duration = source.total_duration()
-		.unwrap_or(std::time::Duration::from_secs(1));
+	queue_event(AudioEvent::SetMusic { path: path.into(), speed });
+}
+
+/// Play a sound effect at full volume (UI sounds)
+#[inline] pub fn play_sfx(sound: Sound) {
+	play(sound, 1., 0.);
+}
+
+/// Play a sound effect from a spot in the world, quieter the farther it is and panned to its side.
+/// With no world running there is no one to hear it from, so it plays like a UI sound
+pub fn play_at(sound: Sound, pos: Vec3) {
+	let (gain, balance) = match listener() {
+		Some(listener) => spatialize(listener, pos, has_settings() && get_settings().music_settings.positional_pan),
+		None => (1., 0.),
+	};
+	if gain < MIN_AUDIBLE { return; }
+	play(sound, gain, balance);
+}
 
+/// The camera of the player, None outside a world
+fn listener() -> Option<Listener> {
+	if !state::has_gamestate() { return None; }
+	let player = state::get_gamestate().player();
+	Some(Listener { pos: player.cam_pos(), right: player.camera().right() })
+}
+
+fn play(sound: Sound, gain: f32, balance: f32) {
+	if !has_settings() { return; }
 	let music_settings = &get_settings().music_settings;
 	let speed = calculate_playback_speed(
-		music_settings.fg_speed.val * music_settings.main_speed.val,
+		music_settings.fg_speed.val * music_settings.main_speed.val * sound.pitch(),
 		music_settings.use_random,
 		music_settings.random_value
 	);
-	
-	let source = source.speed(speed);
-	let duration = original_duration.div_f32(speed);
-	
-	// No looping for foreground sounds
-	let source: Box<dyn Source<Item = i16> + Send> = Box::new(source);
-	
-	system.fg_sink.append(source);
-	
-	Ok(duration)
+	queue_event(AudioEvent::PlayOneShot { sound, gain, balance, speed });
 }
 
 /// Calculate playback speed with optional randomization
@@ -184,47 +393,18 @@ fn play_terminal_ping() {
 	let _ = std::io::stdout().flush();
 }
 
-// Volume control functions
-#[inline]
-pub fn set_bg_volume(volume: f32) {
-	if let Some(system) = get_audio_system() {
-		system.bg_sink.set_volume(volume);
-	}
-}
-
-#[inline]
-pub fn set_fg_volume(volume: f32) {
-	if let Some(system) = get_audio_system() {
-		system.fg_sink.set_volume(volume);
-	}
-}
-
-// Playback control functions
-#[inline]
-pub fn stop_bg() {
-	if let Some(system) = get_audio_system() {
-		system.bg_sink.stop();
-	}
-}
-
-#[inline]
-pub fn stop_fg() {
-	if let Some(system) = get_audio_system() {
-		system.fg_sink.stop();
-	}
-}
-
+/// Stops the music and every effect, along with the effects still queued
 #[inline]
 pub fn stop_all_sounds() {
-	stop_bg();
-	stop_fg();
+	queue_event(AudioEvent::StopAll);
 }
 
-/// Clean up audio system resources
+/// Closes the queue and waits for the audio thread to finish what is left and let go of the device
 #[inline]
 pub fn cleanup_audio() {
-	let system_ptr = AUDIO_SYSTEM_PTR.swap(ptr::null_mut(), Ordering::AcqRel);
-	if !system_ptr.is_null() {
-		unsafe { let _ = Box::from_raw(system_ptr); }
+	QUEUE.close();
+	let thread = AUDIO_THREAD.lock().unwrap_or_else(|e| e.into_inner()).take();
+	if let Some(thread) = thread {
+		let _ = thread.join();
 	}
 }
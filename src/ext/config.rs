@@ -1,7 +1,9 @@
 ﻿
 use crate::Vec3;
 use crate::item::inventory::AreaType;
-use crate::utils::color::{Color, Border};
+use crate::utils::color::{Color, Border, Palette};
+use crate::fs::json::{self, JsonError, JsonParser, JsonSerializable, JsonValue};
+use std::{borrow::Cow, collections::HashMap, path::PathBuf};
 
 
 // note that these are currently offsets from real pos, might change them to actual pos later on
@@ -69,7 +71,7 @@ impl CameraConfig {
 // Extra is optional since not all elements need it
 // The text color is optional since it is only used for like half of the cases
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct ElementStyle {
 	pub color: Color,
 	pub border: Border,
@@ -96,7 +98,7 @@ impl ElementStyle {
 	}
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct VariantStyles {
 	pub basic: ElementStyle,
 	pub nice: ElementStyle,
@@ -104,13 +106,39 @@ pub struct VariantStyles {
 	pub extra: Option<ElementStyle>, 
 }
 impl VariantStyles {
+	pub const NAMES: [&'static str; 4] = ["basic", "nice", "bad", "extra"];
+
 	#[inline] pub fn extra(&self) -> ElementStyle {
 		self.extra.clone().unwrap_or(self.basic.clone())
 	}
+	/// A variant by name, None for an unset extra
+	pub fn get(&self, variant: &str) -> Option<ElementStyle> {
+		match variant {
+			"basic" => Some(self.basic),
+			"nice" => Some(self.nice),
+			"bad" => Some(self.bad),
+			"extra" => self.extra,
+			_ => None,
+		}
+	}
+	/// A variant to change, an unset extra starts out as basic
+	pub fn get_mut(&mut self, variant: &str) -> Option<&mut ElementStyle> {
+		match variant {
+			"basic" => Some(&mut self.basic),
+			"nice" => Some(&mut self.nice),
+			"bad" => Some(&mut self.bad),
+			"extra" => {
+				let basic = self.basic;
+				Some(self.extra.get_or_insert(basic))
+			}
+			_ => None,
+		}
+	}
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct UITheme {
+	pub name: Cow<'static, str>, // a built-in one or the custom file it was loaded from, saved in the settings
 	pub bg_panel: ElementStyle,
 	pub title_label: ElementStyle,
 	pub best_button: ElementStyle,
@@ -207,6 +235,7 @@ impl InvConfig {
 impl UITheme {
 	#[inline] pub const fn default() -> Self {		
 		Self {
+			name: Cow::Borrowed("dark"),
 			bg_panel: ElementStyle {
 				color: Color::rgb(15, 15, 25),
 				border: Border::NONE, // No border for background
@@ -378,3 +407,208 @@ impl UITheme {
 		}
 	}
 }
+
+impl UITheme {
+	/// The themes that come with the game, the first one is the default
+	pub const BUILT_IN: [&'static str; 3] = ["dark", "light", "high_contrast"];
+	const SINGLES: [&'static str; 6] = ["bg_panel", "title_label", "best_button", "worst_button", "okay_button", "deny_button"];
+	const GROUPS: [&'static str; 7] = ["buttons", "panels", "labels", "images", "checkboxs", "sliders", "inputs"];
+
+	pub fn built_in(name: &str) -> Option<Self> {
+		match name {
+			"dark" => Some(Self::default()),
+			"light" => Some(Self::from_palette("light", &Palette::LIGHT)),
+			"high_contrast" => Some(Self::from_palette("high_contrast", &Palette::HIGH_CONTRAST)),
+			_ => None,
+		}
+	}
+
+	/// Every style spread out of a few colors, labels get the surface color so their text is checked against what they sit on
+	pub fn from_palette(name: &'static str, p: &Palette) -> Self {
+		let (thin, thick) = (p.border_width, p.border_width * 1.6);
+		let style = |color: Color, border: Border, text: Color| ElementStyle { color, border, text_color: Some(text) };
+		let plain = |color: Color, border: Border| ElementStyle { color, border, text_color: None };
+		// Extra is written out as basic, so a theme file saved from this one has every style
+		let variants = |basic: ElementStyle, nice, bad| VariantStyles { basic, nice, bad, extra: Some(basic) };
+		let framed = Border::colf(p.border, thin);
+		let highlighted = Border::colf(p.accent, thick);
+		let warning = Border::colf(p.bad, thin);
+		Self {
+			name: Cow::Borrowed(name),
+			bg_panel: plain(p.background, Border::NONE),
+			title_label: style(p.surface, highlighted, p.text),
+			best_button: style(p.control, highlighted, p.text),
+			worst_button: style(p.bad, framed, p.on_color),
+			okay_button: style(p.good, framed, p.on_color),
+			deny_button: style(p.bad, framed, p.on_color),
+			buttons: variants(style(p.control, framed, p.text), style(p.control, highlighted, p.text), style(p.bad, framed, p.on_color)),
+			panels: variants(plain(p.surface, framed), plain(p.surface, highlighted), plain(p.background, warning)),
+			labels: variants(style(p.surface, Border::NONE, p.text), style(p.surface, framed, p.text), style(p.surface, framed, p.muted_text)),
+			images: variants(plain(Color::WHITE, Border::NONE), plain(Color::WHITE, highlighted), plain(Color::WHITE, framed)),
+			checkboxs: variants(style(p.control, framed, p.text), style(p.control, highlighted, p.text), style(p.bad, framed, p.on_color)),
+			sliders: variants(style(p.control, framed, p.text), style(p.control, highlighted, p.text), style(p.bad, framed, p.on_color)),
+			inputs: variants(style(p.control, framed, p.text), style(p.control, highlighted, p.text), style(p.bad, framed, p.on_color)),
+		}
+	}
+
+	/// Every style key (`"bg_panel"`, `"buttons.basic"` ..), the ones `style` and `style_mut` take
+	pub fn keys() -> impl Iterator<Item = String> {
+		Self::SINGLES.into_iter().map(str::to_string)
+			.chain(Self::GROUPS.into_iter().flat_map(|group| VariantStyles::NAMES.into_iter().map(move |variant| format!("{}.{}", group, variant))))
+	}
+	fn single(&self, name: &str) -> Option<ElementStyle> {
+		match name {
+			"bg_panel" => Some(self.bg_panel),
+			"title_label" => Some(self.title_label),
+			"best_button" => Some(self.best_button),
+			"worst_button" => Some(self.worst_button),
+			"okay_button" => Some(self.okay_button),
+			"deny_button" => Some(self.deny_button),
+			_ => None,
+		}
+	}
+	fn group(&self, name: &str) -> Option<&VariantStyles> {
+		match name {
+			"buttons" => Some(&self.buttons),
+			"panels" => Some(&self.panels),
+			"labels" => Some(&self.labels),
+			"images" => Some(&self.images),
+			"checkboxs" => Some(&self.checkboxs),
+			"sliders" => Some(&self.sliders),
+			"inputs" => Some(&self.inputs),
+			_ => None,
+		}
+	}
+	fn single_mut(&mut self, name: &str) -> Option<&mut ElementStyle> {
+		match name {
+			"bg_panel" => Some(&mut self.bg_panel),
+			"title_label" => Some(&mut self.title_label),
+			"best_button" => Some(&mut self.best_button),
+			"worst_button" => Some(&mut self.worst_button),
+			"okay_button" => Some(&mut self.okay_button),
+			"deny_button" => Some(&mut self.deny_button),
+			_ => None,
+		}
+	}
+	fn group_mut(&mut self, name: &str) -> Option<&mut VariantStyles> {
+		match name {
+			"buttons" => Some(&mut self.buttons),
+			"panels" => Some(&mut self.panels),
+			"labels" => Some(&mut self.labels),
+			"images" => Some(&mut self.images),
+			"checkboxs" => Some(&mut self.checkboxs),
+			"sliders" => Some(&mut self.sliders),
+			"inputs" => Some(&mut self.inputs),
+			_ => None,
+		}
+	}
+	/// A style by key, None for an unknown key or an unset extra
+	pub fn style(&self, key: &str) -> Option<ElementStyle> {
+		match key.split_once('.') {
+			Some((group, variant)) => self.group(group)?.get(variant),
+			None => self.single(key),
+		}
+	}
+	/// A style to change by key, an unset extra starts out as its basic
+	pub fn style_mut(&mut self, key: &str) -> Option<&mut ElementStyle> {
+		match key.split_once('.') {
+			Some((group, variant)) => self.group_mut(group)?.get_mut(variant),
+			None => self.single_mut(key),
+		}
+	}
+
+	/// A style by key like `style`, but an unset extra is its basic like `VariantStyles::extra` gives
+	pub fn resolve(&self, key: &str) -> Option<ElementStyle> {
+		self.style(key).or_else(|| self.style(&key.replace(".extra", ".basic")))
+	}
+
+	/// How an element styled with `key` from this theme looks in `to`.
+	/// Frames keep their width relative to the style's, None (left as is) for an unknown key
+	pub fn restyle(&self, to: &Self, key: &str, border: Border) -> Option<(Color, Border, Color)> {
+		let (from, style) = (self.resolve(key)?, to.resolve(key)?);
+		let width = if from.border.width > 0. { border.width * style.border.width / from.border.width } else { style.border.width };
+		Some((style.color, Border::colf(style.border.color, width), style.text_color()))
+	}
+
+	/// Where custom themes are kept, one json file each named after the theme
+	pub fn dir() -> PathBuf {
+		crate::world::manager::get_save_path().join("themes")
+	}
+	/// The built-in themes and then the custom ones found in `dir`
+	pub fn available() -> Vec<String> {
+		let mut custom: Vec<String> = std::fs::read_dir(Self::dir()).into_iter().flatten().flatten()
+			.map(|entry| entry.path())
+			.filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+			.filter_map(|path| Some(path.file_stem()?.to_str()?.to_string()))
+			.filter(|name| !Self::BUILT_IN.contains(&name.as_str()))
+			.collect();
+		custom.sort();
+		Self::BUILT_IN.iter().map(|name| name.to_string()).chain(custom).collect()
+	}
+	/// A built-in theme or a custom one from `dir`, None (and a note why) if there is no such theme or it can't be read
+	pub fn load(name: &str) -> Option<Self> {
+		if let Some(theme) = Self::built_in(name) { return Some(theme); }
+		let text = json::read_json_file(&Self::dir().join(format!("{}.json", name)))?;
+		match JsonParser::parse(&text).and_then(|value| Self::from_json(&value)) {
+			Ok(theme) => Some(Self { name: Cow::Owned(name.to_string()), ..theme }),
+			Err(e) => {
+				println!("Theme {} not loaded: {}", name, e);
+				None
+			}
+		}
+	}
+}
+
+/// `{"name": "mine", "base": "light", "styles": {"buttons.basic": {"color": "#283250FF", "border": "#..", "border_width": 0.005, "text_color": "#.."}}}`,
+/// whatever is left out keeps the base theme's (dark without one), unknown styles are skipped
+impl JsonSerializable for UITheme {
+	fn from_json(value: &JsonValue) -> Result<Self, JsonError> {
+		let obj = value.as_object().ok_or(JsonError::Custom("Theme is not an object".into()))?;
+		let base = obj.get("base").and_then(|v| v.as_str()).unwrap_or(Self::BUILT_IN[0]);
+		let mut theme = Self::built_in(base).ok_or_else(|| JsonError::Custom(format!("No built-in theme called {}", base).into()))?;
+		theme.name = Cow::Owned(obj.get("name").and_then(|v| v.as_str()).unwrap_or("custom").to_string());
+		let Some(styles) = obj.get("styles") else { return Ok(theme); };
+		let styles = styles.as_object().ok_or(JsonError::Custom("Theme styles are not an object".into()))?;
+		for (key, entries) in styles {
+			let Some(style) = theme.style_mut(key) else {
+				println!("Unknown theme style {} skipped", key);
+				continue;
+			};
+			let entries = entries.as_object().ok_or_else(|| JsonError::Custom(format!("Theme style {} is not an object", key).into()))?;
+			let color = |name: &str| match entries.get(name) {
+				None => Ok(None),
+				Some(value) => value.as_str().and_then(Color::from_hex).map(Some)
+					.ok_or_else(|| JsonError::Custom(format!("{}.{} is not a hex color", key, name).into())),
+			};
+			if let Some(c) = color("color")? { style.color = c; }
+			if let Some(c) = color("border")? { style.border.color = c; }
+			if let Some(c) = color("text_color")? { style.text_color = Some(c); }
+			if let Some(width) = entries.get("border_width") {
+				let width = width.as_f64().filter(|w| (0. ..=0.1).contains(w))
+					.ok_or_else(|| JsonError::Custom(format!("{}.border_width is not between 0 and 0.1", key).into()))?;
+				style.border.width = width as f32;
+			}
+		}
+		Ok(theme)
+	}
+
+	/// Every style written out, so a saved theme is a whole starting point for a custom one
+	fn to_json(&self) -> JsonValue {
+		let styles: HashMap<String, JsonValue> = Self::keys()
+			.filter_map(|key| {
+				let style = self.style(&key)?;
+				let mut entries = HashMap::from([
+					("color".to_string(), JsonValue::String(style.color.to_hex())),
+					("border".to_string(), JsonValue::String(style.border.color.to_hex())),
+					("border_width".to_string(), JsonValue::Number(style.border.width as f64)),
+				]);
+				if let Some(text) = style.text_color { entries.insert("text_color".to_string(), JsonValue::String(text.to_hex())); }
+				Some((key, JsonValue::Object(entries)))
+			})
+			.collect();
+		JsonValue::Object(HashMap::from([
+			("name".to_string(), JsonValue::String(self.name.to_string())),
+			("styles".to_string(), JsonValue::Object(styles)),
+		]))
+	}
+}
@@ -1,4 +1,5 @@
 
+use crate::{ext::ptr::{get_gamestate, has_gamestate}, world::main::World};
 use std::alloc::{alloc, dealloc, Layout};
 use std::ptr;
 
@@ -55,10 +56,42 @@ pub fn hard_clean(device: Option<&wgpu::Device>) {
 
 	// Clean GPU memory if device provided
 	if let Some(device) = device {
+		if has_gamestate() { trim_chunk_gpu(get_gamestate().world_mut()); }
 		device.poll(wgpu::Maintain::Wait);
 	}
 }
 
+/// What `trim_chunk_gpu` gave back
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct GpuTrim {
+	pub meshes: usize,
+	pub bind_groups: usize,
+	pub bytes: u64, // instance buffer sizes of the meshes
+}
+
+/// Drops the meshes and bind groups of chunks kept around without being loaded.
+/// Meshes are discarded through the pool, which holds them until the frame that may still draw them is submitted
+/// and then drops them instead of keeping them for reuse, wgpu keeps a dropped bind group alive until the submissions using it are done.
+/// The chunks are left clean, `make_chunk_meshes` only builds loaded chunks so nothing comes back until they load again
+pub fn trim_chunk_gpu(world: &mut World) -> GpuTrim {
+	let mut trim = GpuTrim::default();
+	let World { chunks, loaded_chunks, mesh_pool, .. } = world;
+	for (coord, chunk) in chunks.iter_mut() {
+		if loaded_chunks.contains(coord) { continue; }
+		if let Some(mesh) = chunk.take_mesh() {
+			trim.meshes += 1;
+			trim.bytes += mesh.instance_buffer.size();
+			mesh_pool.discard(mesh);
+		}
+		if chunk.bind_group().is_some() {
+			chunk.set_bind_group(None);
+			trim.bind_groups += 1;
+		}
+	}
+	println!("GPU trim: {} meshes ({} KB) and {} bind groups of unloaded chunks dropped", trim.meshes, trim.bytes / 1024, trim.bind_groups);
+	trim
+}
+
 /// Universal memory pressure technique
 fn fast_pressure(mb: usize) {
 	const BLOCK_SIZE: usize = 1_048_576; // 1MB
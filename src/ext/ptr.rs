@@ -23,6 +23,9 @@ pub static SETTINGS: AtomicPtr<Settings> = AtomicPtr::new(ptr::null_mut());
 		unsafe { let _ = Box::from_raw(old_ptr); }
 	}
 }
+#[inline] pub fn has_settings() -> bool {
+	!SETTINGS.load(Ordering::Acquire).is_null()
+}
 // Helper function to safely access the Settings pointer
 #[inline] pub fn get_settings() -> &'static mut Settings {
 	let ptr = SETTINGS.load(Ordering::Acquire);
@@ -49,6 +52,16 @@ pub fn get_state() -> &'static mut State<'static> {
 	}
 	unsafe { &mut *ptr }
 }
+/// Whether the window state (and so the gpu) is up, worlds in tests run without it
+#[inline]
+pub fn has_state() -> bool {
+	!STATE_PTR.load(Ordering::Acquire).is_null()
+}
+/// Whether a game (and so a world) is running, check it before `get_gamestate` where a panic is not an option
+#[inline]
+pub fn has_gamestate() -> bool {
+	!GAMESTATE_PTR.load(Ordering::Acquire).is_null()
+}
 #[inline]
 pub fn get_gamestate() -> &'static mut GameState {
 	let ptr = GAMESTATE_PTR.load(Ordering::Acquire);
@@ -1,5 +1,9 @@
 
-use crate::ext::config::{InvLayout, UITheme, InvConfig};
+use crate::ext::{audio::Channel, config::{InvLayout, UITheme, InvConfig}, ptr};
+use crate::block::main::Chunk;
+use crate::fs::json::{self, JsonError, JsonParser, JsonSerializable, JsonValue, read_json_file};
+use crate::utils::input::{Action, Binding};
+use std::{collections::HashMap, io::Write, path::{Path, PathBuf}};
 
 
 /// I implement manual default for this even if it is useless
@@ -11,6 +15,14 @@ pub struct Settings {
 	pub inv_config: InvConfig,
 	pub inv_layout: InvLayout,
 	pub music_settings: MusiConfig,
+	pub render_config: RenderConfig,
+	pub save_config: SaveConfig,
+	pub mod_config: ModConfig,
+	pub net_config: NetConfig,
+	pub key_bindings: KeyBindings,
+	pub mouse_config: MouseConfig,
+	pub gamepad_config: GamepadConfig,
+	pub fov_config: FovConfig,
 }
 impl Settings {
 	#[inline] pub const fn default() -> Self {
@@ -21,11 +33,264 @@ impl Settings {
 			inv_layout: InvLayout::default(),
 
 			music_settings: MusiConfig::default(),
+			render_config: RenderConfig::default(),
+			save_config: SaveConfig::default(),
+			mod_config: ModConfig::default(),
+			net_config: NetConfig::default(),
+			key_bindings: KeyBindings::default(),
+			mouse_config: MouseConfig::default(),
+			gamepad_config: GamepadConfig::default(),
+			fov_config: FovConfig::default(),
 		}
 	}
 	#[inline] pub fn remake_window_config(&mut self, size: winit::dpi::PhysicalSize<u32>) {
 		self.window_config = WindowConfig::new(size);
 	}
+
+	/// Where the settings are kept between runs (the key bindings have their own file)
+	pub fn path() -> PathBuf {
+		crate::world::manager::get_save_path().join("settings.json")
+	}
+
+	/// The saved settings over the defaults, out of range values clamped.
+	/// A file that isn't settings at all is kept next to it (`.bad`) and replaced by the defaults, a fixed one is written back
+	pub fn load_or_default(path: &Path) -> Self {
+		let mut settings = Self::default();
+		let problem = match std::fs::read_to_string(path) {
+			Ok(text) => match JsonParser::parse(&text) {
+				Ok(JsonValue::Object(root)) => {
+					if settings.apply_json(&root) {
+						println!("Some settings were out of range and got fixed");
+						settings.save_logged(path);
+					}
+					return settings;
+				}
+				Ok(_) => "not an object".to_string(),
+				Err(e) => e.to_string(),
+			},
+			Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+				settings.save_logged(path);
+				return settings;
+			}
+			Err(e) if e.kind() == std::io::ErrorKind::InvalidData => "not text".to_string(),
+			// Can't be read right now (permissions, locked), it may well be fine so it is left alone
+			Err(e) => {
+				println!("Settings not read: {}", e);
+				return settings;
+			}
+		};
+		println!("Settings file is broken ({}), the defaults are used", problem);
+		let backup = backup_path(path);
+		if let Err(e) = std::fs::rename(path, &backup) {
+			println!("Broken settings not kept: {}", e);
+		}
+		settings.save_logged(path);
+		settings
+	}
+
+	/// Writes a temporary file and renames it over the old one, a crash halfway leaves the old file whole
+	pub fn save(&self, path: &Path) -> std::io::Result<()> {
+		if let Some(parent) = path.parent() { std::fs::create_dir_all(parent)?; }
+		let mut temp = path.as_os_str().to_owned();
+		temp.push(crate::world::manager::TEMP_FILE_SUFFIX);
+		{
+			let mut file = std::fs::File::create(&temp)?;
+			file.write_all(json::to_string_pretty(&self.to_json(), 4).as_bytes())?;
+			file.sync_all()?;
+		}
+		std::fs::rename(temp, path)
+	}
+	fn save_logged(&self, path: &Path) {
+		if let Err(e) = self.save(path) { println!("Settings not saved: {}", e); }
+	}
+
+	/// The player facing settings as sections, the window isn't saved and the theme only by name
+	pub fn to_json(&self) -> JsonValue {
+		let music = &self.music_settings;
+		let render = &self.render_config;
+		let sections = [
+			("audio", section([
+				("main_volume", music.main_volume.val.to_json()),
+				("bg_volume", music.bg_volume.val.to_json()),
+				("fg_volume", music.fg_volume.val.to_json()),
+				("use_random", music.use_random.to_json()),
+				("random_value", music.random_value.to_json()),
+				("positional_pan", music.positional_pan.to_json()),
+			])),
+			("render", section([
+				("greedy_meshing", render.greedy_meshing.to_json()),
+				("frustum_culling", render.frustum_culling.to_json()),
+				("lod_distance", render.lod_distance.to_json()),
+				("mipmaps", render.mipmaps.to_json()),
+				("anisotropy", render.anisotropy.to_json()),
+				("animation_fps", render.animation_fps.to_json()),
+				("msaa_samples", render.msaa_samples.to_json()),
+				("bloom", render.bloom.to_json()),
+				("bloom_threshold", render.bloom_threshold.to_json()),
+				("bloom_intensity", render.bloom_intensity.to_json()),
+				("render_distance", render.render_distance.to_json()),
+			])),
+			("save", section([
+				("autosave_interval", self.save_config.autosave_interval.to_json()),
+				("keep_flying", self.save_config.keep_flying.to_json()),
+			])),
+			("net", section([
+				("connect_timeout", self.net_config.connect_timeout.to_json()),
+				("connect_retries", self.net_config.connect_retries.to_json()),
+			])),
+			("mouse", section([
+				("sensitivity", self.mouse_config.mouse_sensitivity.to_json()),
+				("invert_y", self.mouse_config.invert_y.to_json()),
+			])),
+			("fov", section([
+				("fov", self.fov_config.fov.to_json()),
+				("sprint_boost", self.fov_config.sprint_boost.to_json()),
+				("transition_speed", self.fov_config.transition_speed.to_json()),
+			])),
+			("ui", section([
+				("theme", JsonValue::String(self.ui_theme.name.to_string())),
+			])),
+			("gamepad", section([
+				("left_deadzone", self.gamepad_config.left_deadzone.to_json()),
+				("right_deadzone", self.gamepad_config.right_deadzone.to_json()),
+				("look_speed", self.gamepad_config.look_speed.to_json()),
+				("cursor_speed", self.gamepad_config.cursor_speed.to_json()),
+			])),
+		];
+		JsonValue::Object(sections.into_iter().map(|(name, value)| (name.to_string(), value)).collect())
+	}
+
+	/// Takes what it knows from the file, true if something was missing the mark and got fixed.
+	/// Unknown sections and entries (from a newer version) are skipped, missing ones keep their value
+	pub fn apply_json(&mut self, root: &HashMap<String, JsonValue>) -> bool {
+		let mut fixed = false;
+
+		let mut audio = Section::of(root, "audio");
+		let music = &mut self.music_settings;
+		for (name, range) in [("main_volume", &mut music.main_volume), ("bg_volume", &mut music.bg_volume), ("fg_volume", &mut music.fg_volume)] {
+			audio.num(name, &mut range.val, range.min, range.max);
+		}
+		audio.flag("use_random", &mut music.use_random);
+		audio.num("random_value", &mut music.random_value, 0., 0.5);
+		audio.flag("positional_pan", &mut music.positional_pan);
+		fixed |= audio.fixed;
+
+		let mut render = Section::of(root, "render");
+		let config = &mut self.render_config;
+		render.flag("greedy_meshing", &mut config.greedy_meshing);
+		render.flag("frustum_culling", &mut config.frustum_culling);
+		render.whole("lod_distance", &mut config.lod_distance, 0, 32);
+		render.flag("mipmaps", &mut config.mipmaps);
+		let mut anisotropy = config.anisotropy as u32;
+		render.whole("anisotropy", &mut anisotropy, 1, 16);
+		// Only powers of two are valid for the sampler
+		config.anisotropy = 1 << anisotropy.ilog2();
+		fixed |= config.anisotropy as u32 != anisotropy;
+		render.num("animation_fps", &mut config.animation_fps, 1., 60.);
+		render.whole("msaa_samples", &mut config.msaa_samples, 1, 16);
+		render.flag("bloom", &mut config.bloom);
+		render.num("bloom_threshold", &mut config.bloom_threshold, 0., 1.);
+		render.num("bloom_intensity", &mut config.bloom_intensity, 0., 4.);
+		render.whole("render_distance", &mut config.render_distance, RenderConfig::MIN_RENDER_DISTANCE, RenderConfig::MAX_RENDER_DISTANCE);
+		fixed |= render.fixed;
+
+		let mut save = Section::of(root, "save");
+		save.num("autosave_interval", &mut self.save_config.autosave_interval, 0., 3600.);
+		save.flag("keep_flying", &mut self.save_config.keep_flying);
+		fixed |= save.fixed;
+
+		let mut net = Section::of(root, "net");
+		net.num("connect_timeout", &mut self.net_config.connect_timeout, 1., 120.);
+		let mut retries = self.net_config.connect_retries as u32;
+		net.whole("connect_retries", &mut retries, 0, 10);
+		self.net_config.connect_retries = retries as u8;
+		fixed |= net.fixed;
+
+		let mut mouse = Section::of(root, "mouse");
+		mouse.num("sensitivity", &mut self.mouse_config.mouse_sensitivity, MouseConfig::MIN_SENSITIVITY, MouseConfig::MAX_SENSITIVITY);
+		mouse.flag("invert_y", &mut self.mouse_config.invert_y);
+		fixed |= mouse.fixed;
+
+		let mut fov = Section::of(root, "fov");
+		fov.num("fov", &mut self.fov_config.fov, FovConfig::MIN_FOV, FovConfig::MAX_FOV);
+		fov.num("sprint_boost", &mut self.fov_config.sprint_boost, 0., 30.);
+		fov.num("transition_speed", &mut self.fov_config.transition_speed, 0.5, 30.);
+		fixed |= fov.fixed;
+
+		let mut gamepad = Section::of(root, "gamepad");
+		gamepad.num("left_deadzone", &mut self.gamepad_config.left_deadzone, 0., 0.9);
+		gamepad.num("right_deadzone", &mut self.gamepad_config.right_deadzone, 0., 0.9);
+		gamepad.num("look_speed", &mut self.gamepad_config.look_speed, 50., 5000.);
+		gamepad.num("cursor_speed", &mut self.gamepad_config.cursor_speed, 0.1, 5.);
+		fixed |= gamepad.fixed;
+
+		// A theme that is gone (a deleted custom file) falls back to the default
+		let mut ui = Section::of(root, "ui");
+		if let Some(name) = ui.text("theme") {
+			match UITheme::load(name) {
+				Some(theme) => self.ui_theme = theme,
+				None => ui.fixed = true,
+			}
+		}
+		fixed |= ui.fixed;
+
+		fixed
+	}
+}
+
+/// Where a broken file is kept, `settings.json` -> `settings.json.bad`
+pub fn backup_path(path: &Path) -> PathBuf {
+	let mut backup = path.as_os_str().to_owned();
+	backup.push(".bad");
+	PathBuf::from(backup)
+}
+
+#[inline] fn section<const N: usize>(entries: [(&str, JsonValue); N]) -> JsonValue {
+	JsonValue::Object(entries.into_iter().map(|(name, value)| (name.to_string(), value)).collect())
+}
+
+/// One section of the settings file, read leniently: a missing entry keeps its value,
+/// a mistyped one too (but counts as fixed), numbers out of range are clamped
+struct Section<'a> {
+	entries: Option<&'a HashMap<String, JsonValue>>,
+	fixed: bool,
+}
+impl<'a> Section<'a> {
+	fn of(root: &'a HashMap<String, JsonValue>, name: &str) -> Self {
+		let value = root.get(name);
+		Self { entries: value.and_then(|v| v.as_object()), fixed: value.is_some_and(|v| v.as_object().is_none()) }
+	}
+	fn get(&self, name: &str) -> Option<&'a JsonValue> {
+		self.entries.and_then(|entries| entries.get(name))
+	}
+	fn num(&mut self, name: &str, value: &mut f32, min: f32, max: f32) {
+		let Some(entry) = self.get(name) else { return; };
+		match entry.as_f64().map(|n| n as f32).filter(|n| n.is_finite()) {
+			Some(n) => {
+				*value = n.clamp(min, max);
+				self.fixed |= *value != n;
+			}
+			None => self.fixed = true,
+		}
+	}
+	fn whole(&mut self, name: &str, value: &mut u32, min: u32, max: u32) {
+		let mut n = *value as f32;
+		self.num(name, &mut n, min as f32, max as f32);
+		self.fixed |= n.fract() != 0.;
+		*value = n.round() as u32;
+	}
+	fn text(&mut self, name: &str) -> Option<&'a str> {
+		let entry = self.get(name)?;
+		self.fixed |= entry.as_str().is_none();
+		entry.as_str()
+	}
+	fn flag(&mut self, name: &str, value: &mut bool) {
+		let Some(entry) = self.get(name) else { return; };
+		match entry.as_bool() {
+			Some(b) => *value = b,
+			None => self.fixed = true,
+		}
+	}
 }
 
 pub struct RangeConfig {
@@ -47,6 +312,7 @@ pub struct MusiConfig {
 	pub use_random: bool, // sometimes i heard that randomizing pitch and speed from 0.9 to 1.1 is nicer to the ear than playing the same sound repeatedly
 	// so this is the config for that (setting the random_value to 0.1 will give the mentioned results)
 	pub random_value: f32,
+	pub positional_pan: bool, // world sounds lean to the ear on their side
 
 	pub bg_volume: RangeConfig,
 	pub fg_volume: RangeConfig,
@@ -63,6 +329,7 @@ impl MusiConfig {
 			main_speed: RangeConfig::new(0.3, 1., 2.),
 			use_random: true,
 			random_value: 0.1,
+			positional_pan: true,
 
 			bg_volume: RangeConfig::new(0., 0.5, 2.), // Lower volume for background music
 			fg_volume: RangeConfig::new(0., 0.7, 2.), // Higher volume for UI sounds
@@ -71,6 +338,297 @@ impl MusiConfig {
 			bg_music: "background_music.ogg",
 		}
 	}
+	/// The slider of a channel, music is the background and effects the foreground
+	#[inline] pub const fn volume(&self, channel: Channel) -> &RangeConfig {
+		match channel {
+			Channel::Master => &self.main_volume,
+			Channel::Music => &self.bg_volume,
+			Channel::Sfx => &self.fg_volume,
+		}
+	}
+	/// Sets a channel's level, kept in its range
+	pub fn set_volume(&mut self, channel: Channel, level: f32) {
+		let range = match channel {
+			Channel::Master => &mut self.main_volume,
+			Channel::Music => &mut self.bg_volume,
+			Channel::Sfx => &mut self.fg_volume,
+		};
+		range.set(level.clamp(range.min, range.max));
+	}
+	/// What a sink of that channel plays at, master scales the other two
+	#[inline] pub fn mixed_volume(&self, channel: Channel) -> f32 {
+		match channel {
+			Channel::Master => self.main_volume.val,
+			_ => self.volume(channel).val * self.main_volume.val,
+		}
+	}
+}
+
+
+/// Rendering related toggles, mostly here so things can be A/B tested at runtime
+pub struct RenderConfig {
+	pub greedy_meshing: bool, // merge coplanar faces into bigger quads, false falls back to one quad per face
+	pub frustum_culling: bool, // skip chunks outside the camera view, false draws everything in render distance
+	pub lod_distance: u32, // chunks closer than this to the player are always full detail, each further step is meshed coarser (0 turns LOD off)
+	pub mipmaps: bool, // smaller copies of the block textures for far away blocks, false keeps them pixel sharp (and shimmering)
+	pub anisotropy: u8, // block sampler anisotropic filtering (1, 2, 4, 8 or 16), sharper ground at grazing angles
+	pub animation_fps: f32, // frames per second of animated block textures that don't set their own rate
+	pub msaa_samples: u32, // multisample anti-aliasing of the world (1 is off), unsupported counts use the nearest supported one
+	pub bloom: bool, // glow around bright parts of the world, off skips the whole bloom chain
+	pub bloom_threshold: f32, // brightness (0-1) a pixel needs before it starts to glow
+	pub bloom_intensity: f32, // how strong the glow is added back on top
+	pub render_distance: u32, // radius in chunks loaded around the player
+}
+impl RenderConfig {
+	pub const MIN_RENDER_DISTANCE: u32 = 2;
+	/// A sphere this big is already ~140k chunks, more would run out of memory
+	pub const MAX_RENDER_DISTANCE: u32 = 32;
+	/// The render distance in blocks, what culling, fog and streaming go by
+	#[inline] pub const fn view_distance(&self) -> f32 {
+		(self.render_distance * Chunk::SIZE as u32) as f32
+	}
+	#[inline] pub const fn default() -> Self {
+		Self {
+			greedy_meshing: true,
+			frustum_culling: true,
+			lod_distance: 4,
+			mipmaps: true,
+			anisotropy: 1,
+			animation_fps: 8.,
+			msaa_samples: 4,
+			bloom: false,
+			bloom_threshold: 0.8,
+			bloom_intensity: 0.6,
+			render_distance: 16,
+		}
+	}
+}
+
+
+/// Saving of the running world
+pub struct SaveConfig {
+	pub autosave_interval: f32, // seconds between auto-saves of the changed chunks (0 turns auto-saving off)
+	pub keep_flying: bool, // flying is saved with the player, otherwise every session starts walking
+}
+impl SaveConfig {
+	#[inline] pub const fn default() -> Self {
+		Self {
+			autosave_interval: 60.,
+			keep_flying: false,
+		}
+	}
+}
+
+
+/// Limits of the wasm mods
+pub struct ModConfig {
+	pub fuel_per_call: u64, // wasm instructions a mod may run per call before it traps and gets disabled
+}
+impl ModConfig {
+	#[inline] pub const fn default() -> Self {
+		Self {
+			fuel_per_call: crate::mods::api::MOD_FUEL,
+		}
+	}
+}
+
+
+/// Joining other players' games
+pub struct NetConfig {
+	pub connect_timeout: f32, // seconds a join may take before it is tried again (or given up)
+	pub connect_retries: u8, // extra tries after the first one times out or fails
+}
+impl NetConfig {
+	#[inline] pub const fn default() -> Self {
+		Self {
+			connect_timeout: 10.,
+			connect_retries: 2,
+		}
+	}
+}
+
+
+/// How the mouse turns the camera, read every frame so changes work at once
+pub struct MouseConfig {
+	pub mouse_sensitivity: f32, // multiplies the camera's own sensitivity, for yaw and pitch alike
+	pub invert_y: bool, // moving the mouse up looks down
+}
+impl MouseConfig {
+	pub const MIN_SENSITIVITY: f32 = 0.1;
+	pub const MAX_SENSITIVITY: f32 = 3.;
+	#[inline] pub const fn default() -> Self {
+		Self {
+			mouse_sensitivity: 1.,
+			invert_y: false,
+		}
+	}
+	/// (sensitivity, invert_y) in use, the defaults before the settings are loaded
+	#[inline] pub fn current() -> (f32, bool) {
+		if !ptr::has_settings() { return (1., false); }
+		let config = &ptr::get_settings().mouse_config;
+		(config.mouse_sensitivity, config.invert_y)
+	}
+}
+
+
+/// Field of view of the camera, in degrees, read every frame
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FovConfig {
+	pub fov: f32, // vertical field of view while walking
+	pub sprint_boost: f32, // added on top while sprinting (0 keeps it still)
+	pub transition_speed: f32, // how fast it eases towards its target, higher is snappier (the gap shrinks by e per 1/speed seconds)
+}
+impl FovConfig {
+	pub const MIN_FOV: f32 = 50.;
+	pub const MAX_FOV: f32 = 110.;
+	#[inline] pub const fn default() -> Self {
+		Self {
+			fov: 90.,
+			sprint_boost: 10.,
+			transition_speed: 8.,
+		}
+	}
+	/// The config in use, the defaults before the settings are loaded
+	#[inline] pub fn current() -> Self {
+		if ptr::has_settings() { ptr::get_settings().fov_config } else { Self::default() }
+	}
+	/// Field of view the camera heads to, in radians
+	#[inline] pub fn target(&self, sprinting: bool) -> f32 {
+		let fov = self.fov.clamp(Self::MIN_FOV, Self::MAX_FOV);
+		(if sprinting { fov + self.sprint_boost.max(0.) } else { fov }).to_radians()
+	}
+}
+
+
+/// How the gamepad sticks are read
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GamepadConfig {
+	pub left_deadzone: f32, // stick tilt (0-1) read as rest, worn sticks drift without it
+	pub right_deadzone: f32,
+	pub look_speed: f32, // mouse pixels per second the right stick turns at full tilt
+	pub cursor_speed: f32, // window sizes per second the left stick moves the menu cursor at full tilt
+}
+impl GamepadConfig {
+	#[inline] pub const fn default() -> Self {
+		Self {
+			left_deadzone: 0.2,
+			right_deadzone: 0.15,
+			look_speed: 900.,
+			cursor_speed: 0.8,
+		}
+	}
+	/// The config in use, the defaults before the settings are loaded
+	#[inline] pub fn current() -> Self {
+		if ptr::has_settings() { ptr::get_settings().gamepad_config } else { Self::default() }
+	}
+}
+
+
+/// Key or mouse button of every `Action`, no two actions share one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyBindings {
+	bindings: [Binding; Action::COUNT], // indexed by the action
+}
+static DEFAULT_BINDINGS: KeyBindings = KeyBindings::default();
+
+/// The bindings in use, the defaults before the settings are loaded
+#[inline] pub fn key_bindings() -> &'static KeyBindings {
+	if ptr::has_settings() { &ptr::get_settings().key_bindings } else { &DEFAULT_BINDINGS }
+}
+
+impl KeyBindings {
+	pub const fn default() -> Self {
+		let mut bindings = [Binding::Key(winit::keyboard::KeyCode::KeyW); Action::COUNT];
+		let mut i = 0;
+		while i < Action::COUNT {
+			bindings[i] = Action::ALL[i].default_binding();
+			i += 1;
+		}
+		Self { bindings }
+	}
+	#[inline] pub const fn get(&self, action: Action) -> Binding { self.bindings[action as usize] }
+
+	/// The action bound to a key or button, if any
+	pub fn action_of(&self, binding: Binding) -> Option<Action> {
+		let binding = binding.normalized();
+		Action::ALL.into_iter().find(|action| self.get(*action) == binding)
+	}
+
+	/// Binds the action, Err with the action that already has the key (nothing changes then)
+	pub fn set(&mut self, action: Action, binding: Binding) -> Result<(), Action> {
+		let binding = binding.normalized();
+		match self.action_of(binding) {
+			Some(other) if other != action => Err(other),
+			_ => {
+				self.bindings[action as usize] = binding;
+				Ok(())
+			}
+		}
+	}
+
+	/// Pairs of actions sharing a binding, empty for any bindings made through `set`
+	pub fn conflicts(&self) -> Vec<(Action, Action)> {
+		let mut conflicts = Vec::new();
+		for (i, a) in Action::ALL.iter().enumerate() {
+			for b in &Action::ALL[i + 1..] {
+				if self.get(*a) == self.get(*b) { conflicts.push((*a, *b)); }
+			}
+		}
+		conflicts
+	}
+
+	/// Where the bindings are kept between runs
+	pub fn path() -> PathBuf {
+		crate::world::manager::get_save_path().join("keybindings.json")
+	}
+	/// The saved bindings, the defaults if there are none (or they can't be read)
+	pub fn load() -> Self {
+		let Some(text) = read_json_file(&Self::path()) else { return Self::default(); };
+		match JsonParser::parse(&text).and_then(|value| Self::from_json(&value)) {
+			Ok(bindings) => bindings,
+			Err(e) => {
+				println!("Key bindings not loaded: {}", e);
+				Self::default()
+			}
+		}
+	}
+	pub fn save(&self) -> std::io::Result<()> {
+		let path = Self::path();
+		if let Some(parent) = path.parent() { std::fs::create_dir_all(parent)?; }
+		std::fs::write(path, json::to_string_pretty(&self.to_json(), 4))
+	}
+}
+
+/// `{"forward": "KeyW", "break": "MouseLeft", ..}`, missing or unknown entries keep their default
+impl JsonSerializable for KeyBindings {
+	fn from_json(value: &JsonValue) -> Result<Self, JsonError> {
+		let obj = value.as_object().ok_or(JsonError::Custom("Key bindings are not an object".into()))?;
+		let mut bindings = Self::default();
+		// Everything unbound first, so swapped keys (W and S) load whatever order they are in
+		let saved: Vec<(Action, Binding)> = Action::ALL.into_iter()
+			.filter_map(|action| {
+				let name = obj.get(action.config_name())?.as_str()?;
+				Some((action, Binding::from_config_name(name)?))
+			})
+			.collect();
+		for (action, binding) in &saved {
+			bindings.bindings[*action as usize] = *binding;
+		}
+		// A key bound twice (a hand edited file) goes back to the later action's default, the defaults if that is taken too
+		for (a, b) in bindings.conflicts() {
+			println!("{} and {} share a key, {} is reset", a.name(), b.name(), b.name());
+			bindings.bindings[b as usize] = b.default_binding();
+		}
+		if !bindings.conflicts().is_empty() { return Ok(Self::default()); }
+		Ok(bindings)
+	}
+
+	fn to_json(&self) -> JsonValue {
+		let obj: HashMap<String, JsonValue> = Action::ALL.into_iter()
+			.map(|action| (action.config_name().to_string(), JsonValue::String(self.get(action).config_name())))
+			.collect();
+		JsonValue::Object(obj)
+	}
 }
 
 
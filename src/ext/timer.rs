@@ -1,4 +1,4 @@
-use std::time::Instant;
+use std::{sync::atomic::{AtomicBool, AtomicU32, Ordering}, time::Instant};
 
 #[derive(Clone, Copy)]
 pub struct RunningAverage {
@@ -216,3 +216,257 @@ impl Default for FPSCounter {
 	}
 }
 
+
+/// Parts of a frame the debug overlay shows the time of
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Section {
+	Update = 0, // game and ui update
+	Mesh = 1, // uploading the chunk meshes of a world tick
+	Render = 2,
+}
+
+impl Section {
+	pub const ALL: [Self; 3] = [Self::Update, Self::Mesh, Self::Render];
+	#[inline] pub const fn name(self) -> &'static str {
+		match self {
+			Self::Update => "update",
+			Self::Mesh => "mesh",
+			Self::Render => "render",
+		}
+	}
+}
+
+static TIMING: AtomicBool = AtomicBool::new(false);
+static SECTION_MICROS: [AtomicU32; 3] = [const { AtomicU32::new(0) }; 3];
+
+/// Sections are only timed while something shows them
+#[inline] pub fn set_timing(on: bool) {
+	TIMING.store(on, Ordering::Release);
+}
+#[inline] pub fn is_timing() -> bool { TIMING.load(Ordering::Acquire) }
+
+/// Runs `f`, keeping how long it took while timing is on
+#[inline] pub fn time_section<T>(section: Section, f: impl FnOnce() -> T) -> T {
+	if !is_timing() { return f(); }
+	let start = Instant::now();
+	let result = f();
+	let micros = start.elapsed().as_micros().min(u32::MAX as u128) as u32;
+	SECTION_MICROS[section as usize].store(micros, Ordering::Relaxed);
+	result
+}
+
+/// Milliseconds the last timed run of the section took
+#[inline] pub fn section_ms(section: Section) -> f32 {
+	SECTION_MICROS[section as usize].load(Ordering::Relaxed) as f32 / 1000.0
+}
+
+/// Frames `FrameTimes` keeps, four seconds at 60 fps
+pub const FRAME_HISTORY: usize = 240;
+/// A frame slower than this missed 60 fps
+pub const FRAME_BUDGET_MS: f32 = 1000. / 60.;
+
+/// Slowest frame still in the history
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct WorstFrame {
+	pub ms: f32,
+	pub at: f64, // seconds since the recorder started, when the frame ended
+	pub frame: u64, // its number, counted from 1
+}
+
+/// What the overlay shows of the recent frames
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct FrameSummary {
+	pub p50: f32, // ms
+	pub p95: f32,
+	pub p99: f32,
+	pub over_budget: usize, // frames in the history slower than the budget
+	pub budget_ms: f32,
+	pub worst_ms: f32,
+	pub worst_ago: f32, // seconds since the worst frame
+}
+
+/// The last `FRAME_HISTORY` frame times in a ring, nothing is allocated per frame
+#[derive(Debug, Clone)]
+pub struct FrameTimes {
+	times: [f32; FRAME_HISTORY], // ms, the oldest is overwritten first
+	next: usize, // slot of the next frame
+	len: usize,
+	frame: u64, // frames recorded so far
+	clock: f64, // seconds recorded so far
+	budget_ms: f32,
+	over: usize, // frames in the ring over the budget
+	worst: WorstFrame,
+}
+
+impl FrameTimes {
+	#[inline] pub const fn new() -> Self { Self::with_budget(FRAME_BUDGET_MS) }
+	#[inline] pub const fn with_budget(budget_ms: f32) -> Self {
+		Self { times: [0.; FRAME_HISTORY], next: 0, len: 0, frame: 0, clock: 0., budget_ms, over: 0, worst: WorstFrame { ms: 0., at: 0., frame: 0 } }
+	}
+	#[inline] pub const fn len(&self) -> usize { self.len }
+	#[inline] pub const fn is_empty(&self) -> bool { self.len == 0 }
+	#[inline] pub const fn budget_ms(&self) -> f32 { self.budget_ms }
+	#[inline] pub const fn over_budget(&self) -> usize { self.over }
+	/// None before the first frame
+	#[inline] pub const fn worst(&self) -> Option<WorstFrame> {
+		if self.len == 0 { None } else { Some(self.worst) }
+	}
+	/// Seconds since the recorder started, the timestamps of `worst` count from there
+	#[inline] pub const fn clock(&self) -> f64 { self.clock }
+
+	/// Adds a frame that took `delta` seconds, dropping the oldest once full
+	pub fn record(&mut self, delta: f32) {
+		let ms = delta * 1000.;
+		if self.len == FRAME_HISTORY && self.times[self.next] > self.budget_ms { self.over -= 1; }
+		self.times[self.next] = ms;
+		self.next = (self.next + 1) % FRAME_HISTORY;
+		self.len = (self.len + 1).min(FRAME_HISTORY);
+		self.frame += 1;
+		self.clock += delta as f64;
+		if ms > self.budget_ms { self.over += 1; }
+		if ms >= self.worst.ms {
+			self.worst = WorstFrame { ms, at: self.clock, frame: self.frame };
+		} else if self.frame - self.worst.frame >= FRAME_HISTORY as u64 {
+			self.rescan_worst();
+		}
+	}
+
+	/// The worst frame left the ring, the slowest one still in it takes over (the newest on ties)
+	fn rescan_worst(&mut self) {
+		let mut ago = 0.;
+		self.worst = WorstFrame { ms: f32::NEG_INFINITY, ..WorstFrame::default() };
+		for back in 0..self.len {
+			let ms = self.times[(self.next + FRAME_HISTORY - 1 - back) % FRAME_HISTORY];
+			if ms > self.worst.ms {
+				self.worst = WorstFrame { ms, at: self.clock - ago, frame: self.frame - back as u64 };
+			}
+			ago += ms as f64 / 1000.;
+		}
+	}
+
+	/// Frame time in ms that `p` percent of the frames stay under (nearest rank), 0 while empty
+	pub fn percentile(&self, p: f32) -> f32 {
+		if self.len == 0 { return 0.; }
+		let mut times = self.times;
+		let times = &mut times[..self.len];
+		let (_, nth, _) = times.select_nth_unstable_by(Self::rank(p, self.len), f32::total_cmp);
+		*nth
+	}
+	#[inline] fn rank(p: f32, len: usize) -> usize {
+		((p.clamp(0., 100.) / 100. * len as f32).ceil() as usize).clamp(1, len) - 1
+	}
+
+	/// p50, p95, p99 from one sort of a copy on the stack
+	pub fn summary(&self) -> FrameSummary {
+		let mut summary = FrameSummary { budget_ms: self.budget_ms, over_budget: self.over, ..Default::default() };
+		if self.len == 0 { return summary; }
+		let mut times = self.times;
+		let times = &mut times[..self.len];
+		times.sort_unstable_by(f32::total_cmp);
+		summary.p50 = times[Self::rank(50., self.len)];
+		summary.p95 = times[Self::rank(95., self.len)];
+		summary.p99 = times[Self::rank(99., self.len)];
+		summary.worst_ms = self.worst.ms;
+		summary.worst_ago = (self.clock - self.worst.at) as f32;
+		summary
+	}
+
+	#[inline] pub fn clear(&mut self) { *self = Self::with_budget(self.budget_ms); }
+}
+
+impl Default for FrameTimes {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+
+/// Timings by name in milliseconds, what `report` prints
+#[derive(Debug, Clone, Default)]
+pub struct Buckets {
+	entries: Vec<(&'static str, RunningAverage)>, // only a handful of names, a scan beats hashing them
+}
+
+impl Buckets {
+	#[inline] pub const fn new() -> Self { Self { entries: Vec::new() } }
+	pub fn record(&mut self, name: &'static str, ms: f64) {
+		match self.entries.iter_mut().find(|(n, _)| *n == name) {
+			Some((_, average)) => average.add(ms),
+			None => {
+				let mut average = RunningAverage::new();
+				average.add(ms);
+				self.entries.push((name, average));
+			}
+		}
+	}
+	#[inline] pub fn get(&self, name: &str) -> Option<&RunningAverage> {
+		self.entries.iter().find(|(n, _)| *n == name).map(|(_, average)| average)
+	}
+	#[inline] pub fn is_empty(&self) -> bool { self.entries.is_empty() }
+	#[inline] pub fn clear(&mut self) { self.entries.clear(); }
+	/// A line per bucket in name order: min, avg and max in ms and how many times it ran
+	pub fn lines(&self) -> Vec<String> {
+		let mut entries: Vec<_> = self.entries.iter().collect();
+		entries.sort_by_key(|(name, _)| *name);
+		let width = entries.iter().map(|(name, _)| name.len()).max().unwrap_or(0);
+		entries.into_iter().map(|(name, average)| format!(
+			"{:<width$}  min {:>8.3} ms  avg {:>8.3} ms  max {:>8.3} ms  x{}",
+			name, average.min(), average.avg(), average.max(), average.count(),
+		)).collect()
+	}
+}
+
+#[cfg(feature = "profiling")]
+static BUCKETS: std::sync::Mutex<Buckets> = std::sync::Mutex::new(Buckets::new());
+
+/// Times the scope it lives in, the time goes to its named bucket when dropped.
+/// Nested timers each fill their own bucket (the outer one includes the inner time).
+/// Without the profiling feature it holds nothing and does nothing
+#[must_use = "the time is taken when the timer is dropped, bind it with `let _timer = ..`"]
+pub struct ScopeTimer {
+	#[cfg(feature = "profiling")]
+	name: &'static str,
+	#[cfg(feature = "profiling")]
+	start: Instant,
+}
+
+impl ScopeTimer {
+	#[cfg(feature = "profiling")]
+	#[inline] pub fn new(name: &'static str) -> Self { Self { name, start: Instant::now() } }
+	#[cfg(not(feature = "profiling"))]
+	#[inline(always)] pub const fn new(_name: &'static str) -> Self { Self {} }
+}
+
+#[cfg(feature = "profiling")]
+impl Drop for ScopeTimer {
+	fn drop(&mut self) {
+		let ms = self.start.elapsed().as_secs_f64() * 1000.0;
+		BUCKETS.lock().unwrap_or_else(|e| e.into_inner()).record(self.name, ms);
+	}
+}
+
+/// Copy of the timings so far, empty without the profiling feature
+#[cfg(feature = "profiling")]
+pub fn buckets() -> Buckets {
+	BUCKETS.lock().unwrap_or_else(|e| e.into_inner()).clone()
+}
+#[cfg(not(feature = "profiling"))]
+#[inline] pub const fn buckets() -> Buckets { Buckets::new() }
+
+/// Prints min/avg/max/count of every bucket
+pub fn report() {
+	let buckets = buckets();
+	if buckets.is_empty() {
+		println!("No scope timings{}", if cfg!(feature = "profiling") { " yet" } else { ", build with the profiling feature" });
+		return;
+	}
+	println!("Scope timings:");
+	for line in buckets.lines() { println!("  {}", line); }
+}
+
+/// Empties the buckets, to time a single frame or start over after loading
+#[inline] pub fn reset() {
+	#[cfg(feature = "profiling")]
+	BUCKETS.lock().unwrap_or_else(|e| e.into_inner()).clear();
+}
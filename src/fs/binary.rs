@@ -1,6 +1,8 @@
 use crate::utils::color::Color;
 use crate::utils::time::Time;
 use glam::{Vec2, IVec3, UVec3, Vec3};
+use std::borrow::Cow;
+use std::io::{Error, ErrorKind};
 use std::num::NonZero;
 
 // Trait for binary serialization
@@ -329,3 +331,125 @@ impl BinarySerializable for Time {
 impl FixedBinarySize for Time {
 	const BINARY_SIZE: usize = u16::BINARY_SIZE + u8::BINARY_SIZE * 5;
 }
+
+// Versioned files
+// ===============
+
+/// First bytes of a versioned file, tells it apart from a file made before the header existed
+pub const MAGIC: [u8; 4] = *b"RCUB";
+/// A file format version
+pub type Version = u16;
+/// Files from before the header, read as this version
+pub const UNVERSIONED: Version = 0;
+/// The layout this build writes, raise it when a saved type changes and add the step that upgrades the old one
+pub const FORMAT_VERSION: Version = 2;
+/// From this version on a versioned file ends in a checksum of everything before it
+pub const CHECKSUM_SINCE: Version = 2;
+pub const HEADER_SIZE: usize = MAGIC.len() + Version::BINARY_SIZE;
+
+/// Turns data of one version into the next, None if it doesn't hold together
+pub type Migration = fn(&[u8]) -> Option<Vec<u8>>;
+
+/// The header of the current version, the first thing written in a versioned file
+#[inline] pub fn header() -> [u8; HEADER_SIZE] {
+	let mut header = [0; HEADER_SIZE];
+	header[..MAGIC.len()].copy_from_slice(&MAGIC);
+	header[MAGIC.len()..].copy_from_slice(&FORMAT_VERSION.to_le_bytes());
+	header
+}
+
+/// Reads the header and moves `reader` past it.
+/// Without one the data is `UNVERSIONED` and left as it is, a version newer than this build is refused (`ErrorKind::Unsupported`)
+pub fn read_header(reader: &mut &[u8]) -> Result<Version, Error> {
+	if !reader.starts_with(&MAGIC) { return Ok(UNVERSIONED); }
+	let version = Version::from_binary(&reader[MAGIC.len()..])
+		.ok_or_else(|| Error::new(ErrorKind::InvalidData, "File header cut short"))?;
+	if version > FORMAT_VERSION {
+		return Err(Error::new(ErrorKind::Unsupported, format!(
+			"Saved by a newer version of the game (format {}, this one reads up to {})", version, FORMAT_VERSION
+		)));
+	}
+	*reader = &reader[HEADER_SIZE..];
+	Ok(version)
+}
+
+/// Brings data of `version` up to `FORMAT_VERSION`, `steps[v]` upgrades version v to v + 1.
+/// Current data is handed back as it is
+pub fn migrate<'a>(version: Version, data: &'a [u8], steps: &[Migration]) -> Result<Cow<'a, [u8]>, Error> {
+	let mut data = Cow::Borrowed(data);
+	for from in version..FORMAT_VERSION {
+		let step = steps.get(from as usize)
+			.ok_or_else(|| Error::new(ErrorKind::Unsupported, format!("No way to upgrade format {} files", from)))?;
+		data = Cow::Owned(step(&data)
+			.ok_or_else(|| Error::new(ErrorKind::InvalidData, format!("Format {} data could not be upgraded", from)))?);
+	}
+	Ok(data)
+}
+
+/// A versioned file of `body`: the header, the body and the checksum over both
+pub fn write_versioned(body: &[u8]) -> Vec<u8> {
+	let mut data = Vec::with_capacity(HEADER_SIZE + body.len() + CHECKSUM_SIZE);
+	data.extend_from_slice(&header());
+	data.extend_from_slice(body);
+	write_with_checksum(&mut data);
+	data
+}
+
+/// The body of a versioned file in the current layout, the checksum checked and older ones (or ones without a header) upgraded through `steps`
+pub fn read_versioned<'a>(bytes: &'a [u8], steps: &[Migration]) -> Result<Cow<'a, [u8]>, Error> {
+	let mut reader = bytes;
+	let version = read_header(&mut reader)?;
+	if version >= CHECKSUM_SINCE {
+		reader = read_verified(bytes)?.get(HEADER_SIZE..)
+			.ok_or_else(|| Error::new(ErrorKind::InvalidData, "File cut short"))?;
+	}
+	migrate(version, reader, steps)
+}
+
+// Checksums
+// =========
+
+pub const CHECKSUM_SIZE: usize = u32::BINARY_SIZE;
+
+/// CRC-32 (the zip/png one), a table of the remainders of every byte
+const CRC_TABLE: [u32; 256] = {
+	let mut table = [0; 256];
+	let mut i = 0;
+	while i < 256 {
+		let mut crc = i as u32;
+		let mut bit = 0;
+		while bit < 8 {
+			crc = if crc & 1 != 0 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+			bit += 1;
+		}
+		table[i] = crc;
+		i += 1;
+	}
+	table
+};
+
+/// CRC-32 of the bytes, any single flipped bit (and any burst up to 32 bits) changes it
+pub fn crc32(bytes: &[u8]) -> u32 {
+	!bytes.iter().fold(!0u32, |crc, &byte| CRC_TABLE[((crc ^ byte as u32) & 0xFF) as usize] ^ (crc >> 8))
+}
+
+/// Appends the checksum of everything in `data`, four bytes whatever the size
+#[inline] pub fn write_with_checksum(data: &mut Vec<u8>) {
+	let crc = crc32(data);
+	data.extend_from_slice(&crc.to_le_bytes());
+}
+
+/// The data before the checksum, if the checksum matches it
+pub fn read_verified(bytes: &[u8]) -> Result<&[u8], Error> {
+	let split = bytes.len().checked_sub(CHECKSUM_SIZE)
+		.ok_or_else(|| Error::new(ErrorKind::InvalidData, "File too small to hold a checksum"))?;
+	let (data, stored) = bytes.split_at(split);
+	let stored = u32::from_binary(stored).unwrap_or_default();
+	let actual = crc32(data);
+	if stored != actual {
+		return Err(Error::new(ErrorKind::InvalidData, format!(
+			"Checksum mismatch (stored {:08x}, data gives {:08x}), the file is damaged", stored, actual
+		)));
+	}
+	Ok(data)
+}
@@ -51,6 +51,92 @@ impl JsonValue {
     }
 }
 
+/// Compact JSON text, the parser reads it back (object keys come out sorted, so the same value is always the same text)
+impl fmt::Display for JsonValue {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write_value(f, self, None, 0)
+	}
+}
+
+/// Compact JSON text, no whitespace at all
+#[inline] pub fn to_string(value: &JsonValue) -> String {
+	value.to_string()
+}
+
+/// JSON text for people to read and edit, one entry per line indented by `indent` spaces a level
+pub fn to_string_pretty(value: &JsonValue, indent: usize) -> String {
+	struct Pretty<'a>(&'a JsonValue, usize);
+	impl fmt::Display for Pretty<'_> {
+		fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+			write_value(f, self.0, Some(self.1), 0)
+		}
+	}
+	Pretty(value, indent).to_string()
+}
+
+/// Writes a value, `indent` None for compact text, `level` is how deep it sits
+fn write_value(f: &mut fmt::Formatter, value: &JsonValue, indent: Option<usize>, level: usize) -> fmt::Result {
+	match value {
+		JsonValue::Null => write!(f, "null"),
+		JsonValue::Bool(b) => write!(f, "{}", b),
+		// JSON has no NaN or infinity
+		JsonValue::Number(n) if !n.is_finite() => write!(f, "null"),
+		// Rust prints the shortest text that reads back as the same f64
+		JsonValue::Number(n) => write!(f, "{}", n),
+		JsonValue::String(s) => write_json_string(f, s),
+		JsonValue::Array(arr) if arr.is_empty() => write!(f, "[]"),
+		JsonValue::Array(arr) => {
+			write!(f, "[")?;
+			for (i, item) in arr.iter().enumerate() {
+				if i > 0 { write!(f, ",")?; }
+				write_break(f, indent, level + 1)?;
+				write_value(f, item, indent, level + 1)?;
+			}
+			write_break(f, indent, level)?;
+			write!(f, "]")
+		}
+		JsonValue::Object(obj) if obj.is_empty() => write!(f, "{{}}"),
+		JsonValue::Object(obj) => {
+			let mut entries: Vec<_> = obj.iter().collect();
+			entries.sort_unstable_by(|a, b| a.0.cmp(b.0));
+			write!(f, "{{")?;
+			for (i, (key, value)) in entries.into_iter().enumerate() {
+				if i > 0 { write!(f, ",")?; }
+				write_break(f, indent, level + 1)?;
+				write_json_string(f, key)?;
+				write!(f, "{}", if indent.is_some() { ": " } else { ":" })?;
+				write_value(f, value, indent, level + 1)?;
+			}
+			write_break(f, indent, level)?;
+			write!(f, "}}")
+		}
+	}
+}
+
+/// A new line indented to `level`, nothing in compact text
+#[inline] fn write_break(f: &mut fmt::Formatter, indent: Option<usize>, level: usize) -> fmt::Result {
+	match indent {
+		Some(indent) => write!(f, "\n{:width$}", "", width = indent * level),
+		None => Ok(()),
+	}
+}
+
+fn write_json_string(f: &mut fmt::Formatter, s: &str) -> fmt::Result {
+	write!(f, "\"")?;
+	for c in s.chars() {
+		match c {
+			'"' => write!(f, "\\\"")?,
+			'\\' => write!(f, "\\\\")?,
+			'\n' => write!(f, "\\n")?,
+			'\r' => write!(f, "\\r")?,
+			'\t' => write!(f, "\\t")?,
+			c if c.is_control() => write!(f, "\\u{:04x}", c as u32)?,
+			c => write!(f, "{}", c)?,
+		}
+	}
+	write!(f, "\"")
+}
+
 #[derive(Debug)]
 pub enum JsonError {
 	Parse {
@@ -64,6 +150,17 @@ pub enum JsonError {
 	},
 	MissingField(MutStr),
 	Custom(MutStr),
+	/// Nested deeper than the parser was allowed to go
+	TooDeep {
+		limit: usize,
+		line: usize,
+		column: usize,
+	},
+	/// The input is longer than the parser was allowed to read
+	TooLong {
+		limit: usize,
+		len: usize,
+	},
 }
 
 impl fmt::Display for JsonError {
@@ -77,6 +174,10 @@ impl fmt::Display for JsonError {
 			},
 			JsonError::MissingField(field) => write!(f, "Missing required field: {}", field),
 			JsonError::Custom(msg) => write!(f, "{}", msg),
+			JsonError::TooDeep { limit, line, column } =>
+				write!(f, "JSON nested deeper than {} at {}:{}", limit, line, column),
+			JsonError::TooLong { limit, len } =>
+				write!(f, "JSON input is {} bytes, more than the {} allowed", len, limit),
 		}
 	}
 }
@@ -375,10 +476,23 @@ impl<T: JsonSerializable, U: JsonSerializable> JsonSerializable for (T, U) {
 	}
 }
 
+/// How deep arrays and objects may nest by default, far past any real file but well short of the stack running out
+pub const DEFAULT_MAX_DEPTH: usize = 128;
+
+/// Parses with a nesting and a length (in bytes) limit, going past either is an error rather than a crash
+pub fn parse_with_limits(input: &str, max_depth: usize, max_len: usize) -> Result<JsonValue, JsonError> {
+	if input.len() > max_len {
+		return Err(JsonError::TooLong { limit: max_len, len: input.len() });
+	}
+	JsonParser::new(input).with_max_depth(max_depth).parse_self()
+}
+
 pub struct JsonParser<'a> {
 	chars: Peekable<Chars<'a>>,
 	line: usize,
 	column: usize,
+	depth: usize, // arrays and objects currently open
+	max_depth: usize,
 }
 
 impl<'a> JsonParser<'a> {
@@ -387,8 +501,14 @@ impl<'a> JsonParser<'a> {
 			chars: input.chars().peekable(),
 			line: 1,
 			column: 1,
+			depth: 0,
+			max_depth: DEFAULT_MAX_DEPTH,
 		}
 	}
+	#[inline] pub const fn with_max_depth(mut self, max_depth: usize) -> Self {
+		self.max_depth = max_depth;
+		self
+	}
 
 	pub fn parse(input: &'a str) -> Result<JsonValue, JsonError> {
 		Self::new(input).parse_self()
@@ -399,13 +519,24 @@ impl<'a> JsonParser<'a> {
 		match self.chars.peek() {
 			Some('t') | Some('f') | Some('n') => self.parse_small(),
 			Some('"') => self.parse_string().map(JsonValue::String),
-			Some('[') => self.parse_array(),
-			Some('{') => self.parse_object(),
+			Some('[') => self.nested(Self::parse_array),
+			Some('{') => self.nested(Self::parse_object),
 			Some(c) if c.is_ascii_digit() || *c == '-' => self.parse_number(),
 			_ => Err(self.error("Unexpected token")),
 		}
 	}
 
+	/// Parses an array or object one level deeper, refusing before the recursion gets out of hand
+	fn nested(&mut self, parse: fn(&mut Self) -> Result<JsonValue, JsonError>) -> Result<JsonValue, JsonError> {
+		if self.depth >= self.max_depth {
+			return Err(JsonError::TooDeep { limit: self.max_depth, line: self.line, column: self.column });
+		}
+		self.depth += 1;
+		let value = parse(self);
+		self.depth -= 1;
+		value
+	}
+
 	fn parse_small(&mut self) -> Result<JsonValue, JsonError> {
 		if self.starts_with("true") {
 			self.expect("true")?;
@@ -0,0 +1,37 @@
+use crate::fs::rs::RESOURCE_DIR;
+use std::path::Path;
+
+// Debug builds read the resources straight from the `resources` folder when the file is there,
+// so a shader or a texture can be edited and picked up by a restart without rebuilding.
+// Release builds never look at the disk, everything comes from what `build.rs` embedded.
+
+/// Where debug builds look first, the uncompressed resources next to the manifest
+#[cfg(debug_assertions)]
+pub const DEV_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/resources");
+
+/// The bytes of a resource, from `DEV_DIR` in debug builds if it has the file, embedded otherwise. None if neither has it
+pub fn load(name: &str) -> Option<Vec<u8>> {
+	#[cfg(debug_assertions)]
+	if let Some(bytes) = load_dev(Path::new(DEV_DIR), name) { return Some(bytes); }
+	embedded(name)
+}
+
+/// A resource as text, None if it isn't there or isn't UTF-8
+pub fn load_string(name: &str) -> Option<String> {
+	String::from_utf8(load(name)?).map_err(|e| println!("Resource {} is not valid UTF-8: {}", name, e)).ok()
+}
+
+/// The file from a folder of loose resources, None if it isn't there (a missing dev file just means the embedded one is used)
+pub fn load_dev(dir: &Path, name: &str) -> Option<Vec<u8>> {
+	std::fs::read(dir.join(name)).ok()
+}
+
+/// The embedded resource, decompressed if it was stored compressed
+pub fn embedded(name: &str) -> Option<Vec<u8>> {
+	if let Some(file) = RESOURCE_DIR.get_file(format!("{}.lz4", name)) {
+		return lz4_flex::decompress_size_prepended(file.contents())
+			.map_err(|e| println!("Failed to decompress {}: {}", name, e))
+			.ok();
+	}
+	RESOURCE_DIR.get_file(name).map(|file| file.contents().to_vec())
+}
@@ -18,18 +18,13 @@ macro_rules! get_raw_data {
 }
 
 // Updated get_bytes! macro that adds compression support while maintaining backward compatibility
+// (debug builds read the file from disk first, see `fs::resources`)
 #[macro_export]
 macro_rules! get_bytes {
 	($path:expr) => {{
-		use crate::fs::rs::RESOURCE_DIR;
-		// First try to find a compressed version
-		if let Some(file) = RESOURCE_DIR.get_file(format!("{}{}",$path, ".lz4")) {
-			lz4_flex::decompress_size_prepended(file.contents())
-				.unwrap_or_else(|e| panic!("Failed to decompress {}: {}", $path, e))
-		} else {
-			// Fall back to uncompressed version (original behavior)
-			crate::get_raw_data!($path).to_vec()
-		}
+		let path: &str = &$path;
+		$crate::fs::resources::load(path)
+			.unwrap_or_else(|| panic!("File {} not found in embedded resources", path))
 	}};
 }
 
@@ -3,10 +3,13 @@ use crate::utils::math;
 use crate::physic::aabb::AABB;
 use crate::utils::input::{Keyboard, InputMapping};
 use crate::utils::vec3;
-use crate::ext::config::CameraConfig;
+use crate::ext::{config::CameraConfig, settings::{FovConfig, MouseConfig}};
 use crate::item::inventory;
-use crate::physic::aabb;
-use glam::{Vec3, Mat4, Quat};
+use crate::physic::{aabb, body::{self, Body}};
+use crate::world::main::World;
+use crate::block::math::ChunkCoord;
+use crate::block::extra::render_distance;
+use glam::{Vec2, Vec3, Mat4, Quat};
 use winit::dpi::PhysicalSize;
 use wgpu::util::DeviceExt;
 
@@ -40,13 +43,49 @@ pub struct Player {
 	movement_mode: MovementMode,
 	camera_mode: CameraMode,
 	inventory: inventory::Inventory,
-	body: aabb::PhysicsBody,
+	body: Body,
+	flying: bool, // no-clip, gravity and collisions are off
+	landing: bool, // flying just ended, the body is lifted out of whatever it flew into on the next step
+	fly_speed: f32, // multiplies the speed while flying
+	sprinting: bool, // widens the field of view
 	camera_system: CameraSystem,
 }
 
 const MOUSE_TO_SCREEN: f32 = 0.0056789;
 const SAFE_FRAC_PI_2: f32 = std::f32::consts::FRAC_PI_2 - 0.0001;
-const PLAYER_SIZE: Vec3 = Vec3::new(0.8,1.8,0.8);
+
+/// Yaw (x) and pitch (y) change of a mouse movement, the sensitivity scales both the same
+#[inline] pub fn look_delta(mouse: Vec2, sensitivity: f32, invert_y: bool) -> Vec2 {
+	let y = if invert_y { -mouse.y } else { mouse.y };
+	-Vec2::new(mouse.x, y) * sensitivity * MOUSE_TO_SCREEN
+}
+pub const PLAYER_SIZE: Vec3 = Vec3::new(0.8,1.8,0.8);
+const PLAYER_STEP_HEIGHT: f32 = 0.6;
+/// Upward speed given by a jump, a bit more than one block high with the default gravity
+const JUMP_IMPULSE: f32 = 8.5;
+/// Fly speed change of one scroll step
+const FLY_SPEED_STEP: f32 = 1.25;
+pub const MIN_FLY_SPEED: f32 = 0.25;
+pub const MAX_FLY_SPEED: f32 = 8.;
+/// Blocks a landing player is lifted at most to get out of the ground, further in it stays stuck
+const MAX_LANDING_LIFT: u32 = 64;
+
+/// Lowest spot at or above `pos` where the player isn't inside a block, `pos` if that is too far up
+fn free_spot_above(world: &World, pos: Vec3) -> Vec3 {
+	let mut spot = pos;
+	for _ in 0..MAX_LANDING_LIFT {
+		// Touching a block is standing on it, not being inside
+		let aabb = AABB::from_pos(spot, PLAYER_SIZE).expanded_uniform(-0.01);
+		if !body::solid_blocks(world, &aabb, None).iter().any(|block| block.intersects(&aabb)) { return spot; }
+		spot.y = spot.y.floor() + 1.;
+	}
+	pos
+}
+
+/// One frame of easing the field of view towards its target, the same over a second at any frame rate
+#[inline] pub fn ease_fov(current: f32, target: f32, speed: f32, dt: f32) -> f32 {
+	current + (target - current) * (1. - (-speed.max(0.) * dt).exp())
+}
 
 #[allow(dead_code)]
 impl Player {
@@ -67,14 +106,18 @@ impl Player {
 			movement_mode: MovementMode::Flat,
 			camera_mode: CameraMode::Instant,
 			inventory: inventory::Inventory::default(),
-			body: aabb::PhysicsBody::new(aabb),
+			body: Body::new(aabb).with_step_height(PLAYER_STEP_HEIGHT),
+			flying: false,
+			landing: false,
+			fly_speed: 1.,
+			sprinting: false,
 			camera_system: CameraSystem::new(device, size, config, bind_group_layout),
 		}
 	}
 
 	#[cfg(test)]
 	pub fn dummy(pos: Vec3, config: CameraConfig) -> Self {
-		let aabb = aabb::AABB::from_pos(pos, Vec3::new(0.8,1.8,0.8));
+		let aabb = aabb::AABB::from_pos(pos, PLAYER_SIZE);
 		Self {
 			pos,
 			config,
@@ -82,35 +125,80 @@ impl Player {
 			movement_mode: MovementMode::Flat,
 			camera_mode: CameraMode::Instant,
 			inventory: inventory::Inventory::default(),
-			body: aabb::PhysicsBody::new(aabb),
+			body: Body::new(aabb).with_step_height(PLAYER_STEP_HEIGHT),
+			flying: false,
+			landing: false,
+			fly_speed: 1.,
+			sprinting: false,
 			camera_system: CameraSystem::dummy(),
 		}
 	}
 
 	/// Updates player state and returns movement delta
-	#[inline] pub fn update(&mut self, delta_time: f32, queue: &wgpu::Queue) -> Vec3 {
+	#[inline] pub fn update(&mut self, delta_time: f32, world: &World, queue: &wgpu::Queue) -> Vec3 {
+		let movement = self.step(delta_time, world);
+		
+		// Update the camera system's GPU resources, the fog follows the render distance setting
+		self.camera_system.fog_mut().fit_view(render_distance());
+		self.camera_system.update(queue, self.cam_pos());
+		
+		movement
+	}
+
+	/// The GPU free part of `update`
+	pub fn step(&mut self, delta_time: f32, world: &World) -> Vec3 {
 		// Clamp delta time to prevent physics issues with large frame times
 		let dt = delta_time.min(0.01);
 
 		self.update_rotation(dt);
+		self.set_sprinting(self.controller.is_running() && self.controller.get_direction().z > 0.);
+		// The whole frame time, the easing is framerate independent and a long frame shouldn't slow it down
+		self.update_fov(delta_time);
 		let movement = self.calculate_movement(dt);
-		
-		// Update the camera system's GPU resources
-		self.camera_system.update(queue, self.cam_pos());
-		
-		movement
+		let movement = if self.flying { movement } else { self.walk(world, movement, dt) };
+		// The margin is the half width of the player
+		world.clamp_movement(self.pos, movement, PLAYER_SIZE.x * 0.5)
+	}
+
+	#[inline] pub const fn set_sprinting(&mut self, sprinting: bool) { self.sprinting = sprinting; }
+	#[inline] pub const fn is_sprinting(&self) -> bool { self.sprinting }
+	/// Field of view in use, in radians
+	#[inline] pub const fn fov(&self) -> f32 { self.camera_system.projection().fovy() }
+
+	/// Eases the projection towards the set field of view (wider while sprinting)
+	fn update_fov(&mut self, dt: f32) {
+		let config = FovConfig::current();
+		let target = config.target(self.sprinting).clamp(self.config.min_fov, self.config.max_fov);
+		let fov = ease_fov(self.fov(), target, config.transition_speed, dt);
+		// Close enough is exact, so the matrix stops being rebuilt
+		let fov = if (fov - target).abs() < 1e-4 { target } else { fov };
+		self.camera_system.set_fovy(fov);
+	}
+
+	/// Moves the body with gravity and collisions, returns how far it actually got
+	fn walk(&mut self, world: &World, movement: Vec3, dt: f32) -> Vec3 {
+		// The position can be changed from outside (teleports) so the body follows it
+		self.body.aabb = aabb::AABB::from_pos(self.pos, PLAYER_SIZE);
+		// Nothing to stand on until the chunk is loaded, so hold still instead of falling through it
+		if world.get_chunk(&ChunkCoord::from_world_posf(self.pos)).is_none() { return Vec3::ZERO; }
+		if std::mem::take(&mut self.landing) {
+			let spot = free_spot_above(world, self.pos);
+			if spot != self.pos { return spot - self.pos; }
+		}
+
+		if self.controller.get_direction().y > 0. {
+			self.body.jump(JUMP_IMPULSE);
+		}
+		self.body.apply_gravity(dt);
+		self.body.move_and_collide(world, Vec3::new(movement.x, self.body.velocity.y * dt, movement.z));
+		self.body.position() - self.pos
 	}
 
 	/// Updates camera rotation based on controller input
 	fn update_rotation(&mut self, dt: f32) {
-		// Apply mouse input to target rotation
-		// mouse_x controls yaw (horizontal rotation)
-		// mouse_y controls pitch (vertical rotation)
-		self.controller.target_yaw -= self.controller.mouse_delta.x * self.config.sensitivity * MOUSE_TO_SCREEN;
-		self.controller.target_pitch -= self.controller.mouse_delta.y * self.config.sensitivity * MOUSE_TO_SCREEN;
-		
-		// Clamp pitch to prevent over-rotation
-		self.controller.target_pitch = self.controller.target_pitch.clamp(-SAFE_FRAC_PI_2, SAFE_FRAC_PI_2);
+		// Apply mouse input to target rotation, the settings are read every frame so a change shows at once
+		let (sensitivity, invert_y) = MouseConfig::current();
+		self.controller.apply_look(self.config.sensitivity * sensitivity, invert_y);
 
 		match self.camera_mode {
 			CameraMode::Smooth => {
@@ -148,7 +236,8 @@ impl Player {
 		} else {
 			1.0
 		};
-		let speed = self.config.speed * run_multiplier;
+		let fly_multiplier = if self.flying { self.fly_speed } else { 1.0 };
+		let speed = self.config.speed * run_multiplier * fly_multiplier;
 
 		// Get movement direction from packed input
 		let movement_dir = self.controller.get_direction();
@@ -197,6 +286,21 @@ impl Player {
 		Vec3::new(pos.x + off.x, pos.y + off.y, pos.z + off.z)
 	}
 
+	/// Teleports the player, the body follows on the next step
+	#[inline] pub const fn set_pos(&mut self, pos: Vec3) { self.pos = pos; }
+	/// Where the player is heading to, the camera may still be easing towards it
+	#[inline] pub const fn yaw(&self) -> f32 { self.controller.target_yaw }
+	#[inline] pub const fn pitch(&self) -> f32 { self.controller.target_pitch }
+	/// Turns the camera instantly, the pitch is clamped like the mouse look
+	#[inline] pub fn set_look(&mut self, yaw: f32, pitch: f32) {
+		let pitch = pitch.clamp(-SAFE_FRAC_PI_2, SAFE_FRAC_PI_2);
+		self.controller.target_yaw = yaw;
+		self.controller.current_yaw = yaw;
+		self.controller.target_pitch = pitch;
+		self.controller.current_pitch = pitch;
+		self.camera_system.camera_mut().set_rotation(Vec3::new(pitch, yaw, 0.));
+	}
+
 	/// Resizes the camera projection
 	#[inline] pub fn append_position(&mut self, offset: Vec3) { self.pos += offset; }
 	#[inline] pub fn resize(&mut self, new_size: PhysicalSize<u32>) { self.camera_system.resize(new_size); }
@@ -205,6 +309,22 @@ impl Player {
 	#[inline] pub const fn controller(&self) -> &PlayerController { &self.controller }
 	#[inline] pub const fn controller_mut(&mut self) -> &mut PlayerController { &mut self.controller }
 
+	#[inline] pub const fn body(&self) -> &Body { &self.body }
+	#[inline] pub const fn is_flying(&self) -> bool { self.flying }
+	/// Flying goes through blocks and ignores gravity, jump and sneak go up and down.
+	/// Walking again starts from rest, out of any block it stopped in, and falls to the ground
+	#[inline] pub const fn set_fly(&mut self, flying: bool) {
+		self.landing = self.flying && !flying;
+		self.flying = flying;
+		self.body.velocity = Vec3::ZERO;
+		self.body.grounded = false;
+	}
+	#[inline] pub const fn fly_speed(&self) -> f32 { self.fly_speed }
+	/// Faster for positive steps (scrolling up), slower for negative ones
+	#[inline] pub fn scale_fly_speed(&mut self, steps: f32) {
+		self.fly_speed = (self.fly_speed * FLY_SPEED_STEP.powf(steps)).clamp(MIN_FLY_SPEED, MAX_FLY_SPEED);
+	}
+
 	/// Sets the movement mode
 	#[inline] pub const fn set_movement_mode(&mut self, mode: MovementMode) { self.movement_mode = mode; }
 	#[inline] pub const fn set_camera_mode(&mut self, mode: CameraMode) { self.camera_mode = mode; }
@@ -258,6 +378,11 @@ impl PlayerController {
 		let y = (mapping.up)(keyboard) as i8 - (mapping.down)(keyboard) as i8;
 		let z = (mapping.forward)(keyboard) as i8 - (mapping.backward)(keyboard) as i8;
 		
+		// The stick walks in its own direction, and slower the less it is pushed over
+		let stick = keyboard.pad.movement;
+		if stick != Vec2::ZERO {
+			return Vec3::new(stick.x, y as f32, stick.y).clamp_length_max(1.);
+		}
 		Vec3::new(x as f32, y as f32, z as f32).normalize_or_zero()
 	}
 
@@ -294,6 +419,67 @@ impl PlayerController {
 	#[inline] pub const fn process_mouse(&mut self, delta_x: f32, delta_y: f32) {
 		self.mouse_delta = Vec3::new(delta_x, delta_y, 0.);
 	}
+
+	/// Turns the target rotation by this frame's mouse movement
+	/// mouse_x controls yaw, mouse_y pitch, which stays short of straight up or down so the view never flips
+	pub fn apply_look(&mut self, sensitivity: f32, invert_y: bool) {
+		let delta = look_delta(self.mouse_delta.truncate(), sensitivity, invert_y);
+		self.target_yaw += delta.x;
+		self.target_pitch = (self.target_pitch + delta.y).clamp(-SAFE_FRAC_PI_2, SAFE_FRAC_PI_2);
+	}
+	/// (yaw, pitch) the camera is turning towards
+	#[inline] pub const fn target_look(&self) -> (f32, f32) { (self.target_yaw, self.target_pitch) }
+}
+
+/// How the distance fog thickens between its start and end
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FogMode {
+	/// No fog at all
+	Off = 0,
+	/// Fades in evenly from start to end
+	Linear = 1,
+	/// Stays thin near the start and closes in fast towards the end
+	Exponential = 2,
+}
+
+/// Distance fog of the world shader, hides the chunks popping in at the edge of the render distance
+#[derive(Debug, Clone, Copy)]
+pub struct Fog {
+	pub mode: FogMode,
+	pub start: f32, // camera distance where the fog begins
+	pub end: f32, // camera distance where everything is fog colored
+	pub color: Vec3, // should match the sky at the horizon so chunks fade into it
+}
+
+impl Fog {
+	/// Thickens over the last 40% of the render distance
+	#[inline] pub fn default() -> Self {
+		Self::for_view(render_distance())
+	}
+	/// Fog that is complete at `view` blocks from the camera
+	#[inline] pub const fn for_view(view: f32) -> Self {
+		Self {
+			mode: FogMode::Linear,
+			start: view * 0.6,
+			end: view,
+			color: Vec3::new(0.7, 0.8, 0.9),
+		}
+	}
+	/// Follows a changed render distance, the start keeps its share of the end
+	#[inline] pub fn fit_view(&mut self, view: f32) {
+		if self.end == view { return; }
+		self.start = view * self.start / self.end.max(0.001);
+		self.end = view;
+	}
+	/// How much of the fog color covers something at this distance from the camera (0 to 1), same math as the chunk shader
+	#[inline] pub fn factor(&self, distance: f32) -> f32 {
+		let t = ((distance - self.start) / (self.end - self.start).max(0.001)).clamp(0., 1.);
+		match self.mode {
+			FogMode::Off => 0.,
+			FogMode::Linear => t,
+			FogMode::Exponential => 1. - (-(t * 2.5).powi(2)).exp(),
+		}
+	}
 }
 
 // Uniform buffer data
@@ -302,6 +488,8 @@ impl PlayerController {
 pub struct CameraUniform {
 	view_proj: [[f32; 4]; 4],
 	position: [f32; 4],
+	fog_color: [f32; 4],
+	fog: [f32; 4], // start, end, ambient, mode
 }
 
 impl CameraUniform {
@@ -309,8 +497,18 @@ impl CameraUniform {
 		Self { 
 			view_proj: Mat4::IDENTITY.to_cols_array_2d(),
 			position: [0.0; 4],
+			fog_color: [0.0; 4],
+			fog: [0.0, 0.0, 1.0, 0.0],
 		}
 	}
+	#[inline] pub fn set_fog(&mut self, fog: &Fog) {
+		self.fog_color = fog.color.extend(1.).into();
+		self.fog = [fog.start, fog.end, self.fog[2], fog.mode as u32 as f32];
+	}
+	/// Rides along in the unused fog slot, saves a binding in the chunk shader
+	#[inline] pub const fn set_ambient(&mut self, ambient: f32) {
+		self.fog[2] = ambient;
+	}
 	#[inline] pub const fn to_pos_vec3(&self) -> Vec3 {
 		Vec3::new(self.position[0], self.position[1], self.position[2])
 	}
@@ -322,26 +520,23 @@ pub struct CameraSystem {
 	projection: Projection,
 	uniform: CameraUniform,
 	frustum: Frustum,
-	buffer: wgpu::Buffer,
-	bind_group: wgpu::BindGroup,
+	fog: Fog,
+	gpu: Option<(wgpu::Buffer, wgpu::BindGroup)>, // uniform buffer and its bind group, None without a device (tests)
 }
 
 impl CameraSystem {
-	/// Creates a "dummy" `CameraSystem` with no real functionality.
-	/// All fields are placeholders.
+	/// Creates a `CameraSystem` without GPU resources, the camera math works but nothing can be drawn with it
 	#[cfg(test)]
-	pub fn dummy() -> Self { unsafe {
-		use std::mem::MaybeUninit;
-		#[allow(invalid_value)] // i know it is invalid ... that's the reason for this entire function to make invalid quick non existing data
+	pub fn dummy() -> Self {
 		Self {
 			camera: Camera::default(),
 			projection: Projection::default(),
 			uniform: CameraUniform::default(),
 			frustum: Frustum::default(),
-			buffer: MaybeUninit::uninit().assume_init(),
-			bind_group: MaybeUninit::uninit().assume_init(),
+			fog: Fog::default(),
+			gpu: None,
 		}
-	}}
+	}
 
 	pub fn new(device: &wgpu::Device, size: PhysicalSize<u32>, config: CameraConfig, bind_group_layout: &wgpu::BindGroupLayout) -> Self {
 		let camera = Camera::new(config.rotation);
@@ -369,8 +564,8 @@ impl CameraSystem {
 			projection,
 			uniform,
 			frustum,
-			buffer,
-			bind_group,
+			fog: Fog::default(),
+			gpu: Some((buffer, bind_group)),
 		}
 	}
 
@@ -378,26 +573,39 @@ impl CameraSystem {
 		let view_proj_mat = self.projection.matrix() * self.camera.view_matrix(pos);
 		self.uniform.view_proj = view_proj_mat.to_cols_array_2d();
 		self.uniform.position = pos.extend(0.0).into();
+		self.uniform.set_fog(&self.fog);
 		
 		// More efficient frustum update - directly from matrices instead of recalculating
 		self.frustum = Frustum::from_view_proj_matrix(view_proj_mat);
 		
-		queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(&[self.uniform]));
+		if let Some((buffer, _)) = &self.gpu {
+			queue.write_buffer(buffer, 0, bytemuck::cast_slice(&[self.uniform]));
+		}
 	}
 
 	#[inline] pub fn resize(&mut self, new_size: PhysicalSize<u32>) {
 		self.projection.resize(new_size);
 	}
+	/// Goes into the uniform with the next `update`
+	#[inline] pub fn set_fovy(&mut self, fovy: f32) {
+		if self.projection.fovy() != fovy { self.projection.set_fovy(fovy); }
+	}
 
 	// Getters
 	
 	#[inline] pub const fn frustum(&self) -> &Frustum { &self.frustum }
+	#[inline] pub const fn fog(&self) -> &Fog { &self.fog }
+	#[inline] pub const fn fog_mut(&mut self) -> &mut Fog { &mut self.fog }
+	/// World brightness from the time of day, 1 is full daylight
+	#[inline] pub const fn set_ambient(&mut self, ambient: f32) { self.uniform.set_ambient(ambient); }
 	#[inline] pub const fn camera(&self) -> &Camera { &self.camera }
 	#[inline] pub const fn uniform(&self) -> &CameraUniform { &self.uniform }
 	#[inline] pub const fn camera_mut(&mut self) -> &mut Camera { &mut self.camera }
 	#[inline] pub const fn projection(&self) -> &Projection { &self.projection }
 	#[inline] pub const fn projection_mut(&mut self) -> &mut Projection { &mut self.projection }
-	#[inline] pub const fn bind_group(&self) -> &wgpu::BindGroup { &self.bind_group }
+	#[inline] pub fn bind_group(&self) -> &wgpu::BindGroup {
+		&self.gpu.as_ref().expect("camera without GPU resources can't be drawn with").1
+	}
 }
 
 // Camera representation with improved rotation handling
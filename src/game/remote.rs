@@ -0,0 +1,96 @@
+
+use crate::network::types::PeerId;
+use crate::utils::math;
+use glam::Vec3;
+use std::collections::BTreeMap;
+
+/// How often the local player's state goes out, also how long a remote avatar takes to reach a new state
+pub const STATE_INTERVAL: f32 = 0.05;
+
+/// Where a player is, same world space as `Player::pos` (feet, not the camera)
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct PlayerState {
+	pub pos: Vec3,
+	pub yaw: f32,
+}
+
+impl PlayerState {
+	#[inline] pub const fn new(pos: Vec3, yaw: f32) -> Self {
+		Self { pos, yaw }
+	}
+	#[inline] pub fn lerp(&self, other: &Self, t: f32) -> Self {
+		Self::new(self.pos.lerp(other.pos, t), math::lerp_angle(self.yaw, other.yaw, t))
+	}
+}
+
+/// One other player, drawn between the last two states it sent so network jitter doesn't show
+#[derive(Debug, Clone, Copy)]
+pub struct RemotePlayer {
+	from: PlayerState,
+	to: PlayerState,
+	elapsed: f32, // seconds since `to` arrived
+}
+
+impl RemotePlayer {
+	#[inline] pub const fn new(state: PlayerState) -> Self {
+		Self { from: state, to: state, elapsed: STATE_INTERVAL }
+	}
+	/// Where the avatar is drawn right now
+	#[inline] pub fn state(&self) -> PlayerState {
+		self.from.lerp(&self.to, (self.elapsed / STATE_INTERVAL).clamp(0., 1.))
+	}
+	/// Moves on from wherever the avatar is now, so a late or early packet doesn't make it jump
+	#[inline] pub fn push(&mut self, state: PlayerState) {
+		self.from = self.state();
+		self.to = state;
+		self.elapsed = 0.;
+	}
+}
+
+/// Avatars of everyone else in the game, keyed by the peer the state came through and the player id
+/// it carried (0 is the peer itself, the host numbers the players it passes on)
+#[derive(Debug, Clone, Default)]
+pub struct RemotePlayers {
+	players: BTreeMap<(PeerId, u64), RemotePlayer>,
+}
+
+impl RemotePlayers {
+	#[inline] pub const fn new() -> Self {
+		Self { players: BTreeMap::new() }
+	}
+	#[inline] pub fn len(&self) -> usize { self.players.len() }
+	#[inline] pub fn is_empty(&self) -> bool { self.players.is_empty() }
+	#[inline] pub fn get(&self, peer: PeerId, player: u64) -> Option<&RemotePlayer> {
+		self.players.get(&(peer, player))
+	}
+
+	/// A state came in, the first one places the avatar without sliding it in from the origin
+	pub fn update(&mut self, peer: PeerId, player: u64, state: PlayerState) {
+		self.players.entry((peer, player))
+			.and_modify(|p| p.push(state))
+			.or_insert(RemotePlayer::new(state));
+	}
+	#[inline] pub fn remove(&mut self, peer: PeerId, player: u64) -> bool {
+		self.players.remove(&(peer, player)).is_some()
+	}
+	/// Everyone that was seen through this peer, when it disconnects
+	#[inline] pub fn remove_peer(&mut self, peer: PeerId) -> usize {
+		let before = self.players.len();
+		self.players.retain(|(p, _), _| *p != peer);
+		before - self.players.len()
+	}
+	#[inline] pub fn clear(&mut self) {
+		self.players.clear();
+	}
+
+	/// Runs once a frame
+	pub fn advance(&mut self, delta_time: f32) {
+		for player in self.players.values_mut() {
+			player.elapsed += delta_time;
+		}
+	}
+	/// Current (interpolated) state of every avatar
+	pub fn states(&self) -> impl Iterator<Item = PlayerState> + '_ {
+		self.players.values().map(RemotePlayer::state)
+	}
+}
@@ -3,22 +3,78 @@ use crate::ext::config::CameraConfig;
 use crate::world::manager::{get_save_path, ensure_save_dir};
 use crate::world::main::World;
 use crate::world::data;
-use crate::game::player;
+use crate::world::autosave::AutoSave;
+use crate::game::{player, remote::RemotePlayers};
 use crate::item::{items, recipes};
 use crate::ext::ptr;
+use crate::utils::time::{self, PlaySession};
 use crate::render::debug;
 use std::path::PathBuf;
 use std::sync::atomic::Ordering;
 use glam::Vec3;
 
+/// Real seconds a full in game day takes by default
+pub const DEFAULT_DAY_LENGTH: f32 = 1200.;
+/// World brightness at midnight, full daylight is 1
+pub const NIGHT_AMBIENT: f32 = 0.25;
+
+/// Clock of the in game day, 0 is midnight, 0.25 sunrise, 0.5 noon and 0.75 sunset
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TimeOfDay {
+	time: f32,
+	pub day_length: f32, // real seconds per in game day
+	pub frozen: bool, // stops the clock, `set` still works
+}
+
+impl TimeOfDay {
+	#[inline] pub const fn new(time: f32) -> Self {
+		Self { time, day_length: DEFAULT_DAY_LENGTH, frozen: false }
+	}
+	/// A bit after sunrise
+	#[inline] pub const fn default() -> Self {
+		Self::new(0.3)
+	}
+	#[inline] pub const fn time(&self) -> f32 {
+		self.time
+	}
+	#[inline] pub fn set(&mut self, time: f32) {
+		self.time = time.rem_euclid(1.);
+	}
+	#[inline] pub const fn freeze(&mut self, frozen: bool) {
+		self.frozen = frozen;
+	}
+	#[inline] pub fn advance(&mut self, delta_time: f32) {
+		if self.frozen || self.day_length <= 0. { return; }
+		self.set(self.time + delta_time / self.day_length);
+	}
+	/// 1 during the day, 0 at night, eased across sunrise and sunset so nothing jumps
+	#[inline] pub fn daylight(&self) -> f32 {
+		let sun_height = -(self.time * std::f32::consts::TAU).cos(); // -1 at midnight, 1 at noon
+		let t = ((sun_height + 0.25) / 0.5).clamp(0., 1.);
+		t * t * (3. - 2. * t)
+	}
+	/// Brightness multiplier for the world shader
+	#[inline] pub fn ambient(&self) -> f32 {
+		NIGHT_AMBIENT + (1. - NIGHT_AMBIENT) * self.daylight()
+	}
+	/// The clock as `HH:MM`, noon is 12:00
+	#[inline] pub fn clock_text(&self) -> String {
+		time::format_day_time(self.time)
+	}
+}
+
 #[allow(dead_code)]
 pub struct GameState {
 	worldname: String,
 	player: player::Player,
+	remote_players: RemotePlayers, // everyone else in a network game
 	world: World, // lol main data storage :)
 	save_path: std::path::PathBuf,
 	world_seed: u32,
 	debug: debug::DebugLines,
+	time_of_day: TimeOfDay,
+	play_session: PlaySession, // play time of the world, saved with the clock
+	autosave: AutoSave,
 	is_running: bool,
 }
 
@@ -86,9 +142,22 @@ impl GameState {
 
 		make_world(save_path.clone());
 
-		let creation_date: u64 = data::update_world_data(&save_path)
+		// Spawn where the last session ended, new worlds and older saves keep the default spawn
+		let mut player = player;
+		match data::load_player_data(&save_path) {
+			Ok(Some(saved)) => saved.apply(&mut player),
+			Ok(None) => {},
+			Err(e) => println!("Error loading player data: {}", e),
+		}
+
+		let world_data = data::update_world_data(&save_path)
 			.map_err(|e| println!("Error updating world data: {}", e))
+			.ok();
+		let creation_date: u64 = world_data.as_ref()
 			.map_or(0, |data| data.creation_date.to_unix_timestamp());
+		let time_of_day = world_data.as_ref().map_or(TimeOfDay::default(), |data| TimeOfDay::new(data.time_of_day));
+		let play_session = PlaySession::new(world_data.as_ref().map_or(0, |data| data.play_time));
+		let gen_mode = world_data.map(|data| data.gen_mode).unwrap_or_default();
 
 		// Combine worldname and creation_date into a seed
 		let world_seed = {
@@ -106,6 +175,7 @@ impl GameState {
 		// world create and spawn thread for chunk gen
 		let mut world = World::empty();
 		world.set_seed(world_seed);
+		world.set_gen_mode(gen_mode);
 
 		if let Some(core_count) = std::thread::available_parallelism().ok() {
 			let cpu_cores = core_count.get() as u8;
@@ -128,8 +198,12 @@ impl GameState {
 		Self {
 			worldname: worldname.to_string(),
 			player,
+			remote_players: RemotePlayers::new(),
 			world,
 			debug,
+			time_of_day,
+			play_session,
+			autosave: AutoSave::new(),
 			save_path,
 			world_seed,
 			is_running: false,
@@ -147,6 +221,12 @@ impl GameState {
 	#[inline] pub const fn player(&self) -> &player::Player {
 		&self.player
 	}
+	#[inline] pub const fn remote_players(&self) -> &RemotePlayers {
+		&self.remote_players
+	}
+	#[inline] pub const fn remote_players_mut(&mut self) -> &mut RemotePlayers {
+		&mut self.remote_players
+	}
 	#[inline] pub const fn world(&self) -> &World {
 		&self.world
 	}
@@ -162,6 +242,24 @@ impl GameState {
 	#[inline] pub const fn debug(&mut self) -> &mut debug::DebugLines {
 		&mut self.debug
 	}
+	#[inline] pub const fn time_of_day(&self) -> &TimeOfDay {
+		&self.time_of_day
+	}
+	#[inline] pub const fn time_of_day_mut(&mut self) -> &mut TimeOfDay {
+		&mut self.time_of_day
+	}
+	#[inline] pub const fn play_session(&self) -> &PlaySession {
+		&self.play_session
+	}
+	#[inline] pub const fn play_session_mut(&mut self) -> &mut PlaySession {
+		&mut self.play_session
+	}
+	#[inline] pub const fn autosave(&self) -> &AutoSave {
+		&self.autosave
+	}
+	#[inline] pub const fn autosave_mut(&mut self) -> &mut AutoSave {
+		&mut self.autosave
+	}
 	#[inline] pub const fn seed(&self) -> &u32 {
 		&self.world_seed
 	}
@@ -157,6 +157,24 @@ impl ItemContainer {
 		self.items.iter().filter(|slot| slot.is_some()).count()
 	}
 
+	/// Linear indices of every item matching the predicate (empty slots are skipped)
+	#[inline] pub fn find_all(&self, predicate: impl Fn(&ItemStack) -> bool) -> Vec<usize> {
+		self.items.iter().enumerate()
+			.filter_map(|(i, slot)| slot.as_ref().filter(|item| predicate(item)).map(|_| i))
+			.collect()
+	}
+
+	/// Case-insensitive search by display name (the LUT name or the custom name if the stack has one),
+	/// '_' and ' ' are treated the same so "iron sword" finds "iron_sword"
+	pub fn find_by_name(&self, substr: &str) -> Vec<usize> {
+		let normalize = |s: &str| s.to_lowercase().replace('_', " ");
+		let query = normalize(substr.trim());
+		self.find_all(|item| {
+			normalize(item.lut().name.to_str()).contains(&query)
+				|| item.data.as_ref().and_then(|d| d.name.as_deref()).is_some_and(|name| normalize(name).contains(&query))
+		})
+	}
+
 	/// Check if the container is full
 	#[inline] pub fn is_full(&self) -> bool {
 		self.find_empty_slot().is_none()
@@ -190,6 +208,15 @@ impl ItemContainer {
 		false
 	}
 
+	/// How many of the item fit on the matching stacks and the empty slots
+	#[inline] pub fn room_for(&self, item: &ItemStack) -> u32 {
+		self.iter().map(|slot| match slot {
+			None => item.max_stack_size(),
+			Some(existing) if existing.can_stack_with(item) => existing.max_stack_size().saturating_sub(existing.stack()),
+			Some(_) => 0,
+		}).sum()
+	}
+
 	/// Remove an item at the specified linear index
 	#[inline] pub fn remove(&mut self, index: usize) -> Option<ItemStack> {
 		self.items.get_mut(index)?.take()
@@ -226,6 +253,29 @@ impl ItemContainer {
 		self.resize(new_rows, new_cols);
 	}
 
+	/// Like `expand` but keeps the column count at (or a bit under) `target_cols` and grows the rows instead,
+	/// for layouts that have to fit a narrow panel. Falls back to the balanced dimensions if no column count
+	/// between `target_cols / 2` and `target_cols` divides the capacity
+	#[inline] pub fn expand_with_aspect(&mut self, new_capacity: u8, target_cols: u8) {
+		let (new_rows, new_cols) = Self::aspect_dimensions(new_capacity, target_cols);
+		self.resize(new_rows, new_cols);
+	}
+
+	/// Dimensions with the columns kept near `target_cols`, see `expand_with_aspect`
+	fn aspect_dimensions(capacity: u8, target_cols: u8) -> (u8, u8) {
+		if capacity == 0 { return (0, 0); }
+		if target_cols == 0 { return Self::optimal_dimensions(capacity); }
+		if capacity <= target_cols { return (1, capacity); }
+
+		let min_cols = (target_cols / 2).max(1);
+		for cols in (min_cols..=target_cols).rev() {
+			if capacity % cols == 0 {
+				return (capacity / cols, cols);
+			}
+		}
+		Self::optimal_dimensions(capacity)
+	}
+
 	/// Calculates the most balanced dimensions for given capacity
 	fn optimal_dimensions(capacity: u8) -> (u8, u8) {
 		match capacity {
@@ -263,6 +313,27 @@ impl ItemContainer {
 		}
 	}
 	
+	/// Merges partial stacks of the same item, then orders by item id and bigger stacks first,
+	/// empty slots end up at the back (row-major for 2D grids)
+	pub fn sort(&mut self) {
+		let mut merged: Vec<ItemStack> = Vec::with_capacity(self.items.len());
+		for mut item in self.items.iter_mut().filter_map(Option::take) {
+			for existing in merged.iter_mut() {
+				if item.stack == 0 { break; }
+				if !existing.can_stack_with(&item) { continue; }
+				item.stack = existing.add_to_stack(item.stack);
+			}
+			if item.stack > 0 { merged.push(item); }
+		}
+		// resource_index looks through the resource list, so only do it once per stack
+		merged.sort_by_cached_key(|item| (item.resource_index(), std::cmp::Reverse(item.stack())));
+
+		let mut merged = merged.into_iter();
+		for slot in self.items.iter_mut() {
+			*slot = merged.next();
+		}
+	}
+
 	/// Returns the smallest stack size in the inventory (ignores None slots)
 	#[inline] pub fn smallest_stack_size(&self) -> u32 {
 		self.iter()
@@ -300,6 +371,8 @@ pub struct Inventory {
 	pub storage_ptr: Option<*mut ItemContainer>,
 	// Basic inventory crafting grid to make basic stuff
 	pub crafting_def: ItemContainer,
+	// Slots hovered while dragging the cursor stack, None when not dragging
+	drag_slots: Option<Vec<(AreaType, u8, u8)>>,
 
 	/*
 	// Consider:
@@ -329,6 +402,7 @@ impl Inventory {
 			cursor_item: None,
 			layout: None,
 			storage_ptr: None,
+			drag_slots: None,
 		}
 	}
 
@@ -343,6 +417,7 @@ impl Inventory {
 			cursor_item: None,
 			layout: None,
 			storage_ptr,
+			drag_slots: None,
 		}
 	}
 	
@@ -382,6 +457,78 @@ impl Inventory {
 		self.cursor_item.take()
 	}
 	
+	/// Starts collecting slots to spread the cursor stack over, does nothing with an empty cursor
+	#[inline] pub fn begin_drag(&mut self) -> bool {
+		if self.cursor_item.is_none() { return false; }
+		self.drag_slots = Some(Vec::new());
+		true
+	}
+	#[inline] pub const fn is_dragging(&self) -> bool {
+		self.drag_slots.is_some()
+	}
+	/// Adds the hovered slot to the drag if it can take the cursor item (empty or stackable with room left),
+	/// never more slots than there are items in the cursor
+	pub fn drag_over(&mut self, pos: (u8, u8), area_type: AreaType) -> bool {
+		let Some(cursor_item) = self.cursor_item.as_ref() else { return false; };
+		let Some(slots) = self.drag_slots.as_ref() else { return false; };
+		let slot = (area_type, pos.0, pos.1);
+		if slots.contains(&slot) || slots.len() as u32 >= cursor_item.stack() { return false; }
+		if !self.drag_accepts(cursor_item, area_type, pos) { return false; }
+
+		if let Some(slots) = self.drag_slots.as_mut() { slots.push(slot); }
+		true
+	}
+	/// Spreads the cursor stack over the dragged slots : evenly for Left, one per slot for Right,
+	/// whatever is left stays in the cursor
+	pub fn end_drag(&mut self, mode: ClickMode) {
+		let Some(slots) = self.drag_slots.take() else { return };
+		let Some(mut cursor_item) = self.remove_cursor() else { return };
+		let per_slot = match mode {
+			ClickMode::Left | ClickMode::Double => cursor_item.stack() / slots.len().max(1) as u32,
+			ClickMode::Right => 1,
+			ClickMode::Middle => 0,
+		};
+
+		for (area_type, row, col) in slots {
+			// Slots could have changed since they were hovered
+			if !self.drag_accepts(&cursor_item, area_type, (row, col)) { continue; }
+			let Some(part) = cursor_item.take(per_slot) else { break };
+			let area = self.get_area_mut(area_type);
+			match area.remove_at(row, col) {
+				Some(mut item) => {
+					let remaining = item.add_to_stack(part.stack());
+					area.set_at(row, col, item.opt());
+					cursor_item.add_to_stack(remaining);
+				},
+				None => { area.set_at(row, col, part.opt()); },
+			}
+		}
+		self.set_cursor(cursor_item.opt());
+	}
+	/// Drops the drag without touching anything, the cursor still has the full stack.
+	/// Returns if there was a drag to cancel
+	#[inline] pub fn cancel_drag(&mut self) -> bool {
+		self.drag_slots.take().is_some()
+	}
+	fn drag_accepts(&self, item: &ItemStack, area_type: AreaType, pos: (u8, u8)) -> bool {
+		if matches!(area_type, AreaType::Output | AreaType::Panel) { return false; }
+		if area_type == AreaType::Armor && !item.is_armor() { return false; }
+		let area = self.get_area(&area_type);
+		let Some(index) = area.calculate_index(pos.0, pos.1) else { return false; };
+		match area.get(index) {
+			None => index < area.capacity(),
+			Some(slot) => slot.can_stack_with(item) && slot.stack() < slot.max_stack_size(),
+		}
+	}
+	
+	/// Sorts one area (see `ItemContainer::sort`), the cursor is left alone.
+	/// Output is generated and armor slots mean something by position, so those are refused
+	pub fn sort_area(&mut self, area_type: AreaType) -> bool {
+		if matches!(area_type, AreaType::Output | AreaType::Armor | AreaType::Panel) { return false; }
+		self.get_area_mut(area_type).sort();
+		true
+	}
+	
 	/// Set the UI layout
 	#[inline] pub fn set_layout(&mut self, layout: &InventoryLayout) {
 		self.layout = Some(layout.clone());
@@ -395,6 +542,18 @@ impl Inventory {
 		self.armor.capacity() + self.hotbar.capacity() + self.items.capacity()
 	}
 
+	/// Tooltip of the item in a slot, None for an empty slot or one that holds nothing (panels, missing storage)
+	pub fn tooltip_at(&self, area_type: AreaType, (row, col): (u8, u8)) -> Option<String> {
+		let has_storage = self.storage_ptr.is_some_and(|ptr| !ptr.is_null());
+		match area_type {
+			AreaType::Panel => None,
+			AreaType::Output if has_storage => self.make_result_from_input()?.get_at(row, col).map(ItemStack::tooltip_text),
+			AreaType::Inventory | AreaType::Hotbar | AreaType::Armor => self.get_area(&area_type).get_at(row, col).map(ItemStack::tooltip_text),
+			_ if has_storage => self.get_area(&area_type).get_at(row, col).map(ItemStack::tooltip_text),
+			_ => None,
+		}
+	}
+
 	#[inline] pub fn get_area(&self, area: &AreaType) -> &ItemContainer {
 		match area {
 			AreaType::Inventory => &self.items,
@@ -471,6 +630,12 @@ impl Inventory {
 		item.stack < initial_stack
 	}
 
+	/// How many of the item `add_item_anywhere` can still place
+	#[inline] pub fn room_for(&self, item: &ItemStack) -> u32 {
+		let armor = if item.is_armor() { self.armor.room_for(item) } else { 0 };
+		self.hotbar.room_for(item) + self.items.room_for(item) + armor
+	}
+
 	/// Count total items across all containers
 	#[inline] pub fn total_count(&self) -> usize {
 		self.armor.count_items() + self.hotbar.count_items() + self.items.count_items()
@@ -534,23 +699,34 @@ impl Inventory {
 			let input_area = self.get_area(&AreaType::Storage);
 			// Shift-click: try to add result items directly to inventory
 			let count = match click_type {
-				ClickMode::Left => input_area.smallest_stack_size().max(1),
+				ClickMode::Left | ClickMode::Double => input_area.smallest_stack_size().max(1),
 				ClickMode::Right => (input_area.smallest_stack_size().max(1) + 1) / 2, // Round up division
 				ClickMode::Middle => result_item.max_stack_size(),
 			};
 			
 			if count == 0 { return; }
-			let mut item = result_item.clone().with_stack_size(count);
-			if self.add_item_anywhere(&mut item) && click_type != ClickMode::Middle {
-				self.consume_crafting_materials(count);
+			if click_type == ClickMode::Middle {
+				// Creative: a full stack without using up materials
+				let mut item = result_item.clone().with_stack_size(count);
+				self.add_item_anywhere(&mut item);
+				return;
 			}
+			// Every craft gives the whole output of the recipe, stops once that no longer fits
+			let mut crafted = 0;
+			for _ in 0..count {
+				let mut item = result_item.clone();
+				if self.room_for(&item) < item.stack() { break; }
+				self.add_item_anywhere(&mut item);
+				crafted += 1;
+			}
+			if crafted > 0 { self.consume_crafting_materials(crafted); }
 			return;
 		}
 		
 		// Handle normal clicks (similar to handle_normal_click structure)
 		match (cursor, click_type) {
 			// Case 1: Empty cursor - pick up result item
-			(None, ClickMode::Left) => {
+			(None, ClickMode::Left | ClickMode::Double) => {
 				self.set_cursor(result_item.clone().opt());
 				self.consume_crafting_materials(1);
 			},
@@ -566,13 +742,13 @@ impl Inventory {
 			},
 			
 			// Case 2: Cursor has item - try to stack or reject
-			(Some(mut cursor_item), ClickMode::Left) => {
+			(Some(mut cursor_item), ClickMode::Left | ClickMode::Double) => {
 				if cursor_item.can_stack_with(result_item) {
 					let remaining = cursor_item.add_to_stack(result_item.stack());
 					if remaining == 0 {
 						// Successfully added to cursor stack
 						self.set_cursor(cursor_item.opt());
-						self.consume_crafting_materials(result_item.stack());
+						self.consume_crafting_materials(1);
 					}
 					// If remaining > 0, cursor is full, do nothing
 				}
@@ -620,7 +796,7 @@ impl Inventory {
 						self.set_cursor(remaining);
 						return;
 					},
-					ClickMode::Left | ClickMode::Middle => cursor_item,
+					ClickMode::Left | ClickMode::Middle | ClickMode::Double => cursor_item,
 				};
 				
 				area.set_at(c_x, c_y, item_to_place.opt());
@@ -629,7 +805,7 @@ impl Inventory {
 			// Case 2: item only in inventory
 			(None, Some(mut item)) => {
 				match mode {
-					ClickMode::Left => {
+					ClickMode::Left | ClickMode::Double => {
 						if !self.get_area_mut(target_area).add_item(&mut item) {
 							// If target is full, try to add anywhere
 							self.add_item_anywhere(&mut item);
@@ -658,7 +834,7 @@ impl Inventory {
 			// Case 3: both have item -> do case 2
 			(Some(_cursor_item), Some(mut item)) => {
 				match mode {
-					ClickMode::Left => {
+					ClickMode::Left | ClickMode::Double => {
 						if !self.get_area_mut(target_area).add_item(&mut item) {
 							// If target is full, try to add anywhere
 							self.add_item_anywhere(&mut item);
@@ -691,6 +867,10 @@ impl Inventory {
 
 	/// Handles normal (non-shift) clicks
 	fn handle_normal_click(&mut self, cursor: Option<ItemStack>, c_x: u8, c_y: u8, area_type: AreaType, mode: ClickMode) {
+		if mode == ClickMode::Double {
+			self.gather_to_cursor();
+			return;
+		}
 		let area = self.get_area_mut(area_type);
 		
 		// Check armor restrictions
@@ -713,7 +893,7 @@ impl Inventory {
 						self.set_cursor(remaining);
 						return;
 					},
-					ClickMode::Left | ClickMode::Middle => cursor_item,
+					ClickMode::Left | ClickMode::Middle | ClickMode::Double => cursor_item,
 				};
 				
 				area.set_at(c_x, c_y, item_to_place.opt());
@@ -722,7 +902,7 @@ impl Inventory {
 			// Case 2: Pick up item with empty cursor
 			(None, Some(mut item)) => {
 				match mode {
-					ClickMode::Left => {
+					ClickMode::Left | ClickMode::Double => {
 						self.set_cursor(item.opt());
 					},
 					ClickMode::Right => {
@@ -747,7 +927,7 @@ impl Inventory {
 				} else {
 					// Items can stack
 					match mode {
-						ClickMode::Left => {
+						ClickMode::Left | ClickMode::Double => {
 							let remaining = item.add_to_stack(cursor_item.stack());
 							area.set_at(c_x, c_y, item.opt());
 							self.set_cursor(cursor_item.with_stack_size(remaining).opt());
@@ -771,6 +951,26 @@ impl Inventory {
 		}
 	}
 
+	/// Double click : pulls matching items from the hotbar, inventory and armor (in that order) into the cursor stack
+	/// until it reaches the max stack size, whatever does not fit stays in its slot. Does nothing with an empty cursor.
+	fn gather_to_cursor(&mut self) {
+		let Some(mut cursor_item) = self.get_cursor().cloned() else { return };
+		let max = cursor_item.max_stack_size();
+
+		'areas: for area_type in [AreaType::Hotbar, AreaType::Inventory, AreaType::Armor] {
+			for item_slot in self.get_area_mut(area_type).iter_mut() {
+				if cursor_item.stack() >= max { break 'areas; }
+				let Some(item) = item_slot else { continue };
+				if !cursor_item.can_stack_with(item) { continue; }
+
+				let taken = item.stack().min(max - cursor_item.stack());
+				cursor_item.add_to_stack(taken);
+				*item_slot = item.clone().remove_from_stack(taken);
+			}
+		}
+		self.set_cursor(cursor_item.opt());
+	}
+
 	/// Helper to consume crafting materials after taking output
 	fn consume_crafting_materials(&mut self, count: u32) {
 		let input_area = self.get_area_mut(AreaType::Storage);
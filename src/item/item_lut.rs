@@ -126,6 +126,13 @@ impl ItemComp {
 		};
 		self
 	}
+	#[inline] pub const fn with_durability(mut self, value: NonZero<u32>) -> Self {
+		self.data = match self.data {
+			Some(existing_data) => Some(existing_data.with_durability(value)),
+			None => Some(ItemExtendedData::new().with_durability(value)),
+		};
+		self
+	}
 	
 	#[inline] pub const fn is_block(&self) -> bool { 
 		self.flags.contains(ItemFlags::IS_BLOCK) 
@@ -150,6 +157,12 @@ impl ItemComp {
 	#[inline] pub const fn is_armor(&self) -> bool { 
 		self.flags.contains(ItemFlags::IS_ARMOR) 
 	}
+	/// Durability a fresh stack starts with, only tools and armor have one
+	#[inline] pub fn max_durability(&self) -> Option<u16> {
+		if !self.has_durability() { return None; }
+		let durability = self.data.as_ref()?.get_durability()?;
+		Some(durability.get().min(u16::MAX as u32) as u16)
+	}
 }
 
 
@@ -1,5 +1,5 @@
 
-use std::num::NonZeroU16;
+use std::num::{NonZeroU16, NonZeroU32};
 use crate::fs::rs;
 use crate::item::item_lut::ItemComp;
 
@@ -8,6 +8,7 @@ pub struct ItemStack {
 	name: String,
 	pub stack: u32,  // Typically 1-64 like Minecraft but let it be 255 for extreme cases
 	pub data: Option<Box<CustomData>>,  // Boxed to reduce size when None
+	pub durability: Option<u16>, // None for items that can't wear out (everything but tools and armor)
 }
 impl ItemStack {
 	///////////////////////////////
@@ -21,8 +22,8 @@ impl ItemStack {
 	
 	#[inline] 
 	pub fn new(name: String) -> Self {
-		let stack = lut_by_name(&name).max_stack;
-		Self { name, stack, data: None }
+		let comp = lut_by_name(&name);
+		Self { name, stack: comp.max_stack, data: None, durability: comp.max_durability() }
 	}
 	
 	#[inline] 
@@ -31,8 +32,8 @@ impl ItemStack {
 	}
 	
 	#[inline] 
-	pub fn create(name: String, stack: u32, data: Option<Box<CustomData>>) -> Self {
-		Self {name, stack, data}
+	pub fn create(name: String, stack: u32, data: Option<Box<CustomData>>, durability: Option<u16>) -> Self {
+		Self {name, stack, data, durability}
 	}
 	
 	/// Creates an ItemStack from a resource index where the first bit indicates if it's a block (1) or item (0)
@@ -68,6 +69,14 @@ impl ItemStack {
 		self.lut().max_stack
 	}
 	
+	#[inline] 
+	pub fn durability(&self) -> Option<u16> { self.durability }
+	
+	#[inline] 
+	pub fn max_durability(&self) -> Option<u16> {
+		self.lut().max_durability()
+	}
+	
 	///////////////////////////////
 	// Type Predicates
 	///////////////////////////////
@@ -106,6 +115,15 @@ impl ItemStack {
 		self.data = data; 
 	}
 	
+	/// Wears the item down, returns true if it broke (the caller should remove it)
+	/// items without durability never break
+	pub fn damage(&mut self, amount: u16) -> bool {
+		let Some(durability) = self.durability else { return false; };
+		let durability = durability.saturating_sub(amount);
+		self.durability = Some(durability);
+		durability == 0
+	}
+	
 	/// Sets the stack size to its maximum
 	#[inline] 
 	pub fn set_to_max_stack(&mut self) { 
@@ -153,6 +171,12 @@ impl ItemStack {
 		Self { stack: size, ..self }
 	}
 	
+	/// Returns a new stack with the given durability
+	#[inline] 
+	pub fn with_durability(self, durability: Option<u16>) -> Self { 
+		Self { durability, ..self }
+	}
+	
 	/// Returns half of the current stack (rounded down)
 	#[inline] 
 	pub fn half_stack(&self) -> u32 { 
@@ -166,6 +190,15 @@ impl ItemStack {
 		self.set_stack_size(half);
 		result.remove_from_stack(half)
 	} // this returns an option but will allways return some ... because this is the bigger half ...
+	
+	/// Takes exactly `n` items (clamped to the stack) off this stack and returns them,
+	/// taking everything leaves this stack at 0 so `opt()` drops it
+	pub fn take(&mut self, n: u32) -> Option<Self> {
+		let n = n.min(self.stack);
+		if n == 0 { return None; }
+		self.stack -= n;
+		Some(self.clone().with_stack_size(n))
+	}
 
 	///////////////////////////////
 	// Conversion and Utility
@@ -179,9 +212,39 @@ impl ItemStack {
 
 	#[inline] 
 	pub fn can_stack_with(&self, other: &Self) -> bool {
-		self.name == other.name && self.data == other.data
+		self.name == other.name && self.data == other.data && self.durability == other.durability
 	}
 	
+	/// Name shown to the player, a renamed stack shows its own name
+	pub fn display_name(&self) -> String {
+		match self.data.as_ref().and_then(|d| d.name.as_ref()) {
+			Some(name) => name.clone(),
+			None => display_name(&self.name),
+		}
+	}
+
+	/// Name on the first line, then what the item is and does
+	pub fn tooltip_text(&self) -> String {
+		let comp = self.lut();
+		let mut lines = vec![self.display_name()];
+		let kind = if comp.is_block() { "Block" }
+			else if comp.is_armor() { "Armor" }
+			else if comp.is_tool() { "Tool" }
+			else if comp.is_consumable() { "Food" }
+			else { "Item" };
+		lines.push(kind.to_string());
+		if let Some(data) = comp.data.as_ref() {
+			if let Some(damage) = data.get_damage() { lines.push(format!("Damage: {}", damage)); }
+			if let Some(armor) = data.get_armor_value() { lines.push(format!("Armor: {}", armor)); }
+			if let Some(hunger) = data.get_hunger() { lines.push(format!("Food: {}", hunger)); }
+		}
+		if let (Some(durability), Some(max)) = (self.durability, comp.max_durability()) {
+			lines.push(format!("Durability: {}/{}", durability, max));
+		}
+		if comp.max_stack > 1 { lines.push(format!("Stack: {}/{}", self.stack(), comp.max_stack)); }
+		lines.join("\n")
+	}
+
 	/// Gets the icon path for this item
 	pub fn icon_path(&self) -> String {
 		let (resources, target_name) = self.get_resources_and_target();
@@ -240,6 +303,16 @@ impl CustomData {
 	}
 }
 
+/// Registry name as it is shown: "brick_grey" is "Brick grey"
+pub fn display_name(name: &str) -> String {
+	let name = name.replace('_', " ");
+	let mut chars = name.trim().chars();
+	match chars.next() {
+		Some(first) => first.to_uppercase().chain(chars).collect(),
+		None => String::new(),
+	}
+}
+
 /// I implemented a purple-pink-black "0" block what would represent the "error" .. id 1 is air
 pub const DEFAULT_ITEM_COMP: ItemComp = const { ItemComp::error().as_block() };
 
@@ -284,6 +357,11 @@ pub fn item_lut_ref() -> RwLockReadGuard<'static, FastMap<String, ItemComp>> {
 	item_lut().read().expect("Failed to acquire read lock for item LUT")
 }
 
+/// Adds (or replaces) an item after the start, like the blocks of mods
+pub fn register_item(item: ItemComp) {
+	item_lut().write().expect("Failed to acquire write lock for item LUT").insert(item.name.to_str().to_string(), item);
+}
+
 pub fn clean_item_lut() {
 	item_lut().write().expect("Failed to acquire write lock for item LUT").clear();
 }
@@ -322,11 +400,11 @@ pub fn init_item_lut() {
 		map.insert("brick_red".to_string(), ItemComp::new("brick_red").as_block());
 		map.insert("bush".to_string(), ItemComp::new("bush").as_block());
 		map.insert("wheat".to_string(), ItemComp::new("wheat").as_consumable());
-		map.insert("iron_sword".to_string(), ItemComp::new("iron_sword").as_tool(ToolData::Single{ equip_type:ToolType::String, tier: MaterialLevel::Calcite }).with_damage(5).with_stack(1));
+		map.insert("iron_sword".to_string(), ItemComp::new("iron_sword").as_tool(ToolData::Single{ equip_type:ToolType::String, tier: MaterialLevel::Calcite }).with_damage(5).with_durability(const { NonZeroU32::new(250).unwrap() }).with_stack(1));
 		map.insert("bow".to_string(), ItemComp::new("bow").with_stack(1));
 		map.insert("arrow".to_string(), ItemComp::new("arrow"));
 		map.insert("plank".to_string(), ItemComp::new("plank").as_block().as_storage((5,9).into()));
-		map.insert("coat".to_string(), ItemComp::new("coat").as_armor(ArmorData::Single{ equip_type:ArmorType::Torso, tier: MaterialLevel::Calcite }).with_stack(1));
+		map.insert("coat".to_string(), ItemComp::new("coat").as_armor(ArmorData::Single{ equip_type:ArmorType::Torso, tier: MaterialLevel::Calcite }).with_durability(const { NonZeroU32::new(160).unwrap() }).with_stack(1));
 		map.insert("crafting".to_string(), ItemComp::new("crafting").as_block().as_storage((3,3).into()));
 	}
 }
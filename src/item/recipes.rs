@@ -1,5 +1,6 @@
 
-use crate::item::items::ItemStack;
+use crate::item::items::{ItemStack, item_lut_ref};
+use crate::fs::json::{JsonParser, JsonValue, JsonError};
 use std::collections::HashMap;
 use std::sync::{OnceLock, RwLock, RwLockReadGuard};
 use ahash::RandomState;
@@ -120,6 +121,8 @@ impl From<usize> for CraftingInput {
 pub enum CraftingResult {
 	Single(usize),
 	Multiple(Vec<usize>),
+	/// One item with a stack size other than 1
+	Stack { item_id: usize, count: u32 },
 }
 
 impl From<usize> for CraftingResult {
@@ -147,38 +150,78 @@ impl From<&[usize]> for CraftingResult {
 	}
 }
 
+/// How the grid layout of a recipe is compared
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RecipeKind {
+	/// Fixed pattern, can be placed anywhere in the grid but not mirrored or rotated
+	Shaped,
+	/// Only the items matter, not where they are
+	Shapeless,
+}
+
 /// Recipe data defining input requirements and output
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Recipe {
-	input: CraftingInput,
-	output: CraftingResult,
+	pub kind: RecipeKind,
+	pub inputs: CraftingInput,
+	pub output: CraftingResult,
 }
 
 impl Recipe {
 	#[inline]
-	pub fn new<T: Into<CraftingInput>, K: Into<CraftingResult>>(input: T, output: K) -> Self {
-		let input = input.into();
-		let output = output.into();
-		
-		// Precompute hash for faster lookups
-		if let CraftingInput::Multiple(ref items) = input {
-			let mut items = items.clone();
-			items.sort_by(|a, b| {
-				a.position.y.cmp(&b.position.y)
-					.then(a.position.x.cmp(&b.position.x))
-			});
-		}
-		
-		Self { input, output }
+	pub fn new<T: Into<CraftingInput>, K: Into<CraftingResult>>(kind: RecipeKind, inputs: T, output: K) -> Self {
+		let inputs = inputs.into().normalized(kind);
+		Self { kind, inputs, output: output.into() }
+	}
+	#[inline]
+	pub fn shaped<T: Into<CraftingInput>, K: Into<CraftingResult>>(inputs: T, output: K) -> Self {
+		Self::new(RecipeKind::Shaped, inputs, output)
+	}
+	#[inline]
+	pub fn shapeless<K: Into<CraftingResult>>(item_ids: &[usize], output: K) -> Self {
+		let inputs: Vec<ItemRequirement> = item_ids.iter().map(|&id| ItemRequirement::new(id, 0, 0)).collect();
+		Self::new(RecipeKind::Shapeless, inputs.as_slice(), output)
 	}
 
-	#[inline] pub fn split(self) -> (CraftingInput, CraftingResult) { (self.input, self.output) }
-	#[inline] pub fn input(&self) -> &CraftingInput { &self.input }
+	#[inline] pub fn split(self) -> ((RecipeKind, CraftingInput), CraftingResult) { ((self.kind, self.inputs), self.output) }
+	#[inline] pub fn kind(&self) -> RecipeKind { self.kind }
+	#[inline] pub fn input(&self) -> &CraftingInput { &self.inputs }
 	#[inline] pub fn output(&self) -> &CraftingResult { &self.output }
+
+	/// Checks a (raw, not yet normalized) crafting grid against this recipe
+	#[inline] pub fn matches(&self, input: &CraftingInput) -> bool {
+		input.clone().normalized(self.kind) == self.inputs
+	}
 }
 
-// Replace the HashSet with HashMap
-static RECIPE_REGISTRY: OnceLock<RwLock<HashMap<CraftingInput, CraftingResult, RandomState>>> = OnceLock::new();
+impl CraftingInput {
+	/// Brings the input into the form recipes are stored (and looked up) in :
+	/// shaped inputs get the empty border rows / cols trimmed, so the pattern starts at (0,0) and is sorted row by row,
+	/// shapeless inputs drop the positions and are sorted by item id
+	pub fn normalized(self, kind: RecipeKind) -> Self {
+		let Self::Multiple(mut items) = self else { return self; };
+		match kind {
+			RecipeKind::Shaped => {
+				let min_x = items.iter().map(|i| i.position.x).min().unwrap_or(0);
+				let min_y = items.iter().map(|i| i.position.y).min().unwrap_or(0);
+				for item in items.iter_mut() {
+					item.position = GridPosition::new(item.position.x - min_x, item.position.y - min_y);
+				}
+				items.sort_by_key(|i| (i.position.y, i.position.x));
+			}
+			RecipeKind::Shapeless => {
+				for item in items.iter_mut() {
+					item.position = GridPosition::new(0, 0);
+				}
+				items.sort_by_key(|i| i.item_id);
+			}
+		}
+		Self::Multiple(items)
+	}
+}
+
+// Keyed by the normalized input, so a lookup is one hash per recipe kind
+static RECIPE_REGISTRY: OnceLock<RwLock<HashMap<(RecipeKind, CraftingInput), CraftingResult, RandomState>>> = OnceLock::new();
 
 #[inline] pub fn init_recipe_registry() {
 	RECIPE_REGISTRY.get_or_init(|| RwLock::new(HashMap::with_hasher(RandomState::new())));
@@ -192,73 +235,18 @@ static RECIPE_REGISTRY: OnceLock<RwLock<HashMap<CraftingInput, CraftingResult, R
 }
 
 /// Gets a read-only reference to the recipe registry
-#[inline] pub fn get_recipes() -> RwLockReadGuard<'static, HashMap<CraftingInput, CraftingResult, RandomState>> {
+#[inline] pub fn get_recipes() -> RwLockReadGuard<'static, HashMap<(RecipeKind, CraftingInput), CraftingResult, RandomState>> {
 	RECIPE_REGISTRY.get()
 		.expect("Recipe registry not initialized")
 		.read().expect("Recipe registry poisoned")
 }
 
+/// Shaped recipes win over shapeless ones when both would match
 #[inline] fn lookup_recipe(input: &CraftingInput) -> Option<CraftingResult> {
 	let registry = get_recipes();
-	
-	// First try exact match
-	if let Some(result) = registry.get(input).cloned() {
-		return Some(result);
-	}
-	
-	// If no exact match and it's a multiple input, try to find a subset match
-	if let CraftingInput::Multiple(input_items) = input {
-		// Try all possible offsets to see if any subset matches a recipe
-		for (recipe_input, recipe_output) in registry.iter() {
-			if let CraftingInput::Multiple(recipe_items) = recipe_input {
-				if is_subset_recipe(input_items, recipe_items) {
-					return Some(recipe_output.clone());
-				}
-			}
-		}
-	}
-	
-	None
-}
-
-/// Checks if the recipe_items are a subset of input_items at any offset
-fn is_subset_recipe(input_items: &[ItemRequirement], recipe_items: &[ItemRequirement]) -> bool {
-	if recipe_items.is_empty() || input_items.len() < recipe_items.len() {
-		return false;
-	}
-	
-	// Find all possible offsets that could align the recipe with the input
-	for input_item in input_items {
-		for recipe_item in recipe_items {
-			let offset_x = input_item.position.x - recipe_item.position.x;
-			let offset_y = input_item.position.y - recipe_item.position.y;
-			
-			// Check if all recipe items exist in input with this offset
-			let mut all_match = true;
-			for recipe_item in recipe_items {
-				let expected_pos = GridPosition::new(
-					recipe_item.position.x + offset_x,
-					recipe_item.position.y + offset_y,
-				);
-				
-				let found = input_items.iter().any(|input_item| {
-					input_item.item_id == recipe_item.item_id && 
-					input_item.position == expected_pos
-				});
-				
-				if !found {
-					all_match = false;
-					break;
-				}
-			}
-			
-			if all_match {
-				return true;
-			}
-		}
-	}
-	
-	false
+	[RecipeKind::Shaped, RecipeKind::Shapeless].into_iter().find_map(|kind| {
+		registry.get(&(kind, input.clone().normalized(kind))).cloned()
+	})
 }
 
 pub fn print_all_recipes() {
@@ -309,10 +297,11 @@ impl CraftingResult {
 	/// Converts the crafting result into a vector of ItemStacks
 	/// Returns None if the result is empty or contains invalid item IDs
 	fn to_item_vec_or_none(&self) -> Option<Vec<ItemStack>> {
-		// Get the item IDs from the enum variant
-		let item_ids: Vec<usize> = match self {
-			Self::Single(item_id) => vec![*item_id],
-			Self::Multiple(items) => items.clone(),
+		// Get the item IDs and counts from the enum variant
+		let item_ids: Vec<(usize, u32)> = match self {
+			Self::Single(item_id) => vec![(*item_id, 1)],
+			Self::Multiple(items) => items.iter().map(|&id| (id, 1)).collect(),
+			Self::Stack { item_id, count } => vec![(*item_id, *count)],
 		};
 
 		// Return None for empty results
@@ -322,9 +311,8 @@ impl CraftingResult {
 
 		// Convert each ID to an ItemStack
 		let mut result: Vec<ItemStack> = Vec::with_capacity(item_ids.len());
-		for &id in &item_ids {
-			// Set stack size to 1 (might make recipes store stack sizes)
-			result.push(ItemStack::from_idx(id).with_stack_size(1));
+		for &(id, count) in &item_ids {
+			result.push(ItemStack::from_idx(id).with_stack_size(count));
 		}
 
 		Some(result)
@@ -340,6 +328,215 @@ impl CraftingResult {
 
 
 
+/// Adds recipes to the registry at runtime (mods, json files), a recipe with the same input replaces the old one
+pub fn register_recipes(new_recipes: impl IntoIterator<Item = Recipe>) {
+	init_recipe_registry();
+	let mut recipes = RECIPE_REGISTRY.get()
+		.expect("Recipe registry not initialized")
+		.write().expect("Recipe registry poisoned");
+	recipes.extend(new_recipes.into_iter().map(Recipe::split));
+}
+
+/// Biggest crafting grid a json pattern can describe
+const MAX_PATTERN_SIZE: usize = 3;
+
+/// Reads recipes from json, either a list of recipes or an object with a "recipes" list :
+/// ```json
+/// [
+/// 	{ "type": "shaped", "pattern": [["plank", "plank"], ["plank", ""]], "output": { "name": "crafting", "count": 1 } },
+/// 	{ "type": "shapeless", "ingredients": ["arrow", "bow"], "output": "bow" }
+/// ]
+/// ```
+/// empty pattern cells are `""` or `null`, the output is an item name or a `{ name, count }` object
+pub fn recipes_from_json(input: &str) -> Result<Vec<Recipe>, JsonError> {
+	let json = JsonParser::parse(input)?;
+	let list = match &json {
+		JsonValue::Array(list) => list,
+		JsonValue::Object(map) => map.get("recipes").and_then(|v| v.as_array())
+			.ok_or_else(|| JsonError::MissingField("'recipes' is missing or not an array".into()))?,
+		_ => return Err(JsonError::Custom("Recipe file must be an array or an object with 'recipes'".into())),
+	};
+	list.iter().enumerate()
+		.map(|(i, value)| recipe_from_json(value).map_err(|e| JsonError::Custom(format!("Recipe #{}: {}", i, e).into())))
+		.collect()
+}
+
+fn recipe_from_json(value: &JsonValue) -> Result<Recipe, JsonError> {
+	let obj = value.as_object()
+		.ok_or_else(|| JsonError::Custom("Recipe must be an object".into()))?;
+	let output = recipe_output_from_json(obj.get("output")
+		.ok_or_else(|| JsonError::MissingField("'output' is missing".into()))?)?;
+
+	match obj.get("type").and_then(|v| v.as_str()) {
+		Some("shaped") => {
+			let pattern = obj.get("pattern").and_then(|v| v.as_array())
+				.ok_or_else(|| JsonError::MissingField("'pattern' is missing or not an array".into()))?;
+			if pattern.is_empty() || pattern.len() > MAX_PATTERN_SIZE {
+				return Err(JsonError::Custom(format!("Pattern must have 1 to {} rows, found {}", MAX_PATTERN_SIZE, pattern.len()).into()));
+			}
+			let mut grid: Vec<Vec<usize>> = Vec::with_capacity(pattern.len());
+			for row in pattern {
+				let row = row.as_array()
+					.ok_or_else(|| JsonError::Custom("Pattern rows must be arrays".into()))?;
+				if row.is_empty() || row.len() > MAX_PATTERN_SIZE {
+					return Err(JsonError::Custom(format!("Pattern rows must have 1 to {} cells, found {}", MAX_PATTERN_SIZE, row.len()).into()));
+				}
+				if grid.first().is_some_and(|first| first.len() != row.len()) {
+					return Err(JsonError::Custom("Pattern rows must all have the same length".into()));
+				}
+				let cells = row.iter().map(|cell| match cell {
+					JsonValue::Null => Ok(0),
+					JsonValue::String(name) if name.is_empty() => Ok(0),
+					JsonValue::String(name) => item_id_from_name(name),
+					_ => Err(JsonError::Custom("Pattern cells must be item names or null".into())),
+				}).collect::<Result<Vec<usize>, JsonError>>()?;
+				grid.push(cells);
+			}
+			if grid.iter().flatten().all(|&id| id == 0) {
+				return Err(JsonError::Custom("Pattern has no items".into()));
+			}
+			Ok(Recipe::shaped(grid, output))
+		},
+		Some("shapeless") => {
+			let ingredients = obj.get("ingredients").and_then(|v| v.as_array())
+				.ok_or_else(|| JsonError::MissingField("'ingredients' is missing or not an array".into()))?;
+			if ingredients.is_empty() || ingredients.len() > MAX_PATTERN_SIZE * MAX_PATTERN_SIZE {
+				return Err(JsonError::Custom(format!("Shapeless recipes need 1 to {} ingredients", MAX_PATTERN_SIZE * MAX_PATTERN_SIZE).into()));
+			}
+			let ids = ingredients.iter().map(|v| {
+				let name = v.as_str().ok_or_else(|| JsonError::Custom("Ingredients must be item names".into()))?;
+				item_id_from_name(name)
+			}).collect::<Result<Vec<usize>, JsonError>>()?;
+			Ok(Recipe::shapeless(&ids, output))
+		},
+		Some(other) => Err(JsonError::Custom(format!("Unknown recipe type: '{}' (expected 'shaped' or 'shapeless')", other).into())),
+		None => Err(JsonError::MissingField("'type' is missing or not a string".into())),
+	}
+}
+
+fn recipe_output_from_json(value: &JsonValue) -> Result<CraftingResult, JsonError> {
+	let (name, count) = match value {
+		JsonValue::String(name) => (name.as_str(), 1),
+		JsonValue::Object(obj) => {
+			let name = obj.get("name").and_then(|v| v.as_str())
+				.ok_or_else(|| JsonError::MissingField("'output.name' is missing or not a string".into()))?;
+			let count = match obj.get("count") {
+				None => 1,
+				Some(v) => v.as_f64()
+					.filter(|n| *n >= 1. && n.fract() == 0.)
+					.ok_or_else(|| JsonError::Custom("'output.count' must be a positive whole number".into()))? as u32,
+			};
+			(name, count)
+		},
+		_ => return Err(JsonError::Custom("'output' must be an item name or an object".into())),
+	};
+	let item_id = item_id_from_name(name)?;
+	Ok(if count == 1 { CraftingResult::Single(item_id) } else { CraftingResult::Stack { item_id, count } })
+}
+
+/// Resolves an item name through the item lut, unknown names are an error instead of the "0" item
+fn item_id_from_name(name: &str) -> Result<usize, JsonError> {
+	if !item_lut_ref().contains_key(name) {
+		return Err(JsonError::Custom(format!("Unknown item name: '{}'", name).into()));
+	}
+	Ok(ItemStack::new(name.to_string()).resource_index())
+}
+
+/// Smelting recipes by item name : (input, output, seconds per item)
+pub const SMELTING_RECIPES: [(&str, &str, f32); 4] = [
+	("iron_ore", "iron", 10.),
+	("gold_ore", "gold", 10.),
+	("silver_ore", "silver", 10.),
+	("fish", "cooked_fish", 5.),
+];
+/// How many seconds one item of fuel burns for
+pub const FUELS: [(&str, f32); 4] = [
+	("plank", 15.),
+	("trunk_mid", 15.),
+	("wood_side", 15.),
+	("coal", 80.),
+];
+
+#[inline] pub fn smelting_result(name: &str) -> Option<(&'static str, f32)> {
+	SMELTING_RECIPES.iter().find(|(input, ..)| *input == name).map(|&(_, output, time)| (output, time))
+}
+#[inline] pub fn fuel_time(name: &str) -> Option<f32> {
+	FUELS.iter().find(|(fuel, _)| *fuel == name).map(|&(_, time)| time)
+}
+
+/// Furnace-like block that turns input items into the output over time while burning fuel.
+/// Fuel only burns while something is actually being smelted
+#[derive(Debug, Clone, PartialEq)]
+pub struct Processor {
+	pub input: ItemContainer,
+	pub fuel: ItemContainer,
+	pub output: ItemContainer,
+	pub progress: f32, // seconds spent on the current item
+	pub fuel_remaining: f32, // seconds left of the burning fuel item
+}
+
+impl Processor {
+	#[inline] pub fn new() -> Self {
+		Self {
+			input: ItemContainer::new(1, 1),
+			fuel: ItemContainer::new(1, 1),
+			output: ItemContainer::new(1, 1),
+			progress: 0.,
+			fuel_remaining: 0.,
+		}
+	}
+
+	/// Advances smelting by `dt` seconds, can finish more than one item if `dt` is long enough.
+	/// A full output pauses the progress, running out of fuel stops it where it is until more fuel is added
+	pub fn tick(&mut self, mut dt: f32) {
+		while dt > 0. {
+			let Some(input) = self.input.first() else { self.progress = 0.; return; };
+			let Some((output_name, time)) = smelting_result(input.name()) else { self.progress = 0.; return; };
+			let output = ItemStack::new(output_name.to_string()).with_stack_size(1);
+			if !self.output_has_room(&output) { return; }
+			if self.fuel_remaining <= 0. && !self.burn_fuel() { return; }
+
+			let left = time - self.progress;
+			let step = dt.min(self.fuel_remaining).min(left);
+			dt -= step;
+			self.fuel_remaining -= step;
+			if step < left {
+				self.progress += step;
+				continue;
+			}
+
+			// One item done
+			self.progress = 0.;
+			let input = self.input.remove(0).and_then(|item| item.remove_from_stack(1));
+			self.input.set(0, input);
+			match self.output.remove(0) {
+				Some(mut existing) => { existing.add_to_stack(1); self.output.set(0, existing.opt()); },
+				None => { self.output.set(0, output.opt()); },
+			}
+		}
+	}
+
+	#[inline] pub fn is_burning(&self) -> bool {
+		self.fuel_remaining > 0.
+	}
+
+	fn output_has_room(&self, result: &ItemStack) -> bool {
+		match self.output.first() {
+			None => true,
+			Some(existing) => existing.can_stack_with(result) && existing.stack() < existing.max_stack_size(),
+		}
+	}
+
+	/// Takes one item from the fuel slot, returns false if there was nothing to burn
+	fn burn_fuel(&mut self) -> bool {
+		let Some(time) = self.fuel.first().and_then(|fuel| fuel_time(fuel.name())) else { return false; };
+		let fuel = self.fuel.remove(0).and_then(|item| item.remove_from_stack(1));
+		self.fuel.set(0, fuel);
+		self.fuel_remaining += time;
+		true
+	}
+}
+
 pub fn init_recipe_lut() {
 	init_recipe_registry();
 	
@@ -355,9 +552,9 @@ pub fn init_recipe_lut() {
 	let two_x_two = vec![vec![num,num],vec![num,num]];
 	
 	recipes.extend([
-		Recipe::new(num,num).split(),
-		Recipe::new(two_x_two,crafting_table).split(),
-		Recipe::new(o_shape,storage).split(),
+		Recipe::shaped(num,num).split(),
+		Recipe::shaped(two_x_two,crafting_table).split(),
+		Recipe::shaped(o_shape,storage).split(),
 		// other recipes
 	]);
 }
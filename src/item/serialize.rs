@@ -415,9 +415,30 @@ impl BinarySerializable for CustomData {
 	}
 }
 
+/// Items start with this where the name length used to be (no name is that long), so items saved before the version still read
+const ITEM_MARK: u16 = u16::MAX;
+/// Layout of the fields after the mark and this version byte: 1 added durability. Newer ones are refused rather than misread
+pub const ITEM_VERSION: u8 = 1;
+const ITEM_HEADER_SIZE: usize = u16::BINARY_SIZE + 1;
+
+/// Name, stack count and custom data, the fields every layout starts with, and where they end
+fn item_fields(bytes: &[u8]) -> Option<(String, u32, Option<Box<CustomData>>, usize)> {
+	let name = String::from_binary(bytes)?;
+	let mut offset = name.binary_size();
+	let stack = u32::from_binary(bytes.get(offset..offset + u32::BINARY_SIZE)?)?;
+	offset += u32::BINARY_SIZE;
+	let data = Option::<Box<CustomData>>::from_binary(bytes.get(offset..)?)?;
+	offset += data.binary_size();
+	Some((name, stack, data, offset))
+}
+
 impl BinarySerializable for ItemStack {
 	fn to_binary(&self) -> Vec<u8> {
-		let mut data = Vec::new();
+		let mut data = Vec::with_capacity(self.binary_size());
+		
+		// Mark and layout version
+		data.extend_from_slice(&ITEM_MARK.to_binary());
+		data.push(ITEM_VERSION);
 		
 		// Serialize name (string)
 		data.extend_from_slice(&self.name().to_string().to_binary());
@@ -428,33 +449,37 @@ impl BinarySerializable for ItemStack {
 		// Serialize custom data (optional data)
 		data.extend_from_slice(&self.data.to_binary());
 		
+		// Serialize durability (1 byte flag + optional u16)
+		data.extend_from_slice(&self.durability.to_binary());
+		
 		data
 	}
 	
 	fn from_binary(bytes: &[u8]) -> Option<Self> {
-		if bytes.is_empty() { return None; }
-		
-		let mut offset = 0;
-		
-		// Deserialize name
-		let name = String::from_binary(&bytes[offset..])?;
-		offset += name.binary_size();
+		// Items from before the version are the first three fields and nothing else, tools come back unworn
+		if u16::from_binary(bytes.get(..u16::BINARY_SIZE)?)? != ITEM_MARK {
+			let (name, stack, data, _) = item_fields(bytes)?;
+			let mut item = ItemStack::create(name, stack, data, None);
+			item.durability = item.max_durability();
+			return Some(item);
+		}
+		if *bytes.get(u16::BINARY_SIZE)? != ITEM_VERSION { return None; }
+		let bytes = &bytes[ITEM_HEADER_SIZE..];
 		
-		// Deserialize stack count
-		if offset >= bytes.len() { return None; }
-		let stack = u32::from_binary(&bytes[offset..offset+u32::BINARY_SIZE])?;
-		offset += u32::BINARY_SIZE;
+		let (name, stack, data, offset) = item_fields(bytes)?;
 		
-		// Deserialize custom data
-		let data = Option::<Box<CustomData>>::from_binary(&bytes[offset..])?;
+		// Deserialize durability
+		let durability = Option::<u16>::from_binary(bytes.get(offset..)?)?;
 		
-		Some(ItemStack::create(name,stack,data))
+		Some(ItemStack::create(name,stack,data,durability))
 	}
 	
 	fn binary_size(&self) -> usize {
-		let mut size = self.name().to_string().binary_size(); // string bytes
+		let mut size = ITEM_HEADER_SIZE; // mark and version
+		size += self.name().to_string().binary_size(); // string bytes
 		size += u32::BINARY_SIZE; // stack count
 		size += self.data.binary_size(); // optional boxed data
+		size += self.durability.binary_size(); // optional u16
 		size
 	}
 }
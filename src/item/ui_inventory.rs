@@ -2,7 +2,7 @@
 use crate::item::inventory::{Inventory, ItemContainer, AreaType, Slot};
 use crate::ui::{manager::{UIManager, UIState, FocusState}, element::UIElement};
 use crate::item::items::ItemStack;
-use crate::utils::color::Solor;
+use crate::utils::color::{Border, Solor};
 use crate::ext::ptr;
 use glam::Vec2;
 
@@ -461,7 +461,7 @@ impl UIManager {
 		let panel = UIElement::panel(self.next_id())
 			.with_position(layout.panel_position)
 			.with_size(layout.panel_size)
-			.with_style(&inv_config.panel_bg)
+			.with_element_style(&inv_config.panel_bg)
 			.with_z_index(3);
 		self.add_element(panel);
 	}
@@ -511,10 +511,12 @@ impl UIManager {
 		
 		// If we get here, either no focused element or wrong type
 		let id = self.next_id();
+		let theme = &ptr::get_settings().ui_theme;
 		let slot = UIElement::panel(id)
 			.with_position(Vec2::new(x, y))
 			.with_size(Vec2::new(SLOT, SLOT))
-			.with_style(&ptr::get_settings().ui_theme.panels.nice.with_border_width(0.012))
+			.with_style(theme, "panels.nice")
+			.with_border(Border::colf(theme.panels.nice.border.color, 0.012))
 			.with_z_index(4);
 		self.add_element(slot);
 
@@ -554,7 +556,7 @@ impl UIManager {
 				let slot = UIElement::panel(self.next_id())
 					.with_position(Vec2::new(x, y))
 					.with_size(Vec2::new(SLOT, SLOT))
-					.with_style(&config.inv_config.get_style(area.name))
+					.with_element_style(config.inv_config.get_style(area.name))
 					.with_z_index(5);
 				self.add_element(slot);
 			}
@@ -578,7 +580,7 @@ impl UIManager {
 		let item_display = UIElement::image(id, item.icon_path().into())
 			.with_position(Vec2::new(x, y))
 			.with_size(Vec2::new(SLOT, SLOT))
-			.with_style(&ptr::get_settings().ui_theme.images.basic)
+			.with_style(&ptr::get_settings().ui_theme, "images.basic")
 			.with_z_index(z);
 		self.add_element(item_display);
 
@@ -12,6 +12,8 @@ pub mod block {
 	pub mod storage;
 	// Block entity
 	pub mod entity;
+	// light levels and propagation
+	pub mod light;
 }
 /// Debug, test related
 #[cfg(test)]
@@ -22,6 +24,9 @@ pub mod debug {
 	pub mod json_serial;
 	pub mod serialize_item;
 	pub mod physics;
+	pub mod ui;
+	pub mod input;
+	pub mod audio;
 }
 // Extra things that did not fit anywhere else
 pub mod ext {
@@ -42,6 +47,8 @@ pub mod ext {
 pub mod fs {
 	/// Compiled resources embedded in the binary.
 	pub mod rs;
+	/// Resources read from disk in debug builds, from the binary otherwise.
+	pub mod resources;
 	/// File system operations (reading/writing to disk).
 	pub mod fs;
 	/// Custom JSON parser (alternative to `serde_json`).
@@ -55,6 +62,8 @@ pub mod game {
 	pub mod player;
 	// game-state with seed and stuff
 	pub mod state;
+	// other players seen over the network
+	pub mod remote;
 }
 /// Item + Inventory and related stuffs
 pub mod item {
@@ -81,6 +90,8 @@ pub mod item {
 pub mod mods {
 	// mod loading and wasm sandbox 
 	pub mod api;
+	// mod.json reading and load order
+	pub mod manifest;
 	// this is an overlay made by mods so they would execute instead of the real rust functions
 	pub mod over;
 }
@@ -92,6 +103,19 @@ pub mod network {
 	pub mod discovery;
 	// the networking system and extra utilities for basic stuff
 	pub mod types;
+	// the trait every way of connecting players goes through
+	pub mod backend;
+	// LAN discovery and direct IP, the default backend
+	pub mod direct;
+	// keeps the worlds of the players the same (block edits and chunk syncs)
+	pub mod sync;
+	// chunks sent to a joining player a few at a time
+	pub mod stream;
+	// how game messages are written into packets (binary, compressed or JSON)
+	pub mod wire;
+	// Steam lobbies and P2P
+	#[cfg(feature = "steam")]
+	pub mod steam;
 }
 /// Physics stuff like gravity ...
 pub mod physic {
@@ -108,6 +132,7 @@ pub mod render {
 	pub mod world;
 	pub mod debug;
 	pub mod skybox;
+	pub mod screenshot;
 }
 // Ui and related
 pub mod ui {
@@ -119,11 +144,18 @@ pub mod ui {
 	pub mod dialog;
 	pub mod events;
 	pub mod text;
+	pub mod chat;
+	// hover text shown after the mouse rests on something
+	pub mod tooltip;
+	// fps, chunk and timing stats in the corner, toggled with F3
+	pub mod overlay;
 }
 /// Utility things, like helper Structs
 pub mod utils {
 	/// Input handling (keyboard/mouse).
 	pub mod input;
+	/// Gamepad sticks and buttons (read with the gamepad feature).
+	pub mod gamepad;
 	/// Math utilities (Noise gen, lerping).
 	pub mod math;
 	// my custom color struct with quick init
@@ -149,6 +181,13 @@ pub mod world {
 	pub mod handler;
 	pub mod threading;
 	pub mod data;
+	pub mod biome;
+	pub mod border;
+	pub mod structure;
+	pub mod generation;
+	pub mod autosave;
+	pub mod history;
+	pub mod schematic;
 }
 /// Main event handler (focused on the user input)
 mod event_handler;
@@ -172,6 +211,7 @@ pub struct State<'a> {
 	ui_manager: ui::manager::UIManager,
 	texture_manager: render::texture::TextureManager,
 	fps: ext::timer::FPSCounter,
+	frame_times: ext::timer::FrameTimes, // recent frames for the percentiles in the debug overlay
 	is_world_running: bool,
 }
 
@@ -183,6 +223,7 @@ pub struct RenderContext<'a> {
 	size: winit::dpi::PhysicalSize<u32>,
 	layouts: Box<[wgpu::BindGroupLayout]>,
 	skybox: render::skybox::Skybox,
+	sample_counts: Vec<u32>, // MSAA counts the device can render the world with
 }
 
 impl<'a> State<'a> {
@@ -212,10 +253,13 @@ impl<'a> State<'a> {
 			..wgpu::Limits::default()
 		};
 
+		// Line polygon mode is only for the wireframe debug view, so it is optional,
+		// the adapter's own format features allow more MSAA counts than the guaranteed ones
+		let optional_features = adapter.features() & (wgpu::Features::POLYGON_MODE_LINE | wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES);
 		let (device, queue): (wgpu::Device, wgpu::Queue) = adapter
 			.request_device(
 				&wgpu::DeviceDescriptor {
-					required_features: wgpu::Features::SHADER_INT64,
+					required_features: wgpu::Features::SHADER_INT64 | optional_features,
 					required_limits,
 					..Default::default()
 				},
@@ -247,14 +291,19 @@ impl<'a> State<'a> {
 
 		let layouts = make_layout(&device);
 
-		let texture_manager = render::texture::TextureManager::new(&device, &queue, &surface_config, &layouts[0], &layouts[4]);
+		let sample_counts = render::texture::supported_sample_counts(&adapter, device.features(), surface_format);
+		let sample_count = render::texture::nearest_sample_count(ext::ptr::get_settings().render_config.msaa_samples, &sample_counts);
+		// The setting only ever holds a count this device can do
+		ext::ptr::get_settings().render_config.msaa_samples = sample_count;
+
+		let texture_manager = render::texture::TextureManager::new(&device, &queue, &surface_config, &layouts[0], &layouts[4], sample_count);
 
-		let pipeline = render::pipeline::Pipeline::new(&device, &surface_config, &layouts);
+		let pipeline = render::pipeline::Pipeline::new(&device, &surface_config, &layouts, sample_count);
 
 		let mut ui_manager = ui::manager::UIManager::new(&device, &surface_config, &queue);
 		ui_manager.setup_ui();
 
-		let skybox = render::skybox::Skybox::new(&device, &queue, &layouts[3],"basic_skybox.jpg").expect("basic skybox should work");
+		let skybox = render::skybox::Skybox::new(&device, &queue, &layouts[3], render::skybox::DAY_SKYBOX, render::skybox::NIGHT_SKYBOX).expect("basic skybox should work");
 		
 		let render_context: RenderContext = RenderContext{
 			surface,
@@ -264,6 +313,7 @@ impl<'a> State<'a> {
 			size,
 			layouts,
 			skybox,
+			sample_counts,
 		};
 
 		Self {
@@ -275,6 +325,7 @@ impl<'a> State<'a> {
 			texture_manager,
 			ui_manager,
 			fps: ext::timer::FPSCounter::default(),
+			frame_times: ext::timer::FrameTimes::new(),
 			is_world_running: false,
 		}
 	}
@@ -313,6 +364,58 @@ impl<'a> State<'a> {
 	pub fn texture_manager(&self) -> &render::texture::TextureManager {
 		&self.texture_manager
 	}
+	/// Swaps the block sampler after `render_config.anisotropy` changed
+	#[inline]
+	pub fn apply_anisotropy(&mut self) {
+		let anisotropy = ext::ptr::get_settings().render_config.anisotropy;
+		self.texture_manager.set_anisotropy(&self.render_context.device, &self.render_context.layouts[0], anisotropy);
+	}
+	/// Saves the last rendered frame (world, ui and all) as a png
+	#[inline]
+	pub fn capture_screenshot(&self, path: &std::path::Path) -> std::io::Result<()> {
+		render::screenshot::save_texture(self.device(), self.queue(), self.texture_manager.frame_texture(), path)
+	}
+	/// MSAA sample counts the device can render the world with, 1 (off) is always there
+	#[inline]
+	pub fn sample_counts(&self) -> &[u32] {
+		&self.render_context.sample_counts
+	}
+	/// Switches MSAA to `render_config.msaa_samples` (or the nearest count the device supports, which goes back into the setting)
+	#[inline]
+	pub fn apply_msaa(&mut self) {
+		let render_config = &mut ext::ptr::get_settings().render_config;
+		let sample_count = render::texture::nearest_sample_count(render_config.msaa_samples, &self.render_context.sample_counts);
+		render_config.msaa_samples = sample_count;
+		let RenderContext { device, surface_config, layouts, .. } = &self.render_context;
+		// The pipelines first, if their shaders don't build everything stays at the old count
+		if let Err(e) = self.pipeline.set_sample_count(device, surface_config, layouts, sample_count) {
+			println!("Anti-aliasing not changed: {}", e);
+			render_config.msaa_samples = self.pipeline.sample_count();
+			return;
+		}
+		self.texture_manager.set_sample_count(device, surface_config, sample_count);
+	}
+	/// Turns bloom on or off and picks up its threshold and intensity from `render_config`
+	#[inline]
+	pub fn apply_bloom(&mut self) {
+		let render_config = &ext::ptr::get_settings().render_config;
+		self.pipeline.set_bloom(render_config.bloom, render_config.bloom_threshold, render_config.bloom_intensity);
+	}
+	/// Moves the block outline to the targeted block, None hides it
+	#[inline]
+	pub fn update_block_highlight(&mut self, target: Option<glam::IVec3>) {
+		self.pipeline.highlight.set_target(&self.render_context.device, &self.render_context.queue, target);
+	}
+	/// Rebuilds the world border grid around the eye, None hides it
+	#[inline]
+	pub fn update_world_border(&mut self, border: Option<world::border::WorldBorder>, eye: glam::Vec3) {
+		self.pipeline.border.update(&self.render_context.device, &self.render_context.queue, border, eye);
+	}
+	/// Rebuilds the remote player boxes at their current (interpolated) positions
+	#[inline]
+	pub fn update_avatars(&mut self) {
+		self.pipeline.avatars.update(&self.render_context.device, &self.render_context.queue, ptr::get_gamestate().remote_players());
+	}
 	#[inline]
 	pub fn ui_manager(&self) -> &ui::manager::UIManager {
 		&self.ui_manager
@@ -339,9 +442,11 @@ impl<'a> State<'a> {
 		if self.is_world_running {
 			ptr::get_gamestate().player_mut().resize(new_size);
 		}
+		self.ui_manager.set_aspect(new_size.width as f32 / new_size.height as f32);
 		// Clone the values to avoid holding borrows
 		self.render_context.surface.configure(self.device(), self.surface_config());
-		*self.texture_manager.depth_texture_mut() = render::texture::create_depth_texture(self.device(), self.surface_config(),"depth_texture");
+		self.texture_manager.resize(&self.render_context.device, &self.render_context.surface_config, &self.render_context.layouts[4]);
+		self.pipeline.resize(new_size.width, new_size.height);
 		
 		true
 	}
@@ -350,13 +455,21 @@ impl<'a> State<'a> {
 		let current_time: std::time::Instant = std::time::Instant::now();
 		let delta_seconds: f32 = (current_time - self.previous_frame_time).as_secs_f32();
 		self.previous_frame_time = current_time;
+		self.frame_times.record(delta_seconds);
 		network::api::update_network(); // theoretically it should run in other thread so calling it each frame should not be a problem ...
+		self.handle_gamepad(delta_seconds);
 		
 		if self.is_world_running {
+			self.update_time_of_day(delta_seconds);
 			let game_state = ptr::get_gamestate();
+			// Sitting in a menu isn't playing
+			if matches!(self.ui_manager.state, ui::manager::UIState::InGame | ui::manager::UIState::Inventory(_)) {
+				game_state.play_session_mut().tick(delta_seconds);
+			}
 			let movement_delta = {
+				let world = ptr::get_gamestate().world();
 				let player = &mut game_state.player_mut();
-				player.update(delta_seconds, self.queue())
+				player.update(delta_seconds, world, self.queue())
 			};
 
 			// Update both player and camera positions in one operation
@@ -364,9 +477,15 @@ impl<'a> State<'a> {
 				let player = &mut game_state.player_mut();
 				player.append_position(movement_delta);
 			}
+			self.texture_manager.update_animations(&self.render_context.queue, delta_seconds);
+			game_state.remote_players_mut().advance(delta_seconds);
+			self.update_autosave(delta_seconds);
+			mods::api::dispatch_tick(delta_seconds);
+			// Everything edited this frame is one undo step
+			ptr::get_gamestate().world_mut().end_edit_group();
 		}
 		if self.ui_manager.visibility {
-			self.ui_manager.update(&self.render_context.device, &self.render_context.queue, delta_seconds);
+			self.ui_manager.update(&self.render_context.device, &self.render_context.queue, delta_seconds, &self.frame_times);
 		}
 	}
 	#[inline]
@@ -374,6 +493,7 @@ impl<'a> State<'a> {
 		self.fps.update();
 		
 		// Actual rendering
+		let _timer = ext::timer::ScopeTimer::new("render");
 		render::pipeline::render_all(self)
 	}
 }
@@ -386,7 +506,9 @@ pub async fn run() {
 
 	ext::ptr::init_settings();
 	let settings = ext::ptr::get_settings();
+	*settings = ext::settings::Settings::load_or_default(&ext::settings::Settings::path());
 	settings.remake_window_config(monitor_size);
+	settings.key_bindings = ext::settings::KeyBindings::load();
 
 	// Initialize once at startup
 	ext::audio::init_audio().expect("Failed to initialize audio");
@@ -447,7 +569,7 @@ pub async fn run() {
 		let elapsed = now - last_tick;
 		if elapsed >= tick_iterval && state.is_world_running {
 			last_tick = now;
-			block::extra::update_full_world();
+			block::extra::update_full_world(elapsed.as_secs_f32());
 			/*
 			// Handle potential multiple ticks if we're behind
 			let mut accumulated_time = elapsed;
@@ -513,6 +635,17 @@ fn make_layout(device: &wgpu::Device) -> Box<[wgpu::BindGroupLayout]> {
 				ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
 				count: None,
 			},
+			// Material -> texture layer table, only the animated materials ever change
+			wgpu::BindGroupLayoutEntry {
+				binding: 2,
+				visibility: wgpu::ShaderStages::VERTEX,
+				ty: wgpu::BindingType::Buffer {
+					ty: wgpu::BufferBindingType::Storage { read_only: true },
+					has_dynamic_offset: false,
+					min_binding_size: None,
+				},
+				count: None,
+			},
 		],
 	});
 	let chunk_bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
@@ -547,6 +680,27 @@ fn make_layout(device: &wgpu::Device) -> Box<[wgpu::BindGroupLayout]> {
 				},
 				count: None,
 			},
+			wgpu::BindGroupLayoutEntry {
+				binding: 2,
+				visibility: wgpu::ShaderStages::FRAGMENT,
+				ty: wgpu::BindingType::Texture {
+					sample_type: wgpu::TextureSampleType::Float { filterable: true },
+					view_dimension: wgpu::TextureViewDimension::D2,
+					multisampled: false,
+				},
+				count: None,
+			},
+			// Day -> night blend factor
+			wgpu::BindGroupLayoutEntry {
+				binding: 3,
+				visibility: wgpu::ShaderStages::FRAGMENT,
+				ty: wgpu::BindingType::Buffer {
+					ty: wgpu::BufferBindingType::Uniform,
+					has_dynamic_offset: false,
+					min_binding_size: None,
+				},
+				count: None,
+			},
 		],
 	});
 	let camera_bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
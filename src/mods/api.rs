@@ -1,6 +1,14 @@
 
 use wasmtime::*;
-use std::collections::HashMap;
+use crate::block::{main::{Block, Material}, math::{BlockRotation, ChunkCoord}};
+use crate::render::texture::{self, material_count, MOD_MATERIALS};
+use crate::item::{item_lut::ItemComp, items::register_item, inventory::Slot};
+use crate::utils::string::MutStr;
+use crate::ext::ptr;
+use crate::mods::manifest;
+use glam::IVec3;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicPtr, Ordering};
 use std::error::Error;
 use std::{fmt,fs};
 use std::path::{Path,PathBuf};
@@ -22,6 +30,12 @@ pub enum WasmError {
 	InvalidModuleName,
 	BulkError { errors: Vec<(String, WasmError)> },
 	FunctionNotFound { function: String },
+	BlockRegistration { name: String, reason: String },
+	OutOfFuel { module: String, function: String },
+	ModDisabled { module: String },
+	Manifest { path: String, error: String },
+	MissingDependency { module: String, dependency: String },
+	DependencyCycle { modules: Vec<String> },
 	Unexpected,
 }
 
@@ -39,6 +53,12 @@ impl fmt::Display for WasmError {
 			Self::InvalidModuleName => write!(f, "Invalid module name"),
 			Self::Unexpected => write!(f, "Unexpected error occurred"),
 			Self::FunctionNotFound { function } => write!(f, "Function Not Found: {}", function),
+			Self::BlockRegistration { name, reason } => write!(f, "Can't register block '{}': {}", name, reason),
+			Self::OutOfFuel { module, function } => write!(f, "{}::{} ran out of fuel", module, function),
+			Self::ModDisabled { module } => write!(f, "Module '{}' is disabled", module),
+			Self::Manifest { path, error } => write!(f, "Bad manifest '{}': {}", path, error),
+			Self::MissingDependency { module, dependency } => write!(f, "Module '{}' needs '{}', which is missing or failed to load", module, dependency),
+			Self::DependencyCycle { modules } => write!(f, "Dependency cycle between: {}", modules.join(", ")),
 			Self::BulkError { errors } => {
 				write!(f, "Multiple errors occurred:\n")?;
 				for (module, error) in errors {
@@ -70,6 +90,99 @@ impl From<MemoryAccessError> for WasmError {
 	}
 }
 
+/// Default of `ModConfig::fuel_per_call`, wasm instructions a mod may run per call into it.
+/// A runaway loop traps instead of freezing the game, normal work (even a big on_tick) stays far below
+pub const MOD_FUEL: u64 = 50_000_000;
+/// Blocks a mod may change per call into it, `host_set_block` refuses the rest
+pub const MOD_BLOCK_EDITS: u32 = 4096;
+
+/// Game events mods can listen to with `register_event_handler`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ModEvent {
+	Tick, // on_tick(dt: f32), once a frame while a world runs
+	BlockBreak, // on_block_break(x, y, z, block), the block that was broken packed by `pack_block`
+	BlockPlace, // on_block_place(x, y, z, block)
+}
+impl ModEvent {
+	pub const ALL: [Self; 3] = [Self::Tick, Self::BlockBreak, Self::BlockPlace];
+	#[inline] pub const fn name(self) -> &'static str {
+		match self {
+			Self::Tick => "on_tick",
+			Self::BlockBreak => "on_block_break",
+			Self::BlockPlace => "on_block_place",
+		}
+	}
+	#[inline] pub fn from_name(name: &str) -> Option<Self> {
+		Self::ALL.into_iter().find(|event| event.name() == name)
+	}
+}
+
+/// A mod function called on an event, turned off for good once it traps
+#[derive(Debug, Clone, PartialEq)]
+pub struct EventHandler {
+	pub event: ModEvent,
+	pub module: String,
+	pub func: String,
+	pub enabled: bool,
+}
+
+/// What the host functions may still do for the mod during the current call, and the event handlers of every mod
+#[derive(Debug, Clone)]
+pub struct ModHost {
+	pub block_edits: u32, // host_set_block calls left
+	pub module: String, // the mod being called
+	pub handlers: Vec<EventHandler>, // in registration order, mods are loaded sorted by name so it is the same every run
+}
+impl ModHost {
+	#[inline] pub const fn new() -> Self {
+		Self { block_edits: MOD_BLOCK_EDITS, module: String::new(), handlers: Vec::new() }
+	}
+
+	/// Adds a handler for the mod being called, false if it already had that function on the event
+	pub fn register(&mut self, event: ModEvent, func: &str) -> bool {
+		let module = self.module.clone();
+		self.register_for(&module, event, func)
+	}
+	pub fn register_for(&mut self, module: &str, event: ModEvent, func: &str) -> bool {
+		if self.handlers.iter().any(|h| h.event == event && h.module == module && h.func == func) { return false; }
+		self.handlers.push(EventHandler { event, module: module.to_string(), func: func.to_string(), enabled: true });
+		true
+	}
+	/// Enabled handlers of the event in dispatch order, as (index, module, function)
+	pub fn handlers_of(&self, event: ModEvent) -> Vec<(usize, String, String)> {
+		self.handlers.iter().enumerate()
+			.filter(|(_, h)| h.event == event && h.enabled)
+			.map(|(i, h)| (i, h.module.clone(), h.func.clone()))
+			.collect()
+	}
+}
+
+/// Results of `register_event_handler`
+pub const REGISTER_ADDED: i32 = 1;
+pub const REGISTER_DUPLICATE: i32 = 0;
+pub const REGISTER_UNKNOWN_EVENT: i32 = -1;
+pub const REGISTER_NO_FUNCTION: i32 = -2; // the mod doesn't export the function
+
+/// Results of `host_set_block`
+pub const SET_BLOCK_CHANGED: i32 = 1;
+pub const SET_BLOCK_UNCHANGED: i32 = 0; // same block already there or outside the world border
+pub const SET_BLOCK_INVALID: i32 = -1; // unknown material, bad rotation byte or the chunk isn't loaded
+pub const SET_BLOCK_LIMITED: i32 = -2; // out of edits for this call
+pub const SET_BLOCK_NO_WORLD: i32 = -3;
+
+/// Block as `host_get_block` hands it to a mod: material in the low 16 bits, rotation byte above
+#[inline] pub const fn pack_block(block: Block) -> u32 {
+	block.material.inner() as u32 | (block.rotation.as_u8() as u32) << 16
+}
+
+/// Checks what a mod asked for, None if the material doesn't exist or the rotation byte isn't one of the 24 rotations
+pub fn block_from_mod(material: i32, rotation: i32) -> Option<Block> {
+	let material = u16::try_from(material).ok()?;
+	if material as usize >= material_count() { return None; }
+	let rotation = BlockRotation::from_u8(u8::try_from(rotation).ok()?)?;
+	Some(Block::from(Material(material), rotation))
+}
+
 #[derive(Clone)]
 pub struct ModuleData {
 	pub instance: Instance,
@@ -79,17 +192,21 @@ pub struct ModuleData {
 
 pub struct WasmRuntime {
 	pub engine: Engine,
-	pub store: Store<()>,
-	pub linker: Linker<()>,
+	pub store: Store<ModHost>,
+	pub linker: Linker<ModHost>,
 	pub instances: HashMap<String, ModuleData>,
+	pub fuel_per_call: u64, // refilled before every call
+	pub disabled: HashSet<String>, // mods that ran out of fuel, they aren't called anymore
 }
 
 #[allow(dead_code)]
 impl WasmRuntime {
 	pub fn new() -> Result<Self, WasmError> {
-		let engine = Engine::default();
+		let mut config = Config::new();
+		config.consume_fuel(true);
+		let engine = Engine::new(&config)?;
 		let mut linker = Linker::new(&engine);
-		let store = Store::new(&engine, ());
+		let store = Store::new(&engine, ModHost::new());
 
 		// Setup common host functions
 		Self::setup_host_functions(&mut linker)?;
@@ -99,30 +216,154 @@ impl WasmRuntime {
 			store,
 			linker,
 			instances: HashMap::new(),
+			fuel_per_call: MOD_FUEL,
+			disabled: HashSet::new(),
 		})
 	}
 	
-	fn setup_host_functions(linker: &mut Linker<()>) -> Result<(), WasmError> {
+	/// Gives the mod a fresh budget of fuel and block edits, done before every call into it
+	fn refuel(&mut self, module: &str) -> Result<(), WasmError> {
+		if self.disabled.contains(module) {
+			return Err(WasmError::ModDisabled { module: module.to_string() });
+		}
+		let host = self.store.data_mut();
+		host.block_edits = MOD_BLOCK_EDITS;
+		host.module.clear();
+		host.module.push_str(module);
+		let remaining = self.store.consume_fuel(0)?;
+		if remaining < self.fuel_per_call {
+			self.store.add_fuel(self.fuel_per_call - remaining)?;
+		} else {
+			self.store.consume_fuel(remaining - self.fuel_per_call)?;
+		}
+		Ok(())
+	}
+
+	/// Turns the result of a call into the mod into ours, a mod that ran out of fuel is disabled
+	/// since it would most likely do the same on the next call
+	fn checked<T, E: Into<WasmError>>(&mut self, module: &str, func: &str, result: Result<T, E>) -> Result<T, WasmError> {
+		let error = match result {
+			Ok(value) => return Ok(value),
+			Err(e) => e.into(),
+		};
+		if matches!(&error, WasmError::Wasmtime { error } if is_out_of_fuel(error)) {
+			println!("[WASM] {}::{} ran out of fuel ({} per call), the mod is disabled", module, func, self.fuel_per_call);
+			self.disable_module(module);
+			return Err(WasmError::OutOfFuel { module: module.to_string(), function: func.to_string() });
+		}
+		Err(error)
+	}
+
+	/// Stops calling the mod, its event handlers included. It stays loaded so `enable_module` can bring it back
+	pub fn disable_module(&mut self, module: &str) {
+		self.disabled.insert(module.to_string());
+		for handler in self.store.data_mut().handlers.iter_mut().filter(|h| h.module == module) {
+			handler.enabled = false;
+		}
+	}
+	pub fn enable_module(&mut self, module: &str) {
+		self.disabled.remove(module);
+		for handler in self.store.data_mut().handlers.iter_mut().filter(|h| h.module == module) {
+			handler.enabled = true;
+		}
+	}
+	#[inline] pub fn is_disabled(&self, module: &str) -> bool {
+		self.disabled.contains(module)
+	}
+
+	fn setup_host_functions(linker: &mut Linker<ModHost>) -> Result<(), WasmError> {
 		// Define the log function
-		linker.func_wrap( "env", "log", |mut caller: Caller<'_, ()>, ptr: i32, len: i32| {
-				let memory = caller.get_export("memory")
-					.and_then(|e| e.into_memory())
-					.ok_or_else(|| wasmtime::Error::msg("memory not found"))?;
-					
-				let mut buffer = vec![0u8; len as usize];
-				memory.read(&caller, ptr as usize, &mut buffer)
-					.map_err(|e| wasmtime::Error::msg(format!("memory read failed: {}", e)))?;
-					
-				let msg = String::from_utf8(buffer)
-					.map_err(|e| wasmtime::Error::msg(format!("invalid utf-8: {}", e)))?;
+		linker.func_wrap( "env", "log", |mut caller: Caller<'_, ModHost>, ptr: i32, len: i32| {
+				let msg = read_guest_string(&mut caller, ptr, len)?;
 				println!("[WASM] {}", msg);
 				
 				Ok(())
 			}
 		)?;
 		
+		// Define the recipe registering function, takes a json string (see `recipes_from_json`)
+		// returns how many recipes were added or -1 if the json was invalid
+		linker.func_wrap( "env", "register_recipes", |mut caller: Caller<'_, ModHost>, ptr: i32, len: i32| -> Result<i32, wasmtime::Error> {
+				let json = read_guest_string(&mut caller, ptr, len)?;
+				match crate::item::recipes::recipes_from_json(&json) {
+					Ok(recipes) => {
+						let count = recipes.len() as i32;
+						crate::item::recipes::register_recipes(recipes);
+						Ok(count)
+					},
+					Err(e) => {
+						println!("[WASM] invalid recipes: {}", e);
+						Ok(-1)
+					},
+				}
+			}
+		)?;
+
+		// Block at a world position packed by `pack_block`, air where nothing is loaded
+		linker.func_wrap( "env", "host_get_block", |x: i32, y: i32, z: i32| -> u32 {
+				if !ptr::has_gamestate() { return pack_block(Block::default()); }
+				pack_block(ptr::get_gamestate().world().get_block(IVec3::new(x, y, z)))
+			}
+		)?;
+
+		// Places a block in a loaded chunk, returns one of the `SET_BLOCK_*` codes.
+		// Goes through `World::set_block` so the chunk gets relit and remeshed like after a player edit
+		linker.func_wrap( "env", "host_set_block", |mut caller: Caller<'_, ModHost>, x: i32, y: i32, z: i32, material: i32, rotation: i32| -> i32 {
+				if !ptr::has_gamestate() { return SET_BLOCK_NO_WORLD; }
+				let host = caller.data_mut();
+				if host.block_edits == 0 { return SET_BLOCK_LIMITED; }
+				let Some(block) = block_from_mod(material, rotation) else { return SET_BLOCK_INVALID; };
+
+				let world = ptr::get_gamestate().world_mut();
+				let pos = IVec3::new(x, y, z);
+				if world.get_chunk(&ChunkCoord::from_world_pos(pos)).is_none() { return SET_BLOCK_INVALID; }
+				if world.get_block(pos) == block || !world.in_border(pos) { return SET_BLOCK_UNCHANGED; }
+				host.block_edits -= 1;
+				world.set_block(pos, block);
+				SET_BLOCK_CHANGED
+			}
+		)?;
+
+		// Subscribes a function exported by the calling mod to an event (see `ModEvent::name`),
+		// returns one of the `REGISTER_*` codes
+		linker.func_wrap( "env", "register_event_handler", |mut caller: Caller<'_, ModHost>, event_ptr: i32, event_len: i32, func_ptr: i32, func_len: i32| -> Result<i32, wasmtime::Error> {
+				let event = read_guest_string(&mut caller, event_ptr, event_len)?;
+				let func = read_guest_string(&mut caller, func_ptr, func_len)?;
+				let Some(event) = ModEvent::from_name(&event) else { return Ok(REGISTER_UNKNOWN_EVENT); };
+				if caller.get_export(&func).and_then(|e| e.into_func()).is_none() { return Ok(REGISTER_NO_FUNCTION); }
+				Ok(if caller.data_mut().register(event, &func) { REGISTER_ADDED } else { REGISTER_DUPLICATE })
+			}
+		)?;
+
+		// Adds a block with a texture from the mods folder (see `register_block`), rows or cols of 0 make it a plain block.
+		// Returns the new material or -1 if the block couldn't be added
+		linker.func_wrap( "env", "register_block", |mut caller: Caller<'_, ModHost>, name_ptr: i32, name_len: i32, path_ptr: i32, path_len: i32, rows: i32, cols: i32| -> Result<i32, wasmtime::Error> {
+				let name = read_guest_string(&mut caller, name_ptr, name_len)?;
+				let path = read_guest_string(&mut caller, path_ptr, path_len)?;
+				let Some(texture_path) = mod_texture_path(&path) else {
+					println!("[WASM] block '{}' texture path leaves the mods folder: {}", name, path);
+					return Ok(-1);
+				};
+				let storage = match (u8::try_from(rows), u8::try_from(cols)) {
+					(Ok(0), _) | (_, Ok(0)) => None,
+					(Ok(rows), Ok(cols)) => Some((rows, cols).into()),
+					_ => {
+						println!("[WASM] block '{}' has an invalid storage size {}x{}", name, rows, cols);
+						return Ok(-1);
+					},
+				};
+				match register_block(&name, &texture_path, storage) {
+					Ok(material) => Ok(material.inner() as i32),
+					Err(e) => {
+						println!("[WASM] {}", e);
+						Ok(-1)
+					},
+				}
+			}
+		)?;
+
 		// Define alloc function - using WASM memory allocation
-		linker.func_wrap( "env", "alloc", |mut caller: Caller<'_, ()>, size: i32| -> Result<i32, wasmtime::Error> {
+		linker.func_wrap( "env", "alloc", |mut caller: Caller<'_, ModHost>, size: i32| -> Result<i32, wasmtime::Error> {
 				let memory = caller.get_export("memory")
 					.and_then(|e| e.into_memory())
 					.ok_or_else(|| wasmtime::Error::msg("memory not found"))?;
@@ -153,7 +394,19 @@ impl WasmRuntime {
 
 	pub fn load_module(&mut self, name: &str, path: &Path) -> Result<(), WasmError> {
 		let module = Module::from_file(&self.engine, path)?;
-		let instance = self.linker.instantiate(&mut self.store, &module)?;
+		self.add_module(name, module)
+	}
+	/// Loads a mod from the bytes of a wasm (or wat) file
+	pub fn load_module_bytes(&mut self, name: &str, bytes: &[u8]) -> Result<(), WasmError> {
+		let module = Module::new(&self.engine, bytes)?;
+		self.add_module(name, module)
+	}
+
+	fn add_module(&mut self, name: &str, module: Module) -> Result<(), WasmError> {
+		self.disabled.remove(name);
+		self.refuel(name)?; // the start function runs on instantiation
+		let instance = self.linker.instantiate(&mut self.store, &module);
+		let instance = self.checked(name, "start", instance)?;
 		let memory = instance.get_memory(&mut self.store, "memory")
 			.ok_or(WasmError::MemoryNotFound)?;
 		
@@ -173,14 +426,17 @@ impl WasmRuntime {
 	}
 		
 	pub fn call_function_with_data(&mut self, module: &str, func: &str, data: &[u8]) -> Result<String, WasmError> {
+		self.refuel(module)?;
 		// Split into discrete operations to avoid overlapping borrows
 		let input_ptr = {
 			let m_data = self.instances.get_mut(module)
 				.ok_or_else(|| WasmError::ModuleNotFound { module: module.to_string() })?;
 			
 			let alloc = m_data.instance.get_typed_func::<i32, i32>(&mut self.store, "alloc")?;
-			let ptr = alloc.call(&mut self.store, data.len() as i32)?;
-			m_data.memory.write(&mut self.store, ptr as usize, data)?;
+			let memory = m_data.memory;
+			let ptr = alloc.call(&mut self.store, data.len() as i32);
+			let ptr = self.checked(module, "alloc", ptr)?;
+			memory.write(&mut self.store, ptr as usize, data)?;
 			ptr
 		};
 
@@ -189,7 +445,8 @@ impl WasmRuntime {
 				.ok_or_else(|| WasmError::ModuleNotFound { module: module.to_string() })?;
 			
 			let greet = m_data.instance.get_typed_func::<(i32, i32), i64>(&mut self.store, func)?;
-			greet.call(&mut self.store, (input_ptr, data.len() as i32))?
+			let packed = greet.call(&mut self.store, (input_ptr, data.len() as i32));
+			self.checked(module, func, packed)?
 		};
 
 		let result = {
@@ -199,8 +456,9 @@ impl WasmRuntime {
 			let result_ptr = (packed_result >> 32) as i32;
 			let result_len = (packed_result & 0xFFFFFFFF) as i32;
 			
-			let mut buffer = vec![0u8; result_len as usize];
-			m_data.memory.read(&mut self.store, result_ptr as usize, &mut buffer)?;
+			let (start, len) = guest_range(result_ptr, result_len, m_data.memory.data_size(&self.store))?;
+			let mut buffer = vec![0u8; len];
+			m_data.memory.read(&mut self.store, start, &mut buffer)?;
 			String::from_utf8(buffer)?
 		};
 
@@ -208,30 +466,33 @@ impl WasmRuntime {
 	}
 
 	pub fn call_function_simple(&mut self, module: &str, func: &str) -> Result<(), WasmError> {
+		self.refuel(module)?;
 		let m_data = self.instances.get_mut(module)
 			.ok_or_else(|| WasmError::ModuleNotFound { module: module.to_string() })?;
 		
-		let func = m_data.instance.get_typed_func::<(), ()>(&mut self.store, func)?;
-		func.call(&mut self.store, ())?;
-		Ok(())
+		let typed = m_data.instance.get_typed_func::<(), ()>(&mut self.store, func)?;
+		let result = typed.call(&mut self.store, ());
+		self.checked(module, func, result)
 	}
 
 	pub fn call_function_i32(&mut self, module: &str, func: &str, arg: i32) -> Result<i32, WasmError> {
+		self.refuel(module)?;
 		let m_data = self.instances.get_mut(module)
 			.ok_or_else(|| WasmError::ModuleNotFound { module: module.to_string() })?;
 		
-		let func = m_data.instance.get_typed_func::<i32, i32>(&mut self.store, func)?;
-		let result = func.call(&mut self.store, arg)?;
-		Ok(result)
+		let typed = m_data.instance.get_typed_func::<i32, i32>(&mut self.store, func)?;
+		let result = typed.call(&mut self.store, arg);
+		self.checked(module, func, result)
 	}
 
 	pub fn call_function_two_i32(&mut self, module: &str, func: &str, arg1: i32, arg2: i32) -> Result<i32, WasmError> {
+		self.refuel(module)?;
 		let m_data = self.instances.get_mut(module)
 			.ok_or_else(|| WasmError::ModuleNotFound { module: module.to_string() })?;
 		
-		let func = m_data.instance.get_typed_func::<(i32, i32), i32>(&mut self.store, func)?;
-		let result = func.call(&mut self.store, (arg1, arg2))?;
-		Ok(result)
+		let typed = m_data.instance.get_typed_func::<(i32, i32), i32>(&mut self.store, func)?;
+		let result = typed.call(&mut self.store, (arg1, arg2));
+		self.checked(module, func, result)
 	}
 	
 	pub fn get_instance_mut(&mut self, name: &str) -> Result<&mut ModuleData, WasmError> {
@@ -242,14 +503,16 @@ impl WasmRuntime {
 	// Renamed to be more explicit and avoid confusion
 	pub fn execute_wasm_fn<F, R>(&mut self, module: &str, f: F) -> Result<R, WasmError>
 	where
-		F: FnOnce(&mut Instance, &mut Store<()>) -> Result<R, WasmError>,
+		F: FnOnce(&mut Instance, &mut Store<ModHost>) -> Result<R, WasmError>,
 	{
+		self.refuel(module)?;
 		// Get the ModuleData first
 		let module_data = self.instances.get_mut(module)
 			.ok_or_else(|| WasmError::ModuleNotFound { module: module.to_string() })?;
 		
 		// Then execute with both components
-		f(&mut module_data.instance, &mut self.store)
+		let result = f(&mut module_data.instance, &mut self.store);
+		self.checked(module, "execute_wasm_fn", result)
 	}
 
 	
@@ -258,6 +521,7 @@ impl WasmRuntime {
 	}
 
 	pub fn unload_module(&mut self, name: &str) -> Result<(), WasmError> {
+		self.store.data_mut().handlers.retain(|h| h.module != name);
 		self.instances.remove(name)
 			.map(|_| ())
 			.ok_or(WasmError::ModuleNotFound{module : name.to_string()})
@@ -327,19 +591,37 @@ impl WasmRuntime {
 		}
 	}
 
-	/// Initializes all modules by running their "main" function
+	/// Loads every mod (deps first, see `manifest::load_order`) and runs its "main" function.
+	/// A mod that fails is skipped along with the mods needing it, the others still load
 	pub fn initialize_all_modules(&mut self) -> Result<(), WasmError> {
-		let modules = WasmRuntime::find_wasm_modules("comp_mods")?;
-
-		for path in modules {
-			let module_name = path.file_stem()
-				.and_then(|s| s.to_str())
-				.ok_or(WasmError::InvalidModuleName)?;
-
-			self.load_module(module_name, &path)?;
-			self.execute_module_function(module_name, "main", None)?;
+		let (mods, mut errors) = manifest::find_mods(Path::new(MODS_DIR))?;
+		let (order, order_errors) = manifest::load_order(&mods);
+		errors.extend(order_errors);
+		// Overrides and event handlers go in load order, so this is what to look at when two mods clash
+		let names: Vec<&str> = order.iter().map(|&i| mods[i].name.as_str()).collect();
+		println!("[MODS] load order: {}", names.join(" -> "));
+
+		let mut failed: HashSet<&str> = HashSet::new();
+		for &i in &order {
+			let m = &mods[i];
+			if let Some(dep) = m.deps.iter().find(|dep| failed.contains(dep.as_str())) {
+				errors.push((m.name.clone(), WasmError::MissingDependency { module: m.name.clone(), dependency: dep.clone() }));
+				failed.insert(&m.name);
+				continue;
+			}
+			let result = self.load_module(&m.name, &m.wasm)
+				.and_then(|_| self.execute_module_function(&m.name, "main", None));
+			if let Err(e) = result {
+				println!("[MODS] {} v{} failed to load: {}", m.name, m.version, e);
+				let _ = self.unload_module(&m.name);
+				errors.push((m.name.clone(), e));
+				failed.insert(&m.name);
+			}
 		}
 
+		if !errors.is_empty() {
+			return Err(WasmError::BulkError{errors});
+		}
 		Ok(())
 	}
 
@@ -359,18 +641,164 @@ impl WasmRuntime {
 	}
 }
 
+impl WasmRuntime {
+	/// Subscribes an exported function of a loaded mod to an event, false if it was already subscribed
+	pub fn register_event_handler(&mut self, module: &str, event: ModEvent, func_name: &str) -> Result<bool, WasmError> {
+		let m_data = self.instances.get(module)
+			.ok_or_else(|| WasmError::ModuleNotFound { module: module.to_string() })?;
+		if m_data.instance.get_func(&mut self.store, func_name).is_none() {
+			return Err(WasmError::FunctionNotFound { function: func_name.to_string() });
+		}
+		Ok(self.store.data_mut().register_for(module, event, func_name))
+	}
+
+	/// Calls every enabled handler of the event in registration order.
+	/// A handler that traps (or has the wrong signature) is disabled, the other handlers and mods keep running
+	pub fn dispatch<P: WasmParams + Copy>(&mut self, event: ModEvent, params: P) {
+		for (index, module, func) in self.store.data().handlers_of(event) {
+			if let Err(e) = self.call_handler(&module, &func, params) {
+				println!("[WASM] {}::{} failed on {} and was disabled: {}", module, func, event.name(), e);
+				self.store.data_mut().handlers[index].enabled = false;
+			}
+		}
+	}
+
+	fn call_handler<P: WasmParams>(&mut self, module: &str, func: &str, params: P) -> Result<(), WasmError> {
+		self.refuel(module)?;
+		let m_data = self.instances.get(module)
+			.ok_or_else(|| WasmError::ModuleNotFound { module: module.to_string() })?;
+		let typed = m_data.instance.get_typed_func::<P, ()>(&mut self.store, func)?;
+		let result = typed.call(&mut self.store, params);
+		self.checked(module, func, result)
+	}
+}
+
+/// Whether the guest was stopped for using up its fuel
+#[inline] pub fn is_out_of_fuel(error: &wasmtime::Error) -> bool {
+	matches!(error.downcast_ref::<Trap>(), Some(Trap::OutOfFuel))
+}
+
+/// Offset and length of the `len` bytes at `ptr` the mod handed over, an error if they are negative
+/// or reach past its `memory_size` bytes of memory (checked before anything is allocated for them)
+pub fn guest_range(ptr: i32, len: i32, memory_size: usize) -> Result<(usize, usize), wasmtime::Error> {
+	let (Ok(start), Ok(length)) = (usize::try_from(ptr), usize::try_from(len)) else {
+		return Err(wasmtime::Error::msg(format!("negative pointer or length from the mod ({}, {})", ptr, len)));
+	};
+	if start.checked_add(length).is_none_or(|end| end > memory_size) {
+		return Err(wasmtime::Error::msg(format!("{} bytes at {} are outside the mod's {} bytes of memory", length, start, memory_size)));
+	}
+	Ok((start, length))
+}
+
+/// Reads a utf-8 string the mod passed as pointer and length
+fn read_guest_string(caller: &mut Caller<'_, ModHost>, ptr: i32, len: i32) -> Result<String, wasmtime::Error> {
+	let memory = caller.get_export("memory")
+		.and_then(|e| e.into_memory())
+		.ok_or_else(|| wasmtime::Error::msg("memory not found"))?;
+	let (start, len) = guest_range(ptr, len, memory.data_size(&*caller))?;
+	let mut buffer = vec![0u8; len];
+	memory.read(&*caller, start, &mut buffer)
+		.map_err(|e| wasmtime::Error::msg(format!("memory read failed: {}", e)))?;
+	String::from_utf8(buffer).map_err(|e| wasmtime::Error::msg(format!("invalid utf-8: {}", e)))
+}
+
+/// The mods loaded at startup, kept so the game can send them events
+static RUNTIME: AtomicPtr<WasmRuntime> = AtomicPtr::new(std::ptr::null_mut());
+
+/// Makes the runtime the one events go to, the previous one is dropped
+pub fn set_runtime(runtime: WasmRuntime) {
+	let old_ptr = RUNTIME.swap(Box::into_raw(Box::new(runtime)), Ordering::AcqRel);
+	if !old_ptr.is_null() {
+		unsafe { let _ = Box::from_raw(old_ptr); }
+	}
+}
+#[inline] pub fn get_runtime() -> Option<&'static mut WasmRuntime> {
+	let ptr = RUNTIME.load(Ordering::Acquire);
+	if ptr.is_null() { return None; }
+	unsafe { Some(&mut *ptr) }
+}
+
+/// Sends `on_tick` to the mods, does nothing without mods
+#[inline] pub fn dispatch_tick(delta_time: f32) {
+	if let Some(runtime) = get_runtime() {
+		runtime.dispatch(ModEvent::Tick, delta_time);
+	}
+}
+/// Sends a block event to the mods, `block` is the broken or the placed block
+#[inline] pub fn dispatch_block(event: ModEvent, pos: IVec3, block: Block) {
+	if let Some(runtime) = get_runtime() {
+		runtime.dispatch(event, (pos.x, pos.y, pos.z, pack_block(block)));
+	}
+}
+
+/// Folder the compiled mods (and the textures of their blocks) are loaded from
+pub const MODS_DIR: &str = "comp_mods";
+
+/// Adds a block with its own texture to the game, `storage` makes it hold a container of that size.
+/// The texture has to be the size of the built in ones and gets its own layer of the block texture array.
+/// Registering the same name again gives back the same material, so the ids stay put for the whole session.
+/// Runs out (with an error) once all `MAX_BLOCK_LAYERS` layers are used
+pub fn register_block(name: &str, texture_path: &Path, storage: Option<Slot>) -> Result<Material, WasmError> {
+	let error = |reason: String| WasmError::BlockRegistration { name: name.to_string(), reason };
+	if name.is_empty() || name == "0" { return Err(error("invalid name".to_string())); }
+	let base = texture::get_texture_map().len();
+	if texture::get_texture_map().iter().any(|n| n == name) {
+		return Err(error("a built in block has that name".to_string()));
+	}
+	if let Some(id) = MOD_MATERIALS.read().ok().and_then(|m| m.id_of(base, name)) {
+		return Ok(Material(id));
+	}
+
+	// Checked before a material is handed out, so a bad texture doesn't use up a layer
+	let rgba = image::open(texture_path)
+		.map_err(|e| error(format!("texture {}: {}", texture_path.display(), e)))?
+		.to_rgba8();
+	let state = ptr::get_state();
+	let (width, height) = state.texture_manager().block_texture_size();
+	if rgba.dimensions() != (width, height) {
+		return Err(error(format!("block textures have to be {}x{}", width, height)));
+	}
+
+	let id = MOD_MATERIALS.write().map_err(|_| error("material registry poisoned".to_string()))?
+		.add(base, name)
+		.ok_or_else(|| error(format!("all {} block texture layers are used", texture::MAX_BLOCK_LAYERS)))?;
+	state.texture_manager().write_block_layer(state.queue(), id as u32, rgba.into_raw()).map_err(error)?;
+
+	let mut item = ItemComp::new("0").as_block();
+	item.name = MutStr::Dynamic(name.to_string());
+	if let Some(slot) = storage {
+		item = item.as_storage(slot);
+	}
+	register_item(item);
+	Ok(Material(id))
+}
+
+/// Texture path a mod gave, relative to `MODS_DIR` and not allowed to leave it
+fn mod_texture_path(path: &str) -> Option<PathBuf> {
+	let path = Path::new(path);
+	let inside = path.components().all(|c| matches!(c, std::path::Component::Normal(_) | std::path::Component::CurDir));
+	inside.then(|| Path::new(MODS_DIR).join(path))
+}
+
 // I would like to make some "listeners" or idk to modify / overwrite the vanilla code
 
 
 
 pub fn main() -> Result<(), Box<dyn std::error::Error>> {
 	let mut wasm_modder = WasmRuntime::new()?;
+	wasm_modder.fuel_per_call = ptr::get_settings().mod_config.fuel_per_call;
 		
-	// Propagate initialization errors
-	wasm_modder.initialize_all_modules()?;
+	// A broken mod only takes out itself and what depends on it
+	if let Err(e) = wasm_modder.initialize_all_modules() {
+		println!("[MODS] {}", e);
+	}
 	
-	// Run extra mod and propagate any errors
-	wasm_modder.run_extra_mod()?;
+	// Kept around for the events
+	set_runtime(wasm_modder);
 	
+	// Run extra mod and propagate any errors
+	if let Some(runtime) = get_runtime() {
+		runtime.run_extra_mod()?;
+	}
 	Ok(())
 }
@@ -0,0 +1,154 @@
+
+use crate::fs::json::{JsonParser, JsonValue, JsonError, read_json_file};
+use crate::mods::api::WasmError;
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Name of the manifest in a mod's folder
+pub const MANIFEST_FILE: &str = "mod.json";
+
+/// What a mod is and what it needs, read from its `mod.json` :
+/// ```json
+/// { "name": "ruby", "version": "1.2.0", "deps": ["gems_core"], "wasm": "ruby.wasm" }
+/// ```
+/// `deps` can be left out, `wasm` defaults to `<name>.wasm` next to the manifest.
+/// A lone `.wasm` file in the mods folder is a mod without a manifest (named after the file, no deps)
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModManifest {
+	pub name: String,
+	pub version: String,
+	pub deps: Vec<String>, // sorted, no duplicates
+	pub wasm: PathBuf,
+}
+
+impl ModManifest {
+	/// Parses a manifest, `dir` is the folder it is in (the wasm path is relative to it)
+	pub fn from_json(input: &str, dir: &Path) -> Result<Self, JsonError> {
+		let json = JsonParser::parse(input)?;
+		let obj = json.as_object()
+			.ok_or_else(|| JsonError::Custom("Manifest must be an object".into()))?;
+		let name = obj.get("name").and_then(|v| v.as_str()).filter(|n| !n.is_empty())
+			.ok_or_else(|| JsonError::MissingField("'name' is missing or not a string".into()))?
+			.to_string();
+		let version = obj.get("version").and_then(|v| v.as_str())
+			.ok_or_else(|| JsonError::MissingField("'version' is missing or not a string".into()))?
+			.to_string();
+		let deps = match obj.get("deps") {
+			None | Some(JsonValue::Null) => Vec::new(),
+			Some(JsonValue::Array(list)) => list.iter()
+				.map(|v| v.as_str().map(str::to_string).ok_or_else(|| JsonError::Custom("'deps' must only hold mod names".into())))
+				.collect::<Result<Vec<_>, _>>()?,
+			Some(_) => return Err(JsonError::Custom("'deps' must be an array".into())),
+		};
+		let wasm = match obj.get("wasm") {
+			None => format!("{}.wasm", name),
+			Some(v) => v.as_str().ok_or_else(|| JsonError::Custom("'wasm' must be a file name".into()))?.to_string(),
+		};
+		Ok(Self::new(name, version, deps, dir.join(wasm)))
+	}
+
+	pub fn new(name: String, version: String, mut deps: Vec<String>, wasm: PathBuf) -> Self {
+		deps.sort();
+		deps.dedup();
+		Self { name, version, deps, wasm }
+	}
+	/// A `.wasm` file without a manifest
+	pub fn bare(wasm: PathBuf) -> Option<Self> {
+		let name = wasm.file_stem()?.to_str()?.to_string();
+		Some(Self::new(name, "0.0.0".to_string(), Vec::new(), wasm))
+	}
+}
+
+/// Every mod in the folder (in name order) and the ones that couldn't be read, with why.
+/// Mods are either a `.wasm` file or a folder with a `mod.json`
+pub fn find_mods(directory: &Path) -> Result<(Vec<ModManifest>, Vec<(String, WasmError)>), WasmError> {
+	if !directory.exists() {
+		return Err(WasmError::IOError{ error: "Directory does not exists".to_string()});
+	}
+	let mut paths: Vec<PathBuf> = fs::read_dir(directory)?
+		.filter_map(|entry| entry.ok().map(|e| e.path()))
+		.collect();
+	paths.sort();
+
+	let (mut mods, mut errors): (Vec<ModManifest>, Vec<(String, WasmError)>) = (Vec::new(), Vec::new());
+	for path in paths {
+		let manifest = if path.is_dir() {
+			let file = path.join(MANIFEST_FILE);
+			let Some(input) = read_json_file(&file) else { continue; }; // just a folder
+			match ModManifest::from_json(&input, &path) {
+				Ok(manifest) => manifest,
+				Err(e) => {
+					errors.push((path.display().to_string(), WasmError::Manifest { path: file.display().to_string(), error: e.to_string() }));
+					continue;
+				},
+			}
+		} else if path.extension().is_some_and(|ext| ext == "wasm") {
+			let Some(manifest) = ModManifest::bare(path) else { continue; };
+			manifest
+		} else {
+			continue;
+		};
+
+		if mods.iter().any(|m: &ModManifest| m.name == manifest.name) {
+			let error = format!("another mod is already named '{}'", manifest.name);
+			errors.push((manifest.name.clone(), WasmError::Manifest { path: manifest.wasm.display().to_string(), error }));
+			continue;
+		}
+		mods.push(manifest);
+	}
+	Ok((mods, errors))
+}
+
+/// Order to load the mods in so every mod comes after its deps, as indices into `mods`.
+/// When several mods could go next the one with the smallest name does, so the order is the same every run.
+/// Mods with a missing dep (or a dep that can't load) and mods in a cycle are left out and come back as errors,
+/// the rest still load
+pub fn load_order(mods: &[ModManifest]) -> (Vec<usize>, Vec<(String, WasmError)>) {
+	let index: HashMap<&str, usize> = mods.iter().enumerate().map(|(i, m)| (m.name.as_str(), i)).collect();
+	let mut errors = Vec::new();
+	let mut dropped = vec![false; mods.len()];
+
+	// Repeated so a mod needing a dropped mod is dropped too
+	loop {
+		let mut changed = false;
+		for (i, m) in mods.iter().enumerate() {
+			if dropped[i] { continue; }
+			let missing = m.deps.iter().find(|dep| index.get(dep.as_str()).is_none_or(|&j| dropped[j]));
+			if let Some(dep) = missing {
+				errors.push((m.name.clone(), WasmError::MissingDependency { module: m.name.clone(), dependency: dep.clone() }));
+				dropped[i] = true;
+				changed = true;
+			}
+		}
+		if !changed { break; }
+	}
+
+	let mut waiting: Vec<usize> = mods.iter().map(|m| m.deps.len()).collect();
+	let mut ready: BTreeSet<(&str, usize)> = mods.iter().enumerate()
+		.filter(|(i, m)| !dropped[*i] && m.deps.is_empty())
+		.map(|(i, m)| (m.name.as_str(), i))
+		.collect();
+	let mut order = Vec::with_capacity(mods.len());
+	while let Some((name, i)) = ready.pop_first() {
+		order.push(i);
+		for (j, m) in mods.iter().enumerate() {
+			if dropped[j] || !m.deps.iter().any(|dep| dep == name) { continue; }
+			waiting[j] -= 1;
+			if waiting[j] == 0 {
+				ready.insert((m.name.as_str(), j));
+			}
+		}
+	}
+
+	// Whatever never got ready waits on a cycle (or on a mod in one)
+	let mut stuck: Vec<String> = (0..mods.len())
+		.filter(|i| !dropped[*i] && !order.contains(i))
+		.map(|i| mods[i].name.clone())
+		.collect();
+	stuck.sort();
+	for name in &stuck {
+		errors.push((name.clone(), WasmError::DependencyCycle { modules: stuck.clone() }));
+	}
+	(order, errors)
+}
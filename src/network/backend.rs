@@ -0,0 +1,70 @@
+
+use crate::network::types::{ConnectAttempt, GameMessage, HostInfo, NetworkEvent, NetworkStatus, NetworkSystem, PeerId};
+
+/// One way of finding and talking to other players.
+/// The `network::api` functions only go through this, so the UI sees the same `NetworkEvent`s whatever backend runs
+pub trait NetworkBackend {
+	fn name(&self) -> &'static str;
+	fn is_host(&self) -> bool;
+	fn status(&self) -> NetworkStatus;
+
+	/// Opens a game others can find and join
+	fn host(&mut self) -> Result<String, String>;
+	/// Starts looking for hosts, they show up in `discovered_hosts` (and a `DiscoveryComplete` event)
+	fn discover(&mut self, timeout_ms: u64) -> Result<String, String>;
+	fn discovered_hosts(&self) -> Vec<HostInfo>;
+	/// Joins a host, `target` is whatever the backend put in `HostInfo::join` (or a typed in address)
+	fn join(&mut self, target: &str) -> Result<String, String>;
+	/// The join still waiting for an answer, it is tried again after a timeout (up to the retries in the settings)
+	fn connect_attempt(&self) -> Option<&ConnectAttempt>;
+
+	/// Everyone this end can send to right now
+	fn peers(&self) -> Vec<PeerId>;
+	/// Name to show for a peer, chat messages are signed with it
+	fn get_friend_name(&self, peer: PeerId) -> String;
+	fn send(&mut self, peer: PeerId, msg: &GameMessage) -> Result<(), String>;
+	/// Sends to every connected peer, returns how many it went to
+	fn broadcast(&mut self, msg: &GameMessage) -> Result<usize, String>;
+
+	/// Runs once a frame, received messages and state changes come out of `pop_event`
+	fn update(&mut self);
+	fn pop_event(&mut self) -> Option<NetworkEvent>;
+	/// Closes everything, the backend is dropped right after
+	fn shutdown(&mut self);
+}
+
+/// The backends this build has, Steam needs the `steam` feature
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BackendKind {
+	#[default]
+	Direct, // LAN broadcast discovery and direct IP, TCP handshake + UDP session
+	#[cfg(feature = "steam")]
+	Steam, // Steam lobbies and P2P packets
+}
+
+impl BackendKind {
+	#[inline] pub const fn name(&self) -> &'static str {
+		match self {
+			Self::Direct => "Direct",
+			#[cfg(feature = "steam")]
+			Self::Steam => "Steam",
+		}
+	}
+	#[inline] pub const fn to_u8(self) -> u8 { self as u8 }
+	#[inline] pub const fn from_u8(value: u8) -> Option<Self> {
+		match value {
+			0 => Some(Self::Direct),
+			#[cfg(feature = "steam")]
+			1 => Some(Self::Steam),
+			_ => None,
+		}
+	}
+
+	pub fn create(self, is_host: bool) -> Result<Box<dyn NetworkBackend>, String> {
+		match self {
+			Self::Direct => Ok(Box::new(NetworkSystem::new(is_host))),
+			#[cfg(feature = "steam")]
+			Self::Steam => Ok(Box::new(crate::network::steam::SteamBackend::new(is_host)?)),
+		}
+	}
+}
@@ -0,0 +1,267 @@
+
+use crate::{
+	network::{
+		backend::NetworkBackend,
+		types::{self, ConnectAttempt, GameMessage, HostInfo, NetworkEvent, NetworkStatus, NetworkSystem, Peer, PeerId},
+	},
+};
+use ggrs::SessionState;
+use std::{
+	io::{self, Read, Write},
+	net::{SocketAddr, TcpStream},
+	time::{Duration, Instant},
+};
+
+impl NetworkSystem {
+	pub fn update_game_session(&mut self) {
+		if let Some(mut session) = self.session.take() {
+			session.poll_remote_clients();
+			let mut events = Vec::new();
+			let mut should_return = false;
+
+			match session.current_state() {
+				SessionState::Running => {
+					if matches!(self.status, NetworkStatus::Connected) {
+						self.status = NetworkStatus::InGame;
+						events.push(NetworkEvent::Ready);
+					}
+
+					let input = (self.frame_count % 15) as i32;
+					if let Err(e) = session.add_local_input(self.local_player_id, input) {
+						self.consecutive_errors += 1;
+						if self.consecutive_errors > 10 {
+							events.push(NetworkEvent::Error(format!("Input error: {:?}", e)));
+							should_return = true;
+						}
+					}
+
+					if !should_return {
+						match session.advance_frame() {
+							Ok(_) => {
+								self.consecutive_errors = 0;
+								self.game_state += 1;
+								self.frame_count += 1;
+								if self.frame_count % 60 == 0 {
+									events.push(NetworkEvent::GameStateUpdate(self.game_state));
+								}
+							}
+							Err(e) => {
+								self.consecutive_errors += 1;
+								match e {
+									ggrs::GgrsError::PredictionThreshold if self.consecutive_errors > 12 => {
+										events.push(NetworkEvent::Error("Prediction threshold".to_string()));
+									}
+									ggrs::GgrsError::NotSynchronized => {
+										events.push(NetworkEvent::Synchronizing);
+									}
+									_ => events.push(NetworkEvent::Error(format!("Frame error: {:?}", e))),
+								}
+							}
+						}
+					}
+				}
+				SessionState::Synchronizing => {
+					events.push(NetworkEvent::Synchronizing);
+					if let Some(start) = self.sync_start_time {
+						if start.elapsed() > Duration::from_secs(15) {
+							events.push(NetworkEvent::Error("Sync timeout".to_string()));
+						}
+					}
+				}
+			}
+
+			self.session = Some(session);
+			for event in events { self.push_event(event); }
+		}
+	}
+
+	/// Keeps a finished handshake's stream for the game messages
+	pub fn add_peer(&mut self, addr: SocketAddr, stream: TcpStream) {
+		let id = self.next_peer_id;
+		self.next_peer_id += 1;
+		self.peers.push(Peer { id, addr, stream, inbox: Vec::new(), outbox: Vec::new() });
+		self.push_event(NetworkEvent::PeerJoined(id));
+	}
+
+	/// Drops a join that took too long, starts it again while retries are left and gives up after that.
+	/// A handshake thread still running is left to finish on its own, its stream is closed when it ends
+	fn check_connect_timeout(&mut self, now: Instant) {
+		let Some(attempt) = self.connect.as_mut() else { return; };
+		if !attempt.timed_out(now) { return; }
+		self.pending_connections.clear();
+		if attempt.next_try(now) {
+			println!("Connection to {} timed out, retrying", attempt.target);
+			return;
+		}
+		let msg = format!("Connection to {} timed out after {} tries", attempt.target, attempt.max_retries as u32 + 1);
+		self.connect = None;
+		self.status = NetworkStatus::Error(msg.clone());
+		self.push_event(NetworkEvent::Error(msg));
+	}
+
+	/// Writes what is waiting and reads what came in on every peer stream, dropping the ones that closed or broke
+	fn pump_peers(&mut self) {
+		let mut events = Vec::new();
+		self.peers.retain_mut(|peer| {
+			match pump_peer(peer) {
+				Ok(messages) => {
+					events.extend(messages.into_iter().map(|msg| NetworkEvent::Message(peer.id, msg)));
+					true
+				}
+				Err(e) => {
+					println!("Peer {} ({}) dropped: {}", peer.id, peer.addr, e);
+					events.push(NetworkEvent::PeerLeft(peer.id));
+					events.push(NetworkEvent::Disconnected);
+					false
+				}
+			}
+		});
+		for event in events { self.push_event(event); }
+	}
+
+	#[inline] fn queue_message(peer: &mut Peer, msg: &GameMessage) {
+		peer.outbox.extend_from_slice(&types::encode_frame(msg));
+	}
+}
+
+/// One round of non blocking writes and reads, Err once the stream is of no use
+fn pump_peer(peer: &mut Peer) -> Result<Vec<GameMessage>, String> {
+	while !peer.outbox.is_empty() {
+		match peer.stream.write(&peer.outbox) {
+			Ok(0) => return Err("Connection closed".to_string()),
+			Ok(n) => { peer.outbox.drain(..n); },
+			Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+			Err(e) => return Err(format!("Write error: {}", e)),
+		}
+	}
+	let mut buf = [0; 4096];
+	loop {
+		match peer.stream.read(&mut buf) {
+			Ok(0) => return Err("Connection closed".to_string()),
+			Ok(n) => peer.inbox.extend_from_slice(&buf[..n]),
+			Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+			Err(e) => return Err(format!("Read error: {}", e)),
+		}
+	}
+	types::decode_frames(&mut peer.inbox)
+}
+
+/// LAN broadcast discovery and direct IP joining, the default backend
+impl NetworkBackend for NetworkSystem {
+	#[inline] fn name(&self) -> &'static str { "Direct" }
+	#[inline] fn is_host(&self) -> bool { self.is_host }
+	#[inline] fn status(&self) -> NetworkStatus { self.status.clone() }
+
+	fn host(&mut self) -> Result<String, String> {
+		let msg = self.setup_tcp_listener()?;
+		self.status = NetworkStatus::Discovering;
+		let _ = self.start_broadcast_listener();
+		Ok(format!("TCP: {} | Discovering", msg))
+	}
+
+	#[inline] fn discover(&mut self, timeout_ms: u64) -> Result<String, String> {
+		if self.is_host { return Err("Cannot refresh as host".to_string()); }
+		self.discover_hosts(timeout_ms)
+	}
+
+	#[inline] fn discovered_hosts(&self) -> Vec<HostInfo> {
+		self.discovered_hosts.lock().map_or(Vec::new(), |hosts| hosts.clone())
+	}
+
+	fn join(&mut self, target: &str) -> Result<String, String> {
+		self.status = NetworkStatus::Connecting;
+		self.set_target_host_ip(target.to_string());
+		self.pending_connections.clear();
+		self.connect = Some(ConnectAttempt::from_settings(target));
+		Ok(format!("Connecting to {}", target))
+	}
+
+	#[inline] fn connect_attempt(&self) -> Option<&ConnectAttempt> { self.connect.as_ref() }
+
+	#[inline] fn peers(&self) -> Vec<PeerId> {
+		self.peers.iter().map(|p| p.id).collect()
+	}
+
+	/// A client only ever talks to the host, the host names its clients by address
+	fn get_friend_name(&self, peer: PeerId) -> String {
+		if !self.is_host { return "Host".to_string(); }
+		self.peers.iter().find(|p| p.id == peer).map_or(format!("Player {}", peer), |p| p.addr.ip().to_string())
+	}
+
+	fn send(&mut self, peer: PeerId, msg: &GameMessage) -> Result<(), String> {
+		let peer = self.peers.iter_mut().find(|p| p.id == peer).ok_or(format!("No peer {}", peer))?;
+		Self::queue_message(peer, msg);
+		Ok(())
+	}
+
+	fn broadcast(&mut self, msg: &GameMessage) -> Result<usize, String> {
+		let frame = types::encode_frame(msg);
+		for peer in &mut self.peers {
+			peer.outbox.extend_from_slice(&frame);
+		}
+		Ok(self.peers.len())
+	}
+
+	fn update(&mut self) {
+		self.check_discovery_complete();
+
+		if let Ok(true) = self.check_pending_connections() {
+			if let Err(e) = self.setup_ggrs_session() {
+				self.status = NetworkStatus::Error(e.clone());
+				self.push_event(NetworkEvent::Error(e));
+			}
+		}
+		self.pump_peers();
+
+		let now = Instant::now();
+		if now.duration_since(self.last_frame_time) < Duration::from_millis(16) { return; }
+		self.last_frame_time = now;
+
+		match self.status {
+			NetworkStatus::Discovering if self.is_host => {
+				if let Err(e) = self.try_accept_connection() {
+					// Don't treat "No connection" as an error
+					if !e.contains("No connection") {
+						let msg = format!("Host error: {}", e);
+						self.status = NetworkStatus::Error(msg.clone());
+						self.push_event(NetworkEvent::Error(msg));
+					}
+				}
+			}
+			NetworkStatus::Connecting if !self.is_host => {
+				self.check_connect_timeout(now);
+				if !matches!(self.status, NetworkStatus::Connecting) { return; }
+			// Only try to connect if we haven't already started
+				if self.pending_connections.is_empty() {
+					let target_ip = self.target_host_ip.clone();
+					if let Some(ip) = target_ip {
+						if let Err(e) = self.try_connect_to_host(&ip) {
+							self.connect = None;
+							let msg = format!("Connection error: {}", e);
+							self.status = NetworkStatus::Error(msg.clone());
+							self.push_event(NetworkEvent::Error(msg));
+						}
+					} else {
+						let msg = "No target IP".to_string();
+						self.status = NetworkStatus::Error(msg.clone());
+						self.push_event(NetworkEvent::Error(msg));
+					}
+				}
+			}
+			NetworkStatus::Connected | NetworkStatus::InGame => self.update_game_session(),
+			_ => {}
+		}
+	}
+
+	#[inline] fn pop_event(&mut self) -> Option<NetworkEvent> {
+		self.event_queue.pop_front()
+	}
+
+	fn shutdown(&mut self) {
+		if let Some(h) = self.discovery_thread.take() { let _ = h.join(); }
+		self.broadcast_listener_thread.take();
+		for peer in self.peers.drain(..) {
+			let _ = peer.stream.shutdown(std::net::Shutdown::Both);
+		}
+	}
+}
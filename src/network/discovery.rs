@@ -1,10 +1,7 @@
 use crate::{
 	ext::ptr,
-	network::{
-		api,
-		types::{self, DiscoveryResult, HostInfo, NetworkEvent, NetworkMessage, 
-				NetworkStatus, NetworkSystem, PendingConnection},
-	},
+	network::types::{self, DiscoveryResult, HostInfo, NetworkEvent, NetworkMessage,
+			NetworkStatus, NetworkSystem, PendingConnection, Peer},
 };
 use std::{
 	io::{self, BufRead, BufReader, Write},
@@ -180,7 +177,8 @@ impl NetworkSystem {
 			Err(e) => return Err(format!("Accept error: {}", e)),
 		};
 		
-		stream.set_nonblocking(true).map_err(|e| format!("Failed to set non-blocking: {}", e))?;
+		// The handshake thread waits on it with a read timeout, that only works on a blocking socket
+		stream.set_nonblocking(false).map_err(|e| format!("Failed to set blocking: {}", e))?;
 		
 		let current_pid = self.current_pid;
 		let local_ip = types::get_local_ip().map_err(|e| format!("IP error: {}", e))?;
@@ -198,7 +196,7 @@ impl NetworkSystem {
 		_peer_addr: SocketAddr,
 		current_pid: u32,
 		local_ip: IpAddr
-	) -> Result<(SocketAddr, SocketAddr), String> {
+	) -> Result<(SocketAddr, SocketAddr, TcpStream), String> {
 		stream.set_read_timeout(Some(Duration::from_millis(1000))).map_err(|e| format!("Timeout error: {}", e))?;
 		
 		let mut line = String::new();
@@ -252,7 +250,7 @@ impl NetworkSystem {
 					
 					if let NetworkMessage::PeerAddress(peer_addr) = deserialize_message(&line.trim())
 						.map_err(|e| format!("Parse error: {}", e))? {
-						return Ok((local_udp_addr, peer_addr));
+						return Ok((local_udp_addr, peer_addr, stream));
 					} else {
 						return Err("Wrong message type for peer address".into());
 					}
@@ -275,7 +273,7 @@ impl NetworkSystem {
 		host_addr: SocketAddr,
 		current_pid: u32,
 		local_ip: IpAddr
-	) -> Result<(SocketAddr, SocketAddr), String> {
+	) -> Result<(SocketAddr, SocketAddr, TcpStream), String> {
 		let mut stream = TcpStream::connect_timeout(&host_addr, Duration::from_millis(1000))
 			.map_err(|e| format!("Connect error: {}", e))?;
 		
@@ -304,7 +302,7 @@ impl NetworkSystem {
 			.map_err(|e| format!("Write error: {}", e))?;
 		stream.flush().map_err(|e| format!("Flush error: {}", e))?;
 		
-		Ok((local_udp_addr, host_addr))
+		Ok((local_udp_addr, host_addr, stream))
 	}
 
 	pub fn check_pending_connections(&mut self) -> Result<bool, String> {
@@ -315,13 +313,25 @@ impl NetworkSystem {
 			if self.pending_connections[i].handle.is_finished() {
 				let pending = self.pending_connections.remove(i);
 				match pending.handle.join() {
-					Ok(Ok((local, remote))) => {
+					Ok(Ok((local, remote, stream))) => {
 						self.local_udp_addr = Some(local);
 						self.remote_udp_addr = Some(remote);
+						// The handshake stream stays open for the game messages
+						match stream.set_nonblocking(true) {
+							Ok(()) => self.add_peer(pending.peer_addr, stream),
+							Err(e) => self.push_event(NetworkEvent::Error(format!("Peer {} lost: {}", pending.peer_addr, e))),
+						}
+						self.connect = None;
 						completed = true;
 					}
 					Ok(Err(e)) if !e.contains("No data available yet") && !e.contains("Waiting for peer address") => {
 						let msg = format!("Connection to {} failed: {}", pending.peer_addr, e);
+						// A client tries again while it has retries left, the next update starts the new handshake
+						if self.connect.as_mut().is_some_and(|attempt| attempt.next_try(Instant::now())) {
+							println!("{}, retrying", msg);
+							continue;
+						}
+						self.connect = None;
 						self.status = NetworkStatus::Error(msg.clone());
 						self.push_event(NetworkEvent::Error(msg));
 					}
@@ -412,6 +422,7 @@ impl NetworkSystem {
 								pid: port as u32,
 								address,
 								world_name,
+								join: address.to_string(),
 							});
 						}
 					}
@@ -474,11 +485,13 @@ impl NetworkSystem {
 	}
 }
 
-#[inline]
-pub fn discover_hosts(timeout_ms: u64) -> Result<String, String> {
-	let system = api::get_ptr().ok_or("Network system not initialized")?;
-	system.discovery_thread = Some(thread::spawn(move || 
-		NetworkSystem::discover_hosts_broadcast(timeout_ms)
-	));
-	Ok(format!("Broadcast discovery started with timeout: {}ms", timeout_ms))
+impl NetworkSystem {
+	/// Broadcasts for hosts in the background, the result comes as `NetworkEvent::DiscoveryComplete`
+	pub fn discover_hosts(&mut self, timeout_ms: u64) -> Result<String, String> {
+		if self.discovery_thread.is_some() { return Ok("Already discovering".to_string()); }
+		self.discovery_thread = Some(thread::spawn(move ||
+			Self::discover_hosts_broadcast(timeout_ms)
+		));
+		Ok(format!("Broadcast discovery started with timeout: {}ms", timeout_ms))
+	}
 }
@@ -0,0 +1,321 @@
+
+use crate::{
+	ext::ptr,
+	network::{
+		backend::NetworkBackend,
+		types::{ConnectAttempt, DiscoveryResult, GameMessage, HostInfo, NetworkEvent, NetworkStatus, PeerId},
+		wire,
+	},
+};
+use crossbeam::channel::{unbounded, Receiver, Sender};
+use std::{
+	collections::VecDeque,
+	net::{Ipv4Addr, SocketAddr},
+	time::{Duration, Instant},
+};
+use steamworks::{CallbackHandle, ChatMemberStateChange, Client, LobbyChatUpdate, LobbyId, LobbyType, P2PSessionRequest, SendType, SingleClient, SteamId};
+
+/// Lobby data key holding the world name
+const WORLD_KEY: &str = "world";
+/// Tags our lobbies so the list only shows Rusticubes games
+const GAME_KEY: &str = "game";
+const GAME_NAME: &str = "rusticubes";
+const MAX_LOBBY_MEMBERS: u32 = 8;
+/// Steam answers a lobby list slower than a LAN broadcast, a discovery waits at least this long
+const MIN_DISCOVERY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// What the Steam callbacks report back, they run inside `run_callbacks`
+enum Signal {
+	LobbyCreated(Result<LobbyId, String>),
+	LobbyList(Result<Vec<LobbyId>, String>),
+	LobbyJoined(Result<LobbyId, String>),
+	PeerJoined(SteamId),
+	PeerLeft(SteamId),
+}
+
+/// Steam lobbies for hosting and finding games, P2P packets for the game messages.
+/// Peers are Steam ids, `HostInfo::join` holds the lobby id
+pub struct SteamBackend {
+	client: Client,
+	single: SingleClient,
+	is_host: bool,
+	status: NetworkStatus,
+	lobby: Option<LobbyId>,
+	hosts: Vec<HostInfo>,
+	discovery: Option<(Instant, Duration)>, // when the lobby list was asked for and how long to wait
+	connect: Option<ConnectAttempt>, // the lobby join waiting for Steam
+	events: VecDeque<NetworkEvent>,
+	signals: (Sender<Signal>, Receiver<Signal>),
+	_callbacks: [CallbackHandle; 2],
+}
+
+impl SteamBackend {
+	pub fn new(is_host: bool) -> Result<Self, String> {
+		let (client, single) = Client::init().map_err(|e| format!("Steam init failed: {:?}", e))?;
+		let signals = unbounded();
+		// Anyone who can see the lobby may talk to us
+		let accepting = client.clone();
+		let session_requests = client.register_callback(move |req: P2PSessionRequest| {
+			accepting.networking().accept_p2p_session(req.remote);
+		});
+		let tx: Sender<Signal> = signals.0.clone();
+		let lobby_updates = client.register_callback(move |update: LobbyChatUpdate| {
+			if update.member_state_change == ChatMemberStateChange::Entered {
+				let _ = tx.send(Signal::PeerJoined(update.user_changed));
+			}
+		});
+		Ok(Self {
+			client, single, is_host,
+			status: NetworkStatus::Idle,
+			lobby: None,
+			hosts: Vec::new(),
+			discovery: None,
+			connect: None,
+			events: VecDeque::new(),
+			signals,
+			_callbacks: [session_requests, lobby_updates],
+		})
+	}
+
+	fn push_event(&mut self, event: NetworkEvent) {
+		self.events.push_back(event);
+	}
+	fn fail(&mut self, msg: String) {
+		self.status = NetworkStatus::Error(msg.clone());
+		self.push_event(NetworkEvent::Error(msg));
+	}
+
+	/// Everyone in the lobby but us
+	fn members(&self) -> Vec<SteamId> {
+		let Some(lobby) = self.lobby else { return Vec::new(); };
+		let me = self.client.user().steam_id();
+		self.client.matchmaking().lobby_members(lobby).into_iter().filter(|id| *id != me).collect()
+	}
+
+	fn handle_signal(&mut self, signal: Signal) {
+		match signal {
+			Signal::LobbyCreated(Ok(lobby)) => {
+				let matchmaking = self.client.matchmaking();
+				matchmaking.set_lobby_data(lobby, GAME_KEY, GAME_NAME);
+				matchmaking.set_lobby_data(lobby, WORLD_KEY, ptr::get_gamestate().worldname());
+				self.lobby = Some(lobby);
+				self.status = NetworkStatus::Discovering;
+			}
+			Signal::LobbyList(res) => self.finish_discovery(res),
+			Signal::LobbyJoined(Ok(lobby)) => {
+				// An answer to a join that was given up on, we don't stay in that lobby
+				if self.connect.take().is_none() {
+					self.client.matchmaking().leave_lobby(lobby);
+					return;
+				}
+				self.lobby = Some(lobby);
+				self.status = NetworkStatus::Connected;
+				self.push_event(NetworkEvent::Connected(SocketAddr::from((Ipv4Addr::UNSPECIFIED, 0))));
+				self.push_event(NetworkEvent::Ready);
+			}
+			Signal::PeerJoined(id) => self.push_event(NetworkEvent::PeerJoined(id.raw())),
+			Signal::PeerLeft(id) => {
+				self.client.networking().close_p2p_session(id);
+				self.push_event(NetworkEvent::PeerLeft(id.raw()));
+			}
+			Signal::LobbyJoined(Err(e)) => {
+				if self.connect.as_mut().is_some_and(|attempt| attempt.next_try(Instant::now())) {
+					println!("{}, retrying", e);
+					self.request_join();
+					return;
+				}
+				self.connect = None;
+				self.fail(e);
+			}
+			Signal::LobbyCreated(Err(e)) => self.fail(e),
+		}
+	}
+}
+
+impl SteamBackend {
+	/// Turns the lobby list Steam sent into hosts, a `DiscoveryComplete` tells the UI what was found (or what went wrong)
+	fn finish_discovery(&mut self, res: Result<Vec<LobbyId>, String>) {
+		let mut result = DiscoveryResult { hosts: Vec::new(), debug_info: Vec::new(), errors: Vec::new() };
+		match self.discovery.take() {
+			Some((started, _)) => result.debug_info.push(format!("Lobby list answered after {}ms", started.elapsed().as_millis())),
+			None => result.debug_info.push("Lobby list answered after the discovery timed out".to_string()),
+		}
+		match res {
+			Ok(lobbies) => {
+				let matchmaking = self.client.matchmaking();
+				result.debug_info.push(format!("Steam returned {} lobbies", lobbies.len()));
+				for lobby in lobbies {
+					let Some(host) = lobby_host(lobby.raw(), matchmaking.lobby_data(lobby, GAME_KEY), matchmaking.lobby_data(lobby, WORLD_KEY)) else { continue; };
+					result.debug_info.push(format!("Found lobby {} ({})", host.join, host.world_name));
+					result.hosts.push(host);
+				}
+				self.hosts = result.hosts.clone();
+			}
+			Err(e) => result.errors.push(e),
+		}
+		self.status = NetworkStatus::Idle;
+		self.push_event(NetworkEvent::DiscoveryComplete(result));
+	}
+
+	/// Asks Steam to join the lobby of the running attempt, the answer comes as a `LobbyJoined` signal
+	fn request_join(&mut self) {
+		let Some(raw) = self.connect.as_ref().and_then(|attempt| attempt.target.trim().parse::<u64>().ok()) else { return; };
+		let tx = self.signals.0.clone();
+		self.client.matchmaking().join_lobby(LobbyId::from_raw(raw), move |res| {
+			let _ = tx.send(Signal::LobbyJoined(res.map_err(|_| "Could not join the lobby".to_string())));
+		});
+	}
+
+	/// Tries the join again when Steam didn't answer in time, gives up once the retries are used
+	fn check_connect_timeout(&mut self) {
+		let now = Instant::now();
+		let Some(attempt) = self.connect.as_mut() else { return; };
+		if !attempt.timed_out(now) { return; }
+		if attempt.next_try(now) {
+			println!("Joining lobby {} timed out, retrying", attempt.target);
+			self.request_join();
+			return;
+		}
+		let msg = format!("Joining lobby {} timed out after {} tries", attempt.target, attempt.max_retries as u32 + 1);
+		self.connect = None;
+		self.close_sessions();
+		self.fail(msg);
+	}
+
+	/// Closes the P2P sessions with everyone in the lobby, half open ones included
+	fn close_sessions(&self) {
+		let networking = self.client.networking();
+		for id in self.members() {
+			networking.close_p2p_session(id);
+		}
+	}
+
+	/// Gives up on a lobby list that didn't come back in time, the hosts found before stay
+	fn check_discovery_timeout(&mut self) {
+		let Some((started, timeout)) = self.discovery else { return; };
+		if started.elapsed() < timeout { return; }
+		self.discovery = None;
+		self.status = NetworkStatus::Idle;
+		self.push_event(NetworkEvent::DiscoveryComplete(timed_out_discovery(&self.hosts, timeout)));
+	}
+}
+
+/// How long a discovery waits for the lobby list, never less than `MIN_DISCOVERY_TIMEOUT`
+#[inline] pub fn discovery_timeout(timeout_ms: u64) -> Duration {
+	Duration::from_millis(timeout_ms).max(MIN_DISCOVERY_TIMEOUT)
+}
+
+/// The host a lobby stands for from its `game` and `world` lobby data, None if it belongs to another game
+pub fn lobby_host(id: u64, game: Option<&str>, world: Option<&str>) -> Option<HostInfo> {
+	if game != Some(GAME_NAME) { return None; }
+	Some(HostInfo {
+		pid: 0,
+		address: SocketAddr::from((Ipv4Addr::UNSPECIFIED, 0)),
+		world_name: world.unwrap_or("Unknown").to_string(),
+		join: id.to_string(),
+	})
+}
+
+/// What a discovery without an answer reports, the hosts of the last list stay
+pub fn timed_out_discovery(hosts: &[HostInfo], timeout: Duration) -> DiscoveryResult {
+	DiscoveryResult {
+		hosts: hosts.to_vec(),
+		debug_info: vec![format!("Kept {} lobbies from the last list", hosts.len())],
+		errors: vec![format!("Lobby list timed out after {}ms", timeout.as_millis())],
+	}
+}
+
+impl NetworkBackend for SteamBackend {
+	#[inline] fn name(&self) -> &'static str { "Steam" }
+	#[inline] fn is_host(&self) -> bool { self.is_host }
+	#[inline] fn status(&self) -> NetworkStatus { self.status.clone() }
+
+	fn host(&mut self) -> Result<String, String> {
+		let tx = self.signals.0.clone();
+		self.client.matchmaking().create_lobby(LobbyType::Public, MAX_LOBBY_MEMBERS, move |res| {
+			let _ = tx.send(Signal::LobbyCreated(res.map_err(|e| format!("Lobby creation failed: {:?}", e))));
+		});
+		self.status = NetworkStatus::Connecting;
+		Ok("Creating Steam lobby".to_string())
+	}
+
+	fn discover(&mut self, timeout_ms: u64) -> Result<String, String> {
+		if self.is_host { return Err("Cannot refresh as host".to_string()); }
+		if self.discovery.is_some() { return Ok("Already discovering".to_string()); }
+		let timeout = discovery_timeout(timeout_ms);
+		self.discovery = Some((Instant::now(), timeout));
+		let tx = self.signals.0.clone();
+		self.client.matchmaking().request_lobby_list(move |res| {
+			let _ = tx.send(Signal::LobbyList(res.map_err(|e| format!("Lobby list failed: {:?}", e))));
+		});
+		self.status = NetworkStatus::Discovering;
+		Ok(format!("Requesting Steam lobbies with timeout: {}ms", timeout.as_millis()))
+	}
+
+	#[inline] fn discovered_hosts(&self) -> Vec<HostInfo> { self.hosts.clone() }
+
+	fn join(&mut self, target: &str) -> Result<String, String> {
+		let raw = target.trim().parse::<u64>().map_err(|_| format!("'{}' is not a lobby id", target))?;
+		self.connect = Some(ConnectAttempt::from_settings(&raw.to_string()));
+		self.request_join();
+		self.status = NetworkStatus::Connecting;
+		Ok(format!("Joining lobby {}", raw))
+	}
+
+	#[inline] fn connect_attempt(&self) -> Option<&ConnectAttempt> { self.connect.as_ref() }
+
+	#[inline] fn peers(&self) -> Vec<PeerId> {
+		self.members().into_iter().map(|id| id.raw()).collect()
+	}
+
+	#[inline] fn get_friend_name(&self, peer: PeerId) -> String {
+		self.client.friends().get_friend(SteamId::from_raw(peer)).name()
+	}
+
+	fn send(&mut self, peer: PeerId, msg: &GameMessage) -> Result<(), String> {
+		let sent = self.client.networking().send_p2p_packet(SteamId::from_raw(peer), SendType::Reliable, &wire::encode_packet(msg));
+		if sent { Ok(()) } else { Err(format!("Could not send to {}", peer)) }
+	}
+
+	/// From a client this only goes to the lobby owner, the host passes it on so everyone sees edits in the same order
+	fn broadcast(&mut self, msg: &GameMessage) -> Result<usize, String> {
+		let data = wire::encode_packet(msg);
+		let networking = self.client.networking();
+		let targets = match (self.is_host, self.lobby) {
+			(false, Some(lobby)) => vec![self.client.matchmaking().lobby_owner(lobby)],
+			_ => self.members(),
+		};
+		Ok(targets.into_iter().filter(|id| networking.send_p2p_packet(*id, SendType::Reliable, &data)).count())
+	}
+
+	fn update(&mut self) {
+		self.single.run_callbacks();
+		while let Ok(signal) = self.signals.1.try_recv() {
+			self.handle_signal(signal);
+		}
+		self.check_discovery_timeout();
+		self.check_connect_timeout();
+
+		let networking = self.client.networking();
+		while let Some(size) = networking.is_p2p_packet_available() {
+			let mut buf = vec![0; size];
+			let Some((sender, len)) = networking.read_p2p_packet(&mut buf) else { break; };
+			match wire::decode_packet(&buf[..len]) {
+				Ok(msg) => self.events.push_back(NetworkEvent::Message(sender.raw(), msg)),
+				Err(e) => println!("Dropped a malformed packet from {}: {}", sender.raw(), e),
+			}
+		}
+	}
+
+	#[inline] fn pop_event(&mut self) -> Option<NetworkEvent> {
+		self.events.pop_front()
+	}
+
+	fn shutdown(&mut self) {
+		self.connect = None;
+		self.close_sessions();
+		if let Some(lobby) = self.lobby.take() {
+			self.client.matchmaking().leave_lobby(lobby);
+		}
+	}
+}
@@ -0,0 +1,182 @@
+
+use crate::{
+	block::{main::Chunk, math::ChunkCoord, extra::render_distance},
+	fs::binary::BinarySerializable,
+	network::{api, types::{GameMessage, PeerId}},
+	world::main::World,
+	ext::ptr,
+};
+use std::{
+	collections::{BTreeSet, VecDeque},
+	sync::Mutex,
+	time::{Duration, Instant},
+};
+
+/// Chunks sent to a peer but not acknowledged yet, the host waits for acks before sending more
+pub const STREAM_WINDOW: usize = 16;
+/// An unacknowledged chunk is sent again after this long (lost on the way, or the peer went quiet)
+pub const STREAM_RESEND: Duration = Duration::from_secs(2);
+/// Received chunks put into the world per frame, the rest waits so the client keeps running
+pub const CHUNKS_APPLIED_PER_FRAME: usize = 4;
+
+/// Radius (in chunks) a joining client asks for, what it can see with its render distance
+#[inline] pub fn stream_radius() -> u8 {
+	(render_distance() / Chunk::SIZE as f32).ceil() as u8
+}
+
+/// The chunks still to go to one peer, nearest to what it asked for first.
+/// Every chunk has a sequence number the peer acks once it is in its world,
+/// a resend keeps the number (so a late duplicate is ignored) but takes a fresh copy of the chunk
+#[derive(Debug, Clone)]
+pub struct ChunkStream {
+	pub peer: PeerId,
+	queue: VecDeque<ChunkCoord>,
+	in_flight: Vec<(u32, ChunkCoord, Instant)>, // seq, chunk, when it was last sent
+	next_seq: u32,
+}
+
+impl ChunkStream {
+	pub fn new(peer: PeerId, center: ChunkCoord, mut coords: Vec<ChunkCoord>, first_seq: u32) -> Self {
+		let (cx, cy, cz) = center.unpack();
+		coords.sort_by_key(|c| {
+			let (x, y, z) = c.unpack();
+			((x - cx).pow(2) + (y - cy).pow(2) + (z - cz).pow(2), c.into_u64())
+		});
+		Self { peer, queue: coords.into(), in_flight: Vec::new(), next_seq: first_seq }
+	}
+	#[inline] pub fn remaining(&self) -> usize { self.queue.len() + self.in_flight.len() }
+	#[inline] pub fn is_done(&self) -> bool { self.remaining() == 0 }
+
+	/// The chunk with that number arrived, false if it wasn't waiting (a duplicate ack)
+	pub fn ack(&mut self, seq: u32) -> bool {
+		let before = self.in_flight.len();
+		self.in_flight.retain(|(s, _, _)| *s != seq);
+		self.in_flight.len() != before
+	}
+
+	/// What to send now: the timed out chunks again, then new ones while the window has room
+	pub fn due(&mut self, now: Instant) -> Vec<(u32, ChunkCoord)> {
+		let mut due = Vec::new();
+		for (seq, coord, sent) in &mut self.in_flight {
+			if now.duration_since(*sent) >= STREAM_RESEND {
+				*sent = now;
+				due.push((*seq, *coord));
+			}
+		}
+		while self.in_flight.len() < STREAM_WINDOW {
+			let Some(coord) = self.queue.pop_front() else { break; };
+			let seq = self.next_seq;
+			self.next_seq = self.next_seq.wrapping_add(1);
+			self.in_flight.push((seq, coord, now));
+			due.push((seq, coord));
+		}
+		due
+	}
+}
+
+/// Streaming state of this game, the host side and the client side never run at once
+#[derive(Debug)]
+pub struct Streams {
+	pub sending: Vec<ChunkStream>,
+	pub inbox: VecDeque<(u32, ChunkCoord, Vec<u8>)>, // received, not in the world yet
+	pub seen: BTreeSet<u32>, // sequence numbers already received
+	next_seq: u32,
+}
+
+impl Streams {
+	pub const fn new() -> Self {
+		Self { sending: Vec::new(), inbox: VecDeque::new(), seen: BTreeSet::new(), next_seq: 0 }
+	}
+
+	/// (Re)starts sending a peer every generated chunk within `radius` of `center`
+	pub fn start(&mut self, world: &World, peer: PeerId, center: ChunkCoord, radius: u8) -> usize {
+		let (cx, cy, cz) = center.unpack();
+		let r = radius as i32;
+		let coords: Vec<ChunkCoord> = world.chunks.iter()
+			.filter(|(coord, chunk)| {
+				let (x, y, z) = coord.unpack();
+				chunk.finished_gen() && (x - cx).abs() <= r && (y - cy).abs() <= r && (z - cz).abs() <= r
+			})
+			.map(|(coord, _)| *coord)
+			.collect();
+		let count = coords.len();
+		let stream = ChunkStream::new(peer, center, coords, self.next_seq);
+		// Numbers are never reused, a late packet from an older stream can't pass for a new one
+		self.next_seq = self.next_seq.wrapping_add(count as u32);
+		self.sending.retain(|s| s.peer != peer);
+		self.sending.push(stream);
+		count
+	}
+
+	/// A chunk came in, false if it was a duplicate
+	pub fn receive(&mut self, seq: u32, coord: ChunkCoord, data: Vec<u8>) -> bool {
+		if !self.seen.insert(seq) { return false; }
+		self.inbox.push_back((seq, coord, data));
+		true
+	}
+
+	/// Takes a received chunk out of the queue early, so an edit to it isn't applied before the chunk itself
+	pub fn take_pending(&mut self, coord: ChunkCoord) -> Option<(u32, ChunkCoord, Vec<u8>)> {
+		let i = self.inbox.iter().position(|(_, c, _)| *c == coord)?;
+		self.inbox.remove(i)
+	}
+}
+
+impl Default for Streams {
+	#[inline] fn default() -> Self { Self::new() }
+}
+
+static STREAMS: Mutex<Streams> = Mutex::new(Streams::new());
+
+#[inline] pub fn with_streams<R>(f: impl FnOnce(&mut Streams) -> R) -> R {
+	let mut streams = STREAMS.lock().unwrap_or_else(|e| e.into_inner());
+	f(&mut streams)
+}
+
+/// Forgets every stream, when the network is shut down
+#[inline] pub fn reset() {
+	with_streams(|s| *s = Streams::new());
+}
+
+/// Puts a streamed chunk into the world and tells the host it arrived.
+/// Bad data is refused and the world keeps what it had
+pub fn apply_chunk(world: &mut World, seq: u32, coord: ChunkCoord, data: &[u8]) -> Result<(), String> {
+	let applied = world.load_chunk_bytes(coord, data)
+		.map_err(|e| format!("Streamed chunk {:?} refused: {}", coord, e));
+	// Acked either way, the same bytes would fail again
+	let _ = api::broadcast_message(&GameMessage::ChunkAck { seq });
+	applied
+}
+
+/// Runs once a frame: the host sends what the windows allow, the client puts a few received chunks into its world
+pub fn update() {
+	if !api::is_running() || !ptr::has_gamestate() { return; }
+	let world = ptr::get_gamestate().world_mut();
+	let now = Instant::now();
+
+	let outgoing: Vec<(PeerId, Vec<(u32, ChunkCoord)>)> = with_streams(|s| {
+		s.sending.iter_mut().map(|stream| (stream.peer, stream.due(now))).collect()
+	});
+	let mut lost = Vec::new();
+	for (peer, due) in outgoing {
+		for (seq, coord) in due {
+			// A chunk unloaded since the stream started goes as empty data, the client keeps what it has
+			let data = world.get_chunk(&coord).map_or(Vec::new(), |chunk| chunk.to_binary());
+			if let Err(e) = api::send_message(peer, &GameMessage::ChunkData { seq, coord, data }) {
+				println!("Chunk stream to {} stopped: {}", peer, e);
+				lost.push(peer);
+				break;
+			}
+		}
+	}
+	with_streams(|s| s.sending.retain(|stream| !stream.is_done() && !lost.contains(&stream.peer)));
+
+	for _ in 0..CHUNKS_APPLIED_PER_FRAME {
+		let Some((seq, coord, data)) = with_streams(|s| s.inbox.pop_front()) else { break; };
+		if data.is_empty() {
+			let _ = api::broadcast_message(&GameMessage::ChunkAck { seq });
+			continue;
+		}
+		if let Err(e) = apply_chunk(world, seq, coord, &data) { println!("{}", e); }
+	}
+}
@@ -0,0 +1,247 @@
+
+use crate::{
+	block::{
+		main::{Block, Material},
+		math::{BlockRotation, ChunkCoord, LocalPos},
+	},
+	network::{api, stream::{self, with_streams}, types::{GameMessage, PeerId}},
+	fs::binary::BinarySerializable,
+	item::inventory::ItemContainer,
+	render::texture::material_count,
+	world::main::World,
+	game::remote::{PlayerState, RemotePlayers, STATE_INTERVAL},
+	ui::chat::clean_chat_text,
+	ext::ptr,
+};
+use glam::{IVec3, Vec3};
+use std::{sync::Mutex, time::Instant};
+
+// The host's world is the real one: a client sends its edits to the host,
+// the host applies them in the order they come in and passes every edit (its own too) on to everyone.
+// The transports keep the order per peer and an edit carries the block's whole new state,
+// so the edit coming back to the client that made it changes nothing.
+
+/// Block of an edit packet, None for a material this game doesn't have or a rotation that can't be
+#[inline] pub fn block_from_packet(material: u16, rotation: u8) -> Option<Block> {
+	if material as usize >= material_count() { return None; }
+	Some(Block::from(Material(material), BlockRotation::from_u8(rotation)?))
+}
+
+#[inline] pub fn block_edit_message(pos: IVec3, block: Block) -> GameMessage {
+	GameMessage::BlockEdit { pos: pos.to_array(), material: block.material.inner(), rotation: block.rotation.as_u8() }
+}
+
+/// Tells the other players about a block changed here, does nothing when not connected
+pub fn block_changed(pos: IVec3, block: Block) {
+	if !api::is_running() { return; }
+	if let Err(e) = api::broadcast_message(&block_edit_message(pos, block)) {
+		println!("Block edit not sent: {}", e);
+	}
+}
+
+/// Sends whole chunks after an edit too big for single blocks (fills, pastes, undo)
+pub fn chunks_changed(world: &World, coords: impl IntoIterator<Item = ChunkCoord>) {
+	if !api::is_running() { return; }
+	for coord in coords {
+		let Some(data) = world.get_chunk(&coord).map(|chunk| chunk.to_binary()) else { continue; };
+		if let Err(e) = api::broadcast_message(&GameMessage::ChunkSync { coord, data }) {
+			println!("Chunk sync not sent: {}", e);
+			return;
+		}
+	}
+}
+
+/// Whether the host takes a whole chunk from a client: it has to be loaded here, inside the border
+/// and within the streamed radius of where that client stands (one it never reported can't replace anything)
+pub fn may_sync_chunk(world: &World, players: &RemotePlayers, peer: PeerId, coord: ChunkCoord) -> bool {
+	if world.get_chunk(&coord).is_none() || !world.chunk_in_border(coord) { return false; }
+	let Some(player) = players.get(peer, 0) else { return false; };
+	let (cx, cy, cz) = ChunkCoord::from_world_posf(player.state().pos).unpack();
+	let (x, y, z) = coord.unpack();
+	let radius = stream::stream_radius() as i32;
+	(x - cx).abs() <= radius && (y - cy).abs() <= radius && (z - cz).abs() <= radius
+}
+
+/// Greets a player that just joined with the world name, it answers with a `ChunkRequest` for the chunks around it
+pub fn send_world(peer: PeerId) -> Result<(), String> {
+	if api::is_host() != Ok(true) { return Ok(()); }
+	if !ptr::has_gamestate() { return Err("No world running".to_string()); }
+	api::send_message(peer, &GameMessage::WorldSync(ptr::get_gamestate().worldname().to_string()))
+}
+
+/// Player id the host gives a client's state when passing it on, 0 stays the sender itself
+#[inline] pub const fn relayed_player(peer: PeerId) -> u64 {
+	peer.wrapping_add(1)
+}
+
+static LAST_STATE_SENT: Mutex<Option<Instant>> = Mutex::new(None);
+
+/// Sends where the local player is every `STATE_INTERVAL`, runs once a frame
+pub fn send_player_state() {
+	if !api::is_running() || !ptr::has_gamestate() { return; }
+	let mut last = LAST_STATE_SENT.lock().unwrap_or_else(|e| e.into_inner());
+	if last.is_some_and(|sent| sent.elapsed().as_secs_f32() < STATE_INTERVAL) { return; }
+	*last = Some(Instant::now());
+
+	let player = ptr::get_gamestate().player();
+	let msg = GameMessage::PlayerState { player: 0, pos: player.pos().to_array(), yaw: player.yaw() };
+	if let Err(e) = api::broadcast_message(&msg) {
+		println!("Player state not sent: {}", e);
+	}
+}
+
+/// A player went away: its avatar (and the ones the host passed on through it) and its chunk stream go too
+pub fn handle_disconnect(peer: PeerId) {
+	println!("Peer {} left", peer);
+	with_streams(|s| s.sending.retain(|stream| stream.peer != peer));
+	if !ptr::has_gamestate() { return; }
+	ptr::get_gamestate().remote_players_mut().remove_peer(peer);
+	if api::is_host() == Ok(true) {
+		let _ = api::broadcast_message(&GameMessage::PlayerLeft { player: relayed_player(peer) });
+	}
+}
+
+/// Sends a chat line to everyone, the name on it is filled in by whoever receives it
+pub fn send_chat(text: &str) -> Result<(), String> {
+	if !api::is_running() { return Ok(()); }
+	api::broadcast_message(&GameMessage::ChatMessage { sender: String::new(), text: text.to_string() }).map(|_| ())
+}
+
+/// Drops every avatar, when the network is shut down
+pub fn forget_players() {
+	*LAST_STATE_SENT.lock().unwrap_or_else(|e| e.into_inner()) = None;
+	if ptr::has_gamestate() { ptr::get_gamestate().remote_players_mut().clear(); }
+}
+
+/// Puts a streamed chunk that is still queued into the world now, so an edit to it lands on top of it
+fn flush_pending(world: &mut World, coord: ChunkCoord) {
+	if let Some((seq, coord, data)) = with_streams(|s| s.take_pending(coord)) {
+		// The edit still goes on top of what the world has
+		if let Err(e) = stream::apply_chunk(world, seq, coord, &data) { println!("{}", e); }
+	}
+}
+
+/// Applies a game message from another player, a malformed one is refused without touching the world
+pub fn handle_packet(peer: PeerId, msg: GameMessage) -> Result<(), String> {
+	let is_host = api::is_host()?;
+	match msg {
+		GameMessage::GameState(_) => Ok(()),
+		GameMessage::WorldSync(name) => {
+			println!("Peer {} is in world '{}'", peer, name);
+			if is_host || !ptr::has_gamestate() { return Ok(()); }
+			let center = ChunkCoord::from_world_pos(ptr::get_gamestate().player().pos().as_ivec3());
+			api::send_message(peer, &GameMessage::ChunkRequest { center, radius: stream::stream_radius() })
+		}
+		GameMessage::ChunkRequest { center, radius } => {
+			if !is_host { return Err(format!("Chunk request from {}, but not hosting", peer)); }
+			if !ptr::has_gamestate() { return Err("No world running".to_string()); }
+			let world = ptr::get_gamestate().world();
+			// Nobody gets more than a client would ask for
+			let count = with_streams(|s| s.start(world, peer, center, radius.min(stream::stream_radius())));
+			println!("Streaming {} chunk(s) to peer {}", count, peer);
+			Ok(())
+		}
+		GameMessage::ChunkData { seq, coord, data } => {
+			if !with_streams(|s| s.receive(seq, coord, data)) {
+				// Seen before, the ack must have been lost (unless it is still waiting to be applied)
+				let queued = with_streams(|s| s.inbox.iter().any(|(queued_seq, _, _)| *queued_seq == seq));
+				if !queued { api::send_message(peer, &GameMessage::ChunkAck { seq })?; }
+			}
+			Ok(())
+		}
+		GameMessage::ChunkAck { seq } => {
+			with_streams(|s| s.sending.iter_mut().filter(|st| st.peer == peer).for_each(|st| { st.ack(seq); }));
+			Ok(())
+		}
+		GameMessage::BlockEdit { pos, material, rotation } => {
+			let block = block_from_packet(material, rotation)
+				.ok_or(format!("Bad block edit from {} (material {}, rotation {})", peer, material, rotation))?;
+			let pos = IVec3::from_array(pos);
+			if !ptr::has_gamestate() { return Err("No world running".to_string()); }
+			let world = ptr::get_gamestate().world_mut();
+			flush_pending(world, ChunkCoord::from_world_pos(pos));
+			if !world.apply_remote_block(pos, block) { return Ok(()); }
+			// Everyone else (and the sender, harmlessly) gets it in the host's order
+			if is_host {
+				world.mark_unsaved(ChunkCoord::from_world_pos(pos));
+				block_changed(pos, block);
+			}
+			Ok(())
+		}
+		GameMessage::PlayerState { player, pos, yaw } => {
+			// Only the host numbers players, a client can only speak for itself
+			if is_host && player != 0 { return Err(format!("Peer {} sent the state of player {}", peer, player)); }
+			if !ptr::has_gamestate() { return Ok(()); }
+			ptr::get_gamestate().remote_players_mut().update(peer, player, PlayerState::new(Vec3::from_array(pos), yaw));
+			if is_host {
+				// Everyone but the sender, nobody sees their own avatar
+				let relayed = GameMessage::PlayerState { player: relayed_player(peer), pos, yaw };
+				for other in api::peers().into_iter().filter(|p| *p != peer) {
+					api::send_message(other, &relayed)?;
+				}
+			}
+			Ok(())
+		}
+		GameMessage::PlayerLeft { player } => {
+			if is_host { return Err(format!("Peer {} sent a player leaving, but only the host does that", peer)); }
+			if ptr::has_gamestate() { ptr::get_gamestate().remote_players_mut().remove(peer, player); }
+			Ok(())
+		}
+		GameMessage::ChatMessage { sender, text } => {
+			let text = clean_chat_text(&text).ok_or(format!("Empty chat message from {}", peer))?;
+			// Names come from the backend so nobody can speak as someone else, only the host passes on named lines
+			if is_host && !sender.is_empty() { return Err(format!("Peer {} sent a chat line as '{}'", peer, sender)); }
+			let sender = if sender.is_empty() { api::get_friend_name(peer) } else { sender };
+			if is_host {
+				let relayed = GameMessage::ChatMessage { sender: sender.clone(), text: text.clone() };
+				for other in api::peers().into_iter().filter(|p| *p != peer) {
+					api::send_message(other, &relayed)?;
+				}
+			}
+			ptr::get_state().ui_manager.receive_chat(&sender, &text);
+			Ok(())
+		}
+		GameMessage::ChunkSync { coord, data } => {
+			if !ptr::has_gamestate() { return Err("No world running".to_string()); }
+			let game_state = ptr::get_gamestate();
+			if is_host && !may_sync_chunk(game_state.world(), game_state.remote_players(), peer, coord) {
+				return Err(format!("Peer {} may not replace chunk {:?}", peer, coord));
+			}
+			let world = game_state.world_mut();
+			flush_pending(world, coord);
+			world.load_chunk_bytes(coord, &data).map_err(|e| format!("Bad chunk from {}: {}", peer, e))?;
+			// Only the host keeps what others changed, a client's save isn't the shared world
+			if is_host {
+				world.mark_unsaved(coord);
+				chunks_changed(world, [coord]);
+			}
+			Ok(())
+		}
+	}
+}
+
+impl World {
+	/// Puts a block another player placed or broke, like `set_block` but without the undo history,
+	/// without handing replaced storage to the local player and without marking the chunk for saving.
+	/// Only loaded chunks are changed, returns false if the edit was dropped
+	pub fn apply_remote_block(&mut self, pos: IVec3, block: Block) -> bool {
+		if !self.in_border(pos) { return false; }
+		let coord = ChunkCoord::from_world_pos(pos);
+		let local = LocalPos::from(pos);
+		let index = usize::from(local);
+		let Some(chunk) = self.chunks.get_mut(&coord) else { return false; };
+		if chunk.get_block(index) == block { return true; }
+
+		chunk.remove_entity(local);
+		chunk.set_block(index, block);
+		if block.is_storage() {
+			let slot = block.get_storage();
+			chunk.add_entity(local, ItemContainer::new(slot.rows(), slot.cols()));
+		}
+		self.remove_processor(pos);
+		self.propagate_light(coord);
+		self.set_some_un_final(coord, IVec3::from(local));
+		self.clear_adjacent_occlusion(coord);
+		true
+	}
+}
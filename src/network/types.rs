@@ -1,12 +1,13 @@
 
+use crate::{block::math::ChunkCoord, fs::binary::{BinarySerializable, FixedBinarySize}, network::wire};
 use ggrs::{Config, SessionBuilder, UdpNonBlockingSocket, PlayerType};
 use serde::{Deserialize, Serialize};
 use std::{
 	process::Command, io,
-	time::Instant, thread,
+	time::{Duration, Instant}, thread,
 	collections::VecDeque,
 	sync::{Arc, Mutex},
-	net::{IpAddr, Ipv4Addr, SocketAddr, UdpSocket},
+	net::{IpAddr, Ipv4Addr, SocketAddr, TcpStream, UdpSocket},
 };
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -23,6 +24,196 @@ pub enum NetworkMessage {
 	WorldInfoResponse(String), // Contains world name
 }
 
+/// Who a game message came from or goes to, only has to be unique inside one backend
+pub type PeerId = u64;
+
+/// Game data sent between players once they are connected.
+/// Every backend sends the same packets (see `wire`), only the transport differs
+#[derive(Debug, Clone, PartialEq)]
+pub enum GameMessage {
+	GameState(i32),
+	WorldSync(String), // world name
+	BlockEdit { pos: [i32; 3], material: u16, rotation: u8 }, // the block's whole new state, so applying it twice changes nothing
+	ChunkSync { coord: ChunkCoord, data: Vec<u8> }, // a whole chunk after a big edit, `Chunk::to_binary` (run length encoded)
+	ChunkRequest { center: ChunkCoord, radius: u8 }, // a joining client asks for the chunks around it
+	ChunkData { seq: u32, coord: ChunkCoord, data: Vec<u8> }, // one streamed chunk, empty data if the host no longer has it
+	ChunkAck { seq: u32 }, // the streamed chunk is in the client's world
+	PlayerState { player: u64, pos: [f32; 3], yaw: f32 }, // player 0 is the sender, the host passes the others on numbered
+	PlayerLeft { player: u64 }, // a player the host passed on is gone
+	ChatMessage { sender: String, text: String }, // empty sender is the peer it came from, the host fills it in when passing it on
+}
+
+impl GameMessage {
+	#[inline] const fn tag(&self) -> u8 {
+		match self {
+			Self::GameState(_) => 0,
+			Self::WorldSync(_) => 1,
+			Self::BlockEdit { .. } => 2,
+			Self::ChunkSync { .. } => 3,
+			Self::ChunkRequest { .. } => 4,
+			Self::ChunkData { .. } => 5,
+			Self::ChunkAck { .. } => 6,
+			Self::PlayerState { .. } => 7,
+			Self::PlayerLeft { .. } => 8,
+			Self::ChatMessage { .. } => 9,
+		}
+	}
+}
+
+impl BinarySerializable for GameMessage {
+	fn to_binary(&self) -> Vec<u8> {
+		let mut data = Vec::with_capacity(self.binary_size());
+		data.push(self.tag());
+		match self {
+			Self::GameState(state) => data.extend_from_slice(&state.to_binary()),
+			Self::WorldSync(name) => data.extend_from_slice(&name.to_binary()),
+			Self::BlockEdit { pos, material, rotation } => {
+				for axis in pos { data.extend_from_slice(&axis.to_binary()); }
+				data.extend_from_slice(&material.to_binary());
+				data.push(*rotation);
+			}
+			Self::ChunkSync { coord, data: chunk } => {
+				data.extend_from_slice(&coord.to_binary());
+				data.extend_from_slice(&(chunk.len() as u32).to_binary());
+				data.extend_from_slice(chunk);
+			}
+			Self::ChunkRequest { center, radius } => {
+				data.extend_from_slice(&center.to_binary());
+				data.push(*radius);
+			}
+			Self::ChunkData { seq, coord, data: chunk } => {
+				data.extend_from_slice(&seq.to_binary());
+				data.extend_from_slice(&coord.to_binary());
+				data.extend_from_slice(&(chunk.len() as u32).to_binary());
+				data.extend_from_slice(chunk);
+			}
+			Self::ChunkAck { seq } => data.extend_from_slice(&seq.to_binary()),
+			Self::PlayerState { player, pos, yaw } => {
+				data.extend_from_slice(&player.to_binary());
+				for axis in pos { data.extend_from_slice(&axis.to_binary()); }
+				data.extend_from_slice(&yaw.to_binary());
+			}
+			Self::PlayerLeft { player } => data.extend_from_slice(&player.to_binary()),
+			Self::ChatMessage { sender, text } => {
+				data.extend_from_slice(&sender.to_binary());
+				data.extend_from_slice(&text.to_binary());
+			}
+		}
+		data
+	}
+	fn from_binary(bytes: &[u8]) -> Option<Self> {
+		let body = bytes.get(1..)?;
+		match *bytes.first()? {
+			0 => Some(Self::GameState(i32::from_binary(body)?)),
+			1 => Some(Self::WorldSync(String::from_binary(body)?)),
+			2 => {
+				let axis = |i: usize| i32::from_binary(body.get(i * i32::BINARY_SIZE..)?);
+				let pos = [axis(0)?, axis(1)?, axis(2)?];
+				let offset = 3 * i32::BINARY_SIZE;
+				let material = u16::from_binary(body.get(offset..)?)?;
+				let rotation = *body.get(offset + u16::BINARY_SIZE)?;
+				Some(Self::BlockEdit { pos, material, rotation })
+			}
+			3 => {
+				let (coord, data) = read_chunk(body)?;
+				Some(Self::ChunkSync { coord, data })
+			}
+			4 => {
+				let center = ChunkCoord::from_binary(body)?;
+				let radius = *body.get(ChunkCoord::BINARY_SIZE)?;
+				Some(Self::ChunkRequest { center, radius })
+			}
+			5 => {
+				let seq = u32::from_binary(body)?;
+				let (coord, data) = read_chunk(body.get(u32::BINARY_SIZE..)?)?;
+				Some(Self::ChunkData { seq, coord, data })
+			}
+			6 => Some(Self::ChunkAck { seq: u32::from_binary(body)? }),
+			7 => {
+				let player = u64::from_binary(body)?;
+				let float = |i: usize| f32::from_binary(body.get(u64::BINARY_SIZE + i * f32::BINARY_SIZE..)?);
+				let pos = [float(0)?, float(1)?, float(2)?];
+				// NaN or infinity would break the avatar (and the interpolation after it)
+				let yaw = float(3)?;
+				if !pos.iter().chain([&yaw]).all(|v| v.is_finite()) { return None; }
+				Some(Self::PlayerState { player, pos, yaw })
+			}
+			8 => Some(Self::PlayerLeft { player: u64::from_binary(body)? }),
+			9 => {
+				let sender = String::from_binary(body)?;
+				let text = String::from_binary(body.get(sender.binary_size()..)?)?;
+				Some(Self::ChatMessage { sender, text })
+			}
+			_ => None,
+		}
+	}
+	fn binary_size(&self) -> usize {
+		1 + match self {
+			Self::GameState(_) => i32::BINARY_SIZE,
+			Self::WorldSync(name) => name.binary_size(),
+			Self::BlockEdit { .. } => 3 * i32::BINARY_SIZE + u16::BINARY_SIZE + 1,
+			Self::ChunkSync { data, .. } => ChunkCoord::BINARY_SIZE + u32::BINARY_SIZE + data.len(),
+			Self::ChunkRequest { .. } => ChunkCoord::BINARY_SIZE + 1,
+			Self::ChunkData { data, .. } => u32::BINARY_SIZE + ChunkCoord::BINARY_SIZE + u32::BINARY_SIZE + data.len(),
+			Self::ChunkAck { .. } => u32::BINARY_SIZE,
+			Self::PlayerState { .. } => u64::BINARY_SIZE + 4 * f32::BINARY_SIZE,
+			Self::PlayerLeft { .. } => u64::BINARY_SIZE,
+			Self::ChatMessage { sender, text } => sender.binary_size() + text.binary_size(),
+		}
+	}
+}
+
+/// Chunk coord and its length prefixed bytes
+fn read_chunk(bytes: &[u8]) -> Option<(ChunkCoord, Vec<u8>)> {
+	let coord = ChunkCoord::from_binary(bytes)?;
+	let len = u32::from_binary(bytes.get(ChunkCoord::BINARY_SIZE..)?)? as usize;
+	let start = ChunkCoord::BINARY_SIZE + u32::BINARY_SIZE;
+	Some((coord, bytes.get(start..start + len)?.to_vec()))
+}
+
+/// Biggest frame accepted from a stream, anything claiming more is a broken or hostile peer
+pub const MAX_FRAME_SIZE: usize = 1 << 24;
+
+/// The message as a packet (see `wire`) with its length in front, for backends that send over a byte stream
+pub fn encode_frame(msg: &GameMessage) -> Vec<u8> {
+	let body = wire::encode_packet(msg);
+	let mut data = Vec::with_capacity(u32::BINARY_SIZE + body.len());
+	data.extend_from_slice(&(body.len() as u32).to_binary());
+	data.extend_from_slice(&body);
+	data
+}
+
+/// Takes every complete frame off the front of `buffer`, a partial frame stays for the next read.
+/// A frame that doesn't decode is dropped, Err if the stream can't be trusted anymore (bad length or unknown format)
+pub fn decode_frames(buffer: &mut Vec<u8>) -> Result<Vec<GameMessage>, String> {
+	let mut messages = Vec::new();
+	let mut offset = 0;
+	while let Some(len) = buffer.get(offset..offset + u32::BINARY_SIZE).and_then(u32::from_binary) {
+		let len = len as usize;
+		if len > MAX_FRAME_SIZE { return Err(format!("Frame of {} bytes is too big", len)); }
+		let start = offset + u32::BINARY_SIZE;
+		let Some(body) = buffer.get(start..start + len) else { break; };
+		if !wire::known_header(body) { return Err("Unknown packet format".to_string()); }
+		match wire::decode_packet(body) {
+			Ok(msg) => messages.push(msg),
+			Err(e) => println!("Dropped a malformed frame: {}", e),
+		}
+		offset = start + len;
+	}
+	buffer.drain(..offset);
+	Ok(messages)
+}
+
+/// Someone connected over the direct backend, game messages go over the handshake's TCP stream
+#[derive(Debug)]
+pub struct Peer {
+	pub id: PeerId,
+	pub addr: SocketAddr,
+	pub stream: TcpStream, // non blocking
+	pub inbox: Vec<u8>, // received bytes not yet making a whole frame
+	pub outbox: Vec<u8>, // bytes the socket didn't take yet
+}
+
 #[derive(Debug)]
 pub struct HostConfig;
 
@@ -49,6 +240,9 @@ pub enum NetworkEvent {
 	Ready,
 	HostsDiscovered(Vec<(u32, SocketAddr, String)>), // pid, address, world_name
 	DiscoveryComplete(DiscoveryResult),
+	PeerJoined(PeerId), // a new player is connected and can be sent the world
+	PeerLeft(PeerId), // a connected player went away
+	Message(PeerId, GameMessage),
 }
 
 #[derive(Debug, Clone)]
@@ -66,10 +260,50 @@ pub struct HostInfo {
 	pub pid: u32,
 	pub address: SocketAddr,
 	pub world_name: String,
+	pub join: String, // what `connect_to_host` takes to join this host
+}
+
+/// A join still going on, tried again a few times before giving up
+#[derive(Debug, Clone)]
+pub struct ConnectAttempt {
+	pub target: String,
+	pub retry: u8, // 0 on the first try
+	pub max_retries: u8,
+	timeout: Duration,
+	started: Instant,
+}
+
+impl ConnectAttempt {
+	#[inline] pub fn new(target: &str, timeout: Duration, max_retries: u8) -> Self {
+		Self { target: target.to_string(), retry: 0, max_retries, timeout, started: Instant::now() }
+	}
+	/// With the timeout and retries from the settings
+	#[inline] pub fn from_settings(target: &str) -> Self {
+		let config = &crate::ext::ptr::get_settings().net_config;
+		Self::new(target, Duration::from_secs_f32(config.connect_timeout.max(0.)), config.connect_retries)
+	}
+	#[inline] pub fn timed_out(&self, now: Instant) -> bool {
+		now.duration_since(self.started) >= self.timeout
+	}
+	#[inline] pub const fn timeout(&self) -> Duration { self.timeout }
+	/// Starts the next try, false once the retries are used up
+	pub fn next_try(&mut self, now: Instant) -> bool {
+		if self.retry >= self.max_retries { return false; }
+		self.retry += 1;
+		self.started = now;
+		true
+	}
+	/// What the connect screens show
+	pub fn label(&self) -> String {
+		match self.retry {
+			0 => format!("Connecting to {}...", self.target),
+			retry => format!("Connecting to {}... (retry {}/{})", self.target, retry, self.max_retries),
+		}
+	}
 }
 
 pub struct PendingConnection {
-	pub handle: std::thread::JoinHandle<Result<(SocketAddr, SocketAddr), String>>,
+	pub handle: std::thread::JoinHandle<Result<(SocketAddr, SocketAddr, TcpStream), String>>,
 	pub peer_addr: SocketAddr,
 }
 
@@ -93,6 +327,9 @@ pub struct NetworkSystem {
 	pub target_host_ip: Option<String>,
 	pub broadcast_listener_thread: Option<std::thread::JoinHandle<()>>,
 	pub pending_connections: Vec<PendingConnection>,
+	pub peers: Vec<Peer>,
+	pub next_peer_id: PeerId,
+	pub connect: Option<ConnectAttempt>, // the join in progress (client only)
 }
 
 impl NetworkSystem {
@@ -118,14 +355,20 @@ impl NetworkSystem {
 			target_host_ip: None,
 			broadcast_listener_thread: None,
 			pending_connections: Vec::new(),
+			peers: Vec::new(),
+			next_peer_id: 0,
+			connect: None,
 		}
 	}
 
 	pub fn push_event(&mut self, event: NetworkEvent) {
 		self.event_queue.push_back(event);
-		// Keep queue size reasonable
+		// Keep queue size reasonable, game messages and players coming and going are never dropped (they would never come again)
 		if self.event_queue.len() > 100 {
-			self.event_queue.pop_front();
+			let keep = |e: &NetworkEvent| matches!(e, NetworkEvent::Message(..) | NetworkEvent::PeerJoined(_) | NetworkEvent::PeerLeft(_));
+			if let Some(i) = self.event_queue.iter().position(|e| !keep(e)) {
+				self.event_queue.remove(i);
+			}
 		}
 	}
 	
@@ -0,0 +1,170 @@
+
+use crate::{
+	block::math::ChunkCoord,
+	fs::{binary::BinarySerializable, json::{JsonError, JsonParser, JsonSerializable, JsonValue}},
+	network::types::{GameMessage, MAX_FRAME_SIZE},
+};
+use std::{collections::HashMap, sync::atomic::{AtomicU8, Ordering}};
+
+// Every packet starts with a byte telling how the rest is written, so a peer reads
+// whatever the sender picked: JSON to read along while debugging, the binary form,
+// or the binary form compressed (only for the big ones, chunks and world syncs).
+
+/// Header of a packet holding `GameMessage::to_binary`
+pub const HEADER_BINARY: u8 = 0;
+/// Header of a packet holding `to_binary` compressed with lz4, size prepended
+pub const HEADER_COMPRESSED: u8 = 1;
+/// Header of a packet holding the message as JSON text
+pub const HEADER_JSON: u8 = 2;
+/// Binary packets at least this big are compressed (if that makes them smaller)
+pub const COMPRESS_THRESHOLD: usize = 512;
+
+/// How this game writes the packets it sends, any of them is read
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[repr(u8)]
+pub enum WireFormat {
+	Json = 0, // readable, for debugging
+	Binary = 1,
+	#[default]
+	Compressed = 2, // binary, compressed above `COMPRESS_THRESHOLD`
+}
+
+impl WireFormat {
+	#[inline] pub const fn to_u8(self) -> u8 { self as u8 }
+	#[inline] pub const fn from_u8(value: u8) -> Option<Self> {
+		match value {
+			0 => Some(Self::Json),
+			1 => Some(Self::Binary),
+			2 => Some(Self::Compressed),
+			_ => None,
+		}
+	}
+}
+
+static WIRE_FORMAT: AtomicU8 = AtomicU8::new(WireFormat::Compressed.to_u8());
+
+/// Format of every packet sent from now on
+#[inline] pub fn set_format(format: WireFormat) {
+	WIRE_FORMAT.store(format.to_u8(), Ordering::Release);
+}
+#[inline] pub fn format() -> WireFormat {
+	WireFormat::from_u8(WIRE_FORMAT.load(Ordering::Acquire)).unwrap_or_default()
+}
+
+/// The message as one packet in the current format
+#[inline] pub fn encode_packet(msg: &GameMessage) -> Vec<u8> {
+	encode_packet_as(msg, format())
+}
+
+pub fn encode_packet_as(msg: &GameMessage, format: WireFormat) -> Vec<u8> {
+	if format == WireFormat::Json {
+		let mut data = vec![HEADER_JSON];
+		data.extend_from_slice(msg.to_json().to_string().as_bytes());
+		return data;
+	}
+	let body = msg.to_binary();
+	if format == WireFormat::Compressed && body.len() >= COMPRESS_THRESHOLD {
+		let compressed = lz4_flex::compress_prepend_size(&body);
+		if compressed.len() < body.len() {
+			let mut data = Vec::with_capacity(1 + compressed.len());
+			data.push(HEADER_COMPRESSED);
+			data.extend_from_slice(&compressed);
+			return data;
+		}
+	}
+	let mut data = Vec::with_capacity(1 + body.len());
+	data.push(HEADER_BINARY);
+	data.extend_from_slice(&body);
+	data
+}
+
+/// Whether a packet starts with a header this game knows, a peer sending anything else isn't talking to us
+#[inline] pub fn known_header(packet: &[u8]) -> bool {
+	matches!(packet.first(), Some(&(HEADER_BINARY | HEADER_COMPRESSED | HEADER_JSON)))
+}
+
+/// Reads a packet in any format, Err for a broken one (it is dropped, never applied in part)
+pub fn decode_packet(packet: &[u8]) -> Result<GameMessage, String> {
+	let (&header, body) = packet.split_first().ok_or("Empty packet")?;
+	match header {
+		HEADER_BINARY => GameMessage::from_binary(body).ok_or("Invalid game message".to_string()),
+		HEADER_COMPRESSED => {
+			// The size is checked before anything is allocated for it
+			let size = body.get(..4).and_then(u32::from_binary).ok_or("Truncated compressed packet")? as usize;
+			if size > MAX_FRAME_SIZE { return Err(format!("Compressed packet claims {} bytes", size)); }
+			let data = lz4_flex::decompress_size_prepended(body).map_err(|e| format!("Could not decompress: {}", e))?;
+			GameMessage::from_binary(&data).ok_or("Invalid compressed game message".to_string())
+		}
+		HEADER_JSON => {
+			let text = std::str::from_utf8(body).map_err(|_| "JSON packet isn't text")?;
+			let value = JsonParser::parse(text).map_err(|e| e.to_string())?;
+			GameMessage::from_json(&value).map_err(|e| e.to_string())
+		}
+		other => Err(format!("Unknown packet format {}", other)),
+	}
+}
+
+/// Field of a message object
+fn field<'a>(obj: &'a HashMap<String, JsonValue>, name: &'static str) -> Result<&'a JsonValue, JsonError> {
+	obj.get(name).ok_or(JsonError::MissingField(name.into()))
+}
+#[inline] fn get<T: JsonSerializable>(obj: &HashMap<String, JsonValue>, name: &'static str) -> Result<T, JsonError> {
+	T::from_json(field(obj, name)?)
+}
+/// Player ids are full u64s (Steam ids), too big for a JSON number so they go as text
+fn get_player(obj: &HashMap<String, JsonValue>) -> Result<u64, JsonError> {
+	field(obj, "player")?.as_str().and_then(|s| s.parse().ok()).ok_or(JsonError::Custom("player is not an id".into()))
+}
+#[inline] fn coord_json(coord: ChunkCoord) -> JsonValue {
+	let (x, y, z) = coord.unpack();
+	[x, y, z].to_json()
+}
+#[inline] fn get_coord(obj: &HashMap<String, JsonValue>, name: &'static str) -> Result<ChunkCoord, JsonError> {
+	let [x, y, z] = get::<[i32; 3]>(obj, name)?;
+	Ok(ChunkCoord::new(x, y, z))
+}
+
+/// `{"type": "BlockEdit", "pos": [..], ..}`, only used by the JSON packets
+impl JsonSerializable for GameMessage {
+	fn from_json(value: &JsonValue) -> Result<Self, JsonError> {
+		let obj = value.as_object().ok_or(JsonError::Custom("Game message is not an object".into()))?;
+		let kind: String = get(obj, "type")?;
+		Ok(match kind.as_str() {
+			"GameState" => Self::GameState(get(obj, "state")?),
+			"WorldSync" => Self::WorldSync(get(obj, "name")?),
+			"BlockEdit" => Self::BlockEdit { pos: get(obj, "pos")?, material: get(obj, "material")?, rotation: get(obj, "rotation")? },
+			"ChunkSync" => Self::ChunkSync { coord: get_coord(obj, "coord")?, data: get(obj, "data")? },
+			"ChunkRequest" => Self::ChunkRequest { center: get_coord(obj, "center")?, radius: get(obj, "radius")? },
+			"ChunkData" => Self::ChunkData { seq: get(obj, "seq")?, coord: get_coord(obj, "coord")?, data: get(obj, "data")? },
+			"ChunkAck" => Self::ChunkAck { seq: get(obj, "seq")? },
+			"PlayerState" => Self::PlayerState { player: get_player(obj)?, pos: get(obj, "pos")?, yaw: get(obj, "yaw")? },
+			"PlayerLeft" => Self::PlayerLeft { player: get_player(obj)? },
+			"ChatMessage" => Self::ChatMessage { sender: get(obj, "sender")?, text: get(obj, "text")? },
+			other => return Err(JsonError::Custom(format!("Unknown game message '{}'", other).into())),
+		})
+	}
+
+	fn to_json(&self) -> JsonValue {
+		let (kind, fields): (&str, Vec<(&str, JsonValue)>) = match self {
+			Self::GameState(state) => ("GameState", vec![("state", state.to_json())]),
+			Self::WorldSync(name) => ("WorldSync", vec![("name", name.to_json())]),
+			Self::BlockEdit { pos, material, rotation } => ("BlockEdit", vec![
+				("pos", pos.to_json()), ("material", material.to_json()), ("rotation", rotation.to_json()),
+			]),
+			Self::ChunkSync { coord, data } => ("ChunkSync", vec![("coord", coord_json(*coord)), ("data", data.to_json())]),
+			Self::ChunkRequest { center, radius } => ("ChunkRequest", vec![("center", coord_json(*center)), ("radius", radius.to_json())]),
+			Self::ChunkData { seq, coord, data } => ("ChunkData", vec![
+				("seq", seq.to_json()), ("coord", coord_json(*coord)), ("data", data.to_json()),
+			]),
+			Self::ChunkAck { seq } => ("ChunkAck", vec![("seq", seq.to_json())]),
+			Self::PlayerState { player, pos, yaw } => ("PlayerState", vec![
+				("player", player.to_string().to_json()), ("pos", pos.to_json()), ("yaw", yaw.to_json()),
+			]),
+			Self::PlayerLeft { player } => ("PlayerLeft", vec![("player", player.to_string().to_json())]),
+			Self::ChatMessage { sender, text } => ("ChatMessage", vec![("sender", sender.to_json()), ("text", text.to_json())]),
+		};
+		let mut obj: HashMap<String, JsonValue> = fields.into_iter().map(|(k, v)| (k.to_string(), v)).collect();
+		obj.insert("type".to_string(), JsonValue::String(kind.to_string()));
+		JsonValue::Object(obj)
+	}
+}
@@ -1,6 +1,5 @@
 
 use crate::block::math::ChunkCoord;
-use crate::block::main::Chunk;
 use crate::utils::vec3;
 use glam::{Vec3, IVec3};
 
@@ -46,13 +45,7 @@ impl AABB {
 
 	// Create AABB for a chunk at given coordinates with chunk size
 	#[inline] pub const fn from_chunk_coord(chunk_coord: &ChunkCoord) -> Self {
-		let chunk_size = Chunk::SIZE_F;
-		let min = Vec3::new(
-			chunk_coord.x() as f32 * chunk_size,
-			chunk_coord.y() as f32 * chunk_size,
-			chunk_coord.z() as f32 * chunk_size,
-		);
-		let max = Vec3::new(min.x + chunk_size, min.y + chunk_size, min.z + chunk_size);
+		let (min, max) = chunk_coord.world_aabb();
 		Self { min, max }
 	}
 	
@@ -131,6 +124,27 @@ impl AABB {
 		dx * dx + dy * dy + dz * dz
 	}
 
+	/// Slab test for a ray, returns the entry distance (in `dir` lengths, so the real distance for a normalized `dir`).
+	/// 0 if the ray starts inside, None if it misses or the box is behind it
+	pub fn ray_intersect(&self, origin: Vec3, dir: Vec3) -> Option<f32> {
+		let mut t_enter = f32::NEG_INFINITY;
+		let mut t_exit = f32::INFINITY;
+		for axis in 0..3 {
+			let (o, d) = (origin[axis], dir[axis]);
+			if d == 0. {
+				// Parallel to this slab, only hits if it already runs between the two planes
+				if o < self.min[axis] || o > self.max[axis] { return None; }
+				continue;
+			}
+			let inv = 1. / d;
+			let (t0, t1) = ((self.min[axis] - o) * inv, (self.max[axis] - o) * inv);
+			t_enter = t_enter.max(t0.min(t1));
+			t_exit = t_exit.min(t0.max(t1));
+		}
+		if t_exit < t_enter.max(0.) { return None; }
+		Some(t_enter.max(0.))
+	}
+
 
 	/// Returns a new AABB translated by the given vector
 	#[inline] pub const fn translate(&self, translation: Vec3) -> Self {
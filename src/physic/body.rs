@@ -0,0 +1,255 @@
+
+use crate::{
+	physic::aabb::AABB,
+	world::main::World,
+	block::main::Material,
+};
+use glam::{IVec3, Vec3};
+
+/// Longest distance (per axis) a single sweep moves, longer moves are split into sub steps
+pub const MAX_SWEEP_STEP: f32 = 1.0;
+/// Default downward acceleration in blocks per second squared
+pub const DEFAULT_GRAVITY: f32 = 28.0;
+/// Default fastest fall speed in blocks per second
+pub const DEFAULT_TERMINAL_VELOCITY: f32 = 55.0;
+/// Vertical velocity kept while standing, just enough for every move to touch the ground again
+const GROUND_BIAS: f32 = 0.5;
+/// Upward push of a fully submerged body in multiples of its gravity, above 1 so it slowly floats up
+const FLUID_BUOYANCY: f32 = 1.1;
+/// How fast a fluid slows the body down (per second, fully submerged)
+const FLUID_DRAG: f32 = 4.0;
+/// Fastest a fully submerged body moves
+const FLUID_MAX_SPEED: f32 = 3.0;
+/// Distances this small count as touching, float error from the previous move can't push the body into a block
+const COLLISION_EPSILON: f32 = 1e-4;
+
+/// Which axes were blocked during a move (grounded = `y` while moving down)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CollisionFlags {
+	pub x: bool,
+	pub y: bool,
+	pub z: bool,
+}
+impl CollisionFlags {
+	#[inline] pub const fn any(&self) -> bool {
+		self.x || self.y || self.z
+	}
+	#[inline] const fn merge(self, other: Self) -> Self {
+		Self { x: self.x || other.x, y: self.y || other.y, z: self.z || other.z }
+	}
+	#[inline] const fn set(&mut self, axis: usize) {
+		match axis {
+			0 => self.x = true,
+			1 => self.y = true,
+			_ => self.z = true,
+		}
+	}
+}
+
+/// A box moving through the voxel world
+#[derive(Debug, Clone, PartialEq)]
+pub struct Body {
+	pub aabb: AABB,
+	pub velocity: Vec3,
+	pub step_height: f32, // highest ledge walked onto automatically, 0 turns stepping off
+	pub grounded: bool, // set by `move_and_collide` when a downward move was stopped
+	pub gravity: f32, // downward acceleration
+	pub terminal_velocity: f32, // fall speed is clamped to this
+	pub fluid: Option<Material>, // swum through instead of collided with, set by `update_in_fluid`
+}
+
+impl Body {
+	#[inline] pub const fn new(aabb: AABB) -> Self {
+		Self {
+			aabb, velocity: Vec3::ZERO, step_height: 0., grounded: false,
+			gravity: DEFAULT_GRAVITY, terminal_velocity: DEFAULT_TERMINAL_VELOCITY, fluid: None,
+		}
+	}
+	#[inline] pub const fn with_step_height(mut self, step_height: f32) -> Self {
+		self.step_height = step_height;
+		self
+	}
+	#[inline] pub const fn with_gravity(mut self, gravity: f32, terminal_velocity: f32) -> Self {
+		self.gravity = gravity;
+		self.terminal_velocity = terminal_velocity;
+		self
+	}
+
+	/// Integrates gravity into the vertical velocity.
+	/// A grounded body only keeps a small downward bias so standing still does not build up speed
+	pub fn apply_gravity(&mut self, dt: f32) {
+		if self.grounded && self.velocity.y <= 0. {
+			self.velocity.y = -GROUND_BIAS;
+			return;
+		}
+		self.velocity.y = (self.velocity.y - self.gravity * dt).max(-self.terminal_velocity);
+	}
+
+	/// Launches the body upwards, only works while standing on something
+	#[inline] pub const fn jump(&mut self, impulse: f32) -> bool {
+		if !self.grounded { return false; }
+		self.velocity.y = impulse;
+		self.grounded = false;
+		true
+	}
+
+	/// How much of the body is inside `fluid` blocks, 0 (dry) to 1 (fully under)
+	pub fn submersion(&self, world: &World, fluid: Material) -> f32 {
+		let volume = self.aabb.volume();
+		if volume <= 0. { return 0.; }
+
+		let min = self.aabb.min.floor().as_ivec3();
+		let max = self.aabb.max.ceil().as_ivec3();
+		let mut inside = 0.;
+		for x in min.x..max.x {
+			for y in min.y..max.y {
+				for z in min.z..max.z {
+					let pos = IVec3::new(x, y, z);
+					if world.get_block(pos).material() != fluid { continue; }
+					if let Some(overlap) = self.aabb.intersection(&AABB::from_ivec(pos, pos + IVec3::ONE)) {
+						inside += overlap.volume();
+					}
+				}
+			}
+		}
+		(inside / volume).min(1.)
+	}
+
+	/// Gravity for a body that may be in `fluid`, instead of `apply_gravity`.
+	/// The buoyancy, drag and speed cap all scale with the submerged part, out of the fluid it is plain gravity.
+	/// Returns the submersion
+	pub fn update_in_fluid(&mut self, world: &World, fluid: Material, dt: f32) -> f32 {
+		self.fluid = Some(fluid);
+		let submersion = self.submersion(world, fluid);
+		if submersion <= 0. {
+			self.apply_gravity(dt);
+			return 0.;
+		}
+
+		self.velocity.y += self.gravity * (FLUID_BUOYANCY * submersion - 1.) * dt;
+		self.velocity *= (-FLUID_DRAG * submersion * dt).exp();
+
+		let max_speed = self.terminal_velocity + (FLUID_MAX_SPEED - self.terminal_velocity) * submersion;
+		self.velocity = self.velocity.clamp_length_max(max_speed);
+		submersion
+	}
+
+	/// One physics frame: gravity then the velocity move
+	#[inline] pub fn update(&mut self, world: &World, dt: f32) -> CollisionFlags {
+		self.apply_gravity(dt);
+		self.move_and_collide(world, self.velocity * dt)
+	}
+
+	/// Center of the bottom face, the same point `AABB::from_pos` takes
+	#[inline] pub const fn position(&self) -> Vec3 {
+		let center = self.aabb.center();
+		Vec3::new(center.x, self.aabb.min.y, center.z)
+	}
+
+	/// Moves the body by `delta`, stopping at solid blocks and sliding along them.
+	/// A grounded body walking into a ledge up to `step_height` high steps onto it instead of stopping.
+	/// The velocity is zeroed on every axis that hit something
+	pub fn move_and_collide(&mut self, world: &World, delta: Vec3) -> CollisionFlags {
+		let start = self.aabb;
+		let mut flags = self.sweep_steps(world, delta);
+
+		if self.step_height > 0. && self.grounded && delta.y <= 0. && (flags.x || flags.z) {
+			let direct = self.aabb;
+			match self.try_step(world, start, delta) {
+				Some(step_flags) if horizontal_distance(&start, &self.aabb) > horizontal_distance(&start, &direct) + COLLISION_EPSILON => {
+					flags = step_flags;
+				},
+				_ => self.aabb = direct,
+			}
+		}
+
+		self.grounded = flags.y && delta.y < 0.;
+		if flags.x { self.velocity.x = 0.; }
+		if flags.y { self.velocity.y = 0.; }
+		if flags.z { self.velocity.z = 0.; }
+		flags
+	}
+
+	/// Splits the move into sub steps so the queried block region stays small for fast moves
+	fn sweep_steps(&mut self, world: &World, delta: Vec3) -> CollisionFlags {
+		let steps = (delta.abs().max_element() / MAX_SWEEP_STEP).ceil().max(1.) as u32;
+		let step = delta / steps as f32;
+
+		let mut flags = CollisionFlags::default();
+		for _ in 0..steps {
+			flags = flags.merge(self.sweep(world, step));
+		}
+		flags
+	}
+
+	/// Redoes the move from `start` as up by `step_height`, the horizontal part, then back down onto the ledge.
+	/// None if the head would hit something on the way up
+	fn try_step(&mut self, world: &World, start: AABB, delta: Vec3) -> Option<CollisionFlags> {
+		self.aabb = start;
+		if self.sweep_steps(world, Vec3::Y * self.step_height).y { return None; }
+		let horizontal = self.sweep_steps(world, Vec3::new(delta.x, 0., delta.z));
+		let down = self.sweep_steps(world, Vec3::Y * (delta.y - self.step_height));
+		Some(CollisionFlags { x: horizontal.x, y: down.y, z: horizontal.z })
+	}
+
+	/// One sub step, each axis is clipped against the blocks on its own (Y first so the floor edges don't catch the walking)
+	fn sweep(&mut self, world: &World, delta: Vec3) -> CollisionFlags {
+		let region = self.aabb.union(&self.aabb.translate(delta));
+		let blocks = solid_blocks(world, &region, self.fluid);
+
+		let mut flags = CollisionFlags::default();
+		for axis in [1, 0, 2] {
+			let wanted = delta[axis];
+			if wanted == 0. { continue; }
+
+			let moved = clip_axis(&self.aabb, &blocks, axis, wanted);
+			if moved != wanted { flags.set(axis); }
+
+			let mut offset = Vec3::ZERO;
+			offset[axis] = moved;
+			self.aabb = self.aabb.translate(offset);
+		}
+		flags
+	}
+}
+
+#[inline] fn horizontal_distance(a: &AABB, b: &AABB) -> f32 {
+	let d = b.min - a.min;
+	d.x * d.x + d.z * d.z
+}
+
+/// Boxes of the solid blocks the region overlaps, with one cell of margin (`passable` does not count as solid)
+pub fn solid_blocks(world: &World, region: &AABB, passable: Option<Material>) -> Vec<AABB> {
+	let min = region.min.floor().as_ivec3() - IVec3::ONE;
+	let max = region.max.floor().as_ivec3() + IVec3::ONE;
+
+	let mut blocks = Vec::new();
+	for x in min.x..=max.x {
+		for y in min.y..=max.y {
+			for z in min.z..=max.z {
+				let pos = IVec3::new(x, y, z);
+				let block = world.get_block(pos);
+				if block.is_empty() || Some(block.material()) == passable { continue; }
+				blocks.push(AABB::from_ivec(pos, pos + IVec3::ONE));
+			}
+		}
+	}
+	blocks
+}
+
+/// How far `body` can move along `axis` (up to `distance`) before touching one of the blocks
+fn clip_axis(body: &AABB, blocks: &[AABB], axis: usize, mut distance: f32) -> f32 {
+	let (a1, a2) = ((axis + 1) % 3, (axis + 2) % 3);
+	for block in blocks {
+		// Only blocks overlapping on the other two axes can be hit (touching does not count)
+		if body.max[a1] <= block.min[a1] + COLLISION_EPSILON || body.min[a1] >= block.max[a1] - COLLISION_EPSILON { continue; }
+		if body.max[a2] <= block.min[a2] + COLLISION_EPSILON || body.min[a2] >= block.max[a2] - COLLISION_EPSILON { continue; }
+
+		if distance > 0. && body.max[axis] <= block.min[axis] + COLLISION_EPSILON {
+			distance = distance.min(block.min[axis] - body.max[axis]);
+		} else if distance < 0. && body.min[axis] >= block.max[axis] - COLLISION_EPSILON {
+			distance = distance.max(block.max[axis] - body.min[axis]);
+		}
+	}
+	distance
+}
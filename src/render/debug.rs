@@ -1,6 +1,7 @@
 
 use wgpu::util::DeviceExt;
-use glam::Vec3;
+use crate::world::border::WorldBorder;
+use glam::{IVec3, Vec3};
 use std::mem;
 
 // =============================================
@@ -14,6 +15,8 @@ pub struct Line {
 	pub start: [f32;3],
 	pub direction: [f32;3], // this is the ending point relative to the start position
 }
+// The line shaders index the storage buffer as 6 floats per line
+const _: () = assert!(mem::size_of::<Line>() == 6 * mem::size_of::<f32>());
 
 impl Line {
 	/// Describes the vertex buffer layout for wgpu
@@ -96,6 +99,132 @@ impl DebugLines {
 	}
 }
 
+/// How far the block outline sits outside the block, keeps it from z-fighting with the faces
+pub const HIGHLIGHT_OFFSET: f32 = 0.002;
+
+/// The 12 edges of the axis aligned cube starting at `min` with the given size
+#[inline] pub fn cube_outline(min: Vec3, size: f32) -> [Line; 12] {
+	box_outline(min, Vec3::splat(size))
+}
+
+/// The 12 edges of the axis aligned box starting at `min`
+pub fn box_outline(min: Vec3, size: Vec3) -> [Line; 12] {
+	let (x, y, z) = (Vec3::X * size.x, Vec3::Y * size.y, Vec3::Z * size.z);
+	[
+		// Edges along X
+		Line::new(min, x), Line::new(min + y, x), Line::new(min + z, x), Line::new(min + y + z, x),
+		// Edges along Y
+		Line::new(min, y), Line::new(min + x, y), Line::new(min + z, y), Line::new(min + x + z, y),
+		// Edges along Z
+		Line::new(min, z), Line::new(min + x, z), Line::new(min + y, z), Line::new(min + x + y, z),
+	]
+}
+
+/// Outline drawn around the block the player is looking at
+pub struct BlockHighlight {
+	pub line_buffer: LineBuffer,
+	target: Option<IVec3>,
+}
+
+impl BlockHighlight {
+	pub fn new(device: &wgpu::Device) -> Self {
+		Self {
+			line_buffer: LineBuffer::new(device, &[Line::default(); 12]),
+			target: None,
+		}
+	}
+	#[inline] pub const fn target(&self) -> Option<IVec3> { self.target }
+
+	/// Moves the outline to the given block, the buffer is only rewritten when the target changes
+	pub fn set_target(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, target: Option<IVec3>) {
+		if self.target == target { return; }
+		self.target = target;
+		let Some(pos) = target else { return; };
+		let lines = cube_outline(pos.as_vec3() - Vec3::splat(HIGHLIGHT_OFFSET), 1. + HIGHLIGHT_OFFSET * 2.);
+		self.line_buffer.update(device, queue, &lines);
+	}
+
+	pub fn render<'a>(&'a self, pass: &mut wgpu::RenderPass<'a>) {
+		if self.target.is_none() { return; }
+		pass.set_bind_group(0, &self.line_buffer.bind_group, &[]);
+		pass.draw(0..2, 0..12);
+	}
+}
+
+/// How close the player has to get before the world border shows up, in blocks
+pub const BORDER_VIEW_DISTANCE: f32 = 12.;
+
+/// Grid on the border walls within `BORDER_VIEW_DISTANCE` of `eye`, one line per block
+pub fn border_grid(border: &WorldBorder, eye: Vec3) -> Vec<Line> {
+	let view = BORDER_VIEW_DISTANCE;
+	let (min, max) = (border.min().as_vec2(), border.max().as_vec2());
+	let (y0, y1) = ((eye.y - view).floor(), (eye.y + view).ceil());
+	let mut lines = Vec::new();
+
+	// (wall runs along z, wall position, eye position across the wall, eye position along it, wall start, wall end)
+	let walls = [
+		(true, min.x, eye.x, eye.z, min.y, max.y),
+		(true, max.x, eye.x, eye.z, min.y, max.y),
+		(false, min.y, eye.z, eye.x, min.x, max.x),
+		(false, max.y, eye.z, eye.x, min.x, max.x),
+	];
+	for (along_z, wall, across, along, start, end) in walls {
+		if (across - wall).abs() > view { continue; }
+		let (a0, a1) = ((along - view).floor().max(start), (along + view).ceil().min(end));
+		if a0 >= a1 { continue; }
+		let point = |a: f32, y: f32| if along_z { Vec3::new(wall, y, a) } else { Vec3::new(a, y, wall) };
+		let axis = if along_z { Vec3::Z } else { Vec3::X };
+
+		let mut a = a0;
+		while a <= a1 {
+			lines.push(Line::new(point(a, y0), Vec3::Y * (y1 - y0)));
+			a += 1.;
+		}
+		let mut y = y0;
+		while y <= y1 {
+			lines.push(Line::new(point(a0, y), axis * (a1 - a0)));
+			y += 1.;
+		}
+	}
+	lines
+}
+
+/// Translucent grid drawn on the world border when the player gets close, uses the block highlight pipeline
+pub struct BorderLines {
+	pub line_buffer: LineBuffer,
+	count: u32,
+	key: Option<(WorldBorder, IVec3)>, // border and eye block the buffer was built for
+}
+
+impl BorderLines {
+	pub fn new(device: &wgpu::Device) -> Self {
+		Self {
+			line_buffer: LineBuffer::new(device, &[Line::default()]),
+			count: 0,
+			key: None,
+		}
+	}
+	#[inline] pub const fn is_visible(&self) -> bool { self.count > 0 }
+
+	/// Rebuilds the grid when the border changed or the player moved to another block
+	pub fn update(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, border: Option<WorldBorder>, eye: Vec3) {
+		let key = border.map(|b| (b, eye.floor().as_ivec3()));
+		if self.key == key { return; }
+		self.key = key;
+		let lines = border.map(|b| border_grid(&b, eye)).unwrap_or_default();
+		self.count = lines.len() as u32;
+		if !lines.is_empty() {
+			self.line_buffer.update(device, queue, &lines);
+		}
+	}
+
+	pub fn render<'a>(&'a self, pass: &mut wgpu::RenderPass<'a>) {
+		if self.count == 0 { return; }
+		pass.set_bind_group(0, &self.line_buffer.bind_group, &[]);
+		pass.draw(0..2, 0..self.count);
+	}
+}
+
 pub struct LineBuffer {
 	pub buffer: wgpu::Buffer,
 	pub bind_group: wgpu::BindGroup,
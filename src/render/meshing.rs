@@ -1,6 +1,10 @@
 
+use crate::block::main::{Block, Chunk, Material};
+use crate::block::math::LocalPos;
+use crate::block::light::MAX_LIGHT;
 use wgpu::util::DeviceExt;
 use glam::IVec3;
+use std::collections::HashMap;
 use std::mem;
 
 // =============================================
@@ -17,6 +21,7 @@ pub struct Vertex {
 #[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct InstanceRaw {
 	pub packed_data: u32,  // 5 bits per axis (x,y,z) + normal index in 3 bits
+	pub extent: u32,  // quad size - 1 along the face's 2 axes, 5 bits each (0 = single block face) + light level in bits 10-13
 }
 
 impl Vertex {
@@ -50,9 +55,27 @@ impl InstanceRaw {
 					shader_location: 1,
 					format: wgpu::VertexFormat::Uint32,
 				},
+				wgpu::VertexAttribute {
+					offset: mem::size_of::<u32>() as wgpu::BufferAddress,
+					shader_location: 2,
+					format: wgpu::VertexFormat::Uint32,
+				},
 			],
 		}
 	}
+	/// Single block face (1x1 quad)
+	#[inline] pub const fn new(packed_data: u32) -> Self {
+		Self { packed_data, extent: 0 }
+	}
+	/// Merged face covering `width` x `height` blocks (both 1..=Chunk::SIZE)
+	#[inline] pub const fn quad(packed_data: u32, width: u32, height: u32) -> Self {
+		Self { packed_data, extent: (width - 1) | (height - 1) << 5 }
+	}
+	/// Light level (0-15) of the cell the face looks into, the shader darkens the face with it
+	#[inline] pub const fn with_light(mut self, light: u8) -> Self {
+		self.extent |= ((light & 0xF) as u32) << 10;
+		self
+	}
 }
 
 // =============================================
@@ -62,17 +85,246 @@ impl InstanceRaw {
 /// Builder for constructing chunk meshes efficiently
 pub struct ChunkMeshBuilder {
 	pub instances: Vec<InstanceRaw>,
+	pub greedy: bool,
 }
 impl ChunkMeshBuilder {
 	/// Creates a new mesh builder with optimized initial capacity
 	#[inline] pub fn new() -> Self {
 		Self { // set the starting capacity smaller because now with all the culling there is chance for a chunk to be invisible
 			instances: Vec::new(),
+			greedy: false,
 		}
 	}
+	/// Toggles the greedy path, with `false` every visible face gets its own quad (add_cube_faces)
+	#[inline] pub fn with_greedy(mut self, greedy: bool) -> Self {
+		self.greedy = greedy;
+		self
+	}
 	#[inline] pub fn build(self, device: &wgpu::Device) -> GeometryBuffer {
 		GeometryBuffer::new(device, &self.instances)
 	}
+
+	/// Builds the chunk mesh by merging coplanar faces of the same block (material + rotation) and light level into bigger quads.
+	/// Storage blocks are never merged, they get the plain per face quads.
+	/// A missing (`None`) neighbor chunk counts as solid so the border faces get culled like in the non greedy path.
+	pub fn build_greedy(mut self, device: &wgpu::Device, chunk: &Chunk, neighbors: &[Option<&Chunk>; 6]) -> GeometryBuffer {
+		self.push_greedy(chunk, neighbors);
+		self.build(device)
+	}
+
+	/// CPU side of `build_greedy`, fills `instances` without touching the gpu
+	pub fn push_greedy(&mut self, chunk: &Chunk, neighbors: &[Option<&Chunk>; 6]) {
+		const SIZE: usize = Chunk::SIZE;
+		// is_storage goes through the item lut by name, so only do it once per material
+		let mut storage_cache: HashMap<Material, bool> = HashMap::new();
+		let mut mask: Vec<Option<(Block, u8)>> = vec![None; SIZE * SIZE];
+
+		// Slices without a single solid block have no faces at all, mostly empty chunks skip almost everything
+		let mut occupied = [[false; SIZE]; 3];
+		for (pos, _) in chunk.iter_solid() {
+			occupied[0][pos.x() as usize] = true;
+			occupied[1][pos.y() as usize] = true;
+			occupied[2][pos.z() as usize] = true;
+		}
+
+		for face in 0..CUBE_FACES.len() {
+			let (normal_axis, a_axis, b_axis) = FACE_AXES[face];
+
+			for slice in 0..SIZE {
+				if !occupied[normal_axis][slice] { continue; }
+				// Collect the visible faces of this slice
+				for b in 0..SIZE {
+					for a in 0..SIZE {
+						let mut pos = IVec3::ZERO;
+						pos[normal_axis] = slice as i32;
+						pos[a_axis] = a as i32;
+						pos[b_axis] = b as i32;
+
+						let cell = &mut mask[b * SIZE + a];
+						*cell = None;
+
+						let block = chunk.get_block(usize::from(LocalPos::from(pos)));
+						if block.is_empty() || is_face_hidden(chunk, neighbors, pos, face) { continue; }
+
+						let is_storage = *storage_cache.entry(block.material())
+							.or_insert_with(|| block.is_storage());
+						let light = face_light(chunk, neighbors, pos, face);
+						if is_storage {
+							self.instances.push(InstanceRaw::new(pack_face(pos, face, block.material())).with_light(light));
+							continue;
+						}
+						*cell = Some((block, light));
+					}
+				}
+
+				// Merge the collected faces into rectangles
+				for b in 0..SIZE {
+					let mut a = 0;
+					while a < SIZE {
+						let Some(key) = mask[b * SIZE + a] else { a += 1; continue; };
+
+						let mut width = 1;
+						while a + width < SIZE && mask[b * SIZE + a + width] == Some(key) {
+							width += 1;
+						}
+
+						let mut height = 1;
+						'grow: while b + height < SIZE {
+							let row = (b + height) * SIZE;
+							for i in a..a + width {
+								if mask[row + i] != Some(key) { break 'grow; }
+							}
+							height += 1;
+						}
+
+						for row in b..b + height {
+							mask[row * SIZE + a..row * SIZE + a + width].fill(None);
+						}
+
+						let mut pos = IVec3::ZERO;
+						pos[normal_axis] = slice as i32;
+						pos[a_axis] = a as i32;
+						pos[b_axis] = b as i32;
+						let (block, light) = key;
+						self.instances.push(InstanceRaw::quad(pack_face(pos, face, block.material()), width as u32, height as u32).with_light(light));
+
+						a += width;
+					}
+				}
+			}
+		}
+	}
+}
+
+/// Coarsest level of detail, its cells are `1 << MAX_LOD` blocks wide
+pub const MAX_LOD: u8 = 2;
+
+impl ChunkMeshBuilder {
+	/// Builds a coarse mesh where every `2^lod` wide cube of blocks is drawn as a single cube, lod 0 is the full detail greedy mesh
+	pub fn build_lod(mut self, device: &wgpu::Device, chunk: &Chunk, neighbors: &[Option<&Chunk>; 6], lod: u8) -> GeometryBuffer {
+		self.push_lod(chunk, neighbors, lod);
+		self.build(device)
+	}
+
+	/// CPU side of `build_lod`.
+	/// Any solid block makes its cell solid (drawn with the most common material), so the coarse shell covers
+	/// everything the detailed one does and no holes open up next to chunks meshed at another level.
+	/// Faces on the chunk border are only culled if the neighbor chunk is solid along the whole cell face
+	pub fn push_lod(&mut self, chunk: &Chunk, neighbors: &[Option<&Chunk>; 6], lod: u8) {
+		let lod = lod.min(MAX_LOD).min(Chunk::BITS);
+		if lod == 0 { return self.push_greedy(chunk, neighbors); }
+		let cell = 1usize << lod;
+		let cells = Chunk::SIZE / cell;
+		let cell_index = |c: IVec3| c.x as usize + (c.y as usize + c.z as usize * cells) * cells;
+
+		// Material counts per cell, then the most common one (ties go to the lower id so rebuilds are stable)
+		let mut counts: Vec<Vec<(Material, u32)>> = vec![Vec::new(); cells * cells * cells];
+		for (pos, block) in chunk.iter_solid() {
+			let counts = &mut counts[cell_index(IVec3::from(pos) / cell as i32)];
+			match counts.iter_mut().find(|(material, _)| *material == block.material()) {
+				Some((_, count)) => *count += 1,
+				None => counts.push((block.material(), 1)),
+			}
+		}
+		let grid: Vec<Option<Material>> = counts.iter()
+			.map(|counts| counts.iter().max_by_key(|(material, count)| (*count, std::cmp::Reverse(material.inner()))).map(|(material, _)| *material))
+			.collect();
+
+		for cz in 0..cells as i32 {
+			for cy in 0..cells as i32 {
+				for cx in 0..cells as i32 {
+					let c = IVec3::new(cx, cy, cz);
+					let Some(material) = grid[cell_index(c)] else { continue; };
+					let min = c * cell as i32;
+
+					for face in 0..CUBE_FACES.len() {
+						let normal_axis = FACE_AXES[face].0;
+						let next = c + CUBE_FACES[face];
+						let hidden = if next.cmpge(IVec3::ZERO).all() && next.cmplt(IVec3::splat(cells as i32)).all() {
+							grid[cell_index(next)].is_some()
+						} else {
+							is_cell_face_hidden(neighbors, min, cell, face)
+						};
+						if hidden { continue; }
+
+						// The quad starts at the cell's min corner, positive faces sit on the far block of the cell
+						let mut pos = min;
+						if CUBE_FACES[face][normal_axis] > 0 { pos[normal_axis] += cell as i32 - 1; }
+						let light = face_light(chunk, neighbors, pos, face);
+						self.instances.push(InstanceRaw::quad(pack_face(pos, face, material), cell as u32, cell as u32).with_light(light));
+					}
+				}
+			}
+		}
+	}
+}
+
+/// Border face of a LOD cell, hidden if the neighbor chunk is missing (like `is_face_hidden`) or solid along all of it
+fn is_cell_face_hidden(neighbors: &[Option<&Chunk>; 6], min: IVec3, cell: usize, face: usize) -> bool {
+	let Some(neighbor) = neighbors[face] else { return true; };
+	let (normal_axis, a_axis, b_axis) = FACE_AXES[face];
+	let mut pos = min;
+	// Only the face direction leaves the chunk, LocalPos wraps it into the neighbor
+	pos[normal_axis] = if CUBE_FACES[face][normal_axis] > 0 { Chunk::SIZE_I } else { -1 };
+	for b in 0..cell as i32 {
+		for a in 0..cell as i32 {
+			let mut p = pos;
+			p[a_axis] += a;
+			p[b_axis] += b;
+			if neighbor.get_block(usize::from(LocalPos::from(p))).is_empty() { return false; }
+		}
+	}
+	true
+}
+
+/// Axes per face as (normal, a, b) where a and b are the quad's width and height directions,
+/// these have to match `face_scale` in the chunk shader
+pub const FACE_AXES: [(usize, usize, usize); 6] = [
+	(0, 1, 2), // Left / Right : width along Y, height along Z
+	(0, 1, 2),
+	(2, 0, 1), // Front / Back : width along X, height along Y
+	(2, 0, 1),
+	(1, 0, 2), // Top / Bottom : width along X, height along Z
+	(1, 0, 2),
+];
+
+/// Instance position is always 5 bits per axis (what the shader decodes), independent of `Chunk::BITS`
+#[inline]
+pub const fn pack_pos(local: LocalPos) -> u32 {
+	local.x() as u32 | (local.y() as u32) << 5 | (local.z() as u32) << 10
+}
+
+/// 0-15 : pos ; 15-18 : face ; 19 ... block id
+#[inline]
+const fn pack_face(pos: IVec3, face: usize, material: Material) -> u32 {
+	let local = LocalPos::new(pos.x as u8, pos.y as u8, pos.z as u8);
+	pack_pos(local) | (face as u32) << 15 | (material.inner() as u32) << 19
+}
+
+/// Checks if the block next to `pos` (in the direction of `face`) hides that face
+#[inline]
+fn is_face_hidden(chunk: &Chunk, neighbors: &[Option<&Chunk>; 6], pos: IVec3, face: usize) -> bool {
+	let neighbor_pos = pos + CUBE_FACES[face];
+	let idx = usize::from(LocalPos::from(neighbor_pos));
+	if chunk.contains_position(neighbor_pos) {
+		return !chunk.get_block(idx).is_empty();
+	}
+	// Only the face direction can leave the chunk, so the neighbor has the same index as the face
+	match neighbors[face] {
+		Some(neighbor) => !neighbor.get_block(idx).is_empty(),
+		None => true, // No neighbor chunk - just cull for now
+	}
+}
+
+/// Light level of the cell in front of the face, missing neighbours count as fully lit
+#[inline]
+pub fn face_light(chunk: &Chunk, neighbors: &[Option<&Chunk>; 6], pos: IVec3, face: usize) -> u8 {
+	let neighbor_pos = pos + CUBE_FACES[face];
+	let idx = usize::from(LocalPos::from(neighbor_pos));
+	if chunk.contains_position(neighbor_pos) {
+		return chunk.get_light(idx);
+	}
+	neighbors[face].map_or(MAX_LIGHT, |neighbor| neighbor.get_light(idx))
 }
 
 /// Make sure your CUBE_FACES constant matches the neighbor array order:
@@ -136,4 +388,123 @@ impl GeometryBuffer {
 			num_instances: 0,
 		}
 	}
+
+	/// How many instances fit in the buffer
+	#[inline] pub fn capacity(&self) -> u32 {
+		(self.instance_buffer.size() / mem::size_of::<InstanceRaw>() as wgpu::BufferAddress) as u32
+	}
+}
+
+// =============================================
+// Geometry Buffer Pool
+// =============================================
+
+/// Smallest bucket, meshes below this still get a buffer this big so small remeshes can reuse each other
+pub const POOL_MIN_CAPACITY: u32 = 64;
+/// Default cap on how many idle buffers the pool holds on to
+pub const POOL_MAX_BUFFERS: usize = 256;
+
+/// Capacity of the bucket a mesh of `instances` gets a buffer from (next power of two, at least `POOL_MIN_CAPACITY`)
+#[inline] pub const fn pool_bucket(instances: u32) -> u32 {
+	let n = if instances < POOL_MIN_CAPACITY { POOL_MIN_CAPACITY } else { instances };
+	n.next_power_of_two()
+}
+/// Bucket a released buffer goes back to, the biggest one it can still serve fully (None if it is too small for any)
+#[inline] pub const fn pool_release_bucket(capacity: u32) -> Option<u32> {
+	if capacity < POOL_MIN_CAPACITY { return None; }
+	Some(1 << (31 - capacity.leading_zeros()))
+}
+
+/// Recycles chunk instance buffers by capacity bucket instead of creating and dropping one on every remesh.
+/// Released buffers wait in `pending` until the frame that may still draw them is submitted (`recycle`),
+/// so a buffer is never rewritten while a pass recorded earlier in the same frame reads it
+#[derive(Debug)]
+pub struct GeometryBufferPool {
+	buckets: HashMap<u32, Vec<wgpu::Buffer>>,
+	pending: Vec<wgpu::Buffer>,
+	discarded: Vec<wgpu::Buffer>, // never reused, dropped by the next `recycle`
+	idle: usize, // buffers in `buckets`
+	pub max_buffers: usize, // idle buffers over this are dropped
+}
+
+impl GeometryBufferPool {
+	#[inline] pub fn new() -> Self {
+		Self::with_max_buffers(POOL_MAX_BUFFERS)
+	}
+	#[inline] pub fn with_max_buffers(max_buffers: usize) -> Self {
+		Self {
+			buckets: HashMap::new(),
+			pending: Vec::new(),
+			discarded: Vec::new(),
+			idle: 0,
+			max_buffers,
+		}
+	}
+	/// Idle buffers ready to be reused
+	#[inline] pub const fn len(&self) -> usize { self.idle }
+	#[inline] pub const fn is_empty(&self) -> bool { self.idle == 0 }
+	/// Buffers waiting for the current frame to be submitted
+	#[inline] pub fn pending(&self) -> usize { self.pending.len() + self.discarded.len() }
+
+	/// Uploads the instances into a pooled buffer, creating a bigger one if no idle buffer of the bucket is left.
+	/// Buffers are never reused for a mesh that doesn't fit, growing always means a new buffer
+	pub fn acquire(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, instances: &[InstanceRaw]) -> GeometryBuffer {
+		let bucket = pool_bucket(instances.len() as u32);
+		let reused = self.buckets.get_mut(&bucket).and_then(Vec::pop);
+
+		let instance_buffer = match reused {
+			Some(buffer) => {
+				self.idle -= 1;
+				buffer
+			}
+			None => device.create_buffer(&wgpu::BufferDescriptor {
+				label: Some("Pooled Vertex Buffer"),
+				size: bucket as wgpu::BufferAddress * mem::size_of::<InstanceRaw>() as wgpu::BufferAddress,
+				usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+				mapped_at_creation: false,
+			}),
+		};
+		if !instances.is_empty() {
+			queue.write_buffer(&instance_buffer, 0, bytemuck::cast_slice(instances));
+		}
+
+		GeometryBuffer {
+			instance_buffer,
+			num_instances: instances.len() as u32,
+		}
+	}
+
+	/// Hands a buffer back to the pool, it becomes reusable after the next `recycle`
+	#[inline] pub fn release(&mut self, geometry: GeometryBuffer) {
+		self.pending.push(geometry.instance_buffer);
+	}
+
+	/// Gives up a buffer for good, it is dropped after the next `recycle` instead of being kept for reuse
+	#[inline] pub fn discard(&mut self, geometry: GeometryBuffer) {
+		self.discarded.push(geometry.instance_buffer);
+	}
+
+	/// Moves the released buffers into their buckets, call once the frame is submitted.
+	/// Anything over `max_buffers` (or too small to serve any bucket) is dropped, and so is everything discarded
+	pub fn recycle(&mut self) {
+		self.discarded.clear();
+		for buffer in self.pending.drain(..) {
+			if self.idle >= self.max_buffers { break; }
+			let capacity = (buffer.size() / mem::size_of::<InstanceRaw>() as wgpu::BufferAddress) as u32;
+			let Some(bucket) = pool_release_bucket(capacity) else { continue; };
+			self.buckets.entry(bucket).or_default().push(buffer);
+			self.idle += 1;
+		}
+	}
+
+	/// Drops every idle and pending buffer
+	#[inline] pub fn clear(&mut self) {
+		self.buckets.clear();
+		self.pending.clear();
+		self.discarded.clear();
+		self.idle = 0;
+	}
+}
+impl Default for GeometryBufferPool {
+	#[inline] fn default() -> Self { Self::new() }
 }
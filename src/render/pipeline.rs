@@ -1,8 +1,12 @@
 
-use crate::block::extra::RENDER_DISTANCE;
+use crate::block::extra::{render_distance, REACH};
 use crate::ext::ptr;
 use crate::render::meshing::{Vertex, InstanceRaw, VERTICES};
 use crate::render::texture;
+use crate::render::debug::{BlockHighlight, BorderLines};
+use crate::render::world::Avatars;
+use crate::world::main::RayHit;
+use crate::block::main::Chunk;
 use crate::get_string;
 use crate::State;
 use wgpu::{
@@ -10,7 +14,7 @@ use wgpu::{
 	ShaderModuleDescriptor, ShaderModule, PipelineLayout, PipelineLayoutDescriptor, StencilState, LoadOp, StoreOp, Operations,
 	ShaderSource, TextureFormat, VertexBufferLayout, PrimitiveState, DepthStencilState, DepthBiasState, RenderPassDescriptor,
 	RenderPipelineDescriptor, VertexState, FragmentState, ColorTargetState, BlendState, SurfaceError, BufferUsages, RenderPassDepthStencilAttachment,
-	ColorWrites, MultisampleState, PrimitiveTopology, Face, FrontFace, PolygonMode, TextureViewDescriptor, RenderPassColorAttachment,
+	ColorWrites, MultisampleState, PrimitiveTopology, Face, FrontFace, PolygonMode, TextureViewDescriptor, RenderPassColorAttachment, Features,
 };
 
 /// Struct holding all render pipelines and their associated shaders
@@ -21,20 +25,133 @@ pub struct Pipeline {
 	pub post_pipeline: RenderPipeline,
 	pub sky_pipeline: RenderPipeline,
 	pub debug_pipeline: RenderPipeline,
+	pub highlight_pipeline: RenderPipeline,
+	pub avatar_pipeline: RenderPipeline,
+	pub present_pipeline: RenderPipeline,
+	/// Chunk pipeline drawing only the triangle edges, None if the device has no `POLYGON_MODE_LINE`
+	pub chunk_wireframe_pipeline: Option<RenderPipeline>,
+	wireframe: bool,
+	sample_count: u32, // MSAA samples of the 3D pipelines
+	/// Outline around the block the player is looking at
+	pub highlight: BlockHighlight,
+	/// Grid on the world border
+	pub border: BorderLines,
+	/// Boxes of the other players in a network game
+	pub avatars: Avatars,
+	bloom: Bloom,
 }
 
 impl Pipeline {
-	/// Creates all render pipelines with proper configuration
+	/// Creates all render pipelines with proper configuration, `sample_count` is the MSAA of the 3D passes
 	#[inline]
-	pub fn new(device: &Device, config: &SurfaceConfiguration, layouts: &[BindGroupLayout]) -> Self {
-		// Create shaders
-		let shaders = Shaders::new(device);
+	pub fn new(device: &Device, config: &SurfaceConfiguration, layouts: &[BindGroupLayout], sample_count: u32) -> Self {
+		// Create shaders, a broken one at startup leaves nothing to draw with
+		let shaders = Shaders::new(device).unwrap_or_else(|e| panic!("{}", e));
 
-		let post_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
-			label: Some("Post Processing Pipeline Layout"),
-			bind_group_layouts: &[&layouts[4]],
-			..Default::default()
-		});
+		let scene = ScenePipelines::new(device, config.format, layouts, &shaders, sample_count);
+		let (post_pipeline, present_pipeline) = screen_pipelines(device, config.format, layouts, &shaders);
+
+		Self {
+			chunk_pipeline: scene.chunk,
+			post_pipeline,
+			sky_pipeline: scene.sky,
+			debug_pipeline: scene.debug,
+			highlight_pipeline: scene.highlight,
+			avatar_pipeline: scene.avatar,
+			present_pipeline,
+			chunk_wireframe_pipeline: scene.chunk_wireframe,
+			wireframe: false,
+			sample_count,
+			highlight: BlockHighlight::new(device),
+			border: BorderLines::new(device),
+			avatars: Avatars::new(device),
+			bloom: Bloom::new(device, config, &layouts[4]),
+		}
+	}
+
+	/// Rebuilds the pipelines of the 3D passes for a new MSAA sample count, the rest is single sampled and stays.
+	/// If the shaders don't build the old pipelines and sample count are kept (the error is handed back)
+	pub fn set_sample_count(&mut self, device: &Device, config: &SurfaceConfiguration, layouts: &[BindGroupLayout], sample_count: u32) -> Result<(), String> {
+		if sample_count == self.sample_count { return Ok(()); }
+		let scene = catch_validation(device, || {
+			let shaders = Shaders::new(device)?;
+			Ok::<_, String>(ScenePipelines::new(device, config.format, layouts, &shaders, sample_count))
+		})??;
+		self.chunk_pipeline = scene.chunk;
+		self.chunk_wireframe_pipeline = scene.chunk_wireframe;
+		self.sky_pipeline = scene.sky;
+		self.debug_pipeline = scene.debug;
+		self.highlight_pipeline = scene.highlight;
+		self.avatar_pipeline = scene.avatar;
+		self.sample_count = sample_count;
+		Ok(())
+	}
+	#[inline] pub const fn sample_count(&self) -> u32 { self.sample_count }
+
+	/// Builds every world pipeline again from the current shader sources.
+	/// All of them are swapped in together, or none if a shader doesn't compile (the error is handed back)
+	pub fn reload_shaders(&mut self, device: &Device, config: &SurfaceConfiguration, layouts: &[BindGroupLayout]) -> Result<(), String> {
+		let (scene, (post, present), bloom) = catch_validation(device, || {
+			let shaders = Shaders::new(device)?;
+			Ok::<_, String>((
+				ScenePipelines::new(device, config.format, layouts, &shaders, self.sample_count),
+				screen_pipelines(device, config.format, layouts, &shaders),
+				Bloom::new(device, config, &layouts[4]),
+			))
+		})??;
+		self.chunk_pipeline = scene.chunk;
+		self.chunk_wireframe_pipeline = scene.chunk_wireframe;
+		self.sky_pipeline = scene.sky;
+		self.debug_pipeline = scene.debug;
+		self.highlight_pipeline = scene.highlight;
+		self.avatar_pipeline = scene.avatar;
+		self.post_pipeline = post;
+		self.present_pipeline = present;
+		// Keeps the settings it had, the targets get made again at the current size
+		let (enabled, threshold, intensity) = (self.bloom.enabled, self.bloom.threshold, self.bloom.intensity);
+		self.bloom = bloom;
+		self.bloom.set(enabled, threshold, intensity);
+		Ok(())
+	}
+
+	/// Turns the bloom chain on or off and sets how bright a pixel has to be to glow and how strong the glow is
+	#[inline] pub fn set_bloom(&mut self, enabled: bool, threshold: f32, intensity: f32) {
+		self.bloom.set(enabled, threshold, intensity);
+	}
+	#[inline] pub const fn is_bloom(&self) -> bool { self.bloom.enabled }
+	/// Call after the surface changed size, the screen sized bloom textures get remade
+	#[inline] pub fn resize(&mut self, width: u32, height: u32) {
+		self.bloom.resize(width, height);
+	}
+
+	/// Switches the chunks to wireframe drawing, returns false (and stays solid) if the device can't do it
+	#[inline] pub fn set_wireframe(&mut self, wireframe: bool) -> bool {
+		if wireframe && self.chunk_wireframe_pipeline.is_none() { return false; }
+		self.wireframe = wireframe;
+		true
+	}
+	#[inline] pub const fn is_wireframe(&self) -> bool { self.wireframe }
+	/// The chunk pipeline to draw with, depends on the wireframe toggle
+	#[inline] pub fn chunk_pipeline(&self) -> &RenderPipeline {
+		match (&self.chunk_wireframe_pipeline, self.wireframe) {
+			(Some(wireframe), true) => wireframe,
+			_ => &self.chunk_pipeline,
+		}
+	}
+}
+
+/// The pipelines drawing into the (possibly multisampled) 3D attachments
+struct ScenePipelines {
+	chunk: RenderPipeline,
+	chunk_wireframe: Option<RenderPipeline>,
+	sky: RenderPipeline,
+	debug: RenderPipeline,
+	highlight: RenderPipeline,
+	avatar: RenderPipeline,
+}
+
+impl ScenePipelines {
+	fn new(device: &Device, format: TextureFormat, layouts: &[BindGroupLayout], shaders: &Shaders, sample_count: u32) -> Self {
 		let chunk_layout: PipelineLayout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
 			label: Some("Chunk Render Pipeline Layout"),
 			bind_group_layouts: &[&layouts[0],&layouts[1],&layouts[2]],
@@ -53,10 +170,13 @@ impl Pipeline {
 		});
 
 		Self {
-			chunk_pipeline: create_chunk_pipeline(device, &chunk_layout, &shaders.chunk, config.format),
-			post_pipeline: create_post_pipeline(device, &post_layout, &shaders.post, config.format),
-			sky_pipeline: create_sky_pipeline(device, &sky_layout, &shaders.sky, config.format),
-			debug_pipeline: create_debug_pipeline(device, &debug_layout, &shaders.debug, config.format),
+			chunk: create_chunk_pipeline(device, &chunk_layout, &shaders.chunk, format, sample_count),
+			chunk_wireframe: device.features().contains(Features::POLYGON_MODE_LINE)
+				.then(|| create_chunk_wireframe_pipeline(device, &chunk_layout, &shaders.chunk, format, sample_count)),
+			sky: create_sky_pipeline(device, &sky_layout, &shaders.sky, format, sample_count),
+			debug: create_debug_pipeline(device, &debug_layout, &shaders.debug, format, sample_count),
+			highlight: create_highlight_pipeline(device, &debug_layout, &shaders.highlight, format, sample_count, "Block Highlight Pipeline"),
+			avatar: create_highlight_pipeline(device, &debug_layout, &shaders.avatar, format, sample_count, "Avatar Pipeline"),
 		}
 	}
 }
@@ -67,26 +187,73 @@ struct Shaders {
 	pub post: ShaderModule,
 	pub sky: ShaderModule,
 	pub debug: ShaderModule,
+	pub highlight: ShaderModule,
+	pub avatar: ShaderModule,
+	pub present: ShaderModule,
 }
 
 impl Shaders {
+	/// Loads and compiles every shader, fails if the chunk shader can't be patched to the chunk size
 	#[inline]
-	fn new(device: &Device) -> Self {
+	fn new(device: &Device) -> Result<Self, String> {
 		// Load shader sources first
-		let chunk_shader = get_string!("chunk_shader.wgsl");
+		// The chunk size is a compile time option, so the shader constant is patched to match it
+		let chunk_shader = set_shader_const(&get_string!("chunk_shader.wgsl"), "CHUNK_SIZE_I", Chunk::SIZE as i32)
+			.ok_or("chunk_shader.wgsl has no `const CHUNK_SIZE_I: i32` to patch")?;
 		let fxaa_shader = get_string!("fxaa.wgsl");
 		let sky_shader = get_string!("sky_shader.wgsl");
 		let debug_shader = get_string!("debug_shader.wgsl");
+		let highlight_shader = get_string!("highlight_shader.wgsl");
+		let avatar_shader = get_string!("avatar_shader.wgsl");
+		let present_shader = get_string!("present_shader.wgsl");
 
-		Self {
+		Ok(Self {
 			chunk: create_shader(device, "Chunk Shader", &chunk_shader),
 			post: create_shader(device, "Post Processing Shader", &fxaa_shader),
 			sky: create_shader(device, "Sky Shader", &sky_shader),
 			debug: create_shader(device, "Debug Shader", &debug_shader),
-		}
+			highlight: create_shader(device, "Highlight Shader", &highlight_shader),
+			avatar: create_shader(device, "Avatar Shader", &avatar_shader),
+			present: create_shader(device, "Present Shader", &present_shader),
+		})
 	}
 }
 
+/// The post processing and present pipelines, the full screen passes after the 3D ones
+fn screen_pipelines(device: &Device, format: TextureFormat, layouts: &[BindGroupLayout], shaders: &Shaders) -> (RenderPipeline, RenderPipeline) {
+	let post_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+		label: Some("Post Processing Pipeline Layout"),
+		bind_group_layouts: &[&layouts[4]],
+		..Default::default()
+	});
+	(
+		create_post_pipeline(device, &post_layout, &shaders.post, format),
+		create_present_pipeline(device, &post_layout, &shaders.present, format),
+	)
+}
+
+/// Runs `build` with wgpu validation errors caught instead of crashing the app.
+/// A shader that doesn't compile (or anything else invalid) makes it an Err with the message, and what was built is thrown away
+pub fn catch_validation<T>(device: &Device, build: impl FnOnce() -> T) -> Result<T, String> {
+	use std::task::{Context, Poll, Waker};
+	device.push_error_scope(wgpu::ErrorFilter::Validation);
+	let built = build();
+	// Native wgpu has the answer right away, the future is ready on the first poll
+	let mut error = std::pin::pin!(device.pop_error_scope());
+	match error.as_mut().poll(&mut Context::from_waker(Waker::noop())) {
+		Poll::Ready(Some(error)) => Err(error.to_string()),
+		Poll::Ready(None) | Poll::Pending => Ok(built),
+	}
+}
+
+/// Sets the value of a `const NAME: i32 = ..;` line in a WGSL source, None if the source doesn't declare it
+pub fn set_shader_const(source: &str, name: &str, value: i32) -> Option<String> {
+	let decl = format!("const {name}: i32 = ");
+	let start = source.find(&decl)? + decl.len();
+	let end = start + source[start..].find(';')?;
+	Some(format!("{}{value}i{}", &source[..start], &source[end..]))
+}
+
 /// Creates a shader module with the given label and source
 #[inline]
 fn create_shader(device: &Device, label: &str, source: &str) -> ShaderModule {
@@ -107,6 +274,7 @@ fn create_base_pipeline(
 	depth_stencil: Option<DepthStencilState>,
 	primitive: PrimitiveState,
 	label: &str,
+	sample_count: u32,
 ) -> RenderPipeline {
 	device.create_render_pipeline(&RenderPipelineDescriptor {
 		label: Some(label),
@@ -129,7 +297,7 @@ fn create_base_pipeline(
 		}),
 		primitive,
 		depth_stencil,
-		multisample: MultisampleState::default(),
+		multisample: MultisampleState { count: sample_count, ..Default::default() },
 		multiview: None,
 		cache: None,
 	})
@@ -140,6 +308,7 @@ fn create_chunk_pipeline(
 	layout: &PipelineLayout,
 	shader: &ShaderModule,
 	format: TextureFormat,
+	sample_count: u32,
 ) -> RenderPipeline {
 	create_base_pipeline(
 		device,
@@ -150,6 +319,30 @@ fn create_chunk_pipeline(
 		Some(depth_stencil_state()),
 		default_primitive_state(),
 		"Chunk Render Pipeline",
+		sample_count,
+	)
+}
+#[inline]
+fn create_chunk_wireframe_pipeline(
+	device: &Device,
+	layout: &PipelineLayout,
+	shader: &ShaderModule,
+	format: TextureFormat,
+	sample_count: u32,
+) -> RenderPipeline {
+	create_base_pipeline(
+		device,
+		Some(layout),
+		shader,
+		format,
+		&[Vertex::desc(), InstanceRaw::desc()],
+		Some(depth_stencil_state()),
+		PrimitiveState {
+			polygon_mode: PolygonMode::Line, // needs Features::POLYGON_MODE_LINE
+			..default_primitive_state()
+		},
+		"Chunk Wireframe Pipeline",
+		sample_count,
 	)
 }
 /*
@@ -167,6 +360,7 @@ fn create_debug_pipeline(
 	layout: &PipelineLayout,
 	shader: &ShaderModule,
 	format: TextureFormat,
+	sample_count: u32,
 ) -> RenderPipeline {
 	create_base_pipeline(
 		device,
@@ -186,8 +380,76 @@ fn create_debug_pipeline(
 			..Default::default()
 		},
 		"Debug Render Pipeline",
+		sample_count,
 	)
 }
+/// Same line list setup as the debug lines, but the outline only tests depth so it never hides what is behind it
+#[inline]
+fn create_highlight_pipeline(
+	device: &Device,
+	layout: &PipelineLayout,
+	shader: &ShaderModule,
+	format: TextureFormat,
+	sample_count: u32,
+	label: &str,
+) -> RenderPipeline {
+	create_base_pipeline(
+		device,
+		Some(layout),
+		shader,
+		format,
+		&[],
+		Some(DepthStencilState {
+			depth_write_enabled: false,
+			depth_compare: CompareFunction::LessEqual,
+			..depth_stencil_state()
+		}),
+		PrimitiveState {
+			topology: PrimitiveTopology::LineList,
+			cull_mode: None,
+			..default_primitive_state()
+		},
+		label,
+		sample_count,
+	)
+}
+/// Copies the finished frame to the surface, no blending so the surface ends up with the exact texels
+#[inline]
+fn create_present_pipeline(
+	device: &Device,
+	layout: &PipelineLayout,
+	shader: &ShaderModule,
+	format: TextureFormat,
+) -> RenderPipeline {
+	device.create_render_pipeline(&RenderPipelineDescriptor {
+		label: Some("Present Pipeline"),
+		layout: Some(layout),
+		vertex: VertexState {
+			module: shader,
+			entry_point: Some("vs_main"),
+			compilation_options: Default::default(),
+			buffers: &[],
+		},
+		fragment: Some(FragmentState {
+			module: shader,
+			entry_point: Some("fs_main"),
+			compilation_options: Default::default(),
+			targets: &[Some(ColorTargetState {
+				format,
+				blend: None,
+				write_mask: ColorWrites::ALL,
+			})],
+		}),
+		primitive: PrimitiveState {
+			cull_mode: None,
+			..default_primitive_state()
+		},
+		depth_stencil: None,
+		multisample: MultisampleState::default(),
+		multiview: None,
+		cache: None,
+	})
+}
 #[inline]
 fn create_post_pipeline(
 	device: &Device,
@@ -204,6 +466,7 @@ fn create_post_pipeline(
 		None,
 		default_primitive_state(),
 		"Post Processing Pipeline",
+		1,
 	)
 }
 #[inline]
@@ -212,6 +475,7 @@ fn create_sky_pipeline(
 	layout: &PipelineLayout,
 	shader: &ShaderModule,
 	format: TextureFormat,
+	sample_count: u32,
 ) -> RenderPipeline {
 	create_base_pipeline(
 		device,
@@ -222,6 +486,7 @@ fn create_sky_pipeline(
 		None,
 		default_primitive_state(),
 		"Sky Render Pipeline",
+		sample_count,
 	)
 }
 
@@ -255,7 +520,11 @@ fn depth_stencil_state() -> DepthStencilState {
 #[inline]
 pub fn render_all(current_state: &mut State) -> Result<(), SurfaceError> {
 	let output = current_state.surface().get_current_texture()?;
-	let view = output.texture.create_view(&TextureViewDescriptor::default());
+	let surface_view = output.texture.create_view(&TextureViewDescriptor::default());
+	// Everything is drawn into the offscreen frame, which is copied to the surface at the end (and can be read back for screenshots)
+	let view = current_state.texture_manager().frame_view().clone();
+	// With MSAA the 3D passes draw into the multisampled texture and resolve into the frame
+	let msaa_view = current_state.texture_manager().msaa_view().cloned();
 	let mut encoder = current_state.device().create_command_encoder(&CommandEncoderDescriptor { label: Some("Render Encoder") });
 
 	// 3D pass
@@ -266,14 +535,7 @@ pub fn render_all(current_state: &mut State) -> Result<(), SurfaceError> {
 		{
 			let mut sky_pass = encoder.begin_render_pass(&RenderPassDescriptor {
 				label: Some("Sky Render Pass"),
-				color_attachments: &[Some(RenderPassColorAttachment {
-					view: &view,
-					resolve_target: None,
-					ops: Operations {
-						load: LoadOp::Load,
-						store: StoreOp::Store,
-					},
-				})],
+				color_attachments: &[Some(scene_attachment(&view, msaa_view.as_ref()))],
 				depth_stencil_attachment: None,
 				occlusion_query_set: None,
 				timestamp_writes: None,
@@ -289,14 +551,7 @@ pub fn render_all(current_state: &mut State) -> Result<(), SurfaceError> {
 		{
 			let mut rpass = encoder.begin_render_pass(&RenderPassDescriptor {
 				label: Some("3D Render Pass"),
-				color_attachments: &[Some(RenderPassColorAttachment {
-					view: &view,
-					resolve_target: None,
-					ops: Operations {
-						load: LoadOp::Load,
-						store: StoreOp::Store,
-					},
-				})],
+				color_attachments: &[Some(scene_attachment(&view, msaa_view.as_ref()))],
 				depth_stencil_attachment: Some(RenderPassDepthStencilAttachment {
 					view: &binding,
 					depth_ops: Some(Operations {
@@ -310,7 +565,7 @@ pub fn render_all(current_state: &mut State) -> Result<(), SurfaceError> {
 			});
 
 			// Render chunks
-			rpass.set_pipeline(&current_state.pipeline().chunk_pipeline);
+			rpass.set_pipeline(current_state.pipeline().chunk_pipeline());
 			rpass.set_bind_group(0, current_state.texture_manager().bind_group(), &[]);
 			let cam_sys = game_state.player().camera_system();
 			rpass.set_bind_group(1, cam_sys.bind_group(), &[]);
@@ -320,7 +575,28 @@ pub fn render_all(current_state: &mut State) -> Result<(), SurfaceError> {
 					label: Some("Vertex Buffer"), contents: bytemuck::cast_slice(&VERTICES), usage: BufferUsages::VERTEX });
 				rpass.set_vertex_buffer(0, vertex_buffer.slice(..));
 			}
-			ptr::get_gamestate().world().render_chunks_with_culling(&mut rpass, cam_sys, RENDER_DISTANCE);
+			ptr::get_gamestate().world().render_chunks_with_culling(&mut rpass, cam_sys, render_distance());
+		}
+		{
+			// The target is raycast again every frame so the outline follows the crosshair
+			let player = game_state.player();
+			match game_state.world().raycast(player.cam_pos(), player.camera().forward(), REACH) {
+				Some(hit) => render_block_highlight(current_state, &mut encoder, &view, msaa_view.as_ref(), &binding, &hit),
+				None => current_state.update_block_highlight(None),
+			}
+		}
+		{
+			let eye = game_state.player().cam_pos();
+			current_state.update_world_border(game_state.world().border(), eye);
+			if current_state.pipeline().border.is_visible() {
+				render_world_border(current_state, &mut encoder, &view, msaa_view.as_ref(), &binding);
+			}
+		}
+		{
+			current_state.update_avatars();
+			if current_state.pipeline().avatars.is_visible() {
+				render_avatars(current_state, &mut encoder, &view, msaa_view.as_ref(), &binding);
+			}
 		}
 		{
 			let game_state = ptr::get_gamestate();
@@ -329,14 +605,7 @@ pub fn render_all(current_state: &mut State) -> Result<(), SurfaceError> {
 			if game_state.debug().lines.len() > 0 {
 				let mut debug_pass = encoder.begin_render_pass(&RenderPassDescriptor {
 					label: Some("Debug Render Pass"),
-					color_attachments: &[Some(RenderPassColorAttachment {
-						view: &view,
-						resolve_target: None,
-						ops: Operations {
-							load: LoadOp::Load,
-							store: StoreOp::Store,
-						},
-					})],
+					color_attachments: &[Some(scene_attachment(&view, msaa_view.as_ref()))],
 					depth_stencil_attachment: Some(RenderPassDepthStencilAttachment {
 						view: &binding,
 						depth_ops: Some(Operations {
@@ -363,7 +632,8 @@ pub fn render_all(current_state: &mut State) -> Result<(), SurfaceError> {
 				view: &view,
 				resolve_target: None,
 				ops: Operations {
-					load: LoadOp::Load,
+					// The frame texture keeps the last frame, without a world nothing else would clear it
+					load: if current_state.is_world_running { LoadOp::Load } else { LoadOp::Clear(wgpu::Color::BLACK) },
 					store: StoreOp::Store,
 				},
 			})],
@@ -377,6 +647,12 @@ pub fn render_all(current_state: &mut State) -> Result<(), SurfaceError> {
 		post_pass.draw(0..3, 0..1);
 	}
 
+	// Bloom goes on the finished world, before the ui so text stays crisp
+	if current_state.is_world_running {
+		let State { pipeline, texture_manager, render_context, .. } = &mut *current_state;
+		pipeline.bloom.render(&render_context.device, &render_context.queue, &mut encoder, &view, texture_manager.frame_bind_group(), &render_context.layouts[4]);
+	}
+
 	// UI pass 
 	{
 		let mut ui_rpass = encoder.begin_render_pass(&RenderPassDescriptor {
@@ -397,10 +673,351 @@ pub fn render_all(current_state: &mut State) -> Result<(), SurfaceError> {
 		current_state.ui_manager.render(&mut ui_rpass);
 	}
 
+	// Present pass
+	{
+		let mut present_pass = encoder.begin_render_pass(&RenderPassDescriptor {
+			label: Some("Present Pass"),
+			color_attachments: &[Some(RenderPassColorAttachment {
+				view: &surface_view,
+				resolve_target: None,
+				ops: Operations {
+					load: LoadOp::Clear(wgpu::Color::BLACK),
+					store: StoreOp::Store,
+				},
+			})],
+			depth_stencil_attachment: None,
+			occlusion_query_set: None,
+			timestamp_writes: None,
+		});
+
+		present_pass.set_pipeline(&current_state.pipeline().present_pipeline);
+		present_pass.set_bind_group(0, current_state.texture_manager().frame_bind_group(), &[]);
+		present_pass.draw(0..3, 0..1);
+	}
+
 	// Submit commands
 	current_state.queue()
 		.submit(std::iter::once(encoder.finish()));
 	output.present();
+	// Buffers released during this frame can be rewritten from now on
+	if current_state.is_world_running {
+		ptr::get_gamestate().world_mut().mesh_pool.recycle();
+	}
 
 	Ok(())
 }
+
+/// Color attachment of the 3D passes: the multisampled texture resolving into the frame, or the frame itself without MSAA.
+/// Every pass resolves, so the frame is complete whichever pass ends up being the last one
+#[inline]
+fn scene_attachment<'a>(frame: &'a wgpu::TextureView, msaa: Option<&'a wgpu::TextureView>) -> RenderPassColorAttachment<'a> {
+	RenderPassColorAttachment {
+		view: msaa.unwrap_or(frame),
+		resolve_target: msaa.map(|_| frame),
+		ops: Operations {
+			load: LoadOp::Load,
+			store: StoreOp::Store,
+		},
+	}
+}
+
+/// Draws the outline around the hit block, depth tested against the chunks drawn before it
+#[inline]
+fn render_block_highlight(
+	current_state: &mut State,
+	encoder: &mut wgpu::CommandEncoder,
+	view: &wgpu::TextureView,
+	msaa_view: Option<&wgpu::TextureView>,
+	depth_view: &wgpu::TextureView,
+	hit: &RayHit,
+) {
+	current_state.update_block_highlight(Some(hit.world_pos));
+
+	let mut highlight_pass = encoder.begin_render_pass(&RenderPassDescriptor {
+		label: Some("Block Highlight Pass"),
+		color_attachments: &[Some(scene_attachment(view, msaa_view))],
+		depth_stencil_attachment: Some(RenderPassDepthStencilAttachment {
+			view: depth_view,
+			depth_ops: Some(Operations {
+				load: LoadOp::Load,
+				store: StoreOp::Store,
+			}),
+			stencil_ops: None,
+		}),
+		occlusion_query_set: None,
+		timestamp_writes: None,
+	});
+	highlight_pass.set_pipeline(&current_state.pipeline().highlight_pipeline);
+	highlight_pass.set_bind_group(1, ptr::get_gamestate().player().camera_system().bind_group(), &[]);
+	current_state.pipeline().highlight.render(&mut highlight_pass);
+}
+
+/// Draws the world border grid with the block highlight pipeline, depth tested so terrain in front hides it
+#[inline]
+fn render_world_border(
+	current_state: &State,
+	encoder: &mut wgpu::CommandEncoder,
+	view: &wgpu::TextureView,
+	msaa_view: Option<&wgpu::TextureView>,
+	depth_view: &wgpu::TextureView,
+) {
+	let mut border_pass = encoder.begin_render_pass(&RenderPassDescriptor {
+		label: Some("World Border Pass"),
+		color_attachments: &[Some(scene_attachment(view, msaa_view))],
+		depth_stencil_attachment: Some(RenderPassDepthStencilAttachment {
+			view: depth_view,
+			depth_ops: Some(Operations {
+				load: LoadOp::Load,
+				store: StoreOp::Store,
+			}),
+			stencil_ops: None,
+		}),
+		occlusion_query_set: None,
+		timestamp_writes: None,
+	});
+	border_pass.set_pipeline(&current_state.pipeline().highlight_pipeline);
+	border_pass.set_bind_group(1, ptr::get_gamestate().player().camera_system().bind_group(), &[]);
+	current_state.pipeline().border.render(&mut border_pass);
+}
+
+/// Draws the other players' boxes, depth tested like the border grid
+#[inline]
+fn render_avatars(
+	current_state: &State,
+	encoder: &mut wgpu::CommandEncoder,
+	view: &wgpu::TextureView,
+	msaa_view: Option<&wgpu::TextureView>,
+	depth_view: &wgpu::TextureView,
+) {
+	let mut avatar_pass = encoder.begin_render_pass(&RenderPassDescriptor {
+		label: Some("Avatar Pass"),
+		color_attachments: &[Some(scene_attachment(view, msaa_view))],
+		depth_stencil_attachment: Some(RenderPassDepthStencilAttachment {
+			view: depth_view,
+			depth_ops: Some(Operations {
+				load: LoadOp::Load,
+				store: StoreOp::Store,
+			}),
+			stencil_ops: None,
+		}),
+		occlusion_query_set: None,
+		timestamp_writes: None,
+	});
+	avatar_pass.set_pipeline(&current_state.pipeline().avatar_pipeline);
+	avatar_pass.set_bind_group(1, ptr::get_gamestate().player().camera_system().bind_group(), &[]);
+	current_state.pipeline().avatars.render(&mut avatar_pass);
+}
+
+// --- Bloom ---
+
+/// Format of the intermediate bloom textures, float so bright colors don't clip while blurring
+const BLOOM_FORMAT: TextureFormat = TextureFormat::Rgba16Float;
+
+/// One intermediate bloom texture and the bind group that samples it
+struct BloomTarget {
+	_texture: wgpu::Texture,
+	view: wgpu::TextureView,
+	bind_group: wgpu::BindGroup,
+}
+
+impl BloomTarget {
+	fn new(device: &Device, width: u32, height: u32, post_layout: &BindGroupLayout) -> Self {
+		let texture = device.create_texture(&wgpu::TextureDescriptor {
+			label: Some("Bloom Texture"),
+			size: wgpu::Extent3d { width: width.max(1), height: height.max(1), depth_or_array_layers: 1 },
+			mip_level_count: 1,
+			sample_count: 1,
+			dimension: wgpu::TextureDimension::D2,
+			format: BLOOM_FORMAT,
+			usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+			view_formats: &[],
+		});
+		let view = texture.create_view(&TextureViewDescriptor::default());
+		let bind_group = texture::create_post_processing_bind_group(device, &view, post_layout);
+		Self { _texture: texture, view, bind_group }
+	}
+}
+
+/// Bright pass -> blur at half size -> downsample and blur at quarter size -> both added back onto the frame
+struct Bloom {
+	enabled: bool,
+	threshold: f32,
+	intensity: f32,
+	params_dirty: bool, // threshold / intensity changed since the uniform was written
+	size: (u32, u32),
+	params: wgpu::Buffer,
+	params_bind_group: wgpu::BindGroup,
+	extract: RenderPipeline,
+	downsample: RenderPipeline,
+	blur_h: RenderPipeline,
+	blur_v: RenderPipeline,
+	composite: RenderPipeline,
+	targets: Option<[BloomTarget; 4]>, // half a, half b, quarter a, quarter b; only exist while bloom is on
+}
+
+impl Bloom {
+	fn new(device: &Device, config: &SurfaceConfiguration, post_layout: &BindGroupLayout) -> Self {
+		let shader = create_shader(device, "Bloom Shader", &get_string!("bloom_shader.wgsl"));
+		let params_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+			label: Some("Bloom Params Layout"),
+			entries: &[wgpu::BindGroupLayoutEntry {
+				binding: 0,
+				visibility: wgpu::ShaderStages::FRAGMENT,
+				ty: wgpu::BindingType::Buffer {
+					ty: wgpu::BufferBindingType::Uniform,
+					has_dynamic_offset: false,
+					min_binding_size: None,
+				},
+				count: None,
+			}],
+		});
+		let params = device.create_buffer_init(&BufferInitDescriptor {
+			label: Some("Bloom Params"),
+			contents: bytemuck::cast_slice(&[0f32; 4]),
+			usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+		});
+		let params_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+			label: Some("Bloom Params Bind Group"),
+			layout: &params_layout,
+			entries: &[wgpu::BindGroupEntry { binding: 0, resource: params.as_entire_binding() }],
+		});
+		let layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+			label: Some("Bloom Pipeline Layout"),
+			bind_group_layouts: &[post_layout, &params_layout],
+			..Default::default()
+		});
+
+		// Adds the glow on top of the frame and keeps its alpha
+		let additive = BlendState {
+			color: wgpu::BlendComponent { src_factor: wgpu::BlendFactor::One, dst_factor: wgpu::BlendFactor::One, operation: wgpu::BlendOperation::Add },
+			alpha: wgpu::BlendComponent { src_factor: wgpu::BlendFactor::Zero, dst_factor: wgpu::BlendFactor::One, operation: wgpu::BlendOperation::Add },
+		};
+		let render_config = &ptr::get_settings().render_config;
+		Self {
+			enabled: render_config.bloom,
+			threshold: render_config.bloom_threshold,
+			intensity: render_config.bloom_intensity,
+			params_dirty: true,
+			size: (config.width, config.height),
+			params,
+			params_bind_group,
+			extract: create_bloom_pipeline(device, &layout, &shader, "fs_extract", BLOOM_FORMAT, None),
+			downsample: create_bloom_pipeline(device, &layout, &shader, "fs_downsample", BLOOM_FORMAT, None),
+			blur_h: create_bloom_pipeline(device, &layout, &shader, "fs_blur_h", BLOOM_FORMAT, None),
+			blur_v: create_bloom_pipeline(device, &layout, &shader, "fs_blur_v", BLOOM_FORMAT, None),
+			composite: create_bloom_pipeline(device, &layout, &shader, "fs_composite", config.format, Some(additive)),
+			targets: None,
+		}
+	}
+
+	fn set(&mut self, enabled: bool, threshold: f32, intensity: f32) {
+		self.enabled = enabled;
+		self.threshold = threshold.max(0.);
+		self.intensity = intensity.max(0.);
+		self.params_dirty = true;
+		if !enabled { self.targets = None; } // don't hold on to the textures while nobody uses them
+	}
+
+	/// The targets are made again (at the new size) the next time bloom renders
+	fn resize(&mut self, width: u32, height: u32) {
+		self.size = (width, height);
+		self.targets = None;
+	}
+
+	fn render(
+		&mut self,
+		device: &Device,
+		queue: &wgpu::Queue,
+		encoder: &mut wgpu::CommandEncoder,
+		frame_view: &wgpu::TextureView,
+		frame_bind_group: &wgpu::BindGroup,
+		post_layout: &BindGroupLayout,
+	) {
+		if !self.enabled { return; }
+		if self.params_dirty {
+			queue.write_buffer(&self.params, 0, bytemuck::cast_slice(&[self.threshold, self.intensity, 0., 0.]));
+			self.params_dirty = false;
+		}
+		if self.targets.is_none() {
+			let (half, quarter) = ((self.size.0 / 2, self.size.1 / 2), (self.size.0 / 4, self.size.1 / 4));
+			self.targets = Some([
+				BloomTarget::new(device, half.0, half.1, post_layout),
+				BloomTarget::new(device, half.0, half.1, post_layout),
+				BloomTarget::new(device, quarter.0, quarter.1, post_layout),
+				BloomTarget::new(device, quarter.0, quarter.1, post_layout),
+			]);
+		}
+		let Some([half_a, half_b, quarter_a, quarter_b]) = &self.targets else { return; };
+
+		self.pass(encoder, &self.extract, frame_bind_group, &half_a.view, true);
+		self.pass(encoder, &self.blur_h, &half_a.bind_group, &half_b.view, true);
+		self.pass(encoder, &self.blur_v, &half_b.bind_group, &half_a.view, true);
+		self.pass(encoder, &self.downsample, &half_a.bind_group, &quarter_a.view, true);
+		self.pass(encoder, &self.blur_h, &quarter_a.bind_group, &quarter_b.view, true);
+		self.pass(encoder, &self.blur_v, &quarter_b.bind_group, &quarter_a.view, true);
+		// Tight glow from the half size blur, wide glow from the quarter size one
+		self.pass(encoder, &self.composite, &half_a.bind_group, frame_view, false);
+		self.pass(encoder, &self.composite, &quarter_a.bind_group, frame_view, false);
+	}
+
+	/// One full screen draw from `source` into `target`, `clear` for the intermediate textures (the frame is kept)
+	fn pass(&self, encoder: &mut wgpu::CommandEncoder, pipeline: &RenderPipeline, source: &wgpu::BindGroup, target: &wgpu::TextureView, clear: bool) {
+		let mut pass = encoder.begin_render_pass(&RenderPassDescriptor {
+			label: Some("Bloom Pass"),
+			color_attachments: &[Some(RenderPassColorAttachment {
+				view: target,
+				resolve_target: None,
+				ops: Operations {
+					load: if clear { LoadOp::Clear(wgpu::Color::BLACK) } else { LoadOp::Load },
+					store: StoreOp::Store,
+				},
+			})],
+			depth_stencil_attachment: None,
+			occlusion_query_set: None,
+			timestamp_writes: None,
+		});
+		pass.set_pipeline(pipeline);
+		pass.set_bind_group(0, source, &[]);
+		pass.set_bind_group(1, &self.params_bind_group, &[]);
+		pass.draw(0..3, 0..1);
+	}
+}
+
+#[inline]
+fn create_bloom_pipeline(
+	device: &Device,
+	layout: &PipelineLayout,
+	shader: &ShaderModule,
+	entry_point: &str,
+	format: TextureFormat,
+	blend: Option<BlendState>,
+) -> RenderPipeline {
+	device.create_render_pipeline(&RenderPipelineDescriptor {
+		label: Some("Bloom Pipeline"),
+		layout: Some(layout),
+		vertex: VertexState {
+			module: shader,
+			entry_point: Some("vs_main"),
+			compilation_options: Default::default(),
+			buffers: &[],
+		},
+		fragment: Some(FragmentState {
+			module: shader,
+			entry_point: Some(entry_point),
+			compilation_options: Default::default(),
+			targets: &[Some(ColorTargetState {
+				format,
+				blend,
+				write_mask: ColorWrites::ALL,
+			})],
+		}),
+		primitive: PrimitiveState {
+			cull_mode: None,
+			..default_primitive_state()
+		},
+		depth_stencil: None,
+		multisample: MultisampleState::default(),
+		multiview: None,
+		cache: None,
+	})
+}
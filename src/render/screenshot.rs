@@ -0,0 +1,89 @@
+
+use std::io::{Error, ErrorKind, Result};
+use std::path::Path;
+
+/// Bytes per row of a texture to buffer copy, wgpu wants every row to start on a 256 byte boundary
+#[inline] pub const fn padded_bytes_per_row(width: u32) -> u32 {
+	let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+	(width * 4).div_ceil(align) * align
+}
+
+/// Channel order of the formats screenshots can read, true for BGRA, None for anything that is not 8 bit per channel
+#[inline] pub const fn is_bgra(format: wgpu::TextureFormat) -> Option<bool> {
+	match format {
+		wgpu::TextureFormat::Rgba8Unorm | wgpu::TextureFormat::Rgba8UnormSrgb => Some(false),
+		wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb => Some(true),
+		_ => None,
+	}
+}
+
+/// Drops the row padding of a copied texture and returns tightly packed RGBA texels
+pub fn unpad_rows(data: &[u8], width: u32, height: u32, padded_row: u32, bgra: bool) -> Vec<u8> {
+	let row = (width * 4) as usize;
+	let mut pixels = Vec::with_capacity(row * height as usize);
+	for y in 0..height as usize {
+		let start = y * padded_row as usize;
+		pixels.extend_from_slice(&data[start..start + row]);
+	}
+	if bgra {
+		pixels.chunks_exact_mut(4).for_each(|texel| texel.swap(0, 2));
+	}
+	pixels
+}
+
+/// Reads the texture back from the gpu and saves it as a png.
+/// The bytes are written as they are stored, for sRGB formats that is already the encoded color the screen shows
+pub fn save_texture(device: &wgpu::Device, queue: &wgpu::Queue, texture: &wgpu::Texture, path: &Path) -> Result<()> {
+	let (width, height) = (texture.width(), texture.height());
+	let bgra = is_bgra(texture.format())
+		.ok_or_else(|| Error::new(ErrorKind::Unsupported, format!("Can't take a screenshot of a {:?} texture", texture.format())))?;
+	let padded_row = padded_bytes_per_row(width);
+
+	let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+		label: Some("Screenshot Buffer"),
+		size: padded_row as wgpu::BufferAddress * height as wgpu::BufferAddress,
+		usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+		mapped_at_creation: false,
+	});
+
+	let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("Screenshot Encoder") });
+	encoder.copy_texture_to_buffer(
+		wgpu::TexelCopyTextureInfo {
+			texture,
+			mip_level: 0,
+			origin: wgpu::Origin3d::ZERO,
+			aspect: wgpu::TextureAspect::All,
+		},
+		wgpu::TexelCopyBufferInfo {
+			buffer: &buffer,
+			layout: wgpu::TexelCopyBufferLayout {
+				offset: 0,
+				bytes_per_row: Some(padded_row),
+				rows_per_image: Some(height),
+			},
+		},
+		texture.size(),
+	);
+	queue.submit(std::iter::once(encoder.finish()));
+
+	// Block until the copy is done and the buffer is mapped
+	let slice = buffer.slice(..);
+	let (tx, rx) = std::sync::mpsc::channel();
+	slice.map_async(wgpu::MapMode::Read, move |result| { let _ = tx.send(result); });
+	device.poll(wgpu::Maintain::Wait);
+	rx.recv()
+		.map_err(|e| Error::new(ErrorKind::Other, e))?
+		.map_err(|e| Error::new(ErrorKind::Other, e))?;
+
+	let pixels = {
+		let data = slice.get_mapped_range();
+		unpad_rows(&data, width, height, padded_row, bgra)
+	};
+	buffer.unmap();
+
+	if let Some(dir) = path.parent() {
+		std::fs::create_dir_all(dir)?;
+	}
+	image::save_buffer(path, &pixels, width, height, image::ExtendedColorType::Rgba8)
+		.map_err(|e| Error::new(ErrorKind::Other, e))
+}
@@ -2,24 +2,40 @@
 
 use crate::fs::rs;
 use crate::State;
+use crate::ext::ptr;
+use glam::Vec3;
+use wgpu::util::DeviceExt;
 
-/// Struct to hold skybox resources
+/// Day sky texture loaded at startup
+pub const DAY_SKYBOX: &str = "basic_skybox.jpg";
+/// Night sky texture loaded at startup
+pub const NIGHT_SKYBOX: &str = "skybox_night.jpg";
+
+/// Struct to hold skybox resources, a day and a night sky blended by the time of day
 pub struct Skybox {
-	pub texture: wgpu::Texture,
+	pub day_texture: wgpu::Texture,
+	pub night_texture: wgpu::Texture,
 	pub bind_group: wgpu::BindGroup,
+	blend_buffer: wgpu::Buffer,
+	blend: f32, // 0 is full day, 1 full night
+	day_horizon: Vec3, // average color around the horizon of each sky, the fog fades into it
+	night_horizon: Vec3,
 }
 
 impl Skybox {
-	/// Creates a new skybox from a texture path
+	/// Creates a new skybox from the day and night texture paths
 	pub fn new(
 		device: &wgpu::Device,
 		queue: &wgpu::Queue,
 		layout: &wgpu::BindGroupLayout,
-		skybox_path: &str,
+		day_path: &str,
+		night_path: &str,
 	) -> Option<Self> {
-		let texture = create_skybox_texture(device, queue, skybox_path)?;
+		let (day_texture, day_horizon) = create_skybox_texture(device, queue, day_path)?;
+		let (night_texture, night_horizon) = create_skybox_texture(device, queue, night_path)?;
 		
-		let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+		let day_view = day_texture.create_view(&wgpu::TextureViewDescriptor::default());
+		let night_view = night_texture.create_view(&wgpu::TextureViewDescriptor::default());
 		let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
 			address_mode_u: wgpu::AddressMode::ClampToEdge,
 			address_mode_v: wgpu::AddressMode::ClampToEdge,
@@ -29,6 +45,12 @@ impl Skybox {
 			mipmap_filter: wgpu::FilterMode::Linear,
 			..Default::default()
 		});
+		// Padded to 16 bytes for the uniform
+		let blend_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+			label: Some("skybox_blend_buffer"),
+			contents: bytemuck::cast_slice(&[0f32; 4]),
+			usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+		});
 
 		let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
 			layout,
@@ -39,33 +61,72 @@ impl Skybox {
 				},
 				wgpu::BindGroupEntry {
 					binding: 1,
-					resource: wgpu::BindingResource::TextureView(&view),
+					resource: wgpu::BindingResource::TextureView(&day_view),
+				},
+				wgpu::BindGroupEntry {
+					binding: 2,
+					resource: wgpu::BindingResource::TextureView(&night_view),
+				},
+				wgpu::BindGroupEntry {
+					binding: 3,
+					resource: blend_buffer.as_entire_binding(),
 				},
 			],
 			label: Some("skybox_bind_group"),
 		});
 
-		Some(Self { texture, bind_group })
+		Some(Self { day_texture, night_texture, bind_group, blend_buffer, blend: 0., day_horizon, night_horizon })
+	}
+
+	/// Moves the sky between the day (0) and night (1) texture, the uniform is only written when it changed
+	pub fn set_blend(&mut self, queue: &wgpu::Queue, blend: f32) {
+		let blend = blend.clamp(0., 1.);
+		if blend == self.blend { return; }
+		self.blend = blend;
+		queue.write_buffer(&self.blend_buffer, 0, bytemuck::bytes_of(&blend));
+	}
+	#[inline] pub const fn blend(&self) -> f32 {
+		self.blend
+	}
+	/// Horizon color of the sky as it is blended right now
+	#[inline] pub fn horizon_color(&self) -> Vec3 {
+		self.day_horizon.lerp(self.night_horizon, self.blend)
 	}
 }
 
 
 // Add a method to State to set skybox:
 impl State<'_> {
-	pub fn set_skybox(&mut self, skybox_path: &str) -> Result<(), String> {
+	pub fn set_skybox(&mut self, day_path: &str, night_path: &str) -> Result<(), String> {
+		let blend = self.render_context.skybox.blend();
 		self.render_context.skybox = Skybox::new(
 			self.device(),
 			self.queue(),
 			&self.render_context.layouts[3],
-			skybox_path,
+			day_path,
+			night_path,
 		).ok_or("Failed to create skybox")?;
+		self.render_context.skybox.set_blend(&self.render_context.queue, blend);
 		
 		Ok(())
 	}
+
+	/// Advances the day and pushes it to the sky blend, the fog color and the world brightness
+	pub fn update_time_of_day(&mut self, delta_time: f32) {
+		let game_state = ptr::get_gamestate();
+		game_state.time_of_day_mut().advance(delta_time);
+		let time = *game_state.time_of_day();
+
+		let skybox = &mut self.render_context.skybox;
+		skybox.set_blend(&self.render_context.queue, 1. - time.daylight());
+		let camera = game_state.player_mut().camera_system_mut();
+		camera.fog_mut().color = skybox.horizon_color();
+		camera.set_ambient(time.ambient());
+	}
 }
 
 
-fn create_skybox_texture(device: &wgpu::Device, queue: &wgpu::Queue, skybox: &str) -> Option<wgpu::Texture> {
+fn create_skybox_texture(device: &wgpu::Device, queue: &wgpu::Queue, skybox: &str) -> Option<(wgpu::Texture, Vec3)> {
 	// Load the image
 	let (rgba, width, height) = rs::load_image_from_path(skybox)?;
 	
@@ -106,5 +167,16 @@ fn create_skybox_texture(device: &wgpu::Device, queue: &wgpu::Queue, skybox: &st
 		},
 	);
 
-	Some(texture)
+	Some((texture, horizon_color(&rgba, width, height)))
+}
+
+/// Average color of the middle row of the sideways T cubemap, which runs along the horizon of all four side faces.
+/// The texture is not sRGB so this is in the same space the sky shader outputs
+pub fn horizon_color(rgba: &[u8], width: u32, height: u32) -> Vec3 {
+	if width == 0 || height == 0 { return Vec3::ZERO; }
+	let row_start = (height / 2 * width * 4) as usize;
+	let row = &rgba[row_start..row_start + (width * 4) as usize];
+	let sum = row.chunks_exact(4)
+		.fold(Vec3::ZERO, |sum, texel| sum + Vec3::new(texel[0] as f32, texel[1] as f32, texel[2] as f32));
+	sum / (width as f32 * 255.)
 }
\ No newline at end of file
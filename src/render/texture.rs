@@ -1,15 +1,28 @@
 
 use crate::fs::rs;
-use std::sync::OnceLock;
+use crate::ext::ptr;
+use crate::block::main::Material;
+use std::collections::HashMap;
+use std::sync::{OnceLock, RwLock};
+use wgpu::util::DeviceExt;
+/// Highest anisotropy a sampler accepts
+pub const MAX_ANISOTROPY: u8 = 16;
 /// Standard format for depth textures
 pub const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
 pub static TEXTURE_MAP: OnceLock<Vec<String>> = OnceLock::new();
+/// Layers of the block texture array, the built in textures and the blocks added by mods share them
+pub const MAX_BLOCK_LAYERS: usize = 256;
+/// Blocks added by mods this session
+pub static MOD_MATERIALS: RwLock<ModMaterials> = RwLock::new(ModMaterials::new());
+/// Textures named `<name>_frame<N>` are the animation frames of the `<name>` texture (played after it, in order of N)
+pub const ANIMATION_FRAME_SUFFIX: &str = "_frame";
 
-/// Creates a depth texture for rendering
+/// Creates a depth texture for rendering, `sample_count` has to match the color attachment it's used with
 pub fn create_depth_texture(
 	device: &wgpu::Device,
 	config: &wgpu::SurfaceConfiguration,
 	label: &str,
+	sample_count: u32,
 ) -> wgpu::Texture {
 	let size = wgpu::Extent3d {
 		width: config.width.max(1),
@@ -21,7 +34,7 @@ pub fn create_depth_texture(
 		label: Some(label),
 		size,
 		mip_level_count: 1,
-		sample_count: 1,
+		sample_count,
 		dimension: wgpu::TextureDimension::D2,
 		format: DEPTH_FORMAT,
 		usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
@@ -49,6 +62,53 @@ pub fn get_texture_map() -> &'static [String] {
 	TEXTURE_MAP.get().expect("Not initialized").as_slice()
 }
 
+/// Names of the blocks mods registered, their materials (and texture layers) come right after the built in textures.
+/// Nothing is ever removed, so a material stays the same for the whole session
+#[derive(Debug, Clone, Default)]
+pub struct ModMaterials {
+	names: Vec<String>,
+}
+
+impl ModMaterials {
+	#[inline] pub const fn new() -> Self {
+		Self { names: Vec::new() }
+	}
+	#[inline] pub fn len(&self) -> usize { self.names.len() }
+	#[inline] pub fn is_empty(&self) -> bool { self.names.is_empty() }
+
+	/// `base` is the number of built in materials
+	#[inline] pub fn id_of(&self, base: usize, name: &str) -> Option<u16> {
+		self.names.iter().position(|n| n == name).map(|i| (base + i) as u16)
+	}
+	#[inline] pub fn name_of(&self, base: usize, id: u16) -> Option<&str> {
+		self.names.get((id as usize).checked_sub(base)?).map(String::as_str)
+	}
+	/// Material of the name, a new one if it wasn't registered yet. None once every layer is taken
+	pub fn add(&mut self, base: usize, name: &str) -> Option<u16> {
+		if let Some(id) = self.id_of(base, name) { return Some(id); }
+		if base + self.names.len() >= MAX_BLOCK_LAYERS { return None; }
+		self.names.push(name.to_string());
+		Some((base + self.names.len() - 1) as u16)
+	}
+}
+
+/// Built in and mod materials together
+#[inline] pub fn material_count() -> usize {
+	get_texture_map().len() + MOD_MATERIALS.read().map_or(0, |m| m.len())
+}
+/// Name of a built in or mod material
+pub fn material_name(id: u16) -> Option<String> {
+	let map = get_texture_map();
+	if let Some(name) = map.get(id as usize) { return Some(name.clone()); }
+	MOD_MATERIALS.read().ok()?.name_of(map.len(), id).map(str::to_string)
+}
+/// Material of a built in or mod texture name
+pub fn material_by_name(name: &str) -> Option<u16> {
+	let map = get_texture_map();
+	if let Some(idx) = map.iter().position(|n| n == name) { return Some(idx as u16); }
+	MOD_MATERIALS.read().ok()?.id_of(map.len(), name)
+}
+
 // --- Texture Manager ---
 
 /// Manages all texture resources for rendering
@@ -58,6 +118,16 @@ pub struct TextureManager {
 	render_texture: wgpu::Texture,
 	render_texture_view: wgpu::TextureView,
 	post_bind_group: wgpu::BindGroup,
+	array_texture: wgpu::Texture, // has room for `MAX_BLOCK_LAYERS`, mod blocks are written into the free layers
+	array_texture_view: wgpu::TextureView, // kept so the sampler can be swapped without reloading the textures
+	mipmaps: bool,
+	layer_map: wgpu::Buffer, // texture layer of each material, read by the chunk shader
+	animations: BlockAnimations,
+	frame_texture: wgpu::Texture, // everything is drawn here first, then presented, so the frame can be copied out
+	frame_view: wgpu::TextureView,
+	frame_bind_group: wgpu::BindGroup,
+	sample_count: u32, // MSAA of the 3D passes, 1 is off
+	msaa_texture: Option<(wgpu::Texture, wgpu::TextureView)>, // multisampled color target, resolved into the frame
 }
 
 impl TextureManager {
@@ -68,26 +138,26 @@ impl TextureManager {
 		config: &wgpu::SurfaceConfiguration,
 		layout: &wgpu::BindGroupLayout,
 		post_layout: &wgpu::BindGroupLayout,
+		sample_count: u32,
 	) -> Self {
-		let depth_texture = create_depth_texture(device, config, "Depth Texture");
+		let depth_texture = create_depth_texture(device, config, "Depth Texture", sample_count);
+		let msaa_texture = create_msaa_texture(device, config, sample_count);
 
 		let (render_texture, render_texture_view) = create_render_texture(device, config);
+		let (frame_texture, frame_view) = create_frame_texture(device, config);
+		let frame_bind_group = create_post_processing_bind_group(device, &frame_view, post_layout);
 		let post_bind_group = create_post_processing_bind_group(device, &render_texture_view, post_layout);
 
 		// Create resources
 		let paths = rs::find_png_resources("block");
 		initialize_texture_map(paths.clone());
-		let (_array_texture, array_texture_view) = create_texture_array(&device, &queue, &paths).unwrap();
-		let array_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
-			address_mode_u: wgpu::AddressMode::ClampToEdge,
-			address_mode_v: wgpu::AddressMode::ClampToEdge,
-			address_mode_w: wgpu::AddressMode::ClampToEdge,
-			mag_filter: wgpu::FilterMode::Linear,
-			min_filter: wgpu::FilterMode::Nearest,
-			mipmap_filter: wgpu::FilterMode::Nearest,
-			..Default::default()
-		});
-		let bind_group = create_texture_array_bind_group(&device, layout, &array_texture_view, &array_sampler);
+		let render_config = &ptr::get_settings().render_config;
+		let mipmaps = render_config.mipmaps;
+		let (array_texture, array_texture_view) = create_texture_array(&device, &queue, &paths, mipmaps).unwrap();
+		let array_sampler = create_block_sampler(device, mipmaps, render_config.anisotropy);
+		let layer_map = create_layer_map(device, array_texture.depth_or_array_layers() as usize);
+		let bind_group = create_texture_array_bind_group(&device, layout, &array_texture_view, &array_sampler, &layer_map);
+		let animations = BlockAnimations::new(find_material_animations(get_texture_map()), render_config.animation_fps);
 
 		Self {
 			depth_texture,
@@ -95,7 +165,78 @@ impl TextureManager {
 			render_texture,
 			render_texture_view,
 			post_bind_group,
+			array_texture,
+			array_texture_view,
+			mipmaps,
+			layer_map,
+			animations,
+			frame_texture,
+			frame_view,
+			frame_bind_group,
+			sample_count,
+			msaa_texture,
+		}
+	}
+
+	/// Recreates the screen sized textures after the surface changed size
+	pub fn resize(&mut self, device: &wgpu::Device, config: &wgpu::SurfaceConfiguration, post_layout: &wgpu::BindGroupLayout) {
+		self.depth_texture = create_depth_texture(device, config, "Depth Texture", self.sample_count);
+		self.msaa_texture = create_msaa_texture(device, config, self.sample_count);
+		(self.frame_texture, self.frame_view) = create_frame_texture(device, config);
+		self.frame_bind_group = create_post_processing_bind_group(device, &self.frame_view, post_layout);
+	}
+
+	/// Remakes the depth and multisampled color textures for a new MSAA sample count
+	pub fn set_sample_count(&mut self, device: &wgpu::Device, config: &wgpu::SurfaceConfiguration, sample_count: u32) {
+		if sample_count == self.sample_count { return; }
+		self.sample_count = sample_count;
+		self.depth_texture = create_depth_texture(device, config, "Depth Texture", sample_count);
+		self.msaa_texture = create_msaa_texture(device, config, sample_count);
+	}
+	#[inline] pub const fn sample_count(&self) -> u32 {
+		self.sample_count
+	}
+	/// The multisampled color target, None without MSAA
+	#[inline] pub fn msaa_view(&self) -> Option<&wgpu::TextureView> {
+		self.msaa_texture.as_ref().map(|(_, view)| view)
+	}
+
+	/// Recreates only the block sampler (and its bind group) with the new anisotropy, the texture array is reused
+	pub fn set_anisotropy(&mut self, device: &wgpu::Device, layout: &wgpu::BindGroupLayout, anisotropy: u8) {
+		let sampler = create_block_sampler(device, self.mipmaps, anisotropy);
+		self.bind_group = create_texture_array_bind_group(device, layout, &self.array_texture_view, &sampler, &self.layer_map);
+	}
+
+	/// Size of one block texture, every layer has the same
+	#[inline] pub fn block_texture_size(&self) -> (u32, u32) {
+		(self.array_texture.width(), self.array_texture.height())
+	}
+	/// Fills a free layer of the block texture array (for a mod block), `rgba` has to be `block_texture_size` big
+	pub fn write_block_layer(&self, queue: &wgpu::Queue, layer: u32, rgba: Vec<u8>) -> Result<(), String> {
+		let (width, height) = self.block_texture_size();
+		if layer >= self.array_texture.depth_or_array_layers() {
+			return Err(format!("layer {} is past the {} layers of the block textures", layer, self.array_texture.depth_or_array_layers()));
+		}
+		if rgba.len() != (width * height * 4) as usize {
+			return Err(format!("block textures have to be {}x{}", width, height));
 		}
+		write_layer(queue, &self.array_texture, layer, rgba);
+		Ok(())
+	}
+
+	/// Steps the block texture animations, only the materials that switched frame get written to the gpu
+	#[inline]
+	pub fn update_animations(&mut self, queue: &wgpu::Queue, delta_time: f32) {
+		for (material, layer) in self.animations.advance(delta_time) {
+			let offset = material.inner() as wgpu::BufferAddress * std::mem::size_of::<u32>() as wgpu::BufferAddress;
+			queue.write_buffer(&self.layer_map, offset, bytemuck::bytes_of(&layer));
+		}
+	}
+	#[inline] pub const fn animations(&self) -> &BlockAnimations {
+		&self.animations
+	}
+	#[inline] pub const fn animations_mut(&mut self) -> &mut BlockAnimations {
+		&mut self.animations
 	}
 
 	#[inline] pub const fn depth_texture(&self) -> &wgpu::Texture {
@@ -116,15 +257,109 @@ impl TextureManager {
 	#[inline] pub const fn post_bind_group(&self) -> &wgpu::BindGroup {
 		&self.post_bind_group
 	}
+	#[inline] pub const fn frame_texture(&self) -> &wgpu::Texture {
+		&self.frame_texture
+	}
+	#[inline] pub const fn frame_view(&self) -> &wgpu::TextureView {
+		&self.frame_view
+	}
+	#[inline] pub const fn frame_bind_group(&self) -> &wgpu::BindGroup {
+		&self.frame_bind_group
+	}
+}
+
+/// Animated block textures: the layers each material cycles through and how fast
+pub struct BlockAnimations {
+	pub material_animation: HashMap<Material, Vec<u32>>, // frame layers, the material's own layer first
+	frame_rates: HashMap<Material, f32>, // frames per second, materials not in here use `default_frame_rate`
+	pub default_frame_rate: f32,
+	timer: f32,
+	shown: HashMap<Material, u32>, // layer the gpu table currently holds for each animated material
+}
+
+impl BlockAnimations {
+	pub fn new(material_animation: HashMap<Material, Vec<u32>>, default_frame_rate: f32) -> Self {
+		Self {
+			material_animation,
+			frame_rates: HashMap::new(),
+			default_frame_rate,
+			timer: 0.,
+			shown: HashMap::new(),
+		}
+	}
+	#[inline] pub fn set_frame_rate(&mut self, material: Material, frame_rate: f32) {
+		self.frame_rates.insert(material, frame_rate);
+	}
+	#[inline] pub fn frame_rate(&self, material: Material) -> f32 {
+		self.frame_rates.get(&material).copied().unwrap_or(self.default_frame_rate)
+	}
+
+	/// Moves the timer forward, returns the materials whose frame changed together with their new layer
+	pub fn advance(&mut self, delta_time: f32) -> Vec<(Material, u32)> {
+		if self.material_animation.is_empty() { return Vec::new(); }
+		self.timer += delta_time;
+
+		let mut changed = Vec::new();
+		for (material, frames) in &self.material_animation {
+			let frame_rate = self.frame_rates.get(material).copied().unwrap_or(self.default_frame_rate);
+			let Some(&layer) = frames.get(current_frame(frames.len(), frame_rate, self.timer)) else { continue; };
+			if self.shown.insert(*material, layer) != Some(layer) {
+				changed.push((*material, layer));
+			}
+		}
+		changed
+	}
+}
+
+/// Frame index of a looping animation after `time` seconds
+#[inline] pub fn current_frame(frame_count: usize, frame_rate: f32, time: f32) -> usize {
+	if frame_count == 0 || frame_rate <= 0. { return 0; }
+	(time * frame_rate) as usize % frame_count
+}
+
+/// Groups the `_frame<N>` textures under the material of their base texture
+pub fn find_material_animations(names: &[String]) -> HashMap<Material, Vec<u32>> {
+	let mut frames: HashMap<Material, Vec<(u32, u32)>> = HashMap::new();
+	for (layer, name) in names.iter().enumerate() {
+		let Some((base, number)) = name.rsplit_once(ANIMATION_FRAME_SUFFIX) else { continue; };
+		let Ok(number) = number.parse::<u32>() else { continue; };
+		let Some(base_layer) = names.iter().position(|n| n == base) else {
+			println!("Animation frame {} has no base texture {}", name, base);
+			continue;
+		};
+		frames.entry(Material(base_layer as u16)).or_default().push((number, layer as u32));
+	}
+
+	frames.into_iter()
+		.map(|(material, mut frames)| {
+			frames.sort_unstable();
+			let layers = std::iter::once(material.inner() as u32)
+				.chain(frames.into_iter().map(|(_, layer)| layer))
+				.collect();
+			(material, layers)
+		})
+		.collect()
+}
+
+/// Identity material -> layer table, the animations overwrite their own entries
+fn create_layer_map(device: &wgpu::Device, layer_count: usize) -> wgpu::Buffer {
+	let layers: Vec<u32> = (0..layer_count.max(1) as u32).collect();
+	device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+		label: Some("material_layer_map"),
+		contents: bytemuck::cast_slice(&layers),
+		usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+	})
 }
 
 /// Creates a texture array from a list of image paths.
 /// Only images with matching dimensions will be included in the array.
+/// With `mipmaps` every layer gets its full mip chain (downsampled on the cpu).
 /// Returns the texture, its view, and the actual number of layers loaded.
 fn create_texture_array(
 	device: &wgpu::Device,
 	queue: &wgpu::Queue,
 	image_paths: &[String],
+	mipmaps: bool,
 ) -> Option<(wgpu::Texture, wgpu::TextureView)> {
 	if image_paths.is_empty() {
 		println!("No image paths provided");
@@ -164,15 +399,22 @@ fn create_texture_array(
 		println!("No valid images found");
 		return None;
 	}
+	// Room is left for the blocks of mods, they are written in later
+	let max_layers = (device.limits().max_texture_array_layers as usize).min(MAX_BLOCK_LAYERS);
+	if valid_count > max_layers {
+		println!("{} block textures but only {} layers fit, the rest are dropped", valid_count, max_layers);
+		valid_images.truncate(max_layers);
+	}
+	let mip_levels = if mipmaps { mip_level_count(base_width, base_height) } else { 1 };
 
 	let texture = device.create_texture(&wgpu::TextureDescriptor {
 		label: Some("texture_array"),
 		size: wgpu::Extent3d {
 			width: base_width,
 			height: base_height,
-			depth_or_array_layers: valid_count as u32,
+			depth_or_array_layers: max_layers as u32,
 		},
-		mip_level_count: 1,
+		mip_level_count: mip_levels,
 		sample_count: 1,
 		dimension: wgpu::TextureDimension::D2,
 		format: wgpu::TextureFormat::Rgba8UnormSrgb,
@@ -180,37 +422,120 @@ fn create_texture_array(
 		view_formats: &[],
 	});
 
-	// Fill each layer with its corresponding image
+	// Fill each layer (and its mips) with its corresponding image
 	for (layer, image_data) in valid_images.into_iter().enumerate() {
+		write_layer(queue, &texture, layer as u32, image_data);
+	}
+
+	let view = texture.create_view(&wgpu::TextureViewDescriptor {
+		dimension: Some(wgpu::TextureViewDimension::D2Array),
+		base_array_layer: 0,
+		array_layer_count: Some(max_layers as u32),
+		..Default::default()
+	});
+
+	Some((texture, view))
+}
+
+/// Writes one layer of the array texture and (if it has them) its mips, downsampled on the cpu
+fn write_layer(queue: &wgpu::Queue, texture: &wgpu::Texture, layer: u32, rgba: Vec<u8>) {
+	let (mut data, mut width, mut height) = (rgba, texture.width(), texture.height());
+	for mip_level in 0..texture.mip_level_count() {
+		if mip_level > 0 {
+			(data, width, height) = downsample_rgba(&data, width, height);
+		}
 		queue.write_texture(
 			wgpu::TexelCopyTextureInfo {
-				texture: &texture,
-				mip_level: 0,
-				origin: wgpu::Origin3d { x: 0, y: 0, z: layer as u32 },
+				texture,
+				mip_level,
+				origin: wgpu::Origin3d { x: 0, y: 0, z: layer },
 				aspect: wgpu::TextureAspect::All,
 			},
-			&image_data,
+			&data,
 			wgpu::TexelCopyBufferLayout {
 				offset: 0,
-				bytes_per_row: Some(4 * base_width),
-				rows_per_image: Some(base_height),
+				bytes_per_row: Some(4 * width),
+				rows_per_image: Some(height),
 			},
 			wgpu::Extent3d {
-				width: base_width,
-				height: base_height,
+				width,
+				height,
 				depth_or_array_layers: 1,
 			},
 		);
 	}
+}
 
-	let view = texture.create_view(&wgpu::TextureViewDescriptor {
-		dimension: Some(wgpu::TextureViewDimension::D2Array),
-		base_array_layer: 0,
-		array_layer_count: Some(valid_count as u32),
+/// Sampler for the block texture array
+fn create_block_sampler(device: &wgpu::Device, mipmaps: bool, anisotropy: u8) -> wgpu::Sampler {
+	let anisotropy = clamp_anisotropy(anisotropy);
+	// Anisotropic filtering is only allowed with every filter linear, so it gives up the sharp minification
+	let filtered = anisotropy > 1;
+	device.create_sampler(&wgpu::SamplerDescriptor {
+		label: Some("block_sampler"),
+		address_mode_u: wgpu::AddressMode::ClampToEdge,
+		address_mode_v: wgpu::AddressMode::ClampToEdge,
+		address_mode_w: wgpu::AddressMode::ClampToEdge,
+		mag_filter: wgpu::FilterMode::Linear,
+		min_filter: if filtered { wgpu::FilterMode::Linear } else { wgpu::FilterMode::Nearest },
+		mipmap_filter: if mipmaps || filtered { wgpu::FilterMode::Linear } else { wgpu::FilterMode::Nearest },
+		anisotropy_clamp: anisotropy as u16,
 		..Default::default()
-	});
+	})
+}
 
-	Some((texture, view))
+/// Clamps to what samplers accept: a power of two from 1 to `MAX_ANISOTROPY` (rounding down), logs if it had to change it
+pub fn clamp_anisotropy(anisotropy: u8) -> u8 {
+	let clamped = anisotropy.clamp(1, MAX_ANISOTROPY);
+	let clamped = 1 << (7 - clamped.leading_zeros());
+	if clamped != anisotropy {
+		println!("Anisotropy {} is not supported, using {}", anisotropy, clamped);
+	}
+	clamped
+}
+
+/// Mip levels of a full chain, down to 1x1
+#[inline] pub const fn mip_level_count(width: u32, height: u32) -> u32 {
+	let size = if width > height { width } else { height };
+	32 - (if size == 0 { 1 } else { size }).leading_zeros()
+}
+
+/// Halves an Rgba8UnormSrgb image with a 2x2 box filter (odd sizes repeat their last row / column).
+/// Colors are averaged in linear space and weighted by alpha, so the (usually black) transparent texels
+/// of cutout textures don't bleed into the edges
+pub fn downsample_rgba(data: &[u8], width: u32, height: u32) -> (Vec<u8>, u32, u32) {
+	let (new_width, new_height) = ((width / 2).max(1), (height / 2).max(1));
+	let texel = |x: u32, y: u32| {
+		let i = ((y.min(height - 1) * width + x.min(width - 1)) * 4) as usize;
+		&data[i..i + 4]
+	};
+
+	let mut out = Vec::with_capacity((new_width * new_height * 4) as usize);
+	for y in 0..new_height {
+		for x in 0..new_width {
+			let samples = [texel(2 * x, 2 * y), texel(2 * x + 1, 2 * y), texel(2 * x, 2 * y + 1), texel(2 * x + 1, 2 * y + 1)];
+			let alpha_sum: f32 = samples.iter().map(|t| t[3] as f32).sum();
+			for c in 0..3 {
+				let color = if alpha_sum > 0. {
+					samples.iter().map(|t| srgb_to_linear(t[c]) * t[3] as f32).sum::<f32>() / alpha_sum
+				} else {
+					samples.iter().map(|t| srgb_to_linear(t[c])).sum::<f32>() / 4.
+				};
+				out.push(linear_to_srgb(color));
+			}
+			out.push((alpha_sum / 4.).round() as u8);
+		}
+	}
+	(out, new_width, new_height)
+}
+
+#[inline] fn srgb_to_linear(value: u8) -> f32 {
+	let v = value as f32 / 255.;
+	if v <= 0.04045 { v / 12.92 } else { ((v + 0.055) / 1.055).powf(2.4) }
+}
+#[inline] fn linear_to_srgb(value: f32) -> u8 {
+	let v = if value <= 0.0031308 { value * 12.92 } else { 1.055 * value.powf(1. / 2.4) - 0.055 };
+	(v * 255.).round().clamp(0., 255.) as u8
 }
 
 // Creates a bind group for the texture array
@@ -219,6 +544,7 @@ fn create_texture_array_bind_group(
 	layout: &wgpu::BindGroupLayout,
 	texture_view: &wgpu::TextureView,
 	sampler: &wgpu::Sampler,
+	layer_map: &wgpu::Buffer,
 ) -> wgpu::BindGroup {
 	device.create_bind_group(&wgpu::BindGroupDescriptor {
 		label: Some("texture_array_bind_group"),
@@ -232,6 +558,10 @@ fn create_texture_array_bind_group(
 				binding: 1,
 				resource: wgpu::BindingResource::Sampler(sampler),
 			},
+			wgpu::BindGroupEntry {
+				binding: 2,
+				resource: layer_map.as_entire_binding(),
+			},
 		],
 	})
 }
@@ -259,7 +589,82 @@ fn create_render_texture(
 	(texture, view)
 }
 
-fn create_post_processing_bind_group(
+/// Multisampled color target of the 3D passes, nothing to make for a single sample
+fn create_msaa_texture(
+	device: &wgpu::Device,
+	config: &wgpu::SurfaceConfiguration,
+	sample_count: u32,
+) -> Option<(wgpu::Texture, wgpu::TextureView)> {
+	if sample_count <= 1 { return None; }
+	let texture = device.create_texture(&wgpu::TextureDescriptor {
+		label: Some("MSAA Texture"),
+		size: wgpu::Extent3d {
+			width: config.width.max(1),
+			height: config.height.max(1),
+			depth_or_array_layers: 1,
+		},
+		mip_level_count: 1,
+		sample_count,
+		dimension: wgpu::TextureDimension::D2,
+		format: config.format,
+		usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+		view_formats: &[],
+	});
+
+	let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+	Some((texture, view))
+}
+
+/// MSAA sample counts both the surface format and the depth format can render with (1 is always in there).
+/// Without `TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES` on the device only the guaranteed counts may be used
+pub fn supported_sample_counts(adapter: &wgpu::Adapter, device_features: wgpu::Features, format: wgpu::TextureFormat) -> Vec<u32> {
+	let flags = |format: wgpu::TextureFormat| if device_features.contains(wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES) {
+		adapter.get_texture_format_features(format).flags
+	} else {
+		format.guaranteed_format_features(device_features).flags
+	};
+	let (color, depth) = (flags(format), flags(DEPTH_FORMAT));
+	[1, 2, 4, 8, 16].into_iter()
+		.filter(|&count| count == 1 || (color.sample_count_supported(count) && depth.sample_count_supported(count)))
+		.collect()
+}
+
+/// Closest supported sample count to the requested one (the lower one on a tie), logs if it had to change it
+pub fn nearest_sample_count(requested: u32, supported: &[u32]) -> u32 {
+	let nearest = supported.iter().copied()
+		.min_by_key(|&count| (count.abs_diff(requested), count))
+		.unwrap_or(1);
+	if nearest != requested {
+		println!("MSAA x{} is not supported, using x{}", requested, nearest);
+	}
+	nearest
+}
+
+/// Offscreen copy of the surface, `COPY_SRC` is what the swapchain texture can't promise
+fn create_frame_texture(
+	device: &wgpu::Device,
+	config: &wgpu::SurfaceConfiguration,
+) -> (wgpu::Texture, wgpu::TextureView) {
+	let texture = device.create_texture(&wgpu::TextureDescriptor {
+		label: Some("Frame Texture"),
+		size: wgpu::Extent3d {
+			width: config.width.max(1),
+			height: config.height.max(1),
+			depth_or_array_layers: 1,
+		},
+		mip_level_count: 1,
+		sample_count: 1,
+		dimension: wgpu::TextureDimension::D2,
+		format: config.format,
+		usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_SRC,
+		view_formats: &[],
+	});
+
+	let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+	(texture, view)
+}
+
+pub fn create_post_processing_bind_group(
 	device: &wgpu::Device,
 	render_texture_view: &wgpu::TextureView,
 	layout: &wgpu::BindGroupLayout,
@@ -1,14 +1,16 @@
 
 use crate::physic::aabb::AABB;
-use crate::render::meshing::{CUBE_FACES, InstanceRaw, ChunkMeshBuilder, GeometryBuffer};
+use crate::render::meshing::{CUBE_FACES, InstanceRaw, ChunkMeshBuilder, GeometryBufferPool, pack_pos, face_light, MAX_LOD};
 use crate::block::math::{ChunkCoord, LocalPos};
 use crate::block::main::{Block, Chunk};
 use crate::block::storage::BlockStorage;
-use crate::player::CameraSystem;
-use crate::world::main::World;
+use crate::player::{CameraSystem, Frustum};
+use crate::world::{main::World, threading::MeshJob};
+use crate::render::debug::{Line, LineBuffer, box_outline};
+use crate::game::{player::PLAYER_SIZE, remote::{PlayerState, RemotePlayers}};
 use crate::ext::ptr;
 use wgpu::util::DeviceExt;
-use glam::IVec3;
+use glam::{IVec3, Quat, Vec3};
 
 pub struct NeighboringChunks<'a> {
 	chunks: [Option<&'a Chunk>; 6],
@@ -31,6 +33,10 @@ impl<'a> NeighboringChunks<'a> {
 		self.chunks.iter().all(Option::is_some)
 	}
 
+	pub fn as_array(&self) -> &[Option<&'a Chunk>; 6] {
+		&self.chunks
+	}
+
 	// Add an iter() method that returns an iterator over Option<&Chunk>
 	pub fn iter(&self) -> impl Iterator<Item = Option<&'a Chunk>> + '_ {
 		self.chunks.iter().copied()
@@ -42,103 +48,119 @@ impl<'a> NeighboringChunks<'a> {
 // =============================================
 
 impl Chunk {
-	pub fn make_mesh(&mut self, device: &wgpu::Device, _queue: &wgpu::Queue, neighbors: NeighboringChunks) {
-		if !self.dirty 
-			&& (self.mesh().is_some() ^ self.is_empty()) 
-			&& (self.final_mesh || !neighbors.is_some()) 
-		{
-			return;
+	/// Whether the chunk has to be (re)meshed at this level of detail.
+	/// A remesh running on a worker counts as a mesh, unless the chunk changed again since it was queued
+	pub fn needs_mesh(&self, neighbors: &NeighboringChunks, lod: u8) -> bool {
+		let has_mesh = self.mesh_job() != 0 || self.mesh().is_some();
+		self.dirty
+			|| !(has_mesh ^ self.is_empty())
+			|| (!self.final_mesh && neighbors.is_some())
+			|| self.mesh_lod() != lod
+	}
+
+	/// Remeshes the chunk on the spot if needed, `lod` above 0 builds the coarse far away mesh (see `chunk_lod`).
+	/// The instance buffer comes from the pool and the replaced one goes back to it
+	pub fn make_mesh(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, pool: &mut GeometryBufferPool, neighbors: NeighboringChunks, lod: u8) {
+		if !self.needs_mesh(&neighbors, lod) { return; }
+		self.set_mesh_lod(lod);
+		self.set_mesh_job(0); // anything still on a worker is outdated now
+
+		let instances = self.build_instances(&neighbors, lod, ptr::get_settings().render_config.greedy_meshing);
+		let mesh = pool.acquire(device, queue, &instances);
+		if let Some(old) = self.set_mesh(Some(mesh)) {
+			pool.release(old);
 		}
+		self.dirty = false;
+		self.final_mesh = neighbors.is_some();
+	}
 
-		// Early return if chunk is empty
-		if self.is_empty() {
-			self.set_mesh(Some(GeometryBuffer::empty(device)));
-			self.dirty = false;
-			self.final_mesh = neighbors.is_some();
-			return;
+	/// CPU side of meshing, touches nothing but the chunk and its neighbors so it can run on a worker
+	pub fn build_instances(&self, neighbors: &NeighboringChunks, lod: u8, greedy: bool) -> Vec<InstanceRaw> {
+		let mut builder = ChunkMeshBuilder::new().with_greedy(greedy);
+		// Empty chunks just get an empty mesh
+		if !self.is_empty() {
+			self.fill_mesh(&mut builder, neighbors, lod);
 		}
+		builder.instances
+	}
 
-		let mut builder = ChunkMeshBuilder::new();
+	/// Pushes the instances of the chunk, picking the lod, greedy or per storage path
+	fn fill_mesh(&self, builder: &mut ChunkMeshBuilder, neighbors: &NeighboringChunks, lod: u8) {
+		if lod > 0 {
+			return builder.push_lod(self, neighbors.as_array(), lod);
+		}
+		if builder.greedy {
+			return builder.push_greedy(self, neighbors.as_array());
+		}
 
 		// Optimize based on storage type
 		match &self.storage() {
 			BlockStorage::Uniform { block } => {
-				self.make_mesh_uniform(*block, &mut builder, &neighbors);
+				self.make_mesh_uniform(*block, builder, neighbors);
 			}
 			BlockStorage::Compact { palette, indices } => {
-				self.make_mesh_compact(&palette, &indices, &mut builder, &neighbors);
+				self.make_mesh_compact(&palette, &indices, builder, neighbors);
 			}
 			BlockStorage::Sparse { palette, indices } => {
-				self.make_mesh_sparse(&palette, &indices, &mut builder, &neighbors);
-			}
-			BlockStorage::Rle { .. } => {
-				// Never gonna happen ...
-				todo!();
+				self.make_mesh_sparse(&palette, &indices, builder, neighbors);
 			}
 			_ => {
-				// Fallback to original method for Giant and Zigzag
-				for pos_idx in 0..Self::VOLUME {
-					let block = self.get_block(pos_idx);
-					if block.is_empty() { continue; }
-
-					self.add_cube_faces(pos_idx, block.material().inner(), &mut builder, &neighbors);
+				// Fallback for Giant, Zigzag and Rle, only walks the solid blocks
+				for (pos, block) in self.iter_solid() {
+					self.add_cube_faces(usize::from(pos), block.material().inner(), builder, neighbors);
 				}
 			}
 		}
-
-		self.set_mesh(Some(builder.build(device)));
-		self.dirty = false;
-		self.final_mesh = neighbors.is_some();
 	}
 
 	#[inline]
 	fn add_cube_faces(&self, pos: usize, material_id: u16, builder: &mut ChunkMeshBuilder, neighbors: &NeighboringChunks) {
 		let local = LocalPos::from(pos);
 		let pos = IVec3::from(local);
-		let local_pos_packed = u16::from(local) as u32;
+		let local_pos_packed = pack_pos(local);
 		
 		// Unrolled loop for better performance
 		// Face 0: Left (-X)
 		if !self.should_cull_face(pos + IVec3::NEG_X, neighbors) {
-			builder.instances.push(InstanceRaw {
-				packed_data: local_pos_packed | (0u32 << 15) | (material_id as u32) << 19
-			});
+			builder.instances.push(InstanceRaw::new(
+				local_pos_packed | (0u32 << 15) | (material_id as u32) << 19
+			).with_light(face_light(self, neighbors.as_array(), pos, 0)));
 		}
 		// 0-15 : pos ; 16-19 : rot ; 19 ... block id
 		
 		// Face 1: Right (+X)
 		if !self.should_cull_face(pos + IVec3::X, neighbors) {
-			builder.instances.push(InstanceRaw {
-				packed_data: local_pos_packed | (1u32 << 15) | (material_id as u32) << 19
-			});
+			builder.instances.push(InstanceRaw::new(
+				local_pos_packed | (1u32 << 15) | (material_id as u32) << 19
+			).with_light(face_light(self, neighbors.as_array(), pos, 1)));
 		}
 		
 		// Face 2: Front (-Z)
 		if !self.should_cull_face(pos + IVec3::NEG_Z, neighbors) {
-			builder.instances.push(InstanceRaw {
-				packed_data: local_pos_packed | (2u32 << 15) | (material_id as u32) << 19
-			});
+			builder.instances.push(InstanceRaw::new(
+				local_pos_packed | (2u32 << 15) | (material_id as u32) << 19
+			).with_light(face_light(self, neighbors.as_array(), pos, 2)));
 		}
 		
 		// Face 3: Back (+Z)
 		if !self.should_cull_face(pos + IVec3::Z, neighbors) {
-			builder.instances.push(InstanceRaw {
-				packed_data: local_pos_packed | (3u32 << 15) | (material_id as u32) << 19
-			});
+			builder.instances.push(InstanceRaw::new(
+				local_pos_packed | (3u32 << 15) | (material_id as u32) << 19
+			).with_light(face_light(self, neighbors.as_array(), pos, 3)));
 		}
 		
 		// Face 4: Top (+Y)
 		if !self.should_cull_face(pos + IVec3::Y, neighbors) {
-			builder.instances.push(InstanceRaw {
-				packed_data: local_pos_packed | (4u32 << 15) | (material_id as u32) << 19
-			});
+			builder.instances.push(InstanceRaw::new(
+				local_pos_packed | (4u32 << 15) | (material_id as u32) << 19
+			).with_light(face_light(self, neighbors.as_array(), pos, 4)));
 		}
 		
 		// Face 5: Bottom (-Y)
 		if !self.should_cull_face(pos + IVec3::NEG_Y, neighbors) {
-			builder.instances.push(InstanceRaw {
-				packed_data: local_pos_packed | (5u32 << 15) | (material_id as u32) << 19
-			});
+			builder.instances.push(InstanceRaw::new(
+				local_pos_packed | (5u32 << 15) | (material_id as u32) << 19
+			).with_light(face_light(self, neighbors.as_array(), pos, 5)));
 		}
 	}
 
@@ -162,10 +184,10 @@ impl Chunk {
 						// Quick boundary checks for uniform blocks
 						if self.should_cull_face_uniform(neighbor_pos, block, neighbors) { continue }
 
-						let local_pos_packed = u16::from(LocalPos::from(pos)) as u32;
-						builder.instances.push(InstanceRaw {
-							packed_data: local_pos_packed | (face_idx as u32) << 15 | (material_id as u32) << 19
-						});
+						let local_pos_packed = pack_pos(LocalPos::from(pos));
+						builder.instances.push(InstanceRaw::new(
+							local_pos_packed | (face_idx as u32) << 15 | (material_id as u32) << 19
+						).with_light(face_light(self, neighbors.as_array(), pos, face_idx)));
 					}
 				}
 			}
@@ -298,15 +320,28 @@ impl Chunk {
 	}
 }
 impl World {
-	/// Generates meshes for all dirty chunks and all non final meshed ones
+	/// Remeshes all dirty chunks and all non final meshed ones.
+	/// With the worker threads running the meshing is queued for them (see `queue_mesh_jobs`) and only the upload happens here
 	#[inline]
 	pub fn make_chunk_meshes(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+		self.process_meshed_chunks(device, queue);
+		let threaded = self.generation_threads_running.load(std::sync::atomic::Ordering::Relaxed);
+		let greedy = ptr::get_settings().render_config.greedy_meshing;
+		let mut jobs = Vec::new();
 		
 		// Get raw pointer to the world's chunks
 		let world_ptr = self as *mut World;
+		let player_chunk = ChunkCoord::from_world_posf(ptr::get_gamestate().player().pos());
+		let lod_distance = ptr::get_settings().render_config.lod_distance;
 
 		for (chunk_coord, chunk) in self.chunks.iter_mut() {
-			if chunk.is_empty() { continue; }
+			// Unloaded chunks keep what `trim_chunk_gpu` left them, they aren't drawn
+			if chunk.is_empty() || !self.loaded_chunks.contains(chunk_coord) { continue; }
+
+			if chunk.occluded.is_none() {
+				// SAFETY: same as for the neighbors below
+				chunk.occluded = Some(is_occluded(*chunk_coord, unsafe { &*world_ptr }));
+			}
 
 			// SAFETY:
 			// 1. We only use the pointer to access different chunks than the one we're modifying
@@ -316,9 +351,23 @@ impl World {
 				let world_ref = &*world_ptr;
 				world_ref.get_neighboring_chunks(*chunk_coord)
 			};
+			let lod = chunk_lod(*chunk_coord, player_chunk, lod_distance);
 
-			chunk.make_mesh(device, queue, neighbors);
+			if !threaded {
+				chunk.make_mesh(device, queue, &mut self.mesh_pool, neighbors, lod);
+				continue;
+			}
+			if !chunk.needs_mesh(&neighbors, lod) { continue; }
+
+			self.mesh_job_id = self.mesh_job_id.wrapping_add(1).max(1);
+			chunk.set_mesh_lod(lod);
+			chunk.set_mesh_job(self.mesh_job_id);
+			chunk.dirty = false;
+			chunk.final_mesh = neighbors.is_some();
+			jobs.push(MeshJob::new(*chunk_coord, self.mesh_job_id, chunk, &neighbors, lod, greedy));
 		}
+
+		self.queue_mesh_jobs(jobs);
 	}
 
 	#[inline]
@@ -338,6 +387,7 @@ impl World {
 	// Improved rendering function with better culling
 	pub fn render_chunks_with_culling<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>, cam_sys: &CameraSystem, max_render_distance: f32) {
 		let frustum = cam_sys.frustum();
+		let culling = ptr::get_settings().render_config.frustum_culling;
 		let camera_pos = cam_sys.uniform().to_pos_vec3();
 		
 		// Pre-calculate max_render_distance_squared for faster comparisons
@@ -347,16 +397,9 @@ impl World {
 			if chunk.is_empty() { continue; }
 			let (Some(mesh), Some(bind_group)) = (&chunk.mesh(), &chunk.bind_group()) else { continue };
 			if mesh.num_instances == 0 { continue }
+			if chunk.occluded == Some(true) { continue }
 			
-			let chunk_aabb = AABB::from_chunk_coord(&chunk_coord);
-			
-			// Distance culling first (cheaper) - using squared distance to avoid sqrt
-			let chunk_center = chunk_aabb.center();
-			let distance_squared_to_camera = (chunk_center - camera_pos).length_squared();
-			if distance_squared_to_camera > max_render_distance_squared { continue }
-			
-			// Enhanced frustum culling with detailed intersection info
-			if !frustum.contains_aabb(&chunk_aabb) { continue }
+			if !chunk_in_view(chunk_coord, frustum, camera_pos, max_render_distance_squared, culling) { continue }
 
 			// Inside or intersecting frustum - render it
 			render_pass.set_bind_group(2, *bind_group, &[]);
@@ -366,9 +409,80 @@ impl World {
 	}
 
 	pub fn create_bind_group(&mut self, chunk_coord: ChunkCoord) {
-		if !self.loaded_chunks.contains(&chunk_coord) { return; }
+		if !self.loaded_chunks.contains(&chunk_coord) || !ptr::has_state() { return; }
 		let Some(c) = self.get_chunk_mut(&chunk_coord) else { return; };
 		
 		c.create_bind_group(chunk_coord);
 	}
 }
+
+/// Whether a chunk should be drawn, anything at least partly inside the frustum is kept.
+/// With `culling` off only the render distance counts
+pub fn chunk_in_view(chunk_coord: &ChunkCoord, frustum: &Frustum, camera_pos: glam::Vec3, max_render_distance_squared: f32, culling: bool) -> bool {
+	let chunk_aabb = AABB::from_chunk_coord(chunk_coord);
+	
+	// Distance culling first (cheaper) - using squared distance to avoid sqrt
+	let distance_squared_to_camera = (chunk_aabb.center() - camera_pos).length_squared();
+	if distance_squared_to_camera > max_render_distance_squared { return false; }
+	
+	!culling || frustum.contains_aabb(&chunk_aabb)
+}
+
+/// Level of detail for a chunk: full detail up to `lod_distance` chunks away from the player, then one level coarser every `lod_distance` chunks.
+/// A `lod_distance` of 0 keeps everything at full detail
+pub fn chunk_lod(chunk_coord: ChunkCoord, player_chunk: ChunkCoord, lod_distance: u32) -> u8 {
+	if lod_distance == 0 { return 0; }
+	let distance = (chunk_coord.x() - player_chunk.x()).unsigned_abs()
+		.max((chunk_coord.y() - player_chunk.y()).unsigned_abs())
+		.max((chunk_coord.z() - player_chunk.z()).unsigned_abs());
+	(distance / lod_distance).min(MAX_LOD as u32) as u8
+}
+
+/// True when all six neighbors are loaded and completely solid, nothing inside can be seen then.
+/// A missing neighbor (the edge of the loaded world) never occludes
+pub fn is_occluded(chunk_coord: ChunkCoord, world: &World) -> bool {
+	chunk_coord.get_adjacent().iter().all(|coord| world.get_chunk(coord).is_some_and(|chunk| chunk.is_full()))
+}
+
+// =============================================
+// Remote players
+// =============================================
+
+/// Box of another player where `Player` would put its collider, plus a line from the eyes the way it faces
+pub fn avatar_lines(state: &PlayerState) -> [Line; 13] {
+	let aabb = AABB::from_pos(state.pos, PLAYER_SIZE);
+	let facing = Quat::from_rotation_y(state.yaw) * Vec3::NEG_Z;
+	let eye = Vec3::new(state.pos.x, aabb.max.y - 0.2, state.pos.z);
+	let outline = box_outline(aabb.min, aabb.max - aabb.min);
+	std::array::from_fn(|i| outline.get(i).copied().unwrap_or(Line::new(eye, facing)))
+}
+
+/// Line buffer of every remote avatar, rebuilt each frame since they move between network updates
+pub struct Avatars {
+	pub line_buffer: LineBuffer,
+	count: u32,
+}
+
+impl Avatars {
+	pub fn new(device: &wgpu::Device) -> Self {
+		Self {
+			line_buffer: LineBuffer::new(device, &[Line::default(); 13]),
+			count: 0,
+		}
+	}
+	#[inline] pub const fn is_visible(&self) -> bool { self.count > 0 }
+
+	pub fn update(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, players: &RemotePlayers) {
+		let lines: Vec<Line> = players.states().flat_map(|state| avatar_lines(&state)).collect();
+		self.count = lines.len() as u32;
+		if !lines.is_empty() {
+			self.line_buffer.update(device, queue, &lines);
+		}
+	}
+
+	pub fn render<'a>(&'a self, pass: &mut wgpu::RenderPass<'a>) {
+		if self.count == 0 { return; }
+		pass.set_bind_group(0, &self.line_buffer.bind_group, &[]);
+		pass.draw(0..2, 0..self.count);
+	}
+}
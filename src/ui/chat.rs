@@ -0,0 +1,191 @@
+use crate::{
+	ext::ptr,
+	network::sync,
+	ui::{
+		element::UIElement,
+		manager::{FocusState, UIManager, UIState},
+	},
+	utils::color::Solor,
+};
+use glam::Vec2;
+use std::collections::VecDeque;
+use winit::keyboard::KeyCode as Key;
+
+/// Lines kept in the chat, older ones are dropped
+pub const CHAT_HISTORY: usize = 100;
+/// Longest message in characters, longer ones are cut
+pub const CHAT_MAX_LEN: usize = 256;
+/// Lines shown while the chat is open, the wheel scrolls through the rest
+pub const CHAT_VISIBLE_LINES: usize = 10;
+/// Lines still shown under the game while the chat is closed
+pub const CHAT_PREVIEW_LINES: usize = 3;
+/// Height of one chat line in screen units
+const CHAT_LINE_HEIGHT: f32 = 0.05;
+
+/// The text of a message as it is shown, None if nothing is left of it.
+/// Control characters (new lines, escapes) are dropped so a message stays one line
+pub fn clean_chat_text(text: &str) -> Option<String> {
+	let clean: String = text.chars().filter(|c| !c.is_control()).take(CHAT_MAX_LEN).collect();
+	let clean = clean.trim();
+	if clean.is_empty() { None } else { Some(clean.to_string()) }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChatLine {
+	pub sender: String,
+	pub text: String,
+}
+
+impl ChatLine {
+	#[inline] pub fn display(&self) -> String {
+		format!("<{}> {}", self.sender, self.text)
+	}
+}
+
+/// Messages of this session and the line being typed
+#[derive(Debug, Clone, Default)]
+pub struct ChatLog {
+	lines: VecDeque<ChatLine>,
+	open: bool,
+	pub draft: String,
+	scroll: usize, // lines scrolled back from the newest
+	input_id: usize, // element showing the draft, 0 while closed
+}
+
+impl ChatLog {
+	#[inline] pub const fn new() -> Self {
+		Self { lines: VecDeque::new(), open: false, draft: String::new(), scroll: 0, input_id: 0 }
+	}
+	#[inline] pub const fn is_open(&self) -> bool { self.open }
+	#[inline] pub fn len(&self) -> usize { self.lines.len() }
+	#[inline] pub fn is_empty(&self) -> bool { self.lines.is_empty() }
+	#[inline] pub const fn scroll(&self) -> usize { self.scroll }
+
+	pub fn push(&mut self, sender: &str, text: &str) {
+		self.lines.push_back(ChatLine { sender: sender.to_string(), text: text.to_string() });
+		while self.lines.len() > CHAT_HISTORY {
+			self.lines.pop_front();
+		}
+		// Someone reading back keeps looking at the same lines
+		if self.scroll > 0 { self.scroll_by(1); }
+	}
+
+	/// Moves the view back (positive) or forward through the history
+	pub fn scroll_by(&mut self, lines: isize) {
+		let max = self.lines.len().saturating_sub(CHAT_VISIBLE_LINES);
+		self.scroll = self.scroll.saturating_add_signed(lines).min(max);
+	}
+
+	/// The `count` lines ending `scroll` lines before the newest, oldest first
+	pub fn visible(&self, count: usize) -> impl Iterator<Item = &ChatLine> {
+		let end = self.lines.len().saturating_sub(self.scroll);
+		self.lines.range(end.saturating_sub(count)..end)
+	}
+}
+
+impl UIManager {
+	/// Opens the chat line, from here on the keyboard types into it instead of moving the player
+	pub fn open_chat(&mut self) {
+		if self.chat.open || !matches!(self.state, UIState::InGame) { return; }
+		self.chat.open = true;
+		self.chat.draft.clear();
+		self.chat.scroll = 0;
+		self.setup_ui();
+	}
+
+	pub fn close_chat(&mut self) {
+		if !self.chat.open { return; }
+		self.chat.open = false;
+		self.chat.scroll = 0;
+		self.setup_ui();
+	}
+
+	/// Every key while the chat is open ends up here, Enter sends and Escape throws the line away
+	pub fn handle_chat_key(&mut self, key: Key, input_str: &str) {
+		match key {
+			Key::Escape => {
+				self.close_chat();
+				return;
+			}
+			Key::Enter => {
+				let draft = std::mem::take(&mut self.chat.draft);
+				if let Some(text) = clean_chat_text(&draft) {
+					if let Err(e) = sync::send_chat(&text) {
+						println!("Chat message not sent: {}", e);
+					}
+					self.chat.push("You", &text);
+				}
+				self.close_chat();
+				return;
+			}
+			Key::Backspace => { self.chat.draft.pop(); },
+			_ => {
+				if self.chat.draft.chars().count() >= CHAT_MAX_LEN { return; }
+				self.chat.draft.extend(input_str.chars().filter(|c| !c.is_control()));
+			}
+		}
+		let (input_id, draft) = (self.chat.input_id, self.chat.draft.clone());
+		if let Some(text) = self.get_element_mut(input_id).and_then(|e| e.get_text_mut()) {
+			*text = draft;
+		}
+	}
+
+	/// A message from another player, the overlay is rebuilt if it is on screen
+	pub fn receive_chat(&mut self, sender: &str, text: &str) {
+		println!("<{}> {}", sender, text);
+		self.chat.push(sender, text);
+		if matches!(self.state, UIState::InGame) { self.setup_ui(); }
+	}
+
+	/// Scrolls the history while the chat is open, false if the wheel should do something else
+	pub fn scroll_chat(&mut self, delta: f32) -> bool {
+		if !self.chat.open { return false; }
+		// The wheel delta is reversed, up is negative
+		let before = self.chat.scroll;
+		self.chat.scroll_by(-delta.signum() as isize);
+		if self.chat.scroll != before { self.setup_ui(); }
+		true
+	}
+
+	/// History panel in the bottom left, with the input line under it while the chat is open
+	pub fn setup_chat_ui(&mut self) {
+		let theme = &ptr::get_settings().ui_theme;
+		let open = self.chat.open;
+		let count = if open { CHAT_VISIBLE_LINES } else { CHAT_PREVIEW_LINES };
+		let lines: Vec<String> = self.chat.visible(count).map(ChatLine::display).collect();
+		let bottom = -0.75;
+
+		if open {
+			let panel = UIElement::panel(self.next_id())
+				.with_position(Vec2::new(-0.98, bottom))
+				.with_size(Vec2::new(1.0, CHAT_LINE_HEIGHT * CHAT_VISIBLE_LINES as f32 + 0.02))
+				.with_color(Solor::Black.i().with_a(50))
+				.with_alpha(40)
+				.with_z_index(15);
+			self.add_element(panel);
+		}
+		// Newest line at the bottom
+		for (i, line) in lines.iter().rev().enumerate() {
+			let label = UIElement::label(self.next_id(), line.clone().into())
+				.with_position(Vec2::new(-0.97, bottom + 0.01 + i as f32 * CHAT_LINE_HEIGHT))
+				.with_size(Vec2::new(0.98, CHAT_LINE_HEIGHT))
+				.with_style(theme, "labels.basic")
+				.with_z_index(16);
+			self.add_element(label);
+		}
+
+		self.chat.input_id = 0;
+		if !open { return; }
+		let input_id = self.next_id();
+		let input = UIElement::input(input_id)
+			.with_position(Vec2::new(-0.98, bottom - 0.09))
+			.with_size(Vec2::new(1.0, 0.08))
+			.with_style(theme, "inputs.basic")
+			.with_placeholder("Say something")
+			.with_text(&self.chat.draft)
+			.with_z_index(16);
+		self.add_element(input);
+		self.chat.input_id = input_id;
+		self.set_focused_state(FocusState::input(input_id));
+	}
+}
@@ -327,10 +327,73 @@ impl fmt::Display for DialogError {
 
 impl std::error::Error for DialogError {}
 
+// ============================================================================
+// ConfirmDialog
+// ============================================================================
+
+type ConfirmAction = Box<dyn FnMut() + 'static>;
+
+/// A yes/no question, the answer runs one of its actions after the screen under it is back
+pub struct ConfirmDialog {
+	pub message: String,
+	pub on_yes: ConfirmAction,
+	pub on_no: ConfirmAction,
+	previous: UIState, // screen the dialog was opened over
+}
+
+impl ConfirmDialog {
+	/// A question that does nothing either way until its actions are set
+	pub fn new(message: impl Into<String>) -> Self {
+		Self { message: message.into(), on_yes: Box::new(|| {}), on_no: Box::new(|| {}), previous: UIState::None }
+	}
+	pub fn on_yes(mut self, action: impl FnMut() + 'static) -> Self { self.on_yes = Box::new(action); self }
+	pub fn on_no(mut self, action: impl FnMut() + 'static) -> Self { self.on_no = Box::new(action); self }
+	#[inline] pub const fn previous(&self) -> &UIState { &self.previous }
+
+	/// Runs the action of the answer
+	pub fn answer(&mut self, yes: bool) {
+		if yes { (self.on_yes)() } else { (self.on_no)() }
+	}
+}
+
+impl fmt::Debug for ConfirmDialog {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("ConfirmDialog").field("message", &self.message).finish_non_exhaustive()
+	}
+}
+
 impl UIManager {
-	pub fn confirm(&mut self, id: u8, _prompt: impl Into<String>) {
-		let ui_manager = &mut ptr::get_state().ui_manager;
-		ui_manager.state = UIState::Confirm(UIStateID::from(&ui_manager.state), id.clone());
-		ui_manager.setup_ui();
+	/// Asks on behalf of a pending dialog of the `DialogManager`, the answer goes to its callback or future
+	pub fn confirm(&mut self, id: u8, prompt: impl Into<String>) {
+		let dialog = ConfirmDialog::new(prompt)
+			.on_yes(move || { ptr::get_state().ui_manager.dialogs.respond(id, true); })
+			.on_no(move || { ptr::get_state().ui_manager.dialogs.respond(id, false); });
+		self.open_confirm(dialog);
+	}
+
+	/// Shows the dialog over the current screen, a dialog opened from another one goes on top of it
+	pub fn open_confirm(&mut self, mut dialog: ConfirmDialog) {
+		dialog.previous = self.state.clone();
+		let depth = self.confirms.len().min(u8::MAX as usize) as u8;
+		self.state = UIState::Confirm(UIStateID::from(&self.state), depth);
+		self.confirms.push(dialog);
+		self.setup_ui();
+	}
+
+	/// Closes the top dialog: its screen comes back first, so the action can change it (or ask again)
+	pub fn answer_confirm(&mut self, yes: bool) {
+		let Some(mut dialog) = self.confirms.pop() else {
+			self.state = self.state.inner_state();
+			self.setup_ui();
+			return;
+		};
+		self.state = dialog.previous.clone();
+		dialog.answer(yes);
+		self.setup_ui();
+	}
+
+	/// Question of the dialog on top, None while there is none
+	#[inline] pub fn confirm_message(&self) -> Option<&str> {
+		self.confirms.last().map(|dialog| dialog.message.as_str())
 	}
 }
\ No newline at end of file
@@ -1,6 +1,6 @@
 
 use crate::utils::color::{Color, Border};
-use crate::ext::config::ElementStyle;
+use crate::ext::config::{ElementStyle, UITheme};
 use crate::utils::string::MutStr;
 use crate::utils::vec2;
 use std::{cell::RefCell, sync::Arc };
@@ -8,10 +8,72 @@ use glam::Vec2;
 
 type Callback = Arc<RefCell<dyn FnMut() + 'static>>;
 
+/// Drawn around the focused element in place of its own border (if that one is thinner)
+pub const FOCUS_RING: Border = Border::rgbf(255, 210, 60, 0.008);
+/// Width of the scrollbar on the right of a scroll list, its children are cut off before it
+pub const SCROLLBAR_WIDTH: f32 = 0.03;
+
+/// Rect (position, size) a child of a scroll list is cut to, None for elements outside of lists
+pub fn clip_rect(elements: &[UIElement], element: &UIElement) -> Option<(Vec2, Vec2)> {
+	let Parent::Some { parent, .. } = element.parent else { return None; };
+	let list = elements.iter().find(|e| e.id == parent && e.is_scroll_list())?;
+	Some((list.position, Vec2::new(list.size.x - SCROLLBAR_WIDTH, list.size.y)))
+}
+
+/// Whether any of the element is inside its list, the rest are neither drawn nor clicked
+pub fn in_clip(elements: &[UIElement], element: &UIElement) -> bool {
+	let Some((pos, size)) = clip_rect(elements, element) else { return true; };
+	let (min_x, min_y, max_x, max_y) = element.get_bounds();
+	max_x > pos.x && min_x < pos.x + size.x && max_y > pos.y && min_y < pos.y + size.y
+}
+
+/// Whether a click at the point lands on the element, the part of it scrolled out of its list doesn't count
+pub fn hits(elements: &[UIElement], element: &UIElement, x: f32, y: f32) -> bool {
+	if !element.contains_point(x, y) { return false; }
+	let Some((pos, size)) = clip_rect(elements, element) else { return true; };
+	x >= pos.x && x <= pos.x + size.x && y >= pos.y && y <= pos.y + size.y
+}
+
+/// Width / height the screens are laid out for, element rects are given at this aspect
+pub const REFERENCE_ASPECT: f32 = 16.0 / 9.0;
+
+/// Which side an element stays at when the window gets wider or narrower than `REFERENCE_ASPECT`.
+/// Heights are a part of the window height already, so only the horizontal side matters
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Anchor {
+	#[default]
+	Stretch, // stretched with the window, like the fixed screen coordinates always were
+	Left,
+	Center,
+	Right,
+}
+impl Anchor {
+	/// Screen x the element is scaled towards
+	#[inline] pub const fn pivot(&self) -> f32 {
+		match self {
+			Self::Left => -1.0,
+			Self::Right => 1.0,
+			Self::Stretch | Self::Center => 0.0,
+		}
+	}
+}
+
+/// Layer an open dropdown is drawn and clicked on, its option list goes over everything else
+pub const DROPDOWN_OPEN_Z: i32 = i32::MAX / 2;
+
+/// Slider value kept inside min..max and on a whole number of steps from min (the max stays reachable)
+pub const fn snap_value(value: f32, min: f32, max: f32, step: Option<f32>) -> f32 {
+	let value = value.clamp(min, max);
+	let Some(step) = step else { return value; };
+	if step <= 0.0 || value >= max { return value; }
+	let steps = ((value - min) / step + 0.5) as i32 as f32;
+	(min + steps * step).clamp(min, max)
+}
+
 #[derive(Clone, Debug)]
 pub enum UIElementData {
 	Panel,
-	Label { text: MutStr },
+	Label { text: MutStr, wrap: bool }, // `wrap` breaks it into lines at the element's width instead of cutting it off
 	Button { text: MutStr },
 	MultiStateButton { states: Vec<MutStr>, current_state: usize },
 	InputField { text: MutStr, placeholder: MutStr },
@@ -22,6 +84,8 @@ pub enum UIElementData {
 		looping: bool, playing: bool, blend_delay: Option<u32> // having a blend delay "0" means the animation will be smooth, otherwise it just "snaps" to the next frame
 	},
 	Slider { min_value: f32, max_value: f32, current_value: f32, step: Option<f32> },
+	ScrollList { scroll: f32, content: f32 }, // its children are moved up by `scroll`, `content` is how tall they are together
+	Dropdown { options: Vec<MutStr>, selected: usize, open: bool }, // while open the options are listed under it, one row each
 }
 impl UIElementData {
 	#[inline] pub const fn default() -> Self { UIElementData::Panel }
@@ -58,6 +122,12 @@ impl std::fmt::Debug for UIElement {
 		debug_struct.field("size", &format_args!("({:.1}, {:.1})", self.size.x, self.size.y));
 		debug_struct.field("vertical", &self.vertical);
 		debug_struct.field("z_index", &self.z_index);
+		if self.anchor != Anchor::Stretch {
+			debug_struct.field("anchor", &self.anchor);
+		}
+		if self.keep_aspect {
+			debug_struct.field("keep_aspect", &true);
+		}
 		
 		// Appearance - simplified color representation
 		debug_struct.field("color", &format_args!("{:?}", self.color));
@@ -68,6 +138,9 @@ impl std::fmt::Debug for UIElement {
 		if self.hovered {
 			debug_struct.field("hovered", &true);
 		}
+		if self.focused {
+			debug_struct.field("focused", &true);
+		}
 		if !self.visible {
 			debug_struct.field("visible", &false);
 		}
@@ -103,16 +176,19 @@ pub struct UIElement {
 	//pub padding: Rect,
 	pub vertical: bool,
 	pub z_index: i32,
+	pub anchor: Anchor, // side it keeps to on a window wider than `REFERENCE_ASPECT`
+	pub keep_aspect: bool, // keeps its shape at any aspect (icons, the crosshair), even if that makes it wider
 
 	// Appearance
 	pub color: Color,
 	pub border: Border,
 	pub ext_color: Color,
+	pub style_key: Option<&'static str>, // theme style it was made with ("labels.basic" ..), what a theme change restyles it by
 	//pub background: Option<Color>,  // Could support gradients/textures
 
 	// State
 	pub hovered: bool,
-	//pub focused: bool,
+	pub focused: bool, // set by the manager, draws the focus ring
 	//pub active: bool,
 	pub visible: bool,
 	pub enabled: bool,
@@ -169,9 +245,13 @@ impl UIElement {
 			ext_color: Color::DEF_COLOR,
 			parent: Parent::None,
 			hovered: false,
+			focused: false,
 			z_index: 0,
+			anchor: Anchor::Stretch,
+			keep_aspect: false,
 			visible: true,
 			border: Border::NONE,
+			style_key: None,
 			enabled: true,
 			vertical: false,
 			event_handler: None,
@@ -183,12 +263,17 @@ impl UIElement {
 	}
 	#[inline]
 	pub fn label(id: usize, text: MutStr) -> Self {
-		Self::new(id, UIElementData::Label { text })
+		Self::new(id, UIElementData::Label { text, wrap: false })
 	}
 	#[inline]
 	pub fn button(id: usize, text: MutStr) -> Self {
 		Self::new(id, UIElementData::Button { text })
 	}
+	/// Children are added with `with_parent(id)`, `UIManager::fit_scroll_list` measures them once they are all in
+	#[inline]
+	pub fn scroll_list(id: usize) -> Self {
+		Self::new(id, UIElementData::ScrollList { scroll: 0., content: 0. })
+	}
 	#[inline]
 	pub fn input(id: usize) -> Self {
 		Self::new(id, UIElementData::InputField { text: MutStr::default(), placeholder: MutStr::default() })
@@ -213,6 +298,10 @@ impl UIElement {
 		Self::new(id, UIElementData::MultiStateButton { states, current_state: 0 })
 	}
 	#[inline]
+	pub fn dropdown(id: usize, options: Vec<MutStr>) -> Self {
+		Self::new(id, UIElementData::Dropdown { options, selected: 0, open: false })
+	}
+	#[inline]
 	pub fn slider(id: usize, min_value: f32, max_value: f32) -> Self {
 		Self::new(id, UIElementData::Slider {
 			min_value, max_value, current_value: min_value, step: None,
@@ -250,6 +339,8 @@ impl UIElement {
 	builder_method!(with_border, border: Border);
 	builder_method!(with_ext_color, ext_color: Color);
 	builder_method!(with_vertical, vertical: bool);
+	builder_method!(with_anchor, anchor: Anchor);
+	builder_method!(with_keep_aspect, keep_aspect: bool);
 	
 	// Setter methods (mutating self)
 	setter_method!(set_position, position: Vec2);
@@ -267,6 +358,11 @@ impl UIElement {
 	#[inline] pub const fn with_ext_alpha(mut self, a: u8) -> Self { self.ext_color = self.ext_color.with_a(a); self }
 	#[inline] pub const fn with_parent(mut self, parent: usize) -> Self { self.parent = Parent::some(parent, Vec2::ZERO); self }
 	#[inline] pub const fn with_parent_off(mut self, parent: usize, offset: Vec2) -> Self { self.parent = Parent::some(parent, offset); self }
+	/// Only labels wrap, anything else is returned as it was
+	#[inline] pub const fn with_wrap(mut self, wrap: bool) -> Self {
+		if let UIElementData::Label { wrap: w, .. } = &mut self.data { *w = wrap; }
+		self
+	}
 
 	// Setter methods
 	#[inline] pub const fn set_alpha(&mut self, a: u8){ self.color = self.color.with_a(a); }
@@ -305,7 +401,16 @@ impl UIElement {
 
 
 
-	#[inline] pub const fn with_style(self, style: &ElementStyle) -> Self {
+	/// Colors from the theme style `key`, kept on the element so `UIManager::apply_theme` can restyle it
+	#[inline] pub fn with_style(mut self, theme: &UITheme, key: &'static str) -> Self {
+		self.style_key = Some(key);
+		match theme.resolve(key) {
+			Some(style) => self.with_element_style(&style),
+			None => self,
+		}
+	}
+	/// Colors from a style outside the theme (inventory slots ..), a theme change leaves these alone
+	#[inline] pub const fn with_element_style(self, style: &ElementStyle) -> Self {
 		self.with_color(style.color)
 			.with_border(style.border)
 			.with_ext_color(style.text_color())
@@ -316,28 +421,100 @@ impl UIElement {
 		let corner = vec2::const_add(self.position, self.size);
 		(self.position.x, self.position.y, corner.x, corner.y)
 	}
+	/// How much narrower than at `REFERENCE_ASPECT` the element is on a window of this aspect.
+	/// Anchored elements only narrow on wider windows, on narrower ones they stretch so they stay on screen
+	#[inline] pub const fn x_scale(&self, aspect: f32) -> f32 {
+		let scale = REFERENCE_ASPECT / aspect;
+		if self.keep_aspect { scale }
+		else if matches!(self.anchor, Anchor::Stretch) || scale > 1.0 { 1.0 }
+		else { scale }
+	}
+	/// Moves and resizes the element from a window of aspect `from` to one of aspect `to`,
+	/// its anchor keeps the same distance (in pixels, per window height) from its side of the screen
+	#[inline] pub const fn fit_aspect(&mut self, from: f32, to: f32) {
+		let ratio = self.x_scale(to) / self.x_scale(from);
+		if ratio == 1.0 { return; }
+		let pivot = self.anchor.pivot();
+		self.position.x = pivot + (self.position.x - pivot) * ratio;
+		self.size.x *= ratio;
+	}
 	#[inline] pub const fn center(&self) -> (f32, f32) {
 		let center = vec2::const_add(self.position, vec2::const_mul_s(self.size, 0.5) );
 		(center.x, center.y)
 	}
-	#[inline] pub const fn contains_point(&self, x: f32, y: f32) -> bool {
+	#[inline] pub fn contains_point(&self, x: f32, y: f32) -> bool {
 		if !self.visible || !self.enabled { return false; }
 		let (min_x, min_y, max_x, max_y) = self.get_bounds();
-		x >= min_x && x <= max_x && y >= min_y && y <= max_y
+		// An open dropdown also covers its option list
+		(x >= min_x && x <= max_x && y >= min_y && y <= max_y) || self.option_at(x, y).is_some()
 	}
 	#[inline] pub const fn is_input(&self) -> bool { matches!(self.data, UIElementData::InputField { .. }) }
-
-	#[inline] pub const fn update_hover_state(&mut self, is_hovered: bool) {
+	/// Whether Tab can land on it, hidden and disabled elements are skipped
+	#[inline] pub const fn is_focusable(&self) -> bool {
+		self.visible && self.enabled && matches!(self.data,
+			UIElementData::Button { .. } |
+			UIElementData::MultiStateButton { .. } |
+			UIElementData::Checkbox { .. } |
+			UIElementData::InputField { .. } |
+			UIElementData::Dropdown { .. })
+	}
+	/// Z index it is drawn and hit tested with, an open dropdown is lifted above everything
+	#[inline] pub const fn layer_z(&self) -> i32 {
+		if self.is_open_dropdown() { DROPDOWN_OPEN_Z } else { self.z_index }
+	}
+	#[inline] pub const fn is_wrapped(&self) -> bool { matches!(self.data, UIElementData::Label { wrap: true, .. }) }
+	#[inline] pub const fn is_scroll_list(&self) -> bool { matches!(self.data, UIElementData::ScrollList { .. }) }
+	#[inline] pub const fn scroll(&self) -> f32 {
+		if let UIElementData::ScrollList { scroll, .. } = self.data { scroll } else { 0. }
+	}
+	/// Furthest a scroll list goes, 0 when everything fits
+	#[inline] pub const fn max_scroll(&self) -> f32 {
+		let UIElementData::ScrollList { content, .. } = self.data else { return 0.; };
+		if content > self.size.y { content - self.size.y } else { 0. }
+	}
+	/// Track and thumb (position, size) of a scroll list's scrollbar, None if there is nothing to scroll
+	pub fn scrollbar_rects(&self) -> Option<((Vec2, Vec2), (Vec2, Vec2))> {
+		let UIElementData::ScrollList { scroll, content } = self.data else { return None; };
+		let max = self.max_scroll();
+		if max <= 0. { return None; }
+		let (x, y, h) = (self.position.x + self.size.x - SCROLLBAR_WIDTH, self.position.y, self.size.y);
+		let thumb_h = (h * h / content).max(h * 0.1);
+		let thumb_y = y + h - thumb_h - (scroll / max) * (h - thumb_h);
+		Some(((Vec2::new(x, y), Vec2::new(SCROLLBAR_WIDTH, h)), (Vec2::new(x, thumb_y), Vec2::new(SCROLLBAR_WIDTH, thumb_h))))
+	}
+	/// Whether the point is on the scrollbar (a click there starts dragging it)
+	pub fn on_scrollbar(&self, x: f32, y: f32) -> bool {
+		let Some(((pos, size), _)) = self.scrollbar_rects() else { return false; };
+		x >= pos.x && x <= pos.x + size.x && y >= pos.y && y <= pos.y + size.y
+	}
+	/// Scroll that puts the middle of the thumb at `y`, for a click or drag on the track
+	pub fn scroll_for_thumb_at(&self, y: f32) -> f32 {
+		let Some(((track_pos, track_size), (_, thumb_size))) = self.scrollbar_rects() else { return 0.; };
+		let free = track_size.y - thumb_size.y;
+		if free <= 0. { return 0.; }
+		let from_top = track_pos.y + track_size.y - thumb_size.y / 2. - y;
+		(from_top / free).clamp(0., 1.) * self.max_scroll()
+	}
+	/// The border the renderer draws, the focus ring while focused
+	#[inline] pub const fn drawn_border(&self) -> Border {
+		if !self.focused { return self.border; }
+		let width = if self.border.width > FOCUS_RING.width { self.border.width } else { FOCUS_RING.width };
+		Border::colf(FOCUS_RING.color, width)
+	}
+
+	/// Returns whether the element looks different now (its hover color changed)
+	#[inline] pub const fn update_hover_state(&mut self, is_hovered: bool) -> bool {
 		self.hovered = is_hovered && self.enabled;
-		if self.should_handle_hover() {
-			self.color.a = if self.hovered && self.enabled {
-				Color::HOVER_ALPHA
-			} else if !self.enabled {
-				Color::HOVER_ALPHA / 2
-			} else {
-				Color::DEF_ALPHA
-			};
-		}
+		if !self.should_handle_hover() { return false; }
+		let alpha = self.color.a;
+		self.color.a = if self.hovered && self.enabled {
+			Color::HOVER_ALPHA
+		} else if !self.enabled {
+			Color::HOVER_ALPHA / 2
+		} else {
+			Color::DEF_ALPHA
+		};
+		self.color.a != alpha
 	}
 	#[inline] pub fn get_text_mut(&mut self) -> Option<&mut String> {
 		match &mut self.data {
@@ -356,6 +533,7 @@ impl UIElement {
 			UIElementData::Button { .. } |
 			UIElementData::InputField { .. } |
 			UIElementData::Slider { .. } |
+			UIElementData::Dropdown { .. } |
 			UIElementData::MultiStateButton { .. } => {
 				true
 			},
@@ -384,6 +562,9 @@ impl UIElement {
 			UIElementData::MultiStateButton { states, current_state, .. } => {
 				ElementData::Text((&states[*current_state]).to_string())
 			},
+			UIElementData::Dropdown { options, selected, .. } => {
+				ElementData::Text(options.get(*selected).map_or(String::new(), |o| o.to_string()))
+			},
 			UIElementData::Animation { frames, current_frame, .. } => {
 				ElementData::Text((&frames[*current_frame as usize]).to_string())
 			},
@@ -431,6 +612,53 @@ impl UIElement {
 			None
 		}
 	}
+	// Dropdown-related methods
+	#[inline]
+	pub fn with_selected(mut self, index: usize) -> Self {
+		self.select(index);
+		self
+	}
+	#[inline]
+	pub const fn get_selected(&self) -> Option<usize> {
+		if let UIElementData::Dropdown { selected, .. } = &self.data { Some(*selected) } else { None }
+	}
+	#[inline] pub const fn is_open_dropdown(&self) -> bool { matches!(self.data, UIElementData::Dropdown { open: true, .. }) }
+	/// Picks an option (kept inside the list), true if that changed the selection
+	#[inline]
+	pub fn select(&mut self, index: usize) -> bool {
+		let UIElementData::Dropdown { options, selected, .. } = &mut self.data else { return false; };
+		if options.is_empty() { return false; }
+		let index = if index < options.len() { index } else { options.len() - 1 };
+		let changed = *selected != index;
+		*selected = index;
+		changed
+	}
+	/// Up (negative) or down the options, for the arrow keys
+	#[inline]
+	pub fn step_selected(&mut self, step: isize) -> bool {
+		let Some(selected) = self.get_selected() else { return false; };
+		self.select(selected.saturating_add_signed(step))
+	}
+	#[inline]
+	pub const fn set_open(&mut self, open: bool) {
+		if let UIElementData::Dropdown { open: o, .. } = &mut self.data { *o = open; }
+	}
+	/// Rect (position, size) of an option row of an open dropdown, under it or over it if there is no room below
+	pub fn option_rect(&self, index: usize) -> Option<(Vec2, Vec2)> {
+		let UIElementData::Dropdown { options, open: true, .. } = &self.data else { return None; };
+		if index >= options.len() { return None; }
+		let h = self.size.y;
+		let below = self.position.y - h * options.len() as f32 >= -1.0;
+		let y = if below { self.position.y - h * (index + 1) as f32 } else { self.position.y + h * (index + 1) as f32 };
+		Some((Vec2::new(self.position.x, y), self.size))
+	}
+	/// Option row of an open dropdown under the point
+	pub fn option_at(&self, x: f32, y: f32) -> Option<usize> {
+		let UIElementData::Dropdown { options, .. } = &self.data else { return None; };
+		(0..options.len()).find(|&i| self.option_rect(i).is_some_and(|(pos, size)|
+			x >= pos.x && x <= pos.x + size.x && y >= pos.y && y <= pos.y + size.y))
+	}
+
 	// Slider-related methods
 	#[inline]
 	pub const fn with_step(mut self, step: f32) -> Self {
@@ -441,23 +669,21 @@ impl UIElement {
 	}
 	#[inline]
 	pub const fn with_value(mut self, value: f32) -> Self {
-		if let UIElementData::Slider { min_value, max_value, current_value, .. } = &mut self.data {
-			*current_value = value.clamp(*min_value, *max_value);
-		}
+		self.set_value(value);
 		self
 	}
+	/// Moves the handle under the pointer, true if the value changed (only then is a drag worth a callback)
 	#[inline]
-	pub const fn set_calc_value(&mut self, norm_x: f32, norm_y: f32) {
-		if let UIElementData::Slider { .. } = &mut self.data {
-			if let Some(value) = self.calc_value(norm_x, norm_y) {
-				self.set_value(value);
-			}
-		}
+	pub const fn set_calc_value(&mut self, norm_x: f32, norm_y: f32) -> bool {
+		let Some(value) = self.calc_value(norm_x, norm_y) else { return false; };
+		let changed = match self.get_value() { Some(old) => old != value, None => false };
+		self.set_value(value);
+		changed
 	}
 	#[inline]
 	pub const fn set_value(&mut self, value: f32) {
-		if let UIElementData::Slider { current_value, .. } = &mut self.data {
-			*current_value = value;
+		if let UIElementData::Slider { min_value, max_value, current_value, step } = &mut self.data {
+			*current_value = snap_value(value, *min_value, *max_value, *step);
 		}
 	}
 	#[inline]
@@ -467,13 +693,8 @@ impl UIElement {
 		let effective_width = self.size.x - handle_width;
 		let click_x = (norm_x - self.position.x - handle_width / 2.0).clamp(0.0, effective_width);
 		let normalized_value = click_x / effective_width;
-		let value = (*min_value + normalized_value * (*max_value - *min_value)).clamp(*min_value, *max_value);
-
-		let Some(step) = step else { return Some(value) };
-
-		let rounded_val = (value / *step + if value >= 0.0 { 0.5 } else { -0.5 }) as i32 as f32;
-		let stepped_value = (rounded_val * *step).clamp(*min_value, *max_value);
-		Some(stepped_value)
+		let value = *min_value + normalized_value * (*max_value - *min_value);
+		Some(snap_value(value, *min_value, *max_value, *step))
 	}
 	#[inline]
 	pub const fn get_value(&self) -> Option<f32> {
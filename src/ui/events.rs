@@ -1,48 +1,102 @@
 
 use crate::{
-	ext::{audio, ptr},
+	ext::{audio::{self, Sound}, ptr},
 	ui::{
 		manager::{UIManager, UIState, FocusState},
-		element::{UIElement, UIElementData},
+		element::{self, UIElement, UIElementData},
 	},
 	item::ui_inventory::ClickResult,
 	utils::input::{ClickMode, Keyboard},
 };
+use glam::Vec2;
 use winit::keyboard::{ModifiersState, KeyCode as Key};
 
+/// Distance one wheel step scrolls a list
+const SCROLL_STEP: f32 = 0.12;
+/// Room left under the last child of a scroll list
+const SCROLL_PADDING: f32 = 0.02;
+
+/// Ids Tab goes through: the top layer first (a dialog before the screen under it),
+/// then top to bottom and left to right
+pub fn focus_order(elements: &[UIElement]) -> Vec<usize> {
+	let mut order: Vec<&UIElement> = elements.iter().filter(|e| e.is_focusable()).collect();
+	order.sort_by(|a, b| b.z_index.cmp(&a.z_index)
+		.then(b.position.y.total_cmp(&a.position.y))
+		.then(a.position.x.total_cmp(&b.position.x)));
+	order.into_iter().map(|e| e.id).collect()
+}
+
+/// The id `step` places after `current` in `order`, wrapping around at both ends.
+/// Starts at the first (or last, going back) if `current` isn't in it, None if nothing can be focused
+pub fn next_focus(order: &[usize], current: usize, step: isize) -> Option<usize> {
+	if order.is_empty() { return None; }
+	let next = match order.iter().position(|id| *id == current) {
+		Some(i) => (i as isize + step).rem_euclid(order.len() as isize) as usize,
+		None if step > 0 => 0,
+		None => order.len() - 1,
+	};
+	Some(order[next])
+}
+
 impl UIManager {
 	pub fn handle_keyboard_input(&mut self, key: Key, input_str: &str) -> bool {
+		// Tab moves the focus even out of an input, the event handler does that
+		if key == Key::Tab { return false; }
 		let focus_state = self.get_focused_state();
 		if matches!(focus_state, FocusState::Input { .. }) {
 			return self.handle_key_input_on_input_field(key, input_str);
 		}
+		// The arrows go through a focused dropdown's options, open or not
+		if let Some(dropdown) = self.get_focused_element_mut().filter(|e| e.get_selected().is_some()) {
+			match key {
+				Key::ArrowUp | Key::ArrowDown => {
+					if dropdown.step_selected(if key == Key::ArrowUp { -1 } else { 1 }) { dropdown.trigger_callback(); }
+					return true;
+				}
+				Key::Escape if dropdown.is_open_dropdown() => {
+					dropdown.set_open(false);
+					return true;
+				}
+				_ => {}
+			}
+		}
 		// we do not handle other kinds because i don't think we need to ...
 
 		false
 	}
 
-	#[inline] pub fn select_next_element(&mut self) {
-		if let Some(foc) = self.get_focused_element_mut() {
-			foc.update_hover_state(false);
-		}
-		let mut id = self.get_focused_state().id() + 1;
-		let mut temp_element = self.get_element(id);
-		let mut f = 0;
-		while temp_element.is_none() || !temp_element.unwrap().should_handle_hover() {
-			if id + 1 >= self.elements.len() { id = 1; f += 1; } else { id += 1; }
-			temp_element = self.get_element(id);
-			if f > 5 { self.set_focused_state(FocusState::None); return }
-		}
-		self.set_focused_state(FocusState::Simple{ id });
-		{
-			let Some(foc) = self.get_focused_element_mut() else { return };
-			foc.update_hover_state(true);
-		}
+	#[inline] pub fn focus_next(&mut self) { self.step_focus(1); }
+	#[inline] pub fn focus_prev(&mut self) { self.step_focus(-1); }
+
+	fn step_focus(&mut self, step: isize) {
+		let order = focus_order(&self.elements);
+		let Some(id) = next_focus(&order, self.get_focused_state().id(), step) else {
+			self.clear_focused_state();
+			return;
+		};
+		let focus = if self.get_element(id).is_some_and(UIElement::is_input) { FocusState::input(id) } else { FocusState::Simple { id } };
+		self.set_focused_state(focus);
+		self.scroll_into_view(id);
 	}
-	#[inline] pub fn trigger_click_on_focused_element(&mut self) {
-		let Some(foc) = self.get_focused_element() else { return };
-		let (x,y) = foc.center();
-		self.handle_click_release(x,y);
+
+	/// Enter on the focused element does what a click on it would, inputs keep the key for themselves
+	pub fn activate_focused(&mut self) {
+		let Some(element) = self.get_focused_element_mut() else { return };
+		if !element.is_focusable() || element.is_input() { return; }
+		match &element.data {
+			UIElementData::Checkbox { .. } => element.toggle_checked(),
+			UIElementData::MultiStateButton { .. } => element.next_state(),
+			// Opens or closes the list, the arrows pick and the callback runs on picking
+			UIElementData::Dropdown { open, .. } => {
+				let open = !*open;
+				element.set_open(open);
+				audio::play_sfx(Sound::Click);
+				return;
+			}
+			_ => (),
+		}
+		audio::play_sfx(Sound::Click);
+		element.trigger_callback();
 	}
 
 	fn handle_click_press(&mut self, x: f32, y: f32) {
@@ -52,15 +106,21 @@ impl UIManager {
 				.iter()
 				.enumerate()
 				.filter(|(_, e)| e.visible && e.enabled)
-				.map(|(i, e)| (i, e.z_index))
+				.map(|(i, e)| (i, e.layer_z()))
 				.collect::<Vec<_>>();
 			temp.sort_by_key(|&(_, z)| std::cmp::Reverse(z));
 			temp
 		};
+		// A click can change any element (a checkbox, a slider, the focus ring)
+		self.mark_dirty();
+		// A click anywhere but on an open dropdown closes it
+		self.elements.iter_mut()
+			.filter(|e| e.is_open_dropdown() && !e.contains_point(x, y))
+			.for_each(|e| e.set_open(false));
 
 		for (element_index, _z_index) in active_elements {
+			if !element::hits(&self.elements, &self.elements[element_index], x, y) { continue }
 			let element: &mut UIElement = &mut self.elements[element_index];
-			if !element.contains_point(x, y) { continue }
 
 			let focus_state = match element.data {
 				UIElementData::Checkbox { .. } |
@@ -69,17 +129,36 @@ impl UIManager {
 					FocusState::Simple { id: element.id }
 				}
 				UIElementData::Slider { .. } => {
-					element.set_calc_value(x, y);
+					// Anywhere on the track, the handle jumps there
+					if element.set_calc_value(x, y) { element.trigger_callback(); }
 					FocusState::Simple { id: element.id }
 				}
 				UIElementData::InputField { .. } => {
 					element.handle_input_clicked(x, y)
 				}
+				UIElementData::Dropdown { .. } => {
+					// Clicking an option picks it, the box itself opens or closes the list
+					match element.option_at(x, y) {
+						Some(index) => {
+							element.set_open(false);
+							if element.select(index) { element.trigger_callback(); }
+						}
+						None => element.set_open(!element.is_open_dropdown()),
+					}
+					FocusState::Simple { id: element.id }
+				}
+				// Only the scrollbar, a click on the rest goes to what is under it
+				UIElementData::ScrollList { .. } if element.on_scrollbar(x, y) => {
+					let (id, scroll) = (element.id, element.scroll_for_thumb_at(y));
+					self.scroll_list_to(id, scroll);
+					self.set_focused_state(FocusState::Simple { id });
+					return
+				}
 				_ => FocusState::default(),
 			};
 
 			if focus_state.is_some() {
-				audio::set_fg("click.ogg");
+				audio::play_sfx(Sound::Click);
 				self.set_focused_state(focus_state);
 				return
 			}
@@ -87,14 +166,20 @@ impl UIManager {
 	}
 	#[inline]
 	fn handle_click_release(&mut self, x: f32, y: f32) {
+		// A slider dragged off its track still takes the value it was let go at
+		if let Some(slider) = self.get_focused_element_mut().filter(|e| matches!(e.data, UIElementData::Slider { .. })) {
+			slider.set_calc_value(x, y);
+			slider.trigger_callback();
+			return;
+		}
+		if !self.get_focused_element().is_some_and(|e| element::hits(&self.elements, e, x, y)) { return };
 		let Some(element) = self.get_focused_element_mut() else { return };
 
-		if !element.contains_point(x, y) { return };
-
 		match &element.data {
 			UIElementData::Checkbox { .. } => element.toggle_checked(),
 			UIElementData::MultiStateButton { .. } => element.next_state(),
-			UIElementData::Slider { .. } => element.set_calc_value(x, y),
+			// Its callback is for picking an option, that happened on the press
+			UIElementData::Dropdown { .. } => return,
 			_ => (),
 		}
 		element.trigger_callback();
@@ -108,6 +193,11 @@ impl UIManager {
 				
 				let Some(inv_lay) = inv.layout.as_ref() else { return };
 				let ClickResult::SlotClicked { area_type, slot } = inv_lay.handle_click(inv_state, x, y) else { return };
+				// Holding a stack over a slot that takes it starts a drag, the items are placed on release
+				if matches!(mode, ClickMode::Left | ClickMode::Right) && !modifiers.shift_key() && inv.begin_drag() {
+					if inv.drag_over(slot, area_type) { return; }
+					inv.cancel_drag();
+				}
 				inv.handle_click_press(slot, modifiers, keyboard.w, area_type, mode); // the faslse represents the "W is pressed" variable
 				
 				self.setup_ui();
@@ -116,15 +206,24 @@ impl UIManager {
 			self.clear_focused_state();
 
 			match mode {
-				ClickMode::Left => self.handle_click_press(x, y),
+				ClickMode::Left | ClickMode::Double => self.handle_click_press(x, y),
 				ClickMode::Right => {}, // self.handle_rclick_press(x, y),
 				ClickMode::Middle => {}, // self.handle_mclick_press(x, y),
 			}
 			return
 		}
 
+		if matches!(self.state, UIState::Inventory(_)) {
+			let inv = ptr::get_gamestate().player_mut().inventory_mut();
+			if inv.is_dragging() {
+				inv.end_drag(mode);
+				self.setup_ui();
+				return
+			}
+		}
+
 		match mode {
-			ClickMode::Left => self.handle_click_release(x, y),
+			ClickMode::Left | ClickMode::Double => self.handle_click_release(x, y),
 			ClickMode::Right => {}, // self.handle_rclick_release(x, y),
 			ClickMode::Middle => {}, // self.handle_mclick_release(x, y),
 		}
@@ -133,24 +232,104 @@ impl UIManager {
 	#[inline]
 	pub fn handle_mouse_move(&mut self, x: f32, y: f32, is_pressed: bool) {
 		// Update all elements - this is for hover handling
-		self.elements
+		let hovered: Vec<bool> = self.elements.iter().map(|e| element::hits(&self.elements, e, x, y)).collect();
+		let changed = self.elements
 			.iter_mut()
-			.for_each(|e| e.update_hover_state(e.contains_point(x, y)));
+			.zip(hovered)
+			.fold(false, |changed, (e, hovered)| e.update_hover_state(hovered) | changed);
+		if changed { self.mark_dirty(); }
 
 		// First check the conditions that don't need the element
-		if matches!(self.state, UIState::Inventory(_)) {
-			let inventory = ptr::get_gamestate().player().inventory();
-			let Some(item) = inventory.get_cursor() else { return; };
+		if let UIState::Inventory(inv_state) = self.state {
+			let inventory = ptr::get_gamestate().player_mut().inventory_mut();
+			let hovered = inventory.layout.as_ref().map(|l| l.handle_click(inv_state, x, y));
+			if inventory.is_dragging() {
+				if let Some(ClickResult::SlotClicked { area_type, slot }) = hovered {
+					inventory.drag_over(slot, area_type);
+				}
+			}
+			let Some(item) = inventory.get_cursor() else {
+				// Nothing held, the item under the mouse gets a tooltip
+				match hovered {
+					Some(ClickResult::SlotClicked { area_type, slot }) => match inventory.tooltip_at(area_type, slot) {
+						Some(text) => self.show_tooltip(&text, Vec2::new(x, y)),
+						None => self.hide_tooltip(),
+					},
+					_ => self.hide_tooltip(),
+				}
+				return;
+			};
 
+			self.hide_tooltip();
 			self.cursor_item_display(x,y,item);
 		}
+		// Dragging a scrollbar
+		if let Some(list) = self.get_focused_element().filter(|e| e.is_scroll_list()) {
+			if !is_pressed { return; }
+			let (id, scroll) = (list.id, list.scroll_for_thumb_at(y));
+			self.scroll_list_to(id, scroll);
+			return;
+		}
 		// Then get the element
 		let Some(element) = self.get_focused_element_mut() else { return };
 
 		if matches!(element.data, UIElementData::Slider{..}) {
 			if !is_pressed { return; } // slider only processes if the button is pressed
-			element.set_calc_value(x, y);
-			element.trigger_callback();
+			if element.set_calc_value(x, y) { element.trigger_callback(); }
+		}
+	}
+
+	/// Moves a scroll list's children by `delta` (positive goes down the list), kept inside its content
+	pub fn scroll_list_by(&mut self, id: usize, delta: f32) -> bool {
+		let Some(list) = self.get_element_mut(id) else { return false };
+		let max = list.max_scroll();
+		let UIElementData::ScrollList { scroll, .. } = &mut list.data else { return false };
+		let new_scroll = (*scroll + delta).clamp(0., max);
+		let moved = new_scroll - *scroll;
+		if moved == 0. { return false; }
+		*scroll = new_scroll;
+		// Children keep their scrolled positions, so hit testing needs nothing else
+		for child in self.elements_with_parent_mut(id) {
+			child.position.y += moved;
+		}
+		true
+	}
+	#[inline] pub fn scroll_list_to(&mut self, id: usize, scroll: f32) -> bool {
+		let current = self.get_element(id).map_or(0., UIElement::scroll);
+		self.scroll_list_by(id, scroll - current)
+	}
+
+	/// Measures a scroll list's children, after all of them were added
+	pub fn fit_scroll_list(&mut self, id: usize) {
+		let Some(list) = self.get_element(id) else { return };
+		let (top, scroll) = (list.position.y + list.size.y, list.scroll());
+		// Where the lowest child would be without scrolling
+		let bottom = self.elements_with_parent(id).iter().map(|e| e.position.y - scroll).fold(top, f32::min);
+		let content = top - bottom + SCROLL_PADDING;
+		if let Some(UIElementData::ScrollList { content: c, .. }) = self.get_element_mut(id).map(|e| &mut e.data) {
+			*c = content;
+		}
+	}
+
+	/// The wheel scrolls the list under the mouse, false if there is none
+	pub fn scroll_hovered_list(&mut self, delta: f32) -> bool {
+		let Some(id) = self.elements.iter()
+			.filter(|e| e.is_scroll_list() && e.visible && e.hovered)
+			.max_by_key(|e| e.z_index)
+			.map(|e| e.id) else { return false };
+		self.scroll_list_by(id, delta * SCROLL_STEP);
+		true
+	}
+
+	/// Scrolls the list holding the element until all of it shows, when keyboard focus lands on it
+	pub fn scroll_into_view(&mut self, id: usize) {
+		let Some(element) = self.get_element(id) else { return };
+		let Some((pos, size)) = element::clip_rect(&self.elements, element) else { return };
+		let (bottom, top, list) = (element.position.y, element.position.y + element.size.y, element.parent.id());
+		if top > pos.y + size.y {
+			self.scroll_list_by(list, pos.y + size.y - top);
+		} else if bottom < pos.y {
+			self.scroll_list_by(list, pos.y - bottom);
 		}
 	}
 
@@ -1,16 +1,23 @@
 
 use crate::{
-	ext::ptr,
+	ext::{ptr, settings::Settings, timer::FrameTimes},
 	get_string,
 	ui::{
+		chat::ChatLog,
 		dialog,
-		element::{UIElement, UIElementData, ElementData},
+		element::{Anchor, UIElement, UIElementData, ElementData, REFERENCE_ASPECT},
 		render::{UIRenderer, Vertex},
+		overlay::DebugOverlay,
+		tooltip::Tooltip,
 	},
-	utils::input::Keyboard,
-	item::ui_inventory::InventoryUIState
+	utils::{gamepad, input::{Action, InputDevice, Keyboard}},
+	item::ui_inventory::InventoryUIState,
+	world::handler,
 };
 
+/// Width of the full loading bar
+pub const LOADING_BAR_WIDTH: f32 = 0.49;
+
 #[derive(PartialEq, Clone, Copy)]
 pub struct UIStateID(u32);
 
@@ -38,6 +45,7 @@ impl UIStateID {
 			UIState::Error(..) => UIStateID(10),
 			UIState::ConnectLocal => UIStateID(11),
 			UIState::Inventory(_) => UIStateID(12),
+			UIState::Controls(..) => UIStateID(13),
 			_ => UIStateID(0),
 		}
 	}
@@ -55,6 +63,7 @@ pub enum UIState {
 	Escape,
 	InGame,
 	Settings(UIStateID),
+	Controls(UIStateID), // opened from the settings, holds the screen they go back to
 	Loading,
 	Confirm(UIStateID, u8),
 	Error(UIStateID, u8),
@@ -79,6 +88,7 @@ impl UIState {
 			10 => UIState::Error(UIStateID::default(), 0),
 			11 => UIState::ConnectLocal,
 			12 => UIState::Inventory(InventoryUIState::default()),
+			13 => UIState::Controls(UIStateID::default()),
 			_ => UIState::None,
 		}
 	}
@@ -119,15 +129,32 @@ pub fn close_pressed() {
 			state.toggle_mouse_capture();
 		},
 		UIState::NewWorld => state.ui_manager.state = UIState::WorldSelection,
-		UIState::Error(prev_state, dialog_id) | UIState::Confirm(prev_state, dialog_id) => {
+		UIState::Error(prev_state, dialog_id) => {
 			state.ui_manager.dialogs.cancel_dialog(dialog_id);
 			state.ui_manager.state = UIState::from(prev_state);
 		},
-		UIState::Settings(prev_state) => state.ui_manager.state = UIState::from(prev_state),
+		// Backing out of a question is a no
+		UIState::Confirm(..) => return state.ui_manager.answer_confirm(false),
+		UIState::Settings(prev_state) => {
+			let settings = ptr::get_settings();
+			if let Err(e) = settings.save(&Settings::path()) { println!("Settings not saved: {}", e); }
+			state.ui_manager.state = UIState::from(prev_state);
+		},
+		UIState::Controls(prev_state) => {
+			// Escape while waiting for a key only stops waiting
+			if state.ui_manager.rebinding.take().is_none() {
+				state.ui_manager.state = UIState::Settings(prev_state);
+			}
+		},
 		UIState::ConnectLocal => state.ui_manager.state = UIState::WorldSelection,
 		UIState::Inventory(_) => {
 			let focus_state = state.ui_manager.get_focused_state();
 			let inv = ptr::get_gamestate().player_mut().inventory_mut();
+			// Escape while dragging only cancels the drag
+			if inv.cancel_drag() {
+				state.ui_manager.setup_ui();
+				return
+			}
 			if matches!(focus_state, FocusState::CursorItem { .. }) {
 				let mut itm = inv.remove_cursor().unwrap(); // already checked
 				inv.add_item_anywhere(&mut itm);
@@ -169,6 +196,26 @@ pub struct UIManager {
 	renderer: UIRenderer,
 	// extra for double callbacks
 	pub dialogs: dialog::DialogManager,
+	// yes/no dialogs open over each other, the last one is shown
+	pub confirms: Vec<dialog::ConfirmDialog>,
+	// in game chat, kept across ui rebuilds
+	pub chat: ChatLog,
+	// label and bar of the loading screen, 0 while it isn't shown
+	pub loading_ids: (usize, usize),
+	// what the mouse rests on, shown after a delay
+	pub tooltip: Tooltip,
+	// performance stats in the corner, kept across ui rebuilds
+	pub debug_overlay: DebugOverlay,
+	// action waiting for a key on the controls screen
+	pub rebinding: Option<Action>,
+	// why the last key couldn't be bound, shown on the controls screen
+	pub binding_note: String,
+	// device the help texts name the keys or buttons of, the menu is rebuilt when another one is used
+	pub prompt_device: InputDevice,
+	// width / height of the window, anchored elements are fitted to it
+	aspect: f32,
+	// elements changed since the vertex and index buffers were written
+	dirty: bool,
 	// helper stuff, mainly for init
 	next_id: usize,
 }
@@ -224,41 +271,7 @@ impl UIManager {
 	#[inline]
 	pub fn new(device: &wgpu::Device, config: &wgpu::SurfaceConfiguration, queue: &wgpu::Queue) -> Self {
 		let renderer = UIRenderer::new(device, queue);
-		let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-			bind_group_layouts: &[renderer.bind_group_layout(), renderer.uniform_bind_group_layout()],
-			..Default::default()
-		});
-
-		let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
-			label: Some("UI Shader"),
-			source: wgpu::ShaderSource::Wgsl(std::borrow::Cow::from(get_string!("ui_shader.wgsl"))),
-		});
-
-		let ui_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-			label: Some("UI Pipeline"),
-			layout: Some(&pipeline_layout),
-			vertex: wgpu::VertexState {
-				module: &shader,
-				entry_point: Some("vs_main"),
-				buffers: &[Vertex::desc()],
-				compilation_options: Default::default(),
-			},
-			fragment: Some(wgpu::FragmentState {
-				module: &shader,
-				entry_point: Some("fs_main"),
-				targets: &[Some(wgpu::ColorTargetState {
-					format: config.format,
-					blend: Some(wgpu::BlendState::ALPHA_BLENDING),
-					write_mask: wgpu::ColorWrites::ALL,
-				})],
-				compilation_options: Default::default(),
-			}),
-			depth_stencil : None,
-			primitive: wgpu::PrimitiveState::default(),
-			multisample: wgpu::MultisampleState::default(),
-			multiview: None,
-			cache: None,
-		});
+		let ui_pipeline = create_ui_pipeline(device, config.format, &renderer);
 
 		let vertex_buffer = device.create_buffer(&wgpu::BufferDescriptor {
 			label: Some("UI Vertex Buffer"),
@@ -283,19 +296,73 @@ impl UIManager {
 			focused_state: FocusState::default(),
 			visibility: true,
 			dialogs: dialog::DialogManager::new(),
+			confirms: Vec::new(),
+			chat: ChatLog::new(),
+			loading_ids: (0, 0),
+			tooltip: Tooltip::new(),
+			debug_overlay: DebugOverlay::new(),
+			rebinding: None,
+			binding_note: String::new(),
+			prompt_device: InputDevice::KeyboardMouse,
+			aspect: config.width.max(1) as f32 / config.height.max(1) as f32,
+			dirty: true,
 			renderer,
 			next_id: 1,
 		}
 	}
 
 	#[inline] pub const fn renderer(&self) -> &UIRenderer { &self.renderer }
-	#[inline] pub const fn renderer_mut(&mut self) -> &mut UIRenderer { &mut self.renderer }
+	/// Builds the pipeline again from the shader source, the old one stays if the new shader doesn't compile
+	pub fn reload_shader(&mut self, device: &wgpu::Device, format: wgpu::TextureFormat) -> Result<(), String> {
+		self.pipeline = crate::render::pipeline::catch_validation(device, || create_ui_pipeline(device, format, &self.renderer))?;
+		self.mark_dirty();
+		Ok(())
+	}
+	/// The renderer's textures may change (a new font), so the mesh is rebuilt
+	#[inline] pub const fn renderer_mut(&mut self) -> &mut UIRenderer { self.dirty = true; &mut self.renderer }
+
+	/// `render` draws from the elements as they are now, so anything that changes how they look has to call this
+	#[inline] pub const fn mark_dirty(&mut self) { self.dirty = true; }
+	#[inline] pub const fn is_dirty(&self) -> bool { self.dirty }
+
+	/// Switches to the theme: what's on screen is recolored in place and the menus built later use it
+	pub fn apply_theme(&mut self, theme: &UITheme) {
+		let settings = ptr::get_settings();
+		for element in self.elements.iter_mut() {
+			let Some(key) = element.style_key else { continue; };
+			let Some((color, border, ext_color)) = settings.ui_theme.restyle(theme, key, element.border) else { continue; };
+			element.color = color;
+			element.border = border;
+			element.ext_color = ext_color;
+		}
+		settings.ui_theme = theme.clone();
+		self.mark_dirty();
+	}
+	
+	/// What to press for `action` on `prompt_device`, the gamepad button or else the bound key
+	pub fn prompt(&self, action: Action) -> String {
+		match (self.prompt_device, gamepad::button_label(action)) {
+			(InputDevice::Gamepad, Some(button)) => button.to_string(),
+			_ => ptr::get_settings().key_bindings.get(action).label(),
+		}
+	}
+	/// The help texts follow the device used last, only the pause menu shows them
+	pub fn set_prompt_device(&mut self, device: InputDevice) {
+		if self.prompt_device == device { return; }
+		self.prompt_device = device;
+		if matches!(self.state, UIState::Escape) { self.setup_ui(); }
+	}
 	
 	#[inline]
-	pub fn update(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, delta: f32) {
+	pub fn update(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, delta: f32, frame_times: &FrameTimes) {
+		// Animations only change the frame uniform written while rendering, not the mesh
 		self.update_anim(delta);
+		self.update_loading();
+		self.update_tooltip(delta);
+		self.update_debug_overlay(delta, frame_times);
 
-		if true { // decided to remove the condition ...
+		if self.dirty {
+			self.dirty = false;
 			self.remake_mesh(device, queue);
 		}
 	}
@@ -313,6 +380,27 @@ impl UIManager {
 		}
 	}
 	
+	/// Moves the loading bar along with the spawn chunks, the game starts (paused) once they are all there
+	fn update_loading(&mut self) {
+		if !matches!(self.state, UIState::Loading) { return; }
+		let Some((done, total)) = handler::loading_progress() else { return; };
+		if total > 0 && done >= total {
+			handler::finish_loading();
+			self.state = UIState::Escape;
+			self.setup_ui();
+			return;
+		}
+		let (label_id, bar_id) = self.loading_ids;
+		let fraction = if total == 0 { 0. } else { done as f32 / total as f32 };
+		let aspect = self.aspect;
+		if let Some(bar) = self.get_element_mut(bar_id) {
+			bar.size.x = LOADING_BAR_WIDTH * fraction.max(0.02) * bar.x_scale(aspect);
+		}
+		if let Some(text) = self.get_element_mut(label_id).and_then(|e| e.get_text_mut()) {
+			*text = format!("Loading... {}/{} chunks", done, total);
+		}
+	}
+
 	#[inline]
 	fn update_anim(&mut self, delta: f32) {
 		self.elements.iter_mut()
@@ -320,12 +408,34 @@ impl UIManager {
 			.for_each(|e| e.update_anim(delta));
 	}
 	
+	#[inline] pub const fn aspect(&self) -> f32 { self.aspect }
+	/// The window changed shape, anchored elements are moved and resized to keep theirs
+	pub fn set_aspect(&mut self, aspect: f32) {
+		if aspect <= 0.0 || aspect == self.aspect { return; }
+		let old = self.aspect;
+		self.aspect = aspect;
+		self.dirty = true;
+		self.elements.iter_mut().for_each(|e| e.fit_aspect(old, aspect));
+	}
+	/// Menus keep their 16:9 proportions in the middle of wider windows, the full screen background still stretches
+	pub fn anchor_menu(&mut self) {
+		let aspect = self.aspect;
+		self.dirty = true;
+		for element in self.elements.iter_mut().filter(|e| e.anchor == Anchor::Stretch && e.size.x < 2.0) {
+			element.anchor = Anchor::Center;
+			element.fit_aspect(REFERENCE_ASPECT, aspect);
+		}
+	}
+
+	/// Elements are given in `REFERENCE_ASPECT` coordinates, anchored ones are fitted to the window as they are added
 	#[inline]
 	pub fn add_element(&mut self, mut element: UIElement) -> usize {
+		element.fit_aspect(REFERENCE_ASPECT, self.aspect);
 		if element.id == 0 {
 			element.id = self.next_id;
 			self.next_id += 1;
 		}
+		self.dirty = true;
 		self.elements.push(element);
 		self.next_id-1
 	}
@@ -340,25 +450,38 @@ impl UIManager {
 			}
 		}
 		self.elements.remove(element_id);
+		self.dirty = true;
 		true
 	}
 
 	#[inline] pub fn get_element(&self, id: usize) -> Option<&UIElement> { self.elements.iter().find(|e| e.id == id) }
-	#[inline] pub fn get_element_mut(&mut self, id: usize) -> Option<&mut UIElement> { self.elements.iter_mut().find(|e| e.id == id) }
+	/// Handing out an element counts as changing it
+	#[inline] pub fn get_element_mut(&mut self, id: usize) -> Option<&mut UIElement> { self.dirty = true; self.elements.iter_mut().find(|e| e.id == id) }
 
 	#[inline] pub fn visible_elements(&self) -> Vec<&UIElement> { self.elements.iter().filter(|e| e.visible).collect() }
 
 	#[inline] pub fn elements_with_parent(&self, parent: usize) -> Vec<&UIElement> { self.elements.iter().filter(|e| e.parent.is_some() && e.parent.id() == parent).collect() }
-	#[inline] pub fn elements_with_parent_mut(&mut self, parent: usize) -> Vec<&mut UIElement> { self.elements.iter_mut().filter(|e| e.parent.is_some() && e.parent.id() == parent).collect() }
+	#[inline] pub fn elements_with_parent_mut(&mut self, parent: usize) -> Vec<&mut UIElement> { self.dirty = true; self.elements.iter_mut().filter(|e| e.parent.is_some() && e.parent.id() == parent).collect() }
 	 
-	#[inline] pub fn clear_elements(&mut self) { self.elements.clear(); self.clear_focused_state(); self.next_id = 1; }
+	#[inline] pub fn clear_elements(&mut self) { self.dirty = true; self.elements.clear(); self.clear_focused_state(); self.tooltip = Tooltip::new(); self.next_id = 1; }
 		
-	#[inline] pub const fn clear_focused_state(&mut self) { self.focused_state = FocusState::default(); }
+	#[inline] pub fn clear_focused_state(&mut self) { self.set_focused_state(FocusState::default()); }
 		
-	#[inline] pub const fn toggle_visibility(&mut self) { self.visibility = !self.visibility; }
+	#[inline] pub const fn toggle_visibility(&mut self) { self.visibility = !self.visibility; self.dirty = true; }
 	#[inline] pub const fn focused_is_some(&self) -> bool { if self.focused_state.is_some() { true } else { false } }
 
-	#[inline] pub fn set_focused_state(&mut self, focused_state: FocusState) { self.focused_state = focused_state }
+	#[inline] pub fn set_focused_state(&mut self, focused_state: FocusState) { self.focused_state = focused_state; self.mark_focused(); }
+	/// Moves the focus ring to the focused button or input, the inventory states don't get one
+	fn mark_focused(&mut self) {
+		let id = match self.focused_state {
+			FocusState::Simple { id } | FocusState::Input { id, .. } => id,
+			_ => 0,
+		};
+		self.dirty = true;
+		for element in &mut self.elements {
+			element.focused = id != 0 && element.id == id && element.is_focusable();
+		}
+	}
 	#[inline] pub fn get_focused_state(&self) -> &FocusState { &self.focused_state }
 
 	#[inline] pub fn get_focused_element(&self) -> Option<&UIElement> { if self.focused_state.is_some() { self.get_element(self.focused_state.id()) } else { None } }
@@ -393,3 +516,51 @@ pub fn get_element_num_by_id(id: &usize) -> f32 {
 		.and_then(|data| data.num())
 		.unwrap_or(0.)
 }
+/// Option picked in a dropdown, 0 if there is no such dropdown
+#[inline]
+pub fn get_element_selected_by_id(id: &usize) -> usize {
+	ptr::get_state()
+		.ui_manager()
+		.get_element(*id)
+		.and_then(|element| element.get_selected())
+		.unwrap_or(0)
+}
+
+/// The UI pipeline from the current `ui_shader.wgsl`
+fn create_ui_pipeline(device: &wgpu::Device, format: wgpu::TextureFormat, renderer: &UIRenderer) -> wgpu::RenderPipeline {
+	let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+		bind_group_layouts: &[renderer.bind_group_layout(), renderer.uniform_bind_group_layout()],
+		..Default::default()
+	});
+
+	let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+		label: Some("UI Shader"),
+		source: wgpu::ShaderSource::Wgsl(std::borrow::Cow::from(get_string!("ui_shader.wgsl"))),
+	});
+
+	device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+		label: Some("UI Pipeline"),
+		layout: Some(&pipeline_layout),
+		vertex: wgpu::VertexState {
+			module: &shader,
+			entry_point: Some("vs_main"),
+			buffers: &[Vertex::desc()],
+			compilation_options: Default::default(),
+		},
+		fragment: Some(wgpu::FragmentState {
+			module: &shader,
+			entry_point: Some("fs_main"),
+			targets: &[Some(wgpu::ColorTargetState {
+				format,
+				blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+				write_mask: wgpu::ColorWrites::ALL,
+			})],
+			compilation_options: Default::default(),
+		}),
+		depth_stencil : None,
+		primitive: wgpu::PrimitiveState::default(),
+		multisample: wgpu::MultisampleState::default(),
+		multiview: None,
+		cache: None,
+	})
+}
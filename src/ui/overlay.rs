@@ -0,0 +1,164 @@
+use crate::{
+	block::{main::Chunk, storage::StorageType},
+	ext::{ptr, timer::{self, FrameSummary, FrameTimes, Section}},
+	ui::{
+		element::UIElement,
+		manager::UIManager,
+		text::WRAP_LINE_HEIGHT,
+	},
+	utils::color::Solor,
+};
+use glam::Vec2;
+use std::sync::atomic::Ordering;
+
+/// Seconds between two refreshes of the text, every new text is a new texture so it isn't done every frame
+pub const OVERLAY_REFRESH: f32 = 0.25;
+/// Lines of `DebugStats::text`
+const OVERLAY_LINES: usize = 6;
+const OVERLAY_WIDTH: f32 = 0.9;
+const OVERLAY_PADDING: f32 = 0.015;
+/// Over the game and the menus, under tooltips and open dropdowns
+const OVERLAY_Z_INDEX: i32 = 1000;
+
+const STORAGE_TYPES: [StorageType; 6] = [
+	StorageType::Uniform, StorageType::Compact, StorageType::Sparse,
+	StorageType::Giant, StorageType::Zigzag, StorageType::Rle,
+];
+
+/// How many chunks use each storage, in `StorageType` order
+pub fn storage_counts<'a>(chunks: impl IntoIterator<Item = &'a Chunk>) -> [usize; 6] {
+	let mut counts = [0; 6];
+	for chunk in chunks {
+		counts[chunk.storage().to_type().as_u8() as usize] += 1;
+	}
+	counts
+}
+
+/// What the overlay shows, gathered once per refresh
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DebugStats {
+	pub fps: f32,
+	pub frame_ms: f32, // average since the last refresh
+	pub chunks: usize,
+	pub loaded: usize,
+	pub queued: Option<usize>, // chunks waiting to generate, None if the queue was busy
+	pub workers: usize, // generation workers busy right now
+	pub storage: [usize; 6], // chunks per `StorageType`
+	pub sections: [f32; 3], // milliseconds per `Section`
+	pub frames: FrameSummary, // percentiles and stutters of the recent frames
+}
+
+impl DebugStats {
+	pub fn text(&self) -> String {
+		let queued = self.queued.map_or("?".to_string(), |n| n.to_string());
+		let storage: Vec<String> = STORAGE_TYPES.iter().zip(self.storage)
+			.filter(|(_, n)| *n > 0)
+			.map(|(kind, n)| format!("{:?} {}", kind, n))
+			.collect();
+		let sections: Vec<String> = Section::ALL.iter().zip(self.sections)
+			.map(|(section, ms)| format!("{} {:.2}ms", section.name(), ms))
+			.collect();
+		format!(
+			"{:.0} fps ({:.2}ms)\n{}\nChunks: {} ({} loaded)\nGenerating: {} queued, {} workers\nStorage: {}\n{}",
+			self.fps, self.frame_ms, self.frames_line(), self.chunks, self.loaded, queued, self.workers,
+			if storage.is_empty() { "-".to_string() } else { storage.join(", ") },
+			sections.join(", "),
+		)
+	}
+	/// p50/p95/p99, the frames over budget and the worst one with how long ago it was
+	fn frames_line(&self) -> String {
+		let f = &self.frames;
+		format!(
+			"p50 {:.1} p95 {:.1} p99 {:.1}ms, {} over {:.1}ms, worst {:.1}ms {:.1}s ago",
+			f.p50, f.p95, f.p99, f.over_budget, f.budget_ms, f.worst_ms, f.worst_ago,
+		)
+	}
+}
+
+/// Whether the overlay is on and the frames counted since its last refresh
+#[derive(Debug, Clone, Default)]
+pub struct DebugOverlay {
+	shown: bool,
+	frames: u32,
+	elapsed: f32, // seconds since the last refresh
+	label_id: usize, // 0 while not on screen
+}
+
+impl DebugOverlay {
+	#[inline] pub const fn new() -> Self {
+		Self { shown: false, frames: 0, elapsed: 0., label_id: 0 }
+	}
+	#[inline] pub const fn is_shown(&self) -> bool { self.shown }
+
+	/// Counts a frame, returns (fps, average frame time in ms) once `OVERLAY_REFRESH` has passed
+	pub fn advance(&mut self, delta: f32) -> Option<(f32, f32)> {
+		self.frames += 1;
+		self.elapsed += delta;
+		if self.elapsed < OVERLAY_REFRESH { return None; }
+		let frames = self.frames as f32;
+		let stats = (frames / self.elapsed, self.elapsed * 1000. / frames);
+		self.frames = 0;
+		self.elapsed = 0.;
+		Some(stats)
+	}
+}
+
+/// Only counts and atomics, the generation queue is skipped if a worker holds it
+fn gather_stats(fps: f32, frame_ms: f32, frames: FrameSummary) -> DebugStats {
+	let mut stats = DebugStats { fps, frame_ms, frames, sections: Section::ALL.map(timer::section_ms), ..Default::default() };
+	if !ptr::has_gamestate() { return stats; }
+	let world = ptr::get_gamestate().world();
+	stats.chunks = world.chunks.len();
+	stats.loaded = world.loaded_chunks.len();
+	stats.queued = world.chunk_generation_queue.try_lock().ok().map(|queue| queue.len());
+	stats.workers = world.active_workers.load(Ordering::Relaxed);
+	stats.storage = storage_counts(world.chunks.values());
+	stats
+}
+
+impl UIManager {
+	pub fn toggle_debug_overlay(&mut self) {
+		let shown = !self.debug_overlay.shown;
+		self.debug_overlay = DebugOverlay { shown, ..DebugOverlay::new() };
+		timer::set_timing(shown);
+		self.setup_ui();
+	}
+
+	/// Top left corner, added after every rebuild of the ui while it's on
+	pub fn setup_debug_overlay(&mut self) {
+		self.debug_overlay.label_id = 0;
+		if !self.debug_overlay.shown { return; }
+		let theme = &ptr::get_settings().ui_theme;
+		let size = Vec2::new(OVERLAY_WIDTH, OVERLAY_LINES as f32 * WRAP_LINE_HEIGHT + OVERLAY_PADDING * 2.);
+		let pos = Vec2::new(-0.99, 0.99 - size.y);
+
+		let panel = UIElement::panel(self.next_id())
+			.with_position(pos)
+			.with_size(size)
+			.with_color(Solor::Black.i().with_a(160))
+			.with_z_index(OVERLAY_Z_INDEX);
+		self.add_element(panel);
+		let label_id = self.next_id();
+		let label = UIElement::label(label_id, "...".into())
+			.with_position(pos + Vec2::splat(OVERLAY_PADDING))
+			.with_size(size - Vec2::splat(OVERLAY_PADDING * 2.))
+			.with_style(theme, "labels.basic")
+			.with_wrap(true)
+			.with_z_index(OVERLAY_Z_INDEX + 1);
+		self.add_element(label);
+		self.debug_overlay.label_id = label_id;
+	}
+
+	/// Runs once a frame, the text only changes every `OVERLAY_REFRESH`
+	pub fn update_debug_overlay(&mut self, delta: f32, frame_times: &FrameTimes) {
+		if !self.debug_overlay.shown || self.debug_overlay.label_id == 0 { return; }
+		let Some((fps, frame_ms)) = self.debug_overlay.advance(delta) else { return; };
+		let text = gather_stats(fps, frame_ms, frame_times.summary()).text();
+		let label_id = self.debug_overlay.label_id;
+		// The old text won't come back, its texture goes
+		if let Some(label) = self.get_element(label_id).cloned() { self.renderer_mut().forget_text(&label); }
+		if let Some(old) = self.get_element_mut(label_id).and_then(|e| e.get_text_mut()) {
+			*old = text;
+		}
+	}
+}
@@ -3,7 +3,7 @@ use crate::ui::text::{TruncateMode, AlignMode};
 use crate::utils::color::Color;
 use crate::fs::rs;
 use crate::ext::ptr;
-use crate::ui::element::{UIElement, UIElementData};
+use crate::ui::element::{self, UIElement, UIElementData};
 use crate::ui::manager::{UIManager};
 use rusttype::Font;
 use glam::Vec2;
@@ -148,11 +148,18 @@ impl UIRenderer {
 	#[inline] pub fn clear_text(&mut self) {
 		self.text_textures.clear();
 	}
+	/// Drops the texture of the element's current text, for text that changes all the time and won't be shown again
+	#[inline] pub fn forget_text(&mut self, element: &UIElement) {
+		let Some(text) = element.get_element_data().text() else { return; };
+		let key = self.text_key(element, &text);
+		self.text_textures.remove(&key);
+	}
 	
 	#[inline] 
-	pub fn process_elements(&mut self, elements: &[UIElement]) -> (Vec<Vertex>, Vec<u32>) {
-		let mut elements: Vec<_> = elements.iter().filter(|e| e.visible).collect();
-		elements.sort_by_key(|e| e.z_index);
+	pub fn process_elements(&mut self, all: &[UIElement]) -> (Vec<Vertex>, Vec<u32>) {
+		// Children scrolled out of their list are skipped, `render` skips the same ones
+		let mut elements: Vec<_> = all.iter().filter(|e| e.visible && element::in_clip(all, e)).collect();
+		elements.sort_by_key(|e| e.layer_z());
 		let mut mesh_data = MeshData {
 			v: Vec::with_capacity(elements.len() * 8),
 			i: Vec::with_capacity(elements.len() * 12),
@@ -160,7 +167,7 @@ impl UIRenderer {
 		};
 
 		for element in elements {
-			if element.border.width > 0.0 {
+			if element.drawn_border().width > 0.0 {
 				self.process_border(element, &mut mesh_data);
 			}
 			match &element.data {
@@ -179,6 +186,8 @@ impl UIRenderer {
 					self.process_rect_element(element, &mut mesh_data);
 					self.process_text_element(element, element.get_element_data().text(), &mut mesh_data);
 				}
+				UIElementData::ScrollList { .. } => self.process_scroll_list(element, &mut mesh_data),
+				UIElementData::Dropdown { .. } => self.process_dropdown(element, &mut mesh_data),
 				_ => self.process_rect_element(element, &mut mesh_data),
 			}
 		}
@@ -186,14 +195,25 @@ impl UIRenderer {
 	}
 
 
+	/// Key of an element's text texture, a wrapped one also keeps the width it was wrapped to
+	#[inline] fn text_key(&self, element: &UIElement, text: &str) -> String {
+		if !element.is_wrapped() { return format!("{}_{:?}", text, element.ext_color); }
+		let wrap_px = (element.size.x * 100.0 * self.pixel_ratio_for(element.size)) as u32;
+		format!("{}_{:?}_w{}", text, element.ext_color, wrap_px)
+	}
+
 	#[inline] 
 	fn process_text_element(&mut self, element: &UIElement, text: Option<String>, mesh: &mut MeshData) {
 		let state = ptr::get_state();
 		
 		if let Some(text) = text {
-			let texture_key = format!("{}_{:?}", text, element.ext_color);
+			let texture_key = self.text_key(element, &text);
 			if !self.text_textures.contains_key(&texture_key) {
-				let texture = self.render_text_to_texture(state.device(), state.queue(), &text, element.size, element.ext_color, TruncateMode::default(), AlignMode::default());
+				let texture = if element.is_wrapped() {
+					self.render_wrapped_text_to_texture(state.device(), state.queue(), &text, element.size, element.ext_color, AlignMode::Lt)
+				} else {
+					self.render_text_to_texture(state.device(), state.queue(), &text, element.size, element.ext_color, TruncateMode::default(), AlignMode::default())
+				};
 				let texture_view = texture.create_view(&wgpu::TextureViewDescriptor {
 					dimension: Some(wgpu::TextureViewDimension::D2Array), ..Default::default() });
 				let bind_group = state.device().create_bind_group(&wgpu::BindGroupDescriptor {
@@ -207,13 +227,18 @@ impl UIRenderer {
 				self.text_textures.insert(texture_key.clone(), (texture, bind_group));
 			}
 
-			let pix = if element.size.x + element.size.y < 0.2 { self.pixel_ratio * 3.0 } else { self.pixel_ratio };
+			let pix = self.pixel_ratio_for(element.size);
 			if let Some((texture, _)) = self.text_textures.get(&texture_key) {
 				let pixel_to_unit = 1.0 / (100.0 * pix);
 				let tex_w = texture.width() as f32 * pixel_to_unit;
 				let tex_h = texture.height() as f32 * pixel_to_unit;
 				let real_x = element.position.x + (element.size.x - tex_w) / 2.0;
-				let real_y = element.position.y + (element.size.y - tex_h) / 2.0;
+				// Wrapped text starts at the top, the lines run down from there
+				let real_y = if element.is_wrapped() {
+					element.position.y + element.size.y - tex_h
+				} else {
+					element.position.y + (element.size.y - tex_h) / 2.0
+				};
 				self.proc_rect_element(Vec2::new(real_x, real_y), Vec2::new(tex_w, tex_h), element.ext_color, mesh);
 			}
 		}
@@ -239,6 +264,26 @@ impl UIRenderer {
 		}
 	}
 
+	/// Background, then the track and thumb if there is anything to scroll
+	fn process_scroll_list(&mut self, element: &UIElement, mesh: &mut MeshData) {
+		self.process_rect_element(element, mesh);
+		let Some(((track_pos, track_size), (thumb_pos, thumb_size))) = element.scrollbar_rects() else { return; };
+		self.proc_rect_element(track_pos, track_size, element.ext_color.with_a(60), mesh);
+		self.proc_rect_element(thumb_pos, thumb_size, element.ext_color, mesh);
+	}
+
+	/// The box with the selected option, then a row per option while open (the selected one lighter)
+	fn process_dropdown(&mut self, element: &UIElement, mesh: &mut MeshData) {
+		self.process_rect_element(element, mesh);
+		self.process_text_element(element, element.get_element_data().text(), mesh);
+		let selected = element.get_selected().unwrap_or(0);
+		for (i, row) in dropdown_rows(element).iter().enumerate() {
+			let color = if i == selected { element.color.with_a(255) } else { element.color };
+			self.proc_rect_element(row.position, row.size, color, mesh);
+			self.process_text_element(row, row.get_element_data().text(), mesh);
+		}
+	}
+
 	#[inline] fn process_image_element(&mut self, element: &UIElement, mesh: &mut MeshData) {
 		if let UIElementData::Image { path } = &element.data {
 			let state = ptr::get_state();			
@@ -355,7 +400,7 @@ impl UIRenderer {
 			let label_element = UIElement {
 				position: Vec2::new(element.position.x + element.size.x + 0.01, element.position.y),
 				size: Vec2::new(text.len() as f32 * 0.015, element.size.y),
-				data: UIElementData::Label { text: text.clone().into() },
+				data: UIElementData::Label { text: text.clone().into(), wrap: false },
 				color: element.ext_color,
 				ext_color: element.ext_color,
 				event_handler: None,
@@ -368,12 +413,13 @@ impl UIRenderer {
 	#[inline] fn process_border(&self, element: &UIElement, mesh: &mut MeshData) {
 			let (x, y) = (element.position.x, element.position.y);
 			let (w, h) = (element.size.x, element.size.y);
-		let border_width = element.border.width;
+		let border = element.drawn_border();
+		let border_width = border.width;
 		let border_x = x - border_width;
 		let border_y = y - border_width;
 		let border_w = w + 2.0 * border_width;
 		let border_h = h + 2.0 * border_width;
-		self.proc_rect_element(Vec2::new(border_x, border_y), Vec2::new(border_w, border_h), element.border.color, mesh);
+		self.proc_rect_element(Vec2::new(border_x, border_y), Vec2::new(border_w, border_h), border.color, mesh);
 	}
 
 	#[inline] fn process_rect_element(&self, element: &UIElement, mesh: &mut MeshData) {
@@ -409,8 +455,11 @@ impl UIRenderer {
 		r_pass.set_index_buffer(ui_manager.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
 		r_pass.set_bind_group(1, &self.uniform_bind_group, &[]);
 		let mut i_off:u32 = 0;
-		let mut sorted_elements = ui_manager.visible_elements();
-		sorted_elements.sort_by_key(|e| e.z_index);
+		let all = &ui_manager.elements;
+		let mut sorted_elements: Vec<_> = ui_manager.visible_elements().into_iter().filter(|e| element::in_clip(all, e)).collect();
+		sorted_elements.sort_by_key(|e| e.layer_z());
+		let size = *ptr::get_state().size();
+		let mut clipped = false;
 
 		#[inline] fn draw_six_set<'a>(r_pass: &mut wgpu::RenderPass<'a>, bind_g_idx:u32, bind_group: &wgpu::BindGroup, i_off: &mut u32) {
 			set_bind(r_pass, bind_g_idx, bind_group);
@@ -425,7 +474,20 @@ impl UIRenderer {
 		}
 		
 		for element in sorted_elements {
-			if element.border.width > 0.0 {
+			// Children of a scroll list are cut to it, everything else draws on the whole screen
+			match element::clip_rect(all, element) {
+				Some(clip) => {
+					let (x, y, w, h) = scissor_rect(clip, size.width, size.height);
+					r_pass.set_scissor_rect(x, y, w, h);
+					clipped = true;
+				}
+				None if clipped => {
+					r_pass.set_scissor_rect(0, 0, size.width, size.height);
+					clipped = false;
+				}
+				None => {}
+			}
+			if element.drawn_border().width > 0.0 {
 				draw_six_set(r_pass, 0, &self.default_bind_group, &mut i_off);
 			}
 			match &element.data {
@@ -450,7 +512,7 @@ impl UIRenderer {
 						draw_six(r_pass, &mut i_off);
 					}
 					if let Some(text) = element.get_element_data().text() {
-						let texture_key = format!("{}_{:?}", text, element.ext_color);
+						let texture_key = self.text_key(element, &text);
 						if let Some((_, bind_group)) = self.text_textures.get(&texture_key) {
 							draw_six_set(r_pass, 0, &bind_group, &mut i_off);
 						}
@@ -467,7 +529,7 @@ impl UIRenderer {
 				UIElementData::MultiStateButton { .. } => {
 					draw_six_set(r_pass, 0, &self.default_bind_group, &mut i_off);
 					if let Some(text) = element.get_element_data().text() {
-						let texture_key = format!("{}_{:?}", text, element.ext_color);
+						let texture_key = self.text_key(element, &text);
 						if let Some((_, bind_group)) = self.text_textures.get(&texture_key) {
 							draw_six_set(r_pass, 0, &bind_group, &mut i_off);
 						}
@@ -476,9 +538,30 @@ impl UIRenderer {
 				UIElementData::Panel { .. } => {
 					draw_six_set(r_pass, 0, &self.default_bind_group, &mut i_off);
 				},
+				UIElementData::Dropdown { .. } => {
+					// The box, then every option row, each with its text
+					let rows = dropdown_rows(element);
+					for row in std::iter::once(element).chain(rows.iter()) {
+						draw_six_set(r_pass, 0, &self.default_bind_group, &mut i_off);
+						if let Some(text) = row.get_element_data().text() {
+							let texture_key = self.text_key(row, &text);
+							if let Some((_, bind_group)) = self.text_textures.get(&texture_key) {
+								draw_six_set(r_pass, 0, &bind_group, &mut i_off);
+							}
+						}
+					}
+				},
+				UIElementData::ScrollList { .. } => {
+					draw_six_set(r_pass, 0, &self.default_bind_group, &mut i_off);
+					if element.scrollbar_rects().is_some() {
+						// Track and thumb
+						draw_six(r_pass, &mut i_off);
+						draw_six(r_pass, &mut i_off);
+					}
+				},
 				UIElementData::Label { .. } => {
 					if let Some(text) = element.get_element_data().text() {
-						let texture_key = format!("{}_{:?}", text, element.ext_color);
+						let texture_key = self.text_key(element, &text);
 						if let Some((_, bind_group)) = self.text_textures.get(&texture_key) {
 							draw_six_set(r_pass, 0, &bind_group, &mut i_off);
 						}
@@ -488,3 +571,30 @@ impl UIRenderer {
 		}
 	}
 }
+
+/// Labels of the option rows of an open dropdown, where they are drawn
+pub fn dropdown_rows(element: &UIElement) -> Vec<UIElement> {
+	let UIElementData::Dropdown { options, .. } = &element.data else { return Vec::new(); };
+	options.iter().enumerate().filter_map(|(i, option)| {
+		let (position, size) = element.option_rect(i)?;
+		Some(UIElement {
+			position, size,
+			data: UIElementData::Label { text: option.to_string().into(), wrap: false },
+			color: element.color,
+			ext_color: element.ext_color,
+			event_handler: None,
+			..UIElement::default()
+		})
+	}).collect()
+}
+
+/// Pixel rect (x, y, width, height) of a clip rect in screen units, for `set_scissor_rect`
+pub fn scissor_rect((pos, size): (Vec2, Vec2), width: u32, height: u32) -> (u32, u32, u32, u32) {
+	let to_x = |x: f32| (((x + 1.) / 2.).clamp(0., 1.) * width as f32).round() as u32;
+	// Screen units go up, pixels go down
+	let to_y = |y: f32| (((1. - y) / 2.).clamp(0., 1.) * height as f32).round() as u32;
+	let (left, right) = (to_x(pos.x), to_x(pos.x + size.x));
+	let (top, bottom) = (to_y(pos.y + size.y), to_y(pos.y));
+	(left, top, right.saturating_sub(left), bottom.saturating_sub(top))
+}
+
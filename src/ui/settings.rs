@@ -1,9 +1,13 @@
 
 use glam::Vec2;
 use crate::ext::ptr;
-use crate::ext::audio::{set_bg_volume, set_fg_volume};
-use crate::ui::manager::{close_pressed, UIManager, get_element_num_by_id};
+use crate::ext::audio::{self, Channel};
+use crate::ext::config::UITheme;
+use crate::ext::settings::{FovConfig, KeyBindings, MouseConfig, RenderConfig};
+use crate::ui::manager::{close_pressed, UIManager, UIState, get_element_num_by_id, get_element_selected_by_id};
 use crate::ui::element::UIElement;
+use crate::utils::input::{Action, Binding};
+use winit::keyboard::KeyCode;
 
 impl UIManager {
 	#[inline]
@@ -14,22 +18,84 @@ impl UIManager {
 		let title = UIElement::label(self.next_id(), "Settings ... yah".into())
 			.with_position(Vec2::new(-0.4, 0.6))
 			.with_size(Vec2::new(0.8, 0.15))
-			.with_style(&theme.title_label)
+			.with_style(theme, "title_label")
 			.with_z_index(10);
 		self.add_element(title);
 
 		// Settings panel
 		let list_panel = UIElement::panel(self.next_id())
-			.with_position(Vec2::new(-0.6, -0.4))
-			.with_size(Vec2::new(1.2, 0.9))
-			.with_style(&theme.panels.basic)
+			.with_position(Vec2::new(-0.6, -0.64))
+			.with_size(Vec2::new(1.2, 1.22))
+			.with_style(theme, "panels.basic")
 			.with_z_index(1);
 		self.add_element(list_panel);
 
+		let fov_label = UIElement::label(self.next_id(), "Field of view".into())
+			.with_position(Vec2::new(-0.4, 0.48))
+			.with_size(Vec2::new(0.55, 0.06))
+			.with_style(theme, "labels.basic")
+			.with_z_index(6);
+		self.add_element(fov_label);
+		// Degrees, the camera eases to it so dragging doesn't jump the view around
+		let id = self.next_id();
+		let fov_slider = UIElement::slider(id, FovConfig::MIN_FOV, FovConfig::MAX_FOV)
+			.with_position(Vec2::new(-0.4, 0.4))
+			.with_size(Vec2::new(0.8, 0.08))
+			.with_style(theme, "sliders.basic")
+			.with_z_index(5)
+			.with_step(1.0)
+			.with_value(settings.fov_config.fov)
+			.with_callback(move || {
+				ptr::get_settings().fov_config.fov = get_element_num_by_id(&id);
+			});
+		self.add_element(fov_slider);
+
+		let anisotropy_label = UIElement::label(self.next_id(), "Anisotropic filtering".into())
+			.with_position(Vec2::new(-0.4, 0.32))
+			.with_size(Vec2::new(0.38, 0.06))
+			.with_style(theme, "labels.basic")
+			.with_z_index(6);
+		self.add_element(anisotropy_label);
+		// The slider is the power of two, 0 (off) to 4 (16x)
+		let id = self.next_id();
+		let anisotropy_slider = UIElement::slider(id, 0.0, 4.0)
+			.with_position(Vec2::new(-0.4, 0.24))
+			.with_size(Vec2::new(0.38, 0.08))
+			.with_style(theme, "sliders.basic")
+			.with_z_index(5)
+			.with_step(1.0)
+			.with_value(settings.render_config.anisotropy.max(1).ilog2() as f32)
+			.with_callback(move || {
+				let data = get_element_num_by_id(&id);
+				ptr::get_settings().render_config.anisotropy = 1 << (data as u8).min(4);
+				ptr::get_state().apply_anisotropy();
+			});
+		self.add_element(anisotropy_slider);
+
+		let distance_label = UIElement::label(self.next_id(), "Render distance".into())
+			.with_position(Vec2::new(0.02, 0.32))
+			.with_size(Vec2::new(0.38, 0.06))
+			.with_style(theme, "labels.basic")
+			.with_z_index(6);
+		self.add_element(distance_label);
+		// Chunks, the world grows or shrinks to it over the next ticks
+		let id = self.next_id();
+		let distance_slider = UIElement::slider(id, RenderConfig::MIN_RENDER_DISTANCE as f32, RenderConfig::MAX_RENDER_DISTANCE as f32)
+			.with_position(Vec2::new(0.02, 0.24))
+			.with_size(Vec2::new(0.38, 0.08))
+			.with_style(theme, "sliders.basic")
+			.with_z_index(5)
+			.with_step(1.0)
+			.with_value(settings.render_config.render_distance as f32)
+			.with_callback(move || {
+				ptr::get_settings().render_config.render_distance = get_element_num_by_id(&id).round() as u32;
+			});
+		self.add_element(distance_slider);
+
 		let core_label = UIElement::label(self.next_id(), "Multithreading".into())
 			.with_position(Vec2::new(-0.4, 0.14))
-			.with_size(Vec2::new(0.55, 0.06))
-			.with_style(&theme.labels.basic)
+			.with_size(Vec2::new(0.38, 0.06))
+			.with_style(theme, "labels.basic")
 			.with_z_index(6);
 		self.add_element(core_label);
 		let core_slider = {
@@ -42,7 +108,7 @@ impl UIManager {
 
 						let data = get_element_num_by_id(&id);
 						let world = ptr::get_gamestate().world_mut();
-						world.stop_generation_threads();
+						world.stop_generation();
 						world.start_generation_threads(data as u8);
 					})
 			} else {
@@ -51,63 +117,272 @@ impl UIManager {
 					.with_value(0.0)
 			}
 			.with_position(Vec2::new(-0.4, 0.06))
-			.with_size(Vec2::new(0.8, 0.08))
-			.with_style(&theme.sliders.basic)
+			.with_size(Vec2::new(0.38, 0.08))
+			.with_style(theme, "sliders.basic")
 			.with_z_index(5)
 			.with_step(1.0)
 		};
 		self.add_element(core_slider);
 
-		let fgvolume_label = UIElement::label(self.next_id(), "Foreground volume".into())
+		let msaa_label = UIElement::label(self.next_id(), "Anti-aliasing".into())
+			.with_position(Vec2::new(0.02, 0.14))
+			.with_size(Vec2::new(0.38, 0.06))
+			.with_style(theme, "labels.basic")
+			.with_z_index(6);
+		self.add_element(msaa_label);
+		// Walks the sample counts this device supports, so nothing else can be picked
+		let counts = ptr::get_state().sample_counts().to_vec();
+		let current = counts.iter().position(|&count| count == settings.render_config.msaa_samples).unwrap_or(0);
+		let id = self.next_id();
+		let msaa_slider = UIElement::slider(id, 0.0, counts.len().saturating_sub(1).max(1) as f32)
+			.with_position(Vec2::new(0.02, 0.06))
+			.with_size(Vec2::new(0.38, 0.08))
+			.with_style(theme, "sliders.basic")
+			.with_z_index(5)
+			.with_step(1.0)
+			.with_enabled(counts.len() > 1)
+			.with_value(current as f32)
+			.with_callback(move || {
+				let Some(&count) = counts.get(get_element_num_by_id(&id).round() as usize) else { return };
+				let render_config = &mut ptr::get_settings().render_config;
+				if render_config.msaa_samples == count { return; }
+				render_config.msaa_samples = count;
+				ptr::get_state().apply_msaa();
+			});
+		self.add_element(msaa_slider);
+
+		let music_settings = &settings.music_settings;
+		let volume_label = UIElement::label(self.next_id(), "Master volume".into())
 			.with_position(Vec2::new(-0.4, -0.04))
 			.with_size(Vec2::new(0.55, 0.06))
-			.with_style(&theme.labels.basic)
+			.with_style(theme, "labels.basic")
 			.with_z_index(6);
-		self.add_element(fgvolume_label);
+		self.add_element(volume_label);
 		let id = self.next_id();
-		let fgvolume_slider = UIElement::slider(id, settings.music_settings.fg_volume.min, settings.music_settings.fg_volume.max)
-			.with_position(Vec2::new(-0.4, -0.12))
-			.with_size(Vec2::new(0.8, 0.08))
-			.with_style(&theme.sliders.basic)
+		let bloom_checkbox = UIElement::checkbox(id)
+			.with_text("Bloom")
+			.with_position(Vec2::new(0.2, -0.04))
+			.with_size(Vec2::new(0.06, 0.06))
+			.with_style(theme, "checkboxs.basic")
 			.with_z_index(5)
-			//.with_step(0.5)
-			.with_value(settings.music_settings.fg_volume.val)
+			.with_checked(settings.render_config.bloom)
 			.with_callback(move || {
-				let data = get_element_num_by_id(&id);
-				ptr::get_settings().music_settings.fg_volume.set(data);
-				set_fg_volume(data);
+				let checked = ptr::get_state().ui_manager.get_element(id).and_then(|e| e.is_checked());
+				ptr::get_settings().render_config.bloom = checked.unwrap_or(false);
+				ptr::get_state().apply_bloom();
 			});
-		self.add_element(fgvolume_slider);
+		self.add_element(bloom_checkbox);
+		// Master over the whole width, music and effects side by side under it
+		for (channel, name, position, width) in [
+			(Channel::Master, "", Vec2::new(-0.4, -0.12), 0.8),
+			(Channel::Music, "Music", Vec2::new(-0.4, -0.3), 0.38),
+			(Channel::Sfx, "Effects", Vec2::new(0.02, -0.3), 0.38),
+		] {
+			if !name.is_empty() {
+				let label = UIElement::label(self.next_id(), name.into())
+					.with_position(position + Vec2::new(0., 0.08))
+					.with_size(Vec2::new(0.3, 0.06))
+					.with_style(theme, "labels.basic")
+					.with_z_index(6);
+				self.add_element(label);
+			}
+			let range = music_settings.volume(channel);
+			let id = self.next_id();
+			let slider = UIElement::slider(id, range.min, range.max)
+				.with_position(position)
+				.with_size(Vec2::new(width, 0.08))
+				.with_style(theme, "sliders.basic")
+				.with_z_index(5)
+				.with_value(range.val)
+				.with_callback(move || audio::set_volume(channel, get_element_num_by_id(&id)));
+			self.add_element(slider);
+		}
 
-		let bgvolume_label = UIElement::label(self.next_id(), "Background volume".into())
-			.with_position(Vec2::new(-0.4, -0.22))
+		let sensitivity_label = UIElement::label(self.next_id(), "Mouse sensitivity".into())
+			.with_position(Vec2::new(-0.4, -0.4))
 			.with_size(Vec2::new(0.55, 0.06))
-			.with_style(&theme.labels.basic)
+			.with_style(theme, "labels.basic")
 			.with_z_index(6);
-		self.add_element(bgvolume_label);
+		self.add_element(sensitivity_label);
 		let id = self.next_id();
-		let bgvolume_slider = UIElement::slider(id, settings.music_settings.bg_volume.min, settings.music_settings.bg_volume.max)
-			.with_position(Vec2::new(-0.4, -0.3))
-			.with_size(Vec2::new(0.8, 0.08))
-			.with_style(&theme.sliders.basic)
+		let sensitivity_slider = UIElement::slider(id, MouseConfig::MIN_SENSITIVITY, MouseConfig::MAX_SENSITIVITY)
+			.with_position(Vec2::new(-0.4, -0.48))
+			.with_size(Vec2::new(0.55, 0.08))
+			.with_style(theme, "sliders.basic")
 			.with_z_index(5)
-			//.with_step(0.5)
-			.with_value(settings.music_settings.bg_volume.val)
+			.with_step(0.1)
+			.with_value(settings.mouse_config.mouse_sensitivity)
 			.with_callback(move || {
-				let data = get_element_num_by_id(&id);
-				ptr::get_settings().music_settings.bg_volume.set(data);
-				set_bg_volume(data);
+				ptr::get_settings().mouse_config.mouse_sensitivity = get_element_num_by_id(&id);
+			});
+		self.add_element(sensitivity_slider);
+		let id = self.next_id();
+		let invert_checkbox = UIElement::checkbox(id)
+			.with_text("Invert Y")
+			.with_position(Vec2::new(0.2, -0.48))
+			.with_size(Vec2::new(0.06, 0.08))
+			.with_style(theme, "checkboxs.basic")
+			.with_z_index(5)
+			.with_checked(settings.mouse_config.invert_y)
+			.with_callback(move || {
+				let checked = ptr::get_state().ui_manager.get_element(id).and_then(|e| e.is_checked());
+				ptr::get_settings().mouse_config.invert_y = checked.unwrap_or(false);
+			});
+		self.add_element(invert_checkbox);
+
+		let controls_button = UIElement::button(self.next_id(), "Controls".into())
+			.with_position(Vec2::new(-0.45, -0.58))
+			.with_size(Vec2::new(0.4, 0.08))
+			.with_style(theme, "buttons.nice")
+			.with_z_index(8)
+			.with_callback(|| {
+				let ui_manager = &mut ptr::get_state().ui_manager;
+				let UIState::Settings(prev) = ui_manager.state else { return };
+				ui_manager.state = UIState::Controls(prev);
+				ui_manager.binding_note.clear();
+				ui_manager.setup_ui();
 			});
-		self.add_element(bgvolume_slider);
+		self.add_element(controls_button);
+
+		// Recolors this menu right away, no rebuild needed
+		let themes = UITheme::available();
+		let current = themes.iter().position(|name| *name == theme.name).unwrap_or(0);
+		let id = self.next_id();
+		let theme_dropdown = UIElement::dropdown(id, themes.iter().map(|name| name.clone().into()).collect())
+			.with_position(Vec2::new(0.05, -0.58))
+			.with_size(Vec2::new(0.4, 0.08))
+			.with_style(theme, "buttons.basic")
+			.with_z_index(9)
+			.with_selected(current)
+			.with_callback(move || {
+				let Some(theme) = themes.get(get_element_selected_by_id(&id)).and_then(|name| UITheme::load(name)) else { return };
+				ptr::get_state().ui_manager.apply_theme(&theme);
+			});
+		self.add_element(theme_dropdown);
 
 		// Back button
 		let back_button = UIElement::button(self.next_id(), "Back".into())
 			.with_position(Vec2::new(-0.1, -0.8))
 			.with_size(Vec2::new(0.2, 0.08))
-			.with_style(&theme.buttons.extra())
+			.with_style(theme, "buttons.extra")
+			.with_z_index(8)
+			.with_callback(|| close_pressed());
+		self.add_element(back_button);
+	}
+
+	/// A row per action with its key, clicking one waits for the next key or mouse button
+	pub fn setup_controls_ui(&mut self) {
+		let theme = &ptr::get_settings().ui_theme;
+		let bindings = ptr::get_settings().key_bindings;
+		let title = UIElement::label(self.next_id(), "Controls".into())
+			.with_position(Vec2::new(-0.4, 0.6))
+			.with_size(Vec2::new(0.8, 0.15))
+			.with_style(theme, "title_label")
+			.with_z_index(10);
+		self.add_element(title);
+
+		let list_panel = UIElement::panel(self.next_id())
+			.with_position(Vec2::new(-0.6, -0.4))
+			.with_size(Vec2::new(1.2, 0.9))
+			.with_style(theme, "panels.basic")
+			.with_z_index(1);
+		self.add_element(list_panel);
+		let list_id = self.next_id();
+		let list = UIElement::scroll_list(list_id)
+			.with_position(Vec2::new(-0.6, -0.38))
+			.with_size(Vec2::new(1.2, 0.86))
+			.with_style(theme, "panels.basic")
+			.with_alpha(0)
+			.with_z_index(2);
+		self.add_element(list);
+
+		for (i, action) in Action::ALL.into_iter().enumerate() {
+			let y_pos = 0.38 - i as f32 * 0.1;
+			let name = UIElement::label(self.next_id(), action.name().into())
+				.with_position(Vec2::new(-0.5, y_pos))
+				.with_size(Vec2::new(0.5, 0.08))
+				.with_style(theme, "labels.basic")
+				.with_z_index(5)
+				.with_parent(list_id);
+			self.add_element(name);
+
+			let text = if self.rebinding == Some(action) { "Press a key...".to_string() } else { bindings.get(action).label() };
+			let key_button = UIElement::button(self.next_id(), text.into())
+				.with_position(Vec2::new(0.05, y_pos))
+				.with_size(Vec2::new(0.45, 0.08))
+				.with_style(theme, "buttons.basic")
+				.with_z_index(5)
+				.with_parent(list_id)
+				.with_callback(move || {
+					let ui_manager = &mut ptr::get_state().ui_manager;
+					ui_manager.rebinding = Some(action);
+					ui_manager.binding_note.clear();
+					ui_manager.setup_ui();
+				});
+			self.add_element(key_button);
+		}
+		self.fit_scroll_list(list_id);
+
+		let note = if !self.binding_note.is_empty() { self.binding_note.clone() }
+			else if self.rebinding.is_some() { "Escape keeps the old key".to_string() }
+			else { String::new() };
+		let note_label = UIElement::label(self.next_id(), note.into())
+			.with_position(Vec2::new(-0.6, -0.52))
+			.with_size(Vec2::new(1.2, 0.08))
+			.with_style(theme, "labels.bad")
+			.with_z_index(6);
+		self.add_element(note_label);
+
+		let reset_button = UIElement::button(self.next_id(), "Defaults".into())
+			.with_position(Vec2::new(0.3, -0.8))
+			.with_size(Vec2::new(0.3, 0.08))
+			.with_style(theme, "buttons.bad")
+			.with_z_index(8)
+			.with_callback(|| {
+				ptr::get_settings().key_bindings = KeyBindings::default();
+				let ui_manager = &mut ptr::get_state().ui_manager;
+				ui_manager.rebinding = None;
+				ui_manager.binding_note = save_bindings();
+				ui_manager.setup_ui();
+			});
+		self.add_element(reset_button);
+
+		let back_button = UIElement::button(self.next_id(), "Back".into())
+			.with_position(Vec2::new(-0.1, -0.8))
+			.with_size(Vec2::new(0.2, 0.08))
+			.with_style(theme, "buttons.extra")
 			.with_z_index(8)
 			.with_callback(|| close_pressed());
 		self.add_element(back_button);
 	}
 
+	/// Binds the key or button to the action waiting on the controls screen, false if none is waiting
+	pub fn capture_binding(&mut self, binding: Binding) -> bool {
+		let Some(action) = self.rebinding else { return false; };
+		if !matches!(self.state, UIState::Controls(..)) {
+			self.rebinding = None;
+			return false;
+		}
+		// Escape goes on to `close_pressed`, which stops waiting
+		if binding == Binding::Key(KeyCode::Escape) { return false; }
+		self.binding_note = if !binding.is_bindable() {
+			format!("{} can't be bound", binding.label())
+		} else {
+			match ptr::get_settings().key_bindings.set(action, binding) {
+				Ok(()) => save_bindings(),
+				Err(other) => format!("{} is already used by {}", binding.label(), other.name()),
+			}
+		};
+		self.rebinding = None;
+		self.setup_ui();
+		true
+	}
+}
+
+/// Writes the bindings to disk, the text to show if that failed
+fn save_bindings() -> String {
+	match ptr::get_settings().key_bindings.save() {
+		Ok(()) => String::new(),
+		Err(e) => format!("Not saved: {}", e),
+	}
 }
@@ -1,8 +1,10 @@
 
-use crate::world::{handler, manager};
+use crate::world::{handler, manager, generation::GenMode};
 use crate::utils::color::Solor;
-use crate::ui::manager::{UIState, close_pressed, UIManager, UIStateID, get_element_str_by_id};
-use crate::ui::element::UIElement;
+use crate::ui::manager::{UIState, close_pressed, UIManager, UIStateID, get_element_str_by_id, get_element_selected_by_id, LOADING_BAR_WIDTH};
+use crate::ui::element::{Anchor, UIElement};
+use crate::ui::dialog::ConfirmDialog;
+use crate::utils::input::Action;
 use crate::ext::{ptr, memory};
 use crate::network::api;
 use crate::block;
@@ -17,7 +19,7 @@ impl UIManager {
 		let bg_panel = UIElement::panel(self.next_id())
 			.with_position(Vec2::new(-1.0, -1.0))
 			.with_size(Vec2::new(2.0, 2.0))
-			.with_style(&theme.bg_panel)
+			.with_style(theme, "bg_panel")
 			.with_z_index(-5);
 
 		#[allow(unreachable_patterns)]
@@ -54,6 +56,10 @@ impl UIManager {
 				self.add_element(bg_panel);
 				self.setup_settings_ui();
 			},
+			UIState::Controls(..) => {
+				self.add_element(bg_panel);
+				self.setup_controls_ui();
+			},
 			UIState::Escape => {
 				self.setup_escape_ui();
 			},
@@ -74,6 +80,11 @@ impl UIManager {
 			}
 			_ => {},
 		}
+		// The inventory is hit tested by its own layout, so only menus are anchored
+		if !matches!(self.state, UIState::InGame | UIState::Inventory(_)) {
+			self.anchor_menu();
+		}
+		self.setup_debug_overlay();
 	}
 
 	#[inline]
@@ -83,7 +94,7 @@ impl UIManager {
 		let title = UIElement::label(self.next_id(), "Rusticubes".into())
 			.with_position(Vec2::new(-0.4, 0.3))
 			.with_size(Vec2::new(0.8, 0.2))
-			.with_style(&theme.title_label)
+			.with_style(theme, "title_label")
 			.with_z_index(10);
 		self.add_element(title);
 
@@ -91,7 +102,7 @@ impl UIManager {
 		let button_panel = UIElement::panel(self.next_id())
 			.with_position(Vec2::new(-0.35, -0.2))
 			.with_size(Vec2::new(0.7, 0.5))
-			.with_style(&theme.panels.basic)
+			.with_style(theme, "panels.basic")
 			.with_z_index(1);
 		self.add_element(button_panel);
 
@@ -99,7 +110,7 @@ impl UIManager {
 		let start_button = UIElement::button(self.next_id(), "Start".into())
 			.with_position(Vec2::new(-0.15, 0.0))
 			.with_size(Vec2::new(0.3, 0.1))
-			.with_style(&theme.best_button)
+			.with_style(theme, "best_button")
 			.with_z_index(6)
 			.with_callback(|| {
 				let ui_manager = &mut ptr::get_state().ui_manager;
@@ -112,7 +123,7 @@ impl UIManager {
 		let exit_button = UIElement::button(self.next_id(), "Exit".into())
 			.with_position(Vec2::new(-0.15, -0.15))
 			.with_size(Vec2::new(0.3, 0.1))
-			.with_style(&theme.worst_button)
+			.with_style(theme, "worst_button")
 			.with_z_index(5)
 			.with_callback(|| close_pressed());
 		self.add_element(exit_button);
@@ -120,7 +131,7 @@ impl UIManager {
 		let memory_button = UIElement::button(self.next_id(), "Memory".into())
 			.with_position(Vec2::new(0.55, 0.2))
 			.with_size(Vec2::new(0.35, 0.1))
-			.with_style(&theme.buttons.nice)
+			.with_style(theme, "buttons.nice")
 			.with_z_index(6)
 			.with_callback(|| {
 				memory::light_trim();
@@ -131,7 +142,7 @@ impl UIManager {
 		let setting_button = UIElement::button(self.next_id(), "Settings".into())
 			.with_position(Vec2::new(-0.9, 0.0))
 			.with_size(Vec2::new(0.4, 0.1))
-			.with_style(&theme.buttons.nice)
+			.with_style(theme, "buttons.nice")
 			.with_z_index(6)
 			.with_callback(|| {
 				let ui_manager = &mut ptr::get_state().ui_manager;
@@ -143,7 +154,7 @@ impl UIManager {
 		let multiplayer_button = UIElement::button(self.next_id(), "Multi".into())
 			.with_position(Vec2::new(0.55, -0.1))
 			.with_size(Vec2::new(0.35, 0.1))
-			.with_style(&theme.buttons.nice)
+			.with_style(theme, "buttons.nice")
 			.with_z_index(6)
 			.with_callback(|| {
 				let state = ptr::get_state();
@@ -159,14 +170,14 @@ impl UIManager {
 		let tree_picture = UIElement::image(self.next_id(), "happy-tree.png".into())
 			.with_position(Vec2::new(0.6, 0.5))
 			.with_size(Vec2::new(0.27, 0.45))
-			.with_style(&theme.images.basic)
+			.with_style(theme, "images.basic")
 			.with_z_index(6);
 		self.add_element(tree_picture);
 
 		let tree_animation = UIElement::animation(self.next_id(), vec!["happy-tree.png".into(), "cube.jpg".into()])
 			.with_position(Vec2::new(-0.8, 0.5))
 			.with_size(Vec2::new(0.27, 0.45))
-			.with_style(&theme.images.nice)
+			.with_style(theme, "images.nice")
 			.with_z_index(6)
 			.with_animation_duration(2.5);
 		self.add_element(tree_animation);
@@ -175,7 +186,7 @@ impl UIManager {
 		let version = UIElement::label(self.next_id(), env!("CARGO_PKG_VERSION").into())
 			.with_position(Vec2::new(0.7, -0.95))
 			.with_size(Vec2::new(0.2, 0.05))
-			.with_style(&theme.labels.extra())
+			.with_style(theme, "labels.extra")
 			.with_z_index(8);
 		self.add_element(version);
 	}
@@ -187,7 +198,7 @@ impl UIManager {
 		let title = UIElement::label(self.next_id(), "Select World".into())
 			.with_position(Vec2::new(-0.4, 0.6))
 			.with_size(Vec2::new(0.8, 0.15))
-			.with_style(&theme.title_label)
+			.with_style(theme, "title_label")
 			.with_z_index(10);
 		self.add_element(title);
 
@@ -195,15 +206,24 @@ impl UIManager {
 		let list_panel = UIElement::panel(self.next_id())
 			.with_position(Vec2::new(-0.6, -0.4))
 			.with_size(Vec2::new(1.2, 0.9))
-			.with_style(&theme.panels.basic)
+			.with_style(theme, "panels.basic")
 			.with_z_index(1);
 		self.add_element(list_panel);
+		// Scrolls the worlds, any number of them fits
+		let list_id = self.next_id();
+		let list = UIElement::scroll_list(list_id)
+			.with_position(Vec2::new(-0.6, -0.38))
+			.with_size(Vec2::new(1.2, 0.72))
+			.with_style(theme, "panels.basic")
+			.with_alpha(0)
+			.with_z_index(2);
+		self.add_element(list);
 
 		// New World button
 		let new_button = UIElement::button(self.next_id(), "Create New World".into())
 			.with_position(Vec2::new(-0.3, 0.4))
 			.with_size(Vec2::new(0.6, 0.08))
-			.with_style(&theme.buttons.nice)
+			.with_style(theme, "buttons.nice")
 			.with_z_index(8)
 			.with_callback(|| {
 				let state = ptr::get_state();
@@ -222,14 +242,15 @@ impl UIManager {
 
 		// World buttons
 		for (i, name) in worlds.iter().enumerate() {
-			let y_pos = 0.2 - (i as f32 * 0.12);
+			let y_pos = 0.22 - (i as f32 * 0.12);
 			let name_clone = name.clone();
 			
 			let world_button = UIElement::button(self.next_id(), name.clone().into())
 				.with_position(Vec2::new(-0.4, y_pos))
 				.with_size(Vec2::new(0.8, 0.1))
-				.with_style(&theme.buttons.basic)
+				.with_style(theme, "buttons.basic")
 				.with_z_index(5)
+				.with_parent(list_id)
 				.with_callback({
 					let name_clone = name_clone.clone();
 					move || handler::join_world(&name_clone)
@@ -240,28 +261,27 @@ impl UIManager {
 			let delete_button = UIElement::button(self.next_id(), "X".into())
 				.with_position(Vec2::new(0.43, y_pos))
 				.with_size(Vec2::new(0.1, 0.1))
-				.with_style(&theme.buttons.bad)
+				.with_style(theme, "buttons.bad")
 				.with_z_index(5)
+				.with_parent(list_id)
 				.with_callback(move || {
 					let name_clone = name_clone.clone();
-					ptr::get_state().ui_manager.dialogs.ask_with_callback(
-						"Delete world?",
-						move |confirmed| {
-							if confirmed { 
-								if let Err(e) = manager::del_world(&name_clone) {
-									println!("Deleting world failed: {:?}", e);
-								} }
-						}
-					);
+					// The list is rebuilt once the dialog closes, a deleted world is gone from it
+					let dialog = ConfirmDialog::new(format!("Delete world '{}'?", name_clone))
+						.on_yes(move || if let Err(e) = manager::del_world(&name_clone) {
+							println!("Deleting world failed: {:?}", e);
+						});
+					ptr::get_state().ui_manager.open_confirm(dialog);
 				});
 			self.add_element(delete_button);
 		}
+		self.fit_scroll_list(list_id);
 
 		// Back button
 		let back_button = UIElement::button(self.next_id(), "Back".into())
 			.with_position(Vec2::new(-0.1, -0.8))
 			.with_size(Vec2::new(0.2, 0.08))
-			.with_style(&theme.buttons.extra())
+			.with_style(theme, "buttons.extra")
 			.with_z_index(8)
 			.with_callback(|| {
 				let state = ptr::get_state();
@@ -274,54 +294,42 @@ impl UIManager {
 	#[inline]
 	fn setup_confirm_ui(&mut self) {
 		let theme = &ptr::get_settings().ui_theme;
-		let manager = &ptr::get_state().ui_manager;
-		let dialog_id = manager.state.inner().unwrap_or(0);
-		let prompt: String = manager.dialogs.get_pending_dialog(dialog_id).unwrap_or("Yeah?".to_string());
+		let prompt = self.confirm_message().unwrap_or("Yeah?").to_string();
 		
 		let title = UIElement::label(self.next_id(), prompt.clone().into())
 			.with_position(Vec2::new(-0.4, 0.6))
 			.with_size(Vec2::new(0.8, 0.15))
-			.with_style(&theme.title_label)
+			.with_style(theme, "title_label")
 			.with_z_index(10);
 		self.add_element(title);
 
 		let list_panel = UIElement::panel(self.next_id())
 			.with_position(Vec2::new(-0.9, -0.4))
 			.with_size(Vec2::new(1.8, 0.9))
-			.with_style(&theme.panels.basic)
+			.with_style(theme, "panels.basic")
 			.with_z_index(1);
 		self.add_element(list_panel);
 
 		let option_button_1 = UIElement::button(self.next_id(), "Yes".into())
 			.with_position(Vec2::new(-0.8, 0.0))
 			.with_size(Vec2::new(0.6, 0.1))
-			.with_style(&theme.deny_button)
+			.with_style(theme, "deny_button")
 			.with_z_index(5)
-			.with_callback(move || {
-				let ui_manager = &mut ptr::get_state().ui_manager;
-				ui_manager.dialogs.respond(dialog_id.clone(), true);
-				ui_manager.state = ui_manager.state.inner_state();
-				ui_manager.setup_ui();
-			});
+			.with_callback(|| ptr::get_state().ui_manager.answer_confirm(true));
 		self.add_element(option_button_1);
 		
 		let option_button_2 = UIElement::button(self.next_id(), "No".into())
 			.with_position(Vec2::new(0.2, 0.0))
 			.with_size(Vec2::new(0.6, 0.1))
-			.with_style(&theme.okay_button)
+			.with_style(theme, "okay_button")
 			.with_z_index(5)
-			.with_callback(move || {
-				let ui_manager = &mut ptr::get_state().ui_manager;
-				ui_manager.dialogs.respond(dialog_id.clone(), false);
-				ui_manager.state = ui_manager.state.inner_state();
-				ui_manager.setup_ui();
-			});
+			.with_callback(|| ptr::get_state().ui_manager.answer_confirm(false));
 		self.add_element(option_button_2);
 
 		let back_button = UIElement::button(self.next_id(), "Back".into())
 			.with_position(Vec2::new(-0.1, -0.8))
 			.with_size(Vec2::new(0.2, 0.08))
-			.with_style(&theme.buttons.extra())
+			.with_style(theme, "buttons.extra")
 			.with_z_index(8)
 			.with_callback(|| close_pressed());
 		self.add_element(back_button);
@@ -334,24 +342,26 @@ impl UIManager {
 		let dialog_id = manager.state.inner().unwrap_or(0);
 		let prompt: String = manager.dialogs.get_pending_dialog(dialog_id).unwrap_or("ERROR!!".to_string());
 		
+		// Errors can be long, they go on as many lines as they need
 		let title = UIElement::label(self.next_id(), prompt.clone().into())
-			.with_position(Vec2::new(-0.4, 0.6))
-			.with_size(Vec2::new(0.8, 0.15))
-			.with_style(&theme.title_label)
+			.with_position(Vec2::new(-0.8, 0.6))
+			.with_size(Vec2::new(1.6, 0.15))
+			.with_style(theme, "title_label")
+			.with_wrap(true)
 			.with_z_index(10);
 		self.add_element(title);
 
 		let list_panel = UIElement::panel(self.next_id())
 			.with_position(Vec2::new(-0.9, -0.4))
 			.with_size(Vec2::new(1.8, 0.9))
-			.with_style(&theme.panels.basic)
+			.with_style(theme, "panels.basic")
 			.with_z_index(1);
 		self.add_element(list_panel);
 
 		let option_button_1 = UIElement::button(self.next_id(), "Continue".into())
 			.with_position(Vec2::new(-0.8, 0.0))
 			.with_size(Vec2::new(0.6, 0.1))
-			.with_style(&theme.deny_button)
+			.with_style(theme, "deny_button")
 			.with_z_index(5)
 			.with_callback(move || {
 				let ui_manager = &mut ptr::get_state().ui_manager;
@@ -364,7 +374,7 @@ impl UIManager {
 		let option_button_2 = UIElement::button(self.next_id(), "Cancel".into())
 			.with_position(Vec2::new(0.2, 0.0))
 			.with_size(Vec2::new(0.6, 0.1))
-			.with_style(&theme.okay_button)
+			.with_style(theme, "okay_button")
 			.with_z_index(5)
 			.with_callback(move || {
 				let ui_manager = &mut ptr::get_state().ui_manager;
@@ -377,7 +387,7 @@ impl UIManager {
 		let back_button = UIElement::button(self.next_id(), "Back".into())
 			.with_position(Vec2::new(-0.1, -0.8))
 			.with_size(Vec2::new(0.2, 0.08))
-			.with_style(&theme.buttons.extra())
+			.with_style(theme, "buttons.extra")
 			.with_z_index(8)
 			.with_callback(|| close_pressed());
 		self.add_element(back_button);
@@ -389,39 +399,50 @@ impl UIManager {
 		let title = UIElement::label(self.next_id(), "Select World".into())
 			.with_position(Vec2::new(-0.4, 0.6))
 			.with_size(Vec2::new(0.8, 0.15))
-			.with_style(&theme.title_label)
+			.with_style(theme, "title_label")
 			.with_z_index(10);
 		self.add_element(title);
 
 		let list_panel = UIElement::panel(self.next_id())
 			.with_position(Vec2::new(-0.6, -0.4))
 			.with_size(Vec2::new(1.2, 0.9))
-			.with_style(&theme.panels.basic)
+			.with_style(theme, "panels.basic")
 			.with_z_index(1);
 		self.add_element(list_panel);
 
-		let worlds: Vec<String> = api::get_discovered_hosts()
-			.iter()
-			.map(|s| s.world_name.clone())
-			.collect();
+		// Filled in when a discovery finishes, the screen is rebuilt then
+		let hosts = api::get_discovered_hosts();
 
-		for (i, name) in worlds.iter().enumerate() {
+		for (i, host) in hosts.into_iter().enumerate() {
 			let y_pos = 0.2 - (i as f32 * 0.12);
-			let name_clone = name.clone();
 
-			let world_button = UIElement::button(self.next_id(), name.clone().into())
+			let world_button = UIElement::button(self.next_id(), host.world_name.clone().into())
 				.with_position(Vec2::new(-0.4, y_pos))
 				.with_size(Vec2::new(0.8, 0.1))
-				.with_style(&theme.buttons.basic)
+				.with_style(theme, "buttons.basic")
 				.with_z_index(5)
-				.with_callback(move || handler::join_local_world(&name_clone));
+				.with_callback(move || {
+					handler::join_local_world(&host.world_name);
+					if let Err(e) = api::connect_to_host(&host.join) {
+						println!("Error: {}", e);
+					}
+				});
 			self.add_element(world_button);
 		}
 
+		if let Some(progress) = api::connect_progress() {
+			let progress_label = UIElement::label(self.next_id(), progress.into())
+				.with_position(Vec2::new(-0.6, -0.6))
+				.with_size(Vec2::new(1.2, 0.08))
+				.with_style(theme, "labels.basic")
+				.with_z_index(8);
+			self.add_element(progress_label);
+		}
+
 		let re_button = UIElement::button(self.next_id(), "refresh".into())
 			.with_position(Vec2::new(-0.4, -0.8))
 			.with_size(Vec2::new(0.25, 0.08))
-			.with_style(&theme.buttons.nice)
+			.with_style(theme, "buttons.nice")
 			.with_z_index(8)
 			.with_callback(|| {
 				if let Err(e) = api::refresh_discovery() {
@@ -435,7 +456,7 @@ impl UIManager {
 		let connect_button = UIElement::button(self.next_id(), "manual connect".into())
 			.with_position(Vec2::new(0.15, -0.8))
 			.with_size(Vec2::new(0.5, 0.08))
-			.with_style(&theme.buttons.nice)
+			.with_style(theme, "buttons.nice")
 			.with_z_index(8)
 			.with_callback(|| {
 				let ui_manager = &mut ptr::get_state().ui_manager;
@@ -447,7 +468,7 @@ impl UIManager {
 		let back_button = UIElement::button(self.next_id(), "Back".into())
 			.with_position(Vec2::new(-0.1, -0.8))
 			.with_size(Vec2::new(0.2, 0.08))
-			.with_style(&theme.buttons.extra())
+			.with_style(theme, "buttons.extra")
 			.with_z_index(8)
 			.with_callback(|| close_pressed());
 		self.add_element(back_button);
@@ -459,21 +480,21 @@ impl UIManager {
 		let title = UIElement::label(self.next_id(), "Create New World".into())
 			.with_position(Vec2::new(-0.5, 0.4))
 			.with_size(Vec2::new(1.0, 0.15))
-			.with_style(&theme.title_label)
+			.with_style(theme, "title_label")
 			.with_z_index(10);
 		self.add_element(title);
 
 		let form_panel = UIElement::panel(self.next_id())
 			.with_position(Vec2::new(-0.4, -0.3))
 			.with_size(Vec2::new(0.8, 0.7))
-			.with_style(&theme.panels.basic)
+			.with_style(theme, "panels.basic")
 			.with_z_index(1);
 		self.add_element(form_panel);
 
 		let w_name_label = UIElement::label(self.next_id(), "World Name:".into())
 			.with_position(Vec2::new(-0.35, 0.1))
 			.with_size(Vec2::new(0.4, 0.08))
-			.with_style(&theme.labels.basic)
+			.with_style(theme, "labels.basic")
 			.with_z_index(3);
 		self.add_element(w_name_label);
 
@@ -481,18 +502,34 @@ impl UIManager {
 		let world_name_input = UIElement::input(input_id)
 			.with_position(Vec2::new(-0.35, -0.0))
 			.with_size(Vec2::new(0.7, 0.1))
-			.with_style(&theme.inputs.basic)
+			.with_style(theme, "inputs.basic")
 			.with_placeholder("New World")
 			.with_z_index(5);
 		self.add_element(world_name_input);
 
+		let mode_label = UIElement::label(self.next_id(), "Generation:".into())
+			.with_position(Vec2::new(-0.35, -0.12))
+			.with_size(Vec2::new(0.35, 0.08))
+			.with_style(theme, "labels.basic")
+			.with_z_index(3);
+		self.add_element(mode_label);
+
+		let mode_id = self.next_id();
+		let mode_dropdown = UIElement::dropdown(mode_id, GenMode::NAMES.iter().map(|&name| name.into()).collect())
+			.with_position(Vec2::new(0.0, -0.12))
+			.with_size(Vec2::new(0.35, 0.08))
+			.with_style(theme, "buttons.basic")
+			.with_z_index(5);
+		self.add_element(mode_dropdown);
+
 		let gen_button = UIElement::button(self.next_id(), "Create World".into())
-			.with_position(Vec2::new(-0.3, -0.2))
+			.with_position(Vec2::new(-0.3, -0.25))
 			.with_size(Vec2::new(0.6, 0.1))
-			.with_style(&theme.buttons.nice)
+			.with_style(theme, "buttons.nice")
 			.with_z_index(6)
 			.with_callback(move || {
-				handler::create_world(get_element_str_by_id(&input_id).to_string());
+				let gen_mode = GenMode::from_index(get_element_selected_by_id(&mode_id));
+				handler::create_world(get_element_str_by_id(&input_id).to_string(), gen_mode);
 				let ui_manager = &mut ptr::get_state().ui_manager;
 				ui_manager.state = UIState::WorldSelection;
 				ui_manager.setup_ui();
@@ -502,7 +539,7 @@ impl UIManager {
 		let back_button = UIElement::button(self.next_id(), "Back".into())
 			.with_position(Vec2::new(-0.1, -0.45))
 			.with_size(Vec2::new(0.2, 0.08))
-			.with_style(&theme.buttons.extra())
+			.with_style(theme, "buttons.extra")
 			.with_z_index(8)
 			.with_callback(|| close_pressed());
 		self.add_element(back_button);
@@ -514,21 +551,21 @@ impl UIManager {
 		let title = UIElement::label(self.next_id(), "Manual Connect".into())
 			.with_position(Vec2::new(-0.5, 0.4))
 			.with_size(Vec2::new(1.0, 0.15))
-			.with_style(&theme.title_label)
+			.with_style(theme, "title_label")
 			.with_z_index(10);
 		self.add_element(title);
 
 		let form_panel = UIElement::panel(self.next_id())
 			.with_position(Vec2::new(-0.4, -0.3))
 			.with_size(Vec2::new(0.8, 0.7))
-			.with_style(&theme.panels.basic)
+			.with_style(theme, "panels.basic")
 			.with_z_index(1);
 		self.add_element(form_panel);
 
 		let w_ip_label = UIElement::label(self.next_id(), "Server IP:".into())
 			.with_position(Vec2::new(-0.35, 0.1))
 			.with_size(Vec2::new(0.4, 0.08))
-			.with_style(&theme.labels.basic)
+			.with_style(theme, "labels.basic")
 			.with_z_index(3);
 		self.add_element(w_ip_label);
 
@@ -536,30 +573,38 @@ impl UIManager {
 		let world_ip_input = UIElement::input(input_id)
 			.with_position(Vec2::new(-0.35, -0.0))
 			.with_size(Vec2::new(0.7, 0.1))
-			.with_style(&theme.inputs.basic)
+			.with_style(theme, "inputs.basic")
 			.with_placeholder("255.255.255.255")
 			.with_z_index(5);
 		self.add_element(world_ip_input);
 
+		if let Some(progress) = api::connect_progress() {
+			let progress_label = UIElement::label(self.next_id(), progress.into())
+				.with_position(Vec2::new(-0.35, 0.22))
+				.with_size(Vec2::new(0.7, 0.08))
+				.with_style(theme, "labels.basic")
+				.with_z_index(3);
+			self.add_element(progress_label);
+		}
+
 		let connect_button = UIElement::button(self.next_id(), "Connect Server".into())
 			.with_position(Vec2::new(-0.3, -0.2))
 			.with_size(Vec2::new(0.6, 0.1))
-			.with_style(&theme.buttons.nice)
+			.with_style(theme, "buttons.nice")
 			.with_z_index(6)
 			.with_callback(move || {
 				if let Err(e) = api::connect_to_host(&get_element_str_by_id(&input_id)) {
 					println!("Error: {}", e);
 				}
-				let ui_manager = &mut ptr::get_state().ui_manager;
-				ui_manager.state = UIState::WorldSelection;
-				ui_manager.setup_ui();
+				// Stays here so the attempt (and its retries) can be followed
+				ptr::get_state().ui_manager.setup_ui();
 			});
 		self.add_element(connect_button);
 
 		let back_button = UIElement::button(self.next_id(), "Back".into())
 			.with_position(Vec2::new(-0.1, -0.45))
 			.with_size(Vec2::new(0.2, 0.08))
-			.with_style(&theme.buttons.extra())
+			.with_style(theme, "buttons.extra")
 			.with_z_index(8)
 			.with_callback(|| close_pressed());
 		self.add_element(back_button);
@@ -571,30 +616,34 @@ impl UIManager {
 		let loading_panel = UIElement::panel(self.next_id())
 			.with_position(Vec2::new(-0.3, -0.1))
 			.with_size(Vec2::new(0.6, 0.2))
-			.with_style(&theme.panels.bad)
+			.with_style(theme, "panels.bad")
 			.with_z_index(10);
 		self.add_element(loading_panel);
 
-		let loading_text = UIElement::label(self.next_id(), "Loading...".into())
+		// Both are updated every frame with the spawn chunks generated so far
+		let label_id = self.next_id();
+		let loading_text = UIElement::label(label_id, "Loading...".into())
 			.with_position(Vec2::new(-0.25, -0.05))
 			.with_size(Vec2::new(0.5, 0.1))
-			.with_style(&theme.labels.basic)
+			.with_style(theme, "labels.basic")
 			.with_z_index(15);
 		self.add_element(loading_text);
 
 		let progress_bg = UIElement::panel(self.next_id())
 			.with_position(Vec2::new(-0.25, -0.15))
 			.with_size(Vec2::new(0.5, 0.03))
-			.with_style(&theme.panels.basic)
+			.with_style(theme, "panels.basic")
 			.with_z_index(8);
 		self.add_element(progress_bg);
 
-		let progress_bar = UIElement::panel(self.next_id())
+		let bar_id = self.next_id();
+		let progress_bar = UIElement::panel(bar_id)
 			.with_position(Vec2::new(-0.245, -0.145))
-			.with_size(Vec2::new(0.01, 0.02))
-			.with_style(&theme.best_button)
-			.with_z_index(8);
+			.with_size(Vec2::new(LOADING_BAR_WIDTH * 0.02, 0.02))
+			.with_style(theme, "best_button")
+			.with_z_index(9);
 		self.add_element(progress_bar);
+		self.loading_ids = (label_id, bar_id);
 	}
 
 	#[inline]
@@ -611,7 +660,7 @@ impl UIManager {
 		let save_button = UIElement::button(self.next_id(), "Save World".into())
 			.with_position(Vec2::new(-0.8, 0.15))
 			.with_size(Vec2::new(0.4, 0.08))
-			.with_style(&theme.buttons.nice)
+			.with_style(theme, "buttons.nice")
 			.with_z_index(8)
 			.with_callback(|| {
 				let save_path = ptr::get_gamestate().save_path();
@@ -624,7 +673,7 @@ impl UIManager {
 		let load_button = UIElement::button(self.next_id(), "Load World".into())
 			.with_position(Vec2::new(-0.8, 0.0))
 			.with_size(Vec2::new(0.4, 0.08))
-			.with_style(&theme.buttons.nice)
+			.with_style(theme, "buttons.nice")
 			.with_z_index(8)
 			.with_callback(|| {
 				let save_path = ptr::get_gamestate().save_path();
@@ -637,7 +686,7 @@ impl UIManager {
 		let setting_button = UIElement::button(self.next_id(), "Settings".into())
 			.with_position(Vec2::new(-0.8, -0.15))
 			.with_size(Vec2::new(0.4, 0.08))
-			.with_style(&theme.buttons.nice)
+			.with_style(theme, "buttons.nice")
 			.with_z_index(6)
 			.with_callback(|| {
 				let ui_manager = &mut ptr::get_state().ui_manager;
@@ -649,7 +698,7 @@ impl UIManager {
 		let memory_button = UIElement::button(self.next_id(), "Memory".into())
 			.with_position(Vec2::new(-0.8, -0.3))
 			.with_size(Vec2::new(0.4, 0.08))
-			.with_style(&theme.buttons.nice)
+			.with_style(theme, "buttons.nice")
 			.with_z_index(6)
 			.with_callback(|| {
 				memory::light_trim();
@@ -660,21 +709,33 @@ impl UIManager {
 		let side_panel = UIElement::panel(self.next_id())
 			.with_position(Vec2::new(0.4, -0.9))
 			.with_size(Vec2::new(0.6, 1.8))
-			.with_style(&theme.panels.basic)
+			.with_style(theme, "panels.basic")
 			.with_z_index(1);
 		self.add_element(side_panel);
 
 		let panel_title = UIElement::label(self.next_id(), "Game Menu".into())
 			.with_position(Vec2::new(0.45, 0.75))
 			.with_size(Vec2::new(0.5, 0.1))
-			.with_style(&theme.title_label)
+			.with_style(theme, "title_label")
 			.with_z_index(10);
 		self.add_element(panel_title);
 
+		// Doesn't tick while this menu is open, so it is only written once
+		if ptr::has_gamestate() {
+			let game_state = ptr::get_gamestate();
+			let played = format!("Played {}, {}", game_state.play_session().display(), game_state.time_of_day().clock_text());
+			let played_label = UIElement::label(self.next_id(), played.into())
+				.with_position(Vec2::new(0.45, 0.6))
+				.with_size(Vec2::new(0.5, 0.08))
+				.with_style(theme, "labels.basic")
+				.with_z_index(5);
+			self.add_element(played_label);
+		}
+
 		let clean_button = UIElement::button(self.next_id(), "Clean World".into())
 			.with_position(Vec2::new(0.45, 0.4))
 			.with_size(Vec2::new(0.5, 0.1))
-			.with_style(&theme.buttons.bad)
+			.with_style(theme, "buttons.bad")
 			.with_z_index(8)
 			.with_callback(|| block::extra::add_full_world());
 		self.add_element(clean_button);
@@ -682,7 +743,7 @@ impl UIManager {
 		let host_button = UIElement::button(self.next_id(), "Host World".into())
 			.with_position(Vec2::new(0.5, 0.22))
 			.with_size(Vec2::new(0.4, 0.08))
-			.with_style(&theme.buttons.extra())
+			.with_style(theme, "buttons.extra")
 			.with_z_index(8)
 			.with_callback(|| { 
 				if let Err(e) = api::begin_online_giveaway() {
@@ -692,18 +753,18 @@ impl UIManager {
 		self.add_element(host_button);
 
 		let help_texts = [
-			("ALT to lock", 0.1),
-			("L to fill chunk", -0.05),
-			("R to craft", -0.2),
-			("E to inventory", -0.35),
-			("ESC to pause", -0.5)
+			(format!("{} to lock", self.prompt(Action::MouseLock)), 0.1),
+			(format!("{} to fill chunk", self.prompt(Action::FillChunk)), -0.05),
+			(format!("{} to craft", self.prompt(Action::Craft)), -0.2),
+			(format!("{} to inventory", self.prompt(Action::Inventory)), -0.35),
+			("ESC to pause".to_string(), -0.5)
 		];
 
-		for (_i, (text, y_pos)) in help_texts.iter().enumerate() {
-			let help_text = UIElement::label(self.next_id(), (*text).into())
-				.with_position(Vec2::new(0.5, *y_pos))
+		for (text, y_pos) in help_texts {
+			let help_text = UIElement::label(self.next_id(), text.into())
+				.with_position(Vec2::new(0.5, y_pos))
 				.with_size(Vec2::new(0.4, 0.08))
-				.with_style(&theme.labels.bad)
+				.with_style(theme, "labels.bad")
 				.with_z_index(5);
 			self.add_element(help_text);
 		}
@@ -711,7 +772,7 @@ impl UIManager {
 		let back_button = UIElement::button(self.next_id(), "Back to World".into())
 			.with_position(Vec2::new(0.5, -0.8))
 			.with_size(Vec2::new(0.4, 0.08))
-			.with_style(&theme.buttons.extra())
+			.with_style(theme, "buttons.extra")
 			.with_z_index(8)
 			.with_callback(|| close_pressed());
 		self.add_element(back_button);
@@ -719,7 +780,7 @@ impl UIManager {
 		let close_button = UIElement::button(self.next_id(), "Quit World".into())
 			.with_position(Vec2::new(-0.2, -0.8))
 			.with_size(Vec2::new(0.4, 0.08))
-			.with_style(&theme.worst_button)
+			.with_style(theme, "worst_button")
 			.with_z_index(8)
 			.with_callback(|| {
 				let state = ptr::get_state();
@@ -733,19 +794,35 @@ impl UIManager {
 	#[inline]
 	fn setup_in_game_ui(&mut self) {
 		let theme = &ptr::get_settings().ui_theme;
+		// Keeps its shape on any window
 		let crosshair_v = UIElement::panel(self.next_id())
 			.with_position(Vec2::new(0.0, -0.02))
 			.with_size(Vec2::new(0.02, 0.06))
-			.with_style(&theme.panels.extra())
+			.with_style(theme, "panels.extra")
+			.with_anchor(Anchor::Center)
+			.with_keep_aspect(true)
 			.with_z_index(20);
 		let crosshair_h = UIElement::panel(self.next_id())
 			.with_position(Vec2::new(-0.02, 0.0))
 			.with_size(Vec2::new(0.06, 0.02))
 			.with_vertical(true)
-			.with_style(&theme.panels.extra())
+			.with_style(theme, "panels.extra")
+			.with_anchor(Anchor::Center)
+			.with_keep_aspect(true)
 			.with_z_index(20);
 
 		self.add_element(crosshair_v);
 		self.add_element(crosshair_h);
+
+		// Shown while the auto-save writes in the background
+		if ptr::get_gamestate().autosave().is_saving() {
+			let saving_text = UIElement::label(self.next_id(), "Saving...".into())
+				.with_position(Vec2::new(0.65, 0.88))
+				.with_size(Vec2::new(0.3, 0.08))
+				.with_style(theme, "labels.basic")
+				.with_z_index(20);
+			self.add_element(saving_text);
+		}
+		self.setup_chat_ui();
 	}
 }
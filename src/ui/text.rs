@@ -9,7 +9,7 @@ use crate::{
 	utils::color::Color,
 };
 use glam::Vec2;
-use rusttype::{Font, Scale, point};
+use rusttype::{Font, Point, Scale, point};
 use image::{ImageBuffer, Rgba};
 use winit::keyboard::KeyCode::{self as Key, *};
 
@@ -47,6 +47,44 @@ impl UIManager {
 
 
 const ELLIPSIS: &str = "...";
+/// Height of one line of a wrapped label in screen units, the element's height doesn't change the font size
+pub const WRAP_LINE_HEIGHT: f32 = 0.05;
+
+/// Breaks text into lines no wider than `max_width`, between words where it can and inside a word too long
+/// for a line of its own. Line breaks in the text are kept, `advance` is the width of a character
+pub fn wrap_lines(text: &str, max_width: f32, advance: impl Fn(char) -> f32) -> Vec<String> {
+	let mut lines = Vec::new();
+	for paragraph in text.split('\n') {
+		let mut line = String::new();
+		let mut width = 0.0;
+		for word in paragraph.split_whitespace() {
+			let word_width: f32 = word.chars().map(&advance).sum();
+			let space = if line.is_empty() { 0.0 } else { advance(' ') };
+			if width + space + word_width <= max_width {
+				if !line.is_empty() { line.push(' '); }
+				line.push_str(word);
+				width += space + word_width;
+				continue;
+			}
+			if !line.is_empty() {
+				lines.push(std::mem::take(&mut line));
+				width = 0.0;
+			}
+			// Every line gets at least one character, even if that one is too wide
+			for c in word.chars() {
+				let c_width = advance(c);
+				if width + c_width > max_width && !line.is_empty() {
+					lines.push(std::mem::take(&mut line));
+					width = 0.0;
+				}
+				line.push(c);
+				width += c_width;
+			}
+		}
+		lines.push(line);
+	}
+	lines
+}
 
 #[derive(Clone, Copy, Eq, PartialEq)]
 pub enum TruncateMode {
@@ -99,14 +137,14 @@ impl UIRenderer {
 	#[inline] pub const fn set_pixel_ratio(&mut self, ratio: f32) {
 		self.pixel_ratio = ratio.max(10.0).min(0.5);
 	}
+	/// Small elements get their text drawn sharper
+	#[inline] pub const fn pixel_ratio_for(&self, element_size: Vec2) -> f32 {
+		if element_size.x + element_size.y < 0.2 { self.pixel_ratio * 3.0 } else { self.pixel_ratio }
+	}
 
 	/// Renders text to a GPU texture with specified formatting
 	pub fn render_text_to_texture(&self, device: &wgpu::Device, queue: &wgpu::Queue, text: &str, element_size: Vec2, color: Color, truncate_mode: TruncateMode, align_mode: AlignMode) -> wgpu::Texture {
-		let pixel_ratio = if element_size.x + element_size.y < 0.2 {
-			self.pixel_ratio * 3.0
-		} else {
-			self.pixel_ratio
-		};
+		let pixel_ratio = self.pixel_ratio_for(element_size);
 
 		let target_width_px = (element_size.x * 100.0 * pixel_ratio) as u32;
 		let target_height_px = (element_size.y * 100.0 * pixel_ratio) as u32;
@@ -134,17 +172,51 @@ impl UIRenderer {
 
 		let height = (text_height + padding * 2).max(1);
 		let mut image = ImageBuffer::from_pixel(width, height, Rgba([0, 0, 0, 0]));
+		self.draw_line(&mut image, &final_text, final_scale, point(text_start_x, v_metrics.ascent + padding as f32), color);
+
+		Self::upload_text_image(device, queue, image)
+	}
+
+	/// Renders text as lines wrapped to the element's width, `WRAP_LINE_HEIGHT` apart.
+	/// The texture is as tall as the lines need, so it can be taller than the element
+	pub fn render_wrapped_text_to_texture(&self, device: &wgpu::Device, queue: &wgpu::Queue, text: &str, element_size: Vec2, color: Color, align_mode: AlignMode) -> wgpu::Texture {
+		let (lines, width, scale, padding) = self.wrap_layout(text, element_size);
+		let v_metrics = self.font.v_metrics(scale);
+		let line_step = v_metrics.ascent - v_metrics.descent + v_metrics.line_gap;
+		let text_height = v_metrics.ascent - v_metrics.descent + line_step * (lines.len() - 1) as f32;
+		let height = ((text_height + padding * 2.0).ceil() as u32).max(1);
+
+		let mut image = ImageBuffer::from_pixel(width, height, Rgba([0, 0, 0, 0]));
+		for (i, line) in lines.iter().enumerate() {
+			let x = self.calculate_x(line, scale, width as f32 - padding * 2.0, align_mode) + padding;
+			let y = padding + v_metrics.ascent + line_step * i as f32;
+			self.draw_line(&mut image, line, scale, point(x, y), color);
+		}
 
-		let adjusted_glyphs: Vec<_> = self
-			.font
-			.layout(
-				&final_text,
-				final_scale,
-				point(text_start_x, v_metrics.ascent + padding as f32),
-			)
-			.collect();
+		Self::upload_text_image(device, queue, image)
+	}
 
-		for glyph in adjusted_glyphs {
+	/// The lines a wrapped label of this size shows the text in
+	#[inline] pub fn wrap_text(&self, text: &str, element_size: Vec2) -> Vec<String> {
+		self.wrap_layout(text, element_size).0
+	}
+
+	/// Lines, texture width, font scale and padding of a wrapped label, in pixels
+	fn wrap_layout(&self, text: &str, element_size: Vec2) -> (Vec<String>, u32, Scale, f32) {
+		let pixel_ratio = self.pixel_ratio_for(element_size);
+		let width = ((element_size.x * 100.0 * pixel_ratio) as u32).max(1);
+		let font_size = WRAP_LINE_HEIGHT * 100.0 * pixel_ratio * 0.8;
+		let scale = Scale::uniform(font_size);
+		let padding = (font_size * 0.05).ceil();
+
+		let lines = wrap_lines(text, width as f32 - padding * 2.0, |c| self.font.glyph(c).scaled(scale).h_metrics().advance_width);
+		(lines, width, scale, padding)
+	}
+
+	/// Draws one line of text with its baseline starting at `start`, anything off the image is cut off
+	fn draw_line(&self, image: &mut ImageBuffer<Rgba<u8>, Vec<u8>>, text: &str, scale: Scale, start: Point<f32>, color: Color) {
+		let (width, height) = image.dimensions();
+		for glyph in self.font.layout(text, scale, start) {
 			let Some(bounding_box) = glyph.pixel_bounding_box() else {
 				continue;
 			};
@@ -161,7 +233,10 @@ impl UIRenderer {
 				}
 			});
 		}
+	}
 
+	fn upload_text_image(device: &wgpu::Device, queue: &wgpu::Queue, image: ImageBuffer<Rgba<u8>, Vec<u8>>) -> wgpu::Texture {
+		let (width, height) = image.dimensions();
 		let texture = device.create_texture(&wgpu::TextureDescriptor {
 			label: Some("Text Texture"),
 			size: wgpu::Extent3d {
@@ -0,0 +1,122 @@
+use crate::{
+	ext::ptr,
+	ui::{
+		element::UIElement,
+		manager::UIManager,
+		text::WRAP_LINE_HEIGHT,
+	},
+	utils::color::Solor,
+};
+use glam::Vec2;
+
+/// Seconds the mouse has to rest on something before its tooltip shows
+pub const TOOLTIP_DELAY: f32 = 0.4;
+/// Width of the tooltip panel in screen units, longer lines are wrapped
+pub const TOOLTIP_WIDTH: f32 = 0.6;
+/// Drawn above everything else, the text one higher
+pub const TOOLTIP_Z_INDEX: i32 = i32::MAX - 1;
+const TOOLTIP_PADDING: f32 = 0.015;
+/// Gap between the cursor and the tooltip's corner, so the cursor doesn't cover the text
+const CURSOR_GAP: f32 = 0.03;
+
+/// Where a tooltip of this size goes: right of and below the cursor, pushed back inside the screen at the edges
+pub fn tooltip_position(at: Vec2, size: Vec2) -> Vec2 {
+	let pos = Vec2::new(at.x + CURSOR_GAP, at.y - CURSOR_GAP - size.y);
+	pos.clamp(Vec2::splat(-1.), (Vec2::splat(1.) - size).max(Vec2::splat(-1.)))
+}
+
+/// Panel size fitting the text wrapped at the tooltip width, `wrap` gives the lines a label of the given size shows
+pub fn tooltip_size(text: &str, wrap: impl Fn(&str, Vec2) -> Vec<String>) -> Vec2 {
+	let label = Vec2::new(TOOLTIP_WIDTH - TOOLTIP_PADDING * 2., WRAP_LINE_HEIGHT);
+	let lines = wrap(text, label).len().max(1);
+	Vec2::new(TOOLTIP_WIDTH, lines as f32 * WRAP_LINE_HEIGHT + TOOLTIP_PADDING * 2.)
+}
+
+/// What the mouse rests on and for how long, kept across frames
+#[derive(Debug, Clone, Default)]
+pub struct Tooltip {
+	text: String, // empty while there is nothing to show
+	at: Vec2, // cursor position
+	waited: f32, // seconds the mouse has been on the same thing
+	ids: (usize, usize), // panel and label, 0 while not shown
+}
+
+impl Tooltip {
+	#[inline] pub const fn new() -> Self {
+		Self { text: String::new(), at: Vec2::ZERO, waited: 0., ids: (0, 0) }
+	}
+	#[inline] pub fn text(&self) -> &str { &self.text }
+	#[inline] pub const fn is_shown(&self) -> bool { self.ids.0 != 0 }
+
+	/// The mouse is on something with this text, false if it was on something else before (the delay starts over)
+	pub fn rest(&mut self, text: &str, at: Vec2) -> bool {
+		self.at = at;
+		if self.text == text { return true; }
+		self.text = text.to_string();
+		self.waited = 0.;
+		false
+	}
+
+	/// Counts the time the mouse rests, true on the frame the delay runs out
+	pub fn advance(&mut self, delta: f32) -> bool {
+		if self.text.is_empty() || self.is_shown() { return false; }
+		self.waited += delta;
+		self.waited >= TOOLTIP_DELAY
+	}
+}
+
+impl UIManager {
+	/// Shows the text next to the cursor once the mouse stayed on it for `TOOLTIP_DELAY`, a shown one follows the cursor
+	pub fn show_tooltip(&mut self, text: &str, at: Vec2) {
+		if text.is_empty() { self.hide_tooltip(); return; }
+		if !self.tooltip.rest(text, at) {
+			// Something else was under the mouse, its tooltip goes away at once
+			self.remove_tooltip_elements();
+			return;
+		}
+		if !self.tooltip.is_shown() { return; }
+		let (panel_id, label_id) = self.tooltip.ids;
+		let pos = tooltip_position(at, tooltip_size(text, |text, size| self.renderer().wrap_text(text, size)));
+		if let Some(panel) = self.get_element_mut(panel_id) { panel.set_position(pos); }
+		if let Some(label) = self.get_element_mut(label_id) { label.set_position(pos + Vec2::splat(TOOLTIP_PADDING)); }
+	}
+
+	pub fn hide_tooltip(&mut self) {
+		self.remove_tooltip_elements();
+		self.tooltip = Tooltip::new();
+	}
+
+	fn remove_tooltip_elements(&mut self) {
+		let (panel_id, label_id) = std::mem::take(&mut self.tooltip.ids);
+		if panel_id == 0 { return; }
+		// Not `remove_element`, the focus (a held item or an input) stays where it is
+		self.elements.retain(|e| e.id != panel_id && e.id != label_id);
+		self.mark_dirty();
+	}
+
+	/// Runs once a frame, adds the tooltip when the mouse rested long enough
+	pub fn update_tooltip(&mut self, delta: f32) {
+		if !self.tooltip.advance(delta) { return; }
+		let theme = &ptr::get_settings().ui_theme;
+		let text = self.tooltip.text().to_string();
+		let size = tooltip_size(&text, |text, size| self.renderer().wrap_text(text, size));
+		let pos = tooltip_position(self.tooltip.at, size);
+
+		let (panel_id, label_id) = (self.next_id(), self.next_id());
+		let panel = UIElement::panel(panel_id)
+			.with_position(pos)
+			.with_size(size)
+			.with_element_style(&theme.panels.basic)
+			.with_color(Solor::Black.i().with_a(220))
+			.with_z_index(TOOLTIP_Z_INDEX);
+		self.add_element(panel);
+		let label = UIElement::label(label_id, text.into())
+			.with_position(pos + Vec2::splat(TOOLTIP_PADDING))
+			.with_size(size - Vec2::splat(TOOLTIP_PADDING * 2.))
+			.with_style(theme, "labels.basic")
+			.with_wrap(true)
+			.with_z_index(TOOLTIP_Z_INDEX + 1);
+		self.add_element(label);
+		self.tooltip.ids = (panel_id, label_id);
+	}
+}
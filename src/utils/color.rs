@@ -166,6 +166,22 @@ impl Color {
 		}
 	}
 
+	/// Relative luminance as WCAG defines it, 0 for black and 1 for white (alpha ignored)
+	pub fn luminance(&self) -> f32 {
+		let linear = |c: u8| {
+			let c = c as f32 / 255.0;
+			if c <= 0.03928 { c / 12.92 } else { ((c + 0.055) / 1.055).powf(2.4) }
+		};
+		0.2126 * linear(self.r) + 0.7152 * linear(self.g) + 0.0722 * linear(self.b)
+	}
+
+	/// WCAG contrast ratio, 1 for the same color up to 21 for black on white.
+	/// Text wants at least 4.5 against its background, 7 for the high contrast level
+	pub fn contrast_ratio(&self, other: &Self) -> f32 {
+		let (a, b) = (self.luminance(), other.luminance());
+		(a.max(b) + 0.05) / (a.min(b) + 0.05)
+	}
+
 	pub fn to_hex(&self) -> String {
 		format!("#{:02X}{:02X}{:02X}{:02X}", self.r, self.g, self.b, self.a)
 	}
@@ -246,7 +262,7 @@ impl Color {
 		let h = if delta == 0.0 {
 		0.0
 		} else if max == r {
-		60.0 * (((g - b) / delta) % 6.0)
+		60.0 * ((g - b) / delta).rem_euclid(6.0) // magenta side would come out negative with %
 		} else if max == g {
 		60.0 * (((b - r) / delta) + 2.0)
 		} else {
@@ -259,6 +275,44 @@ impl Color {
 		(h / 360.0, s, v) // Normalize hue to 0-1
 	}
 
+	/// From hue (0-1, wraps around), saturation and value (0-1), opaque like `rgb`
+	pub fn from_hsv(h: f32, s: f32, v: f32) -> Self {
+		let s = s.clamp(0.0, 1.0);
+		let v = v.clamp(0.0, 1.0);
+		let h = h.rem_euclid(1.0) * 6.0;
+
+		let c = v * s;
+		let x = c * (1.0 - (h % 2.0 - 1.0).abs());
+		let m = v - c;
+		let (r, g, b) = match h as u32 {
+			0 => (c, x, 0.0),
+			1 => (x, c, 0.0),
+			2 => (0.0, c, x),
+			3 => (0.0, x, c),
+			4 => (x, 0.0, c),
+			_ => (c, 0.0, x), // 5, and 6 when a hue just under 0 rounds up to a full turn
+		};
+		let channel = |f: f32| ((f + m) * 255.0).round() as u8;
+		Self::rgb(channel(r), channel(g), channel(b))
+	}
+
+	/// Blends around the hue wheel (the short way) instead of straight across RGB, red to blue passes magenta not gray.
+	/// A gray end has no hue so it takes the other end's, alpha is blended like `lerp`
+	pub fn lerp_hsv(self, other: Self, t: f32) -> Self {
+		let t = t.clamp(0.0, 1.0);
+		let (h1, s1, v1) = self.to_hsv();
+		let (h2, s2, v2) = other.to_hsv();
+		let (h1, h2) = match (s1 == 0.0, s2 == 0.0) {
+			(true, false) => (h2, h2),
+			(false, true) => (h1, h1),
+			_ => (h1, h2),
+		};
+		// Signed step of at most half a turn
+		let step = (h2 - h1 + 0.5).rem_euclid(1.0) - 0.5;
+		let lerp = |a: f32, b: f32| a + (b - a) * t;
+		Self::from_hsv(h1 + step * t, lerp(s1, s2), lerp(v1, v2)).with_a(lerp_u8(self.a, other.a, t))
+	}
+
 	pub fn from_hsl(h: f32, s: f32, l: f32) -> Self {
 		let h = h.clamp(0.0, 1.0);
 		let s = s.clamp(0.0, 1.0);
@@ -413,7 +467,7 @@ fn hue_to_rgb(p: f32, q: f32, t: f32) -> f32 {
 	p
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Border {
 	pub color: Color,
 	pub width: f32,
@@ -430,6 +484,52 @@ impl Border {
 	#[inline]pub const fn col(color: Color) -> Self { Self { color, width:0.0 } }
 }
 
+/// The handful of colors a UI theme is made of, `UITheme::from_palette` spreads them over every style
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Palette {
+	pub background: Color, // behind the whole menu
+	pub surface: Color, // panels, and labels sit on them
+	pub control: Color, // buttons, sliders, checkboxes and inputs
+	pub border: Color,
+	pub accent: Color, // frames of the highlighted elements
+	pub text: Color,
+	pub muted_text: Color, // less important labels
+	pub good: Color, // confirm buttons
+	pub bad: Color, // delete and cancel buttons
+	pub on_color: Color, // text on the good and bad fills
+	pub border_width: f32, // of a plain frame, highlighted ones are a bit thicker
+}
+
+impl Palette {
+	pub const LIGHT: Self = Self {
+		background: Color::rgb(232, 235, 240),
+		surface: Color::rgb(248, 249, 251),
+		control: Color::rgb(214, 222, 236),
+		border: Color::rgb(140, 152, 180),
+		accent: Color::rgb(40, 90, 180),
+		text: Color::rgb(25, 30, 45),
+		muted_text: Color::rgb(75, 85, 105),
+		good: Color::rgb(30, 120, 45),
+		bad: Color::rgb(185, 40, 40),
+		on_color: Color::WHITE,
+		border_width: 0.005,
+	};
+	/// White text on black with thick frames, every text clears the 7:1 contrast level
+	pub const HIGH_CONTRAST: Self = Self {
+		background: Color::BLACK,
+		surface: Color::BLACK,
+		control: Color::rgb(20, 20, 20),
+		border: Color::WHITE,
+		accent: Color::YELLOW,
+		text: Color::WHITE,
+		muted_text: Color::rgb(230, 230, 230),
+		good: Color::rgb(0, 100, 0),
+		bad: Color::rgb(150, 0, 0),
+		on_color: Color::WHITE,
+		border_width: 0.009,
+	};
+}
+
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub enum Solor {
 	// Basic colors
@@ -0,0 +1,198 @@
+use crate::ext::settings::GamepadConfig;
+use crate::utils::input::Action;
+use glam::Vec2;
+
+// The sticks and buttons feed the same `Action`s the keys do: the held state is copied into the
+// `Keyboard`, presses come back from `InputSystem::poll_gamepad` for the event handler to run.
+// Reading the device needs gilrs, without the gamepad feature nothing is ever connected.
+
+/// Rescales a stick so the deadzone reads as rest and the edge still reaches full tilt
+pub fn apply_deadzone(stick: Vec2, deadzone: f32) -> Vec2 {
+	let length = stick.length();
+	if length <= deadzone || deadzone >= 1. { return Vec2::ZERO; }
+	stick / length * ((length.min(1.) - deadzone) / (1. - deadzone))
+}
+
+/// What the gamepad holds, kept in the `Keyboard` so held actions read the same from both
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PadState {
+	pub movement: Vec2, // left stick after the deadzone, y up (zero while it moves the menu cursor)
+	pub look: Vec2, // right stick after the deadzone, y up
+	buttons: u16, // a bit per `Action`
+}
+
+impl PadState {
+	#[inline] pub const fn default() -> Self {
+		Self { movement: Vec2::ZERO, look: Vec2::ZERO, buttons: 0 }
+	}
+	#[inline] pub const fn is_button_down(&self, action: Action) -> bool {
+		self.buttons & (1 << action as u16) != 0
+	}
+	#[inline] pub const fn set_button(&mut self, action: Action, down: bool) {
+		if down { self.buttons |= 1 << action as u16; } else { self.buttons &= !(1 << action as u16); }
+	}
+	/// A button held, or for walking the left stick pointing that way
+	pub const fn is_action_down(&self, action: Action) -> bool {
+		match action {
+			Action::Forward => self.movement.y > 0.,
+			Action::Backward => self.movement.y < 0.,
+			Action::Left => self.movement.x < 0.,
+			Action::Right => self.movement.x > 0.,
+			_ => self.is_button_down(action),
+		}
+	}
+	#[inline] pub fn is_idle(&self) -> bool {
+		self.buttons == 0 && self.movement == Vec2::ZERO && self.look == Vec2::ZERO
+	}
+}
+
+/// What one poll found, for the event handler to act on
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PadPoll {
+	pub presses: Vec<(Action, bool)>, // buttons that went down (true) or up since the last poll, in order
+	pub look: Vec2, // camera turn in mouse pixels, like a mouse movement (y down)
+	pub cursor: Vec2, // menu cursor movement in window sizes (y down)
+}
+
+impl PadPoll {
+	#[inline] pub fn is_empty(&self) -> bool {
+		self.presses.is_empty() && self.look == Vec2::ZERO && self.cursor == Vec2::ZERO
+	}
+}
+
+/// The connected gamepads as one, read once a frame by `InputSystem::poll_gamepad`
+pub struct Gamepad {
+	#[cfg(feature = "gamepad")]
+	gilrs: Option<gilrs::Gilrs>, // started on the first poll
+	#[cfg(feature = "gamepad")]
+	unavailable: bool, // gilrs could not start, it isn't tried again
+	left: Vec2, // raw sticks, y up
+	right: Vec2,
+	state: PadState,
+	presses: Vec<(Action, bool)>,
+}
+
+impl std::fmt::Debug for Gamepad {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.debug_struct("Gamepad").field("left", &self.left).field("right", &self.right).field("state", &self.state).finish()
+	}
+}
+
+impl Gamepad {
+	#[inline] pub const fn new() -> Self {
+		Self {
+			#[cfg(feature = "gamepad")]
+			gilrs: None,
+			#[cfg(feature = "gamepad")]
+			unavailable: false,
+			left: Vec2::ZERO,
+			right: Vec2::ZERO,
+			state: PadState::default(),
+			presses: Vec::new(),
+		}
+	}
+	#[inline] pub const fn state(&self) -> &PadState { &self.state }
+
+	/// A mapped button changed, false if it already was that way
+	pub fn button(&mut self, action: Action, down: bool) -> bool {
+		if self.state.is_button_down(action) == down { return false; }
+		self.state.set_button(action, down);
+		self.presses.push((action, down));
+		true
+	}
+	/// Raw stick positions, y up
+	#[inline] pub const fn set_sticks(&mut self, left: Vec2, right: Vec2) {
+		self.left = left;
+		self.right = right;
+	}
+	/// Lets go of everything, the releases are still reported
+	pub fn release_all(&mut self) {
+		for action in Action::ALL { self.button(action, false); }
+		self.set_sticks(Vec2::ZERO, Vec2::ZERO);
+	}
+
+	/// Reads the device and turns what changed into a poll, the left stick moves the cursor in menus instead of the player
+	pub fn poll(&mut self, delta: f32, config: &GamepadConfig, in_menu: bool) -> PadPoll {
+		self.read_events();
+		self.update(delta, config, in_menu)
+	}
+
+	/// The poll from the sticks and buttons set so far
+	pub fn update(&mut self, delta: f32, config: &GamepadConfig, in_menu: bool) -> PadPoll {
+		let left = apply_deadzone(self.left, config.left_deadzone);
+		self.state.look = apply_deadzone(self.right, config.right_deadzone);
+		self.state.movement = if in_menu { Vec2::ZERO } else { left };
+		let flip = Vec2::new(1., -1.);
+		PadPoll {
+			presses: std::mem::take(&mut self.presses),
+			look: if in_menu { Vec2::ZERO } else { self.state.look * flip * config.look_speed * delta },
+			cursor: if in_menu { left * flip * config.cursor_speed * delta } else { Vec2::ZERO },
+		}
+	}
+
+	#[cfg(not(feature = "gamepad"))]
+	#[inline] fn read_events(&mut self) {}
+
+	#[cfg(feature = "gamepad")]
+	fn read_events(&mut self) {
+		use gilrs::{Axis, EventType};
+		if self.unavailable { return; }
+		if self.gilrs.is_none() {
+			match gilrs::Gilrs::new() {
+				Ok(gilrs) => self.gilrs = Some(gilrs),
+				Err(e) => {
+					println!("Gamepads not available: {}", e);
+					self.unavailable = true;
+					return;
+				}
+			}
+		}
+		let Some(gilrs) = &mut self.gilrs else { return; };
+		let mut events = Vec::new();
+		while let Some(event) = gilrs.next_event() { events.push(event.event); }
+		for event in events {
+			match event {
+				EventType::ButtonPressed(button, _) => if let Some(action) = button_action(button) { self.button(action, true); },
+				EventType::ButtonReleased(button, _) => if let Some(action) = button_action(button) { self.button(action, false); },
+				EventType::AxisChanged(Axis::LeftStickX, value, _) => self.left.x = value,
+				EventType::AxisChanged(Axis::LeftStickY, value, _) => self.left.y = value,
+				EventType::AxisChanged(Axis::RightStickX, value, _) => self.right.x = value,
+				EventType::AxisChanged(Axis::RightStickY, value, _) => self.right.y = value,
+				EventType::Disconnected => self.release_all(),
+				_ => {}
+			}
+		}
+	}
+}
+
+/// Name of the gamepad button an action is on, for the help texts (the same buttons as `button_action`)
+pub const fn button_label(action: Action) -> Option<&'static str> {
+	match action {
+		Action::Jump => Some("A"),
+		Action::Sneak => Some("B"),
+		Action::Run => Some("L3"),
+		Action::Break => Some("RT"),
+		Action::Place => Some("LT"),
+		Action::Inventory => Some("Y"),
+		Action::Craft => Some("X"),
+		Action::MouseLock => Some("Select"),
+		_ => None,
+	}
+}
+
+/// Action of a gamepad button, jump also clicks in the menus
+#[cfg(feature = "gamepad")]
+const fn button_action(button: gilrs::Button) -> Option<Action> {
+	use gilrs::Button;
+	match button {
+		Button::South => Some(Action::Jump),
+		Button::East => Some(Action::Sneak),
+		Button::LeftThumb => Some(Action::Run),
+		Button::RightTrigger2 => Some(Action::Break),
+		Button::LeftTrigger2 => Some(Action::Place),
+		Button::North => Some(Action::Inventory),
+		Button::West => Some(Action::Craft),
+		Button::Select => Some(Action::MouseLock),
+		_ => None,
+	}
+}
@@ -4,6 +4,8 @@ use winit::keyboard::ModifiersState;
 use winit::dpi::PhysicalPosition;
 use winit::keyboard::KeyCode as Key;
 use std::time::Instant;
+use crate::ext::settings::{key_bindings, GamepadConfig};
+use crate::utils::gamepad::{Gamepad, PadPoll, PadState};
 
 #[derive(Debug, Clone)]
 pub struct DragSample {
@@ -35,6 +37,18 @@ pub struct InputSystem {
 	keyboard: Keyboard,
 	drag_state: DragState,
 	mouse_captured: bool,
+	last_left_press: Option<(Instant, PhysicalPosition<f64>)>,
+	click_mode: ClickMode, // mode of the last press / release (Double is only reported on press)
+	gamepad: Gamepad,
+	last_device: InputDevice, // the one the player used last, the help texts name its buttons and only it moves the menu cursor
+	pad_cursor: Option<PhysicalPosition<f64>>, // where the gamepad put the cursor, the system reports that move back
+}
+
+/// Where the input comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputDevice {
+	KeyboardMouse,
+	Gamepad,
 }
 
 macro_rules! setter_method {
@@ -63,6 +77,10 @@ impl InputSystem {
 	getter_method!(modifiers: ModifiersState);
 	getter_method!(drag_state: DragState);
 	getter_method!(keyboard: Keyboard);
+	getter_method!(click_mode: ClickMode);
+	getter_method!(last_device: InputDevice);
+	getter_method!(gamepad: Gamepad);
+	#[inline] pub const fn gamepad_mut(&mut self) -> &mut Gamepad { &mut self.gamepad }
 
 	#[inline] pub const fn default() -> Self {
 		Self {
@@ -72,15 +90,46 @@ impl InputSystem {
 			keyboard: Keyboard::default(),
 			drag_state: DragState::NotDragging,
 			mouse_captured: false,
+			last_left_press: None,
+			click_mode: ClickMode::Left,
+			gamepad: Gamepad::new(),
+			last_device: InputDevice::KeyboardMouse,
+			pad_cursor: None,
 		}
 	}
 
 	#[inline] pub fn clear(&mut self) {
 		let is_mouse_captured: bool = self.is_mouse_captured();
+		// The gamepad stays connected, only what it held is let go
+		let mut gamepad = std::mem::replace(&mut self.gamepad, Gamepad::new());
+		gamepad.release_all();
 		*self = Self::default();
+		self.gamepad = gamepad;
 		self.set_mouse_captured(is_mouse_captured);
 	}
 
+	/// Reads the gamepad, runs once a frame. Its held buttons and stick go into the keyboard,
+	/// outside the game (mouse not captured) the left stick moves the cursor instead of the player
+	pub fn poll_gamepad(&mut self, delta: f32) -> PadPoll {
+		let poll = self.gamepad.poll(delta, &GamepadConfig::current(), !self.mouse_captured);
+		self.keyboard.pad = *self.gamepad.state();
+		if !poll.is_empty() || !self.keyboard.pad.is_idle() { self.last_device = InputDevice::Gamepad; }
+		poll
+	}
+	/// A key, button or wheel of the keyboard and mouse was used
+	#[inline] pub const fn use_keyboard_mouse(&mut self) {
+		self.last_device = InputDevice::KeyboardMouse;
+	}
+	/// The cursor moved on the system's side, only a move the gamepad didn't cause counts as using the mouse
+	pub fn cursor_moved(&mut self, position: PhysicalPosition<f64>) {
+		let by_pad = self.pad_cursor.take().is_some_and(|pad| (pad.x - position.x).abs() < 1. && (pad.y - position.y).abs() < 1.);
+		if !by_pad { self.last_device = InputDevice::KeyboardMouse; }
+	}
+	/// The gamepad put the cursor here
+	#[inline] pub const fn set_pad_cursor(&mut self, position: PhysicalPosition<f64>) {
+		self.pad_cursor = Some(position);
+	}
+
 	pub fn handle_key_input(&mut self, key: Key, is_pressed: bool) {
 		match key {
 			// Movement keys (keeping original)
@@ -158,6 +207,9 @@ impl InputSystem {
 			MouseButton::Forward => self.mouse_button_state.forward = pressed,
 			MouseButton::Other(_) => {},
 		}
+		// Actions can be bound to mouse buttons too
+		self.keyboard.mouse = self.mouse_button_state;
+		self.click_mode = self.detect_click_mode(button, pressed, position);
 
 		if pressed {
 			if !self.is_dragging() && self.is_any_mouse_button_pressed() {
@@ -170,6 +222,24 @@ impl InputSystem {
 		}
 	}
 
+	/// Turns a second left press into `ClickMode::Double`, a third press starts counting again
+	fn detect_click_mode(&mut self, button: MouseButton, pressed: bool, position: PhysicalPosition<f64>) -> ClickMode {
+		// Back / Forward / Other are not click modes, keep the last one
+		if !matches!(button, MouseButton::Left | MouseButton::Right | MouseButton::Middle) { return self.click_mode; }
+		let mode = ClickMode::from(button);
+		if !pressed || mode != ClickMode::Left { return mode; }
+
+		let now = Instant::now();
+		if let Some((time, pos)) = self.last_left_press.take() {
+			let (dx, dy) = (position.x - pos.x, position.y - pos.y);
+			if now.duration_since(time).as_secs_f32() <= DOUBLE_CLICK_TIME && dx * dx + dy * dy <= DOUBLE_CLICK_DISTANCE * DOUBLE_CLICK_DISTANCE {
+				return ClickMode::Double;
+			}
+		}
+		self.last_left_press = Some((now, position));
+		mode
+	}
+
 	#[inline] pub fn handle_mouse_move(&mut self, position: PhysicalPosition<f64>) {
 		if self.is_dragging() {
 			self.update_drag(position);
@@ -286,7 +356,12 @@ pub enum ClickMode {
 	Left,
 	Right,
 	Middle,
+	Double, // second left press in a short time at (almost) the same spot
 }
+/// Max time between two left presses to count as a double click
+pub const DOUBLE_CLICK_TIME: f32 = 0.3;
+/// Max distance (in pixels) the mouse can move between the two presses of a double click
+pub const DOUBLE_CLICK_DISTANCE: f64 = 4.;
 
 impl ClickMode {
 	#[inline] pub const fn from(button: MouseButton) -> Self {
@@ -324,6 +399,161 @@ impl MouseButtonState {
 
 
 
+/// Something the player does, bound to a key or a mouse button by `KeyBindings`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum Action {
+	Forward = 0,
+	Backward = 1,
+	Left = 2,
+	Right = 3,
+	Jump = 4, // up while flying
+	Sneak = 5, // down while flying
+	Run = 6,
+	MouseLock = 7, // frees or captures the mouse
+	Chat = 8,
+	Inventory = 9,
+	Craft = 10,
+	FillChunk = 11,
+	Break = 12,
+	Place = 13,
+	Fly = 14, // no-clip flying on and off
+}
+
+impl Action {
+	pub const COUNT: usize = 15;
+	pub const ALL: [Self; Self::COUNT] = [
+		Self::Forward, Self::Backward, Self::Left, Self::Right, Self::Jump, Self::Sneak, Self::Run,
+		Self::MouseLock, Self::Chat, Self::Inventory, Self::Craft, Self::FillChunk, Self::Break, Self::Place,
+		Self::Fly,
+	];
+
+	/// Shown on the controls screen
+	#[inline] pub const fn name(self) -> &'static str {
+		match self {
+			Self::Forward => "Forward",
+			Self::Backward => "Backward",
+			Self::Left => "Left",
+			Self::Right => "Right",
+			Self::Jump => "Jump",
+			Self::Sneak => "Sneak",
+			Self::Run => "Run",
+			Self::MouseLock => "Lock mouse",
+			Self::Chat => "Chat",
+			Self::Inventory => "Inventory",
+			Self::Craft => "Craft",
+			Self::FillChunk => "Fill chunk",
+			Self::Break => "Break block",
+			Self::Place => "Place block",
+			Self::Fly => "Fly",
+		}
+	}
+	/// Key of the action in the saved bindings
+	#[inline] pub const fn config_name(self) -> &'static str {
+		match self {
+			Self::Forward => "forward",
+			Self::Backward => "backward",
+			Self::Left => "left",
+			Self::Right => "right",
+			Self::Jump => "jump",
+			Self::Sneak => "sneak",
+			Self::Run => "run",
+			Self::MouseLock => "mouse_lock",
+			Self::Chat => "chat",
+			Self::Inventory => "inventory",
+			Self::Craft => "craft",
+			Self::FillChunk => "fill_chunk",
+			Self::Break => "break",
+			Self::Place => "place",
+			Self::Fly => "fly",
+		}
+	}
+	/// The keys the game always used
+	#[inline] pub const fn default_binding(self) -> Binding {
+		match self {
+			Self::Forward => Binding::Key(Key::KeyW),
+			Self::Backward => Binding::Key(Key::KeyS),
+			Self::Left => Binding::Key(Key::KeyA),
+			Self::Right => Binding::Key(Key::KeyD),
+			Self::Jump => Binding::Key(Key::Space),
+			Self::Sneak => Binding::Key(Key::ControlLeft),
+			Self::Run => Binding::Key(Key::ShiftLeft),
+			Self::MouseLock => Binding::Key(Key::AltLeft),
+			Self::Chat => Binding::Key(Key::KeyT),
+			Self::Inventory => Binding::Key(Key::KeyE),
+			Self::Craft => Binding::Key(Key::KeyR),
+			Self::FillChunk => Binding::Key(Key::KeyG),
+			Self::Break => Binding::Mouse(MouseButton::Left),
+			Self::Place => Binding::Mouse(MouseButton::Right),
+			Self::Fly => Binding::Key(Key::KeyF),
+		}
+	}
+}
+
+/// Keys an action can be bound to, the ones `Keyboard` keeps track of (right modifiers count as the left ones).
+/// Z and Y are left out, they are undo and redo
+pub const BINDABLE_KEYS: [Key; 29] = [
+	Key::KeyA, Key::KeyB, Key::KeyC, Key::KeyD, Key::KeyE, Key::KeyF, Key::KeyG, Key::KeyH, Key::KeyI,
+	Key::KeyJ, Key::KeyK, Key::KeyL, Key::KeyM, Key::KeyN, Key::KeyO, Key::KeyP, Key::KeyQ, Key::KeyR,
+	Key::KeyS, Key::KeyT, Key::KeyU, Key::KeyV, Key::KeyW, Key::KeyX,
+	Key::Space, Key::ShiftLeft, Key::ControlLeft, Key::AltLeft, Key::SuperLeft,
+];
+/// Mouse buttons an action can be bound to
+pub const BINDABLE_BUTTONS: [MouseButton; 5] = [
+	MouseButton::Left, MouseButton::Right, MouseButton::Middle, MouseButton::Back, MouseButton::Forward,
+];
+
+/// A key or a mouse button
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Binding {
+	Key(Key),
+	Mouse(MouseButton),
+}
+
+impl Binding {
+	/// The right modifiers act as the left ones, so either side works
+	#[inline] pub const fn normalized(self) -> Self {
+		match self {
+			Self::Key(Key::ShiftRight) => Self::Key(Key::ShiftLeft),
+			Self::Key(Key::ControlRight) => Self::Key(Key::ControlLeft),
+			Self::Key(Key::AltRight) => Self::Key(Key::AltLeft),
+			Self::Key(Key::SuperRight) => Self::Key(Key::SuperLeft),
+			other => other,
+		}
+	}
+	#[inline] pub fn is_bindable(self) -> bool {
+		match self.normalized() {
+			Self::Key(key) => BINDABLE_KEYS.contains(&key),
+			Self::Mouse(button) => BINDABLE_BUTTONS.contains(&button),
+		}
+	}
+	/// Name in the saved bindings, like `KeyW` or `MouseLeft`
+	pub fn config_name(self) -> String {
+		match self.normalized() {
+			Self::Key(key) => format!("{:?}", key),
+			Self::Mouse(button) => format!("Mouse{:?}", button),
+		}
+	}
+	/// The bindable key or button with this config name
+	pub fn from_config_name(name: &str) -> Option<Self> {
+		BINDABLE_KEYS.iter().map(|key| Self::Key(*key))
+			.chain(BINDABLE_BUTTONS.iter().map(|button| Self::Mouse(*button)))
+			.find(|binding| binding.config_name() == name)
+	}
+	/// Shown on buttons and help texts, like `W`, `Shift` or `Mouse Left`
+	pub fn label(self) -> String {
+		match self.normalized() {
+			Self::Key(Key::ControlLeft) => "Ctrl".to_string(),
+			Self::Key(key) => {
+				let name = format!("{:?}", key);
+				let name = name.strip_prefix("Key").unwrap_or(&name);
+				name.strip_suffix("Left").unwrap_or(name).to_string()
+			}
+			Self::Mouse(button) => format!("Mouse {:?}", button),
+		}
+	}
+}
+
 /// Input mapping configuration for flexible key binding
 #[derive(Debug, Clone)]
 pub struct InputMapping {
@@ -338,13 +568,13 @@ pub struct InputMapping {
 impl InputMapping {
 	pub const fn default() -> Self {
 		Self {
-			forward: |kb| kb.w,
-			backward: |kb| kb.s,
-			left: |kb| kb.a,
-			right: |kb| kb.d,
-			up: |kb| kb.space,
-			down: |kb| kb.is_ctrl(),
-			run: |kb| kb.is_shift(),
+			forward: |kb| kb.is_action_down(Action::Forward),
+			backward: |kb| kb.is_action_down(Action::Backward),
+			left: |kb| kb.is_action_down(Action::Left),
+			right: |kb| kb.is_action_down(Action::Right),
+			up: |kb| kb.is_action_down(Action::Jump),
+			down: |kb| kb.is_action_down(Action::Sneak),
+			run: |kb| kb.is_action_down(Action::Run),
 		}
 	}
 }
@@ -387,6 +617,11 @@ pub struct Keyboard {
 	pub ctrl_left: bool, pub ctrl_right: bool,
 	pub alt_left: bool, pub alt_right: bool,
 	pub super_left: bool, pub super_right: bool,  // Windows key / Cmd key
+
+	// Held mouse buttons, for actions bound to them
+	pub mouse: MouseButtonState,
+	// Held gamepad buttons and its left stick
+	pub pad: PadState,
 }
 
 impl Keyboard {
@@ -428,6 +663,9 @@ impl Keyboard {
 			ctrl_left: false, ctrl_right: false,
 			alt_left: false, alt_right: false,
 			super_left: false, super_right: false,
+
+			mouse: MouseButtonState::default(),
+			pad: PadState::default(),
 		}
 	}
 	
@@ -444,4 +682,37 @@ impl Keyboard {
 	#[inline] pub const fn is_super(&self) -> bool {
 		self.super_left || self.super_right
 	}
+
+	/// Whether the key or button is held, only the bindable ones are tracked
+	pub const fn is_down(&self, binding: Binding) -> bool {
+		match binding {
+			Binding::Mouse(button) => match button {
+				MouseButton::Left => self.mouse.left,
+				MouseButton::Right => self.mouse.right,
+				MouseButton::Middle => self.mouse.middle,
+				MouseButton::Back => self.mouse.back,
+				MouseButton::Forward => self.mouse.forward,
+				MouseButton::Other(_) => false,
+			},
+			Binding::Key(key) => match key {
+				Key::KeyA => self.a, Key::KeyB => self.b, Key::KeyC => self.c, Key::KeyD => self.d,
+				Key::KeyE => self.e, Key::KeyF => self.f, Key::KeyG => self.g, Key::KeyH => self.h,
+				Key::KeyI => self.i, Key::KeyJ => self.j, Key::KeyK => self.k, Key::KeyL => self.l,
+				Key::KeyM => self.m, Key::KeyN => self.n, Key::KeyO => self.o, Key::KeyP => self.p,
+				Key::KeyQ => self.q, Key::KeyR => self.r, Key::KeyS => self.s, Key::KeyT => self.t,
+				Key::KeyU => self.u, Key::KeyV => self.v, Key::KeyW => self.w, Key::KeyX => self.x,
+				Key::KeyY => self.y, Key::KeyZ => self.z,
+				Key::Space => self.space,
+				Key::ShiftLeft | Key::ShiftRight => self.is_shift(),
+				Key::ControlLeft | Key::ControlRight => self.is_ctrl(),
+				Key::AltLeft | Key::AltRight => self.is_alt(),
+				Key::SuperLeft | Key::SuperRight => self.is_super(),
+				_ => false,
+			},
+		}
+	}
+	/// Whether the key or button bound to the action is held, or the gamepad does it
+	#[inline] pub fn is_action_down(&self, action: Action) -> bool {
+		self.is_down(key_bindings().get(action)) || self.pad.is_action_down(action)
+	}
 }
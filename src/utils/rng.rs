@@ -108,6 +108,51 @@ impl Rand {
     }
 }
 
+/// Random choices tied to a world position instead of a running state, for the generation:
+/// the same (seed, coord) always gives the same sequence whatever order or thread asks for it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SeededRng {
+	state: u32,
+}
+
+impl SeededRng {
+	#[inline] pub const fn new(state: u32) -> Self {
+		Self { state }
+	}
+
+	/// Generator for one block or cell, each coordinate is mixed in on its own so swapping them gives another sequence
+	pub const fn from_coord(seed: u32, x: i32, y: i32, z: i32) -> Self {
+		let hash = Rand::pcg_hash(seed);
+		let hash = Rand::pcg_hash(hash ^ x as u32);
+		let hash = Rand::pcg_hash(hash ^ y as u32);
+		Self::new(Rand::pcg_hash(hash ^ z as u32))
+	}
+
+	/// Counter based, so the sequence never gets stuck the way a plain xorshift does on 0
+	#[inline] pub const fn next_u32(&mut self) -> u32 {
+		self.state = self.state.wrapping_add(0x9e37_79b9);
+		Rand::pcg_hash(self.state)
+	}
+
+	/// Random f32 in [0, 1), from the top 24 bits so every value is equally likely
+	#[inline] pub const fn next_f32(&mut self) -> f32 {
+		(self.next_u32() >> 8) as f32 / (1u32 << 24) as f32
+	}
+
+	/// Random i32 in [min, max), min when the range is empty
+	#[inline] pub const fn next_range(&mut self, min: i32, max: i32) -> i32 {
+		if max <= min { return min; }
+		let span = max.wrapping_sub(min) as u32 as u64;
+		// Multiply and shift instead of a modulo, no bias toward the low end
+		min.wrapping_add(((self.next_u32() as u64 * span) >> 32) as i32)
+	}
+
+	/// True with probability `p`, never for 0 or less and always for 1 or more
+	#[inline] pub const fn chance(&mut self, p: f32) -> bool {
+		self.next_f32() < p
+	}
+}
+
 // ========== Convenience Functions ==========
 
 /// Returns a random f32 in [min, max) using a time-based seed
@@ -273,6 +318,47 @@ impl Noise {
 		(n0 + n1 + n2) * 35.0
 	}
 
+	#[inline]
+	fn grad_3d(seed: i32, x_primed: i32, y_primed: i32, z_primed: i32, xd: f32, yd: f32, zd: f32) -> f32 {
+		let hash = Self::hash(seed, x_primed, y_primed) ^ z_primed;
+		let hash = hash.wrapping_mul(0x27d4eb2d);
+		let hash = hash ^ (hash >> 15);
+		let [xg, yg, zg] = GRADIENTS_3D[((hash >> 24) & 15) as usize];
+		xd * xg + yd * yg + zd * zg
+	}
+
+	#[inline(always)]
+	const fn fade(t: f32) -> f32 {
+		t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+	}
+
+	/// 3D gradient noise in about -1..1, zero on every lattice point
+	/// Only depends on the seed and the position, so samples from neighbouring chunks line up
+	pub fn noise_3d(&self, x: f32, y: f32, z: f32) -> f32 {
+		const PRIME_X: i32 = 0x5205402B;
+		const PRIME_Y: i32 = 0x5AC0E4F1;
+		const PRIME_Z: i32 = 0x1F1F1F1F;
+		let seed = self.seed as i32;
+
+		let (i, j, k) = (Self::floor(x), Self::floor(y), Self::floor(z));
+		let (xd, yd, zd) = (x - i as f32, y - j as f32, z - k as f32);
+		let (u, v, w) = (Self::fade(xd), Self::fade(yd), Self::fade(zd));
+
+		// Prime the corners
+		let (x0, y0, z0) = (i.wrapping_mul(PRIME_X), j.wrapping_mul(PRIME_Y), k.wrapping_mul(PRIME_Z));
+		let (x1, y1, z1) = (x0.wrapping_add(PRIME_X), y0.wrapping_add(PRIME_Y), z0.wrapping_add(PRIME_Z));
+
+		let lerp = |a: f32, b: f32, t: f32| a + (b - a) * t;
+		let edge = |yp: i32, zp: i32, yd: f32, zd: f32| lerp(
+			Self::grad_3d(seed, x0, yp, zp, xd, yd, zd),
+			Self::grad_3d(seed, x1, yp, zp, xd - 1.0, yd, zd),
+			u,
+		);
+		let near = lerp(edge(y0, z0, yd, zd), edge(y1, z0, yd - 1.0, zd), v);
+		let far = lerp(edge(y0, z1, yd, zd - 1.0), edge(y1, z1, yd - 1.0, zd - 1.0), v);
+		lerp(near, far, w)
+	}
+
 	// Smoother fractal noise with lower frequency and gentler octaves
 	pub fn fractal_noise_2d(&self, x: i32, y: i32) -> f32 {
 		const BASE_FREQ: f32 = 0.0017;       // Reduced for larger features
@@ -380,6 +466,40 @@ impl Noise {
 }
 
 
+/// Dev tools for tuning the terrain without loading a world
+#[cfg(debug_assertions)]
+impl Noise {
+	/// The surface heights `Chunk::generate` builds over `size` columns from `origin` (world XZ), row by row along X
+	pub fn heightmap(seed: u32, origin: glam::IVec2, size: glam::UVec2) -> Vec<i32> {
+		use crate::{block::main::Chunk, world::biome::BiomeGen};
+		let biome_gen = BiomeGen::new(seed);
+		(0..size.y as i32).flat_map(|z| (0..size.x as i32).map(move |x| (x, z)))
+			.map(|(x, z)| Chunk::generated_surface(biome_gen.height(origin.x + x, origin.y + z)))
+			.collect()
+	}
+
+	/// Writes the `heightmap` as a grayscale PNG, the lowest column black and the highest white.
+	/// Gives back those two heights, gray level `v` is `lowest + v / 255 * (highest - lowest)`
+	pub fn export_heightmap(seed: u32, origin: glam::IVec2, size: glam::UVec2, path: &std::path::Path) -> image::ImageResult<(i32, i32)> {
+		let heights = Self::heightmap(seed, origin, size);
+		let lowest = heights.iter().copied().min().unwrap_or(0);
+		let highest = heights.iter().copied().max().unwrap_or(0);
+		let range = (highest - lowest).max(1) as f32;
+		let pixels: Vec<u8> = heights.iter().map(|&h| ((h - lowest) as f32 / range * 255.).round() as u8).collect();
+		if let Some(dir) = path.parent() { std::fs::create_dir_all(dir)?; }
+		image::save_buffer(path, &pixels, size.x, size.y, image::ExtendedColorType::L8)?;
+		Ok((lowest, highest))
+	}
+}
+
+// The 12 cube edge directions, 4 of them twice so a hash picks one with a mask
+const GRADIENTS_3D: [[f32; 3]; 16] = [
+	[1., 1., 0.], [-1., 1., 0.], [1., -1., 0.], [-1., -1., 0.],
+	[1., 0., 1.], [-1., 0., 1.], [1., 0., -1.], [-1., 0., -1.],
+	[0., 1., 1.], [0., -1., 1.], [0., 1., -1.], [0., -1., -1.],
+	[1., 1., 0.], [-1., 1., 0.], [0., -1., 1.], [0., -1., -1.],
+];
+
 #[allow(dead_code)]
 // Improved gradient table with 64 normalized 2D gradients
 const GRADIENTS_2D: [f32; 128] = [
@@ -203,3 +203,50 @@ impl fmt::Display for Time {
 		)
 	}
 }
+
+/// Short length of a duration for the menus: `45s`, `12m`, `1h 23m`, `2d 5h`
+pub fn format_duration(seconds: u64) -> String {
+	let (days, hours, minutes) = (seconds / 86_400, seconds / 3600 % 24, seconds / 60 % 60);
+	if days > 0 {
+		format!("{}d {}h", days, hours)
+	} else if hours > 0 {
+		format!("{}h {}m", hours, minutes)
+	} else if minutes > 0 {
+		format!("{}m", minutes)
+	} else {
+		format!("{}s", seconds)
+	}
+}
+
+/// Hour and minute on the in game clock, `time` is the day fraction of `TimeOfDay` (0 midnight, 0.5 noon)
+pub fn day_clock(time: f32) -> (u8, u8) {
+	let minutes = (time.rem_euclid(1.) * 1440.) as u32 % 1440;
+	((minutes / 60) as u8, (minutes % 60) as u8)
+}
+
+/// The in game clock as `HH:MM`
+pub fn format_day_time(time: f32) -> String {
+	let (hour, minute) = day_clock(time);
+	format!("{:02}:{:02}", hour, minute)
+}
+
+/// Play time of the open world, the saved total plus this session
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PlaySession {
+	saved: u64, // seconds from the earlier sessions
+	session: f64, // seconds played since the world was opened
+}
+
+impl PlaySession {
+	#[inline] pub const fn new(saved: u64) -> Self {
+		Self { saved, session: 0. }
+	}
+	/// Counts a frame of play, only while the world runs without a menu open
+	#[inline] pub fn tick(&mut self, delta: f32) {
+		if delta > 0. { self.session += delta as f64; }
+	}
+	#[inline] pub fn session_secs(&self) -> u64 { self.session as u64 }
+	/// What goes into the save
+	#[inline] pub fn total_secs(&self) -> u64 { self.saved + self.session_secs() }
+	#[inline] pub fn display(&self) -> String { format_duration(self.total_secs()) }
+}
@@ -0,0 +1,121 @@
+
+use crate::{
+	block::{main::Chunk, math::ChunkCoord},
+	world::{main::World, manager, data::PlayerData},
+	fs::binary::BinarySerializable,
+	ui::manager::UIState,
+	ext::ptr,
+};
+use std::{
+	io::Result,
+	path::{Path, PathBuf},
+	thread::{self, JoinHandle},
+};
+
+/// Saves the running world every `SaveConfig::autosave_interval` seconds.
+/// The changed chunks are copied on the main thread (so the workers never see them half saved)
+/// and written to the region files by a background thread, only one save is ever in flight
+#[derive(Debug, Default)]
+pub struct AutoSave {
+	elapsed: f32, // seconds since the last save started
+	writer: Option<JoinHandle<Result<usize>>>, // the save being written, gives back how many chunks went out
+}
+
+impl AutoSave {
+	#[inline] pub const fn new() -> Self {
+		Self { elapsed: 0., writer: None }
+	}
+	#[inline] pub const fn is_saving(&self) -> bool { self.writer.is_some() }
+	#[inline] pub const fn elapsed(&self) -> f32 { self.elapsed }
+
+	/// Advances the timer and tells if a save should start now, `interval` of 0 turns auto-saving off.
+	/// `postpone` holds a due save back (while an open storage is edited outside the world for example)
+	pub fn tick(&mut self, delta: f32, interval: f32, postpone: bool) -> bool {
+		self.poll();
+		if interval <= 0. { return false; }
+		self.elapsed += delta;
+		self.elapsed >= interval && !postpone && !self.is_saving()
+	}
+
+	/// Copies the chunks changed since the last save and writes them in the background
+	/// together with the clocks and the player (already serialized, see `PlayerData`).
+	/// Does nothing (and returns false) while the previous save is still being written
+	pub fn start(&mut self, world: &mut World, save_path: &Path, time_of_day: f32, play_time: u64, player: Vec<u8>) -> bool {
+		self.poll();
+		if self.is_saving() { return false; }
+		self.elapsed = 0.;
+
+		let chunks = world.take_unsaved();
+		let gen_mode = world.gen_mode().clone();
+		let path: PathBuf = save_path.to_path_buf();
+		self.writer = Some(thread::spawn(move || {
+			manager::save_chunks(&path, time_of_day, play_time, &gen_mode, &player, &chunks)?;
+			Ok(chunks.len())
+		}));
+		true
+	}
+
+	/// Collects the save if the writer is done
+	fn poll(&mut self) {
+		if self.writer.as_ref().is_some_and(|w| w.is_finished()) {
+			self.finish();
+		}
+	}
+
+	/// Waits for the save in flight (if any), call it before touching the region files from somewhere else
+	pub fn finish(&mut self) {
+		let Some(writer) = self.writer.take() else { return; };
+		match writer.join() {
+			Ok(Ok(count)) => println!("Auto-saved {} chunk(s)", count),
+			Ok(Err(e)) => println!("Error auto-saving world: {}", e),
+			Err(_) => println!("Auto-save thread panicked"),
+		}
+	}
+}
+
+impl World {
+	/// Remembers that the chunk has to be written by the next save
+	#[inline] pub fn mark_unsaved(&mut self, coord: ChunkCoord) {
+		self.unsaved.insert(coord);
+	}
+	#[inline] pub fn has_unsaved(&self) -> bool {
+		!self.unsaved.is_empty() || !self.unsaved_unloaded.is_empty()
+	}
+
+	/// Copies of every changed chunk, clearing their marks.
+	/// Chunks still waiting on the generator stay marked until they are finished
+	pub fn take_unsaved(&mut self) -> Vec<(ChunkCoord, Chunk)> {
+		let mut taken: Vec<_> = self.unsaved_unloaded.drain().collect();
+		let chunks = &self.chunks;
+		self.unsaved.retain(|coord| match chunks.get(coord) {
+			Some(chunk) if chunk.finished_gen() => { taken.push((*coord, chunk.clone())); false },
+			Some(_) => true,
+			None => false,
+		});
+		taken
+	}
+}
+
+impl<'a> crate::State<'a> {
+	/// Runs the auto-save timer, the in game UI is rebuilt when the saving indicator has to appear or go away
+	pub fn update_autosave(&mut self, delta_time: f32) {
+		let game_state = ptr::get_gamestate();
+		let was_saving = game_state.autosave().is_saving();
+		let interval = ptr::get_settings().save_config.autosave_interval;
+		// Edits to an open chest go through a pointer the world doesn't see, so the save waits until it is closed
+		let inv = game_state.player().inventory();
+		let postpone = inv.storage_ptr.is_some() && !inv.is_self_pointing();
+
+		if game_state.autosave_mut().tick(delta_time, interval, postpone) {
+			let time_of_day = game_state.time_of_day().time();
+			let play_time = game_state.play_session().total_secs();
+			let player = PlayerData::for_save(game_state.player(), ptr::get_settings().save_config.keep_flying).to_binary();
+			let save_path = game_state.save_path().clone();
+			let world = ptr::get_gamestate().world_mut();
+			game_state.autosave_mut().start(world, &save_path, time_of_day, play_time, player);
+		}
+		if was_saving != game_state.autosave().is_saving() && matches!(self.ui_manager.state, UIState::InGame) {
+			self.ui_manager.setup_ui();
+		}
+	}
+}
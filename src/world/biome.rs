@@ -0,0 +1,168 @@
+
+use crate::{
+	block::main::Material,
+	block::extra::get_block_id_from_item_name,
+	utils::math::lerp_f32,
+	utils::rng::Noise,
+};
+
+/// Describes how one biome shapes the terrain column
+/// the materials are stored by texture name so they survive re-ordering of the block textures
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BiomeParams {
+	pub name: &'static str,
+	pub base_height: f32, // offset in blocks added to the terrain height
+	pub height_amplitude: f32, // how much the terrain noise is scaled (in blocks)
+	pub base_material: &'static str,
+	pub surface_material: &'static str,
+	pub surface_depth: i32, // how many blocks of surface material are placed on top of the base
+	pub center: f32, // where this biome sits on the biome noise axis (-1..1)
+}
+
+/// Biomes ordered by their `center`, neighbours in this list are the ones that blend into each other
+pub const BIOMES: [BiomeParams; 4] = [
+	BiomeParams { name: "desert", base_height: 4., height_amplitude: 64., base_material: "stone", surface_material: "sand", surface_depth: 4, center: -0.6 },
+	BiomeParams { name: "plains", base_height: 0., height_amplitude: 128., base_material: "stone", surface_material: "dirt_grass", surface_depth: 3, center: -0.1 },
+	BiomeParams { name: "mountains", base_height: 16., height_amplitude: 320., base_material: "stone", surface_material: "rock", surface_depth: 1, center: 0.35 },
+	BiomeParams { name: "snowy", base_height: 8., height_amplitude: 192., base_material: "stone", surface_material: "snow", surface_depth: 2, center: 0.75 },
+];
+
+/// Width of the band (on the biome noise axis) where two neighbouring biomes are mixed
+const BLEND_WIDTH: f32 = 0.2;
+/// Frequency of the biome noise, low so biomes span many chunks
+const BIOME_FREQ: f32 = 0.0009;
+/// Mixed into the seed so the biome map does not line up with the height map
+const BIOME_SEED_SALT: u32 = 0x5bd1_e995;
+/// Mixed into the seed so the caves do not follow the hills
+const CAVE_SEED_SALT: u32 = 0x68e3_1da4;
+
+/// One resolved terrain column
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Column {
+	pub height: i32,
+	pub surface_depth: i32,
+	pub base: Material,
+	pub surface: Material,
+}
+impl Column {
+	/// Returns the material at the given world height or None if it is air
+	#[inline] pub const fn material_at(&self, pos_y: i32) -> Option<Material> {
+		if pos_y > self.height { return None; }
+		if pos_y > self.height - self.surface_depth { Some(self.surface) } else { Some(self.base) }
+	}
+}
+
+/// Terrain sampler made once per chunk, holds the noises and the resolved material ids
+pub struct BiomeGen {
+	terrain: Noise,
+	biome: Noise,
+	materials: [(Material, Material); BIOMES.len()], // (base, surface)
+}
+
+impl BiomeGen {
+	pub fn new(seed: u32) -> Self {
+		let mut materials = [(Material(0), Material(0)); BIOMES.len()];
+		for (slot, params) in materials.iter_mut().zip(BIOMES.iter()) {
+			*slot = (
+				Material(get_block_id_from_item_name(params.base_material)),
+				Material(get_block_id_from_item_name(params.surface_material)),
+			);
+		}
+		Self {
+			terrain: Noise::new(seed),
+			biome: Noise::new(seed ^ BIOME_SEED_SALT),
+			materials,
+		}
+	}
+
+	/// Raw biome noise value at world XZ, clamped to -1..1
+	#[inline] pub fn biome_value(&self, x: i32, z: i32) -> f32 {
+		self.biome.noise_2d(x as f32 * BIOME_FREQ, z as f32 * BIOME_FREQ).clamp(-1., 1.)
+	}
+
+	/// Picks the two biomes around `t` and the blend factor between them (0 = fully the first one)
+	pub fn biome_weights(t: f32) -> (usize, usize, f32) {
+		let last = BIOMES.len() - 1;
+		if t <= BIOMES[0].center { return (0, 0, 0.); }
+		if t >= BIOMES[last].center { return (last, last, 0.); }
+
+		let mut i = 0;
+		while i < last && t >= BIOMES[i + 1].center { i += 1; }
+		let mid = (BIOMES[i].center + BIOMES[i + 1].center) * 0.5;
+		let f = ((t - (mid - BLEND_WIDTH * 0.5)) / BLEND_WIDTH).clamp(0., 1.);
+		(i, i + 1, f * f * (3. - 2. * f)) // smoothstep
+	}
+
+	/// Samples the terrain column at world XZ, height and depth are blended between the neighbouring biomes
+	/// while the materials come from whichever biome dominates
+	pub fn column(&self, x: i32, z: i32) -> Column {
+		let (a, b, s) = Self::biome_weights(self.biome_value(x, z));
+		let (pa, pb) = (&BIOMES[a], &BIOMES[b]);
+		let depth = lerp_f32(pa.surface_depth as f32, pb.surface_depth as f32, s);
+
+		let (base, surface) = if s < 0.5 { self.materials[a] } else { self.materials[b] };
+		Column {
+			height: self.blended_height(x, z, a, b, s),
+			surface_depth: depth.round() as i32,
+			base,
+			surface,
+		}
+	}
+
+	/// Terrain height at world XZ, the one `column` gives without picking the materials
+	#[inline] pub fn height(&self, x: i32, z: i32) -> i32 {
+		let (a, b, s) = Self::biome_weights(self.biome_value(x, z));
+		self.blended_height(x, z, a, b, s)
+	}
+
+	#[inline] fn blended_height(&self, x: i32, z: i32, a: usize, b: usize, s: f32) -> i32 {
+		let (pa, pb) = (&BIOMES[a], &BIOMES[b]);
+		let noise = self.terrain.terrain_noise_2d(x, z);
+		let base_height = lerp_f32(pa.base_height, pb.base_height, s);
+		let amplitude = lerp_f32(pa.height_amplitude, pb.height_amplitude, s);
+		(base_height + noise * amplitude) as i32
+	}
+}
+
+/// Shape of the caves carved out under the terrain
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CaveParams {
+	pub size: f32, // blocks across one noise cell, bigger makes wider caves further apart
+	pub density: f32, // 0 leaves the ground solid, 0.5 hollows out about half of it (swiss cheese)
+	pub roof: i32, // solid blocks kept under the surface, 0 lets the caves open up to the sky
+	pub floor: i32, // nothing is carved below this y, the deep chunks stay plain stone
+}
+
+impl CaveParams {
+	#[inline] pub const fn default() -> Self {
+		Self { size: 24., density: 0.12, roof: 3, floor: -64 }
+	}
+	/// No caves at all, the terrain is solid like before
+	#[inline] pub const fn none() -> Self {
+		Self { density: 0., ..Self::default() }
+	}
+}
+
+/// Decides which underground blocks are air, from world positions only so caves run on across chunk borders
+pub struct CaveGen {
+	noise: Noise,
+	params: CaveParams,
+}
+
+impl CaveGen {
+	#[inline] pub const fn new(seed: u32, params: CaveParams) -> Self {
+		Self { noise: Noise::new(seed ^ CAVE_SEED_SALT), params }
+	}
+	/// Whether anything at or below `top` can be carved, lets whole chunks skip the noise
+	#[inline] pub const fn reaches(&self, top: i32) -> bool {
+		self.params.density > 0. && top >= self.params.floor
+	}
+	/// The block at world XYZ is carved out of a column whose top is at `surface`
+	pub fn is_carved(&self, x: i32, y: i32, z: i32, surface: i32) -> bool {
+		if self.params.density <= 0. || y < self.params.floor || y > surface.saturating_sub(self.params.roof) { return false; }
+		// Squashed vertically, wide low caves are easier to walk than shafts
+		let scale = 1. / self.params.size.max(1.);
+		let noise = self.noise.noise_3d(x as f32 * scale, y as f32 * scale * 1.5, z as f32 * scale);
+		noise > 0.5 - self.params.density
+	}
+}
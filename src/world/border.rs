@@ -0,0 +1,108 @@
+
+use crate::block::{
+	main::{Block, Chunk},
+	math::{ChunkCoord, LocalPos},
+};
+use crate::world::main::World;
+use glam::{IVec2, IVec3, Vec3};
+
+/// Square limit of a bounded world on the x/z plane, blocks with `min <= pos < max` are inside
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WorldBorder {
+	pub center: IVec2, // x/z of the middle in blocks
+	pub radius: i32, // half width in blocks
+}
+
+impl WorldBorder {
+	#[inline] pub const fn new(center: IVec2, radius: i32) -> Self {
+		Self { center, radius: if radius < 0 { 0 } else { radius } }
+	}
+	/// First block inside the border on each axis
+	#[inline] pub const fn min(&self) -> IVec2 {
+		IVec2::new(self.center.x - self.radius, self.center.y - self.radius)
+	}
+	/// First block outside the border on each axis
+	#[inline] pub const fn max(&self) -> IVec2 {
+		IVec2::new(self.center.x + self.radius, self.center.y + self.radius)
+	}
+
+	#[inline] pub const fn contains(&self, pos: IVec3) -> bool {
+		let (min, max) = (self.min(), self.max());
+		pos.x >= min.x && pos.x < max.x && pos.z >= min.y && pos.z < max.y
+	}
+	/// Whether any column of the chunk is inside, the y of the chunk doesn't matter
+	#[inline] pub const fn overlaps_chunk(&self, coord: ChunkCoord) -> bool {
+		let (min, max) = (self.min(), self.max());
+		let (x, z) = (coord.x() * Chunk::SIZE_I, coord.z() * Chunk::SIZE_I);
+		x < max.x && x + Chunk::SIZE_I > min.x && z < max.y && z + Chunk::SIZE_I > min.y
+	}
+	/// Whether every column of the chunk is inside
+	#[inline] pub const fn contains_chunk(&self, coord: ChunkCoord) -> bool {
+		let (min, max) = (self.min(), self.max());
+		let (x, z) = (coord.x() * Chunk::SIZE_I, coord.z() * Chunk::SIZE_I);
+		x >= min.x && x + Chunk::SIZE_I <= max.x && z >= min.y && z + Chunk::SIZE_I <= max.y
+	}
+
+	/// Keeps a box of half width `margin` centered on `pos` inside, y is left alone
+	#[inline] pub fn clamp(&self, pos: Vec3, margin: f32) -> Vec3 {
+		let (min, max) = (self.min().as_vec2() + margin, self.max().as_vec2() - margin);
+		// Narrower than the box, pin it to the middle
+		let clamp = |v: f32, lo: f32, hi: f32| if lo > hi { (lo + hi) * 0.5 } else { v.clamp(lo, hi) };
+		Vec3::new(clamp(pos.x, min.x, max.x), pos.y, clamp(pos.z, min.y, max.y))
+	}
+
+	/// Clears the columns of a chunk straddling the border that lie outside, so only its in-bounds part is kept
+	pub fn crop_chunk(&self, coord: ChunkCoord, chunk: &mut Chunk) {
+		if self.contains_chunk(coord) { return; }
+		let origin = IVec3::new(coord.x() * Chunk::SIZE_I, 0, coord.z() * Chunk::SIZE_I);
+		let air = Block::default();
+		for x in 0..Chunk::SIZE as u8 {
+			for z in 0..Chunk::SIZE as u8 {
+				if self.contains(origin + IVec3::new(x as i32, 0, z as i32)) { continue; }
+				for y in 0..Chunk::SIZE as u8 {
+					let index: usize = LocalPos::new(x, y, z).into();
+					if chunk.get_block(index) != air {
+						chunk.set_block(index, air);
+					}
+				}
+			}
+		}
+	}
+}
+
+impl World {
+	#[inline] pub const fn border(&self) -> Option<WorldBorder> { self.border }
+	/// Limits the world to `radius_blocks` around the current border center (the origin if there was no border)
+	#[inline] pub fn set_border(&mut self, radius_blocks: i32) {
+		let center = self.border.map_or(IVec2::ZERO, |b| b.center);
+		self.border = Some(WorldBorder::new(center, radius_blocks));
+	}
+	#[inline] pub fn set_border_at(&mut self, center: IVec2, radius_blocks: i32) {
+		self.border = Some(WorldBorder::new(center, radius_blocks));
+	}
+	#[inline] pub fn remove_border(&mut self) {
+		self.border = None;
+	}
+
+	/// Whether the block can be changed, everything is without a border
+	#[inline] pub fn in_border(&self, pos: IVec3) -> bool {
+		self.border.is_none_or(|b| b.contains(pos))
+	}
+	/// Whether the chunk should be loaded at all
+	#[inline] pub fn chunk_in_border(&self, coord: ChunkCoord) -> bool {
+		self.border.is_none_or(|b| b.overlaps_chunk(coord))
+	}
+	/// The part of the box `lo..=hi` inside the border, None if none of it is
+	#[inline] pub fn clip_to_border(&self, lo: IVec3, hi: IVec3) -> Option<(IVec3, IVec3)> {
+		let Some(border) = self.border else { return Some((lo, hi)); };
+		let (min, max) = (border.min(), border.max() - 1);
+		let lo = IVec3::new(lo.x.max(min.x), lo.y, lo.z.max(min.y));
+		let hi = IVec3::new(hi.x.min(max.x), hi.y, hi.z.min(max.y));
+		(lo.x <= hi.x && lo.z <= hi.z).then_some((lo, hi))
+	}
+	/// Cuts a move from `pos` so a box of half width `margin` stays inside the border
+	#[inline] pub fn clamp_movement(&self, pos: Vec3, movement: Vec3, margin: f32) -> Vec3 {
+		let Some(border) = self.border else { return movement; };
+		border.clamp(pos + movement, margin) - pos
+	}
+}
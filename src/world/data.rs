@@ -1,9 +1,13 @@
 
 
 use crate::{
-	fs::binary::{BinarySerializable, FixedBinarySize},
+	fs::binary::{self, BinarySerializable, FixedBinarySize, Migration},
 	utils::time::Time,
+	game::{state::TimeOfDay, player::Player},
+	item::inventory::Inventory,
+	world::generation::GenMode,
 };
+use glam::Vec3;
 use std::{
 	fs::{self, File},
 	io::{Error, ErrorKind, Read, Result, Write},
@@ -20,6 +24,9 @@ pub struct WorldData {
 	pub version: String,
 	pub creation_date: Time,
 	pub last_opened_date: Time,
+	pub time_of_day: f32, // see `TimeOfDay`, older saves without it start in the morning
+	pub gen_mode: GenMode, // older saves are Noise
+	pub play_time: u64, // seconds played in the world, older saves start at 0
 }
 
 impl WorldData {
@@ -28,6 +35,9 @@ impl WorldData {
 			version: env!("CARGO_PKG_VERSION").to_string(),
 			creation_date: Time::now(),
 			last_opened_date: Time::now(),
+			time_of_day: TimeOfDay::default().time(),
+			gen_mode: GenMode::Noise,
+			play_time: 0,
 		}
 	}
 
@@ -42,6 +52,9 @@ impl BinarySerializable for WorldData {
 		data.extend_from_slice(&self.version.to_binary());
 		data.extend_from_slice(&self.creation_date.to_binary());
 		data.extend_from_slice(&self.last_opened_date.to_binary());
+		data.extend_from_slice(&self.time_of_day.to_le_bytes());
+		data.extend_from_slice(&self.gen_mode.to_binary());
+		data.extend_from_slice(&self.play_time.to_le_bytes());
 		data
 	}
 
@@ -59,17 +72,117 @@ impl BinarySerializable for WorldData {
 		offset += Time::BINARY_SIZE;
 		
 		let last_opened_date = Time::from_binary(&bytes[offset..offset + Time::BINARY_SIZE])?;
+		offset += Time::BINARY_SIZE;
+
+		// Added later, so it is optional
+		let time_of_day = bytes.get(offset..offset + 4)
+			.map_or(TimeOfDay::default().time(), |b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]));
+		offset += 4;
+		let gen_mode = bytes.get(offset..).and_then(GenMode::from_binary).unwrap_or_default();
+		offset += gen_mode.binary_size();
+		let play_time = bytes.get(offset..offset + 8)
+			.map_or(0, |b| u64::from_le_bytes([b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7]]));
 		
 		Some(Self {
 			version,
 			creation_date,
 			last_opened_date,
+			time_of_day,
+			gen_mode,
+			play_time,
 		})
 	}
 
 	fn binary_size(&self) -> usize {
-		self.version.binary_size() + Time::BINARY_SIZE * 2
+		self.version.binary_size() + Time::BINARY_SIZE * 2 + 4 + self.gen_mode.binary_size() + 8
+	}
+}
+
+/// Where the player left the world, saved next to the world data in `player.dat`
+#[derive(Clone, PartialEq)]
+pub struct PlayerData {
+	pub pos: Vec3,
+	pub yaw: f32,
+	pub pitch: f32,
+	pub selected_slot: u8, // hotbar slot in hand
+	pub flying: bool,
+	pub inventory: Inventory, // the default one if it could not be read
+}
+
+impl PlayerData {
+	pub fn from_player(player: &Player) -> Self {
+		Self {
+			pos: player.pos(),
+			yaw: player.yaw(),
+			pitch: player.pitch(),
+			selected_slot: player.inventory().selected_index() as u8,
+			flying: player.is_flying(),
+			inventory: player.inventory().clone(),
+		}
+	}
+
+	/// What goes into the save, flying only if the settings keep it (otherwise it is for this session only)
+	pub fn for_save(player: &Player, keep_flying: bool) -> Self {
+		let mut data = Self::from_player(player);
+		data.flying &= keep_flying;
+		data
+	}
+
+	/// Puts the player back where it was saved
+	pub fn apply(self, player: &mut Player) {
+		player.set_pos(self.pos);
+		player.set_look(self.yaw, self.pitch);
+		player.set_fly(self.flying);
+		*player.inventory_mut() = self.inventory;
+		player.inventory_mut().select_slot(self.selected_slot as isize);
+	}
+}
+
+/// How `player.dat` of older formats becomes the current one, indexed by the version upgraded from
+const PLAYER_MIGRATIONS: [Migration; binary::FORMAT_VERSION as usize] = [
+	// 0 -> 1 -> 2: the header and the checksum were added around the same player data
+	|data| Some(data.to_vec()),
+	|data| Some(data.to_vec()),
+];
+
+/// How `world_data.dat` of older formats becomes the current one, indexed by the version upgraded from
+const WORLD_DATA_MIGRATIONS: [Migration; binary::FORMAT_VERSION as usize] = [
+	// 0 -> 1 -> 2: the header and the checksum were added, the fields after the dates were already optional
+	|data| Some(data.to_vec()),
+	|data| Some(data.to_vec()),
+];
+
+/// Reads `player.dat`, None if the world was never saved with a player (older saves)
+pub fn load_player_data(path: &Path) -> Result<Option<PlayerData>> {
+	let file_path = path.join("player.dat");
+	
+	match File::open(&file_path) {
+		Ok(mut file) => {
+			let mut bytes = Vec::new();
+			file.read_to_end(&mut bytes)?;
+			let bytes = binary::read_versioned(&bytes, &PLAYER_MIGRATIONS)?;
+			PlayerData::from_binary(&bytes)
+				.map(Some)
+				.ok_or_else(|| Error::new(ErrorKind::InvalidData, "Invalid player data"))
+		},
+		Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+		Err(e) => Err(e),
+	}
+}
+
+/// Writes `player.dat` from bytes made by `PlayerData::to_binary`, so it can be done off the main thread
+pub fn save_player_bytes(path: &Path, bytes: &[u8]) -> Result<()> {
+	let file_path = path.join("player.dat");
+	fs::create_dir_all(path)?;
+	
+	let temp_path = file_path.with_extension(TEMP_FILE_SUFFIX);
+	{
+		let mut file = File::create(&temp_path)?;
+		file.write_all(&binary::write_versioned(bytes))?;
 	}
+	
+	fs::rename(temp_path, file_path)?;
+	Ok(())
 }
 
 pub fn load_world_data(path: &Path) -> Result<WorldData> {
@@ -79,6 +192,7 @@ pub fn load_world_data(path: &Path) -> Result<WorldData> {
 		Ok(mut file) => {
 			let mut bytes = Vec::new();
 			file.read_to_end(&mut bytes)?;
+			let bytes = binary::read_versioned(&bytes, &WORLD_DATA_MIGRATIONS)?;
 			WorldData::from_binary(&bytes)
 				.ok_or_else(|| Error::new(ErrorKind::InvalidData, "Invalid world data"))
 		},
@@ -98,7 +212,7 @@ pub fn save_world_data(path: &Path, data: &WorldData) -> Result<()> {
 	}
 	
 	let temp_path = file_path.with_extension(TEMP_FILE_SUFFIX);
-	let bytes = data.to_binary();
+	let bytes = binary::write_versioned(&data.to_binary());
 	
 	{
 		let mut file = File::create(&temp_path)?;
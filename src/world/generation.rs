@@ -0,0 +1,122 @@
+
+use crate::{
+	block::main::{Block, Material},
+	block::extra::{get_block_id_from_item_name, get_item_name_from_block_id},
+	fs::binary::{BinarySerializable, FixedBinarySize},
+};
+
+/// Half width of the void spawn platform, it spans `-SPAWN_PLATFORM_RADIUS..=SPAWN_PLATFORM_RADIUS` on x and z
+pub const SPAWN_PLATFORM_RADIUS: i32 = 2;
+
+/// How the terrain of a world is made, picked when the world is created and saved with it
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum GenMode {
+	#[default]
+	Noise, // biome terrain with structures
+	Flat { layers: Vec<(Material, u32)> }, // (material, thickness) from the bottom up, the top layer ends at y = -1
+	Void, // nothing but a small platform under the spawn
+}
+
+impl GenMode {
+	/// Every mode in the order the New World menu lists them
+	pub const NAMES: [&'static str; 3] = ["Noise", "Flat", "Void"];
+
+	/// Stone, dirt and grass on top
+	pub fn superflat() -> Self {
+		let material = |name: &str| Material(get_block_id_from_item_name(name));
+		Self::Flat { layers: vec![(material("stone"), 3), (material("dirt"), 2), (material("dirt_grass"), 1)] }
+	}
+	/// Mode by its index in `NAMES`, anything past the end is Void
+	#[inline] pub fn from_index(index: usize) -> Self {
+		match index {
+			0 => Self::Noise,
+			1 => Self::superflat(),
+			_ => Self::Void,
+		}
+	}
+	#[inline] pub const fn index(&self) -> usize {
+		match self {
+			Self::Noise => 0,
+			Self::Flat { .. } => 1,
+			Self::Void => 2,
+		}
+	}
+	#[inline] pub const fn name(&self) -> &'static str { Self::NAMES[self.index()] }
+
+	/// Block of the flat or void terrain at a world position, None for air (and always for Noise)
+	pub fn block_at(&self, x: i32, y: i32, z: i32) -> Option<Block> {
+		match self {
+			Self::Noise => None,
+			Self::Flat { layers } => {
+				if y >= 0 { return None; }
+				// Walk down from the top layer
+				let mut top = 0;
+				for (material, thickness) in layers.iter().rev() {
+					let bottom = top - *thickness as i32;
+					if y >= bottom { return Some(Block::new(*material)); }
+					top = bottom;
+				}
+				None
+			}
+			Self::Void => {
+				let on_platform = y == -1 && x.abs() <= SPAWN_PLATFORM_RADIUS && z.abs() <= SPAWN_PLATFORM_RADIUS;
+				on_platform.then(|| Block::new(Material(get_block_id_from_item_name("stone"))))
+			}
+		}
+	}
+
+	/// Lowest and highest y that can hold blocks in the flat and void modes, None for Noise
+	pub fn block_range(&self) -> Option<(i32, i32)> {
+		match self {
+			Self::Noise => None,
+			Self::Flat { layers } => Some((-(layers.iter().map(|(_, t)| *t as i32).sum::<i32>()), -1)),
+			Self::Void => Some((-1, -1)),
+		}
+	}
+}
+
+impl BinarySerializable for GenMode {
+	fn to_binary(&self) -> Vec<u8> {
+		let mut data = Vec::with_capacity(self.binary_size());
+		data.push(self.index() as u8);
+		if let Self::Flat { layers } = self {
+			data.push(layers.len() as u8);
+			// Materials by name, so reordering the block textures doesn't change the layers
+			for (material, thickness) in layers {
+				data.extend_from_slice(&get_item_name_from_block_id(material.inner()).to_binary());
+				data.extend_from_slice(&thickness.to_binary());
+			}
+		}
+		data
+	}
+
+	fn from_binary(bytes: &[u8]) -> Option<Self> {
+		match *bytes.first()? {
+			0 => Some(Self::Noise),
+			2 => Some(Self::Void),
+			1 => {
+				let count = *bytes.get(1)? as usize;
+				let mut offset = 2;
+				let mut layers = Vec::with_capacity(count);
+				for _ in 0..count {
+					let name = String::from_binary(bytes.get(offset..)?)?;
+					offset += name.binary_size();
+					let thickness = u32::from_binary(bytes.get(offset..offset + u32::BINARY_SIZE)?)?;
+					offset += u32::BINARY_SIZE;
+					layers.push((Material(get_block_id_from_item_name(&name)), thickness));
+				}
+				Some(Self::Flat { layers })
+			}
+			_ => None,
+		}
+	}
+
+	fn binary_size(&self) -> usize {
+		match self {
+			Self::Flat { layers } => 2 + layers.iter()
+				.map(|(material, _)| get_item_name_from_block_id(material.inner()).binary_size() + u32::BINARY_SIZE)
+				.sum::<usize>(),
+			_ => 1,
+		}
+	}
+}
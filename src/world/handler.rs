@@ -1,29 +1,73 @@
 
 use crate::world::manager::get_save_path;
+use crate::world::{data, generation::GenMode, main::World};
+use crate::block::math::ChunkCoord;
 use crate::ui::manager::UIState;
 use crate::game::state;
 use crate::network::api;
 use crate::ext::ptr;
+use glam::Vec3;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 
+/// Chunks (radius, in chunks) around the player that are generated before the loading screen goes away
+pub const SPAWN_RADIUS: i32 = 2;
+
+// Written by the world tick, read by the loading screen
+static LOADING: AtomicBool = AtomicBool::new(false);
+static LOAD_DONE: AtomicUsize = AtomicUsize::new(0);
+static LOAD_TOTAL: AtomicUsize = AtomicUsize::new(0);
+
+/// Starts the world and shows the loading screen, the chunks are generated by the worker threads over
+/// the next frames and the screen switches to the game once the ones around the player are there
 pub fn join_world(world_name: &str) {
 	println!("Loading world: {}", world_name);
 
 	state::start_world(&world_name);
+	LOAD_DONE.store(0, Ordering::Release);
+	LOAD_TOTAL.store(0, Ordering::Release);
+	LOADING.store(true, Ordering::Release);
 	let ui_manager = &mut ptr::get_state().ui_manager;
 	ui_manager.state = UIState::Loading;
 	ui_manager.setup_ui();
-	
-	ui_manager.state = UIState::Escape;
-	ui_manager.setup_ui();
 }
 
-pub fn create_world(world_name: String) {
+/// Generated and total spawn chunks while a world is loading, None otherwise
+#[inline] pub fn loading_progress() -> Option<(usize, usize)> {
+	if !LOADING.load(Ordering::Acquire) { return None; }
+	Some((LOAD_DONE.load(Ordering::Acquire), LOAD_TOTAL.load(Ordering::Acquire)))
+}
+
+/// Runs every world tick, counts the spawn chunks that are ready. Nothing to do once loading finished
+pub fn report_loading(world: &World, player_pos: Vec3) {
+	if !LOADING.load(Ordering::Acquire) { return; }
+	let (done, total) = world.generated_around(ChunkCoord::from_world_posf(player_pos), SPAWN_RADIUS);
+	// Nothing to wait for outside the border, counted as one ready chunk so the screen still moves on
+	let (done, total) = if total == 0 { (1, 1) } else { (done, total) };
+	LOAD_DONE.store(done, Ordering::Release);
+	LOAD_TOTAL.store(total, Ordering::Release);
+}
+
+/// The loading screen is gone, the progress isn't counted anymore
+#[inline] pub fn finish_loading() {
+	LOADING.store(false, Ordering::Release);
+}
+
+/// Creates the save folder of a new world, the generation mode is only written if the world didn't exist yet
+pub fn create_world(world_name: String, gen_mode: GenMode) {
 	// Create the save path
 	let save_path = get_save_path()
 		.join("saves")
 		.join(world_name);
 	
-	state::make_world(save_path);
+	let is_new = !save_path.join("world_data.dat").exists();
+	state::make_world(save_path.clone());
+	if is_new {
+		let mut world_data = data::WorldData::new();
+		world_data.gen_mode = gen_mode;
+		if let Err(e) = data::save_world_data(&save_path, &world_data) {
+			println!("Failed to save world data: {}", e);
+		}
+	}
 }
 
 pub fn join_local_world(world_name: &str) {
@@ -33,6 +77,7 @@ pub fn join_local_world(world_name: &str) {
 pub fn leave_world() {
 	let state = ptr::get_state();
 	state.is_world_running = false;
+	finish_loading();
 
 	ptr::drop_gamestate();
 	if api::is_host() == Ok(true) {
@@ -0,0 +1,222 @@
+
+use crate::block::{
+	main::Block,
+	math::{ChunkCoord, LocalPos},
+	entity::{BlockEntity, EntityStorage},
+	storage::BlockStorage,
+};
+use crate::world::main::World;
+use crate::network::sync;
+use std::collections::{HashSet, VecDeque};
+use glam::IVec3;
+
+/// Undo steps kept before the oldest one is dropped
+pub const HISTORY_STEPS: usize = 64;
+/// Single block edits kept over all the steps, a huge fill can push out everything before it
+pub const HISTORY_EDITS: usize = 1 << 20;
+/// How many single block edits a chunk snapshot counts as against `HISTORY_EDITS`
+pub const SNAPSHOT_WEIGHT: usize = 256;
+
+/// One changed block, with the block entities on both sides so storage contents come back too
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockEdit {
+	pub pos: IVec3,
+	pub old: Block,
+	pub new: Block,
+	pub old_entity: Option<BlockEntity>, // what was stored before the edit
+	pub new_entity: Option<BlockEntity>, // filled in on undo, whatever was stored by then
+}
+
+/// A whole chunk's blocks and block entities, swapped with the world's on undo and again on redo.
+/// Fills and pastes keep one of these per touched chunk instead of an edit per block
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChunkSnapshot {
+	pub coord: ChunkCoord,
+	pub storage: BlockStorage,
+	pub entities: EntityStorage,
+}
+
+/// One entry of an undo step
+#[derive(Debug, Clone, PartialEq)]
+pub enum Edit {
+	Block(BlockEdit),
+	Chunk(ChunkSnapshot),
+}
+
+impl Edit {
+	/// What the edit counts against `HISTORY_EDITS`
+	#[inline] pub const fn weight(&self) -> usize {
+		match self {
+			Edit::Block(_) => 1,
+			Edit::Chunk(_) => SNAPSHOT_WEIGHT,
+		}
+	}
+	#[inline] pub fn coord(&self) -> ChunkCoord {
+		match self {
+			Edit::Block(edit) => ChunkCoord::from_world_pos(edit.pos),
+			Edit::Chunk(snapshot) => snapshot.coord,
+		}
+	}
+}
+
+#[inline] fn weight(step: &[Edit]) -> usize {
+	step.iter().map(Edit::weight).sum()
+}
+
+/// Bounded undo/redo stacks of block edits.
+/// Edits are collected into a pending group until `World::end_edit_group` (once a frame and around every fill)
+#[derive(Debug)]
+pub struct EditHistory {
+	undo: VecDeque<Vec<Edit>>,
+	redo: Vec<Vec<Edit>>,
+	pending: Vec<Edit>,
+	edits: usize, // weight of the edits in `undo`
+	pub max_steps: usize,
+	pub max_edits: usize,
+}
+
+impl EditHistory {
+	#[inline] pub const fn new() -> Self {
+		Self::with_limits(HISTORY_STEPS, HISTORY_EDITS)
+	}
+	#[inline] pub const fn with_limits(max_steps: usize, max_edits: usize) -> Self {
+		Self { undo: VecDeque::new(), redo: Vec::new(), pending: Vec::new(), edits: 0, max_steps, max_edits }
+	}
+	#[inline] pub fn undo_steps(&self) -> usize { self.undo.len() + !self.pending.is_empty() as usize }
+	#[inline] pub fn redo_steps(&self) -> usize { self.redo.len() }
+	#[inline] pub const fn edits(&self) -> usize { self.edits }
+	#[inline] pub fn clear(&mut self) {
+		*self = Self::with_limits(self.max_steps, self.max_edits);
+	}
+
+	#[inline] pub fn record(&mut self, edit: BlockEdit) {
+		self.pending.push(Edit::Block(edit));
+	}
+	#[inline] pub fn record_snapshot(&mut self, snapshot: ChunkSnapshot) {
+		self.pending.push(Edit::Chunk(snapshot));
+	}
+
+	/// Closes the pending group as one undo step, a new step makes the redo stack meaningless
+	pub fn end_group(&mut self) {
+		if self.pending.is_empty() { return; }
+		let step = std::mem::take(&mut self.pending);
+		self.redo.clear();
+		self.push_undo(step);
+	}
+
+	#[inline] fn push_undo(&mut self, step: Vec<Edit>) {
+		if step.is_empty() { return; }
+		self.edits += weight(&step);
+		self.undo.push_back(step);
+		// Always keep the newest step, even if it is over the edit limit on its own
+		while self.undo.len() > 1 && (self.undo.len() > self.max_steps || self.edits > self.max_edits) {
+			let Some(oldest) = self.undo.pop_front() else { break; };
+			self.edits -= weight(&oldest);
+		}
+	}
+}
+
+impl Default for EditHistory {
+	#[inline] fn default() -> Self { Self::new() }
+}
+
+impl World {
+	/// Adds a block change to the current undo group, call it before the change.
+	/// A storage block that is about to go is kept empty, `set_block` hands its items to the player so undo must not bring them back too
+	pub fn record_edit(&mut self, pos: IVec3, old: Block, new: Block) {
+		if old == new { return; }
+		let old_entity = self.get_chunk(&ChunkCoord::from_world_pos(pos))
+			.and_then(|chunk| chunk.entities().get(LocalPos::from(pos)).cloned())
+			.map(|mut entity| { entity.storage.clear(); entity });
+		self.history.record(BlockEdit { pos, old, new, old_entity, new_entity: None });
+	}
+	/// Keeps the chunk as it is now in the current undo group, call it once before a fill or paste changes it
+	pub fn record_chunk(&mut self, coord: ChunkCoord) {
+		let Some(chunk) = self.get_chunk(&coord) else { return; };
+		let snapshot = ChunkSnapshot { coord, storage: chunk.storage().clone(), entities: chunk.entities().clone() };
+		self.history.record_snapshot(snapshot);
+	}
+	/// Makes everything recorded since the last call one undo step
+	#[inline] pub fn end_edit_group(&mut self) {
+		self.history.end_group();
+	}
+
+	/// Reverts the last step, returns false if there was nothing to undo
+	pub fn undo(&mut self) -> bool {
+		self.history.end_group();
+		let Some(mut step) = self.history.undo.pop_back() else { return false; };
+		self.history.edits -= weight(&step);
+
+		let mut touched = HashSet::new();
+		step.reverse();
+		self.apply_step(&mut step, true, &mut touched);
+		step.reverse();
+		self.refresh_edited(touched);
+		self.history.redo.push(step);
+		true
+	}
+
+	/// Puts back the last undone step, returns false if there was nothing to redo
+	pub fn redo(&mut self) -> bool {
+		let Some(mut step) = self.history.redo.pop() else { return false; };
+
+		let mut touched = HashSet::new();
+		self.apply_step(&mut step, false, &mut touched);
+		self.refresh_edited(touched);
+		self.history.push_undo(step);
+		true
+	}
+
+	/// Undoes or redoes the edits in the given order.
+	/// Edits in chunks that are not loaded can't be applied, they are dropped from the step so undo and redo stay in step with each other
+	fn apply_step(&mut self, step: &mut Vec<Edit>, undo: bool, touched: &mut HashSet<ChunkCoord>) {
+		step.retain_mut(|edit| {
+			if !self.chunks.contains_key(&edit.coord()) { return false; }
+			match edit {
+				Edit::Block(edit) if undo => edit.new_entity = self.swap_block(edit.pos, edit.old, edit.old_entity.clone(), touched),
+				Edit::Block(edit) => edit.old_entity = self.swap_block(edit.pos, edit.new, edit.new_entity.clone(), touched),
+				Edit::Chunk(snapshot) => self.swap_chunk(snapshot, touched),
+			}
+			true
+		});
+	}
+
+	/// Writes a block and its block entity without recording or handing anything to the player, returns the replaced entity.
+	/// The chunk has to be loaded
+	fn swap_block(&mut self, pos: IVec3, block: Block, entity: Option<BlockEntity>, touched: &mut HashSet<ChunkCoord>) -> Option<BlockEntity> {
+		let coord = ChunkCoord::from_world_pos(pos);
+		let local = LocalPos::from(pos);
+		let chunk = self.chunks.get_mut(&coord).expect("Chunk should exist");
+
+		let replaced = chunk.remove_entity(local);
+		chunk.set_block(local.into(), block);
+		if let Some(entity) = entity {
+			chunk.entities_mut().add(local, entity);
+		}
+		// Blocks on the chunk edge show in the neighbour's mesh too
+		self.set_some_un_final(coord, IVec3::from(local));
+		touched.insert(coord);
+		replaced
+	}
+
+	/// Trades the chunk's blocks and block entities with the snapshot, so the snapshot holds what the world had.
+	/// The chunk has to be loaded
+	fn swap_chunk(&mut self, snapshot: &mut ChunkSnapshot, touched: &mut HashSet<ChunkCoord>) {
+		let chunk = self.chunks.get_mut(&snapshot.coord).expect("Chunk should exist");
+		std::mem::swap(chunk.storage_mut(), &mut snapshot.storage);
+		std::mem::swap(chunk.entities_mut(), &mut snapshot.entities);
+		chunk.dirty = true;
+		self.set_adjacent_un_final(snapshot.coord);
+		touched.insert(snapshot.coord);
+	}
+
+	/// Relights and saves every chunk an undo or redo went through (once per chunk)
+	fn refresh_edited(&mut self, touched: HashSet<ChunkCoord>) {
+		for &coord in &touched {
+			self.mark_unsaved(coord);
+			self.propagate_light(coord);
+			self.clear_adjacent_occlusion(coord);
+		}
+		sync::chunks_changed(self, touched);
+	}
+}
@@ -1,25 +1,61 @@
 
 use crate::{
 	block::{
-		math::{LocalPos, ChunkCoord},
-		main::{Block, Chunk},
-		entity::BlockEntity,
+		math::{LocalPos, ChunkCoord, AxisBasic},
+		main::{Block, Chunk, Material},
+		entity::{BlockEntity, EntityStorage},
+		storage::BlockStorage,
+		light::LightQueue,
 	}, ptr,
-	world::threading::PriorityChunk,
-	item::inventory::ItemContainer,
+	ext::settings::RenderConfig,
+	world::{threading::{PriorityChunk, MeshJob, MeshResult}, border::WorldBorder, generation::GenMode, history::{EditHistory, ChunkSnapshot}},
+	render::meshing::GeometryBufferPool,
+	network::sync,
+	item::{inventory::ItemContainer, recipes::Processor},
 };
 use std::{
-	collections::{BinaryHeap, HashMap, HashSet},
+	collections::{BinaryHeap, HashMap, HashSet, VecDeque},
 	hash::BuildHasherDefault,
 	sync::{atomic::{AtomicBool, AtomicUsize}, Arc, Mutex},
+	thread::JoinHandle,
 };
-use crossbeam::channel::{bounded, Sender, Receiver};
+use crossbeam::channel::{bounded, unbounded, Sender, Receiver};
 use glam::{IVec3, Vec3};
 use ahash::AHasher;
 
 // Type aliases for better readability
 type FastMap<K, V> = HashMap<K, V, BuildHasherDefault<AHasher>>;
 
+/// How many unloaded chunks are kept in the archive before the oldest ones get evicted
+pub const ARCHIVE_CAPACITY: usize = 4096;
+/// The queued chunks get re-sorted once the view turned further than this from the last sort (cos of ~25°)
+pub const RESORT_DOT: f32 = 0.9;
+/// Chunks the load radius moves per update towards the render distance, a big change fills in over a few ticks instead of one long frame
+pub const LOAD_RADIUS_STEP: i32 = 1;
+
+/// Result of `World::raycast`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RayHit {
+	pub world_pos: IVec3, // the solid block that was hit
+	pub prev_pos: IVec3, // the last empty cell before the hit (where a block would be placed), same as `world_pos` if the ray started inside a solid block
+	pub face: AxisBasic, // axis of the face the ray entered through
+	pub distance: f32, // distance along the (normalized) ray to the entry point
+}
+impl RayHit {
+	/// Outward normal of the hit face, zero if the ray started inside the block
+	#[inline] pub fn normal(&self) -> IVec3 {
+		self.prev_pos - self.world_pos
+	}
+}
+
+/// An unloaded chunk kept in `World::archive`
+#[derive(Debug)]
+pub struct ArchivedChunk {
+	pub storage: BlockStorage, // RLE compressed if it was worth it
+	pub entities: EntityStorage, // chests and the like, their items come back with the chunk
+	seq: u64, // tells its `archive_order` entry from the ones of earlier times the chunk was archived
+}
+
 /// Represents the game world containing chunks
 #[derive(Debug)]
 pub struct World {
@@ -32,16 +68,62 @@ pub struct World {
 	pub chunk_generation_sender: Sender<(ChunkCoord, Chunk)>,
 	pub generation_threads_running: Arc<AtomicBool>,
 	pub active_workers: Arc<AtomicUsize>,
+	pub generation_workers: Vec<JoinHandle<()>>,
+	
+	// Remeshing, built by the same workers and uploaded by `make_chunk_meshes`
+	pub mesh_queue: Arc<Mutex<VecDeque<MeshJob>>>,
+	pub meshed_receiver: Receiver<MeshResult>,
+	pub meshed_sender: Sender<MeshResult>,
+	pub mesh_job_id: u32, // last handed out `MeshJob` id
+	
+	// Bounded worlds, nothing loads or changes outside
+	pub border: Option<WorldBorder>,
+	
+	// Camera forward, chunks in view generate first
+	pub look_dir: Vec3,
+	sorted_dir: Vec3, // look_dir the generation queue was last sorted for
+	load_radius: i32, // chunks loaded around the player, follows the asked radius by `LOAD_RADIUS_STEP`
+	
+	// Unloaded chunks kept in memory so they restore without regeneration
+	pub archive: FastMap<ChunkCoord, ArchivedChunk>,
+	archive_order: VecDeque<(ChunkCoord, u64)>, // oldest first, with the `seq` the chunk was archived under
+	archive_seq: u64,
+	archive_enabled: bool,
+	
+	// Furnace-like blocks, ticked with the world
+	pub processors: FastMap<IVec3, Processor>,
+	
+	// Instance buffers of remeshed and unloaded chunks, reused by `make_mesh`
+	pub mesh_pool: GeometryBufferPool,
+	
+	// Changed since the last save, written by `AutoSave` and `save_entire_world`
+	pub unsaved: HashSet<ChunkCoord>,
+	pub unsaved_unloaded: FastMap<ChunkCoord, Chunk>, // changed chunks that got unloaded before they were saved
+	
+	// Undo/redo of block edits
+	pub history: EditHistory,
+	
+	// Chunks to relight, worked off by `update_light` a few a frame
+	pub light_queue: LightQueue,
 	
 	// Configuration
 	thread_count: u8,
 	seed: u32,
+	gen_mode: GenMode,
+}
+
+impl Drop for World {
+	/// Makes sure no worker outlives the world (leaving and re-entering would pile them up otherwise)
+	fn drop(&mut self) {
+		self.stop_generation();
+	}
 }
 
 impl World {
 	/// Creates an empty world
 	pub fn empty() -> Self {
 		let (sender, receiver) = bounded(100); // Use bounded channel to prevent memory explosion
+		let (meshed_sender, meshed_receiver) = unbounded(); // Bounded by the queued mesh jobs anyway
 		
 		Self {
 			chunks: FastMap::with_capacity_and_hasher(10_000, BuildHasherDefault::<AHasher>::default()),
@@ -51,14 +133,54 @@ impl World {
 			chunk_generation_sender: sender,
 			generation_threads_running: Arc::new(AtomicBool::new(false)),
 			active_workers: Arc::new(AtomicUsize::new(0)),
+			generation_workers: Vec::new(),
+			mesh_queue: Arc::new(Mutex::new(VecDeque::new())),
+			meshed_receiver,
+			meshed_sender,
+			mesh_job_id: 0,
+			border: None,
+			look_dir: Vec3::ZERO,
+			sorted_dir: Vec3::ZERO,
+			load_radius: 0,
+			archive: FastMap::default(),
+			archive_order: VecDeque::new(),
+			archive_seq: 0,
+			archive_enabled: false,
+			processors: FastMap::default(),
+			mesh_pool: GeometryBufferPool::new(),
+			unsaved: HashSet::new(),
+			unsaved_unloaded: FastMap::default(),
+			history: EditHistory::new(),
+			light_queue: LightQueue::new(),
 			thread_count: 1,
 			seed: 0,
+			gen_mode: GenMode::Noise,
 		}
 	}
 	#[inline] pub fn seed(&self) -> u32 { self.seed }
 	#[inline] pub fn thread_count(&self) -> u8 { self.thread_count }
 	#[inline] pub fn set_seed(&mut self, seed:u32) { self.seed = seed }
 	#[inline] pub fn set_thread_count(&mut self, thread_count:u8) { self.thread_count = thread_count }
+	#[inline] pub const fn gen_mode(&self) -> &GenMode { &self.gen_mode }
+	/// Changes how new chunks are generated, running workers are restarted since they hold a copy of the mode
+	pub fn set_gen_mode(&mut self, mode: GenMode) {
+		if self.gen_mode == mode { return; }
+		self.gen_mode = mode;
+		if self.generation_threads_running.load(std::sync::atomic::Ordering::Relaxed) {
+			self.stop_generation();
+			self.start_generation_threads(self.thread_count);
+		}
+	}
+	#[inline] pub const fn load_radius(&self) -> i32 { self.load_radius }
+	#[inline] pub fn archive_enabled(&self) -> bool { self.archive_enabled }
+	/// Turns on "archive instead of drop" for unloaded chunks, disabling it also frees the archive
+	#[inline] pub fn set_archive_enabled(&mut self, enabled: bool) {
+		self.archive_enabled = enabled;
+		if !enabled {
+			self.archive.clear();
+			self.archive_order.clear();
+		}
+	}
 
 	#[inline] pub fn get_chunk(&self, coord: &ChunkCoord) -> Option<&Chunk> {
 		self.chunks.get(coord)
@@ -71,6 +193,7 @@ impl World {
 		let chunk_coord = ChunkCoord::from_world_pos(world_pos);
 		let local_pos: LocalPos = LocalPos::from(world_pos);
 
+		self.mark_unsaved(chunk_coord);
 		let Some(chunk) = self.get_chunk_mut(&chunk_coord) else { return None; };
 		chunk.remove_entity(local_pos)
 	}
@@ -78,6 +201,7 @@ impl World {
 		let chunk_coord = ChunkCoord::from_world_pos(world_pos);
 		let local_pos: LocalPos = LocalPos::from(world_pos);
 
+		self.mark_unsaved(chunk_coord);
 		let Some(chunk) = self.get_chunk_mut(&chunk_coord) else { return; };
 		chunk.add_entity(local_pos, inv)
 	}
@@ -89,15 +213,35 @@ impl World {
 		let Some(chunk) = self.get_chunk(&chunk_coord) else { return None; };
 		chunk.get_entity(local_pos)
 	}
-	/// Gets a storage container (mutable)
+	/// Gets a storage container (mutable), its chunk is marked unsaved since the caller can change anything
 	#[inline] pub fn get_storage_mut(&mut self, world_pos: IVec3) -> Option<&mut ItemContainer> {
 		let chunk_coord = ChunkCoord::from_world_pos(world_pos);
 		let local_pos: LocalPos = LocalPos::from(world_pos);
 
+		self.mark_unsaved(chunk_coord);
 		let Some(chunk) = self.get_chunk_mut(&chunk_coord) else { return None; };
 		chunk.get_entity_mut(local_pos)
 	}
 
+	#[inline] pub fn add_processor(&mut self, world_pos: IVec3, processor: Processor) {
+		self.processors.insert(world_pos, processor);
+	}
+	#[inline] pub fn remove_processor(&mut self, world_pos: IVec3) -> Option<Processor> {
+		self.processors.remove(&world_pos)
+	}
+	#[inline] pub fn get_processor(&self, world_pos: IVec3) -> Option<&Processor> {
+		self.processors.get(&world_pos)
+	}
+	#[inline] pub fn get_processor_mut(&mut self, world_pos: IVec3) -> Option<&mut Processor> {
+		self.processors.get_mut(&world_pos)
+	}
+	/// Advances every processor by `dt` seconds
+	#[inline] pub fn tick_processors(&mut self, dt: f32) {
+		for processor in self.processors.values_mut() {
+			processor.tick(dt);
+		}
+	}
+
 	#[inline] pub fn get_block(&self, world_pos: IVec3) -> Block {
 		let chunk_coord = ChunkCoord::from_world_pos(world_pos);
 		let local_pos: LocalPos = LocalPos::from(world_pos);
@@ -110,6 +254,7 @@ impl World {
 	}
 
 	#[inline] pub fn set_block(&mut self, world_pos: IVec3, block: Block) {
+		if !self.in_border(world_pos) { return; }
 		let chunk_coord = ChunkCoord::from_world_pos(world_pos);
 		let local_pos: LocalPos = LocalPos::from(world_pos);
 		let index: usize = usize::from(local_pos);
@@ -123,25 +268,172 @@ impl World {
 		let old_block = chunk.get_block(index);
 		// Skip if block is the same
 		if old_block == block { return; }
-		
-		// If placing a new storage block, initialize its storage
-		if block.is_storage() {
-			let slot = block.get_storage();
-			self.create_storage(world_pos, ItemContainer::new(slot.rows(), slot.cols()));
-		}
+		self.record_edit(world_pos, old_block, block);
 
 		let chunk = self.chunks.get_mut(&chunk_coord).expect("Chunk should exist");
+		let replaced = chunk.remove_entity(local_pos);
 		chunk.set_block(index, block);
+		// A new storage block starts empty
+		if block.is_storage() {
+			let slot = block.get_storage();
+			chunk.add_entity(local_pos, ItemContainer::new(slot.rows(), slot.cols()));
+		}
+		self.mark_unsaved(chunk_coord);
+		self.propagate_light(chunk_coord);
 
+		// Replacing a storage block gives back whatever was inside
 		let inv_mut = ptr::get_gamestate().player_mut().inventory_mut();
-		if let Some(storage) = self.get_storage(world_pos) {
-			for item in storage.iter() {
-				let Some(itm) = item.clone() else { continue; };
-				inv_mut.add_item_anywhere(&mut itm.clone());
+		if let Some(entity) = replaced {
+			for mut itm in entity.storage.into_iter().flatten() {
+				inv_mut.add_item_anywhere(&mut itm);
+			}
+		}
+		// Replacing a processor block gives back whatever was inside
+		if let Some(processor) = self.remove_processor(world_pos) {
+			for container in [processor.input, processor.fuel, processor.output] {
+				for mut itm in container.into_iter().flatten() {
+					inv_mut.add_item_anywhere(&mut itm);
+				}
 			}
 		}
 		
 		self.set_some_un_final(chunk_coord, IVec3::from(local_pos));
+		self.clear_adjacent_occlusion(chunk_coord);
+		sync::block_changed(world_pos, block);
+	}
+
+	/// Sets every block in the box `min..=max` (corners in any order).
+	/// Writes go straight into each chunk's storage, every touched chunk is optimized, marked dirty and queued for one relight,
+	/// and only chunks on the border of the box mark their outside neighbours for a remesh.
+	/// Storage blocks that get overwritten lose their block entity, placing a storage block gives it an empty container.
+	/// The whole fill is a single undo step, keeping one snapshot per changed chunk.
+	/// The box is cut to the world border, nothing outside it is created or changed.
+	pub fn fill_region(&mut self, min: IVec3, max: IVec3, block: Block) {
+		self.end_edit_group();
+		let Some((lo, hi)) = self.clip_to_border(min.min(max), min.max(max)) else { return; };
+		let (chunk_lo, chunk_hi) = (ChunkCoord::from_world_pos(lo), ChunkCoord::from_world_pos(hi));
+		let (lo_x, lo_y, lo_z) = chunk_lo.unpack();
+		let (hi_x, hi_y, hi_z) = chunk_hi.unpack();
+		
+		let slot = block.is_storage().then(|| block.get_storage());
+		// is_storage goes through the item lut by name, so only do it once per material
+		let mut storage_cache: FastMap<Material, bool> = FastMap::default();
+		let mut touched = Vec::new();
+		
+		for cx in lo_x..=hi_x {
+			for cy in lo_y..=hi_y {
+				for cz in lo_z..=hi_z {
+					let coord = ChunkCoord::new(cx, cy, cz);
+					let base = IVec3::from(coord.unpack_to_worldpos());
+					let from = (lo - base).max(IVec3::ZERO);
+					let to = (hi - base).min(IVec3::splat(Chunk::SIZE_I - 1));
+					
+					if !self.chunks.contains_key(&coord) {
+						self.set_chunk(coord, Chunk::empty());
+					}
+					let chunk = self.chunks.get_mut(&coord).expect("Chunk should exist");
+					let snapshot = ChunkSnapshot { coord, storage: chunk.storage().clone(), entities: chunk.entities().clone() };
+					let mut changed = false;
+					
+					if from == IVec3::ZERO && to == IVec3::splat(Chunk::SIZE_I - 1) {
+						// Whole chunk covered, no need to touch the blocks one by one
+						changed = (0..Chunk::VOLUME).any(|idx| chunk.get_block(idx) != block);
+						*chunk.entities_mut() = EntityStorage::default();
+						*chunk.storage_mut() = BlockStorage::uniform(block);
+						if let Some(slot) = &slot {
+							for idx in 0..Chunk::VOLUME {
+								chunk.add_entity(LocalPos::from(idx), ItemContainer::new(slot.rows(), slot.cols()));
+							}
+						}
+					} else {
+						for z in from.z..=to.z {
+							for y in from.y..=to.y {
+								for x in from.x..=to.x {
+									let local = LocalPos::from(IVec3::new(x, y, z));
+									let idx = usize::from(local);
+									let old = chunk.get_block(idx);
+									if old == block { continue; }
+									
+									let old_is_storage = *storage_cache.entry(old.material())
+										.or_insert_with(|| old.is_storage());
+									if old_is_storage { chunk.remove_entity(local); }
+									chunk.storage_mut().set(idx, block);
+									changed = true;
+									if let Some(slot) = &slot {
+										chunk.add_entity(local, ItemContainer::new(slot.rows(), slot.cols()));
+									}
+								}
+							}
+						}
+						chunk.optimize_storage();
+					}
+					if changed { self.history.record_snapshot(snapshot); }
+					chunk.dirty = true;
+					touched.push(coord);
+				}
+			}
+		}
+		
+		// Relit from the top down by `update_light`, the sky light reaches the lower chunks before they are computed
+		touched.sort_unstable_by_key(|coord| std::cmp::Reverse(coord.y()));
+		for &coord in &touched {
+			let (x, y, z) = coord.unpack();
+			let on_border = x == lo_x || x == hi_x || y == lo_y || y == hi_y || z == lo_z || z == hi_z;
+			if on_border {
+				self.set_adjacent_un_final(coord);
+			}
+			self.mark_unsaved(coord);
+			self.propagate_light(coord);
+		}
+		sync::chunks_changed(self, touched);
+		self.end_edit_group();
+	}
+
+	/// Walks the voxels along the ray (Amanatides & Woo traversal) and returns the first solid block
+	/// `dir` does not need to be normalized, but a zero direction never hits anything
+	pub fn raycast(&self, origin: Vec3, dir: Vec3, max_dist: f32) -> Option<RayHit> {
+		let dir = dir.try_normalize()?;
+		let mut pos = origin.floor().as_ivec3();
+		
+		// Started inside something, there is no empty cell to report
+		if !self.get_block(pos).is_empty() {
+			let abs = dir.abs();
+			let face = if abs.x >= abs.y && abs.x >= abs.z { AxisBasic::X } else if abs.y >= abs.z { AxisBasic::Y } else { AxisBasic::Z };
+			return Some(RayHit { world_pos: pos, prev_pos: pos, face, distance: 0. });
+		}
+		
+		let step = IVec3::new(dir.x.signum() as i32, dir.y.signum() as i32, dir.z.signum() as i32);
+		// Distance along the ray between two voxel borders on each axis
+		let t_delta = Vec3::ONE / dir.abs().max(Vec3::splat(f32::MIN_POSITIVE));
+		// Distance along the ray to the first border on each axis
+		let border = |p: i32, o: f32, s: i32| if s > 0 { (p + 1) as f32 - o } else { o - p as f32 };
+		let mut t_max = Vec3::new(
+			border(pos.x, origin.x, step.x),
+			border(pos.y, origin.y, step.y),
+			border(pos.z, origin.z, step.z),
+		) * t_delta;
+		
+		loop {
+			let prev_pos = pos;
+			let (face, distance) = if t_max.x < t_max.y && t_max.x < t_max.z {
+				pos.x += step.x;
+				let t = t_max.x; t_max.x += t_delta.x;
+				(AxisBasic::X, t)
+			} else if t_max.y < t_max.z {
+				pos.y += step.y;
+				let t = t_max.y; t_max.y += t_delta.y;
+				(AxisBasic::Y, t)
+			} else {
+				pos.z += step.z;
+				let t = t_max.z; t_max.z += t_delta.z;
+				(AxisBasic::Z, t)
+			};
+			if distance > max_dist { return None; }
+			
+			if !self.get_block(pos).is_empty() {
+				return Some(RayHit { world_pos: pos, prev_pos, face, distance });
+			}
+		}
 	}
 
 	/// Marks adjacent chunks as needing mesh updates
@@ -149,6 +441,15 @@ impl World {
 		for coord in chunk_coord.get_adjacent() {
 			if let Some(neighbor_chunk) = self.get_chunk_mut(&coord) {
 				neighbor_chunk.final_mesh = false;
+				neighbor_chunk.occluded = None;
+			}
+		}
+	}
+	/// Drops the cached occlusion of the adjacent chunks, any change in a chunk can open a view into them
+	#[inline] pub fn clear_adjacent_occlusion(&mut self, chunk_coord: ChunkCoord) {
+		for coord in chunk_coord.get_adjacent() {
+			if let Some(neighbor_chunk) = self.get_chunk_mut(&coord) {
+				neighbor_chunk.occluded = None;
 			}
 		}
 	}
@@ -188,20 +489,29 @@ impl World {
 		}
 	}
 
-	/// Updates which chunks are loaded based on player position
+	/// Updates which chunks are loaded based on player position.
+	/// `radius` is in chunks and capped at `RenderConfig::MAX_RENDER_DISTANCE`, the loaded radius steps towards it
 	pub fn update_loaded_chunks(&mut self, center: Vec3, radius: f32) {
 		let center_coord = ChunkCoord::from_world_posf(center);
-		let radius_i32 = radius.round() as i32;
-		let radius_sq = (radius * radius) as i32;
+		let target = (radius.round() as i32).clamp(0, RenderConfig::MAX_RENDER_DISTANCE as i32);
+		self.load_radius += (target - self.load_radius).clamp(-LOAD_RADIUS_STEP, LOAD_RADIUS_STEP);
+		let radius_i32 = self.load_radius;
+		let radius_sq = radius_i32 * radius_i32;
 
 		self.unload_distant_chunks(center_coord, radius_sq);
 		self.process_generated_chunks();
+		if self.look_dir != Vec3::ZERO && self.look_dir.dot(self.sorted_dir) < RESORT_DOT && self.reprioritize_generation(center_coord) {
+			self.sorted_dir = self.look_dir;
+		}
 		self.load_nearby_chunks(center_coord, radius_i32, radius_sq);
 	}
 
-	/// Unloads chunks beyond the given radius
+	/// Unloads chunks beyond the given radius (archiving them if enabled)
 	#[inline] fn unload_distant_chunks(&mut self, center: ChunkCoord, radius_sq: i32) {
 		let (center_x, center_y, center_z) = center.unpack();
+		let archive_enabled = self.archive_enabled;
+		let mut unloaded = Vec::new();
+		let mut archived = Vec::new();
 		
 		self.loaded_chunks.retain(|&coord| {
 			let (x, y, z) = coord.unpack();
@@ -211,11 +521,84 @@ impl World {
 			let keep = dx * dx + dy * dy + dz * dz <= radius_sq;
 			
 			if !keep {
-				self.chunks.remove(&coord);
+				let Some(mut chunk) = self.chunks.remove(&coord) else { return keep; };
+				unloaded.push(coord);
+				if let Some(mesh) = chunk.take_mesh() {
+					self.mesh_pool.release(mesh);
+				}
+				// Keep the edits around for the next save
+				if self.unsaved.remove(&coord) {
+					self.unsaved_unloaded.insert(coord, chunk.clone());
+				}
+				// Chunks still waiting on the generator have nothing worth keeping
+				if archive_enabled && chunk.finished_gen {
+					archived.push((coord, chunk));
+				}
 			}
 			
 			keep
 		});
+		// A missing neighbor opens a view into the chunks next to it
+		for coord in unloaded {
+			self.clear_adjacent_occlusion(coord);
+		}
+		for (coord, chunk) in archived {
+			self.archive_chunk(coord, chunk);
+		}
+		
+		// Don't waste the workers on chunks that are gone
+		let loaded = &self.loaded_chunks;
+		self.mesh_queue.lock().unwrap().retain(|job| loaded.contains(&job.coord()));
+	}
+
+	/// Stores the chunk's blocks and block entities in the archive, the blocks compressed if `to_rle` thinks it is worth it
+	fn archive_chunk(&mut self, coord: ChunkCoord, mut chunk: Chunk) {
+		let storage = chunk.storage().to_rle().unwrap_or_else(|| chunk.storage().clone());
+		let entities = std::mem::replace(chunk.entities_mut(), EntityStorage::default());
+		self.archive_seq += 1;
+		let seq = self.archive_seq;
+		self.archive.insert(coord, ArchivedChunk { storage, entities, seq });
+		self.archive_order.push_back((coord, seq));
+		
+		// Evict the oldest entries, the ones left behind by restored or archived again chunks are skipped here
+		while self.archive.len() > ARCHIVE_CAPACITY {
+			let Some((oldest, seq)) = self.archive_order.pop_front() else { break; };
+			if self.archive.get(&oldest).is_some_and(|a| a.seq == seq) {
+				self.archive.remove(&oldest);
+			}
+		}
+		// Chunks that keep coming and going pile those up, drop them all once in a while
+		if self.archive_order.len() > ARCHIVE_CAPACITY * 2 {
+			let archive = &self.archive;
+			self.archive_order.retain(|(coord, seq)| archive.get(coord).is_some_and(|a| a.seq == *seq));
+		}
+	}
+
+	/// Brings back a changed chunk that was unloaded before it was saved, generating it again would lose the edits.
+	/// Returns false if there is none
+	fn restore_unsaved(&mut self, coord: ChunkCoord) -> bool {
+		let Some(chunk) = self.unsaved_unloaded.remove(&coord) else { return false; };
+		// The archived copy is the same or older
+		self.archive.remove(&coord);
+		self.set_adjacent_un_final(coord);
+		self.set_chunk(coord, chunk);
+		self.mark_unsaved(coord);
+		self.propagate_light(coord);
+		self.create_bind_group(coord);
+		true
+	}
+
+	/// Restores a chunk from the archive, returns false if it was not archived
+	fn restore_archived(&mut self, coord: ChunkCoord) -> bool {
+		// Its entry in `archive_order` stays behind until the eviction gets to it
+		let Some(ArchivedChunk { storage, entities, .. }) = self.archive.remove(&coord) else { return false; };
+		
+		let storage = storage.from_rle().unwrap_or(storage);
+		self.set_adjacent_un_final(coord);
+		self.set_chunk(coord, Chunk::from_storage_and_entities(storage, entities));
+		self.propagate_light(coord);
+		self.create_bind_group(coord);
+		true
 	}
 
 	/// Loads chunks within the given radius using a more efficient spiral pattern
@@ -241,8 +624,9 @@ impl World {
 					}
 					
 					let coord = ChunkCoord::new(center_x + x, center_y + dy, center_z + z);
-					if !self.loaded_chunks.contains(&coord) {
-						chunks_to_load.push(PriorityChunk::new(coord, center));
+					if !self.chunk_in_border(coord) { continue; }
+					if !self.loaded_chunks.contains(&coord) && !self.restore_unsaved(coord) && !self.restore_archived(coord) {
+						chunks_to_load.push(PriorityChunk::new_with_dir(coord, center, self.look_dir));
 					}
 				}
 			}
@@ -266,6 +650,24 @@ impl World {
 		}
 	}
 
+	/// Generated and total chunks inside the world border within `radius` chunks of `center` (a sphere, like loading)
+	pub fn generated_around(&self, center: ChunkCoord, radius: i32) -> (usize, usize) {
+		let (cx, cy, cz) = center.unpack();
+		let (mut done, mut total) = (0, 0);
+		for x in -radius..=radius {
+			for y in -radius..=radius {
+				for z in -radius..=radius {
+					if x * x + y * y + z * z > radius * radius { continue; }
+					let coord = ChunkCoord::new(cx + x, cy + y, cz + z);
+					if !self.chunk_in_border(coord) { continue; }
+					total += 1;
+					if self.get_chunk(&coord).is_some_and(Chunk::finished_gen) { done += 1; }
+				}
+			}
+		}
+		(done, total)
+	}
+
 	/// Adds a chunk to the world
 	#[inline] pub fn set_chunk(&mut self, chunk_coord: ChunkCoord, chunk: Chunk) {
 		self.chunks.insert(chunk_coord, chunk);
@@ -4,25 +4,28 @@ use crate::{
 		math::{ChunkCoord, LocalPos, REGION_SIZE_U, SUFFIX, PREFIX},
 		storage::BlockStorage,
 	},
-	ext::ptr,
-	fs::binary::{BinarySerializable, FixedBinarySize},
-	world::main::World,
+	ext::{ptr, settings::backup_path},
+	fs::binary::{self, BinarySerializable, FixedBinarySize, Migration},
+	world::{data::{self, PlayerData, WorldData}, generation::GenMode, main::World},
 };
 use std::{
 	collections::HashMap,
 	fs::{self, File},
-	hash::BuildHasherDefault,
 	io::{Error, ErrorKind, Result, Write},
 	path::{Path, PathBuf},
 };
-use ahash::AHasher;
-
-// Type aliases
-type FastMap<K, V> = HashMap<K, V, BuildHasherDefault<AHasher>>;
 
 // Constants
 pub const TEMP_FILE_SUFFIX: &str = ".tmp";
 
+/// How region files of older formats become the current one, indexed by the version upgraded from
+pub const REGION_MIGRATIONS: [Migration; binary::FORMAT_VERSION as usize] = [
+	// 0 -> 1: only the header was added, the chunks are laid out the same
+	|data| Some(data.to_vec()),
+	// 1 -> 2: only the checksum was added (and is gone by now)
+	|data| Some(data.to_vec()),
+];
+
 // Directory Management
 // ===================
 
@@ -110,17 +113,66 @@ pub fn del_world(world_name: &str) -> Result<()> {
 /// Save the entire world to disk
 pub fn save_entire_world(world_path: &Path) -> Result<()> {
 	let game_state = ptr::get_gamestate();
-	let world = game_state.world();
-	
-	if world.chunks.is_empty() {
+	// Never write the region files from two places at once
+	game_state.autosave_mut().finish();
+	let time_of_day = game_state.time_of_day().time();
+	let play_time = game_state.play_session().total_secs();
+	let player = PlayerData::for_save(game_state.player(), ptr::get_settings().save_config.keep_flying).to_binary();
+	let world = game_state.world_mut();
+	
+	// Everything gets written, nothing is left for the next auto-save
+	world.unsaved.clear();
+	let unloaded: Vec<_> = world.unsaved_unloaded.drain().collect();
+	
+	// The player and the chunks are saved even if the world data can't be, its error comes after them
+	let clocks = save_clocks(world_path, time_of_day, play_time, world.gen_mode());
+	data::save_player_bytes(world_path, &player)?;
+	// Loaded chunks go last so they win over any older copy of the same chunk
+	let loaded = world.chunks.iter().map(|(coord, chunk)| (*coord, chunk));
+	write_regions(world_path, unloaded.iter().map(|(coord, chunk)| (*coord, chunk)).chain(loaded))?;
+	clocks
+}
+
+/// Writes the clocks, the player (see `PlayerData::to_binary`) and the given chunks, the chunks of the region files that aren't in the list are kept.
+/// Doesn't touch the game state, so the auto-save runs it on its own thread
+pub fn save_chunks(world_path: &Path, time_of_day: f32, play_time: u64, gen_mode: &GenMode, player: &[u8], chunks: &[(ChunkCoord, Chunk)]) -> Result<()> {
+	// Like `save_entire_world`, world data that can't be written doesn't hold back the chunks
+	let clocks = save_clocks(world_path, time_of_day, play_time, gen_mode);
+	data::save_player_bytes(world_path, player)?;
+	write_regions(world_path, chunks.iter().map(|(coord, chunk)| (*coord, chunk)))?;
+	clocks
+}
+
+/// The day clock and the play time are stored with the rest of the world data.
+/// Damaged world data is kept next to it (`.bad`) and started anew for the running world, any other error is returned
+fn save_clocks(world_path: &Path, time_of_day: f32, play_time: u64, gen_mode: &GenMode) -> Result<()> {
+	let mut world_data = match data::load_world_data(world_path) {
+		Err(e) if e.kind() == ErrorKind::InvalidData => {
+			let file_path = world_path.join("world_data.dat");
+			let backup = backup_path(&file_path);
+			println!("Warning: {:?} is damaged ({}), kept as {:?}", file_path, e, backup);
+			fs::rename(&file_path, &backup)?;
+			WorldData { gen_mode: gen_mode.clone(), ..WorldData::new() }
+		}
+		// A file from a newer game, or one that can't be read right now, is left alone rather than overwritten
+		Err(e) => return Err(e),
+		Ok(world_data) => world_data,
+	};
+	world_data.time_of_day = time_of_day;
+	world_data.play_time = play_time;
+	data::save_world_data(world_path, &world_data)
+}
+
+/// Merges the chunks into their region files
+pub fn write_regions<'a>(world_path: &Path, chunks: impl IntoIterator<Item = (ChunkCoord, &'a Chunk)>) -> Result<()> {
+	let regions = group_chunks_by_region(chunks);
+	if regions.is_empty() {
 		return Ok(());
 	}
 	
 	let region_dir = world_path.join("region");
 	fs::create_dir_all(&region_dir)?;
 	
-	let regions = group_chunks_by_region(&world.chunks);
-	
 	for (region_coord, chunks) in regions {
 		save_region(region_coord, chunks, &region_dir)?;
 	}
@@ -148,9 +200,18 @@ fn save_region(
 	let file_path = region_file_path(region_dir, region_coord);
 	let temp_path = file_path.with_extension(TEMP_FILE_SUFFIX);
 	
-	// Load existing chunks if the file exists
-	let existing_chunks = load_existing_chunks(&file_path, region_coord)
-		.unwrap_or_default();
+	// Load existing chunks if the file exists, a damaged one is kept next to it (`.bad`) before the region is written anew
+	let existing_chunks = match load_existing_chunks(&file_path, region_coord) {
+		Err(e) if e.kind() == ErrorKind::InvalidData => {
+			let backup = backup_path(&file_path);
+			println!("Warning: {:?} is damaged ({}), kept as {:?}", file_path, e, backup);
+			fs::rename(&file_path, &backup)?;
+			Vec::new()
+		}
+		// A file from a newer game, or one that can't be read right now, is left alone rather than overwritten
+		Err(e) => return Err(e),
+		Ok(existing) => existing,
+	};
 	
 	// Merge existing and new chunks
 	let mut all_chunks: HashMap<ChunkCoord, Vec<u8>> = existing_chunks
@@ -173,12 +234,12 @@ fn save_region(
 }
 
 /// Group chunks by their region coordinates
-fn group_chunks_by_region(chunks: &FastMap<ChunkCoord, Chunk>) -> HashMap<ChunkCoord, Vec<(ChunkCoord, &Chunk)>> {
+fn group_chunks_by_region<'a>(chunks: impl IntoIterator<Item = (ChunkCoord, &'a Chunk)>) -> HashMap<ChunkCoord, Vec<(ChunkCoord, &'a Chunk)>> {
 	let mut regions: HashMap<ChunkCoord, Vec<(ChunkCoord, &Chunk)>> = HashMap::new();
 	
 	for (coord, chunk) in chunks {
-		let region_coord = ChunkCoord::to_region_step(*coord);
-		regions.entry(region_coord).or_default().push((*coord, chunk));
+		let region_coord = ChunkCoord::to_region_step(coord);
+		regions.entry(region_coord).or_default().push((coord, chunk));
 	}
 	
 	regions
@@ -200,7 +261,8 @@ fn serialize_region_data(chunks: &HashMap<ChunkCoord, Vec<u8>>, region_coord: Ch
 		data.extend_from_slice(chunk_data);
 	}
 	
-	Ok(data)
+	// One header and checksum for the whole file
+	Ok(binary::write_versioned(&data))
 }
 
 /// Convert global chunk coordinate to local region coordinate
@@ -221,6 +283,40 @@ fn coord_to_local(global_coord: ChunkCoord, region_coord: ChunkCoord) -> Result<
 	Ok((local_x, local_y, local_z))
 }
 
+// Single Chunk Operations
+// =======================
+
+impl World {
+	/// Serializes one chunk (block storage + entities) so it can be streamed to disk on its own
+	/// Empty chunks always end up as the same few byte blob (uniform air + no entities)
+	pub fn save_chunk(&self, coord: ChunkCoord) -> Result<Vec<u8>> {
+		let chunk = self.get_chunk(&coord)
+			.ok_or_else(|| Error::new(ErrorKind::NotFound, format!("Chunk {:?} is not loaded", coord)))?;
+
+		let mut data = Vec::with_capacity(chunk.binary_size());
+		data.extend_from_slice(&chunk.storage().to_binary());
+		data.extend_from_slice(&chunk.entities().to_binary());
+		Ok(data)
+	}
+
+	/// Restores a chunk from the bytes made by `save_chunk`, replacing whatever was at `coord`
+	pub fn load_chunk_bytes(&mut self, coord: ChunkCoord, bytes: &[u8]) -> Result<()> {
+		let mut chunk = Chunk::from_binary(bytes)
+			.ok_or_else(|| Error::new(ErrorKind::InvalidData, format!("Invalid chunk data for {:?}", coord)))?;
+
+		// Decompress storage if needed
+		if let Some(storage) = BlockStorage::from_rle(&chunk.storage()) {
+			*chunk.storage_mut() = storage;
+		}
+
+		self.set_adjacent_un_final(coord);
+		self.set_chunk(coord, chunk);
+		self.propagate_light(coord);
+		self.create_bind_group(coord);
+		Ok(())
+	}
+}
+
 // World Load Operations
 // ====================
 
@@ -232,6 +328,9 @@ pub fn load_entire_world(world_path: &Path) -> Result<()> {
 		return Err(Error::new(ErrorKind::NotFound, "World region directory not found"));
 	}
 	
+	// Let a running auto-save finish its files first
+	ptr::get_gamestate().autosave_mut().finish();
+	
 	let entries = fs::read_dir(&region_dir)?;
 	let mut loaded_world = World::empty();
 	
@@ -249,12 +348,19 @@ pub fn load_entire_world(world_path: &Path) -> Result<()> {
 	
 	// Transfer loaded chunks to the game world
 	let game_world = ptr::get_gamestate().world_mut();
-	for (coord, chunk) in loaded_world.chunks {
+	game_world.history.clear(); // the edits may not match the loaded blocks anymore
+	for (coord, chunk) in std::mem::take(&mut loaded_world.chunks) {
+		game_world.unsaved.remove(&coord); // back to what is on disk
 		game_world.chunks.insert(coord, chunk);
 		game_world.loaded_chunks.insert(coord);
 		game_world.create_bind_group(coord);
 	}
 	
+	// Back to where the player was at the save, older saves leave it be
+	if let Some(player) = data::load_player_data(world_path)? {
+		player.apply(ptr::get_gamestate().player_mut());
+	}
+	
 	Ok(())
 }
 
@@ -262,6 +368,7 @@ pub fn load_entire_world(world_path: &Path) -> Result<()> {
 fn load_region_file(path: &Path, world: &mut World) -> Result<()> {
 	let region_coord = parse_region_filename(path)?;
 	let bytes = fs::read(path)?;
+	let bytes = binary::read_versioned(&bytes, &REGION_MIGRATIONS)?;
 
 	if bytes.len() < usize::BINARY_SIZE {
 		return Err(Error::new(ErrorKind::InvalidData, "Region file too small"));
@@ -289,12 +396,13 @@ fn load_region_file(path: &Path, world: &mut World) -> Result<()> {
 }
 
 /// Load existing chunks from a region file
-fn load_existing_chunks(path: &Path, region_coord: ChunkCoord) -> Result<Vec<(ChunkCoord, Chunk)>> {
+pub fn load_existing_chunks(path: &Path, region_coord: ChunkCoord) -> Result<Vec<(ChunkCoord, Chunk)>> {
 	if !path.exists() {
 		return Ok(Vec::new());
 	}
 	
 	let bytes = fs::read(path)?;
+	let bytes = binary::read_versioned(&bytes, &REGION_MIGRATIONS)?;
 	
 	if bytes.len() < usize::BINARY_SIZE {
 		return Ok(Vec::new());
@@ -306,11 +414,11 @@ fn load_existing_chunks(path: &Path, region_coord: ChunkCoord) -> Result<Vec<(Ch
 	let mut chunks = Vec::new();
 	let mut cursor = usize::BINARY_SIZE;
 	
+	// A chunk that doesn't read makes the whole file damaged, the chunks after it would be lost on the next write
 	for _ in 0..chunk_count {
-		match load_chunk(&bytes, &mut cursor, region_coord) {
-			Ok((coord, chunk)) => chunks.push((coord, chunk)),
-			Err(_) => break, // Stop on first error to avoid corruption
-		}
+		let chunk = load_chunk(&bytes, &mut cursor, region_coord)
+			.map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+		chunks.push(chunk);
 	}
 	
 	Ok(chunks)
@@ -331,9 +439,9 @@ fn load_chunk(bytes: &[u8], cursor: &mut usize, region_coord: ChunkCoord) -> Res
 	}
 	
 	let global_coord = region_coord.from_region_step() + local_coord.to_chunk_coord();
-	let mut chunk = Chunk::from_binary(&bytes[*cursor..])
+	let (mut chunk, len) = Chunk::read_binary(&bytes[*cursor..])
 		.ok_or_else(|| Error::new(ErrorKind::InvalidData, "Invalid chunk data"))?;
-	*cursor += chunk.binary_size();
+	*cursor += len;
 	
 	// Decompress storage if needed
 	if let Some(storage) = BlockStorage::from_rle(&chunk.storage()) {
@@ -347,7 +455,7 @@ fn load_chunk(bytes: &[u8], cursor: &mut usize, region_coord: ChunkCoord) -> Res
 // ================
 
 /// Generate the file path for a region file
-fn region_file_path(region_dir: &Path, coord: ChunkCoord) -> PathBuf {
+pub fn region_file_path(region_dir: &Path, coord: ChunkCoord) -> PathBuf {
 	let (x, y, z) = coord.unpack();
 	let filename = format!("{}{}.{}.{}{}", PREFIX, x, y, z, SUFFIX);
 	region_dir.join(filename)
@@ -0,0 +1,295 @@
+
+use crate::{
+	block::{
+		main::{Block, Material},
+		math::{AxisBasic, BlockRotation, ChunkCoord, LocalPos},
+		extra::{get_block_id_from_item_name, get_item_name_from_block_id},
+	},
+	world::{main::World, manager::{get_save_path, TEMP_FILE_SUFFIX}, history::ChunkSnapshot},
+	network::sync,
+	fs::binary::{BinarySerializable, FixedBinarySize},
+	item::inventory::ItemContainer,
+};
+use std::{
+	collections::{HashMap, HashSet},
+	fs::{self, File},
+	io::{Error, ErrorKind, Read, Result, Write},
+	path::{Path, PathBuf},
+};
+use glam::IVec3;
+
+/// Extension of the schematic files
+pub const SCHEMATIC_EXTENSION: &str = "schem";
+/// Most blocks a schematic can hold, bigger copies and files are refused
+pub const MAX_SCHEMATIC_VOLUME: usize = 1 << 24;
+
+/// Blocks in a box of that size (negative sizes count as 0), None past `MAX_SCHEMATIC_VOLUME`
+pub fn checked_volume(size: IVec3) -> Option<usize> {
+	let [x, y, z] = size.max(IVec3::ZERO).to_array().map(|v| v as usize);
+	x.checked_mul(y)?.checked_mul(z).filter(|&volume| volume <= MAX_SCHEMATIC_VOLUME)
+}
+
+/// Quarter turns around one axis, applied to a schematic when it is pasted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PasteRotation {
+	pub axis: AxisBasic,
+	pub steps: u8, // 90° clockwise turns, taken mod 4
+}
+
+impl PasteRotation {
+	pub const NONE: Self = Self::new(AxisBasic::Y, 0);
+	#[inline] pub const fn new(axis: AxisBasic, steps: u8) -> Self {
+		Self { axis, steps: steps % 4 }
+	}
+	/// Turns around the vertical axis, the usual way to rotate a building
+	#[inline] pub const fn around_y(steps: u8) -> Self {
+		Self::new(AxisBasic::Y, steps)
+	}
+	#[inline] pub const fn is_none(&self) -> bool { self.steps == 0 }
+
+	/// Turns an offset the same way `BlockRotation::rotate` turns a facing
+	pub const fn rotate_offset(&self, offset: IVec3) -> IVec3 {
+		let mut v = offset;
+		let mut i = 0;
+		while i < self.steps {
+			v = match self.axis {
+				AxisBasic::X => IVec3::new(v.x, -v.z, v.y),
+				AxisBasic::Y => IVec3::new(v.z, v.y, -v.x),
+				AxisBasic::Z => IVec3::new(-v.y, v.x, v.z),
+			};
+			i += 1;
+		}
+		v
+	}
+	#[inline] pub const fn rotate_block(&self, block: Block) -> Block {
+		Block::from(block.material, block.rotation.rotate(self.axis, self.steps))
+	}
+}
+
+/// A copied box of blocks, x runs fastest then z then y.
+/// Only the blocks are kept, storage contents are not part of a schematic
+#[derive(Debug, Clone, PartialEq)]
+pub struct Schematic {
+	pub size: IVec3,
+	pub blocks: Vec<Block>,
+}
+
+impl Schematic {
+	/// Box of air, negative sizes count as 0, None if it would be bigger than `MAX_SCHEMATIC_VOLUME`
+	pub fn new(size: IVec3) -> Option<Self> {
+		let volume = checked_volume(size)?;
+		Some(Self { size: size.max(IVec3::ZERO), blocks: vec![Block::default(); volume] })
+	}
+	#[inline] pub const fn volume(&self) -> usize { self.blocks.len() }
+
+	#[inline] pub fn index(&self, offset: IVec3) -> Option<usize> {
+		if offset.cmplt(IVec3::ZERO).any() || offset.cmpge(self.size).any() { return None; }
+		Some(((offset.y * self.size.z + offset.z) * self.size.x + offset.x) as usize)
+	}
+	#[inline] pub fn offset(&self, index: usize) -> IVec3 {
+		let index = index as i32;
+		let layer = self.size.x * self.size.z;
+		IVec3::new(index % self.size.x, index / layer, (index % layer) / self.size.x)
+	}
+	#[inline] pub fn get(&self, offset: IVec3) -> Option<Block> {
+		self.index(offset).map(|i| self.blocks[i])
+	}
+	#[inline] pub fn set(&mut self, offset: IVec3, block: Block) {
+		if let Some(i) = self.index(offset) { self.blocks[i] = block; }
+	}
+	/// Every block with its offset from the first corner
+	#[inline] pub fn iter(&self) -> impl Iterator<Item = (IVec3, Block)> + '_ {
+		self.blocks.iter().enumerate().map(|(i, block)| (self.offset(i), *block))
+	}
+
+	/// The schematic turned as a whole, the box is moved back so it starts at 0 again
+	/// and every block gets its own facing turned as well
+	pub fn rotated(&self, rotation: PasteRotation) -> Self {
+		if rotation.is_none() || self.volume() == 0 { return self.clone(); }
+		let corner = rotation.rotate_offset(self.size - IVec3::ONE);
+		let shift = corner.min(IVec3::ZERO);
+		let mut rotated = Self::new(corner.abs() + IVec3::ONE).expect("a turn keeps the volume");
+		for (offset, block) in self.iter() {
+			rotated.set(rotation.rotate_offset(offset) - shift, rotation.rotate_block(block));
+		}
+		rotated
+	}
+
+	/// Writes the schematic to `<name>.schem` in the schematics folder
+	#[inline] pub fn save(&self, name: &str) -> Result<()> {
+		self.save_to(&schematic_path(name))
+	}
+	#[inline] pub fn load(name: &str) -> Result<Self> {
+		Self::load_from(&schematic_path(name))
+	}
+	pub fn save_to(&self, file_path: &Path) -> Result<()> {
+		if let Some(dir) = file_path.parent() {
+			fs::create_dir_all(dir)?;
+		}
+		let temp_path = file_path.with_extension(TEMP_FILE_SUFFIX);
+		{
+			let mut file = File::create(&temp_path)?;
+			file.write_all(&self.to_binary())?;
+		}
+		fs::rename(temp_path, file_path)?;
+		Ok(())
+	}
+	pub fn load_from(file_path: &Path) -> Result<Self> {
+		let mut bytes = Vec::new();
+		File::open(file_path)?.read_to_end(&mut bytes)?;
+		Self::from_binary(&bytes).ok_or_else(|| Error::new(ErrorKind::InvalidData, "Invalid schematic"))
+	}
+}
+
+/// Where a schematic of that name is saved
+#[inline] pub fn schematic_path(name: &str) -> PathBuf {
+	get_save_path().join("schematics").join(format!("{}.{}", name, SCHEMATIC_EXTENSION))
+}
+
+/// Size, a palette of (material name, rotation) and runs of (palette index, length).
+/// Materials go by name so the file still works after the block textures change order
+impl BinarySerializable for Schematic {
+	fn to_binary(&self) -> Vec<u8> {
+		let (palette, runs) = self.palette_runs();
+		let mut data = Vec::with_capacity(self.binary_size());
+		data.extend_from_slice(&self.size.to_binary());
+		data.extend_from_slice(&(palette.len() as u16).to_binary());
+		for block in &palette {
+			data.extend_from_slice(&get_item_name_from_block_id(block.material.inner()).to_binary());
+			data.push(block.rotation.as_u8());
+		}
+		data.extend_from_slice(&(runs.len() as u32).to_binary());
+		for (index, len) in runs {
+			data.extend_from_slice(&index.to_binary());
+			data.extend_from_slice(&len.to_binary());
+		}
+		data
+	}
+
+	fn from_binary(bytes: &[u8]) -> Option<Self> {
+		let size = IVec3::from_binary(bytes.get(..IVec3::BINARY_SIZE)?)?;
+		if size.cmplt(IVec3::ZERO).any() { return None; }
+		let mut offset = IVec3::BINARY_SIZE;
+		let mut read = |len: usize| {
+			let part = bytes.get(offset..offset + len);
+			offset += len;
+			part
+		};
+
+		let palette_len = u16::from_binary(read(u16::BINARY_SIZE)?)?;
+		let mut palette = Vec::with_capacity(palette_len as usize);
+		for _ in 0..palette_len {
+			let name_len = u16::from_binary(read(u16::BINARY_SIZE)?)? as usize;
+			let name = std::str::from_utf8(read(name_len)?).ok()?;
+			let rotation = BlockRotation::from_u8(*read(1)?.first()?)?;
+			palette.push(Block::from(Material(get_block_id_from_item_name(name)), rotation));
+		}
+
+		let volume = checked_volume(size)?;
+		let run_count = u32::from_binary(read(u32::BINARY_SIZE)?)?;
+		// Grows with the runs that are really there, the header alone can't make it allocate
+		let mut blocks = Vec::new();
+		for _ in 0..run_count {
+			let index = u16::from_binary(read(u16::BINARY_SIZE)?)?;
+			let len = u32::from_binary(read(u32::BINARY_SIZE)?)? as usize;
+			if blocks.len() + len > volume { return None; }
+			blocks.extend(std::iter::repeat_n(*palette.get(index as usize)?, len));
+		}
+		if blocks.len() != volume { return None; }
+		Some(Self { size, blocks })
+	}
+
+	fn binary_size(&self) -> usize {
+		let (palette, runs) = self.palette_runs();
+		IVec3::BINARY_SIZE + u16::BINARY_SIZE
+			+ palette.iter().map(|b| get_item_name_from_block_id(b.material.inner()).binary_size() + 1).sum::<usize>()
+			+ u32::BINARY_SIZE + runs.len() * (u16::BINARY_SIZE + u32::BINARY_SIZE)
+	}
+}
+
+impl Schematic {
+	/// Distinct blocks in order of appearance and the runs of equal blocks
+	fn palette_runs(&self) -> (Vec<Block>, Vec<(u16, u32)>) {
+		let mut palette: Vec<Block> = Vec::new();
+		let mut runs: Vec<(u16, u32)> = Vec::new();
+		for block in &self.blocks {
+			let index = match palette.iter().position(|b| b == block) {
+				Some(i) => i as u16,
+				None => { palette.push(*block); (palette.len() - 1) as u16 },
+			};
+			match runs.last_mut() {
+				Some((last, len)) if *last == index => *len += 1,
+				_ => runs.push((index, 1)),
+			}
+		}
+		(palette, runs)
+	}
+}
+
+impl World {
+	/// Copies the box `min..=max` (corners in any order), missing chunks read as air.
+	/// None if the box is bigger than `MAX_SCHEMATIC_VOLUME`
+	pub fn copy_region(&self, min: IVec3, max: IVec3) -> Option<Schematic> {
+		let (lo, hi) = (min.min(max), min.max(max));
+		let extent = |lo: i32, hi: i32| i32::try_from(hi as i64 - lo as i64 + 1).ok();
+		let mut schematic = Schematic::new(IVec3::new(extent(lo.x, hi.x)?, extent(lo.y, hi.y)?, extent(lo.z, hi.z)?))?;
+		for i in 0..schematic.volume() {
+			let offset = schematic.offset(i);
+			schematic.blocks[i] = self.get_block(lo + offset);
+		}
+		Some(schematic)
+	}
+
+	/// Places the schematic with its first corner (after the rotation) at `origin`, the whole paste is one undo step.
+	/// With `overwrite` every block of the box is replaced, air included,
+	/// otherwise only air in the world is filled and the air of the schematic is skipped.
+	/// Blocks that land in chunks which are not loaded are skipped.
+	/// Returns how many blocks changed
+	pub fn paste(&mut self, origin: IVec3, schematic: &Schematic, rotation: PasteRotation, overwrite: bool) -> usize {
+		let schematic = schematic.rotated(rotation);
+		self.end_edit_group();
+		// is_storage goes through the item lut by name, so only do it once per material
+		let mut storage_cache: HashMap<Material, bool> = HashMap::new();
+		let mut touched = HashSet::new();
+		let mut changed = 0;
+
+		for (offset, block) in schematic.iter() {
+			if !overwrite && block.is_empty() { continue; }
+			let pos = origin + offset;
+			if !self.in_border(pos) { continue; }
+			let coord = ChunkCoord::from_world_pos(pos);
+			let Some(chunk) = self.chunks.get_mut(&coord) else { continue; };
+			let local = LocalPos::from(pos);
+			let idx = usize::from(local);
+			let old = chunk.get_block(idx);
+			if old == block || (!overwrite && !old.is_empty()) { continue; }
+
+			let old_is_storage = *storage_cache.entry(old.material()).or_insert_with(|| old.is_storage());
+			// The first change in a chunk keeps it as it was for undo
+			if !touched.contains(&coord) {
+				self.history.record_snapshot(ChunkSnapshot { coord, storage: chunk.storage().clone(), entities: chunk.entities().clone() });
+			}
+			if old_is_storage { chunk.remove_entity(local); }
+			chunk.storage_mut().set(idx, block);
+			if *storage_cache.entry(block.material()).or_insert_with(|| block.is_storage()) {
+				let slot = block.get_storage();
+				chunk.add_entity(local, ItemContainer::new(slot.rows(), slot.cols()));
+			}
+			touched.insert(coord);
+			changed += 1;
+		}
+
+		for &coord in &touched {
+			if let Some(chunk) = self.chunks.get_mut(&coord) {
+				chunk.optimize_storage();
+				chunk.dirty = true;
+			}
+			self.set_adjacent_un_final(coord);
+			self.mark_unsaved(coord);
+			self.propagate_light(coord);
+		}
+		sync::chunks_changed(self, touched);
+		self.end_edit_group();
+		changed
+	}
+}
@@ -3,7 +3,8 @@ use crate::block::entity::StorageAction;
 use crate::block::entity::BlockEntity;
 use crate::block::entity::StorageProperties;
 use crate::item::filter::ItemFilter;
-use crate::item::inventory::ItemContainer;
+use crate::item::inventory::{Inventory, ItemContainer, Slot};
+use crate::world::data::PlayerData;
 use crate::block::entity::EntityStorage;
 use crate::block::math::{BlockRotation, ChunkCoord, LocalPos};
 use crate::block::main::{Block, Material, Chunk};
@@ -13,7 +14,7 @@ use crate::impl_option_binary;
 use std::collections::HashMap;
 use std::hash::BuildHasherDefault;
 use ahash::AHasher;
-use glam::IVec3;
+use glam::{IVec3, Vec3};
 
 impl_option_binary!(BlockEntity);
 
@@ -164,8 +165,23 @@ impl BinarySerializable for BlockStorage {
 					data.push(index);
 				}
 			},
-			Self::Giant { .. } |
-			Self::Zigzag { .. } => todo!()
+			Self::Giant { palette, indices } => {
+				data.push(self.to_type().as_u8());
+				// Write palette length (can go up to 4K so u8 is not enough)
+				data.extend_from_slice(&(palette.len() as u16).to_binary());
+				// Write palette
+				for block in palette {
+					data.extend_from_slice(&block.to_binary());
+				}
+				// 12-bit packed indices are written as is
+				data.extend_from_slice(&indices[..]);
+			},
+			Self::Zigzag { blocks } => {
+				data.push(self.to_type().as_u8());
+				for block in blocks.iter() {
+					data.extend_from_slice(&block.to_binary());
+				}
+			},
 		}
 		data
 	}
@@ -189,16 +205,29 @@ impl BinarySerializable for BlockStorage {
 			}
 			Some(palette)
 		}
+		fn read_giant_palette(bytes: &[u8], offset: &mut usize) -> Option<Vec<Block>> {
+			if *offset + u16::BINARY_SIZE > bytes.len() { return None; }
+			let palette_len = u16::from_binary(&bytes[*offset..*offset + u16::BINARY_SIZE])? as usize; *offset += u16::BINARY_SIZE;
+			if *offset + palette_len * Block::BINARY_SIZE > bytes.len() { return None; }
+			let mut palette = Vec::with_capacity(palette_len);
+			for _ in 0..palette_len {
+				let block = Block::from_binary(&bytes[*offset..*offset + Block::BINARY_SIZE])?;
+				*offset += Block::BINARY_SIZE;
+				palette.push(block);
+			}
+			Some(palette)
+		}
 		
 		let mut offset = 0;
 		let storage_type = StorageType::from_u8(bytes[offset])?; offset += 1;
 
 		if offset >= bytes.len() { return None; }
-		match storage_type {
+		// Chunks come from peers too, nothing out of range may reach `get`
+		let storage = match storage_type {
 			StorageType::Uniform => {
 				if offset + Block::BINARY_SIZE > bytes.len() { return None; }
 				let block = Block::from_binary(&bytes[offset..offset + Block::BINARY_SIZE])?;
-				Some(Self::Uniform { block })
+				Self::Uniform { block }
 			}
 			StorageType::Compact => {
 				let palette = read_palette(bytes, &mut offset)?;
@@ -207,7 +236,7 @@ impl BinarySerializable for BlockStorage {
 				let mut indices = Box::new([0u8; Chunk::VOLUME/2]);
 				indices.copy_from_slice(&bytes[offset..offset + Chunk::VOLUME/2]);
 
-				Some(Self::Compact { palette, indices })
+				Self::Compact { palette, indices }
 			}
 			StorageType::Sparse => {
 				let palette = read_palette(bytes, &mut offset)?;
@@ -216,7 +245,7 @@ impl BinarySerializable for BlockStorage {
 				let mut indices = Box::new([0u8; Chunk::VOLUME]);
 				indices.copy_from_slice(&bytes[offset..offset + Chunk::VOLUME]);
 
-				Some(Self::Sparse { palette, indices })
+				Self::Sparse { palette, indices }
 			}
 			StorageType::Rle => {
 				let palette = read_palette(bytes, &mut offset)?;
@@ -236,11 +265,29 @@ impl BinarySerializable for BlockStorage {
 					offset += 2;
 				}
 				// Convert RLE to Compact/Sparse storage
-				Some(Self::Rle { palette, runs })
+				Self::Rle { palette, runs }
 			},
-			StorageType::Giant |
-			StorageType::Zigzag => todo!()
-		}
+			StorageType::Giant => {
+				let palette = read_giant_palette(bytes, &mut offset)?;
+
+				if offset + Chunk::VOLUME * 3 / 2 > bytes.len() { return None; }
+				let mut indices = Box::new([0u8; Chunk::VOLUME * 3 / 2]);
+				indices.copy_from_slice(&bytes[offset..offset + Chunk::VOLUME * 3 / 2]);
+
+				Self::Giant { palette, indices }
+			},
+			StorageType::Zigzag => {
+				if offset + Chunk::VOLUME * Block::BINARY_SIZE > bytes.len() { return None; }
+				let mut blocks = Box::new([Block::default(); Chunk::VOLUME]);
+				for block in blocks.iter_mut() {
+					*block = Block::from_binary(&bytes[offset..offset + Block::BINARY_SIZE])?;
+					offset += Block::BINARY_SIZE;
+				}
+
+				Self::Zigzag { blocks }
+			},
+		};
+		storage.is_consistent().then_some(storage)
 	}
 
 	fn binary_size(&self) -> usize {
@@ -270,67 +317,47 @@ impl BinarySerializable for BlockStorage {
 				2 + // run count
 				runs.len() * 2 // runs (each run is 2 bytes: count + index)
 			}
-			Self::Giant { .. } |
-			Self::Zigzag { .. } => todo!()
+			Self::Giant { palette, .. } => {
+				1 + // type marker
+				2 + // palette length (u16)
+				palette.len() * Block::BINARY_SIZE + // palette entries
+				Chunk::VOLUME * 3 / 2 // 12-bit indices array
+			}
+			Self::Zigzag { .. } => {
+				1 + // type marker
+				Chunk::VOLUME * Block::BINARY_SIZE // every block as is
+			}
 		}
 	}
 }
 
 
-impl BinarySerializable for EntityStorage {
-	fn to_binary(&self) -> Vec<u8> {
-		let mut data = Vec::new();
-		
-		match self {
-			EntityStorage::Empty => {
-				data.push(0); // Marker for Empty
-			}
-			EntityStorage::Sparse(map) => {
-				data.push(1); // Marker for Sparse
-				data.extend_from_slice(&(map.len() as u16).to_binary()); // Number of entries
-				
-				for (pos, container) in map {
-					data.extend_from_slice(&pos.to_binary());
-					data.extend_from_slice(&container.to_binary());
-				}
-			}
-			EntityStorage::Dense(array) => {
-				data.push(2); // Marker for Dense
-				
-				// Write presence markers for all Chunk::VOLUME positions
-				for maybe_container in array.iter() {
-					data.extend_from_slice(&maybe_container.to_binary());
-				}
-			}
-		}
-		
-		data
-	}
-	
-	fn from_binary(bytes: &[u8]) -> Option<Self> {
-		if bytes.is_empty() { return None; }
-		
-		let mut offset = 0;
-		let storage_type = bytes[offset];
-		offset += 1;
+/// Storage marker of the entity list, the format every chunk is written in now.
+/// Markers 0 (Empty), 1 (Sparse) and 2 (Dense) are still read so older saves load
+const ENTITY_LIST: u8 = 3;
+/// Entry flag of a container with nothing in it, only its size and properties are written
+const ENTITY_EMPTY: u8 = 0;
+/// Entry flag of a container with items, written in full
+const ENTITY_FULL: u8 = 1;
+
+impl EntityStorage {
+	/// Reads the storage and the number of bytes it took up.
+	/// Older saves wrote empty containers in full, so the length can't be worked out from the value
+	pub fn read_binary(bytes: &[u8]) -> Option<(Self, usize)> {
+		let storage_type = *bytes.first()?;
+		let mut offset = 1;
 		
 		match storage_type {
-			0 => Some(EntityStorage::Empty),
+			0 => Some((EntityStorage::Empty, offset)),
 			1 => {
 				// Sparse storage
-				if offset + 2 > bytes.len() {
-					return None;
-				}
-				let count = u16::from_binary(&bytes[offset..offset + 2])? as usize;
-				offset += 2;
+				let count = u16::from_binary(bytes.get(offset..offset + u16::BINARY_SIZE)?)? as usize;
+				offset += u16::BINARY_SIZE;
 				
 				let mut map = FastMap::default();
 				
 				for _ in 0..count {
-					if offset + LocalPos::BINARY_SIZE > bytes.len() {
-						return None;
-					}
-					let pos = LocalPos::from_binary(&bytes[offset..offset + LocalPos::BINARY_SIZE])?;
+					let pos = LocalPos::from_binary(bytes.get(offset..offset + LocalPos::BINARY_SIZE)?)?;
 					offset += LocalPos::BINARY_SIZE;
 					
 					let container = BlockEntity::from_binary(&bytes[offset..])?;
@@ -339,7 +366,7 @@ impl BinarySerializable for EntityStorage {
 					map.insert(pos, container);
 				}
 				
-				Some(EntityStorage::Sparse(map))
+				Some((EntityStorage::Sparse(map), offset))
 			}
 			2 => {
 				// Dense storage
@@ -356,36 +383,105 @@ impl BinarySerializable for EntityStorage {
 					array[i] = maybe_container;
 				}
 				
-				Some(EntityStorage::Dense(array))
+				Some((EntityStorage::Dense(array), offset))
+			}
+			ENTITY_LIST => {
+				let count = u16::from_binary(bytes.get(offset..offset + u16::BINARY_SIZE)?)? as usize;
+				offset += u16::BINARY_SIZE;
+				
+				let mut storage = EntityStorage::Empty;
+				for _ in 0..count {
+					let pos = LocalPos::from_binary(bytes.get(offset..offset + LocalPos::BINARY_SIZE)?)?;
+					offset += LocalPos::BINARY_SIZE;
+					
+					let (entity, len) = read_entity(bytes.get(offset..)?)?;
+					offset += len;
+					
+					// add picks the representation, a crowded chunk comes back dense
+					storage.add(pos, entity);
+				}
+				
+				Some((storage, offset))
 			}
 			_ => None, // Invalid storage type
 		}
 	}
+}
+
+impl BinarySerializable for EntityStorage {
+	/// Every representation is written as a position ordered entity list, so the same entities always give the same bytes
+	fn to_binary(&self) -> Vec<u8> {
+		if !self.is_some() {
+			return vec![0]; // Marker for Empty
+		}
+		let mut entries: Vec<_> = self.iter().collect();
+		entries.sort_unstable_by_key(|(pos, _)| *pos);
+		
+		let mut data = Vec::with_capacity(self.binary_size());
+		data.push(ENTITY_LIST);
+		data.extend_from_slice(&(entries.len() as u16).to_binary()); // Number of entries
+		
+		for (pos, entity) in entries {
+			data.extend_from_slice(&pos.to_binary());
+			write_entity(&mut data, entity);
+		}
+		
+		data
+	}
+	
+	#[inline] fn from_binary(bytes: &[u8]) -> Option<Self> {
+		Self::read_binary(bytes).map(|(storage, _)| storage)
+	}
 	
 	fn binary_size(&self) -> usize {
-		match self {
-			EntityStorage::Empty => 1, // Just the type marker
+		if !self.is_some() {
+			return 1; // Just the type marker
+		}
+		1 + // type marker
+		u16::BINARY_SIZE + // count
+		self.iter().map(|(_, entity)| LocalPos::BINARY_SIZE + entity_size(entity)).sum::<usize>()
+	}
+}
+
+/// Writes an entity list entry, a container without items only keeps its size
+fn write_entity(data: &mut Vec<u8>, entity: &BlockEntity) {
+	if entity.storage.count_items() == 0 {
+		data.push(ENTITY_EMPTY);
+		data.extend_from_slice(&entity.storage.size().to_binary());
+		data.extend_from_slice(&entity.properties.to_binary());
+	} else {
+		data.push(ENTITY_FULL);
+		data.extend_from_slice(&entity.to_binary());
+	}
+}
+
+/// Reads an entity list entry and the number of bytes it took up
+fn read_entity(bytes: &[u8]) -> Option<(BlockEntity, usize)> {
+	match *bytes.first()? {
+		ENTITY_EMPTY => {
+			let mut offset = 1;
+			let size = Slot::from_binary(bytes.get(offset..offset + Slot::BINARY_SIZE)?)?;
+			offset += Slot::BINARY_SIZE;
 			
-			EntityStorage::Sparse(map) => {
-				1 + // type marker
-				2 + // count (u16)
-				map.iter().map(|(pos, container)| {
-					pos.binary_size() + container.binary_size()
-				}).sum::<usize>()
-			}
+			let properties = StorageProperties::from_binary(bytes.get(offset..)?)?;
+			offset += properties.binary_size();
 			
-			EntityStorage::Dense(array) => {
-				let mut size = 1 + // type marker
-					Chunk::VOLUME; // presence markers (1 byte each)
-				
-				// Add size of all non-empty containers
-				for maybe_container in array.iter() {
-					size += maybe_container.binary_size();
-				}
-				
-				size
-			}
+			Some((BlockEntity { storage: ItemContainer::with_dimensions(size), properties }, offset))
 		}
+		ENTITY_FULL => {
+			let entity = BlockEntity::from_binary(bytes.get(1..)?)?;
+			let len = 1 + entity.binary_size();
+			Some((entity, len))
+		}
+		_ => None,
+	}
+}
+
+#[inline] fn entity_size(entity: &BlockEntity) -> usize {
+	1 + if entity.storage.count_items() == 0 {
+		Slot::BINARY_SIZE + entity.properties.binary_size()
+	} else {
+		entity.binary_size()
 	}
 }
 
@@ -569,16 +665,55 @@ impl BinarySerializable for Chunk {
 		data.extend_from_slice(&self.entities().to_binary());
 		data
 	}
-	fn from_binary(bytes: &[u8]) -> Option<Self> {
-		let storage = BlockStorage::from_binary(bytes)?;
-		let offset = storage.binary_size();
-		let entity = EntityStorage::from_binary(&bytes[offset..])?;
-		
-		Some(Chunk::from_storage_and_entities(storage, entity))
+	#[inline] fn from_binary(bytes: &[u8]) -> Option<Self> {
+		Self::read_binary(bytes).map(|(chunk, _)| chunk)
 	}
 	fn binary_size(&self) -> usize {
 		self.storage().binary_size() + self.entities().binary_size()
 	}
 }
 
+impl BinarySerializable for PlayerData {
+	fn to_binary(&self) -> Vec<u8> {
+		let mut data = Vec::with_capacity(self.binary_size());
+		data.extend_from_slice(&self.pos.to_binary());
+		data.extend_from_slice(&self.yaw.to_binary());
+		data.extend_from_slice(&self.pitch.to_binary());
+		data.push(self.selected_slot);
+		data.push(self.flying as u8);
+		data.extend_from_slice(&self.inventory.to_binary());
+		data
+	}
+	fn from_binary(bytes: &[u8]) -> Option<Self> {
+		let mut offset = 0;
+		
+		let pos = Vec3::from_binary(bytes.get(offset..offset + Vec3::BINARY_SIZE)?)?;
+		offset += Vec3::BINARY_SIZE;
+		let yaw = f32::from_binary(bytes.get(offset..offset + f32::BINARY_SIZE)?)?;
+		offset += f32::BINARY_SIZE;
+		let pitch = f32::from_binary(bytes.get(offset..offset + f32::BINARY_SIZE)?)?;
+		offset += f32::BINARY_SIZE;
+		let selected_slot = *bytes.get(offset)?;
+		let flying = *bytes.get(offset + 1)? != 0;
+		offset += 2;
+		
+		// A broken inventory shouldn't cost the position too
+		let inventory = bytes.get(offset..).and_then(Inventory::from_binary).unwrap_or_else(Inventory::default);
+		
+		Some(Self { pos, yaw, pitch, selected_slot, flying, inventory })
+	}
+	fn binary_size(&self) -> usize {
+		Vec3::BINARY_SIZE + f32::BINARY_SIZE * 2 + 2 + self.inventory.binary_size()
+	}
+}
 
+impl Chunk {
+	/// Reads a chunk and the number of bytes it took up, use this to walk several chunks stored back to back
+	pub fn read_binary(bytes: &[u8]) -> Option<(Self, usize)> {
+		let storage = BlockStorage::from_binary(bytes)?;
+		let offset = storage.binary_size();
+		let (entities, len) = EntityStorage::read_binary(bytes.get(offset..)?)?;
+		
+		Some((Chunk::from_storage_and_entities(storage, entities), offset + len))
+	}
+}
@@ -0,0 +1,150 @@
+
+use crate::{
+	block::main::{Block, Chunk, Material},
+	block::math::{ChunkCoord, LocalPos},
+	block::extra::get_block_id_from_item_name,
+	world::biome::BiomeGen,
+	utils::rng::Rand,
+};
+use glam::IVec3;
+
+/// Width of the placement grid, every cell gets at most one structure
+pub const STRUCTURE_CELL: i32 = 8;
+/// 1 in this many cells gets a tree (if its spot is grassy)
+pub const TREE_CHANCE: u32 = 3;
+/// Mixed into the seed so structures don't line up with the terrain noise
+const STRUCTURE_SEED_SALT: u32 = 0x27d4_eb2f;
+
+/// Multi block shape stamped into the world, offsets are relative to the origin (the air block above the ground)
+#[derive(Debug, Clone, PartialEq)]
+pub struct Template {
+	pub blocks: Vec<(IVec3, Block)>,
+	pub min: IVec3, // bounds of the offsets (inclusive)
+	pub max: IVec3,
+}
+
+impl Template {
+	#[inline] pub fn new(blocks: Vec<(IVec3, Block)>) -> Self {
+		let min = blocks.iter().fold(IVec3::ZERO, |m, (o, _)| m.min(*o));
+		let max = blocks.iter().fold(IVec3::ZERO, |m, (o, _)| m.max(*o));
+		Self { blocks, min, max }
+	}
+
+	/// Trunk of `height` blocks, two wide leaf layers around its top and a small crown
+	pub fn tree(trunk: Block, leaves: Block, height: i32) -> Self {
+		let mut blocks: Vec<(IVec3, Block)> = (0..height).map(|y| (IVec3::new(0, y, 0), trunk)).collect();
+		for y in height - 2..height {
+			for x in -2..=2i32 {
+				for z in -2..=2i32 {
+					if (x == 0 && z == 0) || (x.abs() == 2 && z.abs() == 2) { continue; } // trunk and corners
+					blocks.push((IVec3::new(x, y, z), leaves));
+				}
+			}
+		}
+		for x in -1..=1i32 {
+			for z in -1..=1i32 {
+				blocks.push((IVec3::new(x, height, z), leaves));
+				if x == 0 || z == 0 {
+					blocks.push((IVec3::new(x, height + 1, z), leaves));
+				}
+			}
+		}
+		Self::new(blocks)
+	}
+}
+
+/// Decides where structures go and stamps them into chunks.
+/// Every chunk looks at all the grid cells whose structure could reach into it and writes its own part,
+/// so the result doesn't depend on load order and a structure across a chunk border is never half there
+pub struct StructurePlacer {
+	seed: u32,
+	tree: Template,
+	grass: Material, // trees only grow on this surface
+}
+
+impl StructurePlacer {
+	pub fn new(seed: u32) -> Self {
+		let block = |name: &str| Block::new(Material(get_block_id_from_item_name(name)));
+		Self {
+			seed: seed ^ STRUCTURE_SEED_SALT,
+			tree: Template::tree(block("trunk_mid"), block("leaves"), 5),
+			grass: Material(get_block_id_from_item_name("dirt_grass")),
+		}
+	}
+	#[inline] pub const fn tree(&self) -> &Template { &self.tree }
+
+	/// Hash of a grid cell, the same seed always gives the same value
+	#[inline] pub const fn cell_hash(&self, cell_x: i32, cell_z: i32) -> u32 {
+		Rand::pcg_hash(self.seed ^ Rand::pcg_hash(cell_x as u32 ^ Rand::pcg_hash(cell_z as u32)))
+	}
+
+	/// Column the cell's tree would stand on, None if the cell has none
+	#[inline] pub const fn cell_spot(&self, cell_x: i32, cell_z: i32) -> Option<(i32, i32)> {
+		let hash = self.cell_hash(cell_x, cell_z);
+		if hash % TREE_CHANCE != 0 { return None; }
+		// Off the cell edge so the trunks of neighboring cells never touch
+		let inner = (STRUCTURE_CELL - 2) as u32;
+		let x = cell_x * STRUCTURE_CELL + 1 + ((hash >> 8) % inner) as i32;
+		let z = cell_z * STRUCTURE_CELL + 1 + ((hash >> 16) % inner) as i32;
+		Some((x, z))
+	}
+
+	/// Origin of the cell's tree, None if the cell has none or its spot isn't grassy generated terrain
+	pub fn cell_origin(&self, cell_x: i32, cell_z: i32, biome_gen: &BiomeGen) -> Option<IVec3> {
+		let (x, z) = self.cell_spot(cell_x, cell_z)?;
+		let column = biome_gen.column(x, z);
+		// Terrain only exists from chunk y -1 to 6 (everything lower is solid stone, higher is cut off)
+		if column.height < -Chunk::SIZE_I || column.height >= 7 * Chunk::SIZE_I { return None; }
+		if column.surface != self.grass || column.surface_depth <= 0 { return None; }
+		Some(IVec3::new(x, column.height + 1, z))
+	}
+
+	/// Stamps every structure overlapping the chunk
+	#[inline] pub fn place(&self, coord: ChunkCoord, chunk: &mut Chunk, biome_gen: &BiomeGen) {
+		let origins = self.origins_near(coord, biome_gen);
+		self.stamp(coord, chunk, &origins);
+	}
+
+	/// Origins of all the structures that can reach into the chunk, in a fixed (cell) order
+	pub fn origins_near(&self, coord: ChunkCoord, biome_gen: &BiomeGen) -> Vec<IVec3> {
+		let (chunk_min, chunk_max) = chunk_bounds(coord);
+		let (reach_min, reach_max) = (self.tree.min, self.tree.max);
+
+		// Any origin in the chunk bounds grown by the template reach can touch the chunk
+		let cell_min = (chunk_min - reach_max).div_euclid(IVec3::splat(STRUCTURE_CELL));
+		let cell_max = (chunk_max - reach_min).div_euclid(IVec3::splat(STRUCTURE_CELL));
+
+		let mut origins = Vec::new();
+		for cell_x in cell_min.x..=cell_max.x {
+			for cell_z in cell_min.z..=cell_max.z {
+				let Some(origin) = self.cell_origin(cell_x, cell_z, biome_gen) else { continue; };
+				if origin.y + reach_max.y < chunk_min.y || origin.y + reach_min.y > chunk_max.y { continue; }
+				origins.push(origin);
+			}
+		}
+		origins
+	}
+
+	/// Writes the part of the trees at `origins` that lies in the chunk, only into air so the terrain is never dug out.
+	/// Where two trees overlap the earlier origin wins, the same in every chunk since the order is fixed
+	pub fn stamp(&self, coord: ChunkCoord, chunk: &mut Chunk, origins: &[IVec3]) {
+		let (chunk_min, _) = chunk_bounds(coord);
+		for origin in origins {
+			for (offset, block) in &self.tree.blocks {
+				let local = *origin + *offset - chunk_min;
+				if local.cmplt(IVec3::ZERO).any() || local.cmpge(IVec3::splat(Chunk::SIZE_I)).any() { continue; }
+				let index: usize = LocalPos::new(local.x as u8, local.y as u8, local.z as u8).into();
+				if chunk.get_block(index).is_empty() {
+					chunk.set_block(index, *block);
+				}
+			}
+		}
+	}
+}
+
+/// First and last block of the chunk
+#[inline] fn chunk_bounds(coord: ChunkCoord) -> (IVec3, IVec3) {
+	let (x, y, z) = coord.unpack_to_worldpos();
+	let min = IVec3::new(x, y, z);
+	(min, min + IVec3::splat(Chunk::SIZE_I - 1))
+}
@@ -1,36 +1,60 @@
 
 use crate::block::{math::ChunkCoord, main::Chunk};
+use crate::render::{meshing::InstanceRaw, world::NeighboringChunks};
 use crate::world::main::World;
+use crate::ext::timer::ScopeTimer;
+use glam::{IVec3, Vec3};
 use std::{
 	cmp::Ordering as CmpOrdering,
+	collections::{BinaryHeap, HashSet},
 	sync::{atomic::Ordering, Arc},
 	thread,
+	time::{Duration, Instant},
 };
 
+/// How much the look direction scales the distance, chunks straight ahead count as `1 - LOOK_WEIGHT` times as far
+/// and the ones behind as `1 + LOOK_WEIGHT`, so a close chunk behind the player still beats a far one in view
+pub const LOOK_WEIGHT: f32 = 0.5;
+/// Fixed point scale of `PriorityChunk::priority`, keeps the ordering on integers
+const PRIORITY_SCALE: f32 = 16.;
+
+/// How long `World::stop_generation` waits for the workers before detaching them
+pub const WORKER_JOIN_TIMEOUT: Duration = Duration::from_secs(2);
+
 /// A chunk with priority information for loading order
 #[derive(Debug, Clone, Eq, Copy)]
 pub struct PriorityChunk {
 	coord: ChunkCoord,
 	distance_sq: i32,
+	priority: i32, // distance_sq weighted by the look direction, lower loads first
 }
 impl PriorityChunk {
 	/// Creates a new PriorityChunk with calculated distance from center
 	pub fn new(coord: ChunkCoord, center: ChunkCoord) -> Self {
+		Self::new_with_dir(coord, center, Vec3::ZERO)
+	}
+	/// Same as `new` but chunks in the `look_dir` direction go first, a zero direction weights nothing
+	pub fn new_with_dir(coord: ChunkCoord, center: ChunkCoord, look_dir: Vec3) -> Self {
 		let (x, y, z) = coord.unpack();
 		let (cx, cy, cz) = center.unpack();
-		let dx = x - cx;
-		let dy = y - cy;
-		let dz = z - cz;
+		let offset = IVec3::new(x - cx, y - cy, z - cz);
+		let distance_sq = offset.length_squared();
+		
+		let alignment = offset.as_vec3().normalize_or_zero().dot(look_dir.normalize_or_zero()).clamp(-1., 1.);
+		let weighted = distance_sq as f32 * (1. - LOOK_WEIGHT * alignment) * PRIORITY_SCALE;
 		
 		Self {
 			coord,
-			distance_sq: dx * dx + dy * dy + dz * dz,
+			distance_sq,
+			priority: weighted as i32, // saturating, so it is always a valid integer
 		}
 	}
+	#[inline] pub const fn coord(&self) -> ChunkCoord { self.coord }
+	#[inline] pub const fn priority(&self) -> i32 { self.priority }
 }
 impl PartialEq for PriorityChunk {
 	fn eq(&self, other: &Self) -> bool {
-		self.distance_sq == other.distance_sq
+		self.cmp(other) == CmpOrdering::Equal
 	}
 }
 impl PartialOrd for PriorityChunk {
@@ -40,11 +64,56 @@ impl PartialOrd for PriorityChunk {
 }
 impl Ord for PriorityChunk {
 	fn cmp(&self, other: &Self) -> CmpOrdering {
-		// Reverse ordering for min-heap (closest chunks first)
-		other.distance_sq.cmp(&self.distance_sq)
+		// Reverse ordering for min-heap (best chunks first), plain distance breaks ties
+		other.priority.cmp(&self.priority)
+			.then_with(|| other.distance_sq.cmp(&self.distance_sq))
+	}
+}
+
+/// A remesh handed to the worker threads.
+/// The chunk and its neighbors are snapshots (see `Chunk::clone`), so the workers never touch the live world
+#[derive(Debug)]
+pub struct MeshJob {
+	coord: ChunkCoord,
+	id: u32,
+	chunk: Chunk,
+	neighbors: [Option<Chunk>; 6],
+	lod: u8,
+	greedy: bool,
+}
+impl MeshJob {
+	#[inline] pub fn new(coord: ChunkCoord, id: u32, chunk: &Chunk, neighbors: &NeighboringChunks, lod: u8, greedy: bool) -> Self {
+		Self {
+			coord,
+			id,
+			chunk: chunk.clone(),
+			neighbors: neighbors.as_array().map(|c| c.cloned()),
+			lod,
+			greedy,
+		}
+	}
+	#[inline] pub const fn coord(&self) -> ChunkCoord { self.coord }
+
+	/// Builds the instances, the CPU only part of `Chunk::make_mesh`
+	#[inline] pub fn build(&self) -> MeshResult {
+		let _timer = ScopeTimer::new("mesh build");
+		let neighbors = NeighboringChunks::new(self.neighbors.each_ref().map(Option::as_ref));
+		MeshResult {
+			coord: self.coord,
+			id: self.id,
+			instances: self.chunk.build_instances(&neighbors, self.lod, self.greedy),
+		}
 	}
 }
 
+/// Instances built by a worker, uploaded to the gpu on the main thread
+#[derive(Debug)]
+pub struct MeshResult {
+	pub coord: ChunkCoord,
+	pub id: u32, // matches `Chunk::mesh_job` unless the chunk was remeshed or replaced since
+	pub instances: Vec<InstanceRaw>,
+}
+
 impl World {
 	/// Starts chunk generation threads
 	pub fn start_generation_threads(&mut self, thread_count: u8) {
@@ -59,21 +128,33 @@ impl World {
 		for _ in 0..thread_count {
 			let queue = Arc::clone(&self.chunk_generation_queue);
 			let sender = self.chunk_generation_sender.clone();
+			let mesh_queue = Arc::clone(&self.mesh_queue);
+			let mesh_sender = self.meshed_sender.clone();
 			let running = Arc::clone(&self.generation_threads_running);
 			let active_workers = Arc::clone(&self.active_workers);
 			let seed = self.seed();
+			let gen_mode = self.gen_mode().clone();
 			
-			thread::spawn(move || {
+			let handle = thread::spawn(move || {
 				active_workers.fetch_add(1, Ordering::Relaxed);
 				
 				while running.load(Ordering::Relaxed) {
+					// Remeshes first, they are for chunks that are already on screen
+					let job = mesh_queue.try_lock().ok().and_then(|mut q| q.pop_front());
+					if let Some(job) = job {
+						if mesh_sender.send(job.build()).is_err() {
+							break; // Channel disconnected
+						}
+						continue;
+					}
+
 					// Try to get work without blocking first
 					let priority_chunk = {
 						let mut queue = match queue.try_lock() {
 							Ok(q) => q,
 							Err(_) => {
 								// Couldn't get lock, try again after short sleep
-								thread::sleep(std::time::Duration::from_micros(10));
+								thread::sleep(Duration::from_micros(10));
 								continue;
 							}
 						};
@@ -81,7 +162,9 @@ impl World {
 					};
 					
 					if let Some(priority_chunk) = priority_chunk {
-						let chunk = Chunk::generate(priority_chunk.coord, seed);
+						let chunk = Chunk::generate_with(priority_chunk.coord, seed, &gen_mode);
+						// Stopped while generating, the world may be gone already
+						if !running.load(Ordering::Relaxed) { break; }
 						
 						// Non-blocking send attempt
 						if let Err(e) = sender.try_send((priority_chunk.coord, chunk)) {
@@ -90,22 +173,48 @@ impl World {
 							}
 							// Full channel, put the chunk back in queue and sleep
 							queue.lock().unwrap().push(priority_chunk);
-							thread::sleep(std::time::Duration::from_millis(1));
+							thread::sleep(Duration::from_millis(1));
 						}
 					} else {
 						// No work, sleep to avoid busy waiting but not too long
-						thread::sleep(std::time::Duration::from_millis(1));
+						thread::sleep(Duration::from_millis(1));
 					}
 				}
 				
 				active_workers.fetch_sub(1, Ordering::Relaxed);
 			});
+			self.generation_workers.push(handle);
 		}
 	}
 
-	#[inline]
-	pub fn stop_generation_threads(&self) {
+	/// Stops and joins the worker threads, safe to call when they aren't running.
+	/// Queued chunks are dropped (and unmarked as loaded so they get queued again after a restart).
+	/// Workers still busy after `WORKER_JOIN_TIMEOUT` are detached, they exit on their own once the chunk is done
+	pub fn stop_generation(&mut self) {
 		self.generation_threads_running.store(false, Ordering::Relaxed);
+
+		// A worker can only hold the locks for a pop, so these never wait long
+		for chunk in self.chunk_generation_queue.lock().unwrap().drain() {
+			self.loaded_chunks.remove(&chunk.coord);
+		}
+		for job in self.mesh_queue.lock().unwrap().drain(..) {
+			if let Some(chunk) = self.chunks.get_mut(&job.coord) {
+				chunk.set_mesh_job(0);
+				chunk.dirty = true;
+			}
+		}
+
+		let deadline = Instant::now() + WORKER_JOIN_TIMEOUT;
+		for handle in self.generation_workers.drain(..) {
+			while !handle.is_finished() && Instant::now() < deadline {
+				thread::sleep(Duration::from_millis(1));
+			}
+			if handle.is_finished() {
+				let _ = handle.join();
+			} else {
+				println!("Chunk worker did not stop in {:?}, detaching it", WORKER_JOIN_TIMEOUT);
+			}
+		}
 	}
 	
 	/// Queues a chunk for generation
@@ -130,12 +239,53 @@ impl World {
 	/// Processes any chunks generated by worker threads
 	#[inline] pub fn process_generated_chunks(&mut self) {
 		// Process all available chunks without blocking
-		while let Ok((coord, chunk)) = self.generated_chunks_receiver.try_recv() {
+		while let Ok((coord, mut chunk)) = self.generated_chunks_receiver.try_recv() {
 			if !self.loaded_chunks.contains(&coord) { continue; }
+			if let Some(border) = self.border {
+				border.crop_chunk(coord, &mut chunk);
+			}
 
 			self.set_adjacent_un_final(coord);
 			self.chunks.insert(coord, chunk);
+			self.propagate_light(coord);
 			self.create_bind_group(coord);
 		}
 	}
+
+	/// Hands the remeshes to the workers, dropping any queued job for the same chunks since those are outdated
+	pub fn queue_mesh_jobs(&mut self, jobs: Vec<MeshJob>) {
+		if jobs.is_empty() { return; }
+		let coords: HashSet<ChunkCoord> = jobs.iter().map(MeshJob::coord).collect();
+
+		let mut queue = self.mesh_queue.lock().unwrap();
+		queue.retain(|job| !coords.contains(&job.coord));
+		queue.extend(jobs);
+	}
+
+	/// Uploads the meshes the workers finished.
+	/// Results for chunks that got unloaded, replaced or queued again since are thrown away
+	pub fn process_meshed_chunks(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+		while let Ok(result) = self.meshed_receiver.try_recv() {
+			if !self.loaded_chunks.contains(&result.coord) { continue; }
+			let Some(chunk) = self.chunks.get_mut(&result.coord) else { continue; };
+			if chunk.mesh_job() != result.id { continue; }
+
+			chunk.set_mesh_job(0);
+			let mesh = self.mesh_pool.acquire(device, queue, &result.instances);
+			if let Some(old) = chunk.set_mesh(Some(mesh)) {
+				self.mesh_pool.release(old);
+			}
+		}
+	}
+
+	/// Re-sorts the queued chunks for the current `look_dir`, so turning around loads the new view first.
+	/// Returns false if a worker held the queue, the next update tries again
+	pub fn reprioritize_generation(&mut self, center: ChunkCoord) -> bool {
+		let Ok(mut queue) = self.chunk_generation_queue.try_lock() else { return false; };
+		let chunks: Vec<PriorityChunk> = queue.drain()
+			.map(|c| PriorityChunk::new_with_dir(c.coord, center, self.look_dir))
+			.collect();
+		*queue = BinaryHeap::from(chunks);
+		true
+	}
 }